@@ -0,0 +1,58 @@
+use super::exercise::*;
+
+// hint: Take `self.start` while it is below `self.end`, and move `start` up
+// hint: by one each time.
+#[test]
+fn range_is_iterated() {
+    let range = MapRange { start: 1, end: 5 };
+    assert_eq!(range.collect::<Vec<i32>>(), vec![1, 2, 3, 4]);
+    assert_eq!(MapRange { start: 3, end: 3 }.count(), 0);
+}
+
+// hint: `match range.start_bound() { Bound::Included(n) => *n, ... }`, and
+// hint: an included end is one more than the end left out.
+#[test]
+fn range_is_made_from_range_syntax() {
+    assert_eq!(MapRange::new(1..10), MapRange { start: 1, end: 10 });
+    assert_eq!(MapRange::new(1..=9), MapRange { start: 1, end: 10 });
+}
+
+// hint: The macro can expand to `MapRange::new($range)`.
+#[test]
+fn macro_makes_a_range() {
+    let start = 5;
+    let end = 8;
+    let range: MapRange = map_range!(start..end);
+    assert_eq!(range, MapRange { start: 5, end: 8 });
+    let range: MapRange = map_range!(1..=3);
+    assert_eq!(range.map(|x| x * 10).collect::<Vec<i32>>(), vec![10, 20, 30]);
+}
+
+// hint: Iterator adapters like `map`, `filter` and `sum` come with `Iterator`
+// hint: once `next` works.
+#[test]
+fn range_works_with_iterator_adapters() {
+    let range: MapRange = map_range!(1..10);
+    let sum: f32 = range.map(|x| x as f32 * 2.0).sum();
+    assert_eq!(sum, 90.0);
+}
+
+fn add_one(x: i32) -> i32 {
+    x + 1
+}
+
+// hint: `f(f(x))`.
+#[test]
+fn function_is_applied_twice() {
+    assert_eq!(apply_twice(add_one, 5), 7);
+    assert_eq!(apply_twice(|x| x * x, 3), 81);
+}
+
+// hint: `Box::new(move |x| x * factor)`.
+#[test]
+fn multiplier_closures() {
+    let double = make_multiplier(2);
+    let triple = make_multiplier(3);
+    assert_eq!(double(21), 42);
+    assert_eq!(triple(-2), -6);
+}
@@ -0,0 +1,47 @@
+// Exercise: advanced features
+//
+// Replace each `todo!()` below with your code. Check your solution from the
+// root of the tutorial with `cargo run -- verify 15`, or run
+// `cargo run -- watch 15` to check it every time you save.
+use std::ops::{Bound, RangeBounds};
+
+/// The numbers from `start` up to `end`, `end` left out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MapRange {
+    pub start: i32,
+    pub end: i32,
+}
+
+impl MapRange {
+    /// The numbers of a range like `1..10` or `1..=9`, with the start
+    /// included and the end left out.
+    pub fn new(range: impl RangeBounds<i32>) -> MapRange {
+        todo!()
+    }
+}
+
+impl Iterator for MapRange {
+    type Item = i32;
+
+    fn next(&mut self) -> Option<i32> {
+        todo!()
+    }
+}
+
+/// `map_range!(1..10)` makes the same `MapRange` as `MapRange::new(1..10)`.
+macro_rules! map_range {
+    ($range:expr) => {
+        todo!()
+    };
+}
+
+/// `f` applied to `x`, then to the result of that.
+pub fn apply_twice(f: fn(i32) -> i32, x: i32) -> i32 {
+    todo!()
+}
+
+/// A closure that multiplies its argument by `factor`, boxed so it can be
+/// returned.
+pub fn make_multiplier(factor: i32) -> Box<dyn Fn(i32) -> i32> {
+    todo!()
+}
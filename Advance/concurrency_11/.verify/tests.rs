@@ -0,0 +1,46 @@
+use super::exercise::*;
+use std::collections::HashMap;
+
+fn counts(pairs: &[(&str, usize)]) -> HashMap<String, usize> {
+    pairs
+        .iter()
+        .map(|(word, count)| (word.to_string(), *count))
+        .collect()
+}
+
+// hint: Split with `split_whitespace`, then
+// hint: `word.trim_matches(|c: char| !c.is_alphanumeric()).to_lowercase()`,
+// hint: and leave out what is empty after that.
+#[test]
+fn words_are_counted() {
+    let texts = ["Hello, hello world!", "The world is big", "hello again"];
+    assert_eq!(
+        count_words(&texts, 1),
+        counts(&[
+            ("hello", 3),
+            ("world", 2),
+            ("the", 1),
+            ("is", 1),
+            ("big", 1),
+            ("again", 1),
+        ])
+    );
+}
+
+// hint: Give each worker its own `Receiver` and send the words round-robin,
+// hint: or share one with `Arc<Mutex<Receiver<String>>>`. Drop the senders
+// hint: so the workers' loops end, then `join` them and add up their maps.
+#[test]
+fn any_number_of_workers_count_the_same() {
+    let texts = ["a b c a", "b a", "c c c", "d"];
+    let expected = counts(&[("a", 3), ("b", 2), ("c", 4), ("d", 1)]);
+    for workers in [1, 2, 3, 8] {
+        assert_eq!(count_words(&texts, workers), expected, "{} workers", workers);
+    }
+}
+
+#[test]
+fn nothing_to_count() {
+    assert!(count_words(&[], 4).is_empty());
+    assert!(count_words(&["", "  ...  "], 2).is_empty());
+}
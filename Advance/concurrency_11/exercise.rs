@@ -0,0 +1,18 @@
+// Exercise: concurrency
+//
+// Replace the `todo!()` below with your code. Check your solution from the
+// root of the tutorial with `cargo run -- verify 11`, or run
+// `cargo run -- watch 11` to check it every time you save.
+use std::collections::HashMap;
+use std::sync::mpsc;
+use std::thread;
+
+/// How often each word occurs in the texts, counted by `workers` threads
+/// (at least one). The words are sent to the workers over a channel, and
+/// each worker sends its own counts back to be added up.
+///
+/// Words are split on whitespace, in lowercase, without the punctuation
+/// around them: `"Hello, hello world!"` has `hello` twice and `world` once.
+pub fn count_words(texts: &[&str], workers: usize) -> HashMap<String, usize> {
+    todo!()
+}
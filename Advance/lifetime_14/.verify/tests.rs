@@ -0,0 +1,30 @@
+use super::exercise::*;
+
+// hint: `format!("Hello, my name is {}!", self.name)`.
+#[test]
+fn person_greets_by_name() {
+    let person = Person { name: "Ferris" };
+    assert_eq!(person.greet(), "Hello, my name is Ferris!");
+    // Greeting did not take the person
+    assert_eq!(person.greet(), "Hello, my name is Ferris!");
+}
+
+// hint: `self.name.split(' ').next().unwrap_or(self.name)` gives a `&'a str`.
+#[test]
+fn first_name_outlives_the_person() {
+    let name = String::from("Grace Hopper");
+    let first = {
+        let person = Person { name: &name };
+        person.first_name()
+    };
+    assert_eq!(first, "Grace");
+    assert_eq!(Person { name: "Ada" }.first_name(), "Ada");
+}
+
+// hint: Compare `a.len()` and `b.len()`, using `>=` so `a` wins a tie.
+#[test]
+fn longest_of_two() {
+    assert_eq!(longest("hello", "hi"), "hello");
+    assert_eq!(longest("a", "abc"), "abc");
+    assert_eq!(longest("one", "two"), "one");
+}
@@ -0,0 +1,29 @@
+// Exercise: lifetimes
+//
+// Replace each `todo!()` below with your code. Check your solution from the
+// root of the tutorial with `cargo run -- verify 14`, or run
+// `cargo run -- watch 14` to check it every time you save.
+
+/// A person whose name is borrowed, so the person cannot outlive it.
+#[derive(Debug)]
+pub struct Person<'a> {
+    pub name: &'a str,
+}
+
+impl<'a> Person<'a> {
+    /// Like `"Hello, my name is Ferris!"`, without taking the person.
+    pub fn greet(&self) -> String {
+        todo!()
+    }
+
+    /// The name up to its first space, borrowed from the same text as the
+    /// name, so it can outlive the person.
+    pub fn first_name(&self) -> &'a str {
+        todo!()
+    }
+}
+
+/// The longer of the two, `a` when they are as long.
+pub fn longest<'a>(a: &'a str, b: &'a str) -> &'a str {
+    todo!()
+}
@@ -0,0 +1,72 @@
+use super::exercise::*;
+
+// hint: The fields are private to the module, so the constructor is the only
+// hint: way to set the balance: `BankAccount { account_number:
+// hint: account_number.to_string(), balance: initial_balance }`.
+#[test]
+fn deposits_add_to_the_balance() {
+    let mut account = BankAccount::new("12-345", 100.0);
+    assert_eq!(account.balance(), 100.0);
+    account.deposit(50.5);
+    assert_eq!(account.balance(), 150.5);
+}
+
+// hint: Check `amount > self.balance` first and return an `Err` with a
+// hint: message, before changing anything.
+#[test]
+fn withdrawing_more_than_the_balance_fails() {
+    let mut account = BankAccount::new("12-345", 100.0);
+    assert_eq!(account.withdraw(30.0), Ok(70.0));
+    assert!(account.withdraw(500.0).is_err());
+    assert_eq!(account.balance(), 70.0);
+}
+
+// hint: `write!(f, "Account {}", self.account_number)`.
+#[test]
+fn account_shows_without_its_balance() {
+    let account = BankAccount::new("12-345", 999.0);
+    assert_eq!(account.to_string(), "Account 12-345");
+}
+
+// hint: `credit` can call `self.deposit(amount)`, and `debit`
+// hint: `self.withdraw(amount)`.
+#[test]
+fn transactions_go_through_the_trait() {
+    let mut account = BankAccount::new("1", 10.0);
+    let transaction: &mut dyn Transaction = &mut account;
+    transaction.credit(5.0);
+    assert_eq!(transaction.debit(15.0), Ok(0.0));
+    assert!(transaction.debit(0.01).is_err());
+}
+
+// hint: `self.accounts.iter().find(|account| account.account_number ==
+// hint: account_number)`, and `iter_mut` for the mutable one.
+#[test]
+fn bank_finds_its_accounts() {
+    let mut bank = Bank::new();
+    bank.add_account(BankAccount::new("1", 10.0)).unwrap();
+    bank.add_account(BankAccount::new("2", 20.0)).unwrap();
+    assert!(bank.add_account(BankAccount::new("1", 0.0)).is_err());
+    assert_eq!(bank.get_account("2").unwrap().balance(), 20.0);
+    assert!(bank.get_account("3").is_none());
+    bank.get_account_mut("1").unwrap().deposit(5.0);
+    assert_eq!(bank.get_account("1").unwrap().balance(), 15.0);
+}
+
+// hint: Make sure both accounts exist before taking the money out, then
+// hint: withdraw from one and deposit into the other.
+#[test]
+fn money_is_transferred_between_accounts() {
+    let mut bank = Bank::new();
+    bank.add_account(BankAccount::new("1", 100.0)).unwrap();
+    bank.add_account(BankAccount::new("2", 0.0)).unwrap();
+    bank.transfer("1", "2", 40.0).unwrap();
+    assert_eq!(bank.get_account("1").unwrap().balance(), 60.0);
+    assert_eq!(bank.get_account("2").unwrap().balance(), 40.0);
+
+    assert!(bank.transfer("1", "2", 1000.0).is_err());
+    assert!(bank.transfer("1", "9", 10.0).is_err());
+    assert!(bank.transfer("9", "1", 10.0).is_err());
+    assert_eq!(bank.get_account("1").unwrap().balance(), 60.0);
+    assert_eq!(bank.get_account("2").unwrap().balance(), 40.0);
+}
@@ -0,0 +1,88 @@
+// Exercise: object-oriented Rust
+//
+// Replace each `todo!()` below with your code. Check your solution from the
+// root of the tutorial with `cargo run -- verify 12`, or run
+// `cargo run -- watch 12` to check it every time you save.
+use std::fmt;
+
+/// An account whose balance can only change through its methods.
+#[derive(Debug)]
+pub struct BankAccount {
+    pub account_number: String,
+    balance: f64,
+}
+
+impl BankAccount {
+    pub fn new(account_number: &str, initial_balance: f64) -> BankAccount {
+        todo!()
+    }
+
+    pub fn balance(&self) -> f64 {
+        todo!()
+    }
+
+    pub fn deposit(&mut self, amount: f64) {
+        todo!()
+    }
+
+    /// Takes `amount` out and returns the new balance. Fails with a message
+    /// when the balance is too low, leaving it as it was.
+    pub fn withdraw(&mut self, amount: f64) -> Result<f64, String> {
+        todo!()
+    }
+}
+
+impl fmt::Display for BankAccount {
+    /// Like `"Account 12-345"`, leaving the balance out for privacy.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        todo!()
+    }
+}
+
+pub trait Transaction {
+    fn credit(&mut self, amount: f64);
+    fn debit(&mut self, amount: f64) -> Result<f64, String>;
+}
+
+impl Transaction for BankAccount {
+    /// The same as a deposit.
+    fn credit(&mut self, amount: f64) {
+        todo!()
+    }
+
+    /// The same as a withdrawal.
+    fn debit(&mut self, amount: f64) -> Result<f64, String> {
+        todo!()
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Bank {
+    accounts: Vec<BankAccount>,
+}
+
+impl Bank {
+    pub fn new() -> Bank {
+        Bank::default()
+    }
+
+    /// Adds `account`, failing when there is one with its number already.
+    pub fn add_account(&mut self, account: BankAccount) -> Result<(), String> {
+        todo!()
+    }
+
+    pub fn get_account(&self, account_number: &str) -> Option<&BankAccount> {
+        todo!()
+    }
+
+    pub fn get_account_mut(&mut self, account_number: &str) -> Option<&mut BankAccount> {
+        todo!()
+    }
+
+    /// Moves `amount` from one account to another. Fails when either account
+    /// does not exist or `from` has too little money, and then changes
+    /// nothing.
+    pub fn transfer(&mut self, from: &str, to: &str, amount: f64) -> Result<(), String> {
+        todo!()
+    }
+}
@@ -0,0 +1,42 @@
+use super::exercise::*;
+
+fn close(a: f64, b: f64) -> bool {
+    (a - b).abs() < 1e-9
+}
+
+// hint: `match shape { Shape::Circle(r) => PI * r * r, ... }`.
+#[test]
+fn area_of_circles_and_rectangles() {
+    assert!(close(area(&Shape::Circle(2.0)), 12.566370614359172));
+    assert!(close(area(&Shape::Rectangle(3.0, 4.0)), 12.0));
+}
+
+// hint: Bind the three sides in the pattern, `Shape::Triangle(a, b, c)`, then
+// hint: `let s = (a + b + c) / 2.0;`.
+#[test]
+fn area_of_a_triangle() {
+    assert!(close(area(&Shape::Triangle(3.0, 4.0, 5.0)), 6.0));
+    assert!(close(area(&Shape::Triangle(2.0, 2.0, 2.0)), 3f64.sqrt()));
+}
+
+// hint: The order of the arms matters: `(0, 0)` first, then `(_, 0)` and
+// hint: `(0, _)`, a guard like `(x, y) if x == y`, then ranges like
+// hint: `(-10..=10, -10..=10)`.
+#[test]
+fn points_are_classified() {
+    assert_eq!(classify((0, 0)), "origin");
+    assert_eq!(classify((5, 0)), "on the x axis");
+    assert_eq!(classify((0, -100)), "on the y axis");
+    assert_eq!(classify((7, 7)), "on the diagonal");
+    assert_eq!(classify((-40, -40)), "on the diagonal");
+    assert_eq!(classify((10, -3)), "near");
+    assert_eq!(classify((11, 2)), "far away");
+}
+
+// hint: `match numbers { [] => None, [only] => ..., [first, .., last] => ... }`.
+#[test]
+fn ends_of_a_slice() {
+    assert_eq!(ends(&[1, 2, 3, 4]), Some((1, 4)));
+    assert_eq!(ends(&[9]), Some((9, 9)));
+    assert_eq!(ends(&[]), None);
+}
@@ -0,0 +1,39 @@
+// Exercise: pattern matching
+//
+// Replace each `todo!()` below with your code. Check your solution from the
+// root of the tutorial with `cargo run -- verify 13`, or run
+// `cargo run -- watch 13` to check it every time you save.
+use std::f64::consts::PI;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Shape {
+    /// Its radius.
+    Circle(f64),
+    /// Its width and height.
+    Rectangle(f64, f64),
+    /// The lengths of its sides.
+    Triangle(f64, f64, f64),
+}
+
+/// The area of the shape, worked out in a `match`. A triangle's comes from
+/// Heron's formula: `sqrt(s(s - a)(s - b)(s - c))`, with `s` half the sum
+/// of the sides.
+pub fn area(shape: &Shape) -> f64 {
+    todo!()
+}
+
+/// Where a point is:
+///
+/// - `"origin"` at `(0, 0)`
+/// - `"on the x axis"` or `"on the y axis"` on one of the axes
+/// - `"on the diagonal"` when `x` and `y` are the same
+/// - `"near"` when both are within 10 of the origin, -10 and 10 included
+/// - `"far away"` otherwise
+pub fn classify(point: (i32, i32)) -> &'static str {
+    todo!()
+}
+
+/// The first and last numbers of the slice, found with a slice pattern.
+pub fn ends(numbers: &[i32]) -> Option<(i32, i32)> {
+    todo!()
+}
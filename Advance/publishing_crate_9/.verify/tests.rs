@@ -0,0 +1,26 @@
+use super::exercise::publish::greeting;
+use super::exercise::{mix, PrimaryColor, SecondaryColor};
+
+// hint: Match on the pair, `match (c1, c2) { (Red, Yellow) | (Yellow, Red) =>
+// hint: Some(Orange), ... }`, with `_ => None` for the rest.
+#[test]
+fn primary_colors_mix_in_either_order() {
+    use PrimaryColor::*;
+    assert_eq!(mix(Red, Yellow), Some(SecondaryColor::Orange));
+    assert_eq!(mix(Yellow, Red), Some(SecondaryColor::Orange));
+    assert_eq!(mix(Blue, Yellow), Some(SecondaryColor::Green));
+    assert_eq!(mix(Red, Blue), Some(SecondaryColor::Purple));
+    assert_eq!(mix(Blue, Red), Some(SecondaryColor::Purple));
+}
+
+#[test]
+fn a_color_mixed_with_itself_makes_nothing() {
+    assert_eq!(mix(PrimaryColor::Red, PrimaryColor::Red), None);
+}
+
+// hint: `format!("Hello, {}!", name)`, with "world" when `name.is_empty()`.
+#[test]
+fn greeting_by_name() {
+    assert_eq!(greeting("Ferris"), "Hello, Ferris!");
+    assert_eq!(greeting(""), "Hello, world!");
+}
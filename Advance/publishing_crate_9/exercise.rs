@@ -0,0 +1,55 @@
+// Exercise: publishing a crate
+//
+// This file stands for the `lib.rs` of a crate about to be published. Replace
+// each `todo!()` below with your code and give the crate a public interface
+// that is easy to use. Check your solution from the root of the tutorial
+// with `cargo run -- verify 9`, or run `cargo run -- watch 9` to check it
+// every time you save.
+//
+// The tests use `PrimaryColor`, `SecondaryColor` and `mix` straight from the
+// root of the crate, so this does not compile until they are re-exported
+// here with `pub use`.
+
+pub mod kinds {
+    /// The primary colors of the RYB color model.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum PrimaryColor {
+        Red,
+        Yellow,
+        Blue,
+    }
+
+    /// The colors made by mixing two different primary colors.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum SecondaryColor {
+        Orange,
+        Green,
+        Purple,
+    }
+}
+
+pub mod utils {
+    use super::kinds::*;
+
+    /// Mixes two primary colors: red and yellow make orange, yellow and blue
+    /// make green, red and blue make purple, in either order. A color mixed
+    /// with itself makes no secondary color.
+    ///
+    /// # Examples
+    ///
+    /// ```ignore
+    /// let color = mix(PrimaryColor::Blue, PrimaryColor::Yellow);
+    /// assert_eq!(color, Some(SecondaryColor::Green));
+    /// ```
+    pub fn mix(c1: PrimaryColor, c2: PrimaryColor) -> Option<SecondaryColor> {
+        todo!()
+    }
+}
+
+pub mod publish {
+    /// A greeting for `name`, like `"Hello, Ferris!"`, or `"Hello, world!"`
+    /// when the name is empty.
+    pub fn greeting(name: &str) -> String {
+        todo!()
+    }
+}
@@ -0,0 +1,40 @@
+use super::exercise::*;
+
+fn tree() -> Option<Box<TreeNode>> {
+    // 2 with 3 on the left, and 4 on the right, 4 having 5 on its left
+    Some(Box::new(TreeNode {
+        value: 2,
+        left: TreeNode::leaf(3),
+        right: Some(Box::new(TreeNode {
+            value: 4,
+            left: TreeNode::leaf(5),
+            right: None,
+        })),
+    }))
+}
+
+// hint: `match root { None => 0, Some(node) => node.value + sum_tree(node.left)
+// hint: + sum_tree(node.right) }`, the function calling itself.
+#[test]
+fn sum_of_a_tree() {
+    assert_eq!(sum_tree(TreeNode::leaf(1)), 1);
+    assert_eq!(sum_tree(tree()), 14);
+}
+
+// hint: An empty tree is `None`.
+#[test]
+fn empty_tree_sums_to_zero() {
+    assert_eq!(sum_tree(None), 0);
+}
+
+// hint: The depth of a node is 1 plus the larger depth of its two children,
+// hint: `max_depth(&node.left).max(max_depth(&node.right))`.
+#[test]
+fn depth_of_a_tree() {
+    assert_eq!(max_depth(&None), 0);
+    assert_eq!(max_depth(&TreeNode::leaf(7)), 1);
+    let tree = tree();
+    assert_eq!(max_depth(&tree), 3);
+    // Still there, it was only borrowed
+    assert_eq!(sum_tree(tree), 14);
+}
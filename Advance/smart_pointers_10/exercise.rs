@@ -0,0 +1,34 @@
+// Exercise: smart pointers
+//
+// Replace each `todo!()` below with your code. Check your solution from the
+// root of the tutorial with `cargo run -- verify 10`, or run
+// `cargo run -- watch 10` to check it every time you save.
+
+/// A node of a binary tree, its children on the heap.
+#[derive(Debug)]
+pub struct TreeNode {
+    pub value: i32,
+    pub left: Option<Box<TreeNode>>,
+    pub right: Option<Box<TreeNode>>,
+}
+
+impl TreeNode {
+    pub fn leaf(value: i32) -> Option<Box<TreeNode>> {
+        Some(Box::new(TreeNode {
+            value,
+            left: None,
+            right: None,
+        }))
+    }
+}
+
+/// The sum of all the values in the tree, 0 for an empty tree.
+pub fn sum_tree(root: Option<Box<TreeNode>>) -> i32 {
+    todo!()
+}
+
+/// How many nodes the longest path from the root down has, 0 for an empty
+/// tree. Only borrows the tree.
+pub fn max_depth(root: &Option<Box<TreeNode>>) -> usize {
+    todo!()
+}
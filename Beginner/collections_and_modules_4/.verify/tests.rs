@@ -0,0 +1,34 @@
+use super::exercise::math::utils::{is_even, is_prime};
+use super::exercise::math::PrimeSet;
+
+// hint: Try every divisor from 2 while `divisor * divisor <= number`, and
+// hint: return false for anything below 2.
+#[test]
+fn prime_numbers() {
+    let primes: Vec<i32> = (-3..30).filter(|n| is_prime(*n)).collect();
+    assert_eq!(primes, vec![2, 3, 5, 7, 11, 13, 17, 19, 23, 29]);
+    assert!(is_prime(7919));
+    assert!(!is_prime(7917));
+}
+
+// hint: `number % 2 == 0`.
+#[test]
+fn even_numbers() {
+    assert!(is_even(4));
+    assert!(is_even(0));
+    assert!(!is_even(-7));
+}
+
+// hint: `HashSet::insert` already returns whether the value was new, check
+// hint: `is_prime` before calling it.
+#[test]
+fn prime_set_only_takes_primes() {
+    let mut set = PrimeSet::new();
+    assert!(set.insert(2));
+    assert!(set.insert(13));
+    assert!(!set.insert(4));
+    assert!(!set.insert(13));
+    assert!(set.contains(13));
+    assert!(!set.contains(4));
+    assert_eq!(set.len(), 2);
+}
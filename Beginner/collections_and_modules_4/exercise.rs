@@ -0,0 +1,48 @@
+// Exercise: collections and modules
+//
+// Replace each `todo!()` below with your code, and fill in the `math` module.
+// Check your solution from the root of the tutorial with
+// `cargo run -- verify 4`, or run `cargo run -- watch 4` to check it every
+// time you save.
+use std::collections::HashSet;
+
+pub mod utils {
+    /// Whether `number` can only be divided by 1 and itself. Numbers below 2
+    /// are not prime.
+    pub fn is_prime(number: i32) -> bool {
+        todo!()
+    }
+
+    pub fn is_even(number: i32) -> bool {
+        todo!()
+    }
+}
+
+/// A set that only takes prime numbers.
+pub struct PrimeSet {
+    data: HashSet<i32>,
+}
+
+impl PrimeSet {
+    pub fn new() -> PrimeSet {
+        todo!()
+    }
+
+    /// Adds `number` when it is prime. Returns whether it was added, so
+    /// `false` for a number that is not prime or already in the set.
+    pub fn insert(&mut self, number: i32) -> bool {
+        todo!()
+    }
+
+    pub fn contains(&self, number: i32) -> bool {
+        todo!()
+    }
+
+    pub fn len(&self) -> usize {
+        todo!()
+    }
+}
+
+/// The tests use `math::utils` and `math::PrimeSet`, so this does not compile
+/// until both are re-exported here with `pub use`.
+pub mod math {}
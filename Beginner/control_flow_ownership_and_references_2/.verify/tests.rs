@@ -0,0 +1,45 @@
+use super::exercise::*;
+
+// hint: Check the numbers from the top down: `if number >= 100 { ... }
+// hint: else if number >= 10 { ... } else { ... }`.
+#[test]
+fn numbers_are_categorized() {
+    assert_eq!(categorize(3), "small");
+    assert_eq!(categorize(-50), "small");
+    assert_eq!(categorize(10), "medium");
+    assert_eq!(categorize(99), "medium");
+    assert_eq!(categorize(100), "large");
+}
+
+// hint: `for n in numbers { sum += n; }`, where `n` is a `&i32`.
+#[test]
+fn numbers_are_summed() {
+    assert_eq!(sum(&[1, 2, 3]), 6);
+    assert_eq!(sum(&[]), 0);
+}
+
+// hint: Keep an index, `break` out of the `loop` when it reaches the end or
+// hint: the line is "exit".
+#[test]
+fn lines_are_read_until_exit() {
+    assert_eq!(lines_before_exit(&["hello", "there", "exit", "again"]), 2);
+    assert_eq!(lines_before_exit(&["exit"]), 0);
+    assert_eq!(lines_before_exit(&["no", "way", "out"]), 3);
+}
+
+// hint: The parameter can be made mutable with `mut text: String`, then
+// hint: `text.push_str("!")` and return `text`.
+#[test]
+fn owned_string_comes_back_changed() {
+    let text = String::from("Hello");
+    assert_eq!(exclaim(text), "Hello!");
+}
+
+// hint: `text.push_str(suffix)` changes the String the reference points to.
+#[test]
+fn suffix_is_appended_through_a_reference() {
+    let mut text = String::from("file");
+    append_suffix(&mut text, ".rs");
+    append_suffix(&mut text, ".bak");
+    assert_eq!(text, "file.rs.bak");
+}
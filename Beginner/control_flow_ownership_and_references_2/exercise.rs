@@ -0,0 +1,31 @@
+// Exercise: control flow, ownership and references
+//
+// Replace each `todo!()` below with your code. Check your solution from the
+// root of the tutorial with `cargo run -- verify 2`, or run
+// `cargo run -- watch 2` to check it every time you save.
+
+/// `"small"` below 10, `"medium"` from 10 up to 99 and `"large"` from 100 on.
+pub fn categorize(number: i32) -> &'static str {
+    todo!()
+}
+
+/// The sum of the numbers, added up in a `for` loop.
+pub fn sum(numbers: &[i32]) -> i32 {
+    todo!()
+}
+
+/// How many lines come before the first `"exit"`, counted in a `loop`. All
+/// of them when there is no `"exit"`.
+pub fn lines_before_exit(lines: &[&str]) -> usize {
+    todo!()
+}
+
+/// Takes ownership of `text`, adds `"!"` to its end and gives it back.
+pub fn exclaim(text: String) -> String {
+    todo!()
+}
+
+/// Adds `suffix` to the end of `text`, through a mutable reference.
+pub fn append_suffix(text: &mut String, suffix: &str) {
+    todo!()
+}
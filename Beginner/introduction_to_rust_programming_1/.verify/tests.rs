@@ -0,0 +1,36 @@
+use super::exercise::*;
+
+// hint: The area is the width multiplied by the height.
+#[test]
+fn area_of_a_rectangle() {
+    assert_eq!(calculate_area(5, 4), 20);
+    assert_eq!(calculate_area(7, 7), 49);
+}
+
+#[test]
+fn area_with_a_side_of_zero() {
+    assert_eq!(calculate_area(0, 12), 0);
+}
+
+// hint: A number is even when `number % 2 == 0`. Negative numbers have a
+// hint: remainder of -1 when they are odd.
+#[test]
+fn even_and_odd_numbers() {
+    assert_eq!(even_or_odd(2), "even");
+    assert_eq!(even_or_odd(7), "odd");
+    assert_eq!(even_or_odd(0), "even");
+    assert_eq!(even_or_odd(-3), "odd");
+}
+
+// hint: Start with `let mut sum = 0;` and add each number in `for n in numbers`.
+#[test]
+fn total_of_an_array() {
+    assert_eq!(total([1, 2, 3, 4, 5]), 15);
+    assert_eq!(total([10, -10, 0, 0, 1]), 1);
+}
+
+// hint: The values of a tuple are `pair.0` and `pair.1`.
+#[test]
+fn flipped_tuple() {
+    assert_eq!(flip((1, 'a')), ('a', 1));
+}
@@ -0,0 +1,25 @@
+// Exercise: Rust fundamentals
+//
+// Replace each `todo!()` below with your code. Check your solution from the
+// root of the tutorial with `cargo run -- verify 1`, or run
+// `cargo run -- watch 1` to check it every time you save.
+
+/// The area of a rectangle `width` wide and `height` high.
+pub fn calculate_area(width: i32, height: i32) -> i32 {
+    todo!()
+}
+
+/// `"even"` or `"odd"`, using an `if`-`else`.
+pub fn even_or_odd(number: i32) -> &'static str {
+    todo!()
+}
+
+/// The sum of the numbers, added up in a `for` loop.
+pub fn total(numbers: [i32; 5]) -> i32 {
+    todo!()
+}
+
+/// The tuple with its two values the other way around.
+pub fn flip(pair: (i32, char)) -> (char, i32) {
+    todo!()
+}
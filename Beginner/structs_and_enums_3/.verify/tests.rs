@@ -0,0 +1,41 @@
+use super::exercise::*;
+
+fn hobbit() -> Book {
+    Book {
+        title: "The Hobbit".to_string(),
+        author: "J. R. R. Tolkien".to_string(),
+        year: 1937,
+        genre: Genre::Fantasy,
+    }
+}
+
+// hint: Build the struct with `Book { title: title.to_string(), ... }`.
+#[test]
+fn a_book_is_created() {
+    assert_eq!(
+        new_book("The Hobbit", "J. R. R. Tolkien", 1937, Genre::Fantasy),
+        hobbit()
+    );
+}
+
+// hint: `format!("{} by {} ({})", self.title, self.author, self.year)`.
+#[test]
+fn details_of_a_book() {
+    assert_eq!(hobbit().details(), "The Hobbit by J. R. R. Tolkien (1937)");
+}
+
+// hint: `match genre { Genre::Fiction => "...", ... }`, one arm per variant.
+#[test]
+fn each_genre_has_its_message() {
+    assert_eq!(genre_message(&Genre::Fiction), "A story to get lost in");
+    assert_eq!(genre_message(&Genre::NonFiction), "Something true to learn from");
+    assert_eq!(genre_message(&Genre::Fantasy), "Dragons may be involved");
+    assert_eq!(genre_message(&Genre::Biography), "The story of a life");
+}
+
+// hint: `if let LibraryItem::BookItem(book) = item { Some(&book.title) } else { None }`.
+#[test]
+fn only_books_have_a_title() {
+    assert_eq!(book_title(&LibraryItem::BookItem(hobbit())), Some("The Hobbit"));
+    assert_eq!(book_title(&LibraryItem::MediaItem("Podcast".to_string())), None);
+}
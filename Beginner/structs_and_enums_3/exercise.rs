@@ -0,0 +1,54 @@
+// Exercise: structs and enums
+//
+// Replace each `todo!()` below with your code. Check your solution from the
+// root of the tutorial with `cargo run -- verify 3`, or run
+// `cargo run -- watch 3` to check it every time you save.
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Genre {
+    Fiction,
+    NonFiction,
+    Fantasy,
+    Biography,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Book {
+    pub title: String,
+    pub author: String,
+    pub year: u16,
+    pub genre: Genre,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum LibraryItem {
+    BookItem(Book),
+    MediaItem(String),
+}
+
+/// A new `Book` with these details.
+pub fn new_book(title: &str, author: &str, year: u16, genre: Genre) -> Book {
+    todo!()
+}
+
+impl Book {
+    /// Like `"The Hobbit by J. R. R. Tolkien (1937)"`.
+    pub fn details(&self) -> String {
+        todo!()
+    }
+}
+
+/// A message for each genre, chosen with a `match`:
+///
+/// - `Fiction`: `"A story to get lost in"`
+/// - `NonFiction`: `"Something true to learn from"`
+/// - `Fantasy`: `"Dragons may be involved"`
+/// - `Biography`: `"The story of a life"`
+pub fn genre_message(genre: &Genre) -> &'static str {
+    todo!()
+}
+
+/// The title of the item when it is a book, found with `if let`.
+pub fn book_title(item: &LibraryItem) -> Option<&str> {
+    todo!()
+}
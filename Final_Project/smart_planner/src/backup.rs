@@ -0,0 +1,391 @@
+// Backups and JSON exports of the whole planner.
+//
+// A backup is one file: a line of JSON saying what it is, when it was made,
+// which profile it is of and the SHA-256 of the rest, then the contents. The
+// contents hold the data, read through the storage backend so a sqlite or
+// http planner backs up the same way, and the other files of the planner
+// folder (config, history and sync state). When the planner is encrypted the
+// contents are too, with its file key, and the vault goes in the first line so
+// the backup can be restored on its own with the passphrase.
+//
+// An export is only the data, as plain JSON for other tools. It says which
+// version of the format it is in, and older versions are brought up to date
+// on import.
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+use crate::data::PlannerData;
+use crate::error::{PlannerError, Result};
+use crate::profile;
+use crate::store::Store;
+use crate::sync;
+use crate::vault::{self, Vault};
+
+/// What the first line of a backup says it is.
+pub const BACKUP_FORMAT: &str = "smart_planner backup";
+/// The latest backup format, the only one so far.
+pub const BACKUP_VERSION: u32 = 1;
+/// What an export says it is.
+pub const EXPORT_FORMAT: &str = "smart_planner export";
+/// The latest export format. Version 0 is a bare data file without the
+/// envelope, as in `data.json` or from `GET /data` of `serve`.
+pub const EXPORT_VERSION: u32 = 1;
+/// The folder of the history, all of which a backup holds.
+const HISTORY_DIR: &str = "history";
+/// The config of the planner folder, of which a backup keeps all but the
+/// storage backend.
+const CONFIG_FILE: &str = "config.json";
+/// The files kept next to the data that a backup holds.
+const FOLDER_FILES: [&str; 2] = [CONFIG_FILE, sync::STATE_FILE];
+/// Name the contents are encrypted under, tying them to a backup.
+const ENCRYPTED_NAME: &str = "backup";
+/// Where a restore writes the files of the backup before moving them in.
+const STAGED_DIR: &str = ".restore.tmp";
+/// Where a restore moves the files it replaces until it is done.
+const REPLACED_DIR: &str = ".replaced.tmp";
+
+/// The first line of a backup.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Header {
+    format: String,
+    version: u32,
+    created_at: DateTime<Utc>,
+    /// Of everything after the first line, in hex.
+    sha256: String,
+    /// The profile backed up, when it was one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    profile: Option<String>,
+    /// Set when the contents are encrypted.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    vault: Option<Vault>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Contents {
+    /// An export, so data from older versions can be read.
+    data: Value,
+    /// Other files of the planner folder by path, using `/`.
+    files: BTreeMap<String, String>,
+}
+
+/// A backup read back and checked.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Backup {
+    pub created_at: DateTime<Utc>,
+    /// The profile backed up, when it was one.
+    pub profile: Option<String>,
+    pub data: PlannerData,
+    pub files: BTreeMap<String, String>,
+    vault: Option<Vault>,
+}
+
+impl Backup {
+    pub fn is_encrypted(&self) -> bool {
+        self.vault.is_some()
+    }
+}
+
+/// Makes a backup of everything in `store`. The passphrase is needed when
+/// the planner is encrypted, so the backup is encrypted too.
+pub fn create(store: &Store, passphrase: Option<&str>, now: DateTime<Utc>) -> Result<Vec<u8>> {
+    let dir = store.dir();
+    let mut files = BTreeMap::new();
+    for name in FOLDER_FILES {
+        let path = dir.join(name);
+        if path.exists() {
+            files.insert(name.to_string(), fs::read_to_string(path)?);
+        }
+    }
+    add_folder(dir, &dir.join(HISTORY_DIR), &mut files)?;
+    let contents = Contents {
+        data: serde_json::from_str(&export(&store.load()?, now)?)?,
+        files,
+    };
+    let mut payload = serde_json::to_vec(&contents)?;
+
+    let vault = Vault::load(dir)?;
+    if let Some(vault) = &vault {
+        let passphrase = passphrase.ok_or_else(|| {
+            PlannerError::Encryption(
+                "the planner is encrypted, its passphrase is needed".to_string(),
+            )
+        })?;
+        payload = vault.open(passphrase)?.encrypt(ENCRYPTED_NAME, &payload);
+    }
+    let header = Header {
+        format: BACKUP_FORMAT.to_string(),
+        version: BACKUP_VERSION,
+        created_at: now,
+        sha256: checksum(&payload),
+        profile: profile::name_of(dir),
+        vault,
+    };
+    let mut backup = serde_json::to_vec(&header)?;
+    backup.push(b'\n');
+    backup.extend(payload);
+    Ok(backup)
+}
+
+/// Adds every file under `folder` to `files`.
+fn add_folder(dir: &Path, folder: &Path, files: &mut BTreeMap<String, String>) -> Result<()> {
+    if !folder.exists() {
+        return Ok(());
+    }
+    for entry in fs::read_dir(folder)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            add_folder(dir, &path, files)?;
+            continue;
+        }
+        let name: Vec<String> = path
+            .strip_prefix(dir)
+            .expect("found inside the folder")
+            .components()
+            .map(|part| part.as_os_str().to_string_lossy().into_owned())
+            .collect();
+        let name = name.join("/");
+        if !name.ends_with(".tmp") {
+            files.insert(name, fs::read_to_string(&path)?);
+        }
+    }
+    Ok(())
+}
+
+/// Whether the backup needs a passphrase to be read.
+pub fn is_encrypted(backup: &[u8]) -> Result<bool> {
+    Ok(header(backup)?.0.vault.is_some())
+}
+
+/// Reads a backup, refusing one that is damaged, cut short or from a newer
+/// version of the planner.
+pub fn read(backup: &[u8], passphrase: Option<&str>) -> Result<Backup> {
+    let (header, payload) = header(backup)?;
+    if checksum(payload) != header.sha256 {
+        return Err(damaged(
+            "its checksum does not match, it was changed or cut short",
+        ));
+    }
+    let plain;
+    let payload = match &header.vault {
+        Some(vault) => {
+            let passphrase = passphrase.ok_or_else(|| {
+                PlannerError::Encryption(
+                    "the backup is encrypted, its passphrase is needed".to_string(),
+                )
+            })?;
+            plain = vault.open(passphrase)?.decrypt(ENCRYPTED_NAME, payload)?;
+            &plain
+        }
+        None => payload,
+    };
+    let contents: Contents = serde_json::from_slice(payload)
+        .map_err(|e| damaged(&format!("its contents are unreadable: {}", e)))?;
+    for name in contents.files.keys() {
+        let allowed =
+            FOLDER_FILES.contains(&name.as_str()) || name.starts_with(&format!("{}/", HISTORY_DIR));
+        if !allowed || name.split('/').any(|part| part == ".." || part.is_empty()) {
+            return Err(damaged(&format!("it holds an unexpected file '{}'", name)));
+        }
+    }
+    Ok(Backup {
+        created_at: header.created_at,
+        profile: header.profile,
+        data: import_value(contents.data)?,
+        files: contents.files,
+        vault: header.vault,
+    })
+}
+
+/// The first line of `backup` and the rest.
+fn header(backup: &[u8]) -> Result<(Header, &[u8])> {
+    let end = backup
+        .iter()
+        .position(|&byte| byte == b'\n')
+        .ok_or_else(|| damaged("it has no header"))?;
+    let header: Header = serde_json::from_slice(&backup[..end])
+        .map_err(|_| damaged("it is not a planner backup"))?;
+    if header.format != BACKUP_FORMAT {
+        return Err(damaged("it is not a planner backup"));
+    }
+    if header.version > BACKUP_VERSION {
+        return Err(PlannerError::Invalid(format!(
+            "the backup is in version {} of the format, this planner reads up to {}",
+            header.version, BACKUP_VERSION
+        )));
+    }
+    Ok((header, &backup[end + 1..]))
+}
+
+/// Replaces everything in the planner folder `dir` with the backup. The
+/// history and sync state go back to what they were, so the backup is not
+/// recorded as a change. The files are written to a folder of their own and
+/// moved in once all are there; when anything fails, the ones replaced are
+/// moved back. The storage backend stays the one set here, so a backup
+/// cannot send the data somewhere else. An encrypted backup brings back its
+/// vault and leaves the folder locked.
+pub fn restore(dir: &Path, backup: &Backup, passphrase: Option<&str>) -> Result<()> {
+    let passphrase = match &backup.vault {
+        Some(_) => Some(passphrase.ok_or_else(|| {
+            PlannerError::Encryption(
+                "the backup is encrypted, its passphrase is needed".to_string(),
+            )
+        })?),
+        None => None,
+    };
+    let staged = dir.join(STAGED_DIR);
+    let replaced = dir.join(REPLACED_DIR);
+    for folder in [&staged, &replaced] {
+        if folder.exists() {
+            fs::remove_dir_all(folder)?;
+        }
+        fs::create_dir_all(folder)?;
+    }
+    let mut moved = Vec::new();
+    let result = swap_in(dir, &staged, &replaced, backup, &mut moved);
+    if result.is_err() {
+        for (from, to) in moved.iter().rev() {
+            let _ = fs::rename(to, from);
+        }
+    }
+    let _ = fs::remove_dir_all(&staged);
+    let _ = fs::remove_dir_all(&replaced);
+    result?;
+
+    if let (Some(vault), Some(passphrase)) = (&backup.vault, passphrase) {
+        vault.save(dir)?;
+        vault::lock(dir, passphrase)?;
+    }
+    Ok(())
+}
+
+/// Writes the files of the backup to `staged`, then moves them into `dir`
+/// and the ones they replace to `replaced`, noting each move, and saves the
+/// data last.
+fn swap_in(
+    dir: &Path,
+    staged: &Path,
+    replaced: &Path,
+    backup: &Backup,
+    moved: &mut Vec<(PathBuf, PathBuf)>,
+) -> Result<()> {
+    for (name, text) in &backup.files {
+        let path = staged.join(name);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, text)?;
+    }
+    if let Some(config) = local_config(dir, backup.files.get(CONFIG_FILE))? {
+        fs::write(staged.join(CONFIG_FILE), config)?;
+    }
+
+    for name in std::iter::once(HISTORY_DIR).chain(FOLDER_FILES) {
+        let path = dir.join(name);
+        if path.exists() {
+            fs::rename(&path, replaced.join(name))?;
+            moved.push((path.clone(), replaced.join(name)));
+        }
+        let new = staged.join(name);
+        if new.exists() {
+            fs::rename(&new, &path)?;
+            moved.push((new, path));
+        }
+    }
+    Store::open(dir)?.storage().save(&backup.data)
+}
+
+/// The config of the backup with the storage backend set in `dir`, or `None`
+/// when neither has a config.
+fn local_config(dir: &Path, restored: Option<&String>) -> Result<Option<String>> {
+    let path = dir.join(CONFIG_FILE);
+    let here: Value = if path.exists() {
+        serde_json::from_str(&fs::read_to_string(path)?)?
+    } else {
+        Value::Null
+    };
+    let mut config: Value = match restored {
+        Some(text) => serde_json::from_str(text)?,
+        None if here.is_null() => return Ok(None),
+        None => Value::Object(Default::default()),
+    };
+    let Some(fields) = config.as_object_mut() else {
+        return Err(damaged("its config.json is not a JSON object"));
+    };
+    match here.get("storage") {
+        Some(storage) => fields.insert("storage".to_string(), storage.clone()),
+        None => fields.remove("storage"),
+    };
+    Ok(Some(serde_json::to_string_pretty(&config)?))
+}
+
+/// The envelope of an export, so it says what it is first.
+#[derive(Serialize)]
+struct Export<'a> {
+    format: &'a str,
+    version: u32,
+    exported_at: DateTime<Utc>,
+    data: &'a PlannerData,
+}
+
+/// All the data as JSON, in the latest export format.
+pub fn export(data: &PlannerData, now: DateTime<Utc>) -> Result<String> {
+    Ok(serde_json::to_string_pretty(&Export {
+        format: EXPORT_FORMAT,
+        version: EXPORT_VERSION,
+        exported_at: now,
+        data,
+    })?)
+}
+
+/// Reads an export of any version up to the latest.
+pub fn import(text: &str) -> Result<PlannerData> {
+    let value: Value = serde_json::from_str(text)
+        .map_err(|e| PlannerError::Invalid(format!("the export is not JSON: {}", e)))?;
+    import_value(value)
+}
+
+fn import_value(mut value: Value) -> Result<PlannerData> {
+    let version = match value.get("format") {
+        None => 0,
+        Some(format) if format == EXPORT_FORMAT => value["version"]
+            .as_u64()
+            .ok_or_else(|| PlannerError::Invalid("the export has no version".to_string()))?
+            as u32,
+        Some(_) => {
+            return Err(PlannerError::Invalid(
+                "this is not a planner export".to_string(),
+            ))
+        }
+    };
+    if version > EXPORT_VERSION {
+        return Err(PlannerError::Invalid(format!(
+            "the export is in version {} of the format, this planner reads up to {}",
+            version, EXPORT_VERSION
+        )));
+    }
+    if version == 0 {
+        value = serde_json::json!({
+            "format": EXPORT_FORMAT,
+            "version": 1,
+            "data": value,
+        });
+    }
+    // Lists added since an export was made read as empty, and routine checks
+    // from before they had a status as done.
+    serde_json::from_value(value["data"].take())
+        .map_err(|e| PlannerError::Invalid(format!("the exported data is unreadable: {}", e)))
+}
+
+fn checksum(bytes: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(bytes))
+}
+
+fn damaged(reason: &str) -> PlannerError {
+    PlannerError::Invalid(format!("the backup cannot be restored, {}", reason))
+}
@@ -0,0 +1,215 @@
+// Weekly time budgets per subject, counted against stopwatch sessions. A
+// target is time the user means to spend ("10h Rust"), a cap is time they do
+// not want to go over ("max 5h meetings").
+//
+// A budget is never edited. Changing one ends it and starts a new one on the
+// same day, so days already over keep the budget they had, and a week with a
+// change in it gets each budget for its share of the days.
+use std::fmt;
+
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::data::PlannerData;
+use crate::report::DateRange;
+use crate::stopwatch::format_duration;
+use crate::time;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BudgetKind {
+    /// Time to spend at least.
+    Target,
+    /// Time to spend at most.
+    Cap,
+}
+
+impl fmt::Display for BudgetKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            BudgetKind::Target => "target",
+            BudgetKind::Cap => "cap",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// When to notify, read from the `budgets` section of `config.json`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BudgetSettings {
+    /// Percentages of a weekly budget that raise a notification.
+    pub thresholds: Vec<u32>,
+}
+
+impl Default for BudgetSettings {
+    fn default() -> Self {
+        BudgetSettings {
+            thresholds: vec![80, 100],
+        }
+    }
+}
+
+/// Time per week for one subject, from one day until it is changed.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Budget {
+    pub id: u64,
+    pub subject: String,
+    pub kind: BudgetKind,
+    /// Minutes per week, Monday to Sunday.
+    pub minutes: u32,
+    /// The first day it applies.
+    pub from: NaiveDate,
+    /// The first day it no longer applies, once changed or removed.
+    #[serde(default)]
+    pub until: Option<NaiveDate>,
+    /// Thresholds already announced, with the Monday of their week.
+    #[serde(default)]
+    pub alerted: Vec<(NaiveDate, u32)>,
+}
+
+impl Budget {
+    pub fn applies_on(&self, date: NaiveDate) -> bool {
+        self.from <= date && self.until.is_none_or(|until| date < until)
+    }
+
+    /// Subjects match ignoring case, like "Rust" and "rust".
+    pub fn is_for(&self, subject: &str) -> bool {
+        self.subject.to_lowercase() == subject.trim().to_lowercase()
+    }
+}
+
+/// How a subject did against its budget over some days.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Progress {
+    pub subject: String,
+    /// The kind of the latest budget in the range.
+    pub kind: BudgetKind,
+    /// The budget for the days it applied on, each day being a seventh of
+    /// its week.
+    pub budget_minutes: i64,
+    /// Minutes spent on the subject on those days.
+    pub spent_minutes: i64,
+}
+
+impl Progress {
+    /// Share of the budget spent, `None` when the budget rounds to nothing.
+    pub fn percent(&self) -> Option<f64> {
+        if self.budget_minutes == 0 {
+            None
+        } else {
+            Some(self.spent_minutes as f64 * 100.0 / self.budget_minutes as f64)
+        }
+    }
+
+    /// A target reached, or a cap not gone over.
+    pub fn is_met(&self) -> bool {
+        match self.kind {
+            BudgetKind::Target => self.spent_minutes >= self.budget_minutes,
+            BudgetKind::Cap => self.spent_minutes <= self.budget_minutes,
+        }
+    }
+
+    /// What is left of the budget, like "6h 50m short" or "1h 10m over".
+    pub fn outcome(&self) -> String {
+        let left = self.budget_minutes - self.spent_minutes;
+        match self.kind {
+            BudgetKind::Target if left <= 0 => "reached".to_string(),
+            BudgetKind::Target => format!("{} short", show(left)),
+            BudgetKind::Cap if left < 0 => format!("{} over", show(-left)),
+            BudgetKind::Cap => format!("{} left", show(left)),
+        }
+    }
+}
+
+impl fmt::Display for Progress {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}: {} of a {} {}",
+            self.subject,
+            show(self.spent_minutes),
+            show(self.budget_minutes),
+            self.kind
+        )?;
+        if let Some(percent) = self.percent() {
+            write!(f, " ({:.0}%)", percent)?;
+        }
+        write!(f, ", {}", self.outcome())
+    }
+}
+
+fn show(minutes: i64) -> String {
+    format_duration(Duration::minutes(minutes))
+}
+
+/// Every budgeted subject against its budgets on the days of `range`, in
+/// the order they were first budgeted. A running session counts up to `now`
+/// when it is given; reports leave it out so they never change.
+pub fn progress(data: &PlannerData, range: DateRange, now: Option<DateTime<Utc>>) -> Vec<Progress> {
+    let mut subjects: Vec<&str> = Vec::new();
+    for budget in &data.budgets {
+        if !subjects.iter().any(|subject| budget.is_for(subject)) {
+            subjects.push(&budget.subject);
+        }
+    }
+
+    let mut results = Vec::new();
+    for subject in subjects {
+        let mut budget_minutes = 0.0;
+        let mut seconds = 0;
+        let mut latest = None;
+        for date in range.dates() {
+            let Some(budget) = data
+                .budgets
+                .iter()
+                .find(|budget| budget.is_for(subject) && budget.applies_on(date))
+            else {
+                continue;
+            };
+            budget_minutes += budget.minutes as f64 / 7.0;
+            latest = Some(budget);
+            let from = time::from_local(date.and_hms_opt(0, 0, 0).unwrap());
+            let to = time::from_local((date + Duration::days(1)).and_hms_opt(0, 0, 0).unwrap());
+            for session in data.sessions.iter().filter(|s| budget.is_for(&s.subject)) {
+                seconds += match now {
+                    Some(now) => session.seconds_until(from, to, now),
+                    None => session.seconds_between(from, to),
+                };
+            }
+        }
+        if let Some(budget) = latest {
+            results.push(Progress {
+                subject: budget.subject.clone(),
+                kind: budget.kind,
+                budget_minutes: budget_minutes.round() as i64,
+                spent_minutes: seconds / 60,
+            });
+        }
+    }
+    results
+}
+
+/// A threshold a subject went past this week.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Alert {
+    pub threshold: u32,
+    pub progress: Progress,
+}
+
+impl fmt::Display for Alert {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let progress = &self.progress;
+        let budget = format!("{} {}", show(progress.budget_minutes), progress.kind);
+        if self.threshold == 100 {
+            write!(f, "{} reached its {} this week", progress.subject, budget)?;
+        } else {
+            write!(
+                f,
+                "{} is at {}% of its {} this week",
+                progress.subject, self.threshold, budget
+            )?;
+        }
+        write!(f, " ({})", show(progress.spent_minutes))
+    }
+}
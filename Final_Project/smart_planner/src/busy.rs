@@ -0,0 +1,66 @@
+use chrono::{DateTime, Duration, NaiveDate, NaiveTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::time;
+
+/// When a busy event happens.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum BusyTime {
+    /// Whole days, `last` included.
+    AllDay { first: NaiveDate, last: NaiveDate },
+    Timed {
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    },
+}
+
+/// Time taken by something in the user's calendar, imported from an `.ics`
+/// file so plans leave it free.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BusyEvent {
+    /// `UID` of the calendar event. Occurrences of a repeating event share it.
+    pub uid: String,
+    pub summary: String,
+    pub time: BusyTime,
+}
+
+impl BusyEvent {
+    /// The local times the event takes on `date`, if any.
+    pub fn slot_on(&self, date: NaiveDate) -> Option<(NaiveTime, NaiveTime)> {
+        let midnight = NaiveTime::MIN;
+        let last_second = NaiveTime::from_hms_opt(23, 59, 59).unwrap();
+        match &self.time {
+            BusyTime::AllDay { first, last } => {
+                (*first <= date && date <= *last).then_some((midnight, last_second))
+            }
+            BusyTime::Timed { start, end } => {
+                let (start, end) = (time::to_local(*start), time::to_local(*end));
+                let day_start = date.and_time(midnight);
+                let day_end = day_start + Duration::days(1);
+                if end <= day_start || start >= day_end {
+                    return None;
+                }
+                let from = if start < day_start {
+                    midnight
+                } else {
+                    start.time()
+                };
+                let to = if end >= day_end {
+                    last_second
+                } else {
+                    end.time()
+                };
+                (to > from).then_some((from, to))
+            }
+        }
+    }
+
+    /// Whether the event is over before `date` starts.
+    pub fn ends_before(&self, date: NaiveDate) -> bool {
+        match &self.time {
+            BusyTime::AllDay { last, .. } => *last < date,
+            BusyTime::Timed { end, .. } => time::to_local(*end).date() < date,
+        }
+    }
+}
@@ -0,0 +1,180 @@
+// Charts drawn with text, for the report and the dashboard: sparklines,
+// horizontal bars and a year of days as a heatmap. Each is drawn with Unicode
+// block characters, or with plain ASCII for terminals and fonts without them.
+use std::env;
+
+use chrono::{Datelike, Duration, NaiveDate};
+
+/// Which characters the charts are drawn with.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Charset {
+    #[default]
+    Unicode,
+    Ascii,
+}
+
+/// From lowest to highest.
+const SPARK_UNICODE: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+const SPARK_ASCII: [char; 8] = ['_', '.', ':', '-', '=', '+', '*', '#'];
+/// Eighths of a cell, for the end of a bar.
+const EIGHTHS: [char; 7] = ['▏', '▎', '▍', '▌', '▋', '▊', '▉'];
+/// Nothing, then the four quarters of the busiest day.
+const HEAT_UNICODE: [char; 5] = ['·', '░', '▒', '▓', '█'];
+const HEAT_ASCII: [char; 5] = ['.', '-', '+', '*', '#'];
+/// Weekday names shown left of the heatmap rows, Monday first.
+const WEEKDAY_LABELS: [&str; 7] = ["Mon", "", "Wed", "", "Fri", "", ""];
+
+impl Charset {
+    /// Unicode when the locale says the terminal takes UTF-8, as in
+    /// `LANG=en_US.UTF-8`.
+    pub fn detect() -> Self {
+        let locale = ["LC_ALL", "LC_CTYPE", "LANG"]
+            .iter()
+            .find_map(|name| env::var(name).ok().filter(|value| !value.is_empty()))
+            .unwrap_or_default()
+            .to_lowercase();
+        if locale.contains("utf-8") || locale.contains("utf8") {
+            Charset::Unicode
+        } else {
+            Charset::Ascii
+        }
+    }
+
+    fn spark(self) -> &'static [char; 8] {
+        match self {
+            Charset::Unicode => &SPARK_UNICODE,
+            Charset::Ascii => &SPARK_ASCII,
+        }
+    }
+
+    fn heat(self) -> &'static [char; 5] {
+        match self {
+            Charset::Unicode => &HEAT_UNICODE,
+            Charset::Ascii => &HEAT_ASCII,
+        }
+    }
+}
+
+/// Which of `levels` steps `value` falls in, 0 only for nothing and the top
+/// one for `max`.
+fn level(value: i64, max: i64, levels: usize) -> usize {
+    if value <= 0 || max <= 0 {
+        return 0;
+    }
+    let top = levels as i64 - 1;
+    ((value * top + max - 1) / max).clamp(1, top) as usize
+}
+
+/// One character per value, as high as the value is against the largest.
+pub fn sparkline(values: &[i64], charset: Charset) -> String {
+    let max = values.iter().copied().max().unwrap_or(0);
+    let glyphs = charset.spark();
+    values
+        .iter()
+        .map(|value| glyphs[level(*value, max, glyphs.len())])
+        .collect()
+}
+
+/// A bar per row, the largest `width` characters long, with the label on
+/// the left and `show(value)` on the right.
+pub fn bars(
+    rows: &[(String, i64)],
+    width: usize,
+    charset: Charset,
+    show: impl Fn(i64) -> String,
+) -> String {
+    let max = rows.iter().map(|(_, value)| *value).max().unwrap_or(0);
+    let label_width = rows
+        .iter()
+        .map(|(label, _)| label.chars().count())
+        .max()
+        .unwrap_or(0);
+    let mut out = String::new();
+    for (label, value) in rows {
+        let bar = bar(*value, max, width, charset);
+        let padding = width.saturating_sub(bar.chars().count());
+        let line = format!(
+            "{:<label_width$}  {}{}  {}",
+            label,
+            bar,
+            " ".repeat(padding),
+            show(*value)
+        );
+        out.push_str(line.trim_end());
+        out.push('\n');
+    }
+    out
+}
+
+fn bar(value: i64, max: i64, width: usize, charset: Charset) -> String {
+    if value <= 0 || max <= 0 {
+        return String::new();
+    }
+    match charset {
+        Charset::Unicode => {
+            let eighths = ((value * width as i64 * 8) / max).max(1) as usize;
+            let mut bar = "█".repeat(eighths / 8);
+            let rest = eighths % 8;
+            if rest > 0 {
+                bar.push(EIGHTHS[rest - 1]);
+            }
+            bar
+        }
+        Charset::Ascii => {
+            let cells = ((value * width as i64 + max / 2) / max).max(1) as usize;
+            "#".repeat(cells)
+        }
+    }
+}
+
+/// A column per week and a row per weekday, Monday on top, shaded by how
+/// much of the busiest day's amount each day had. `days` are in order
+/// without gaps, like a year of `daily_minutes`.
+pub fn heatmap(days: &[(NaiveDate, i64)], charset: Charset) -> String {
+    let (Some((first, _)), Some((last, _))) = (days.first(), days.last()) else {
+        return String::new();
+    };
+    let glyphs = charset.heat();
+    let max = days.iter().map(|(_, value)| *value).max().unwrap_or(0);
+    let monday = *first - Duration::days(first.weekday().num_days_from_monday() as i64);
+    let weeks = ((*last - monday).num_days() / 7 + 1) as usize;
+    let mut grid = vec![vec![' '; weeks]; 7];
+    for (date, value) in days {
+        let offset = (*date - monday).num_days();
+        grid[(offset % 7) as usize][(offset / 7) as usize] =
+            glyphs[level(*value, max, glyphs.len())];
+    }
+
+    // Month names over the week their first day falls in, when they fit
+    let mut months = vec![' '; weeks + 3];
+    let mut free_from = 0;
+    for week in 0..weeks {
+        let start = monday + Duration::days(week as i64 * 7);
+        let starts_month = (0..7).any(|day| (start + Duration::days(day)).day() == 1);
+        if (week == 0 || starts_month) && week >= free_from {
+            let shown = if week == 0 {
+                *first
+            } else {
+                start + Duration::days(6)
+            };
+            for (i, letter) in shown.format("%b").to_string().chars().enumerate() {
+                months[week + i] = letter;
+            }
+            free_from = week + 4;
+        }
+    }
+    let mut out = format!("     {}", months.iter().collect::<String>().trim_end());
+    out.push('\n');
+    for (row, cells) in grid.iter().enumerate() {
+        let line = format!(
+            "{:<4} {}",
+            WEEKDAY_LABELS[row],
+            cells.iter().collect::<String>()
+        );
+        out.push_str(line.trim_end());
+        out.push('\n');
+    }
+    let legend: Vec<String> = glyphs.iter().map(|glyph| glyph.to_string()).collect();
+    out.push_str(&format!("     Less {} More\n", legend.join(" ")));
+    out
+}
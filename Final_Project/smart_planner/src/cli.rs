@@ -0,0 +1,2749 @@
+// Command line interface. Each handler parses what clap gives it, calls the
+// matching method on `PlannerData` and prints the result.
+use std::env;
+use std::fs;
+use std::io::{self, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{mpsc, Arc};
+use std::thread;
+use std::time::Duration;
+
+use chrono::{DateTime, NaiveDate, NaiveTime, Utc};
+use clap::{Parser, Subcommand, ValueEnum};
+
+use crate::backup;
+use crate::budget::{self, BudgetKind};
+use crate::busy::{BusyEvent, BusyTime};
+use crate::chart::Charset;
+use crate::daemon::clock::SystemClock;
+use crate::daemon::{self, Daemon, Request};
+use crate::dashboard::{self, Snapshot};
+use crate::data::{NewTodo, PlannerData};
+use crate::email::ReportEmail;
+use crate::error::{PlannerError, Result};
+use crate::history::{Event, Kind};
+use crate::ical::{self, ExportOptions};
+use crate::notify::{Notifier, TerminalNotifier};
+use crate::plan::{BlockKind, ConflictChecker, LocalScheduler, PlanBlock, PlanRequest, Planner};
+use crate::pomodoro::{Phase, Pomodoro, PomodoroRun};
+use crate::profile::{self, Profiles};
+use crate::project::graph::{list_ids, Graph};
+use crate::report::{self, DateRange, Period, Report, Summary};
+use crate::routine::habit::Habit;
+use crate::routine::{CheckStatus, Frequency};
+use crate::server::{self, ApiServer};
+use crate::stopwatch::format_duration;
+use crate::storage::{self, StorageConfig};
+use crate::store::Store;
+use crate::sync::{Delta, SyncState};
+use crate::time;
+use crate::time::zone::Zone;
+use crate::timer::Timer;
+use crate::todo::{markdown, quick, todotxt, Merged, Priority, Todo};
+use crate::vault::{self, KdfParams, Vault};
+use crate::webhook::{self, EventKind, Sender};
+
+/// Where scripts can give the passphrase instead of typing it.
+const PASSPHRASE: &str = "SMART_PLANNER_PASSPHRASE";
+/// The new passphrase for `encryption rotate`.
+const NEW_PASSPHRASE: &str = "SMART_PLANNER_NEW_PASSPHRASE";
+/// The profile to use when `--profile` is not given.
+const PROFILE: &str = "SMART_PLANNER_PROFILE";
+
+#[derive(Debug, Parser)]
+#[command(
+    name = "smart_planner",
+    about = "Organize your tasks, track your progress and plan your day"
+)]
+pub struct Cli {
+    /// Folder holding the planner data [default: $SMART_PLANNER_HOME or ~/.smart_planner]
+    #[arg(long, global = true)]
+    pub home: Option<PathBuf>,
+    /// Profile to use [default: $SMART_PLANNER_PROFILE or the default profile]
+    #[arg(long, global = true)]
+    pub profile: Option<String>,
+
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Create, view, complete or delete todos
+    #[command(subcommand)]
+    Todo(TodoCommand),
+    /// Group todos into projects and see what can be done next
+    #[command(subcommand)]
+    Project(ProjectCommand),
+    /// Create, view or delete daily routines
+    #[command(subcommand)]
+    Routine(RoutineCommand),
+    /// Create, view, edit or delete daily plans
+    #[command(subcommand)]
+    Plan(PlanCommand),
+    /// Start, stop or reset the stopwatch
+    #[command(subcommand)]
+    Stopwatch(StopwatchCommand),
+    /// Set, list, cancel or wait for timers
+    #[command(subcommand)]
+    Timer(TimerCommand),
+    /// Work in Pomodoro rounds, logged on the stopwatch
+    #[command(subcommand)]
+    Pomodoro(PomodoroCommand),
+    /// Set weekly time budgets per subject and see how the week is going
+    #[command(subcommand)]
+    Budget(BudgetCommand),
+    /// Export to or import busy times from iCalendar (.ics) files
+    #[command(subcommand)]
+    Calendar(CalendarCommand),
+    /// Show today's plan, the stopwatch, timers and urgent todos on one screen
+    Dashboard,
+    /// Report time per subject, todo completion and routine adherence
+    Report(ReportArgs),
+    /// See who changed what, and look at or go back to an earlier state
+    #[command(subcommand)]
+    History(HistoryCommand),
+    /// Share todos, routines and sessions with planners on other machines
+    #[command(subcommand)]
+    Sync(SyncCommand),
+    /// Test webhooks and send failed deliveries again
+    #[command(subcommand)]
+    Webhook(WebhookCommand),
+    /// Copy all data to another storage backend
+    Migrate(MigrateArgs),
+    /// Save the data, config, history and sync state to one checked file
+    Backup { file: PathBuf },
+    /// Put back everything saved by `backup`
+    Restore {
+        file: PathBuf,
+        /// Only check that the backup can be restored
+        #[arg(long)]
+        check: bool,
+        /// Restore without asking
+        #[arg(short, long)]
+        yes: bool,
+        /// Restore a backup of another profile into this one
+        #[arg(long)]
+        other_profile: bool,
+    },
+    /// Write all data as JSON, to look at with other tools
+    Export {
+        /// Write to a file instead of printing
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Replace all data with a JSON export, also from older versions
+    Import {
+        file: PathBuf,
+        /// Import without asking
+        #[arg(short, long)]
+        yes: bool,
+    },
+    /// Answer JSON requests on localhost for other tools
+    Serve(ServeArgs),
+    /// Notify of timers and due todos in the background
+    #[command(subcommand)]
+    Daemon(DaemonCommand),
+    /// Decrypt the planner folder with the passphrase
+    Unlock,
+    /// Encrypt the planner folder until the next `unlock`
+    Lock,
+    /// Turn encryption of the planner folder on or off, or change its key
+    #[command(subcommand)]
+    Encryption(EncryptionCommand),
+    /// Keep separate planners for the people sharing this one
+    #[command(subcommand)]
+    Profile(ProfileCommand),
+}
+
+#[derive(Debug, Subcommand)]
+pub enum TodoCommand {
+    /// Add a todo
+    Add {
+        title: String,
+        #[arg(short, long, default_value = "medium")]
+        priority: Priority,
+        /// Local due time, like "2024-01-31 17:00" or just "2024-01-31"
+        #[arg(long, value_parser = time::parse_local_datetime)]
+        due: Option<DateTime<Utc>>,
+        /// Estimated minutes of work
+        #[arg(short, long)]
+        estimate: Option<u32>,
+        #[arg(short, long)]
+        subject: Option<String>,
+        /// Put it in this project
+        #[arg(long)]
+        project: Option<u64>,
+        /// Make it a subtask of this todo
+        #[arg(long)]
+        parent: Option<u64>,
+    },
+    /// Add a todo from one line, like "finish report tomorrow 3pm !high #work ~45m"
+    Quick {
+        #[arg(required = true, num_args = 1.., allow_hyphen_values = true)]
+        text: Vec<String>,
+        /// Add it without asking
+        #[arg(short, long)]
+        yes: bool,
+        /// Only show what was understood
+        #[arg(long, conflicts_with = "yes")]
+        dry_run: bool,
+    },
+    /// List open todos
+    List {
+        /// Include finished todos
+        #[arg(long)]
+        all: bool,
+    },
+    /// Mark a todo as done
+    Done {
+        id: u64,
+        /// Also complete its open subtasks without asking
+        #[arg(long)]
+        with_subtasks: bool,
+    },
+    /// Record that a todo cannot start before another is done
+    Block { id: u64, by: u64 },
+    /// Remove a blocker added with `block`
+    Unblock { id: u64, by: u64 },
+    /// Make a todo a subtask of another one
+    Parent {
+        id: u64,
+        #[arg(required_unless_present = "none")]
+        parent: Option<u64>,
+        /// Make it a top level todo again
+        #[arg(long, conflicts_with = "parent")]
+        none: bool,
+    },
+    /// Move a todo and its subtasks to a project
+    Move {
+        id: u64,
+        #[arg(required_unless_present = "none")]
+        project: Option<u64>,
+        /// Take it out of its project
+        #[arg(long, conflicts_with = "project")]
+        none: bool,
+    },
+    /// Delete a todo
+    Delete { id: u64 },
+    /// Write todos as a todo.txt file or a Markdown checklist
+    Export {
+        /// Write to a file instead of printing
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+        /// [default: from the output's extension, else txt]
+        #[arg(long, value_enum)]
+        format: Option<TodoFormat>,
+        /// Only this project's todos
+        #[arg(long)]
+        project: Option<u64>,
+    },
+    /// Read todos from a todo.txt file or a Markdown checklist
+    Import {
+        file: PathBuf,
+        /// [default: from the file's extension]
+        #[arg(long, value_enum)]
+        format: Option<TodoFormat>,
+        /// Put new todos in this project
+        #[arg(long)]
+        project: Option<u64>,
+    },
+    /// Bring a Markdown checklist and the todos up to date with each other,
+    /// editing the file in place
+    Sync {
+        file: PathBuf,
+        /// Only this project's todos, and new ones go in it
+        #[arg(long)]
+        project: Option<u64>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum TodoFormat {
+    /// todo.txt, one todo per line
+    Txt,
+    /// A Markdown checklist
+    Md,
+}
+
+impl TodoFormat {
+    /// The format a file name suggests.
+    fn of(path: &std::path::Path) -> Option<Self> {
+        match path.extension()?.to_str()?.to_lowercase().as_str() {
+            "txt" => Some(TodoFormat::Txt),
+            "md" | "markdown" => Some(TodoFormat::Md),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ProjectCommand {
+    /// Add a project
+    Add { name: String },
+    /// List projects with how far along they are
+    List,
+    /// Show a project's todos with their subtasks
+    Show { id: u64 },
+    /// List the todos that can be done now, and what the others wait for
+    Next {
+        /// Only this project's todos [default: every todo]
+        id: Option<u64>,
+    },
+    /// Show the longest chain of todos, by estimate, left in a project
+    Critical { id: u64 },
+    /// Delete a project, keeping its todos
+    Delete { id: u64 },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum RoutineCommand {
+    /// Add a routine
+    Add {
+        name: String,
+        /// Local start time, like 07:30
+        #[arg(long, value_parser = time::parse_time)]
+        at: NaiveTime,
+        /// Length in minutes
+        #[arg(short, long)]
+        duration: u32,
+        /// daily, weekdays, weekends or a list like mon,wed,fri
+        #[arg(long, default_value = "daily")]
+        days: Frequency,
+    },
+    /// List routines
+    List,
+    /// Mark a routine as done for a day
+    Done {
+        id: u64,
+        /// [default: today]
+        #[arg(long)]
+        date: Option<NaiveDate>,
+        /// Only part of it got done
+        #[arg(long, conflicts_with = "skip")]
+        partial: bool,
+        /// Left out on purpose
+        #[arg(long)]
+        skip: bool,
+    },
+    /// Show current and longest streaks and how often each routine got done
+    Streaks,
+    /// Show a calendar of the days a routine was done, skipped or missed
+    Heatmap {
+        id: u64,
+        #[arg(long, default_value_t = 12)]
+        weeks: u32,
+    },
+    /// Delete a routine
+    Delete { id: u64 },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum StopwatchCommand {
+    /// Start timing work on a subject
+    Start { subject: String },
+    /// Stop and record the running session
+    Stop,
+    /// Show the running session
+    Status,
+    /// Throw the running session away
+    Reset,
+    /// List recorded sessions
+    List,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum TimerCommand {
+    #[command(flatten)]
+    Data(TimerDataCommand),
+    /// Stay open and notify when each timer runs out
+    Wait,
+}
+
+/// The timer commands that work on the loaded data.
+#[derive(Debug, Subcommand)]
+pub enum TimerDataCommand {
+    /// Set a timer
+    Set {
+        label: String,
+        /// Minutes from now
+        #[arg(
+            long = "in",
+            required_unless_present_any = ["at", "daily"],
+            conflicts_with_all = ["at", "daily"]
+        )]
+        minutes: Option<u32>,
+        /// Local time it runs out, like "2024-01-31 17:00"
+        #[arg(long, value_parser = time::parse_local_datetime, conflicts_with = "daily")]
+        at: Option<DateTime<Utc>>,
+        /// Go off every day at this local time, like 07:00
+        #[arg(long, value_parser = time::parse_time)]
+        daily: Option<NaiveTime>,
+        /// Todo the timer is a deadline for
+        #[arg(long)]
+        todo: Option<u64>,
+    },
+    /// List timers that have not run out
+    List {
+        /// Include timers that already ran out
+        #[arg(long)]
+        all: bool,
+    },
+    /// Cancel a timer
+    Cancel { id: u64 },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum PomodoroCommand {
+    #[command(flatten)]
+    Data(PomodoroDataCommand),
+    /// Stay open and notify at each change of phase
+    Watch,
+}
+
+/// The Pomodoro commands that work on the loaded data.
+#[derive(Debug, Subcommand)]
+pub enum PomodoroDataCommand {
+    /// Start working on a subject, with breaks in between
+    Start {
+        subject: String,
+        /// Minutes of work [default: 25 or the pomodoro section of config.json]
+        #[arg(long)]
+        work: Option<u32>,
+        /// Minutes of a short break [default: 5]
+        #[arg(long)]
+        short_break: Option<u32>,
+        /// Minutes of a long break [default: 15]
+        #[arg(long)]
+        long_break: Option<u32>,
+        /// Take a long break after every N pomodoros [default: 4]
+        #[arg(long)]
+        long_every: Option<u32>,
+        /// Stop after N pomodoros instead of going on until stopped
+        #[arg(long)]
+        cycles: Option<u32>,
+    },
+    /// Show the phase and the time left
+    Status,
+    /// Stop the run, keeping a cut short pomodoro as interrupted
+    Stop,
+    /// List pomodoros
+    List {
+        /// Only this day [default: all]
+        #[arg(long)]
+        date: Option<NaiveDate>,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum BudgetCommand {
+    #[command(flatten)]
+    Data(BudgetDataCommand),
+    /// Stay open while the stopwatch runs and notify at each threshold
+    Watch,
+}
+
+/// The budget commands that work on the loaded data.
+#[derive(Debug, Subcommand)]
+pub enum BudgetDataCommand {
+    /// Aim to spend at least this long on a subject each week, like "10h rust"
+    Target {
+        #[arg(value_parser = parse_length)]
+        length: u32,
+        subject: String,
+    },
+    /// Try to spend at most this long on a subject each week, like "5h meetings"
+    Cap {
+        #[arg(value_parser = parse_length)]
+        length: u32,
+        subject: String,
+    },
+    /// Stop budgeting a subject from today on
+    Remove { subject: String },
+    /// Show this week's budgets and the time spent so far
+    List {
+        /// Also list budgets that were changed or removed
+        #[arg(long)]
+        all: bool,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum EncryptionCommand {
+    /// Choose a passphrase and lock the folder
+    Enable,
+    /// Unlock the folder for good and forget the key
+    Disable,
+    /// Encrypt everything again with a new key, and optionally a new passphrase
+    Rotate,
+    /// Say whether the folder is encrypted and locked
+    Status,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum HistoryCommand {
+    #[command(flatten)]
+    Data(HistoryDataCommand),
+    /// Check that replaying the history gives the saved data
+    Verify,
+}
+
+/// The history commands that work on the loaded data.
+#[derive(Debug, Subcommand)]
+pub enum HistoryDataCommand {
+    /// List saved changes, oldest first
+    List {
+        /// Show at most the last N changes
+        #[arg(short = 'n', long, default_value = "20")]
+        limit: usize,
+        /// Only changes to this kind of item, like todo or session
+        #[arg(long)]
+        kind: Option<Kind>,
+        /// Only changes to the item with this id
+        #[arg(long)]
+        id: Option<u64>,
+        /// Only changes from this local time on, like "2024-01-31 17:00"
+        #[arg(long, value_parser = time::parse_local_datetime)]
+        since: Option<DateTime<Utc>>,
+    },
+    /// Show the todo list as it was at a local time, like "2024-01-31 17:00"
+    /// or just "2024-01-31" for the end of that day
+    At {
+        #[arg(value_parser = time::parse_local_datetime)]
+        when: DateTime<Utc>,
+        /// Include finished todos
+        #[arg(long)]
+        all: bool,
+        /// Print all of the data as JSON instead
+        #[arg(long)]
+        json: bool,
+    },
+    /// Go back to how everything was at a local time; the rewind is itself
+    /// recorded, so it can be undone
+    Restore {
+        #[arg(value_parser = time::parse_local_datetime)]
+        when: DateTime<Utc>,
+        /// Restore without asking
+        #[arg(short, long)]
+        yes: bool,
+    },
+}
+
+#[derive(Debug, Clone, Copy, Subcommand)]
+pub enum DaemonCommand {
+    /// Stay open and notify when timers run out and todos come due
+    Run,
+    #[command(flatten)]
+    Control(DaemonControlCommand),
+}
+
+/// Requests to a daemon that is already running.
+#[derive(Debug, Clone, Copy, Subcommand)]
+pub enum DaemonControlCommand {
+    /// Show what a running daemon is waiting for
+    Status,
+    /// Make a running daemon read the data and config again
+    Reload,
+    /// Stop a running daemon
+    Stop,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ProfileCommand {
+    /// List the profiles, the default one marked with *
+    List,
+    /// Add an empty profile
+    Create { name: String },
+    /// Delete a profile with all its data
+    Remove {
+        name: String,
+        /// Delete without asking
+        #[arg(short, long)]
+        yes: bool,
+    },
+    /// Show or set the profile used without --profile
+    Default {
+        name: Option<String>,
+        /// Use the home folder itself again
+        #[arg(long, conflicts_with = "name")]
+        clear: bool,
+    },
+    /// Show how much each profile keeps on disk
+    Usage,
+    /// Copy routines or todos from one profile to another
+    Copy {
+        #[arg(value_enum)]
+        what: CopyKind,
+        /// Ids to copy [default: all routines, or all open todos]
+        ids: Vec<u64>,
+        /// Profile to copy from [default: the one in use]
+        #[arg(long)]
+        from: Option<String>,
+        /// Profile to copy to
+        #[arg(long)]
+        to: String,
+    },
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum CopyKind {
+    Routines,
+    Todos,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum WebhookCommand {
+    /// Post a ping to every webhook in config.json
+    Test,
+    /// List deliveries that failed every attempt
+    Failed,
+    /// Post the failed deliveries again
+    Retry,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum SyncCommand {
+    /// Show this planner's replica name and what it has seen
+    Status,
+    /// Write the changes another planner lacks to a file
+    Export {
+        file: PathBuf,
+        /// Only what this replica has not seen yet [default: everything]
+        #[arg(long)]
+        peer: Option<String>,
+    },
+    /// Take in changes written by `sync export` on another planner
+    Import { file: PathBuf },
+    /// Swap changes with another planner running `serve`, like
+    /// http://desktop:7878
+    With {
+        url: String,
+        /// Deprecated, as other users can read it in the process list; set
+        /// SMART_PLANNER_API_TOKEN or `api_token` in config.json instead
+        #[arg(long, hide = true)]
+        token: Option<String>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ExportKind {
+    Plans,
+    Routines,
+    /// Todos with a due time
+    Todos,
+    /// Timers that have not run out
+    Timers,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum CalendarCommand {
+    /// Write plans, routines, todos and timers as an .ics file
+    Export {
+        /// Write to a file instead of printing
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+        /// Only these kinds, like plans,routines [default: all]
+        #[arg(long, value_enum, value_delimiter = ',')]
+        only: Vec<ExportKind>,
+    },
+    /// Read the busy times of an .ics file so plans leave them free
+    Import {
+        file: PathBuf,
+        /// How many days ahead repeating events are taken
+        #[arg(long, default_value = "90")]
+        days: u32,
+    },
+    /// List imported busy times from today on
+    List {
+        /// Only this day
+        #[arg(long)]
+        date: Option<NaiveDate>,
+    },
+    /// Forget every imported busy time
+    Clear,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct MigrateArgs {
+    /// json, sqlite, json:PATH, sqlite:PATH or the URL of a sync server
+    #[arg(long)]
+    pub to: StorageConfig,
+    /// Where to copy from [default: the storage in config.json]
+    #[arg(long)]
+    pub from: Option<StorageConfig>,
+    /// Replace data already in the target
+    #[arg(long)]
+    pub overwrite: bool,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct ServeArgs {
+    #[arg(long, default_value_t = server::DEFAULT_PORT)]
+    pub port: u16,
+    /// Deprecated, as other users can read it in the process list; set
+    /// SMART_PLANNER_API_TOKEN or `api_token` in config.json instead
+    #[arg(long, hide = true)]
+    pub token: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum ReportFormat {
+    /// Aligned table for the terminal
+    Text,
+    Csv,
+    /// Self-contained page with charts
+    Html,
+}
+
+#[derive(Debug, clap::Args)]
+pub struct ReportArgs {
+    /// day, week (Monday to Sunday) or month
+    #[arg(short, long, default_value = "week")]
+    pub period: Period,
+    /// A day inside the period to report [default: today]
+    #[arg(long)]
+    pub date: Option<NaiveDate>,
+    /// First day of a custom range, instead of a period
+    #[arg(long, requires = "to")]
+    pub from: Option<NaiveDate>,
+    /// Last day of a custom range
+    #[arg(long, requires = "from")]
+    pub to: Option<NaiveDate>,
+    #[arg(short, long, value_enum, default_value = "text")]
+    pub format: ReportFormat,
+    /// Write the report to a file instead of printing it
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+    /// Email the report (text and HTML) using the smtp settings in config.json
+    #[arg(long)]
+    pub email: bool,
+    /// Recipient, instead of the ones in the config (repeat for more)
+    #[arg(long = "recipient", requires = "email")]
+    pub recipients: Vec<String>,
+    /// Attach the CSV version to the email
+    #[arg(long, requires = "email")]
+    pub attach_csv: bool,
+    /// Add charts of focus time and a heatmap of the past year (text only)
+    #[arg(long)]
+    pub charts: bool,
+    /// Draw the charts with plain ASCII instead of block characters
+    #[arg(long, requires = "charts")]
+    pub ascii: bool,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum Backend {
+    /// Offline scheduler, no network needed
+    Local,
+    /// Chat-completion API configured in config.json
+    Llm,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum PlanCommand {
+    /// Generate the plan for a day, replacing the existing one
+    Generate {
+        /// Day to plan [default: today]
+        #[arg(long)]
+        date: Option<NaiveDate>,
+        #[arg(short, long, value_enum, default_value = "local")]
+        backend: Backend,
+    },
+    /// Show the plan for a day
+    Show {
+        #[arg(long)]
+        date: Option<NaiveDate>,
+    },
+    /// Change a generated plan
+    Edit {
+        #[arg(long)]
+        date: Option<NaiveDate>,
+        #[command(subcommand)]
+        edit: EditCommand,
+    },
+    /// Delete the plan for a day
+    Delete {
+        #[arg(long)]
+        date: Option<NaiveDate>,
+    },
+    /// List overlapping blocks, blocks without a break between them and
+    /// blocks ending after their todo is due, with ways to fix each
+    Conflicts {
+        #[arg(long)]
+        date: Option<NaiveDate>,
+        /// Minutes wanted between two blocks
+        #[arg(long, default_value = "5")]
+        buffer: u32,
+    },
+    /// Apply option N of a conflict, as `plan conflicts` numbers them
+    Resolve {
+        conflict: usize,
+        option: usize,
+        #[arg(long)]
+        date: Option<NaiveDate>,
+        #[arg(long, default_value = "5")]
+        buffer: u32,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum EditCommand {
+    /// Add a block
+    Add {
+        #[arg(value_parser = time::parse_time)]
+        start: NaiveTime,
+        #[arg(value_parser = time::parse_time)]
+        end: NaiveTime,
+        title: String,
+        /// Todo the block works on
+        #[arg(long)]
+        todo: Option<u64>,
+    },
+    /// Remove block number N
+    Remove { block: usize },
+    /// Move block number N to a new start time
+    Move {
+        block: usize,
+        #[arg(value_parser = time::parse_time)]
+        start: NaiveTime,
+    },
+    /// Change the start and end of block number N
+    Resize {
+        block: usize,
+        #[arg(value_parser = time::parse_time)]
+        start: NaiveTime,
+        #[arg(value_parser = time::parse_time)]
+        end: NaiveTime,
+    },
+    /// Rename block number N
+    Rename { block: usize, title: String },
+}
+
+/// Runs one command against the planner folder.
+pub fn run(cli: Cli) -> Result<()> {
+    let profiles = Profiles::new(&cli.home.unwrap_or_else(Store::default_dir));
+    let profile = cli
+        .profile
+        .or_else(|| env::var(PROFILE).ok().filter(|name| !name.is_empty()));
+    let dir = || profiles.resolve(profile.as_deref());
+    // Opened only by the commands that need it, as it fails while locked
+    let store = || Store::open(&dir()?);
+    match cli.command {
+        Command::Profile(command) => manage_profiles(&profiles, profile.as_deref(), command),
+        Command::Unlock => unlock(&dir()?),
+        Command::Lock => lock(&dir()?),
+        Command::Encryption(command) => encryption(&dir()?, &command),
+        Command::Daemon(DaemonCommand::Run) => run_daemon(store()?),
+        Command::Daemon(DaemonCommand::Control(command)) => control_daemon(&dir()?, command),
+        Command::Migrate(args) => migrate(&store()?, args),
+        Command::Backup { file } => backup(&store()?, &file),
+        Command::Restore {
+            file,
+            check,
+            yes,
+            other_profile,
+        } => restore(store()?, &file, check, yes, other_profile),
+        Command::Timer(TimerCommand::Wait) => wait_for_timers(&store()?, &TerminalNotifier),
+        Command::Pomodoro(PomodoroCommand::Watch) => watch_pomodoro(&store()?, &TerminalNotifier),
+        Command::Budget(BudgetCommand::Watch) => watch_budgets(&store()?, &TerminalNotifier),
+        Command::Dashboard => show_dashboard(&store()?),
+        Command::Serve(args) => serve(store()?, args),
+        Command::History(HistoryCommand::Verify) => verify_history(&store()?),
+        Command::Todo(command) => with_data(&store()?, |_, data, now| todo(data, command, now)),
+        Command::Project(command) => {
+            with_data(&store()?, |_, data, now| project(data, command, now))
+        }
+        Command::Routine(command) => {
+            with_data(&store()?, |_, data, now| routine(data, command, now))
+        }
+        Command::Plan(command) => with_data(&store()?, |store, data, _| plan(store, data, command)),
+        Command::Stopwatch(command) => {
+            with_data(&store()?, |_, data, now| stopwatch(data, command, now))
+        }
+        Command::Timer(TimerCommand::Data(command)) => {
+            with_data(&store()?, |_, data, now| timer(data, command, now))
+        }
+        Command::Pomodoro(PomodoroCommand::Data(command)) => {
+            with_data(&store()?, |store, data, now| {
+                pomodoro(store, data, command, now)
+            })
+        }
+        Command::Budget(BudgetCommand::Data(command)) => {
+            with_data(&store()?, |_, data, now| budget(data, command, now))
+        }
+        Command::Calendar(command) => {
+            with_data(&store()?, |_, data, now| calendar(data, command, now))
+        }
+        Command::Report(args) => with_data(&store()?, |store, data, _| {
+            report(store, data, args)?;
+            Ok(false)
+        }),
+        Command::History(HistoryCommand::Data(command)) => {
+            with_data(&store()?, |store, data, _| history(store, data, command))
+        }
+        Command::Sync(command) => with_data(&store()?, |store, data, _| sync(store, data, command)),
+        Command::Webhook(command) => with_data(&store()?, |store, _, now| {
+            webhook(store, command, now)?;
+            Ok(false)
+        }),
+        Command::Export { output } => with_data(&store()?, |_, data, now| {
+            let json = backup::export(data, now)?;
+            match output {
+                Some(path) => {
+                    fs::write(&path, json + "\n")?;
+                    println!("Wrote {}", path.display());
+                }
+                None => println!("{}", json),
+            }
+            Ok(false)
+        }),
+        Command::Import { file, yes } => {
+            with_data(&store()?, |_, data, _| import(data, &file, yes))
+        }
+    }
+}
+
+/// Loads the data, tells of the timers that ran out, Pomodoro phases that
+/// changed and budget thresholds passed meanwhile, then runs `command` on
+/// it. Saves when anything changed and posts the webhook events.
+fn with_data(
+    store: &Store,
+    command: impl FnOnce(&Store, &mut PlannerData, DateTime<Utc>) -> Result<bool>,
+) -> Result<()> {
+    let mut data = store.load()?;
+    let now = Utc::now();
+    let fired = data.fire_timers(now);
+    for timer in &fired {
+        TerminalNotifier.notify("Time is up", &describe_timer(timer, now));
+    }
+    let transitions = data.advance_pomodoro(now);
+    for transition in &transitions {
+        TerminalNotifier.notify("Pomodoro", &transition.to_string());
+    }
+    let config = store.config()?;
+    let alerts = data.fire_budget_alerts(&config.budgets.thresholds, now);
+    for alert in &alerts {
+        TerminalNotifier.notify("Budget", &alert.to_string());
+    }
+    let mut events: Vec<webhook::Event> = fired
+        .iter()
+        .map(|timer| webhook::Event::timer_expired(timer, now))
+        .collect();
+    if config
+        .webhooks
+        .iter()
+        .any(|hook| hook.wants(EventKind::TodoOverdue))
+    {
+        events.extend(Sender::new(store.dir()).overdue(&data, now)?);
+    }
+
+    let changed = command(store, &mut data, now)?;
+    if changed || !fired.is_empty() || !transitions.is_empty() || !alerts.is_empty() {
+        store.save(&data)?;
+    }
+    post_events(store, &events)
+}
+
+fn describe_todo(todo: &Todo) -> String {
+    let mut line = format!(
+        "#{} [{}] {}{}",
+        todo.id,
+        todo.priority,
+        if todo.done { "(done) " } else { "" },
+        todo.title
+    );
+    if let Some(due) = todo.due {
+        line.push_str(&format!("  due {}", time::format_local(due)));
+    }
+    if let Some(minutes) = todo.estimate_minutes {
+        line.push_str(&format!("  ~{}m", minutes));
+    }
+    if let Some(subject) = &todo.subject {
+        line.push_str(&format!("  #{}", subject));
+    }
+    line
+}
+
+/// Asks a yes/no question on the terminal. Without one (in a script or a
+/// pipe) the answer is yes, as nobody could answer.
+fn confirm(question: &str) -> Result<bool> {
+    if !io::stdin().is_terminal() {
+        return Ok(true);
+    }
+    print!("{} [Y/n] ", question);
+    io::stdout().flush()?;
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer)?;
+    Ok(matches!(
+        answer.trim().to_lowercase().as_str(),
+        "" | "y" | "yes"
+    ))
+}
+
+/// Asks before something that cannot be undone, like deleting data. Without
+/// a terminal it fails instead, so a script has to pass `--yes` to mean it.
+fn confirm_destructive(question: &str) -> Result<bool> {
+    if !io::stdin().is_terminal() {
+        return Err(PlannerError::Invalid(
+            "there is no terminal to confirm this, pass --yes to go ahead".to_string(),
+        ));
+    }
+    print!("{} [y/N] ", question);
+    io::stdout().flush()?;
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer)?;
+    Ok(matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"))
+}
+
+fn todo(data: &mut PlannerData, command: TodoCommand, now: DateTime<Utc>) -> Result<bool> {
+    match command {
+        TodoCommand::Add {
+            title,
+            priority,
+            due,
+            estimate,
+            subject,
+            project,
+            parent,
+        } => {
+            let mut new = NewTodo::new(&title, priority);
+            new.due = due;
+            new.estimate_minutes = estimate;
+            new.subject = subject;
+            new.project = project;
+            new.parent = parent;
+            let id = data.add_todo(new, now)?;
+            println!("Added {}", describe_todo(data.todo(id)?));
+            Ok(true)
+        }
+        TodoCommand::Quick { text, yes, dry_run } => {
+            let parsed = quick::parse(&text.join(" "), time::to_local(now))?;
+            let new = parsed.to_new_todo();
+            // Checked before asking, so a bad line is not confirmed for nothing
+            new.validate()?;
+            print!("{}", parsed);
+            if dry_run || !(yes || confirm("Add it?")?) {
+                println!("Nothing added.");
+                return Ok(false);
+            }
+            let id = data.add_todo(new, now)?;
+            println!("Added {}", describe_todo(data.todo(id)?));
+            Ok(true)
+        }
+        TodoCommand::List { all } => {
+            let mut todos: Vec<&Todo> = data.todos.iter().filter(|t| all || !t.done).collect();
+            todos.sort_by_key(|t| (t.done, std::cmp::Reverse(t.priority), t.due, t.id));
+            if todos.is_empty() {
+                println!("Nothing to do.");
+            }
+            for todo in todos {
+                println!("{}", describe_todo(todo));
+            }
+            Ok(false)
+        }
+        TodoCommand::Done { id, with_subtasks } => {
+            let open = data.open_subtasks(id)?;
+            // Without a terminal nobody can be asked, so the subtasks have to
+            // be asked for
+            let ask = || -> Result<bool> {
+                Ok(io::stdin().is_terminal()
+                    && confirm(&format!(
+                        "#{} has {} open subtasks. Complete them too?",
+                        id,
+                        open.len()
+                    ))?)
+            };
+            if !open.is_empty() && (with_subtasks || ask()?) {
+                let done = data.complete_todo_tree(id, now)?;
+                println!("Completed #{} and {} subtasks", id, done.len() - 1);
+            } else {
+                data.complete_todo(id, now)?;
+                println!("Completed #{}", id);
+            }
+            Ok(true)
+        }
+        TodoCommand::Block { id, by } => {
+            data.block_todo(id, by)?;
+            println!("#{} now waits for #{}", id, by);
+            Ok(true)
+        }
+        TodoCommand::Unblock { id, by } => {
+            data.unblock_todo(id, by)?;
+            println!("#{} no longer waits for #{}", id, by);
+            Ok(true)
+        }
+        TodoCommand::Parent { id, parent, .. } => {
+            data.set_parent(id, parent)?;
+            match parent {
+                Some(parent) => println!("#{} is now a subtask of #{}", id, parent),
+                None => println!("#{} is now a top level todo", id),
+            }
+            Ok(true)
+        }
+        TodoCommand::Move { id, project, .. } => {
+            data.move_todo(id, project)?;
+            match project {
+                Some(project) => println!("Moved #{} to {}", id, data.project(project)?.name),
+                None => println!("Took #{} out of its project", id),
+            }
+            Ok(true)
+        }
+        TodoCommand::Delete { id } => {
+            let todo = data.remove_todo(id)?;
+            println!("Deleted #{} {}", todo.id, todo.title);
+            Ok(true)
+        }
+        TodoCommand::Export {
+            output,
+            format,
+            project,
+        } => {
+            if let Some(project) = project {
+                data.project(project)?;
+            }
+            let format = format
+                .or_else(|| output.as_deref().and_then(TodoFormat::of))
+                .unwrap_or(TodoFormat::Txt);
+            let text = match format {
+                TodoFormat::Txt => todotxt::export(data, project),
+                TodoFormat::Md => markdown::export(data, project),
+            };
+            match output {
+                Some(path) => {
+                    fs::write(&path, text)?;
+                    println!("Wrote {}", path.display());
+                }
+                None => print!("{}", text),
+            }
+            Ok(false)
+        }
+        TodoCommand::Import {
+            file,
+            format,
+            project,
+        } => {
+            let format = format.or_else(|| TodoFormat::of(&file)).ok_or_else(|| {
+                PlannerError::Invalid(format!(
+                    "cannot tell the format of {}, pass --format txt or --format md",
+                    file.display()
+                ))
+            })?;
+            let text = fs::read_to_string(&file)?;
+            let merged = match format {
+                TodoFormat::Txt => todotxt::import(data, &text, project, now)?,
+                TodoFormat::Md => markdown::sync(data, &text, project, now)?.merged,
+            };
+            println!("Read {}: {}", file.display(), merged);
+            Ok(merged != Merged::default())
+        }
+        TodoCommand::Sync { file, project } => {
+            let text = if file.exists() {
+                fs::read_to_string(&file)?
+            } else {
+                String::new()
+            };
+            let synced = markdown::sync(data, &text, project, now)?;
+            if synced.text != text {
+                fs::write(&file, &synced.text)?;
+            }
+            println!(
+                "Synced {}: {}; {} line(s) written to the file",
+                file.display(),
+                synced.merged,
+                synced.written
+            );
+            Ok(synced.merged.added + synced.merged.updated + synced.merged.projects > 0)
+        }
+    }
+}
+
+fn project(data: &mut PlannerData, command: ProjectCommand, now: DateTime<Utc>) -> Result<bool> {
+    match command {
+        ProjectCommand::Add { name } => {
+            let id = data.add_project(&name, now)?;
+            println!("Added project #{}", id);
+            Ok(true)
+        }
+        ProjectCommand::List => {
+            if data.projects.is_empty() {
+                println!("No projects yet.");
+            }
+            for project in &data.projects {
+                let todos: Vec<&Todo> = data
+                    .todos
+                    .iter()
+                    .filter(|todo| todo.project == Some(project.id))
+                    .collect();
+                let done = todos.iter().filter(|todo| todo.done).count();
+                println!(
+                    "#{} {}: {} of {} done",
+                    project.id,
+                    project.name,
+                    done,
+                    todos.len()
+                );
+            }
+            Ok(false)
+        }
+        ProjectCommand::Show { id } => {
+            let project = data.project(id)?;
+            let graph = Graph::new(&data.todos);
+            println!("#{} {}", project.id, project.name);
+            let top: Vec<&Todo> = data
+                .todos
+                .iter()
+                .filter(|todo| todo.project == Some(id) && todo.parent.is_none())
+                .collect();
+            if top.is_empty() {
+                println!("No todos yet.");
+            }
+            for todo in top {
+                show_tree(data, &graph, todo, 0);
+            }
+            Ok(false)
+        }
+        ProjectCommand::Next { id } => {
+            if let Some(id) = id {
+                println!("{}", data.project(id)?.name);
+            }
+            let graph = Graph::new(&data.todos);
+            let order = graph.order()?;
+            let (ready, waiting): (Vec<u64>, Vec<u64>) = order
+                .into_iter()
+                .filter(|todo| id.is_none() || data.todo(*todo).is_ok_and(|t| t.project == id))
+                .partition(|todo| graph.is_ready(*todo));
+            if ready.is_empty() && waiting.is_empty() {
+                println!("Nothing to do.");
+                return Ok(false);
+            }
+            println!("Can be done now:");
+            for todo in &ready {
+                println!("  {}", describe_todo(data.todo(*todo)?));
+            }
+            if !waiting.is_empty() {
+                println!("Waiting, in the order they can be done:");
+            }
+            for todo in &waiting {
+                println!(
+                    "  {}  waits for {}",
+                    describe_todo(data.todo(*todo)?),
+                    list_ids(&graph.waiting_for(*todo))
+                );
+            }
+            Ok(false)
+        }
+        ProjectCommand::Critical { id } => {
+            let project = data.project(id)?;
+            let path = Graph::new(&data.todos).critical_path(|todo| todo.project == Some(id))?;
+            if path.todos.is_empty() {
+                println!("Nothing left in {}.", project.name);
+                return Ok(false);
+            }
+            println!(
+                "Critical path of {}: {}",
+                project.name,
+                format_duration(chrono::Duration::minutes(path.minutes.into()))
+            );
+            for todo in &path.todos {
+                println!("  {}", describe_todo(data.todo(*todo)?));
+            }
+            if !path.unestimated.is_empty() {
+                println!(
+                    "Without an estimate, counted as no time: {}",
+                    list_ids(&path.unestimated)
+                );
+            }
+            Ok(false)
+        }
+        ProjectCommand::Delete { id } => {
+            let project = data.remove_project(id)?;
+            println!("Deleted project #{} {}", project.id, project.name);
+            Ok(true)
+        }
+    }
+}
+
+/// Prints a todo, what it still waits for, and its subtasks below it.
+fn show_tree(data: &PlannerData, graph: &Graph, todo: &Todo, depth: usize) {
+    let mut line = format!("{}{}", "  ".repeat(depth), describe_todo(todo));
+    let blockers: Vec<u64> = graph
+        .waiting_for(todo.id)
+        .into_iter()
+        .filter(|id| todo.blocked_by.contains(id))
+        .collect();
+    if !todo.done && !blockers.is_empty() {
+        line.push_str(&format!("  waits for {}", list_ids(&blockers)));
+    }
+    println!("{}", line);
+    for subtask in data.todos.iter().filter(|t| t.parent == Some(todo.id)) {
+        show_tree(data, graph, subtask, depth + 1);
+    }
+}
+
+/// A percentage, or a dash when nothing was scheduled.
+fn adherence(percent: Option<f64>) -> String {
+    percent.map_or_else(|| "-".to_string(), |percent| format!("{:.0}%", percent))
+}
+
+fn routine(data: &mut PlannerData, command: RoutineCommand, now: DateTime<Utc>) -> Result<bool> {
+    match command {
+        RoutineCommand::Add {
+            name,
+            at,
+            duration,
+            days,
+        } => {
+            let id = data.add_routine(&name, at, duration, days, now)?;
+            println!("Added routine #{}", id);
+            Ok(true)
+        }
+        RoutineCommand::List => {
+            if data.routines.is_empty() {
+                println!("No routines yet.");
+            }
+            let zone = time::zone();
+            for routine in &data.routines {
+                let mut line = format!(
+                    "#{} {} {}-{} {}",
+                    routine.id,
+                    routine.name,
+                    routine.start.format("%H:%M"),
+                    routine.end().format("%H:%M"),
+                    routine.frequency
+                );
+                if let Some(next) = routine.next_start(Utc::now(), &zone) {
+                    line.push_str(&format!("  next {}", time::format_local(next)));
+                }
+                println!("{}", line);
+            }
+            Ok(false)
+        }
+        RoutineCommand::Done {
+            id,
+            date,
+            partial,
+            skip,
+        } => {
+            let date = date.unwrap_or_else(time::today);
+            let status = if partial {
+                CheckStatus::Partial
+            } else if skip {
+                CheckStatus::Skipped
+            } else {
+                CheckStatus::Done
+            };
+            data.mark_routine(id, date, status)?;
+            match status {
+                CheckStatus::Done => println!("Checked routine #{} for {}", id, date),
+                CheckStatus::Partial => {
+                    println!("Checked routine #{} as partly done for {}", id, date)
+                }
+                CheckStatus::Skipped => println!("Skipped routine #{} on {}", id, date),
+            }
+            Ok(true)
+        }
+        RoutineCommand::Streaks => {
+            if data.routines.is_empty() {
+                println!("No routines yet.");
+            }
+            let today = time::today();
+            for routine in &data.routines {
+                let habit = Habit::new(routine, &data.routine_checks, today);
+                let streaks = habit.streaks();
+                let weeks = habit.weekly(4);
+                let month = habit.adherence(weeks[0].0, today);
+                println!(
+                    "#{} {}: current streak {}, longest {}, this week {}, last 4 weeks {}",
+                    routine.id,
+                    routine.name,
+                    streaks.current,
+                    streaks.longest,
+                    adherence(weeks[3].1),
+                    adherence(month)
+                );
+            }
+            Ok(false)
+        }
+        RoutineCommand::Heatmap { id, weeks } => {
+            let routine = data
+                .routines
+                .iter()
+                .find(|routine| routine.id == id)
+                .ok_or_else(|| PlannerError::NotFound(format!("routine #{}", id)))?;
+            let habit = Habit::new(routine, &data.routine_checks, time::today());
+            println!("#{} {} ({})", routine.id, routine.name, routine.frequency);
+            println!("{}", habit.heatmap(weeks));
+            Ok(false)
+        }
+        RoutineCommand::Delete { id } => {
+            let routine = data.remove_routine(id)?;
+            println!("Deleted routine #{} {}", routine.id, routine.name);
+            Ok(true)
+        }
+    }
+}
+
+fn plan(store: &Store, data: &mut PlannerData, command: PlanCommand) -> Result<bool> {
+    match command {
+        PlanCommand::Generate { date, backend } => {
+            let date = date.unwrap_or_else(time::today);
+            let request = PlanRequest::new(date, &data.todos, &data.routines).with_busy(&data.busy);
+            let plan = match backend {
+                Backend::Local => LocalScheduler::default().generate(&request)?,
+                Backend::Llm => match store.config()?.llm_planner()?.generate(&request) {
+                    Err(PlannerError::Backend(message)) => {
+                        eprintln!("The model's plan was refused: {}", message);
+                        eprintln!("Planning with the local scheduler instead.");
+                        LocalScheduler::default().generate(&request)?
+                    }
+                    plan => plan?,
+                },
+            };
+            print!("{}", plan);
+            post_events(store, &[webhook::Event::plan_generated(&plan, Utc::now())])?;
+            data.set_plan(plan);
+            Ok(true)
+        }
+        PlanCommand::Show { date } => {
+            let date = date.unwrap_or_else(time::today);
+            match data.plan(date) {
+                Some(plan) => print!("{}", plan),
+                None => println!("No plan for {}, run `plan generate` first.", date),
+            }
+            Ok(false)
+        }
+        PlanCommand::Edit { date, edit } => {
+            let date = date.unwrap_or_else(time::today);
+            if let EditCommand::Add { todo: Some(id), .. } = &edit {
+                data.todo(*id)?;
+            }
+            let plan = data.plan_mut(date)?;
+            match edit {
+                EditCommand::Add {
+                    start,
+                    end,
+                    title,
+                    todo,
+                } => {
+                    let kind = if todo.is_some() {
+                        BlockKind::Task
+                    } else {
+                        BlockKind::Custom
+                    };
+                    let mut block = PlanBlock::new(start, end, &title, kind)?;
+                    block.todo_id = todo;
+                    plan.add_block(block);
+                }
+                EditCommand::Remove { block } => {
+                    plan.remove_block(block)?;
+                }
+                EditCommand::Move { block, start } => plan.move_block(block, start)?,
+                EditCommand::Resize { block, start, end } => {
+                    plan.resize_block(block, start, end)?
+                }
+                EditCommand::Rename { block, title } => plan.rename_block(block, &title)?,
+            }
+            print!("{}", plan);
+            let plan = data.plan(date).unwrap();
+            let conflicts = ConflictChecker::default().check(plan, data).len();
+            if conflicts > 0 {
+                println!(
+                    "{} conflict(s), run `plan conflicts` to see them",
+                    conflicts
+                );
+            }
+            Ok(true)
+        }
+        PlanCommand::Delete { date } => {
+            let date = date.unwrap_or_else(time::today);
+            data.remove_plan(date)?;
+            println!("Deleted the plan for {}", date);
+            Ok(true)
+        }
+        PlanCommand::Conflicts { date, buffer } => {
+            let date = date.unwrap_or_else(time::today);
+            let plan = data
+                .plan(date)
+                .ok_or_else(|| PlannerError::NotFound(format!("plan for {}", date)))?;
+            let checker = ConflictChecker {
+                buffer_minutes: buffer,
+                ..ConflictChecker::default()
+            };
+            let conflicts = checker.check(plan, data);
+            if conflicts.is_empty() {
+                println!("No conflicts on {}.", date);
+            }
+            for (i, conflict) in conflicts.iter().enumerate() {
+                println!("{}. {}", i + 1, conflict);
+                let options = checker.resolutions(plan, data, conflict);
+                if options.is_empty() {
+                    println!("   (nothing can be moved, change it by hand)");
+                }
+                for (j, option) in options.iter().enumerate() {
+                    println!("   {}) {}", j + 1, option);
+                }
+            }
+            Ok(false)
+        }
+        PlanCommand::Resolve {
+            conflict,
+            option,
+            date,
+            buffer,
+        } => {
+            let date = date.unwrap_or_else(time::today);
+            let checker = ConflictChecker {
+                buffer_minutes: buffer,
+                ..ConflictChecker::default()
+            };
+            let resolution = data.resolve_conflict(date, &checker, conflict, option)?;
+            println!("Applied: {}", resolution);
+            let plan = data.plan(date).unwrap();
+            print!("{}", plan);
+            let left = checker.check(plan, data).len();
+            if left > 0 {
+                println!("{} conflict(s) left", left);
+            }
+            Ok(true)
+        }
+    }
+}
+
+fn stopwatch(
+    data: &mut PlannerData,
+    command: StopwatchCommand,
+    now: DateTime<Utc>,
+) -> Result<bool> {
+    match command {
+        StopwatchCommand::Start { subject } => {
+            data.start_session(&subject, now)?;
+            println!("Stopwatch started on {}", subject.trim());
+            if let Some(progress) = week_budget(data, &subject, now) {
+                println!("This week {}", progress);
+            }
+            Ok(true)
+        }
+        StopwatchCommand::Stop => {
+            let session = data.stop_session(now)?;
+            println!(
+                "Recorded {} on {}",
+                format_duration(session.elapsed(now)),
+                session.subject
+            );
+            Ok(true)
+        }
+        StopwatchCommand::Status => {
+            match data.running_session() {
+                Some(session) => {
+                    println!(
+                        "Running on {} for {} (since {})",
+                        session.subject,
+                        format_duration(session.elapsed(now)),
+                        time::format_local(session.start)
+                    );
+                    if let Some(progress) = week_budget(data, &session.subject, now) {
+                        println!("This week {}", progress);
+                    }
+                }
+                None => println!("The stopwatch is not running."),
+            }
+            Ok(false)
+        }
+        StopwatchCommand::Reset => {
+            let session = data.reset_session()?;
+            println!("Discarded the session on {}", session.subject);
+            Ok(true)
+        }
+        StopwatchCommand::List => {
+            if data.sessions.is_empty() {
+                println!("No sessions yet.");
+            }
+            for session in &data.sessions {
+                println!(
+                    "#{} {}  {}  {}",
+                    session.id,
+                    time::format_local(session.start),
+                    format_duration(session.elapsed(now)),
+                    session.subject
+                );
+            }
+            Ok(false)
+        }
+    }
+}
+
+/// Reads a length like "10h", "90m" or "1h30m" as minutes.
+fn parse_length(text: &str) -> Result<u32> {
+    match quick::minutes(text) {
+        Ok(minutes) if minutes > 0 => Ok(minutes),
+        _ => Err(PlannerError::Invalid(format!(
+            "'{}' is not a length like 10h or 1h30m",
+            text
+        ))),
+    }
+}
+
+fn set_budget(
+    data: &mut PlannerData,
+    subject: &str,
+    kind: BudgetKind,
+    minutes: u32,
+    today: NaiveDate,
+) -> Result<bool> {
+    let length = |minutes: u32| format_duration(chrono::Duration::minutes(minutes as i64));
+    let replaced = data.budget(subject).ok().cloned();
+    data.set_budget(subject, kind, minutes, today)?;
+    match replaced {
+        Some(old) => println!(
+            "{} has a {} {} a week from today on, instead of a {} {}",
+            subject.trim(),
+            length(minutes),
+            kind,
+            length(old.minutes),
+            old.kind
+        ),
+        None => println!(
+            "{} has a {} {} a week",
+            subject.trim(),
+            length(minutes),
+            kind
+        ),
+    }
+    Ok(true)
+}
+
+/// How the week so far went for `subject`, counting the running session.
+fn week_budget(data: &PlannerData, subject: &str, now: DateTime<Utc>) -> Option<budget::Progress> {
+    let week = Period::Week.range(time::to_local(now).date());
+    budget::progress(data, week, Some(now))
+        .into_iter()
+        .find(|progress| progress.subject.to_lowercase() == subject.trim().to_lowercase())
+}
+
+fn budget(data: &mut PlannerData, command: BudgetDataCommand, now: DateTime<Utc>) -> Result<bool> {
+    let today = time::to_local(now).date();
+    match command {
+        BudgetDataCommand::Target { length, subject } => {
+            set_budget(data, &subject, BudgetKind::Target, length, today)
+        }
+        BudgetDataCommand::Cap { length, subject } => {
+            set_budget(data, &subject, BudgetKind::Cap, length, today)
+        }
+        BudgetDataCommand::Remove { subject } => {
+            let removed = data.remove_budget(&subject, today)?;
+            println!("{} has no budget from today on", removed.subject);
+            Ok(true)
+        }
+        BudgetDataCommand::List { all } => {
+            if all {
+                if data.budgets.is_empty() {
+                    println!("No budgets yet.");
+                }
+                for budget in &data.budgets {
+                    println!(
+                        "#{} {} {} {} a week, from {}{}",
+                        budget.id,
+                        budget.subject,
+                        format_duration(chrono::Duration::minutes(budget.minutes as i64)),
+                        budget.kind,
+                        budget.from,
+                        budget
+                            .until
+                            .map(|until| format!(" until {}", until))
+                            .unwrap_or_default()
+                    );
+                }
+                return Ok(false);
+            }
+            let week = Period::Week.range(today);
+            let progress = budget::progress(data, week, Some(now));
+            if progress.is_empty() {
+                println!("No budgets this week.");
+                return Ok(false);
+            }
+            let mut rows = vec![vec![
+                format!("Week of {}", week.start),
+                "Spent".to_string(),
+                "Of".to_string(),
+                "Used".to_string(),
+                String::new(),
+            ]];
+            for progress in progress {
+                rows.push(vec![
+                    format!("{} ({})", progress.subject, progress.kind),
+                    report::text::minutes(progress.spent_minutes),
+                    report::text::minutes(progress.budget_minutes),
+                    report::text::percent(progress.percent()),
+                    progress.outcome(),
+                ]);
+            }
+            print!("{}", report::text::table(&rows));
+            Ok(false)
+        }
+    }
+}
+
+fn describe_timer(timer: &Timer, now: DateTime<Utc>) -> String {
+    let mut line = format!("#{} {}", timer.id, timer.label);
+    if timer.fired {
+        line.push_str(&format!("  ran out {}", time::format_local(timer.due)));
+    } else {
+        line.push_str(&format!(
+            "  at {} (in {})",
+            time::format_local(timer.due),
+            format_duration(timer.remaining(now))
+        ));
+    }
+    if let Some(daily) = timer.daily {
+        line.push_str(&format!("  daily at {}", daily.format("%H:%M")));
+    } else if let Some(set_in) = timer.zone.as_deref() {
+        // Set elsewhere: show the time it was set for there too
+        let zone = time::zone();
+        if set_in != zone.name() {
+            if let Ok(there) = Zone::named(set_in) {
+                line.push_str(&format!(
+                    "  ({} {})",
+                    there.to_local(timer.due).format("%H:%M"),
+                    there
+                ));
+            }
+        }
+    }
+    if let Some(todo_id) = timer.todo_id {
+        line.push_str(&format!("  for todo #{}", todo_id));
+    }
+    line
+}
+
+fn timer(data: &mut PlannerData, command: TimerDataCommand, now: DateTime<Utc>) -> Result<bool> {
+    match command {
+        TimerDataCommand::Set {
+            label,
+            minutes,
+            at,
+            daily,
+            todo,
+        } => {
+            let id = match (minutes, at, daily) {
+                (Some(minutes), _, _) => {
+                    let due = now + chrono::Duration::minutes(minutes as i64);
+                    data.add_timer(&label, due, todo, now)?
+                }
+                (None, Some(at), _) => data.add_timer(&label, at, todo, now)?,
+                (None, None, Some(daily)) => data.add_daily_timer(&label, daily, todo, now)?,
+                (None, None, None) => unreachable!("clap requires --in, --at or --daily"),
+            };
+            let timer = data.timers.iter().find(|timer| timer.id == id).unwrap();
+            println!("Set timer {}", describe_timer(timer, now));
+            Ok(true)
+        }
+        TimerDataCommand::List { all } => {
+            let timers: Vec<&Timer> = if all {
+                data.timers.iter().collect()
+            } else {
+                data.pending_timers()
+            };
+            if timers.is_empty() {
+                println!("No timers running.");
+            }
+            for timer in timers {
+                println!("{}", describe_timer(timer, now));
+            }
+            Ok(false)
+        }
+        TimerDataCommand::Cancel { id } => {
+            let timer = data.cancel_timer(id)?;
+            println!("Cancelled timer #{} {}", timer.id, timer.label);
+            Ok(true)
+        }
+    }
+}
+
+fn describe_run(run: &PomodoroRun, now: DateTime<Utc>) -> String {
+    let mut line = format!(
+        "{} on {}, {} left (until {})",
+        run.phase,
+        run.subject,
+        format_duration(run.remaining(now)),
+        time::format_local(run.phase_end())
+    );
+    line.push_str(&match run.cycles {
+        Some(cycles) => format!("  {}/{} done", run.completed, cycles),
+        None => format!("  {} done", run.completed),
+    });
+    line
+}
+
+fn describe_pomodoro(pomodoro: &Pomodoro) -> String {
+    format!(
+        "#{} {}  {}  {}{}",
+        pomodoro.id,
+        time::format_local(pomodoro.start),
+        format_duration(pomodoro.end - pomodoro.start),
+        pomodoro.subject,
+        if pomodoro.interrupted {
+            "  (interrupted)"
+        } else {
+            ""
+        }
+    )
+}
+
+fn pomodoro(
+    store: &Store,
+    data: &mut PlannerData,
+    command: PomodoroDataCommand,
+    now: DateTime<Utc>,
+) -> Result<bool> {
+    match command {
+        PomodoroDataCommand::Start {
+            subject,
+            work,
+            short_break,
+            long_break,
+            long_every,
+            cycles,
+        } => {
+            let mut settings = store.config()?.pomodoro;
+            settings.work_minutes = work.unwrap_or(settings.work_minutes);
+            settings.short_break_minutes = short_break.unwrap_or(settings.short_break_minutes);
+            settings.long_break_minutes = long_break.unwrap_or(settings.long_break_minutes);
+            settings.long_break_every = long_every.unwrap_or(settings.long_break_every);
+            data.start_pomodoro(&subject, settings, cycles, now)?;
+            println!(
+                "Pomodoro started on {}: {}m of work until {}",
+                subject.trim(),
+                settings.work_minutes,
+                time::format_local(data.pomodoro.as_ref().unwrap().phase_end())
+            );
+            println!("Run `pomodoro watch` to be told when each phase ends.");
+            Ok(true)
+        }
+        PomodoroDataCommand::Status => {
+            match &data.pomodoro {
+                Some(run) => println!("{}", describe_run(run, now)),
+                None => println!("No pomodoro is running."),
+            }
+            Ok(false)
+        }
+        PomodoroDataCommand::Stop => {
+            let run = data.stop_pomodoro(now)?;
+            match run.phase {
+                Phase::Work => println!(
+                    "Stopped {} after {}, the pomodoro is kept as interrupted",
+                    run.subject,
+                    format_duration(now - run.phase_start)
+                ),
+                _ => println!("Stopped {} during the {}", run.subject, run.phase),
+            }
+            println!("{} pomodoros done in this run", run.completed);
+            Ok(true)
+        }
+        PomodoroDataCommand::List { date } => {
+            let pomodoros: Vec<&Pomodoro> = data
+                .pomodoros
+                .iter()
+                .filter(|p| date.is_none_or(|date| time::to_local(p.start).date() == date))
+                .collect();
+            if pomodoros.is_empty() {
+                println!("No pomodoros yet.");
+            }
+            for pomodoro in pomodoros {
+                println!("{}", describe_pomodoro(pomodoro));
+            }
+            Ok(false)
+        }
+    }
+}
+
+/// Checks that replaying the history gives the saved data. Runs before the
+/// data is loaded, since loading fails when it does not.
+fn verify_history(store: &Store) -> Result<()> {
+    let events = store.history().events()?;
+    if store.history().current()? != store.storage().load()? {
+        return Err(PlannerError::Storage(
+            "replaying the history does not give the saved data, it was damaged or \
+             the data was changed elsewhere since it was last loaded here"
+                .to_string(),
+        ));
+    }
+    println!(
+        "The history matches the saved data ({} events).",
+        events.len()
+    );
+    Ok(())
+}
+
+fn history(store: &Store, data: &mut PlannerData, command: HistoryDataCommand) -> Result<bool> {
+    match command {
+        HistoryDataCommand::List {
+            limit,
+            kind,
+            id,
+            since,
+        } => {
+            let id = id.map(serde_json::Value::from);
+            let mut lines = Vec::new();
+            for event in store.history().events()? {
+                if since.is_some_and(|since| event.at < since) {
+                    continue;
+                }
+                for change in &event.changes {
+                    let wanted = kind.is_none_or(|kind| change.kind() == kind)
+                        && (id.is_none() || change.key() == id);
+                    if wanted {
+                        lines.push(describe_event(&event, &change.to_string()));
+                    }
+                }
+            }
+            if lines.is_empty() {
+                println!("No changes recorded.");
+            }
+            for line in &lines[lines.len().saturating_sub(limit)..] {
+                println!("{}", line);
+            }
+            Ok(false)
+        }
+        HistoryDataCommand::At { when, all, json } => {
+            let then = store.history().state_at(when)?;
+            if json {
+                println!("{}", serde_json::to_string_pretty(&then)?);
+                return Ok(false);
+            }
+            println!("Todos at {}:", time::format_local(when));
+            let mut todos: Vec<&Todo> = then.todos.iter().filter(|t| all || !t.done).collect();
+            todos.sort_by_key(|t| (t.done, std::cmp::Reverse(t.priority), t.due, t.id));
+            if todos.is_empty() {
+                println!("Nothing to do.");
+            }
+            for todo in todos {
+                println!("{}", describe_todo(todo));
+            }
+            Ok(false)
+        }
+        HistoryDataCommand::Restore { when, yes } => {
+            let mut then = store.history().state_at(when)?;
+            if then == *data {
+                println!("Nothing changed since {}.", time::format_local(when));
+                return Ok(false);
+            }
+            let question = format!(
+                "Go back to how everything was at {}?",
+                time::format_local(when)
+            );
+            if !yes && !confirm_destructive(&question)? {
+                println!("Nothing restored.");
+                return Ok(false);
+            }
+            // Ids handed out since then are not given out again
+            then.next_id = then.next_id.max(data.next_id);
+            *data = then;
+            println!(
+                "Restored the planner as it was at {}",
+                time::format_local(when)
+            );
+            Ok(true)
+        }
+    }
+}
+
+fn sync(store: &Store, data: &mut PlannerData, command: SyncCommand) -> Result<bool> {
+    let before = data.clone();
+    let mut state = SyncState::load(store.dir())?;
+    state.capture(data)?;
+    match command {
+        SyncCommand::Status => {
+            println!("Replica:  {}", state.replica);
+            println!("Clock:    {}", state.clock);
+            println!("Changes:  {}", state.log.len());
+            for (peer, seen) in &state.peers {
+                let missing = state.delta_for(seen).ops.len();
+                println!("Peer {}: {} changes it may not have yet", peer, missing);
+            }
+        }
+        SyncCommand::Export { file, peer } => {
+            let delta = state.delta_for_peer(peer.as_deref());
+            fs::write(&file, serde_json::to_string_pretty(&delta)?)?;
+            println!("Wrote {} changes to {}", delta.ops.len(), file.display());
+        }
+        SyncCommand::Import { file } => {
+            let delta: Delta = serde_json::from_str(&fs::read_to_string(&file)?)?;
+            let received = state.receive(&delta)?;
+            state.apply_to(data)?;
+            println!("Took in {} new changes from {}", received, delta.from);
+        }
+        SyncCommand::With { url, token } => {
+            let token = api_token(store, token)?.ok_or_else(|| {
+                PlannerError::Invalid(
+                    "set SMART_PLANNER_API_TOKEN or `api_token` in config.json".to_string(),
+                )
+            })?;
+            let peer = state.remotes.get(&url).cloned();
+            let delta = state.delta_for_peer(peer.as_deref());
+            let answer = crate::sync::exchange(&url, &token, &delta)?;
+            let received = state.receive(&answer)?;
+            state.remotes.insert(url, answer.from.clone());
+            state.apply_to(data)?;
+            println!(
+                "Sent {} changes to {}, took in {} new ones",
+                delta.ops.len(),
+                answer.from,
+                received
+            );
+        }
+    }
+    state.save(store.dir())?;
+    Ok(*data != before)
+}
+
+fn describe_event(event: &Event, change: &str) -> String {
+    format!(
+        "#{:<4} {}  {:<12} {}",
+        event.seq,
+        time::format_local(event.at),
+        event.actor,
+        change
+    )
+}
+
+fn describe_busy(event: &BusyEvent) -> String {
+    match &event.time {
+        BusyTime::AllDay { first, last } if first == last => {
+            format!("{} all day  {}", first, event.summary)
+        }
+        BusyTime::AllDay { first, last } => {
+            format!("{} .. {} all day  {}", first, last, event.summary)
+        }
+        BusyTime::Timed { start, end } => format!(
+            "{} - {}  {}",
+            time::format_local(*start),
+            time::format_local(*end),
+            event.summary
+        ),
+    }
+}
+
+fn calendar(data: &mut PlannerData, command: CalendarCommand, now: DateTime<Utc>) -> Result<bool> {
+    match command {
+        CalendarCommand::Export { output, only } => {
+            let options = if only.is_empty() {
+                ExportOptions::default()
+            } else {
+                ExportOptions {
+                    plans: only.contains(&ExportKind::Plans),
+                    routines: only.contains(&ExportKind::Routines),
+                    todos: only.contains(&ExportKind::Todos),
+                    timers: only.contains(&ExportKind::Timers),
+                }
+            };
+            let text = ical::export(data, options, now);
+            match output {
+                Some(path) => {
+                    fs::write(&path, text)?;
+                    println!("Wrote {}", path.display());
+                }
+                None => print!("{}", text),
+            }
+            Ok(false)
+        }
+        CalendarCommand::Import { file, days } => {
+            let calendar = ical::parse(&fs::read_to_string(&file)?)?;
+            let today = time::today();
+            let until = today + chrono::Duration::days(days as i64);
+            let imported = ical::busy_events(&calendar, today, until)?;
+            for reason in &imported.skipped {
+                eprintln!("Skipped {}", reason);
+            }
+            println!(
+                "Imported {} busy time(s) from {} up to {}",
+                imported.events.len(),
+                file.display(),
+                until
+            );
+            data.import_busy(imported.events);
+            Ok(true)
+        }
+        CalendarCommand::List { date } => {
+            let mut events: Vec<&BusyEvent> = match date {
+                Some(date) => data
+                    .busy
+                    .iter()
+                    .filter(|event| event.slot_on(date).is_some())
+                    .collect(),
+                None => data
+                    .busy
+                    .iter()
+                    .filter(|event| !event.ends_before(time::today()))
+                    .collect(),
+            };
+            events.sort_by_key(|event| match event.time {
+                BusyTime::AllDay { first, .. } => time::from_local(first.and_time(NaiveTime::MIN)),
+                BusyTime::Timed { start, .. } => start,
+            });
+            if events.is_empty() {
+                println!("No busy times.");
+            }
+            for event in events {
+                println!("{}", describe_busy(event));
+            }
+            Ok(false)
+        }
+        CalendarCommand::Clear => {
+            let count = data.clear_busy();
+            println!("Forgot {} busy time(s)", count);
+            Ok(count > 0)
+        }
+    }
+}
+
+/// Sleeps until the next timer runs out, notifies and saves, until none are
+/// left. The data is read again every second so timers set or cancelled from
+/// another terminal are noticed.
+fn wait_for_timers(store: &Store, notifier: &dyn Notifier) -> Result<()> {
+    loop {
+        let mut data = store.load()?;
+        let now = Utc::now();
+        let fired = data.fire_timers(now);
+        if !fired.is_empty() {
+            store.save(&data)?;
+        }
+        for timer in &fired {
+            notifier.notify("Time is up", &describe_timer(timer, now));
+        }
+        let events: Vec<webhook::Event> = fired
+            .iter()
+            .map(|timer| webhook::Event::timer_expired(timer, now))
+            .collect();
+        post_events(store, &events)?;
+        let Some(next) = data.pending_timers().first().map(|timer| timer.due) else {
+            println!("No timers left.");
+            return Ok(());
+        };
+        let wait = (next - now).to_std().unwrap_or_default();
+        thread::sleep(wait.min(Duration::from_secs(1)));
+    }
+}
+
+/// Runs the reminder daemon in the foreground until it is stopped.
+fn run_daemon(store: Store) -> Result<()> {
+    let dir = store.dir().to_path_buf();
+    let (calls, incoming) = mpsc::channel();
+    #[cfg(unix)]
+    let _listener = {
+        fs::create_dir_all(dir.join(daemon::DIR))?;
+        daemon::socket::listen(&daemon::socket_path(&dir), calls.clone())?
+    };
+    println!(
+        "Reminding of timers and due todos in {}. `daemon stop` or Ctrl-C stops it.",
+        dir.display()
+    );
+    let daemon = Daemon::new(store, Arc::new(SystemClock), Box::new(TerminalNotifier));
+    let result = daemon.run(incoming);
+    drop(calls);
+    result
+}
+
+/// Sends a request to the daemon running on `dir` and shows its answer.
+#[cfg(unix)]
+fn control_daemon(dir: &Path, command: DaemonControlCommand) -> Result<()> {
+    let request = match command {
+        DaemonControlCommand::Status => Request::Status,
+        DaemonControlCommand::Reload => Request::Reload,
+        DaemonControlCommand::Stop => Request::Stop,
+    };
+    let status = match daemon::socket::send(&daemon::socket_path(dir), request) {
+        Err(PlannerError::NotFound(_)) => {
+            println!("No daemon is running, start one with `daemon run`.");
+            return Ok(());
+        }
+        other => other?,
+    };
+    if status.stopping {
+        println!("Stopped the daemon (pid {}).", status.pid);
+        return Ok(());
+    }
+    if request == Request::Reload {
+        println!("Reloaded the data and config.");
+    }
+    println!(
+        "Running since {} (pid {}), {} notification(s) sent.",
+        time::format_local(status.started_at),
+        status.pid,
+        status.notified
+    );
+    println!(
+        "Waiting on {} timer(s) and {} due reminder(s).",
+        status.timers, status.reminders
+    );
+    if let Some(next) = &status.next {
+        println!(
+            "Next at {}: {}, {}",
+            time::format_local(next.at),
+            next.title,
+            next.message
+        );
+    }
+    if let Some(error) = &status.error {
+        println!("Last error: {}", error);
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn control_daemon(_dir: &Path, _command: DaemonControlCommand) -> Result<()> {
+    Err(PlannerError::Invalid(
+        "talking to a running daemon needs Unix sockets, stop it with Ctrl-C".to_string(),
+    ))
+}
+
+/// Stays open while a Pomodoro run goes on and notifies at every change of
+/// phase. Like `timer wait`, it reads the data every second, so stopping the
+/// run from another terminal ends the watch.
+fn watch_pomodoro(store: &Store, notifier: &dyn Notifier) -> Result<()> {
+    loop {
+        let mut data = store.load()?;
+        let now = Utc::now();
+        let transitions = data.advance_pomodoro(now);
+        if !transitions.is_empty() {
+            store.save(&data)?;
+        }
+        for transition in &transitions {
+            notifier.notify("Pomodoro", &transition.to_string());
+        }
+        let Some(next) = data.pomodoro.as_ref().map(|run| run.phase_end()) else {
+            println!("No pomodoro running.");
+            return Ok(());
+        };
+        let wait = (next - now).to_std().unwrap_or_default();
+        thread::sleep(wait.min(Duration::from_secs(1)));
+    }
+}
+
+/// Stays open while the stopwatch runs and notifies when the time spent
+/// this week goes past a budget threshold. Like `timer wait`, it reads the
+/// data every second, so stopping the stopwatch elsewhere ends the watch.
+fn watch_budgets(store: &Store, notifier: &dyn Notifier) -> Result<()> {
+    let thresholds = store.config()?.budgets.thresholds;
+    loop {
+        let mut data = store.load()?;
+        let now = Utc::now();
+        let alerts = data.fire_budget_alerts(&thresholds, now);
+        if !alerts.is_empty() {
+            store.save(&data)?;
+        }
+        for alert in &alerts {
+            notifier.notify("Budget", &alert.to_string());
+        }
+        if data.running_session().is_none() {
+            println!("The stopwatch is not running.");
+            return Ok(());
+        }
+        thread::sleep(Duration::from_secs(1));
+    }
+}
+
+/// The passphrase from `variable`, or asked for on the terminal without
+/// showing it.
+fn passphrase(variable: &str, prompt: &str) -> Result<String> {
+    if let Ok(passphrase) = env::var(variable) {
+        return Ok(passphrase);
+    }
+    if !io::stdin().is_terminal() {
+        return Err(PlannerError::Invalid(format!(
+            "there is no terminal to ask for the passphrase, set {}",
+            variable
+        )));
+    }
+    Ok(rpassword::prompt_password(prompt)?)
+}
+
+/// A passphrase to encrypt with, asked for twice so a typo cannot lock the
+/// user out.
+fn new_passphrase(variable: &str) -> Result<String> {
+    let passphrase = passphrase(variable, "New passphrase: ")?;
+    vault::check_new_passphrase(&passphrase)?;
+    if env::var(variable).is_err() && passphrase != self::passphrase(variable, "Again: ")? {
+        return Err(PlannerError::Invalid(
+            "the passphrases do not match".to_string(),
+        ));
+    }
+    Ok(passphrase)
+}
+
+fn unlock(dir: &Path) -> Result<()> {
+    let count = vault::unlock(dir, &passphrase(PASSPHRASE, "Passphrase: ")?)?;
+    println!("Unlocked, {} file(s) decrypted", count);
+    Ok(())
+}
+
+fn lock(dir: &Path) -> Result<()> {
+    let count = vault::lock(dir, &passphrase(PASSPHRASE, "Passphrase: ")?)?;
+    println!("Locked, {} file(s) encrypted", count);
+    Ok(())
+}
+
+fn encryption(dir: &Path, command: &EncryptionCommand) -> Result<()> {
+    match command {
+        EncryptionCommand::Enable => {
+            let count = vault::enable(dir, &new_passphrase(PASSPHRASE)?, KdfParams::default())?;
+            println!(
+                "Encryption is on and the planner is locked, {} file(s) encrypted. \
+                 Without the passphrase nothing can be read back.",
+                count
+            );
+        }
+        EncryptionCommand::Disable => {
+            let count = vault::disable(dir, &passphrase(PASSPHRASE, "Passphrase: ")?)?;
+            println!("Encryption is off, {} file(s) decrypted", count);
+        }
+        EncryptionCommand::Rotate => {
+            let old = passphrase(PASSPHRASE, "Current passphrase: ")?;
+            let new = match env::var(NEW_PASSPHRASE) {
+                Ok(new) => new,
+                Err(_) if env::var(PASSPHRASE).is_ok() => old.clone(),
+                Err(_) => {
+                    println!("Leave the new passphrase empty to keep the current one.");
+                    match passphrase(NEW_PASSPHRASE, "New passphrase: ")? {
+                        new if new.is_empty() => old.clone(),
+                        new => {
+                            if new != passphrase(NEW_PASSPHRASE, "Again: ")? {
+                                return Err(PlannerError::Invalid(
+                                    "the passphrases do not match".to_string(),
+                                ));
+                            }
+                            new
+                        }
+                    }
+                }
+            };
+            let count = vault::rotate(dir, &old, &new, KdfParams::default())?;
+            println!("New key in place, {} file(s) encrypted again", count);
+        }
+        EncryptionCommand::Status => match Vault::load(dir)? {
+            None => println!("Encryption is off."),
+            Some(vault) if vault.locked => println!("Encryption is on, the planner is locked."),
+            Some(_) => println!("Encryption is on, the planner is unlocked."),
+        },
+    }
+    Ok(())
+}
+
+/// Opens the full-screen dashboard, or prints it once when stdout is not a
+/// terminal (piped into a file or another program).
+fn show_dashboard(store: &Store) -> Result<()> {
+    if io::stdout().is_terminal() {
+        return dashboard::tui::run(store);
+    }
+    let data = store.load()?;
+    print!(
+        "{}",
+        dashboard::render_plain(&Snapshot::new(&data, Utc::now()))
+    );
+    Ok(())
+}
+
+/// The API token from the environment or `config.json`. The deprecated
+/// `--token` still works, with a warning.
+fn api_token(store: &Store, flag: Option<String>) -> Result<Option<String>> {
+    if flag.is_some() {
+        eprintln!(
+            "--token is deprecated, as other users can read it in the process list; \
+             set SMART_PLANNER_API_TOKEN or `api_token` in config.json instead."
+        );
+        return Ok(flag);
+    }
+    Ok(store.config()?.api_token())
+}
+
+/// Runs the REST API until the process is stopped. It only listens on the
+/// loopback address, other machines should use a sync server instead.
+fn serve(store: Store, args: ServeArgs) -> Result<()> {
+    let token = api_token(&store, args.token)?.unwrap_or_default();
+    let server = ApiServer::bind(store, &format!("127.0.0.1:{}", args.port), &token)?;
+    match server.address() {
+        Some(address) => println!("Listening on http://{}", address),
+        None => println!("Listening on port {}", args.port),
+    }
+    server.run()
+}
+
+fn migrate(store: &Store, args: MigrateArgs) -> Result<()> {
+    let opened;
+    let from = match &args.from {
+        Some(config) => {
+            opened = config.open(store.dir())?;
+            opened.as_ref()
+        }
+        None => store.storage(),
+    };
+    let to = args.to.open(store.dir())?;
+    let data = storage::migrate(from, to.as_ref(), args.overwrite)?;
+    println!(
+        "Copied {} todos, {} routines, {} sessions, {} timers and {} plans from {} to {}",
+        data.todos.len(),
+        data.routines.len(),
+        data.sessions.len(),
+        data.timers.len(),
+        data.plans.len(),
+        from.describe(),
+        to.describe()
+    );
+    println!(
+        "To use it from now on, set \"storage\" in config.json to {}",
+        serde_json::to_string(&args.to)?
+    );
+    Ok(())
+}
+
+fn backup(store: &Store, file: &Path) -> Result<()> {
+    let passphrase = match Vault::load(store.dir())? {
+        Some(_) => Some(passphrase(PASSPHRASE, "Passphrase: ")?),
+        None => None,
+    };
+    let backup = backup::create(store, passphrase.as_deref(), Utc::now())?;
+    fs::write(file, backup)?;
+    println!(
+        "Backed up {} to {}{}",
+        store.dir().display(),
+        file.display(),
+        if passphrase.is_some() {
+            ", encrypted like the planner"
+        } else {
+            ""
+        }
+    );
+    Ok(())
+}
+
+fn restore(store: Store, file: &Path, check: bool, yes: bool, other_profile: bool) -> Result<()> {
+    let bytes = fs::read(file)?;
+    let passphrase = if backup::is_encrypted(&bytes)? {
+        Some(passphrase(PASSPHRASE, "Passphrase of the backup: ")?)
+    } else {
+        None
+    };
+    let backup = backup::read(&bytes, passphrase.as_deref())?;
+    println!(
+        "Backup of {}: {} todos, {} routines, {} sessions, {} plans and {} other files{}",
+        time::format_local(backup.created_at),
+        backup.data.todos.len(),
+        backup.data.routines.len(),
+        backup.data.sessions.len(),
+        backup.data.plans.len(),
+        backup.files.len(),
+        if backup.is_encrypted() {
+            ", encrypted"
+        } else {
+            ""
+        }
+    );
+    let here = profile::name_of(store.dir());
+    if backup.profile != here && !other_profile {
+        return Err(PlannerError::Invalid(format!(
+            "this is a backup of {}, not of {}; add --other-profile to restore it here anyway",
+            describe_profile(backup.profile.as_deref()),
+            describe_profile(here.as_deref())
+        )));
+    }
+    if check {
+        println!("It can be restored.");
+        return Ok(());
+    }
+    let question = format!("Replace everything in {} with it?", store.dir().display());
+    if !yes && !confirm_destructive(&question)? {
+        println!("Nothing restored.");
+        return Ok(());
+    }
+    let dir = store.dir().to_path_buf();
+    drop(store);
+    backup::restore(&dir, &backup, passphrase.as_deref())?;
+    if backup.is_encrypted() {
+        println!("Restored, and locked again. Run `unlock` to use it.");
+    } else {
+        println!("Restored.");
+    }
+    Ok(())
+}
+
+fn describe_profile(name: Option<&str>) -> String {
+    match name {
+        Some(name) => format!("the profile '{}'", name),
+        None => "the home folder".to_string(),
+    }
+}
+
+fn manage_profiles(
+    profiles: &Profiles,
+    current: Option<&str>,
+    command: ProfileCommand,
+) -> Result<()> {
+    match command {
+        ProfileCommand::List => {
+            let names = profiles.names()?;
+            if names.is_empty() {
+                println!(
+                    "No profiles yet, the data is in {}.",
+                    profiles.home().display()
+                );
+            }
+            let default = profiles.settings()?.default;
+            for name in names {
+                let marker = if default.as_deref() == Some(name.as_str()) {
+                    "*"
+                } else {
+                    " "
+                };
+                println!("{} {}", marker, name);
+            }
+        }
+        ProfileCommand::Create { name } => {
+            let dir = profiles.create(&name)?;
+            println!("Created the profile '{}' in {}", name, dir.display());
+        }
+        ProfileCommand::Remove { name, yes } => {
+            let question = format!("Delete the profile '{}' and all its data?", name);
+            profiles.existing(&name)?;
+            if !yes && !confirm_destructive(&question)? {
+                println!("Nothing deleted.");
+                return Ok(());
+            }
+            profiles.remove(&name)?;
+            println!("Deleted the profile '{}'", name);
+        }
+        ProfileCommand::Default { name: None, clear } => {
+            if clear {
+                profiles.set_default(None)?;
+            }
+            match profiles.settings()?.default {
+                Some(name) => println!("The default profile is '{}'", name),
+                None => println!("No default profile, the home folder is used."),
+            }
+        }
+        ProfileCommand::Default {
+            name: Some(name), ..
+        } => {
+            profiles.set_default(Some(&name))?;
+            println!("The default profile is now '{}'", name);
+        }
+        ProfileCommand::Usage => {
+            let usage = profiles.usage()?;
+            if usage.is_empty() {
+                println!("No profiles and no data yet.");
+                return Ok(());
+            }
+            let mut rows = vec![vec![
+                "Profile".to_string(),
+                "Files".to_string(),
+                "Size".to_string(),
+                String::new(),
+            ]];
+            let mut total = 0;
+            for profile in &usage {
+                total += profile.bytes;
+                let mut notes = Vec::new();
+                if profile.is_default {
+                    notes.push("default");
+                }
+                if profile.locked {
+                    notes.push("locked");
+                }
+                rows.push(vec![
+                    profile
+                        .name
+                        .clone()
+                        .unwrap_or_else(|| "(home folder)".to_string()),
+                    profile.files.to_string(),
+                    profile::format_bytes(profile.bytes),
+                    notes.join(", "),
+                ]);
+            }
+            rows.push(vec![
+                "Total".to_string(),
+                usage.iter().map(|p| p.files).sum::<usize>().to_string(),
+                profile::format_bytes(total),
+                String::new(),
+            ]);
+            print!("{}", report::text::table(&rows));
+        }
+        ProfileCommand::Copy {
+            what,
+            ids,
+            from,
+            to,
+        } => {
+            let from_dir = match &from {
+                Some(name) => profiles.existing(name)?,
+                None => profiles.resolve(current)?,
+            };
+            let to_dir = profiles.existing(&to)?;
+            if from_dir == to_dir {
+                return Err(PlannerError::Invalid(
+                    "copying needs two different profiles".to_string(),
+                ));
+            }
+            let source = Store::open(&from_dir)?.load()?;
+            let target_store = Store::open(&to_dir)?;
+            let mut target = target_store.load()?;
+            let now = Utc::now();
+            let (copied, kind) = match what {
+                CopyKind::Routines => (
+                    profile::copy_routines(&source, &mut target, &ids, now)?,
+                    "routine(s)",
+                ),
+                CopyKind::Todos => (
+                    profile::copy_todos(&source, &mut target, &ids, now)?,
+                    "todo(s)",
+                ),
+            };
+            if !copied.is_empty() {
+                target_store.save(&target)?;
+            }
+            let ids: Vec<String> = copied.iter().map(|id| format!("#{}", id)).collect();
+            println!(
+                "Copied {} {} to the profile '{}'{}",
+                copied.len(),
+                kind,
+                to,
+                if ids.is_empty() {
+                    String::new()
+                } else {
+                    format!(" as {}", ids.join(", "))
+                }
+            );
+        }
+    }
+    Ok(())
+}
+
+fn import(data: &mut PlannerData, file: &Path, yes: bool) -> Result<bool> {
+    let mut imported = backup::import(&fs::read_to_string(file)?)?;
+    let question = format!(
+        "Replace all data with the {} todos, {} routines and {} sessions in {}?",
+        imported.todos.len(),
+        imported.routines.len(),
+        imported.sessions.len(),
+        file.display()
+    );
+    if !yes && !confirm_destructive(&question)? {
+        println!("Nothing imported.");
+        return Ok(false);
+    }
+    // Ids handed out here are not given out again
+    imported.next_id = imported.next_id.max(data.next_id);
+    *data = imported;
+    println!("Imported {}, `history restore` can undo it", file.display());
+    Ok(true)
+}
+
+/// Posts `events` to the webhooks in config.json, once each so a command
+/// never waits on a hook that is down. A hook that cannot be reached does not
+/// fail the command, the delivery goes to the dead letters.
+fn post_events(store: &Store, events: &[webhook::Event]) -> Result<()> {
+    if events.is_empty() {
+        return Ok(());
+    }
+    let hooks = store.config()?.webhooks;
+    let sender = Sender::new(store.dir()).without_retries();
+    for event in events {
+        for letter in sender.send(&hooks, event)? {
+            eprintln!(
+                "Webhook {} failed: {}. `webhook retry` sends it again.",
+                letter.url, letter.error
+            );
+        }
+    }
+    Ok(())
+}
+
+fn webhook(store: &Store, command: WebhookCommand, now: DateTime<Utc>) -> Result<()> {
+    let hooks = store.config()?.webhooks;
+    let sender = Sender::new(store.dir());
+    match command {
+        WebhookCommand::Test => {
+            if hooks.is_empty() {
+                println!("No webhooks, add a \"webhooks\" list to config.json.");
+            }
+            let event = webhook::Event::new(EventKind::Ping, serde_json::json!({}), now);
+            for hook in &hooks {
+                match sender.deliver(hook, &event) {
+                    Ok(attempts) => println!("{}: ok ({} attempt(s))", hook.url, attempts),
+                    Err(e) => println!("{}: {}", hook.url, e),
+                }
+            }
+        }
+        WebhookCommand::Failed => {
+            let letters = sender.dead_letters()?;
+            if letters.is_empty() {
+                println!("No failed deliveries.");
+            }
+            for letter in letters {
+                println!(
+                    "{} {} to {}, {} attempt(s): {}",
+                    time::format_local(letter.failed_at),
+                    serde_json::to_string(&letter.event.event)?.trim_matches('"'),
+                    letter.url,
+                    letter.attempts,
+                    letter.error
+                );
+            }
+        }
+        WebhookCommand::Retry => {
+            let (delivered, left) = sender.retry(&hooks)?;
+            println!("Delivered {}, {} still failing", delivered, left.len());
+        }
+    }
+    Ok(())
+}
+
+fn report(store: &Store, data: &PlannerData, args: ReportArgs) -> Result<()> {
+    let report = match (args.from, args.to) {
+        (Some(from), Some(to)) => Report::for_range(data, DateRange::new(from, to)?),
+        _ => Report::for_period(data, args.period, args.date.unwrap_or_else(time::today)),
+    };
+    if args.email {
+        let config = store.config()?;
+        let recipients = if args.recipients.is_empty() {
+            config.smtp()?.to.clone()
+        } else {
+            args.recipients
+        };
+        let email = ReportEmail::from_report(&report, args.attach_csv);
+        let attempts = config.mailer()?.send(&recipients, &email)?;
+        println!(
+            "Emailed the report to {} ({} attempt(s))",
+            recipients.join(", "),
+            attempts
+        );
+        return post_events(store, &[webhook::Event::report_ready(&report, Utc::now())]);
+    }
+    let output = match args.format {
+        ReportFormat::Text if args.charts => {
+            let charset = if args.ascii {
+                Charset::Ascii
+            } else {
+                Charset::detect()
+            };
+            let end = report.current.range.end;
+            let year = Summary::build(
+                data,
+                DateRange::new(end - chrono::Duration::days(364), end)?,
+            );
+            format!(
+                "{}\n{}",
+                report::text::render(&report),
+                report::text::charts(&report, &year.daily_minutes, charset)
+            )
+        }
+        ReportFormat::Text => report::text::render(&report),
+        ReportFormat::Csv => report::csv::render(&report),
+        ReportFormat::Html => report::html::render(&report),
+    };
+    match args.output {
+        Some(path) => {
+            fs::write(&path, output)?;
+            println!("Wrote {}", path.display());
+        }
+        None => print!("{}", output),
+    }
+    post_events(store, &[webhook::Event::report_ready(&report, Utc::now())])
+}
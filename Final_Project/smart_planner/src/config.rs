@@ -0,0 +1,91 @@
+use std::env;
+
+use serde::{Deserialize, Serialize};
+
+use crate::budget::BudgetSettings;
+use crate::daemon::DaemonSettings;
+use crate::email::{Mailer, SmtpConfig};
+use crate::error::{PlannerError, Result};
+use crate::plan::LlmPlanner;
+use crate::pomodoro::PomodoroSettings;
+use crate::storage::StorageConfig;
+use crate::webhook::WebhookConfig;
+
+/// Settings read from `config.json` in the planner folder.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Config {
+    /// Where the data lives, the `data.json` file when left out.
+    #[serde(default)]
+    pub storage: StorageConfig,
+    #[serde(default)]
+    pub llm: Option<LlmConfig>,
+    #[serde(default)]
+    pub smtp: Option<SmtpConfig>,
+    /// Default lengths for `pomodoro start`.
+    #[serde(default)]
+    pub pomodoro: PomodoroSettings,
+    /// When budget notifications go off.
+    #[serde(default)]
+    pub budgets: BudgetSettings,
+    /// URLs that planner events are posted to.
+    #[serde(default)]
+    pub webhooks: Vec<WebhookConfig>,
+    /// When the daemon reminds of due todos.
+    #[serde(default)]
+    pub daemon: DaemonSettings,
+    /// Token of the `serve` API, also sent by `sync with`. Better kept in
+    /// `SMART_PLANNER_API_TOKEN` than in the file.
+    #[serde(default)]
+    pub api_token: Option<String>,
+}
+
+/// Where the `llm` plan backend sends its requests.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LlmConfig {
+    /// For example `https://api.openai.com/v1` or `http://localhost:11434/v1`.
+    pub base_url: String,
+    pub model: String,
+    /// Better kept in `SMART_PLANNER_LLM_KEY` than in the file.
+    #[serde(default)]
+    pub api_key: Option<String>,
+}
+
+impl Config {
+    /// Builds the LLM planner, failing with a hint when it is not configured.
+    pub fn llm_planner(&self) -> Result<LlmPlanner> {
+        let llm = self.llm.as_ref().ok_or_else(|| {
+            PlannerError::Invalid(
+                "the llm backend needs an \"llm\" section with base_url and model in config.json"
+                    .to_string(),
+            )
+        })?;
+        let api_key = env::var("SMART_PLANNER_LLM_KEY")
+            .ok()
+            .or_else(|| llm.api_key.clone());
+        LlmPlanner::new(&llm.base_url, &llm.model, api_key)
+    }
+
+    /// The token of the `serve` API, from `SMART_PLANNER_API_TOKEN` or the
+    /// file. Never taken from the command line, where other users of the
+    /// machine could read it.
+    pub fn api_token(&self) -> Option<String> {
+        env::var("SMART_PLANNER_API_TOKEN")
+            .ok()
+            .filter(|token| !token.trim().is_empty())
+            .or_else(|| self.api_token.clone())
+    }
+
+    /// The SMTP settings, failing with a hint when they are missing.
+    pub fn smtp(&self) -> Result<&SmtpConfig> {
+        self.smtp.as_ref().ok_or_else(|| {
+            PlannerError::Invalid(
+                "sending email needs an \"smtp\" section with host and from in config.json"
+                    .to_string(),
+            )
+        })
+    }
+
+    pub fn mailer(&self) -> Result<Mailer> {
+        Mailer::new(self.smtp()?.clone())
+    }
+}
@@ -0,0 +1,506 @@
+// Keeps running in the background and notifies when timers run out and
+// todos come due, without a planner command having to run at that moment.
+// It sleeps until the next of them. Commands that save tell it over the
+// control socket to reload the data; changes made some other way, like by
+// hand, are found by looking at the planner files every `poll_ms`.
+//
+// On Unix a socket in the planner folder lets `daemon status`, `daemon
+// reload` and `daemon stop` talk to a running daemon.
+use std::collections::BTreeSet;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration as StdDuration, SystemTime};
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::data::PlannerData;
+use crate::error::{PlannerError, Result};
+use crate::notify::Notifier;
+use crate::stopwatch::format_duration;
+use crate::store::Store;
+use crate::time;
+use crate::timer::Timer;
+use crate::todo::Todo;
+use crate::webhook::{self, EventKind, WebhookConfig};
+
+pub mod clock;
+#[cfg(unix)]
+pub mod socket;
+
+use clock::Clock;
+
+/// The folder in the planner folder kept for the daemon.
+pub const DIR: &str = "daemon";
+/// The control socket, in the daemon folder.
+pub const SOCKET_FILE: &str = "control.sock";
+/// Due reminders already sent, so each goes off once.
+const SENT_FILE: &str = "reminded.json";
+
+/// The `daemon` section of `config.json`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DaemonSettings {
+    /// Minutes before a todo's due time to remind of it. It is also
+    /// reminded of when the time comes.
+    pub remind_before: Vec<u32>,
+    /// How often to look for changes to the planner files that no command
+    /// told the daemon of.
+    pub poll_ms: u64,
+}
+
+impl Default for DaemonSettings {
+    fn default() -> Self {
+        DaemonSettings {
+            remind_before: vec![15],
+            poll_ms: 60_000,
+        }
+    }
+}
+
+/// The socket of a daemon running on the planner folder `dir`.
+pub fn socket_path(dir: &Path) -> PathBuf {
+    dir.join(DIR).join(SOCKET_FILE)
+}
+
+/// Tells a daemon running on `dir`, if any, that the data was saved.
+pub fn data_changed(dir: &Path) {
+    #[cfg(unix)]
+    socket::tell(&socket_path(dir), Request::Reload);
+    #[cfg(not(unix))]
+    let _ = dir;
+}
+
+/// Something the daemon notified about.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Reminder {
+    pub at: DateTime<Utc>,
+    pub title: String,
+    pub message: String,
+}
+
+impl Reminder {
+    fn timer(timer: &Timer) -> Self {
+        let mut message = format!("#{} {}", timer.id, timer.label);
+        if let Some(todo_id) = timer.todo_id {
+            message.push_str(&format!("  for todo #{}", todo_id));
+        }
+        Reminder {
+            at: timer.due,
+            title: "Time is up".to_string(),
+            message,
+        }
+    }
+
+    fn due(todo: &Todo, key: &Key) -> Self {
+        let (title, message) = match key.before {
+            0 => ("Due now", format!("#{} {}", todo.id, todo.title)),
+            before => (
+                "Due soon",
+                format!(
+                    "#{} {} is due at {} (in {})",
+                    todo.id,
+                    todo.title,
+                    time::format_local(key.due),
+                    format_duration(Duration::minutes(before as i64))
+                ),
+            ),
+        };
+        Reminder {
+            at: key.at(),
+            title: title.to_string(),
+            message,
+        }
+    }
+}
+
+/// One reminder of a todo: its id, its due time then, and how many minutes
+/// before it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+struct Key {
+    todo_id: u64,
+    due: DateTime<Utc>,
+    before: u32,
+}
+
+impl Key {
+    fn at(&self) -> DateTime<Utc> {
+        self.due - Duration::minutes(self.before as i64)
+    }
+}
+
+/// What to ask of a running daemon.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Request {
+    Status,
+    /// Read the data and config again now.
+    Reload,
+    Stop,
+}
+
+impl fmt::Display for Request {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Request::Status => "status",
+            Request::Reload => "reload",
+            Request::Stop => "stop",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for Request {
+    type Err = PlannerError;
+
+    fn from_str(text: &str) -> Result<Self> {
+        match text.trim() {
+            "status" => Ok(Request::Status),
+            "reload" => Ok(Request::Reload),
+            "stop" => Ok(Request::Stop),
+            other => Err(PlannerError::Invalid(format!(
+                "'{}' is not status, reload or stop",
+                other
+            ))),
+        }
+    }
+}
+
+/// A request to a running daemon, with where to send its status back.
+#[derive(Debug)]
+pub struct Call {
+    pub request: Request,
+    pub reply: mpsc::Sender<Status>,
+}
+
+/// What a running daemon reports about itself.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Status {
+    pub pid: u32,
+    pub started_at: DateTime<Utc>,
+    /// When the data was last read.
+    pub loaded_at: Option<DateTime<Utc>>,
+    /// Timers that have not run out.
+    pub timers: usize,
+    /// Due reminders still to come.
+    pub reminders: usize,
+    pub next: Option<Reminder>,
+    /// Notifications sent since it started.
+    pub notified: usize,
+    /// Why the last look at the planner failed, like it being locked.
+    pub error: Option<String>,
+    /// Set in the answer to `stop`.
+    pub stopping: bool,
+}
+
+/// The files whose size and change time tell that the data changed.
+type Fingerprint = Vec<(PathBuf, u64, Option<SystemTime>)>;
+
+pub struct Daemon {
+    store: Store,
+    clock: Arc<dyn Clock>,
+    notifier: Box<dyn Notifier>,
+    settings: DaemonSettings,
+    webhooks: Vec<WebhookConfig>,
+    data: PlannerData,
+    seen: Fingerprint,
+    reload: bool,
+    /// `None` until the daemon first ran on this folder.
+    sent: Option<BTreeSet<Key>>,
+    started_at: DateTime<Utc>,
+    loaded_at: Option<DateTime<Utc>>,
+    notified: usize,
+    error: Option<String>,
+    /// Webhook deliveries still going, each on a thread of its own so their
+    /// retries do not hold up the daemon.
+    deliveries: Vec<JoinHandle<()>>,
+    /// Set on the way out, so deliveries stop trying again.
+    stopping: Arc<AtomicBool>,
+}
+
+impl Daemon {
+    pub fn new(store: Store, clock: Arc<dyn Clock>, notifier: Box<dyn Notifier>) -> Self {
+        let started_at = clock.now();
+        Daemon {
+            store,
+            clock,
+            notifier,
+            settings: DaemonSettings::default(),
+            webhooks: Vec::new(),
+            data: PlannerData::default(),
+            seen: Vec::new(),
+            reload: true,
+            sent: None,
+            started_at,
+            loaded_at: None,
+            notified: 0,
+            error: None,
+            deliveries: Vec::new(),
+            stopping: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// Reads the data again if it changed, then notifies of everything due
+    /// by now and returns it.
+    pub fn tick(&mut self) -> Result<Vec<Reminder>> {
+        let seen = fingerprint(self.store.dir())?;
+        if self.reload || seen != self.seen {
+            let config = self.store.config()?;
+            self.data = self.store.load()?;
+            self.settings = config.daemon;
+            self.webhooks = config.webhooks;
+            let sent = self.store.dir().join(DIR).join(SENT_FILE);
+            self.sent = match sent.exists() {
+                true => Some(serde_json::from_str(&fs::read_to_string(sent)?)?),
+                false => None,
+            };
+            self.seen = seen;
+            self.reload = false;
+            self.loaded_at = Some(self.clock.now());
+        }
+        let now = self.clock.now();
+        let fired = self.data.fire_timers(now);
+        if !fired.is_empty() {
+            self.store.save(&self.data)?;
+            self.seen = fingerprint(self.store.dir())?;
+        }
+        let mut reminders: Vec<Reminder> = fired.iter().map(Reminder::timer).collect();
+        reminders.extend(self.due_reminders(now)?);
+        for reminder in &reminders {
+            self.notifier.notify(&reminder.title, &reminder.message);
+        }
+        self.notified += reminders.len();
+        self.post(&fired, now)?;
+        Ok(reminders)
+    }
+
+    /// Reminders of todos that came due, at most one per todo: the latest
+    /// that passed. On the very first run those that passed are only noted,
+    /// so old todos do not all go off at once.
+    fn due_reminders(&mut self, now: DateTime<Utc>) -> Result<Vec<Reminder>> {
+        let first_run = self.sent.is_none();
+        let sent = self.sent.take().unwrap_or_default();
+        let mut reminders = Vec::new();
+        let mut keep = BTreeSet::new();
+        for todo in self.data.todos.iter().filter(|todo| !todo.done) {
+            let Some(due) = todo.due else {
+                continue;
+            };
+            let passed: Vec<Key> = self
+                .keys(todo.id, due)
+                .into_iter()
+                .filter(|key| key.at() <= now)
+                .collect();
+            // Keys come soonest first, so the last passed is the latest
+            if let Some(latest) = passed.last() {
+                if !first_run && !sent.contains(latest) {
+                    reminders.push(Reminder::due(todo, latest));
+                }
+            }
+            keep.extend(passed);
+        }
+        if keep != sent || first_run {
+            let dir = self.store.dir().join(DIR);
+            fs::create_dir_all(&dir)?;
+            fs::write(dir.join(SENT_FILE), serde_json::to_string(&keep)?)?;
+        }
+        self.sent = Some(keep);
+        Ok(reminders)
+    }
+
+    /// The reminders of a todo due at `due`, soonest first.
+    fn keys(&self, todo_id: u64, due: DateTime<Utc>) -> Vec<Key> {
+        let mut before: Vec<u32> = self.settings.remind_before.clone();
+        before.push(0);
+        before.sort_unstable_by(|a, b| b.cmp(a));
+        before.dedup();
+        before
+            .into_iter()
+            .map(|before| Key {
+                todo_id,
+                due,
+                before,
+            })
+            .collect()
+    }
+
+    /// Due reminders still to come, soonest first.
+    fn upcoming(&self, now: DateTime<Utc>) -> Vec<Reminder> {
+        let mut upcoming: Vec<Reminder> = self
+            .data
+            .todos
+            .iter()
+            .filter(|todo| !todo.done)
+            .flat_map(|todo| {
+                let keys = todo
+                    .due
+                    .map(|due| self.keys(todo.id, due))
+                    .unwrap_or_default();
+                keys.into_iter()
+                    .filter(move |key| key.at() > now)
+                    .map(move |key| Reminder::due(todo, &key))
+            })
+            .collect();
+        upcoming.sort_by_key(|reminder| reminder.at);
+        upcoming
+    }
+
+    /// The next timer or reminder to go off.
+    pub fn next(&self) -> Option<Reminder> {
+        let now = self.clock.now();
+        let timer = self
+            .data
+            .pending_timers()
+            .first()
+            .map(|timer| Reminder::timer(timer));
+        let due = self.upcoming(now).into_iter().next();
+        match (timer, due) {
+            (Some(timer), Some(due)) if due.at < timer.at => Some(due),
+            (Some(timer), _) => Some(timer),
+            (None, due) => due,
+        }
+    }
+
+    pub fn status(&self) -> Status {
+        Status {
+            pid: process::id(),
+            started_at: self.started_at,
+            loaded_at: self.loaded_at,
+            timers: self.data.pending_timers().len(),
+            reminders: self.upcoming(self.clock.now()).len(),
+            next: self.next(),
+            notified: self.notified,
+            error: self.error.clone(),
+            stopping: false,
+        }
+    }
+
+    /// Posts webhook events for the timers that ran out and todos that became
+    /// overdue, for the hooks that want them, on another thread.
+    fn post(&mut self, fired: &[Timer], now: DateTime<Utc>) -> Result<()> {
+        if self.webhooks.is_empty() {
+            return Ok(());
+        }
+        let sender = webhook::Sender::new(self.store.dir()).stopped_by(self.stopping.clone());
+        let mut events: Vec<webhook::Event> = fired
+            .iter()
+            .map(|timer| webhook::Event::timer_expired(timer, now))
+            .collect();
+        if self
+            .webhooks
+            .iter()
+            .any(|hook| hook.wants(EventKind::TodoOverdue))
+        {
+            events.extend(sender.overdue(&self.data, now)?);
+        }
+        self.deliveries.retain(|delivery| !delivery.is_finished());
+        if events.is_empty() {
+            return Ok(());
+        }
+        let hooks = self.webhooks.clone();
+        self.deliveries.push(thread::spawn(move || {
+            for event in &events {
+                match sender.send(&hooks, event) {
+                    Ok(letters) => {
+                        for letter in letters {
+                            eprintln!(
+                                "Webhook {} failed after {} attempt(s): {}",
+                                letter.url, letter.attempts, letter.error
+                            );
+                        }
+                    }
+                    Err(e) => eprintln!("error: {}", e),
+                }
+            }
+        }));
+        Ok(())
+    }
+
+    /// Ends the webhook deliveries still going. Each finishes the attempt it
+    /// is on, at most the hook's timeout, and leaves the retries to the dead
+    /// letters.
+    pub fn finish_deliveries(&mut self) {
+        self.stopping.store(true, Ordering::SeqCst);
+        for delivery in self.deliveries.drain(..) {
+            let _ = delivery.join();
+        }
+    }
+
+    /// Runs until asked to stop, answering the requests that come in on
+    /// `calls`. A failed look at the planner is reported and tried again on
+    /// the next wake.
+    pub fn run(mut self, calls: mpsc::Receiver<Call>) -> Result<()> {
+        loop {
+            match self.tick() {
+                Ok(_) => self.error = None,
+                Err(e) => {
+                    if self.error.as_deref() != Some(&e.to_string()) {
+                        eprintln!("error: {}", e);
+                    }
+                    self.error = Some(e.to_string());
+                    self.reload = true;
+                }
+            }
+            let poll = StdDuration::from_millis(self.settings.poll_ms.max(10));
+            let wait = match self.next() {
+                Some(next) => (next.at - self.clock.now())
+                    .to_std()
+                    .unwrap_or_default()
+                    .min(poll),
+                None => poll,
+            };
+            match calls.recv_timeout(wait) {
+                Ok(Call { request, reply }) => match request {
+                    Request::Status => {
+                        let _ = reply.send(self.status());
+                    }
+                    Request::Reload => {
+                        self.reload = true;
+                        if let Err(e) = self.tick() {
+                            self.error = Some(e.to_string());
+                        }
+                        let _ = reply.send(self.status());
+                    }
+                    Request::Stop => {
+                        let mut status = self.status();
+                        status.stopping = true;
+                        let _ = reply.send(status);
+                        self.finish_deliveries();
+                        return Ok(());
+                    }
+                },
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => {
+                    self.finish_deliveries();
+                    return Ok(());
+                }
+            }
+        }
+    }
+}
+
+/// Sizes and change times of the files in the planner folder and its
+/// history, which every save through the store writes to.
+fn fingerprint(dir: &Path) -> Result<Fingerprint> {
+    let mut files = Vec::new();
+    for folder in [dir.to_path_buf(), dir.join("history")] {
+        if !folder.exists() {
+            continue;
+        }
+        for entry in fs::read_dir(&folder)? {
+            let entry = entry?;
+            let metadata = entry.metadata()?;
+            if metadata.is_file() {
+                files.push((entry.path(), metadata.len(), metadata.modified().ok()));
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
@@ -0,0 +1,131 @@
+// The control socket of the daemon. A client writes one request per line
+// (`status`, `reload` or `stop`) and reads back the daemon's status as one
+// line of JSON.
+use std::fs;
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::fs::PermissionsExt;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc};
+use std::thread;
+use std::time::Duration;
+
+use super::{Call, Request, Status};
+use crate::error::{PlannerError, Result};
+
+/// How long a client waits for the daemon to answer.
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Accepts connections on the socket until closed or dropped.
+pub struct Listener {
+    path: PathBuf,
+    closed: Arc<AtomicBool>,
+}
+
+/// Starts listening at `path`, passing each request on to `calls`. Fails
+/// when another daemon answers there already.
+pub fn listen(path: &Path, calls: mpsc::Sender<Call>) -> Result<Listener> {
+    if path.exists() {
+        if UnixStream::connect(path).is_ok() {
+            return Err(PlannerError::Invalid(format!(
+                "a daemon is already running, it listens at {}",
+                path.display()
+            )));
+        }
+        // Left behind by a daemon that did not stop cleanly
+        fs::remove_file(path)?;
+    }
+    let listener = UnixListener::bind(path)?;
+    fs::set_permissions(path, fs::Permissions::from_mode(0o600))?;
+    let closed = Arc::new(AtomicBool::new(false));
+    let stop = closed.clone();
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            if stop.load(Ordering::SeqCst) {
+                break;
+            }
+            if let Ok(stream) = stream {
+                let calls = calls.clone();
+                thread::spawn(move || answer(stream, &calls));
+            }
+        }
+    });
+    Ok(Listener {
+        path: path.to_path_buf(),
+        closed,
+    })
+}
+
+impl Listener {
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Stops accepting connections and removes the socket.
+    pub fn close(&self) {
+        if !self.closed.swap(true, Ordering::SeqCst) {
+            // Wakes the thread waiting for a connection so it sees the flag
+            let _ = UnixStream::connect(&self.path);
+            let _ = fs::remove_file(&self.path);
+        }
+    }
+}
+
+impl Drop for Listener {
+    fn drop(&mut self) {
+        self.close();
+    }
+}
+
+fn answer(stream: UnixStream, calls: &mpsc::Sender<Call>) {
+    let _ = stream.set_read_timeout(Some(TIMEOUT));
+    let mut line = String::new();
+    if BufReader::new(&stream).read_line(&mut line).is_err() || line.is_empty() {
+        return;
+    }
+    let reply = match line.parse::<Request>() {
+        Ok(request) => {
+            let (reply, answered) = mpsc::channel();
+            if calls.send(Call { request, reply }).is_err() {
+                return;
+            }
+            match answered.recv_timeout(TIMEOUT) {
+                Ok(status) => serde_json::to_string(&status).unwrap_or_default(),
+                Err(_) => return,
+            }
+        }
+        Err(e) => serde_json::json!({ "error": e.to_string() }).to_string(),
+    };
+    let mut stream = stream;
+    let _ = writeln!(stream, "{}", reply);
+}
+
+/// Sends `request` to the daemon listening at `path` without waiting for the
+/// answer. Does nothing when no daemon is running.
+pub fn tell(path: &Path, request: Request) {
+    if let Ok(mut stream) = UnixStream::connect(path) {
+        let _ = writeln!(stream, "{}", request);
+    }
+}
+
+/// Sends `request` to the daemon listening at `path` and returns its status.
+/// Fails with `NotFound` when no daemon is running.
+pub fn send(path: &Path, request: Request) -> Result<Status> {
+    let mut stream =
+        UnixStream::connect(path).map_err(|_| PlannerError::NotFound("daemon".to_string()))?;
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    writeln!(stream, "{}", request)?;
+    let mut line = String::new();
+    BufReader::new(&stream).read_line(&mut line)?;
+    if line.is_empty() {
+        return Err(PlannerError::Invalid(
+            "the daemon closed the connection without answering".to_string(),
+        ));
+    }
+    let value: serde_json::Value = serde_json::from_str(&line)?;
+    if let Some(error) = value.get("error").and_then(|error| error.as_str()) {
+        return Err(PlannerError::Invalid(error.to_string()));
+    }
+    Ok(serde_json::from_value(value)?)
+}
@@ -0,0 +1,301 @@
+// Full-screen overview of the day: the plan, the stopwatch, upcoming timers,
+// recent focus time and the high priority todos. This file holds what is
+// shown and what the keys do; `tui` draws it in the terminal. When stdout is
+// not a terminal the same information is printed once as plain text.
+use std::fmt::Write;
+
+use chrono::{DateTime, Duration, NaiveDate, NaiveTime, Utc};
+
+use crate::chart::{self, Charset};
+use crate::data::PlannerData;
+use crate::error::PlannerError;
+use crate::plan::Plan;
+use crate::pomodoro::PomodoroRun;
+use crate::report::{DateRange, Period, Summary};
+use crate::stopwatch::{format_duration, Session};
+use crate::time;
+use crate::timer::Timer;
+use crate::todo::{Priority, Todo};
+
+pub mod tui;
+
+/// How many timers the dashboard lists.
+const TIMERS_SHOWN: usize = 5;
+/// How many days the focus sparkline covers, today included.
+const FOCUS_DAYS: i64 = 14;
+/// How many of this week's subjects get a bar.
+const SUBJECTS_SHOWN: usize = 3;
+/// Width of the longest subject bar.
+const BAR_WIDTH: usize = 12;
+
+/// Everything the dashboard shows, taken from the data at one moment.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Snapshot {
+    pub now: DateTime<Utc>,
+    /// Local date and time of `now`.
+    pub date: NaiveDate,
+    pub time: NaiveTime,
+    /// Today's plan, if one was generated.
+    pub plan: Option<Plan>,
+    pub session: Option<Session>,
+    /// The Pomodoro run the session belongs to, if any.
+    pub pomodoro: Option<PomodoroRun>,
+    /// Timers that have not run out, soonest first.
+    pub timers: Vec<Timer>,
+    /// Open high priority todos, the ones due first at the top.
+    pub todos: Vec<Todo>,
+    /// Minutes worked on each of the last days, today last.
+    pub focus: Vec<(NaiveDate, i64)>,
+    /// Minutes per subject this week, most worked first.
+    pub subjects: Vec<(String, i64)>,
+}
+
+impl Snapshot {
+    pub fn new(data: &PlannerData, now: DateTime<Utc>) -> Self {
+        let local = time::to_local(now);
+        let mut todos: Vec<Todo> = data
+            .todos
+            .iter()
+            .filter(|todo| !todo.done && todo.priority == Priority::High)
+            .cloned()
+            .collect();
+        todos.sort_by_key(|todo| (todo.due.is_none(), todo.due, todo.id));
+        let date = local.date();
+        let recent = DateRange {
+            start: date - Duration::days(FOCUS_DAYS - 1),
+            end: date,
+        };
+        let mut subjects = Summary::build(data, Period::Week.range(date)).subjects;
+        subjects.truncate(SUBJECTS_SHOWN);
+        Snapshot {
+            now,
+            date: local.date(),
+            time: local.time(),
+            plan: data.plan(local.date()).cloned(),
+            session: data.running_session().cloned(),
+            pomodoro: data.pomodoro.clone(),
+            timers: data
+                .pending_timers()
+                .into_iter()
+                .take(TIMERS_SHOWN)
+                .cloned()
+                .collect(),
+            todos,
+            focus: Summary::build(data, recent).daily_minutes,
+            subjects,
+        }
+    }
+
+    /// Index of the plan block happening right now.
+    pub fn current_block(&self) -> Option<usize> {
+        self.plan
+            .as_ref()?
+            .blocks
+            .iter()
+            .position(|block| block.start <= self.time && self.time < block.end)
+    }
+
+    pub fn describe_session(&self) -> String {
+        if let Some(run) = &self.pomodoro {
+            return format!(
+                "Pomodoro on {}: {}, {} left ({} done)",
+                run.subject,
+                run.phase,
+                format_duration(run.remaining(self.now)),
+                run.completed
+            );
+        }
+        match &self.session {
+            Some(session) => format!(
+                "Running on {} for {} (since {})",
+                session.subject,
+                format_duration(session.elapsed(self.now)),
+                time::to_local(session.start).format("%H:%M")
+            ),
+            None => "Not running.".to_string(),
+        }
+    }
+
+    pub fn describe_timer(&self, timer: &Timer) -> String {
+        format!(
+            "#{} {}  at {} (in {})",
+            timer.id,
+            timer.label,
+            time::to_local(timer.due).format("%H:%M"),
+            format_duration(timer.remaining(self.now))
+        )
+    }
+
+    /// A sparkline of the last days and bars of this week's subjects, one
+    /// line each.
+    pub fn focus_lines(&self, charset: Charset) -> Vec<String> {
+        let minutes: Vec<i64> = self.focus.iter().map(|(_, minutes)| *minutes).collect();
+        let today = minutes.last().copied().unwrap_or(0);
+        let mut lines = vec![format!(
+            "{} days {}  today {}",
+            self.focus.len(),
+            chart::sparkline(&minutes, charset),
+            format_duration(Duration::minutes(today))
+        )];
+        let bars = chart::bars(&self.subjects, BAR_WIDTH, charset, |minutes| {
+            format_duration(Duration::minutes(minutes))
+        });
+        lines.extend(bars.lines().map(str::to_string));
+        lines
+    }
+
+    pub fn describe_todo(&self, todo: &Todo) -> String {
+        let mut line = format!("#{} {}", todo.id, todo.title);
+        if let Some(due) = todo.due {
+            let overdue = if due < self.now { ", overdue" } else { "" };
+            line.push_str(&format!("  (due {}{})", time::format_local(due), overdue));
+        }
+        line
+    }
+}
+
+/// What a key press asks for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Quit,
+    /// Select the todo above or below.
+    Up,
+    Down,
+    /// Stop the running session, or start one on the selected todo.
+    ToggleStopwatch,
+    /// Mark the selected todo as done.
+    Complete,
+    /// Read the data again.
+    Refresh,
+}
+
+/// The state kept between redraws: which todo is selected and the last
+/// message shown to the user.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Dashboard {
+    pub selected: usize,
+    pub status: Option<String>,
+}
+
+impl Dashboard {
+    /// Keeps the selection on the list after todos went away.
+    pub fn clamp(&mut self, snapshot: &Snapshot) {
+        self.selected = self.selected.min(snapshot.todos.len().saturating_sub(1));
+    }
+
+    pub fn selected_todo<'a>(&self, snapshot: &'a Snapshot) -> Option<&'a Todo> {
+        snapshot.todos.get(self.selected)
+    }
+
+    /// Carries out an action on the data and tells whether it changed.
+    /// Anything the data refuses ends up in `status` instead of an error, so
+    /// the dashboard stays open.
+    pub fn apply(&mut self, action: Action, data: &mut PlannerData, now: DateTime<Utc>) -> bool {
+        let snapshot = Snapshot::new(data, now);
+        self.clamp(&snapshot);
+        let result = match action {
+            Action::Quit | Action::Refresh => return false,
+            Action::Up => {
+                self.selected = self.selected.saturating_sub(1);
+                return false;
+            }
+            Action::Down => {
+                if self.selected + 1 < snapshot.todos.len() {
+                    self.selected += 1;
+                }
+                return false;
+            }
+            Action::ToggleStopwatch => match (&snapshot.session, self.selected_todo(&snapshot)) {
+                (Some(_), _) => data.stop_session(now).map(|session| {
+                    format!(
+                        "Recorded {} on {}",
+                        format_duration(session.elapsed(now)),
+                        session.subject
+                    )
+                }),
+                (None, Some(todo)) => {
+                    let subject = todo.subject.as_deref().unwrap_or(&todo.title);
+                    data.start_session(subject, now)
+                        .map(|_| format!("Stopwatch started on {}", subject.trim()))
+                }
+                (None, None) => Err(PlannerError::Invalid(
+                    "select a todo to time it".to_string(),
+                )),
+            },
+            Action::Complete => match self.selected_todo(&snapshot) {
+                Some(todo) => data
+                    .complete_todo(todo.id, now)
+                    .map(|_| format!("Completed #{} {}", todo.id, todo.title)),
+                None => Err(PlannerError::Invalid(
+                    "there is no todo to complete".to_string(),
+                )),
+            },
+        };
+        match result {
+            Ok(message) => {
+                self.status = Some(message);
+                self.clamp(&Snapshot::new(data, now));
+                true
+            }
+            Err(e) => {
+                self.status = Some(format!("error: {}", e));
+                false
+            }
+        }
+    }
+}
+
+/// The dashboard as plain text, for when stdout is not a terminal.
+pub fn render_plain(snapshot: &Snapshot) -> String {
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        "Smart Planner  {}",
+        snapshot.date.format("%a %Y-%m-%d")
+    );
+    let _ = writeln!(out, "Now {}", snapshot.time.format("%H:%M"));
+
+    out.push_str("\nToday's plan\n");
+    match &snapshot.plan {
+        Some(plan) if !plan.blocks.is_empty() => {
+            let current = snapshot.current_block();
+            for (index, block) in plan.blocks.iter().enumerate() {
+                let _ = writeln!(
+                    out,
+                    "{} {}-{}  {:<8} {}",
+                    if current == Some(index) { ">" } else { " " },
+                    block.start.format("%H:%M"),
+                    block.end.format("%H:%M"),
+                    block.kind.to_string(),
+                    block.title
+                );
+            }
+        }
+        Some(_) => out.push_str("  The plan is empty.\n"),
+        None => out.push_str("  No plan yet, run `plan generate`.\n"),
+    }
+
+    let _ = writeln!(out, "\nStopwatch\n  {}", snapshot.describe_session());
+
+    out.push_str("\nTimers\n");
+    if snapshot.timers.is_empty() {
+        out.push_str("  None running.\n");
+    }
+    for timer in &snapshot.timers {
+        let _ = writeln!(out, "  {}", snapshot.describe_timer(timer));
+    }
+
+    out.push_str("\nFocus\n");
+    for line in snapshot.focus_lines(Charset::detect()) {
+        let _ = writeln!(out, "  {}", line);
+    }
+
+    out.push_str("\nHigh priority\n");
+    if snapshot.todos.is_empty() {
+        out.push_str("  Nothing urgent.\n");
+    }
+    for todo in &snapshot.todos {
+        let _ = writeln!(out, "  {}", snapshot.describe_todo(todo));
+    }
+    out
+}
@@ -0,0 +1,257 @@
+// Draws the dashboard with ratatui and runs its key loop. The data is read
+// again every second, so changes made from another terminal show up and
+// timers are noticed when they run out.
+use std::io::{self, Write};
+use std::time::Duration;
+
+use chrono::Utc;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Direction, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Borders, List, ListItem, ListState, Paragraph, Wrap};
+use ratatui::{DefaultTerminal, Frame};
+
+use super::{Action, Dashboard, Snapshot};
+use crate::chart::Charset;
+use crate::error::Result;
+use crate::plan::BlockKind;
+use crate::store::Store;
+
+/// Below this width the panels are stacked instead of side by side.
+const NARROW: u16 = 80;
+
+const HELP: &str = "↑/↓ select  s start/stop  d done  r refresh  q quit";
+
+/// The action bound to a key, if any.
+pub fn action_for(key: KeyEvent) -> Option<Action> {
+    if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
+        return Some(Action::Quit);
+    }
+    Some(match key.code {
+        KeyCode::Char('q') | KeyCode::Esc => Action::Quit,
+        KeyCode::Up | KeyCode::Char('k') => Action::Up,
+        KeyCode::Down | KeyCode::Char('j') => Action::Down,
+        KeyCode::Char('s') => Action::ToggleStopwatch,
+        KeyCode::Char('d') | KeyCode::Enter => Action::Complete,
+        KeyCode::Char('r') => Action::Refresh,
+        _ => return None,
+    })
+}
+
+/// Takes over the terminal until the user quits, then gives it back as it was.
+pub fn run(store: &Store) -> Result<()> {
+    let mut terminal = ratatui::try_init()?;
+    let result = event_loop(&mut terminal, store);
+    ratatui::restore();
+    result
+}
+
+fn event_loop(terminal: &mut DefaultTerminal, store: &Store) -> Result<()> {
+    let mut dashboard = Dashboard::default();
+    loop {
+        let mut data = store.load()?;
+        let now = Utc::now();
+        let fired = data.fire_timers(now);
+        let transitions = data.advance_pomodoro(now);
+        if !fired.is_empty() || !transitions.is_empty() {
+            store.save(&data)?;
+            let mut messages: Vec<String> = transitions.iter().map(|t| t.to_string()).collect();
+            if !fired.is_empty() {
+                let labels: Vec<&str> = fired.iter().map(|timer| timer.label.as_str()).collect();
+                messages.push(format!("Time is up: {}", labels.join(", ")));
+            }
+            dashboard.status = Some(messages.join("; "));
+            // Ring the bell, the status line alone is easy to miss
+            print!("\x07");
+            io::stdout().flush()?;
+        }
+        let snapshot = Snapshot::new(&data, now);
+        dashboard.clamp(&snapshot);
+        terminal.draw(|frame| draw(frame, &snapshot, &dashboard))?;
+
+        if !event::poll(Duration::from_secs(1))? {
+            continue;
+        }
+        match event::read()? {
+            Event::Key(key) if key.kind == KeyEventKind::Press => match action_for(key) {
+                Some(Action::Quit) => return Ok(()),
+                Some(action) if dashboard.apply(action, &mut data, Utc::now()) => {
+                    store.save(&data)?
+                }
+                _ => {}
+            },
+            // Drawing again at the top of the loop picks up the new size
+            Event::Resize(..) => terminal.autoresize()?,
+            _ => {}
+        }
+    }
+}
+
+/// Draws the whole dashboard into `frame`, fitting whatever size it has.
+pub fn draw(frame: &mut Frame, snapshot: &Snapshot, dashboard: &Dashboard) {
+    let [header, body, footer] = Layout::vertical([
+        Constraint::Length(1),
+        Constraint::Min(0),
+        Constraint::Length(1),
+    ])
+    .areas(frame.area());
+
+    let title = Line::from(vec![
+        Span::styled(
+            "Smart Planner",
+            Style::default().add_modifier(Modifier::BOLD),
+        ),
+        Span::raw(format!(
+            "  {}  {}",
+            snapshot.date.format("%a %Y-%m-%d"),
+            snapshot.time.format("%H:%M")
+        )),
+    ]);
+    frame.render_widget(Paragraph::new(title), header);
+
+    let footer_text = match &dashboard.status {
+        Some(status) => Line::from(vec![
+            Span::styled(status.clone(), Style::default().fg(Color::Yellow)),
+            Span::raw(format!("  |  {}", HELP)),
+        ]),
+        None => Line::from(HELP),
+    };
+    frame.render_widget(Paragraph::new(footer_text), footer);
+
+    let direction = if body.width < NARROW {
+        Direction::Vertical
+    } else {
+        Direction::Horizontal
+    };
+    let [timeline, side] = Layout::default()
+        .direction(direction)
+        .constraints([Constraint::Percentage(55), Constraint::Percentage(45)])
+        .areas(body);
+    draw_timeline(frame, timeline, snapshot);
+
+    // Block characters only, the panels already need a Unicode terminal
+    let focus_lines = snapshot.focus_lines(Charset::Unicode);
+    let [stopwatch, timers, focus, todos] = Layout::vertical([
+        Constraint::Length(3),
+        Constraint::Length(snapshot.timers.len().max(1) as u16 + 2),
+        Constraint::Length(focus_lines.len() as u16 + 2),
+        Constraint::Min(3),
+    ])
+    .areas(side);
+    let session_style = if snapshot.pomodoro.is_some() {
+        Style::default().fg(Color::Red)
+    } else if snapshot.session.is_some() {
+        Style::default().fg(Color::Green)
+    } else {
+        Style::default()
+    };
+    frame.render_widget(
+        Paragraph::new(Span::styled(snapshot.describe_session(), session_style))
+            .wrap(Wrap { trim: true })
+            .block(panel("Stopwatch")),
+        stopwatch,
+    );
+
+    let timer_items: Vec<ListItem> = if snapshot.timers.is_empty() {
+        vec![ListItem::new("None running.")]
+    } else {
+        snapshot
+            .timers
+            .iter()
+            .map(|timer| ListItem::new(snapshot.describe_timer(timer)))
+            .collect()
+    };
+    frame.render_widget(List::new(timer_items).block(panel("Timers")), timers);
+
+    let focus_text: Vec<Line> = focus_lines.into_iter().map(Line::from).collect();
+    frame.render_widget(Paragraph::new(focus_text).block(panel("Focus")), focus);
+
+    draw_todos(frame, todos, snapshot, dashboard);
+}
+
+fn panel(title: &str) -> Block<'_> {
+    Block::default().borders(Borders::ALL).title(title)
+}
+
+fn draw_timeline(frame: &mut Frame, area: Rect, snapshot: &Snapshot) {
+    let block = panel("Today's plan");
+    let Some(plan) = snapshot
+        .plan
+        .as_ref()
+        .filter(|plan| !plan.blocks.is_empty())
+    else {
+        let text = match snapshot.plan {
+            Some(_) => "The plan is empty.",
+            None => "No plan yet, run `plan generate`.",
+        };
+        frame.render_widget(
+            Paragraph::new(text).wrap(Wrap { trim: true }).block(block),
+            area,
+        );
+        return;
+    };
+    let current = snapshot.current_block();
+    let items: Vec<ListItem> = plan
+        .blocks
+        .iter()
+        .enumerate()
+        .map(|(index, plan_block)| {
+            let color = match plan_block.kind {
+                BlockKind::Routine => Color::Cyan,
+                BlockKind::Task => Color::White,
+                BlockKind::Break => Color::Green,
+                BlockKind::Custom => Color::Magenta,
+                BlockKind::Busy => Color::Red,
+            };
+            let mut style = Style::default().fg(color);
+            if plan_block.end <= snapshot.time {
+                style = style.add_modifier(Modifier::DIM);
+            }
+            let marker = if current == Some(index) { "▶ " } else { "  " };
+            ListItem::new(Line::from(vec![
+                Span::raw(marker),
+                Span::styled(
+                    format!(
+                        "{}-{}  {}",
+                        plan_block.start.format("%H:%M"),
+                        plan_block.end.format("%H:%M"),
+                        plan_block.title
+                    ),
+                    style,
+                ),
+            ]))
+        })
+        .collect();
+    // Keep the block happening now in view on short terminals
+    let mut state = ListState::default().with_selected(current);
+    let list = List::new(items)
+        .block(block)
+        .highlight_style(Style::default().add_modifier(Modifier::BOLD));
+    frame.render_stateful_widget(list, area, &mut state);
+}
+
+fn draw_todos(frame: &mut Frame, area: Rect, snapshot: &Snapshot, dashboard: &Dashboard) {
+    let block = panel("High priority");
+    if snapshot.todos.is_empty() {
+        frame.render_widget(Paragraph::new("Nothing urgent.").block(block), area);
+        return;
+    }
+    let items: Vec<ListItem> = snapshot
+        .todos
+        .iter()
+        .map(|todo| {
+            let style = match todo.due {
+                Some(due) if due < snapshot.now => Style::default().fg(Color::Red),
+                _ => Style::default(),
+            };
+            ListItem::new(Span::styled(snapshot.describe_todo(todo), style))
+        })
+        .collect();
+    let mut state = ListState::default().with_selected(Some(dashboard.selected));
+    let list = List::new(items)
+        .block(block)
+        .highlight_symbol("> ")
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+    frame.render_stateful_widget(list, area, &mut state);
+}
@@ -0,0 +1,897 @@
+// The planner's state and every change the user can make to it. The command
+// line only parses arguments and calls these methods, so the rules live here.
+use chrono::{DateTime, NaiveDate, NaiveTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::budget::{self, Alert, Budget, BudgetKind};
+use crate::busy::BusyEvent;
+use crate::error::{PlannerError, Result};
+use crate::plan::{ConflictChecker, Plan, Resolution};
+use crate::pomodoro::{Phase, Pomodoro, PomodoroRun, PomodoroSettings, Transition};
+use crate::project::graph::{list_ids, show_cycle, Graph};
+use crate::project::Project;
+use crate::report::Period;
+use crate::routine::{CheckStatus, Frequency, Routine, RoutineCheck};
+use crate::stopwatch::Session;
+use crate::time;
+use crate::timer::{self, Timer};
+use crate::todo::{Priority, Todo};
+
+/// The fields a user fills in for a new todo.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NewTodo {
+    pub title: String,
+    pub priority: Priority,
+    pub due: Option<DateTime<Utc>>,
+    pub estimate_minutes: Option<u32>,
+    pub subject: Option<String>,
+    pub project: Option<u64>,
+    /// Makes it a subtask of this todo, in the same project.
+    pub parent: Option<u64>,
+}
+
+impl NewTodo {
+    pub fn new(title: &str, priority: Priority) -> Self {
+        NewTodo {
+            title: title.to_string(),
+            priority,
+            due: None,
+            estimate_minutes: None,
+            subject: None,
+            project: None,
+            parent: None,
+        }
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        if self.title.trim().is_empty() {
+            return Err(PlannerError::Invalid("a todo needs a title".to_string()));
+        }
+        if self.estimate_minutes == Some(0) {
+            return Err(PlannerError::Invalid(
+                "the estimate has to be at least one minute".to_string(),
+            ));
+        }
+        if let Some(subject) = &self.subject {
+            if subject.trim().is_empty() {
+                return Err(PlannerError::Invalid("the subject is empty".to_string()));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Everything the planner remembers.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PlannerData {
+    #[serde(default)]
+    pub next_id: u64,
+    #[serde(default)]
+    pub todos: Vec<Todo>,
+    #[serde(default)]
+    pub routines: Vec<Routine>,
+    #[serde(default)]
+    pub plans: Vec<Plan>,
+    #[serde(default)]
+    pub sessions: Vec<Session>,
+    #[serde(default)]
+    pub routine_checks: Vec<RoutineCheck>,
+    #[serde(default)]
+    pub timers: Vec<Timer>,
+    /// Calendar events imported from `.ics` files.
+    #[serde(default)]
+    pub busy: Vec<BusyEvent>,
+    /// The Pomodoro run going on, if any.
+    #[serde(default)]
+    pub pomodoro: Option<PomodoroRun>,
+    /// Work intervals of past and current Pomodoro runs.
+    #[serde(default)]
+    pub pomodoros: Vec<Pomodoro>,
+    #[serde(default)]
+    pub projects: Vec<Project>,
+    /// Weekly budgets per subject, including ones changed or removed since.
+    #[serde(default)]
+    pub budgets: Vec<Budget>,
+}
+
+impl PlannerData {
+    /// Hands out ids shared by every kind of item.
+    pub fn next_id(&mut self) -> u64 {
+        self.next_id += 1;
+        self.next_id
+    }
+
+    pub fn todo(&self, id: u64) -> Result<&Todo> {
+        self.todos
+            .iter()
+            .find(|todo| todo.id == id)
+            .ok_or_else(|| PlannerError::NotFound(format!("todo #{}", id)))
+    }
+
+    pub fn todo_mut(&mut self, id: u64) -> Result<&mut Todo> {
+        self.todos
+            .iter_mut()
+            .find(|todo| todo.id == id)
+            .ok_or_else(|| PlannerError::NotFound(format!("todo #{}", id)))
+    }
+
+    /// Validates and stores a new todo, returning its id.
+    pub fn add_todo(&mut self, new: NewTodo, now: DateTime<Utc>) -> Result<u64> {
+        new.validate()?;
+        let mut project = new.project;
+        if let Some(project) = project {
+            self.project(project)?;
+        }
+        if let Some(parent) = new.parent {
+            let parent = self.todo(parent)?;
+            if project.is_some_and(|project| parent.project != Some(project)) {
+                return Err(PlannerError::Invalid(format!(
+                    "a subtask goes in the project of todo #{}",
+                    parent.id
+                )));
+            }
+            project = parent.project;
+        }
+        let id = self.next_id();
+        let mut todo = Todo::new(id, new.title.trim(), new.priority, now);
+        todo.due = new.due;
+        todo.estimate_minutes = new.estimate_minutes;
+        todo.subject = new.subject.map(|subject| subject.trim().to_string());
+        todo.project = project;
+        todo.parent = new.parent;
+        self.todos.push(todo);
+        Ok(id)
+    }
+
+    /// Fails while the todo has open subtasks, see `complete_todo_tree`.
+    pub fn complete_todo(&mut self, id: u64, now: DateTime<Utc>) -> Result<()> {
+        let open = self.open_subtasks(id)?;
+        let todo = self.todo_mut(id)?;
+        if todo.done {
+            return Err(PlannerError::Invalid(format!(
+                "todo #{} is already done",
+                id
+            )));
+        }
+        if !open.is_empty() {
+            return Err(PlannerError::Invalid(format!(
+                "todo #{} still has open subtasks ({}), complete them first",
+                id,
+                list_ids(&open)
+            )));
+        }
+        todo.complete(now);
+        Ok(())
+    }
+
+    /// Marks a finished todo as open again.
+    pub fn reopen_todo(&mut self, id: u64) -> Result<()> {
+        let todo = self.todo_mut(id)?;
+        if !todo.done {
+            return Err(PlannerError::Invalid(format!("todo #{} is not done", id)));
+        }
+        todo.done = false;
+        todo.completed_at = None;
+        Ok(())
+    }
+
+    /// Completes the todo with all its open subtasks, returning the ids
+    /// completed, the todo itself last.
+    pub fn complete_todo_tree(&mut self, id: u64, now: DateTime<Utc>) -> Result<Vec<u64>> {
+        if self.todo(id)?.done {
+            return Err(PlannerError::Invalid(format!(
+                "todo #{} is already done",
+                id
+            )));
+        }
+        let mut done = self.open_subtasks(id)?;
+        done.push(id);
+        for id in &done {
+            self.todo_mut(*id)?.complete(now);
+        }
+        Ok(done)
+    }
+
+    /// The subtasks of a todo, and theirs, that are not done yet. Deeper
+    /// ones come before their parents.
+    pub fn open_subtasks(&self, id: u64) -> Result<Vec<u64>> {
+        self.todo(id)?;
+        let mut open = Vec::new();
+        self.collect_subtasks(id, &mut open);
+        open.retain(|subtask| self.todo(*subtask).is_ok_and(|todo| !todo.done));
+        Ok(open)
+    }
+
+    fn collect_subtasks(&self, id: u64, into: &mut Vec<u64>) {
+        for todo in self.todos.iter().filter(|todo| todo.parent == Some(id)) {
+            // Guards against a loop written into the data file by hand
+            if into.contains(&todo.id) {
+                continue;
+            }
+            self.collect_subtasks(todo.id, into);
+            into.push(todo.id);
+        }
+    }
+
+    /// Deletes a todo. Its subtasks move up to its parent and todos it
+    /// blocked are no longer blocked by it.
+    pub fn remove_todo(&mut self, id: u64) -> Result<Todo> {
+        let index = self
+            .todos
+            .iter()
+            .position(|todo| todo.id == id)
+            .ok_or_else(|| PlannerError::NotFound(format!("todo #{}", id)))?;
+        let removed = self.todos.remove(index);
+        for todo in &mut self.todos {
+            if todo.parent == Some(id) {
+                todo.parent = removed.parent;
+            }
+            todo.blocked_by.retain(|blocker| *blocker != id);
+        }
+        Ok(removed)
+    }
+
+    /// Makes `id` a subtask of `parent`, or a top level todo again. It and
+    /// its subtasks move to the parent's project.
+    pub fn set_parent(&mut self, id: u64, parent: Option<u64>) -> Result<()> {
+        let project = match parent {
+            Some(parent) if parent == id => {
+                return Err(PlannerError::Invalid(format!(
+                    "todo #{} cannot be its own subtask",
+                    id
+                )))
+            }
+            Some(parent) => self.todo(parent)?.project,
+            None => self.todo(id)?.project,
+        };
+        let old = std::mem::replace(&mut self.todo_mut(id)?.parent, parent);
+        if let Err(e) = self.check_links() {
+            self.todo_mut(id)?.parent = old;
+            return Err(e);
+        }
+        self.move_tree(id, project)
+    }
+
+    /// Records that `id` cannot start before `blocker` is done.
+    pub fn block_todo(&mut self, id: u64, blocker: u64) -> Result<()> {
+        self.todo(blocker)?;
+        if id == blocker {
+            return Err(PlannerError::Invalid(format!(
+                "todo #{} cannot block itself",
+                id
+            )));
+        }
+        let todo = self.todo_mut(id)?;
+        if todo.blocked_by.contains(&blocker) {
+            return Err(PlannerError::Invalid(format!(
+                "todo #{} is already blocked by #{}",
+                id, blocker
+            )));
+        }
+        todo.blocked_by.push(blocker);
+        if let Err(e) = self.check_links() {
+            self.todo_mut(id)?
+                .blocked_by
+                .retain(|other| *other != blocker);
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    pub fn unblock_todo(&mut self, id: u64, blocker: u64) -> Result<()> {
+        let todo = self.todo_mut(id)?;
+        let before = todo.blocked_by.len();
+        todo.blocked_by.retain(|other| *other != blocker);
+        if todo.blocked_by.len() == before {
+            return Err(PlannerError::NotFound(format!(
+                "todo #{} is not blocked by #{}",
+                id, blocker
+            )));
+        }
+        Ok(())
+    }
+
+    /// Fails when todos wait for each other in a loop, through blockers or
+    /// subtasks.
+    fn check_links(&self) -> Result<()> {
+        match Graph::new(&self.todos).cycle() {
+            Some(cycle) => Err(PlannerError::Invalid(format!(
+                "that would make todos wait for each other in a loop: {}",
+                show_cycle(&cycle)
+            ))),
+            None => Ok(()),
+        }
+    }
+
+    pub fn project(&self, id: u64) -> Result<&Project> {
+        self.projects
+            .iter()
+            .find(|project| project.id == id)
+            .ok_or_else(|| PlannerError::NotFound(format!("project #{}", id)))
+    }
+
+    pub fn add_project(&mut self, name: &str, now: DateTime<Utc>) -> Result<u64> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(PlannerError::Invalid("a project needs a name".to_string()));
+        }
+        let id = self.next_id();
+        self.projects.push(Project::new(id, name, now));
+        Ok(id)
+    }
+
+    /// Deletes a project. Its todos stay, outside of any project.
+    pub fn remove_project(&mut self, id: u64) -> Result<Project> {
+        let index = self
+            .projects
+            .iter()
+            .position(|project| project.id == id)
+            .ok_or_else(|| PlannerError::NotFound(format!("project #{}", id)))?;
+        for todo in &mut self.todos {
+            if todo.project == Some(id) {
+                todo.project = None;
+            }
+        }
+        Ok(self.projects.remove(index))
+    }
+
+    /// Puts a top level todo and its subtasks in a project, or takes them
+    /// out of any.
+    pub fn move_todo(&mut self, id: u64, project: Option<u64>) -> Result<()> {
+        if let Some(project) = project {
+            self.project(project)?;
+        }
+        if let Some(parent) = self.todo(id)?.parent {
+            return Err(PlannerError::Invalid(format!(
+                "todo #{} is a subtask of #{}, move that one instead",
+                id, parent
+            )));
+        }
+        self.move_tree(id, project)
+    }
+
+    fn move_tree(&mut self, id: u64, project: Option<u64>) -> Result<()> {
+        let mut tree = Vec::new();
+        self.collect_subtasks(id, &mut tree);
+        tree.push(id);
+        for id in tree {
+            self.todo_mut(id)?.project = project;
+        }
+        Ok(())
+    }
+
+    /// Validates and stores a new routine, returning its id.
+    pub fn add_routine(
+        &mut self,
+        name: &str,
+        start: NaiveTime,
+        duration_minutes: u32,
+        frequency: Frequency,
+        now: DateTime<Utc>,
+    ) -> Result<u64> {
+        if name.trim().is_empty() {
+            return Err(PlannerError::Invalid("a routine needs a name".to_string()));
+        }
+        if duration_minutes == 0 || duration_minutes > 24 * 60 {
+            return Err(PlannerError::Invalid(
+                "a routine lasts between 1 minute and 24 hours".to_string(),
+            ));
+        }
+        if frequency == Frequency::Days(Vec::new()) {
+            return Err(PlannerError::Invalid(
+                "a routine needs at least one day".to_string(),
+            ));
+        }
+        let id = self.next_id();
+        self.routines.push(Routine {
+            id,
+            name: name.trim().to_string(),
+            start,
+            duration_minutes,
+            frequency,
+            created_at: Some(now),
+        });
+        Ok(id)
+    }
+
+    pub fn remove_routine(&mut self, id: u64) -> Result<Routine> {
+        let index = self
+            .routines
+            .iter()
+            .position(|routine| routine.id == id)
+            .ok_or_else(|| PlannerError::NotFound(format!("routine #{}", id)))?;
+        Ok(self.routines.remove(index))
+    }
+
+    /// Marks a routine as done on a day. Checking it twice changes nothing.
+    pub fn check_routine(&mut self, id: u64, date: NaiveDate) -> Result<()> {
+        self.mark_routine(id, date, CheckStatus::Done).map(|_| ())
+    }
+
+    /// Records how much of a routine got done on a day, replacing what was
+    /// recorded for that day before.
+    pub fn mark_routine(
+        &mut self,
+        id: u64,
+        date: NaiveDate,
+        status: CheckStatus,
+    ) -> Result<RoutineCheck> {
+        let routine = self
+            .routines
+            .iter()
+            .find(|routine| routine.id == id)
+            .ok_or_else(|| PlannerError::NotFound(format!("routine #{}", id)))?;
+        if !routine.frequency.occurs_on(date) {
+            return Err(PlannerError::Invalid(format!(
+                "{} is not scheduled on {}",
+                routine.name, date
+            )));
+        }
+        let check = RoutineCheck {
+            routine_id: id,
+            date,
+            status,
+        };
+        match self
+            .routine_checks
+            .iter_mut()
+            .find(|existing| existing.routine_id == id && existing.date == date)
+        {
+            Some(existing) => existing.status = status,
+            None => self.routine_checks.push(check.clone()),
+        }
+        Ok(check)
+    }
+
+    /// The stopwatch session that is still running, if any.
+    pub fn running_session(&self) -> Option<&Session> {
+        self.sessions.iter().find(|session| session.is_running())
+    }
+
+    /// Starts the stopwatch on a subject. Only one session runs at a time,
+    /// and none besides a Pomodoro run's, even during its breaks.
+    pub fn start_session(&mut self, subject: &str, now: DateTime<Utc>) -> Result<u64> {
+        self.check_no_pomodoro()?;
+        if subject.trim().is_empty() {
+            return Err(PlannerError::Invalid(
+                "the stopwatch needs a subject".to_string(),
+            ));
+        }
+        if let Some(running) = self.running_session() {
+            return Err(PlannerError::Invalid(format!(
+                "the stopwatch is already running on {}",
+                running.subject
+            )));
+        }
+        let id = self.next_id();
+        self.sessions.push(Session {
+            id,
+            subject: subject.trim().to_string(),
+            start: now,
+            end: None,
+        });
+        Ok(id)
+    }
+
+    /// Stops the running session and returns it.
+    pub fn stop_session(&mut self, now: DateTime<Utc>) -> Result<Session> {
+        self.check_no_pomodoro()?;
+        let session = self
+            .sessions
+            .iter_mut()
+            .find(|session| session.is_running())
+            .ok_or_else(|| PlannerError::Invalid("the stopwatch is not running".to_string()))?;
+        session.end = Some(now.max(session.start));
+        Ok(session.clone())
+    }
+
+    /// Throws the running session away without recording it.
+    pub fn reset_session(&mut self) -> Result<Session> {
+        self.check_no_pomodoro()?;
+        let index = self
+            .sessions
+            .iter()
+            .position(|session| session.is_running())
+            .ok_or_else(|| PlannerError::Invalid("the stopwatch is not running".to_string()))?;
+        Ok(self.sessions.remove(index))
+    }
+
+    /// The budget `subject` has now, one that has not been changed or
+    /// removed.
+    pub fn budget(&self, subject: &str) -> Result<&Budget> {
+        self.budgets
+            .iter()
+            .find(|budget| budget.is_for(subject) && budget.until.is_none())
+            .ok_or_else(|| PlannerError::NotFound(format!("budget for {}", subject.trim())))
+    }
+
+    /// Gives `subject` a weekly budget from `today` on, ending the one it
+    /// had. Earlier days keep the old budget; one set today is replaced.
+    pub fn set_budget(
+        &mut self,
+        subject: &str,
+        kind: BudgetKind,
+        minutes: u32,
+        today: NaiveDate,
+    ) -> Result<u64> {
+        if subject.trim().is_empty() {
+            return Err(PlannerError::Invalid("the subject is empty".to_string()));
+        }
+        if minutes == 0 || minutes > 7 * 24 * 60 {
+            return Err(PlannerError::Invalid(
+                "a weekly budget is between 1 minute and 168 hours".to_string(),
+            ));
+        }
+        if self.budget(subject).is_ok() {
+            self.remove_budget(subject, today)?;
+        }
+        let id = self.next_id();
+        self.budgets.push(Budget {
+            id,
+            subject: subject.trim().to_string(),
+            kind,
+            minutes,
+            from: today,
+            until: None,
+            alerted: Vec::new(),
+        });
+        Ok(id)
+    }
+
+    /// Stops budgeting `subject` from `today` on.
+    pub fn remove_budget(&mut self, subject: &str, today: NaiveDate) -> Result<Budget> {
+        let id = self.budget(subject)?.id;
+        let index = self
+            .budgets
+            .iter()
+            .position(|budget| budget.id == id)
+            .expect("the budget was just found");
+        if self.budgets[index].from >= today {
+            return Ok(self.budgets.remove(index));
+        }
+        self.budgets[index].until = Some(today);
+        Ok(self.budgets[index].clone())
+    }
+
+    /// Thresholds of this week's budgets that the time spent so far went past
+    /// and nobody was told about yet. Each is remembered so it is only
+    /// announced once a week, and only the highest is returned when several
+    /// were passed at once.
+    pub fn fire_budget_alerts(&mut self, thresholds: &[u32], now: DateTime<Utc>) -> Vec<Alert> {
+        let today = time::to_local(now).date();
+        let week = Period::Week.range(today);
+        let mut alerts = Vec::new();
+        for progress in budget::progress(self, week, Some(now)) {
+            let Some(percent) = progress.percent() else {
+                continue;
+            };
+            let Some(budget) = self
+                .budgets
+                .iter_mut()
+                .find(|budget| budget.is_for(&progress.subject) && budget.applies_on(today))
+            else {
+                continue;
+            };
+            budget.alerted.retain(|(monday, _)| *monday == week.start);
+            let passed: Vec<u32> = thresholds
+                .iter()
+                .copied()
+                .filter(|threshold| percent >= *threshold as f64)
+                .filter(|threshold| !budget.alerted.contains(&(week.start, *threshold)))
+                .collect();
+            let Some(&highest) = passed.iter().max() else {
+                continue;
+            };
+            budget
+                .alerted
+                .extend(passed.iter().map(|threshold| (week.start, *threshold)));
+            alerts.push(Alert {
+                threshold: highest,
+                progress,
+            });
+        }
+        alerts
+    }
+
+    /// The stopwatch belongs to a Pomodoro run while one is going on.
+    fn check_no_pomodoro(&self) -> Result<()> {
+        match &self.pomodoro {
+            Some(run) => Err(PlannerError::Invalid(format!(
+                "a pomodoro on {} is running, stop it with `pomodoro stop`",
+                run.subject
+            ))),
+            None => Ok(()),
+        }
+    }
+
+    /// Starts a Pomodoro run on a subject with its first work interval. With
+    /// `cycles` the run ends by itself after that many work intervals.
+    pub fn start_pomodoro(
+        &mut self,
+        subject: &str,
+        settings: PomodoroSettings,
+        cycles: Option<u32>,
+        now: DateTime<Utc>,
+    ) -> Result<()> {
+        settings.validate()?;
+        if cycles == Some(0) {
+            return Err(PlannerError::Invalid(
+                "a run needs at least 1 pomodoro".to_string(),
+            ));
+        }
+        self.check_no_pomodoro()?;
+        let session_id = self.start_session(subject, now)?;
+        self.pomodoro = Some(PomodoroRun {
+            subject: subject.trim().to_string(),
+            settings,
+            phase: Phase::Work,
+            phase_start: now,
+            completed: 0,
+            cycles,
+            session_id: Some(session_id),
+        });
+        Ok(())
+    }
+
+    /// Moves the run on through every phase that ended by `now`, so a run
+    /// left alone for an hour catches up in one go. Finished work intervals
+    /// end their stopwatch session when the interval ended, not at `now`.
+    pub fn advance_pomodoro(&mut self, now: DateTime<Utc>) -> Vec<Transition> {
+        let mut transitions = Vec::new();
+        while let Some(mut run) = self.pomodoro.take() {
+            let at = run.phase_end();
+            if at > now {
+                self.pomodoro = Some(run);
+                break;
+            }
+            match run.phase {
+                Phase::Work => {
+                    self.record_pomodoro(&run, at, false);
+                    run.completed += 1;
+                    run.session_id = None;
+                    if run.cycles == Some(run.completed) {
+                        transitions.push(Transition::Finished {
+                            at,
+                            completed: run.completed,
+                        });
+                        continue;
+                    }
+                    run.phase = run.next_break();
+                    run.phase_start = at;
+                    transitions.push(Transition::BreakStarted {
+                        at,
+                        completed: run.completed,
+                        phase: run.phase,
+                        minutes: run.phase_length().num_minutes() as u32,
+                    });
+                }
+                Phase::ShortBreak | Phase::LongBreak => {
+                    let id = self.next_id();
+                    self.sessions.push(Session {
+                        id,
+                        subject: run.subject.clone(),
+                        start: at,
+                        end: None,
+                    });
+                    run.phase = Phase::Work;
+                    run.phase_start = at;
+                    run.session_id = Some(id);
+                    transitions.push(Transition::WorkStarted {
+                        at,
+                        subject: run.subject.clone(),
+                    });
+                }
+            }
+            self.pomodoro = Some(run);
+        }
+        transitions
+    }
+
+    /// Ends the run. A work interval cut short is kept as an interrupted
+    /// pomodoro, and its time stays logged on the stopwatch.
+    pub fn stop_pomodoro(&mut self, now: DateTime<Utc>) -> Result<PomodoroRun> {
+        self.advance_pomodoro(now);
+        let run = self
+            .pomodoro
+            .take()
+            .ok_or_else(|| PlannerError::Invalid("no pomodoro is running".to_string()))?;
+        if run.phase == Phase::Work {
+            self.record_pomodoro(&run, now, true);
+        }
+        Ok(run)
+    }
+
+    /// Closes the session of the run's work interval at `end` and keeps the
+    /// interval as a pomodoro.
+    fn record_pomodoro(&mut self, run: &PomodoroRun, end: DateTime<Utc>, interrupted: bool) {
+        let Some(session_id) = run.session_id else {
+            return;
+        };
+        let Some(session) = self
+            .sessions
+            .iter_mut()
+            .find(|session| session.id == session_id)
+        else {
+            return;
+        };
+        let end = end.max(session.start);
+        session.end = Some(end);
+        let start = session.start;
+        let id = self.next_id();
+        self.pomodoros.push(Pomodoro {
+            id,
+            subject: run.subject.clone(),
+            start,
+            end,
+            interrupted,
+            session_id,
+        });
+    }
+
+    /// Sets a timer running out at `due`, optionally as the deadline of a todo.
+    pub fn add_timer(
+        &mut self,
+        label: &str,
+        due: DateTime<Utc>,
+        todo_id: Option<u64>,
+        now: DateTime<Utc>,
+    ) -> Result<u64> {
+        if label.trim().is_empty() {
+            return Err(PlannerError::Invalid("a timer needs a label".to_string()));
+        }
+        if due <= now {
+            return Err(PlannerError::Invalid(
+                "the timer has to run out in the future".to_string(),
+            ));
+        }
+        if let Some(todo_id) = todo_id {
+            self.todo(todo_id)?;
+        }
+        let id = self.next_id();
+        self.timers.push(Timer {
+            id,
+            label: label.trim().to_string(),
+            due,
+            created_at: now,
+            todo_id,
+            fired: false,
+            zone: Some(time::zone().name().to_string()),
+            daily: None,
+        });
+        Ok(id)
+    }
+
+    /// Sets a timer going off every day at the local time `at`.
+    pub fn add_daily_timer(
+        &mut self,
+        label: &str,
+        at: NaiveTime,
+        todo_id: Option<u64>,
+        now: DateTime<Utc>,
+    ) -> Result<u64> {
+        let due = timer::next_daily(at, now, &time::zone());
+        let id = self.add_timer(label, due, todo_id, now)?;
+        let timer = self.timers.last_mut().expect("just added");
+        timer.daily = Some(at);
+        Ok(id)
+    }
+
+    pub fn cancel_timer(&mut self, id: u64) -> Result<Timer> {
+        let index = self
+            .timers
+            .iter()
+            .position(|timer| timer.id == id)
+            .ok_or_else(|| PlannerError::NotFound(format!("timer #{}", id)))?;
+        Ok(self.timers.remove(index))
+    }
+
+    /// Timers that have not fired yet, soonest first.
+    pub fn pending_timers(&self) -> Vec<&Timer> {
+        let mut timers: Vec<&Timer> = self.timers.iter().filter(|timer| !timer.fired).collect();
+        timers.sort_by_key(|timer| (timer.due, timer.id));
+        timers
+    }
+
+    /// Marks every timer that ran out by `now` as fired and returns them.
+    /// Daily timers are set again for their next time instead.
+    pub fn fire_timers(&mut self, now: DateTime<Utc>) -> Vec<Timer> {
+        let zone = time::zone();
+        let mut fired = Vec::new();
+        for timer in &mut self.timers {
+            // A daily timer set in another zone goes off at its time in this
+            // one, so it keeps to the clock after travelling
+            if timer.daily.is_some() && timer.zone.as_deref() != Some(zone.name()) {
+                timer.due = timer.next_due(now, &zone).expect("daily");
+                timer.zone = Some(zone.name().to_string());
+            }
+            if !timer.is_due(now) {
+                continue;
+            }
+            let mut done = timer.clone();
+            done.fired = true;
+            fired.push(done);
+            match timer.next_due(now, &zone) {
+                Some(next) => timer.due = next,
+                None => timer.fired = true,
+            }
+        }
+        fired.sort_by_key(|timer| (timer.due, timer.id));
+        fired
+    }
+
+    /// Stores imported calendar events. Events already imported with the same
+    /// `UID` are replaced, so importing an updated file again is safe.
+    pub fn import_busy(&mut self, events: Vec<BusyEvent>) {
+        self.busy
+            .retain(|old| !events.iter().any(|new| new.uid == old.uid));
+        self.busy.extend(events);
+    }
+
+    /// Forgets every imported calendar event and returns how many there were.
+    pub fn clear_busy(&mut self) -> usize {
+        let count = self.busy.len();
+        self.busy.clear();
+        count
+    }
+
+    pub fn plan(&self, date: NaiveDate) -> Option<&Plan> {
+        self.plans.iter().find(|plan| plan.date == date)
+    }
+
+    pub fn plan_mut(&mut self, date: NaiveDate) -> Result<&mut Plan> {
+        self.plans
+            .iter_mut()
+            .find(|plan| plan.date == date)
+            .ok_or_else(|| PlannerError::NotFound(format!("plan for {}", date)))
+    }
+
+    /// Stores a plan, replacing any plan for the same day.
+    pub fn set_plan(&mut self, plan: Plan) {
+        self.plans.retain(|existing| existing.date != plan.date);
+        self.plans.push(plan);
+        self.plans.sort_by_key(|plan| plan.date);
+    }
+
+    /// Applies option `option` of conflict `conflict` (both numbered from 1,
+    /// as `plan conflicts` lists them) to the plan for `date`. The plan is
+    /// only replaced once the whole change checks out.
+    pub fn resolve_conflict(
+        &mut self,
+        date: NaiveDate,
+        checker: &ConflictChecker,
+        conflict: usize,
+        option: usize,
+    ) -> Result<Resolution> {
+        let plan = self
+            .plan(date)
+            .ok_or_else(|| PlannerError::NotFound(format!("plan for {}", date)))?;
+        let conflicts = checker.check(plan, self);
+        let found = conflict
+            .checked_sub(1)
+            .and_then(|index| conflicts.get(index))
+            .ok_or_else(|| PlannerError::NotFound(format!("conflict {}", conflict)))?;
+        let resolution = option
+            .checked_sub(1)
+            .and_then(|index| {
+                checker
+                    .resolutions(plan, self, found)
+                    .into_iter()
+                    .nth(index)
+            })
+            .ok_or_else(|| {
+                PlannerError::NotFound(format!("option {} of conflict {}", option, conflict))
+            })?;
+        let resolved = checker.apply(plan, self, &resolution)?;
+        self.set_plan(resolved);
+        Ok(resolution)
+    }
+
+    pub fn remove_plan(&mut self, date: NaiveDate) -> Result<Plan> {
+        let index = self
+            .plans
+            .iter()
+            .position(|plan| plan.date == date)
+            .ok_or_else(|| PlannerError::NotFound(format!("plan for {}", date)))?;
+        Ok(self.plans.remove(index))
+    }
+}
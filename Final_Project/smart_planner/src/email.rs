@@ -0,0 +1,249 @@
+// Sends reports by email over SMTP.
+use std::env;
+use std::thread;
+use std::time::Duration;
+
+use lettre::message::header::ContentType;
+use lettre::message::{Attachment, Mailbox, MultiPart, SinglePart};
+use lettre::transport::smtp::authentication::{Credentials, Mechanism};
+use lettre::transport::smtp::client::{Tls, TlsParameters};
+use lettre::{Message, SmtpTransport, Transport};
+use serde::{Deserialize, Serialize};
+
+use crate::error::{PlannerError, Result};
+use crate::report::{self, Report};
+
+/// How the connection to the SMTP server is protected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Security {
+    /// Plain text, only for servers on the same machine.
+    None,
+    /// Upgrade with STARTTLS and refuse servers that do not offer it (port 587).
+    #[default]
+    StartTls,
+    /// TLS from the first byte (port 465).
+    Tls,
+}
+
+/// An SMTP login mechanism.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AuthMechanism {
+    Plain,
+    Login,
+}
+
+fn default_port() -> u16 {
+    587
+}
+
+fn default_retries() -> u32 {
+    3
+}
+
+fn default_retry_delay_ms() -> u64 {
+    2000
+}
+
+fn default_mechanisms() -> Vec<AuthMechanism> {
+    vec![AuthMechanism::Plain, AuthMechanism::Login]
+}
+
+/// The `smtp` section of `config.json`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SmtpConfig {
+    pub host: String,
+    #[serde(default = "default_port")]
+    pub port: u16,
+    #[serde(default)]
+    pub security: Security,
+    /// Leave out for servers that do not need a login.
+    #[serde(default)]
+    pub username: Option<String>,
+    /// Better kept in `SMART_PLANNER_SMTP_PASSWORD` than in the file.
+    #[serde(default)]
+    pub password: Option<String>,
+    /// Mechanisms to try, in order.
+    #[serde(default = "default_mechanisms")]
+    pub auth: Vec<AuthMechanism>,
+    /// Sender, like `Planner <planner@example.com>`.
+    pub from: String,
+    /// Recipients used when none are given on the command line.
+    #[serde(default)]
+    pub to: Vec<String>,
+    /// How many times a failed delivery is tried again.
+    #[serde(default = "default_retries")]
+    pub retries: u32,
+    /// Wait before the first retry, doubled for every retry after it.
+    #[serde(default = "default_retry_delay_ms")]
+    pub retry_delay_ms: u64,
+}
+
+impl SmtpConfig {
+    /// A config for a server without TLS or login, as used in tests.
+    pub fn plain(host: &str, port: u16, from: &str) -> Self {
+        SmtpConfig {
+            host: host.to_string(),
+            port,
+            security: Security::None,
+            username: None,
+            password: None,
+            auth: default_mechanisms(),
+            from: from.to_string(),
+            to: Vec::new(),
+            retries: default_retries(),
+            retry_delay_ms: default_retry_delay_ms(),
+        }
+    }
+}
+
+/// The parts of a report email.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReportEmail {
+    pub subject: String,
+    pub text: String,
+    pub html: String,
+    /// Sent as `report.csv` when present.
+    pub csv: Option<String>,
+}
+
+impl ReportEmail {
+    pub fn from_report(report: &Report, attach_csv: bool) -> Self {
+        ReportEmail {
+            subject: format!(
+                "Smart Planner report for {} {}",
+                report.label, report.current.range
+            ),
+            text: report::text::render(report),
+            html: report::html::render(report),
+            csv: if attach_csv {
+                Some(report::csv::render(report))
+            } else {
+                None
+            },
+        }
+    }
+}
+
+fn mailbox(address: &str) -> Result<Mailbox> {
+    address
+        .parse()
+        .map_err(|_| PlannerError::Invalid(format!("'{}' is not an email address", address)))
+}
+
+/// Builds the MIME message: text and HTML as alternatives, plus the CSV
+/// attachment when there is one.
+pub fn build_message(config: &SmtpConfig, to: &[String], email: &ReportEmail) -> Result<Message> {
+    if to.is_empty() {
+        return Err(PlannerError::Invalid(
+            "no recipients, pass --recipient or set \"to\" in the smtp config".to_string(),
+        ));
+    }
+    let mut builder = Message::builder()
+        .from(mailbox(&config.from)?)
+        .subject(email.subject.clone());
+    for address in to {
+        builder = builder.to(mailbox(address)?);
+    }
+    let alternative = MultiPart::alternative()
+        .singlepart(
+            SinglePart::builder()
+                .header(ContentType::TEXT_PLAIN)
+                .body(email.text.clone()),
+        )
+        .singlepart(
+            SinglePart::builder()
+                .header(ContentType::TEXT_HTML)
+                .body(email.html.clone()),
+        );
+    let body = match &email.csv {
+        Some(csv) => MultiPart::mixed().multipart(alternative).singlepart(
+            Attachment::new("report.csv".to_string())
+                .body(csv.clone(), ContentType::parse("text/csv").unwrap()),
+        ),
+        None => alternative,
+    };
+    builder
+        .multipart(body)
+        .map_err(|e| PlannerError::Email(e.to_string()))
+}
+
+/// Sends messages through one SMTP server.
+pub struct Mailer {
+    config: SmtpConfig,
+    transport: SmtpTransport,
+}
+
+impl Mailer {
+    pub fn new(config: SmtpConfig) -> Result<Self> {
+        let tls = match config.security {
+            Security::None => Tls::None,
+            Security::StartTls => Tls::Required(tls_parameters(&config.host)?),
+            Security::Tls => Tls::Wrapper(tls_parameters(&config.host)?),
+        };
+        let mut builder = SmtpTransport::builder_dangerous(&config.host)
+            .port(config.port)
+            .tls(tls)
+            .timeout(Some(Duration::from_secs(30)));
+        if let Some(username) = &config.username {
+            let password = env::var("SMART_PLANNER_SMTP_PASSWORD")
+                .ok()
+                .or_else(|| config.password.clone())
+                .unwrap_or_default();
+            let mechanisms = config
+                .auth
+                .iter()
+                .map(|mechanism| match mechanism {
+                    AuthMechanism::Plain => Mechanism::Plain,
+                    AuthMechanism::Login => Mechanism::Login,
+                })
+                .collect();
+            builder = builder
+                .credentials(Credentials::new(username.clone(), password))
+                .authentication(mechanisms);
+        }
+        Ok(Mailer {
+            config,
+            transport: builder.build(),
+        })
+    }
+
+    /// Sends the email, trying again after transient failures such as a
+    /// `421` reply or a dropped connection. Returns how many attempts it took.
+    pub fn send(&self, to: &[String], email: &ReportEmail) -> Result<u32> {
+        let message = build_message(&self.config, to, email)?;
+        let mut delay = Duration::from_millis(self.config.retry_delay_ms);
+        let mut attempt = 1;
+        loop {
+            match self.transport.send(&message) {
+                Ok(_) => return Ok(attempt),
+                Err(e) if is_transient(&e) && attempt <= self.config.retries => {
+                    eprintln!("Sending failed ({}), trying again in {:?}", e, delay);
+                    thread::sleep(delay);
+                    delay *= 2;
+                    attempt += 1;
+                }
+                Err(e) => {
+                    return Err(PlannerError::Email(format!(
+                        "sending to {} failed after {} attempt(s): {}",
+                        self.config.host, attempt, e
+                    )))
+                }
+            }
+        }
+    }
+}
+
+fn tls_parameters(host: &str) -> Result<TlsParameters> {
+    TlsParameters::new(host.to_string()).map_err(|e| PlannerError::Email(e.to_string()))
+}
+
+/// A 4xx reply or a network problem may go away, a 5xx reply, a TLS failure
+/// or a bad message will not.
+fn is_transient(e: &lettre::transport::smtp::Error) -> bool {
+    if e.is_transient() || e.is_timeout() {
+        return true;
+    }
+    !(e.is_permanent() || e.is_client() || e.is_response() || e.is_tls())
+}
@@ -0,0 +1,79 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+/// Every error the planner can return.
+#[derive(Debug)]
+pub enum PlannerError {
+    /// Reading or writing a file failed.
+    Io(io::Error),
+    /// Stored or received JSON could not be parsed.
+    Json(serde_json::Error),
+    /// An HTTP request failed or returned an unexpected status.
+    Http(String),
+    /// A plan backend returned something we could not use.
+    Backend(String),
+    /// A storage backend failed or holds data we cannot read.
+    Storage(String),
+    /// An email could not be built or sent.
+    Email(String),
+    /// The planner is locked, or encrypted data could not be read.
+    Encryption(String),
+    /// The requested item does not exist.
+    NotFound(String),
+    /// The user gave a value the planner does not accept.
+    Invalid(String),
+}
+
+impl fmt::Display for PlannerError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PlannerError::Io(e) => write!(f, "I/O error: {}", e),
+            PlannerError::Json(e) => write!(f, "JSON error: {}", e),
+            PlannerError::Http(message) => write!(f, "HTTP error: {}", message),
+            PlannerError::Backend(message) => write!(f, "plan backend error: {}", message),
+            PlannerError::Storage(message) => write!(f, "storage error: {}", message),
+            PlannerError::Email(message) => write!(f, "email error: {}", message),
+            PlannerError::Encryption(message) => write!(f, "encryption error: {}", message),
+            PlannerError::NotFound(what) => write!(f, "{} not found", what),
+            PlannerError::Invalid(message) => write!(f, "invalid input: {}", message),
+        }
+    }
+}
+
+impl Error for PlannerError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            PlannerError::Io(e) => Some(e),
+            PlannerError::Json(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for PlannerError {
+    fn from(e: io::Error) -> Self {
+        PlannerError::Io(e)
+    }
+}
+
+impl From<serde_json::Error> for PlannerError {
+    fn from(e: serde_json::Error) -> Self {
+        PlannerError::Json(e)
+    }
+}
+
+impl From<reqwest::Error> for PlannerError {
+    fn from(e: reqwest::Error) -> Self {
+        PlannerError::Http(e.to_string())
+    }
+}
+
+impl From<sqlite::Error> for PlannerError {
+    fn from(e: sqlite::Error) -> Self {
+        PlannerError::Storage(e.to_string())
+    }
+}
+
+/// Shorthand used across the crate.
+pub type Result<T> = std::result::Result<T, PlannerError>;
@@ -0,0 +1,559 @@
+// Audit log of every change saved to the planner. Each save appends one event
+// to `history/events.jsonl` listing what changed, field by field, and who did
+// it. The planner is loaded by replaying the events, and rebuilt the same way
+// as it was at any moment; a full snapshot every `SNAPSHOT_EVERY` events keeps
+// that replay short. The storage backend keeps a copy of the latest state for
+// sync, backups and other programs.
+use std::fmt;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::data::PlannerData;
+use crate::error::{PlannerError, Result};
+use crate::time;
+
+pub mod diff;
+
+/// A snapshot is written after every this many events.
+pub const SNAPSHOT_EVERY: u64 = 50;
+
+/// Who made changes found in the data but not in the history, like those
+/// synced from another computer or made by hand in the data file.
+pub const ELSEWHERE: &str = "elsewhere";
+
+/// The kinds of things the planner stores.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Kind {
+    Todo,
+    Project,
+    Routine,
+    RoutineCheck,
+    Plan,
+    Session,
+    Timer,
+    Busy,
+    PomodoroRun,
+    Pomodoro,
+    Budget,
+}
+
+impl Kind {
+    pub const ALL: [Kind; 11] = [
+        Kind::Todo,
+        Kind::Project,
+        Kind::Routine,
+        Kind::RoutineCheck,
+        Kind::Plan,
+        Kind::Session,
+        Kind::Timer,
+        Kind::Busy,
+        Kind::PomodoroRun,
+        Kind::Pomodoro,
+        Kind::Budget,
+    ];
+
+    /// The field of `PlannerData` holding this kind.
+    fn field(self) -> &'static str {
+        match self {
+            Kind::Todo => "todos",
+            Kind::Project => "projects",
+            Kind::Routine => "routines",
+            Kind::RoutineCheck => "routine_checks",
+            Kind::Plan => "plans",
+            Kind::Session => "sessions",
+            Kind::Timer => "timers",
+            Kind::Busy => "busy",
+            Kind::PomodoroRun => "pomodoro",
+            Kind::Pomodoro => "pomodoros",
+            Kind::Budget => "budgets",
+        }
+    }
+
+    /// Stored as an optional value rather than a list.
+    fn is_single(self) -> bool {
+        self == Kind::PomodoroRun
+    }
+
+    /// What tells two items of this kind apart. Busy times have no id, so
+    /// the whole item is their key.
+    fn key(self, item: &Value) -> Value {
+        match self {
+            Kind::Plan => item["date"].clone(),
+            Kind::PomodoroRun => Value::from("current"),
+            Kind::RoutineCheck => {
+                serde_json::json!({"routine_id": item["routine_id"], "date": item["date"]})
+            }
+            Kind::Busy => item.clone(),
+            _ => item["id"].clone(),
+        }
+    }
+
+    /// How to refer to the item with `key`.
+    fn name(self, key: &Value) -> String {
+        match self {
+            Kind::RoutineCheck => {
+                format!("routine #{} on {}", key["routine_id"], show(&key["date"]))
+            }
+            Kind::Plan => format!("plan for {}", show(key)),
+            Kind::Busy => format!("busy time \"{}\"", show(&key["summary"])),
+            Kind::PomodoroRun => "pomodoro run".to_string(),
+            _ => format!("{} #{}", self, key),
+        }
+    }
+}
+
+impl fmt::Display for Kind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Kind::Todo => "todo",
+            Kind::Project => "project",
+            Kind::Routine => "routine",
+            Kind::RoutineCheck => "routine check",
+            Kind::Plan => "plan",
+            Kind::Session => "session",
+            Kind::Timer => "timer",
+            Kind::Busy => "busy time",
+            Kind::PomodoroRun => "pomodoro run",
+            Kind::Pomodoro => "pomodoro",
+            Kind::Budget => "budget",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for Kind {
+    type Err = PlannerError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let name = s.trim().to_lowercase().replace(['-', ' '], "_");
+        let name = name.strip_suffix('s').unwrap_or(&name);
+        Kind::ALL
+            .into_iter()
+            .find(|kind| serde_json::to_value(kind).ok() == Some(Value::from(name)))
+            .ok_or_else(|| PlannerError::Invalid(format!("'{}' is not a kind of item", s)))
+    }
+}
+
+/// One field of an item before and after a change.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FieldChange {
+    pub field: String,
+    pub before: Value,
+    pub after: Value,
+}
+
+impl fmt::Display for FieldChange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} {} → {}",
+            self.field.replace('_', " "),
+            show(&self.before),
+            show(&self.after)
+        )
+    }
+}
+
+/// A change to one item. Items are kept as JSON, the same way they are
+/// stored.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "change", rename_all = "snake_case")]
+pub enum Change {
+    Created {
+        kind: Kind,
+        /// Where the item went in its list.
+        index: usize,
+        item: Value,
+    },
+    Updated {
+        kind: Kind,
+        key: Value,
+        fields: Vec<FieldChange>,
+    },
+    Removed {
+        kind: Kind,
+        key: Value,
+        /// Which of the items sharing `key` it was, counting from 0.
+        #[serde(default)]
+        nth: usize,
+        /// The item as it was, so the history can still show it.
+        item: Value,
+    },
+    /// The list was put in a new order, given by the keys of its items.
+    Reordered { kind: Kind, keys: Vec<Value> },
+}
+
+impl Change {
+    pub fn kind(&self) -> Kind {
+        match self {
+            Change::Created { kind, .. }
+            | Change::Updated { kind, .. }
+            | Change::Removed { kind, .. }
+            | Change::Reordered { kind, .. } => *kind,
+        }
+    }
+
+    /// The key of the item changed, if it is about one item.
+    pub fn key(&self) -> Option<Value> {
+        match self {
+            Change::Created { kind, item, .. } => Some(kind.key(item)),
+            Change::Updated { key, .. } | Change::Removed { key, .. } => Some(key.clone()),
+            Change::Reordered { .. } => None,
+        }
+    }
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Change::Created { kind, item, .. } => {
+                let verb = match kind {
+                    Kind::RoutineCheck => "checked",
+                    Kind::Busy => "imported",
+                    Kind::Session | Kind::PomodoroRun => "started",
+                    Kind::Timer => "set",
+                    _ => "created",
+                };
+                write!(f, "{} {}", kind.name(&kind.key(item)), verb)?;
+                match label(item) {
+                    Some(label) if !matches!(kind, Kind::Busy) => write!(f, ": {}", label),
+                    _ => Ok(()),
+                }
+            }
+            Change::Updated { kind, key, fields } => {
+                let became = |field: &str, check: fn(&Value, &Value) -> bool| {
+                    fields
+                        .iter()
+                        .any(|change| change.field == field && check(&change.before, &change.after))
+                };
+                let verb = match kind {
+                    Kind::Todo if became("done", |_, after| *after == Value::Bool(true)) => {
+                        Some("completed")
+                    }
+                    Kind::Session if became("end", |before, _| before.is_null()) => Some("stopped"),
+                    Kind::Timer if became("fired", |_, after| *after == Value::Bool(true)) => {
+                        Some("fired")
+                    }
+                    _ => None,
+                };
+                match verb {
+                    Some(verb) => write!(f, "{} {}", kind.name(key), verb),
+                    None => {
+                        let fields: Vec<String> =
+                            fields.iter().map(|change| change.to_string()).collect();
+                        write!(f, "{} changed: {}", kind.name(key), fields.join(", "))
+                    }
+                }
+            }
+            Change::Removed {
+                kind, key, item, ..
+            } => {
+                let verb = match kind {
+                    Kind::RoutineCheck => "unchecked",
+                    Kind::Busy => "removed",
+                    Kind::PomodoroRun => "ended",
+                    _ => "deleted",
+                };
+                write!(f, "{} {}", kind.name(key), verb)?;
+                match label(item) {
+                    Some(label) if !matches!(kind, Kind::Busy | Kind::PomodoroRun) => {
+                        write!(f, ": {}", label)
+                    }
+                    _ => Ok(()),
+                }
+            }
+            Change::Reordered { kind, .. } => write!(f, "{} list reordered", kind),
+        }
+    }
+}
+
+/// The title, name, subject or label of an item.
+fn label(item: &Value) -> Option<&str> {
+    ["title", "name", "label", "subject", "summary"]
+        .iter()
+        .find_map(|field| item[*field].as_str())
+}
+
+/// A stored value the way the user would write it.
+fn show(value: &Value) -> String {
+    match value {
+        Value::Null => "none".to_string(),
+        Value::String(text) => match DateTime::parse_from_rfc3339(text) {
+            Ok(instant) => time::format_local(instant.with_timezone(&Utc)),
+            Err(_) => text.clone(),
+        },
+        Value::Array(list) => format!("{} items", list.len()),
+        Value::Object(_) => "{…}".to_string(),
+        other => other.to_string(),
+    }
+}
+
+/// One save of the planner.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Event {
+    /// Numbered from 1 in the order they were saved.
+    pub seq: u64,
+    pub at: DateTime<Utc>,
+    pub actor: String,
+    pub changes: Vec<Change>,
+    /// `PlannerData::next_id` after the change.
+    pub next_id: u64,
+    /// Fingerprint of the data after the change, to notice changes made
+    /// behind the history's back.
+    pub state_hash: String,
+}
+
+/// The whole planner after event `seq`. Snapshot 0 holds the data found when
+/// the history was started.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub seq: u64,
+    pub at: DateTime<Utc>,
+    pub data: PlannerData,
+}
+
+/// The `history` folder inside the planner folder.
+pub struct History {
+    dir: PathBuf,
+}
+
+impl History {
+    pub fn new(dir: &Path) -> Self {
+        History {
+            dir: dir.to_path_buf(),
+        }
+    }
+
+    fn events_path(&self) -> PathBuf {
+        self.dir.join("events.jsonl")
+    }
+
+    fn snapshot_path(&self, seq: u64) -> PathBuf {
+        self.dir.join(format!("snapshot-{:08}.json", seq))
+    }
+
+    /// Every event, oldest first. A last line without its newline is an
+    /// event still being written, and is left out.
+    pub fn events(&self) -> Result<Vec<Event>> {
+        let path = self.events_path();
+        if !path.exists() {
+            return Ok(Vec::new());
+        }
+        let text = fs::read_to_string(path)?;
+        let mut events = Vec::new();
+        for line in text
+            .split_inclusive('\n')
+            .filter_map(|line| line.strip_suffix('\n'))
+            .filter(|line| !line.trim().is_empty())
+        {
+            events.push(serde_json::from_str(line)?);
+        }
+        Ok(events)
+    }
+
+    /// Records the save that turned `before` into `after`. Returns the number
+    /// of the new event, or `None` when nothing changed.
+    pub fn record(
+        &self,
+        before: &PlannerData,
+        after: &PlannerData,
+        actor: &str,
+        at: DateTime<Utc>,
+    ) -> Result<Option<u64>> {
+        let mut seq = self.catch_up(before, at)?;
+        let changes = diff::diff(before, after)?;
+        if changes.is_empty() && before.next_id == after.next_id {
+            return Ok(None);
+        }
+        seq += 1;
+        self.append(&Event {
+            seq,
+            at,
+            actor: actor.to_string(),
+            changes,
+            next_id: after.next_id,
+            state_hash: hash(after)?,
+        })?;
+        if seq.is_multiple_of(SNAPSHOT_EVERY) {
+            self.write_snapshot(&Snapshot {
+                seq,
+                at,
+                data: after.clone(),
+            })?;
+        }
+        Ok(Some(seq))
+    }
+
+    /// The planner rebuilt from the latest snapshot and the events after it,
+    /// without writing anything. `stored` is what the storage backend holds;
+    /// when it has changes the history did not see yet, like a sync from
+    /// another computer or a save still being written, it is returned as it
+    /// is and the next save records them. Fails when the replay does not come
+    /// out as the last event says it should.
+    pub fn load(&self, stored: &PlannerData) -> Result<PlannerData> {
+        let (seq, known_hash) = self.last_known()?;
+        if known_hash != hash(stored)? {
+            return Ok(stored.clone());
+        }
+        let data = self.replay(None)?;
+        if hash(&data)? != known_hash {
+            return Err(PlannerError::Storage(format!(
+                "replaying the history up to event {} does not give the saved data; \
+                 move the history folder aside to start it again from the data",
+                seq
+            )));
+        }
+        Ok(data)
+    }
+
+    /// The number of the last event and the fingerprint of the data after it.
+    fn last_known(&self) -> Result<(u64, String)> {
+        let events = self.events()?;
+        match events.last() {
+            Some(event) => Ok((event.seq, event.state_hash.clone())),
+            None => match self.read_snapshot(0)? {
+                Some(snapshot) => Ok((0, hash(&snapshot.data)?)),
+                None => Ok((0, hash(&PlannerData::default())?)),
+            },
+        }
+    }
+
+    /// Makes sure the history ends with `data`, recording the changes it
+    /// missed under `ELSEWHERE`. Returns the number of the last event.
+    fn catch_up(&self, data: &PlannerData, at: DateTime<Utc>) -> Result<u64> {
+        let (mut seq, known_hash) = self.last_known()?;
+        if known_hash == hash(data)? {
+            return Ok(seq);
+        }
+        if seq == 0 && !self.snapshot_path(0).exists() {
+            // The planner was used before the history existed
+            self.write_snapshot(&Snapshot {
+                seq: 0,
+                at,
+                data: data.clone(),
+            })?;
+        } else {
+            let known = self.replay(None)?;
+            seq += 1;
+            self.append(&Event {
+                seq,
+                at,
+                actor: ELSEWHERE.to_string(),
+                changes: diff::diff(&known, data)?,
+                next_id: data.next_id,
+                state_hash: hash(data)?,
+            })?;
+        }
+        Ok(seq)
+    }
+
+    /// The planner as the history says it is now.
+    pub fn current(&self) -> Result<PlannerData> {
+        self.replay(None)
+    }
+
+    /// The planner as it was at `at`, after every event up to then.
+    pub fn state_at(&self, at: DateTime<Utc>) -> Result<PlannerData> {
+        self.replay(Some(at))
+    }
+
+    /// Starts from the latest snapshot taken by `until` and replays the
+    /// events after it.
+    fn replay(&self, until: Option<DateTime<Utc>>) -> Result<PlannerData> {
+        let mut start = None;
+        for seq in self.snapshot_seqs()?.into_iter().rev() {
+            let snapshot = self
+                .read_snapshot(seq)?
+                .ok_or_else(|| PlannerError::Storage(format!("snapshot {} disappeared", seq)))?;
+            if until.is_none_or(|until| snapshot.at <= until) {
+                start = Some(snapshot);
+                break;
+            }
+            if seq == 0 {
+                return Err(PlannerError::Invalid(format!(
+                    "the history starts at {}",
+                    time::format_local(snapshot.at)
+                )));
+            }
+        }
+        let (from, data) = start.map_or((0, PlannerData::default()), |snapshot| {
+            (snapshot.seq, snapshot.data)
+        });
+
+        let mut state = serde_json::to_value(data)?;
+        for event in self.events()?.into_iter().filter(|event| event.seq > from) {
+            if until.is_some_and(|until| event.at > until) {
+                break;
+            }
+            for change in &event.changes {
+                diff::apply(&mut state, change)?;
+            }
+            state["next_id"] = Value::from(event.next_id);
+        }
+        Ok(serde_json::from_value(state)?)
+    }
+
+    fn append(&self, event: &Event) -> Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.events_path())?;
+        // One write, so readers never see half an event followed by another
+        file.write_all(format!("{}\n", serde_json::to_string(event)?).as_bytes())?;
+        Ok(())
+    }
+
+    /// Snapshot numbers, lowest first.
+    fn snapshot_seqs(&self) -> Result<Vec<u64>> {
+        if !self.dir.exists() {
+            return Ok(Vec::new());
+        }
+        let mut seqs = Vec::new();
+        for entry in fs::read_dir(&self.dir)? {
+            let name = entry?.file_name();
+            let seq = name
+                .to_str()
+                .and_then(|name| name.strip_prefix("snapshot-"))
+                .and_then(|name| name.strip_suffix(".json"))
+                .and_then(|seq| seq.parse::<u64>().ok());
+            seqs.extend(seq);
+        }
+        seqs.sort_unstable();
+        Ok(seqs)
+    }
+
+    fn read_snapshot(&self, seq: u64) -> Result<Option<Snapshot>> {
+        let path = self.snapshot_path(seq);
+        if !path.exists() {
+            return Ok(None);
+        }
+        Ok(Some(serde_json::from_str(&fs::read_to_string(path)?)?))
+    }
+
+    fn write_snapshot(&self, snapshot: &Snapshot) -> Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        let path = self.snapshot_path(snapshot.seq);
+        let tmp = path.with_extension("json.tmp");
+        fs::write(&tmp, serde_json::to_string(snapshot)?)?;
+        fs::rename(tmp, path)?;
+        Ok(())
+    }
+}
+
+/// FNV-1a of the data as JSON, which stays the same between builds.
+fn hash(data: &PlannerData) -> Result<String> {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in serde_json::to_vec(data)? {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    Ok(format!("{:016x}", hash))
+}
@@ -0,0 +1,178 @@
+// Turning two states of the planner into the changes between them, and
+// applying those changes again. Items are compared as JSON, so a new field on
+// a todo or a new kind of item needs no code here beyond a `Kind`.
+use serde_json::{Map, Value};
+
+use super::{Change, FieldChange, Kind};
+use crate::data::PlannerData;
+use crate::error::{PlannerError, Result};
+
+/// The changes that turn `before` into `after`, in the order `apply` needs
+/// them.
+pub fn diff(before: &PlannerData, after: &PlannerData) -> Result<Vec<Change>> {
+    let before = serde_json::to_value(before)?;
+    let after = serde_json::to_value(after)?;
+    let mut changes = Vec::new();
+    for kind in Kind::ALL {
+        let old = items(&before, kind);
+        let new = items(&after, kind);
+        if old != new {
+            diff_items(kind, &old, &new, &mut changes)?;
+        }
+    }
+    Ok(changes)
+}
+
+fn diff_items(kind: Kind, old: &[Value], new: &[Value], changes: &mut Vec<Change>) -> Result<()> {
+    let old_keys = keys(kind, old);
+    let new_keys = keys(kind, new);
+    let start = changes.len();
+
+    // From the back, so the positions of earlier copies of a key still hold
+    for (position, key) in old_keys.iter().enumerate().rev() {
+        if !new_keys.contains(key) {
+            changes.push(Change::Removed {
+                kind,
+                key: key.0.clone(),
+                nth: key.1,
+                item: old[position].clone(),
+            });
+        }
+    }
+    for (index, key) in new_keys.iter().enumerate() {
+        if !old_keys.contains(key) {
+            changes.push(Change::Created {
+                kind,
+                index,
+                item: new[index].clone(),
+            });
+        }
+    }
+    for (index, key) in new_keys.iter().enumerate() {
+        if let Some(position) = old_keys.iter().position(|old_key| old_key == key) {
+            let fields = field_changes(&old[position], &new[index]);
+            if !fields.is_empty() {
+                changes.push(Change::Updated {
+                    kind,
+                    key: key.0.clone(),
+                    fields,
+                });
+            }
+        }
+    }
+
+    // Whatever the changes above leave out of place was moved, like plans
+    // sorted by date
+    let mut replayed = old.to_vec();
+    for change in &changes[start..] {
+        apply_items(&mut replayed, change)?;
+    }
+    if replayed != new {
+        changes.push(Change::Reordered {
+            kind,
+            keys: new_keys.into_iter().map(|(key, _)| key).collect(),
+        });
+    }
+    Ok(())
+}
+
+/// The fields of an item that differ, in the order they are stored.
+fn field_changes(old: &Value, new: &Value) -> Vec<FieldChange> {
+    let empty = Map::new();
+    let old = old.as_object().unwrap_or(&empty);
+    let new = new.as_object().unwrap_or(&empty);
+    let mut names: Vec<&String> = new.keys().collect();
+    names.extend(old.keys().filter(|name| !new.contains_key(*name)));
+    names
+        .into_iter()
+        .filter_map(|name| {
+            let before = old.get(name).cloned().unwrap_or(Value::Null);
+            let after = new.get(name).cloned().unwrap_or(Value::Null);
+            (before != after).then(|| FieldChange {
+                field: name.clone(),
+                before,
+                after,
+            })
+        })
+        .collect()
+}
+
+/// Applies one change to the planner data as JSON.
+pub fn apply(state: &mut Value, change: &Change) -> Result<()> {
+    let kind = change.kind();
+    let mut list = items(state, kind);
+    apply_items(&mut list, change)?;
+    state[kind.field()] = if kind.is_single() {
+        list.pop().unwrap_or(Value::Null)
+    } else {
+        Value::Array(list)
+    };
+    Ok(())
+}
+
+fn apply_items(list: &mut Vec<Value>, change: &Change) -> Result<()> {
+    let kind = change.kind();
+    let missing = |key: &Value| {
+        PlannerError::Storage(format!(
+            "the history changes {} {}, which it never created",
+            kind, key
+        ))
+    };
+    match change {
+        Change::Created { index, item, .. } => {
+            list.insert((*index).min(list.len()), item.clone());
+        }
+        Change::Updated { key, fields, .. } => {
+            let position = position(kind, list, key, 0).ok_or_else(|| missing(key))?;
+            let item = list[position].as_object_mut().ok_or_else(|| missing(key))?;
+            for change in fields {
+                item.insert(change.field.clone(), change.after.clone());
+            }
+        }
+        Change::Removed { key, nth, .. } => {
+            let position = position(kind, list, key, *nth).ok_or_else(|| missing(key))?;
+            list.remove(position);
+        }
+        Change::Reordered { keys, .. } => {
+            let mut rest = std::mem::take(list);
+            for key in keys {
+                let position = rest
+                    .iter()
+                    .position(|item| kind.key(item) == *key)
+                    .ok_or_else(|| missing(key))?;
+                list.push(rest.remove(position));
+            }
+            list.append(&mut rest);
+        }
+    }
+    Ok(())
+}
+
+/// The items of one kind, whether stored as a list or as a single optional
+/// value.
+fn items(state: &Value, kind: Kind) -> Vec<Value> {
+    match &state[kind.field()] {
+        Value::Array(list) => list.clone(),
+        Value::Null => Vec::new(),
+        single => vec![single.clone()],
+    }
+}
+
+/// The key of each item, with how many items before it share that key.
+fn keys(kind: Kind, list: &[Value]) -> Vec<(Value, usize)> {
+    let mut keys: Vec<(Value, usize)> = Vec::with_capacity(list.len());
+    for item in list {
+        let key = kind.key(item);
+        let nth = keys.iter().filter(|(other, _)| *other == key).count();
+        keys.push((key, nth));
+    }
+    keys
+}
+
+fn position(kind: Kind, list: &[Value], key: &Value, nth: usize) -> Option<usize> {
+    list.iter()
+        .enumerate()
+        .filter(|(_, item)| kind.key(item) == *key)
+        .nth(nth)
+        .map(|(position, _)| position)
+}
@@ -0,0 +1,348 @@
+// Reading and writing iCalendar (RFC 5545) files. This file holds the line
+// format: folding, escaping, properties and nested components. The modules
+// below turn calendars into busy events and planner data into calendars.
+use chrono::{Duration, NaiveDate, NaiveDateTime};
+
+use crate::error::{PlannerError, Result};
+
+pub mod export;
+pub mod import;
+pub mod recur;
+pub mod timezone;
+
+pub use export::{export, ExportOptions};
+pub use import::busy_events;
+pub use recur::Recurrence;
+pub use timezone::Zone;
+
+/// One `NAME;PARAM=value:value` line.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Property {
+    /// Upper case, like `DTSTART`.
+    pub name: String,
+    /// Parameter names in upper case, values without quotes.
+    pub params: Vec<(String, String)>,
+    /// The raw value, still escaped.
+    pub value: String,
+}
+
+impl Property {
+    pub fn param(&self, name: &str) -> Option<&str> {
+        self.params
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// The value as text, with escapes like `\n` and `\,` undone.
+    pub fn text(&self) -> String {
+        unescape(&self.value)
+    }
+}
+
+/// A `BEGIN:NAME` .. `END:NAME` block.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Component {
+    pub name: String,
+    pub properties: Vec<Property>,
+    pub components: Vec<Component>,
+}
+
+impl Component {
+    pub fn new(name: &str) -> Self {
+        Component {
+            name: name.to_string(),
+            properties: Vec::new(),
+            components: Vec::new(),
+        }
+    }
+
+    pub fn property(&self, name: &str) -> Option<&Property> {
+        self.properties
+            .iter()
+            .find(|property| property.name == name)
+    }
+
+    /// Every property with this name, for ones that may repeat like `EXDATE`.
+    pub fn all(&self, name: &str) -> impl Iterator<Item = &Property> + '_ {
+        let name = name.to_string();
+        self.properties
+            .iter()
+            .filter(move |property| property.name == name)
+    }
+
+    pub fn text(&self, name: &str) -> Option<String> {
+        self.property(name).map(Property::text)
+    }
+
+    /// Nested components with this name.
+    pub fn children(&self, name: &str) -> impl Iterator<Item = &Component> + '_ {
+        let name = name.to_string();
+        self.components
+            .iter()
+            .filter(move |component| component.name == name)
+    }
+
+    /// Adds a property whose value is already in iCalendar form.
+    pub fn push(&mut self, name: &str, value: &str) {
+        self.properties.push(Property {
+            name: name.to_string(),
+            params: Vec::new(),
+            value: value.to_string(),
+        });
+    }
+
+    /// Adds a text property, escaping it.
+    pub fn push_text(&mut self, name: &str, text: &str) {
+        self.push(name, &escape(text));
+    }
+
+    /// Writes the component with CRLF line ends and lines folded at 75 bytes.
+    pub fn write(&self, out: &mut String) {
+        write_line(out, &format!("BEGIN:{}", self.name));
+        for property in &self.properties {
+            let mut line = property.name.clone();
+            for (key, value) in &property.params {
+                line.push_str(&format!(";{}={}", key, value));
+            }
+            line.push(':');
+            line.push_str(&property.value);
+            write_line(out, &line);
+        }
+        for component in &self.components {
+            component.write(out);
+        }
+        write_line(out, &format!("END:{}", self.name));
+    }
+}
+
+/// Parses a file and returns its `VCALENDAR` component.
+pub fn parse(text: &str) -> Result<Component> {
+    let mut stack: Vec<Component> = Vec::new();
+    let mut calendar = None;
+    for (number, line) in unfold(text).iter().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let property = parse_line(line)
+            .ok_or_else(|| invalid(format!("line {} is not a property: {}", number + 1, line)))?;
+        match property.name.as_str() {
+            "BEGIN" => stack.push(Component::new(&property.value.to_uppercase())),
+            "END" => {
+                let component = stack
+                    .pop()
+                    .filter(|open| open.name == property.value.to_uppercase())
+                    .ok_or_else(|| {
+                        invalid(format!(
+                            "unexpected END:{} on line {}",
+                            property.value,
+                            number + 1
+                        ))
+                    })?;
+                match stack.last_mut() {
+                    Some(parent) => parent.components.push(component),
+                    None if component.name == "VCALENDAR" => calendar = Some(component),
+                    None => {}
+                }
+            }
+            _ => {
+                if let Some(open) = stack.last_mut() {
+                    open.properties.push(property);
+                }
+            }
+        }
+    }
+    if let Some(open) = stack.last() {
+        return Err(invalid(format!("{} is never closed", open.name)));
+    }
+    calendar.ok_or_else(|| invalid("the file holds no VCALENDAR".to_string()))
+}
+
+fn invalid(message: String) -> PlannerError {
+    PlannerError::Invalid(format!("calendar file: {}", message))
+}
+
+/// Joins folded lines: a line starting with a space or tab continues the one
+/// before it.
+pub fn unfold(text: &str) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    for line in text.split('\n') {
+        let line = line.strip_suffix('\r').unwrap_or(line);
+        match (line.chars().next(), lines.last_mut()) {
+            (Some(' ') | Some('\t'), Some(last)) => last.push_str(&line[1..]),
+            _ => lines.push(line.to_string()),
+        }
+    }
+    lines
+}
+
+fn parse_line(line: &str) -> Option<Property> {
+    // The value starts at the first colon that is not inside a quoted parameter
+    let mut quoted = false;
+    let colon = line.char_indices().find_map(|(index, c)| match c {
+        '"' => {
+            quoted = !quoted;
+            None
+        }
+        ':' if !quoted => Some(index),
+        _ => None,
+    })?;
+    let (head, value) = (&line[..colon], &line[colon + 1..]);
+    let mut parts = split_unquoted(head, ';').into_iter();
+    let name = parts.next()?.trim().to_uppercase();
+    if name.is_empty() {
+        return None;
+    }
+    let params = parts
+        .filter_map(|part| {
+            let (key, value) = part.split_once('=')?;
+            Some((key.trim().to_uppercase(), value.replace('"', "")))
+        })
+        .collect();
+    Some(Property {
+        name,
+        params,
+        value: value.to_string(),
+    })
+}
+
+fn split_unquoted(text: &str, separator: char) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut quoted = false;
+    let mut start = 0;
+    for (index, c) in text.char_indices() {
+        if c == '"' {
+            quoted = !quoted;
+        } else if c == separator && !quoted {
+            parts.push(&text[start..index]);
+            start = index + 1;
+        }
+    }
+    parts.push(&text[start..]);
+    parts
+}
+
+fn write_line(out: &mut String, line: &str) {
+    // Fold at 75 bytes without cutting a character in half
+    let mut width = 0;
+    for c in line.chars() {
+        if width + c.len_utf8() > 75 {
+            out.push_str("\r\n ");
+            width = 1;
+        }
+        out.push(c);
+        width += c.len_utf8();
+    }
+    out.push_str("\r\n");
+}
+
+/// Escapes text for a property value.
+pub fn escape(text: &str) -> String {
+    let mut escaped = String::new();
+    for c in text.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            ';' => escaped.push_str("\\;"),
+            ',' => escaped.push_str("\\,"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+pub fn unescape(text: &str) -> String {
+    let mut unescaped = String::new();
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') | Some('N') => unescaped.push('\n'),
+            Some(other) => unescaped.push(other),
+            None => unescaped.push('\\'),
+        }
+    }
+    unescaped
+}
+
+/// Parses a `DATE` value like `20240304`.
+pub fn parse_date(value: &str) -> Result<NaiveDate> {
+    NaiveDate::parse_from_str(value.trim(), "%Y%m%d")
+        .map_err(|_| invalid(format!("'{}' is not a date", value)))
+}
+
+/// Parses a `DATE-TIME` value like `20240304T090000`, telling whether it
+/// ended in `Z` (UTC).
+pub fn parse_date_time(value: &str) -> Result<(NaiveDateTime, bool)> {
+    let value = value.trim();
+    let (local, utc) = match value.strip_suffix('Z') {
+        Some(local) => (local, true),
+        None => (value, false),
+    };
+    let parsed = NaiveDateTime::parse_from_str(local, "%Y%m%dT%H%M%S")
+        .map_err(|_| invalid(format!("'{}' is not a date and time", value)))?;
+    Ok((parsed, utc))
+}
+
+/// Parses a `DURATION` value like `PT1H30M`, `P2D` or `-P1W`.
+pub fn parse_duration(value: &str) -> Result<Duration> {
+    let error = || invalid(format!("'{}' is not a duration", value));
+    let value = value.trim();
+    let (sign, rest) = match value.as_bytes().first() {
+        Some(b'-') => (-1, &value[1..]),
+        Some(b'+') => (1, &value[1..]),
+        _ => (1, value),
+    };
+    let rest = rest.strip_prefix('P').ok_or_else(error)?;
+    let mut total = Duration::zero();
+    let mut number = String::new();
+    let mut in_time = false;
+    for c in rest.chars() {
+        match c {
+            '0'..='9' => number.push(c),
+            'T' => in_time = true,
+            unit => {
+                let amount: i64 = number.parse().map_err(|_| error())?;
+                number.clear();
+                let part = match (unit, in_time) {
+                    ('W', false) => Duration::try_weeks(amount),
+                    ('D', false) => Duration::try_days(amount),
+                    ('H', true) => Duration::try_hours(amount),
+                    ('M', true) => Duration::try_minutes(amount),
+                    ('S', true) => Duration::try_seconds(amount),
+                    _ => return Err(error()),
+                };
+                total = part
+                    .and_then(|part| total.checked_add(&part))
+                    .ok_or_else(error)?;
+            }
+        }
+    }
+    if !number.is_empty() {
+        return Err(error());
+    }
+    Ok(total * sign)
+}
+
+/// Formats a duration as `PT1H30M` (or `PT0S`).
+pub fn format_duration(duration: Duration) -> String {
+    let seconds = duration.num_seconds();
+    let sign = if seconds < 0 { "-" } else { "" };
+    let seconds = seconds.abs();
+    let (hours, minutes, seconds) = (seconds / 3600, seconds / 60 % 60, seconds % 60);
+    let mut text = format!("{}PT", sign);
+    if hours > 0 {
+        text.push_str(&format!("{}H", hours));
+    }
+    if minutes > 0 {
+        text.push_str(&format!("{}M", minutes));
+    }
+    if seconds > 0 || (hours == 0 && minutes == 0) {
+        text.push_str(&format!("{}S", seconds));
+    }
+    text
+}
@@ -0,0 +1,169 @@
+// Writes planner data as a calendar: plan blocks and timers as events,
+// routines as repeating events and todos with a due time as VTODOs.
+use chrono::{DateTime, Duration, NaiveDateTime, Utc};
+
+use super::{format_duration, Component, Recurrence};
+use crate::data::PlannerData;
+use crate::plan::BlockKind;
+use crate::time;
+use crate::todo::Priority;
+
+/// Which kinds of items go into the file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExportOptions {
+    pub plans: bool,
+    pub routines: bool,
+    pub todos: bool,
+    pub timers: bool,
+}
+
+impl Default for ExportOptions {
+    fn default() -> Self {
+        ExportOptions {
+            plans: true,
+            routines: true,
+            todos: true,
+            timers: true,
+        }
+    }
+}
+
+fn utc(instant: DateTime<Utc>) -> String {
+    instant.format("%Y%m%dT%H%M%SZ").to_string()
+}
+
+/// Wall-clock time without a zone, so a routine stays at 07:30 through
+/// daylight saving changes.
+fn floating(local: NaiveDateTime) -> String {
+    local.format("%Y%m%dT%H%M%S").to_string()
+}
+
+fn uid(kind: &str, id: impl std::fmt::Display) -> String {
+    format!("{}-{}@smart-planner", kind, id)
+}
+
+/// Builds the `.ics` text. `now` is used for `DTSTAMP` and as the first day
+/// of routines that do not know when they were created.
+pub fn export(data: &PlannerData, options: ExportOptions, now: DateTime<Utc>) -> String {
+    let stamp = utc(now);
+    let mut calendar = Component::new("VCALENDAR");
+    calendar.push("VERSION", "2.0");
+    calendar.push("PRODID", "-//Smart Planner//EN");
+    calendar.push("CALSCALE", "GREGORIAN");
+    calendar.push_text("X-WR-CALNAME", "Smart Planner");
+
+    if options.plans {
+        for plan in &data.plans {
+            for (number, block) in plan.blocks.iter().enumerate() {
+                // Busy blocks came from a calendar, routines are exported as rules
+                if block.kind == BlockKind::Busy
+                    || (options.routines && block.kind == BlockKind::Routine)
+                {
+                    continue;
+                }
+                let mut event = Component::new("VEVENT");
+                event.push("UID", &uid("plan", format!("{}-{}", plan.date, number + 1)));
+                event.push("DTSTAMP", &stamp);
+                event.push(
+                    "DTSTART",
+                    &utc(time::from_local(plan.date.and_time(block.start))),
+                );
+                event.push(
+                    "DTEND",
+                    &utc(time::from_local(plan.date.and_time(block.end))),
+                );
+                event.push_text("SUMMARY", &block.title);
+                event.push_text("CATEGORIES", &block.kind.to_string());
+                if let Some(todo_id) = block.todo_id {
+                    event.push_text("DESCRIPTION", &format!("Todo #{}", todo_id));
+                }
+                calendar.components.push(event);
+            }
+        }
+    }
+
+    if options.routines {
+        for routine in &data.routines {
+            let mut first = time::to_local(routine.created_at.unwrap_or(now)).date();
+            // DTSTART has to be an occurrence itself
+            for _ in 0..7 {
+                if routine.frequency.occurs_on(first) {
+                    break;
+                }
+                first = first.succ_opt().unwrap();
+            }
+            let mut event = Component::new("VEVENT");
+            event.push("UID", &uid("routine", routine.id));
+            event.push("DTSTAMP", &stamp);
+            event.push("DTSTART", &floating(first.and_time(routine.start)));
+            event.push(
+                "DURATION",
+                &format_duration(Duration::minutes(routine.duration_minutes as i64)),
+            );
+            event.push("RRULE", &Recurrence::from(&routine.frequency).to_string());
+            event.push_text("SUMMARY", &routine.name);
+            event.push_text("CATEGORIES", "routine");
+            calendar.components.push(event);
+        }
+    }
+
+    if options.todos {
+        for todo in &data.todos {
+            let Some(due) = todo.due else {
+                continue;
+            };
+            let mut entry = Component::new("VTODO");
+            entry.push("UID", &uid("todo", todo.id));
+            entry.push("DTSTAMP", &stamp);
+            entry.push("CREATED", &utc(todo.created_at));
+            entry.push("DUE", &utc(due));
+            entry.push_text("SUMMARY", &todo.title);
+            let priority = match todo.priority {
+                Priority::High => "1",
+                Priority::Medium => "5",
+                Priority::Low => "9",
+            };
+            entry.push("PRIORITY", priority);
+            if let Some(subject) = &todo.subject {
+                entry.push_text("CATEGORIES", subject);
+            }
+            if let Some(minutes) = todo.estimate_minutes {
+                entry.push(
+                    "ESTIMATED-DURATION",
+                    &format_duration(Duration::minutes(minutes as i64)),
+                );
+            }
+            match todo.completed_at {
+                Some(completed) => {
+                    entry.push("STATUS", "COMPLETED");
+                    entry.push("COMPLETED", &utc(completed));
+                }
+                None => entry.push("STATUS", "NEEDS-ACTION"),
+            }
+            calendar.components.push(entry);
+        }
+    }
+
+    if options.timers {
+        for timer in data.timers.iter().filter(|timer| !timer.fired) {
+            let mut event = Component::new("VEVENT");
+            event.push("UID", &uid("timer", timer.id));
+            event.push("DTSTAMP", &stamp);
+            event.push("DTSTART", &utc(timer.due));
+            event.push("DURATION", "PT0S");
+            event.push_text("SUMMARY", &timer.label);
+            event.push_text("CATEGORIES", "timer");
+            event.push("TRANSP", "TRANSPARENT");
+            let mut alarm = Component::new("VALARM");
+            alarm.push("ACTION", "DISPLAY");
+            alarm.push_text("DESCRIPTION", &timer.label);
+            alarm.push("TRIGGER", "PT0S");
+            event.components.push(alarm);
+            calendar.components.push(event);
+        }
+    }
+
+    let mut out = String::new();
+    calendar.write(&mut out);
+    out
+}
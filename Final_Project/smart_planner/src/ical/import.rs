@@ -0,0 +1,212 @@
+// Turns the events of a calendar into busy time for the planner. Repeating
+// events are expanded into their occurrences inside a window of days, since
+// the planner only needs to know which slots are taken.
+use std::collections::HashSet;
+
+use chrono::{DateTime, Duration, NaiveDate, NaiveDateTime, Utc};
+
+use super::timezone::Declared;
+use super::{parse_date, parse_date_time, parse_duration, Component, Property, Recurrence, Zone};
+use crate::busy::{BusyEvent, BusyTime};
+use crate::error::Result;
+
+/// The busy events found in a file, and the events that had to be left out.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Imported {
+    pub events: Vec<BusyEvent>,
+    /// One line per event that could not be read, saying why.
+    pub skipped: Vec<String>,
+}
+
+/// A `DTSTART`, `DTEND` or `EXDATE` value.
+#[derive(Debug, Clone, PartialEq)]
+enum Moment {
+    Date(NaiveDate),
+    Local(NaiveDateTime, Zone),
+}
+
+/// Identifies one occurrence of a repeating event.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Key {
+    Day(NaiveDate),
+    At(DateTime<Utc>),
+}
+
+impl Moment {
+    fn key(&self) -> Key {
+        match self {
+            Moment::Date(date) => Key::Day(*date),
+            Moment::Local(local, zone) => Key::At(zone.to_utc(*local)),
+        }
+    }
+}
+
+/// Reads the opaque events of `calendar` that overlap the local days `from`
+/// to `to`. Transparent (free) and cancelled events are left out.
+pub fn busy_events(calendar: &Component, from: NaiveDate, to: NaiveDate) -> Result<Imported> {
+    let mut zones = Vec::new();
+    for component in calendar.children("VTIMEZONE") {
+        zones.push(Declared::from_component(component)?);
+    }
+    // Occurrences replaced by an event of their own, found by RECURRENCE-ID
+    let mut replaced: HashSet<(String, Key)> = HashSet::new();
+    for event in calendar.children("VEVENT") {
+        if let (Some(uid), Some(id)) = (event.text("UID"), event.property("RECURRENCE-ID")) {
+            if let Ok(moment) = moment(id, &id.value, &zones) {
+                replaced.insert((uid, moment.key()));
+            }
+        }
+    }
+
+    let mut imported = Imported::default();
+    for event in calendar.children("VEVENT") {
+        let summary = event.text("SUMMARY").unwrap_or_else(|| "Busy".to_string());
+        let free = event
+            .text("TRANSP")
+            .is_some_and(|t| t.eq_ignore_ascii_case("TRANSPARENT"));
+        let cancelled = event
+            .text("STATUS")
+            .is_some_and(|s| s.eq_ignore_ascii_case("CANCELLED"));
+        if free || cancelled {
+            continue;
+        }
+        match occurrences(event, &zones, &replaced, from, to) {
+            Ok(times) => {
+                let uid = event.text("UID").unwrap_or_else(|| summary.clone());
+                imported
+                    .events
+                    .extend(times.into_iter().map(|time| BusyEvent {
+                        uid: uid.clone(),
+                        summary: summary.clone(),
+                        time,
+                    }));
+            }
+            Err(e) => imported.skipped.push(format!("{}: {}", summary, e)),
+        }
+    }
+    Ok(imported)
+}
+
+fn moment(property: &Property, value: &str, zones: &[Declared]) -> Result<Moment> {
+    if property.param("VALUE") == Some("DATE") || value.trim().len() == 8 {
+        return Ok(Moment::Date(parse_date(value)?));
+    }
+    let (local, utc) = parse_date_time(value)?;
+    let zone = match property.param("TZID") {
+        _ if utc => Zone::Utc,
+        Some(id) => match zones.iter().find(|zone| zone.id == id) {
+            Some(zone) => Zone::Declared(zone.clone()),
+            None if matches!(id, "UTC" | "GMT" | "Etc/UTC" | "Etc/GMT") => Zone::Utc,
+            // A zone the file does not declare, read it as local time
+            None => Zone::Floating,
+        },
+        None => Zone::Floating,
+    };
+    Ok(Moment::Local(local, zone))
+}
+
+fn occurrences(
+    event: &Component,
+    zones: &[Declared],
+    replaced: &HashSet<(String, Key)>,
+    from: NaiveDate,
+    to: NaiveDate,
+) -> Result<Vec<BusyTime>> {
+    let start_property = event
+        .property("DTSTART")
+        .ok_or_else(|| super::invalid("the event has no DTSTART".to_string()))?;
+    let start = moment(start_property, &start_property.value, zones)?;
+    let end = match (event.property("DTEND"), event.property("DURATION")) {
+        (Some(end), _) => Some(moment(end, &end.value, zones)?),
+        _ => None,
+    };
+    let duration = match event.property("DURATION") {
+        Some(duration) => Some(parse_duration(&duration.value)?),
+        None => None,
+    };
+    let rule: Option<Recurrence> = match event.property("RRULE") {
+        Some(rule) => Some(rule.value.parse()?),
+        None => None,
+    };
+
+    let mut excluded: HashSet<Key> = HashSet::new();
+    for exdate in event.all("EXDATE") {
+        for value in exdate.value.split(',') {
+            excluded.insert(moment(exdate, value, zones)?.key());
+        }
+    }
+    // An override is imported as an event of its own, not as part of the series
+    let uid = event.text("UID").unwrap_or_default();
+    let is_override = event.property("RECURRENCE-ID").is_some();
+    let skip = |key: Key| {
+        excluded.contains(&key) || (!is_override && replaced.contains(&(uid.clone(), key)))
+    };
+
+    let too_long = || super::invalid("the event lasts past the last date there is".to_string());
+    let window_start = from.and_time(chrono::NaiveTime::MIN);
+    let window_end = (to + Duration::days(1)).and_time(chrono::NaiveTime::MIN);
+    let mut times = Vec::new();
+    match start {
+        Moment::Date(first) => {
+            let days = match (&end, duration) {
+                (Some(Moment::Date(end)), _) => (*end - first).num_days(),
+                (_, Some(duration)) => duration.num_days(),
+                _ => 1,
+            }
+            .max(1);
+            let starts = match &rule {
+                Some(rule) => rule.occurrences(
+                    first.and_time(chrono::NaiveTime::MIN),
+                    window_end,
+                    rule.until.map(|until| Zone::Floating.until(until)),
+                )?,
+                None => vec![first.and_time(chrono::NaiveTime::MIN)],
+            };
+            for start in starts {
+                let first = start.date();
+                let last = first
+                    .checked_add_signed(Duration::days(days - 1))
+                    .ok_or_else(too_long)?;
+                if last < from || first > to || skip(Key::Day(first)) {
+                    continue;
+                }
+                times.push(BusyTime::AllDay { first, last });
+            }
+        }
+        Moment::Local(local, zone) => {
+            let start_utc = zone.to_utc(local);
+            let length = match (&end, duration) {
+                (Some(end @ Moment::Local(..)), _) => match end.key() {
+                    Key::At(end) => end - start_utc,
+                    Key::Day(_) => Duration::zero(),
+                },
+                (_, Some(duration)) => duration,
+                _ => Duration::zero(),
+            };
+            if length <= Duration::zero() {
+                return Ok(times);
+            }
+            let starts = match &rule {
+                Some(rule) => rule.occurrences(
+                    local,
+                    zone.to_local(Zone::Floating.to_utc(window_end)),
+                    rule.until.map(|until| zone.until(until)),
+                )?,
+                None => vec![local],
+            };
+            let window = (
+                Zone::Floating.to_utc(window_start),
+                Zone::Floating.to_utc(window_end),
+            );
+            for start in starts {
+                let start = zone.to_utc(start);
+                let end = start.checked_add_signed(length).ok_or_else(too_long)?;
+                if end <= window.0 || start >= window.1 || skip(Key::At(start)) {
+                    continue;
+                }
+                times.push(BusyTime::Timed { start, end });
+            }
+        }
+    }
+    Ok(times)
+}
@@ -0,0 +1,386 @@
+// Recurrence rules (`RRULE`). Only the parts calendars commonly use are
+// understood: FREQ, INTERVAL, COUNT, UNTIL, BYDAY, BYMONTHDAY and BYMONTH.
+use std::fmt;
+use std::str::FromStr;
+
+use chrono::{Datelike, Duration, Months, NaiveDate, NaiveDateTime, Weekday};
+
+use crate::error::PlannerError;
+use crate::routine::Frequency;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Freq {
+    Daily,
+    Weekly,
+    Monthly,
+    Yearly,
+}
+
+/// The end of a rule as written in the file. A UTC time has to be turned
+/// into the event's own time zone before use.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Until {
+    Date(NaiveDate),
+    Local(NaiveDateTime),
+    Utc(NaiveDateTime),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Recurrence {
+    pub freq: Freq,
+    pub interval: u32,
+    pub count: Option<u32>,
+    pub until: Option<Until>,
+    /// Weekdays, with an ordinal like the `2` in `2SU` or 0 for every one.
+    pub by_day: Vec<(i32, Weekday)>,
+    /// Days of the month, negative ones count from the end.
+    pub by_month_day: Vec<i32>,
+    pub by_month: Vec<u32>,
+}
+
+impl Recurrence {
+    pub fn new(freq: Freq) -> Self {
+        Recurrence {
+            freq,
+            interval: 1,
+            count: None,
+            until: None,
+            by_day: Vec::new(),
+            by_month_day: Vec::new(),
+            by_month: Vec::new(),
+        }
+    }
+
+    /// The starts of every occurrence from `start` (the `DTSTART`, which
+    /// always counts) up to `limit`. `until` is the rule's `UNTIL` in the same
+    /// wall-clock time as `start`. Fails when the rule steps past the dates
+    /// that can be told apart.
+    pub fn occurrences(
+        &self,
+        start: NaiveDateTime,
+        limit: NaiveDateTime,
+        until: Option<NaiveDateTime>,
+    ) -> Result<Vec<NaiveDateTime>, PlannerError> {
+        let too_far = || {
+            PlannerError::Invalid(format!(
+                "calendar file: the RRULE '{}' goes past the last date there is",
+                self
+            ))
+        };
+        let mut found = Vec::new();
+        let interval = self.interval.max(1) as i64;
+        let first_of_month = start.date().with_day(1).unwrap();
+        for period in 0i64.. {
+            let step = period.checked_mul(interval).ok_or_else(too_far)?;
+            let (base, mut dates) = match self.freq {
+                Freq::Daily => {
+                    let date = Duration::try_days(step)
+                        .and_then(|step| start.date().checked_add_signed(step))
+                        .ok_or_else(too_far)?;
+                    let dates = if self.matches_weekday(date) && self.matches_month(date) {
+                        vec![date]
+                    } else {
+                        Vec::new()
+                    };
+                    (date, dates)
+                }
+                Freq::Weekly => {
+                    let weekday = start.weekday().num_days_from_monday() as i64;
+                    let monday = Duration::try_weeks(step)
+                        .and_then(|step| Duration::try_days(-weekday)?.checked_add(&step))
+                        .and_then(|step| start.date().checked_add_signed(step))
+                        .ok_or_else(too_far)?;
+                    let days: Vec<i64> = if self.by_day.is_empty() {
+                        vec![weekday]
+                    } else {
+                        self.by_day
+                            .iter()
+                            .map(|(_, day)| day.num_days_from_monday() as i64)
+                            .collect()
+                    };
+                    let mut dates = Vec::new();
+                    for day in days {
+                        let date = monday
+                            .checked_add_signed(Duration::days(day))
+                            .ok_or_else(too_far)?;
+                        if self.by_day.is_empty() || self.matches_month(date) {
+                            dates.push(date);
+                        }
+                    }
+                    (monday, dates)
+                }
+                Freq::Monthly => {
+                    let month = u32::try_from(step)
+                        .ok()
+                        .and_then(|step| first_of_month.checked_add_months(Months::new(step)))
+                        .ok_or_else(too_far)?;
+                    let dates = if self.matches_month(month) {
+                        self.days_in_month(month, start.date())
+                    } else {
+                        Vec::new()
+                    };
+                    (month, dates)
+                }
+                Freq::Yearly => {
+                    let year = i32::try_from(step)
+                        .ok()
+                        .and_then(|step| start.year().checked_add(step))
+                        .and_then(|year| NaiveDate::from_ymd_opt(year, 1, 1))
+                        .ok_or_else(too_far)?;
+                    let months = if self.by_month.is_empty() {
+                        vec![start.month()]
+                    } else {
+                        self.by_month.clone()
+                    };
+                    let dates = months
+                        .iter()
+                        .filter_map(|month| year.with_month(*month))
+                        .flat_map(|month| self.days_in_month(month, start.date()))
+                        .collect();
+                    (year, dates)
+                }
+            };
+            if base > limit.date() {
+                break;
+            }
+            dates.sort();
+            dates.dedup();
+            for date in dates {
+                let occurrence = date.and_time(start.time());
+                if occurrence < start {
+                    continue;
+                }
+                if occurrence > limit || until.is_some_and(|until| occurrence > until) {
+                    return Ok(found);
+                }
+                found.push(occurrence);
+                if self
+                    .count
+                    .is_some_and(|count| found.len() >= count as usize)
+                {
+                    return Ok(found);
+                }
+            }
+        }
+        Ok(found)
+    }
+
+    fn matches_weekday(&self, date: NaiveDate) -> bool {
+        self.by_day.is_empty() || self.by_day.iter().any(|(_, day)| *day == date.weekday())
+    }
+
+    fn matches_month(&self, date: NaiveDate) -> bool {
+        self.by_month.is_empty() || self.by_month.contains(&date.month())
+    }
+
+    /// The days of the month starting on `first` that the BY parts pick,
+    /// or the day of `start` when there are none.
+    fn days_in_month(&self, first: NaiveDate, start: NaiveDate) -> Vec<NaiveDate> {
+        let all: Vec<NaiveDate> = first
+            .iter_days()
+            .take_while(|date| date.month() == first.month())
+            .collect();
+        let mut days: Vec<NaiveDate> = if !self.by_month_day.is_empty() {
+            self.by_month_day
+                .iter()
+                .filter_map(|day| pick(&all, *day))
+                .collect()
+        } else if self.by_day.is_empty() {
+            first.with_day(start.day()).into_iter().collect()
+        } else {
+            all.clone()
+        };
+        if !self.by_day.is_empty() {
+            let mut picked = Vec::new();
+            for (ordinal, weekday) in &self.by_day {
+                let matching: Vec<NaiveDate> = days
+                    .iter()
+                    .copied()
+                    .filter(|date| date.weekday() == *weekday)
+                    .collect();
+                if *ordinal == 0 {
+                    picked.extend(matching);
+                } else if let Some(date) = pick(&matching, *ordinal) {
+                    picked.push(date);
+                }
+            }
+            days = picked;
+        }
+        days
+    }
+}
+
+/// The `n`th entry counting from 1, or from the end when negative.
+fn pick(dates: &[NaiveDate], n: i32) -> Option<NaiveDate> {
+    let index = if n > 0 {
+        n as usize - 1
+    } else {
+        dates.len().checked_sub(n.unsigned_abs() as usize)?
+    };
+    dates.get(index).copied()
+}
+
+fn weekday_code(day: Weekday) -> &'static str {
+    match day {
+        Weekday::Mon => "MO",
+        Weekday::Tue => "TU",
+        Weekday::Wed => "WE",
+        Weekday::Thu => "TH",
+        Weekday::Fri => "FR",
+        Weekday::Sat => "SA",
+        Weekday::Sun => "SU",
+    }
+}
+
+fn parse_weekday(code: &str) -> Option<Weekday> {
+    Some(match code {
+        "MO" => Weekday::Mon,
+        "TU" => Weekday::Tue,
+        "WE" => Weekday::Wed,
+        "TH" => Weekday::Thu,
+        "FR" => Weekday::Fri,
+        "SA" => Weekday::Sat,
+        "SU" => Weekday::Sun,
+        _ => return None,
+    })
+}
+
+impl From<&Frequency> for Recurrence {
+    /// The rule for a routine.
+    fn from(frequency: &Frequency) -> Self {
+        let days = match frequency {
+            Frequency::Daily => return Recurrence::new(Freq::Daily),
+            Frequency::Weekdays => vec![
+                Weekday::Mon,
+                Weekday::Tue,
+                Weekday::Wed,
+                Weekday::Thu,
+                Weekday::Fri,
+            ],
+            Frequency::Weekends => vec![Weekday::Sat, Weekday::Sun],
+            Frequency::Days(days) => days.clone(),
+        };
+        let mut rule = Recurrence::new(Freq::Weekly);
+        rule.by_day = days.into_iter().map(|day| (0, day)).collect();
+        rule
+    }
+}
+
+impl fmt::Display for Recurrence {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let freq = match self.freq {
+            Freq::Daily => "DAILY",
+            Freq::Weekly => "WEEKLY",
+            Freq::Monthly => "MONTHLY",
+            Freq::Yearly => "YEARLY",
+        };
+        write!(f, "FREQ={}", freq)?;
+        if self.interval > 1 {
+            write!(f, ";INTERVAL={}", self.interval)?;
+        }
+        if !self.by_month.is_empty() {
+            let months: Vec<String> = self.by_month.iter().map(u32::to_string).collect();
+            write!(f, ";BYMONTH={}", months.join(","))?;
+        }
+        if !self.by_month_day.is_empty() {
+            let days: Vec<String> = self.by_month_day.iter().map(i32::to_string).collect();
+            write!(f, ";BYMONTHDAY={}", days.join(","))?;
+        }
+        if !self.by_day.is_empty() {
+            let days: Vec<String> = self
+                .by_day
+                .iter()
+                .map(|(ordinal, day)| match ordinal {
+                    0 => weekday_code(*day).to_string(),
+                    n => format!("{}{}", n, weekday_code(*day)),
+                })
+                .collect();
+            write!(f, ";BYDAY={}", days.join(","))?;
+        }
+        if let Some(count) = self.count {
+            write!(f, ";COUNT={}", count)?;
+        }
+        match self.until {
+            Some(Until::Date(date)) => write!(f, ";UNTIL={}", date.format("%Y%m%d"))?,
+            Some(Until::Local(local)) => write!(f, ";UNTIL={}", local.format("%Y%m%dT%H%M%S"))?,
+            Some(Until::Utc(utc)) => write!(f, ";UNTIL={}", utc.format("%Y%m%dT%H%M%SZ"))?,
+            None => {}
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Recurrence {
+    type Err = PlannerError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let error = |what: &str| {
+            PlannerError::Invalid(format!(
+                "calendar file: unsupported RRULE {} in '{}'",
+                what, text
+            ))
+        };
+        let mut freq = None;
+        let mut rule = Recurrence::new(Freq::Daily);
+        for part in text.trim().split(';').filter(|part| !part.is_empty()) {
+            let (key, value) = part.split_once('=').ok_or_else(|| error(part))?;
+            let list = || value.split(',').map(str::trim);
+            match key.trim().to_uppercase().as_str() {
+                "FREQ" => {
+                    freq = Some(match value.to_uppercase().as_str() {
+                        "DAILY" => Freq::Daily,
+                        "WEEKLY" => Freq::Weekly,
+                        "MONTHLY" => Freq::Monthly,
+                        "YEARLY" => Freq::Yearly,
+                        _ => return Err(error(part)),
+                    })
+                }
+                "INTERVAL" => rule.interval = value.parse().map_err(|_| error(part))?,
+                "COUNT" => rule.count = Some(value.parse().map_err(|_| error(part))?),
+                "UNTIL" => {
+                    rule.until = Some(if value.len() == 8 {
+                        Until::Date(super::parse_date(value)?)
+                    } else {
+                        match super::parse_date_time(value)? {
+                            (utc, true) => Until::Utc(utc),
+                            (local, false) => Until::Local(local),
+                        }
+                    })
+                }
+                "BYDAY" => {
+                    for day in list() {
+                        // `get` refuses to split inside a letter that is not ASCII
+                        let split = day.len().saturating_sub(2);
+                        let (ordinal, code) = day
+                            .get(..split)
+                            .zip(day.get(split..))
+                            .ok_or_else(|| error(part))?;
+                        let weekday =
+                            parse_weekday(&code.to_uppercase()).ok_or_else(|| error(part))?;
+                        let ordinal = match ordinal {
+                            "" => 0,
+                            n => n.trim_start_matches('+').parse().map_err(|_| error(part))?,
+                        };
+                        rule.by_day.push((ordinal, weekday));
+                    }
+                }
+                "BYMONTHDAY" => {
+                    for day in list() {
+                        rule.by_month_day
+                            .push(day.parse().map_err(|_| error(part))?);
+                    }
+                }
+                "BYMONTH" => {
+                    for month in list() {
+                        rule.by_month.push(month.parse().map_err(|_| error(part))?);
+                    }
+                }
+                // Week start only changes results for rules we do not support
+                "WKST" => {}
+                _ => return Err(error(part)),
+            }
+        }
+        rule.freq = freq.ok_or_else(|| error("without FREQ"))?;
+        Ok(rule)
+    }
+}
@@ -0,0 +1,206 @@
+// Time zones as a calendar file declares them in `VTIMEZONE`: a list of
+// observances (standard and daylight time), each with the offsets before and
+// after it starts and a rule for when it starts again every year.
+use chrono::{DateTime, Datelike, Duration, NaiveDateTime, Utc};
+
+use super::recur::{Recurrence, Until};
+use super::{invalid, parse_date_time, Component};
+use crate::error::Result;
+use crate::time;
+
+/// How the wall-clock times of an event turn into instants.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Zone {
+    Utc,
+    /// No zone given: the planner's own local time.
+    Floating,
+    Declared(Declared),
+}
+
+impl Zone {
+    pub fn to_utc(&self, local: NaiveDateTime) -> DateTime<Utc> {
+        match self {
+            Zone::Utc => local.and_utc(),
+            Zone::Floating => time::from_local(local),
+            Zone::Declared(zone) => zone.to_utc(local),
+        }
+    }
+
+    pub fn to_local(&self, instant: DateTime<Utc>) -> NaiveDateTime {
+        match self {
+            Zone::Utc => instant.naive_utc(),
+            Zone::Floating => time::to_local(instant),
+            Zone::Declared(zone) => zone.to_local(instant),
+        }
+    }
+
+    /// A rule's `UNTIL` in this zone's wall-clock time.
+    pub fn until(&self, until: Until) -> NaiveDateTime {
+        match until {
+            Until::Date(date) => date.and_hms_opt(23, 59, 59).unwrap(),
+            Until::Local(local) => local,
+            Until::Utc(utc) => self.to_local(utc.and_utc()),
+        }
+    }
+}
+
+/// One `STANDARD` or `DAYLIGHT` block.
+#[derive(Debug, Clone, PartialEq)]
+struct Observance {
+    /// First onset, in the wall-clock time before it.
+    start: NaiveDateTime,
+    /// Seconds east of UTC before and after the onset.
+    offset_from: i32,
+    offset_to: i32,
+    rule: Option<Recurrence>,
+    /// Extra onsets from `RDATE`.
+    dates: Vec<NaiveDateTime>,
+}
+
+/// A zone declared in a `VTIMEZONE` component.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Declared {
+    pub id: String,
+    observances: Vec<Observance>,
+}
+
+/// An onset: from this wall-clock time on (in the old offset) the zone uses
+/// `offset_to`.
+struct Transition {
+    local: NaiveDateTime,
+    offset_from: i32,
+    offset_to: i32,
+}
+
+impl Declared {
+    pub fn from_component(component: &Component) -> Result<Self> {
+        let id = component
+            .text("TZID")
+            .ok_or_else(|| invalid("a VTIMEZONE has no TZID".to_string()))?;
+        let mut observances = Vec::new();
+        for block in &component.components {
+            if block.name != "STANDARD" && block.name != "DAYLIGHT" {
+                continue;
+            }
+            let required = |name: &str| {
+                block.property(name).ok_or_else(|| {
+                    invalid(format!(
+                        "{} in time zone {} has no {}",
+                        block.name, id, name
+                    ))
+                })
+            };
+            let (start, _) = parse_date_time(&required("DTSTART")?.value)?;
+            let rule: Option<Recurrence> = match block.property("RRULE") {
+                Some(rule) => Some(rule.value.parse()?),
+                None => None,
+            };
+            // Refused here, as the onsets are worked out again for every time
+            if let Some(rule) = &rule {
+                rule.occurrences(start, start, None)?;
+            }
+            let mut dates = Vec::new();
+            for rdate in block.all("RDATE") {
+                for value in rdate.value.split(',') {
+                    dates.push(parse_date_time(value)?.0);
+                }
+            }
+            observances.push(Observance {
+                start,
+                offset_from: parse_offset(&required("TZOFFSETFROM")?.value)?,
+                offset_to: parse_offset(&required("TZOFFSETTO")?.value)?,
+                rule,
+                dates,
+            });
+        }
+        if observances.is_empty() {
+            return Err(invalid(format!(
+                "time zone {} has no STANDARD or DAYLIGHT",
+                id
+            )));
+        }
+        Ok(Declared { id, observances })
+    }
+
+    /// Every onset up to the end of the year after `year`, oldest first.
+    fn transitions(&self, year: i32) -> Vec<Transition> {
+        let limit = chrono::NaiveDate::from_ymd_opt(year + 1, 12, 31)
+            .unwrap()
+            .and_hms_opt(23, 59, 59)
+            .unwrap();
+        let mut transitions = Vec::new();
+        for observance in &self.observances {
+            let mut onsets = match &observance.rule {
+                Some(rule) => {
+                    let until = rule.until.map(|until| match until {
+                        // UNTIL of an observance is UTC, turn it into the old wall-clock time
+                        Until::Utc(utc) => utc + Duration::seconds(observance.offset_from as i64),
+                        other => Zone::Floating.until(other),
+                    });
+                    // Checked when the zone was read
+                    rule.occurrences(observance.start, limit, until)
+                        .unwrap_or_default()
+                }
+                None => vec![observance.start],
+            };
+            onsets.extend(observance.dates.iter().copied());
+            transitions.extend(onsets.into_iter().map(|local| Transition {
+                local,
+                offset_from: observance.offset_from,
+                offset_to: observance.offset_to,
+            }));
+        }
+        transitions.sort_by_key(|transition| transition.local);
+        transitions
+    }
+
+    fn offset_before(&self, transitions: &[Transition]) -> i32 {
+        transitions
+            .first()
+            .map(|first| first.offset_from)
+            .unwrap_or(self.observances[0].offset_to)
+    }
+
+    pub fn to_utc(&self, local: NaiveDateTime) -> DateTime<Utc> {
+        let transitions = self.transitions(local.year());
+        let offset = transitions
+            .iter()
+            .rev()
+            .find(|transition| transition.local <= local)
+            .map(|transition| transition.offset_to)
+            .unwrap_or_else(|| self.offset_before(&transitions));
+        (local - Duration::seconds(offset as i64)).and_utc()
+    }
+
+    pub fn to_local(&self, instant: DateTime<Utc>) -> NaiveDateTime {
+        let utc = instant.naive_utc();
+        let transitions = self.transitions(utc.year());
+        let offset = transitions
+            .iter()
+            .rev()
+            .find(|transition| {
+                transition.local - Duration::seconds(transition.offset_from as i64) <= utc
+            })
+            .map(|transition| transition.offset_to)
+            .unwrap_or_else(|| self.offset_before(&transitions));
+        utc + Duration::seconds(offset as i64)
+    }
+}
+
+/// Parses an offset like `+0100`, `-0500` or `+053000` into seconds.
+fn parse_offset(value: &str) -> Result<i32> {
+    let value = value.trim();
+    let error = || invalid(format!("'{}' is not a UTC offset", value));
+    let (sign, digits) = match value.split_at_checked(1) {
+        Some(("+", digits)) => (1, digits),
+        Some(("-", digits)) => (-1, digits),
+        _ => return Err(error()),
+    };
+    if !(digits.len() == 4 || digits.len() == 6) || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(error());
+    }
+    let number =
+        |range: std::ops::Range<usize>| digits.get(range).map_or(0, |d| d.parse().unwrap_or(0));
+    let seconds = number(0..2) * 3600 + number(2..4) * 60 + number(4..6);
+    Ok(sign * seconds)
+}
@@ -0,0 +1,34 @@
+// Smart Planner, the final project of the tutorial. See final_project.md in
+// the root of the repository for the feature list.
+pub mod backup;
+pub mod budget;
+pub mod busy;
+pub mod chart;
+pub mod cli;
+pub mod config;
+pub mod daemon;
+pub mod dashboard;
+pub mod data;
+pub mod email;
+pub mod error;
+pub mod history;
+pub mod ical;
+pub mod notify;
+pub mod plan;
+pub mod pomodoro;
+pub mod profile;
+pub mod project;
+pub mod report;
+pub mod routine;
+pub mod server;
+pub mod stopwatch;
+pub mod storage;
+pub mod store;
+pub mod sync;
+pub mod time;
+pub mod timer;
+pub mod todo;
+pub mod vault;
+pub mod webhook;
+
+pub use error::{PlannerError, Result};
@@ -0,0 +1,19 @@
+// Tells the user that something happened, like a timer running out.
+use std::io::{self, Write};
+
+/// Somewhere notifications can be sent.
+pub trait Notifier {
+    fn notify(&self, title: &str, message: &str);
+}
+
+/// Prints the notification and rings the terminal bell.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TerminalNotifier;
+
+impl Notifier for TerminalNotifier {
+    fn notify(&self, title: &str, message: &str) {
+        let mut stdout = io::stdout();
+        let _ = writeln!(stdout, "\x07{}: {}", title, message);
+        let _ = stdout.flush();
+    }
+}
@@ -0,0 +1,287 @@
+use std::fmt;
+
+use chrono::{Duration, NaiveDate, NaiveTime};
+use serde::{Deserialize, Serialize};
+
+use crate::busy::BusyEvent;
+use crate::error::{PlannerError, Result};
+use crate::routine::Routine;
+use crate::todo::Todo;
+
+pub mod conflict;
+pub mod llm;
+pub mod local;
+
+pub use conflict::{Conflict, ConflictChecker, Resolution};
+pub use llm::LlmPlanner;
+pub use local::LocalScheduler;
+
+/// What a block of the plan is for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BlockKind {
+    Routine,
+    Task,
+    Break,
+    Custom,
+    /// Taken by an event imported from a calendar.
+    Busy,
+}
+
+impl fmt::Display for BlockKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            BlockKind::Routine => "routine",
+            BlockKind::Task => "task",
+            BlockKind::Break => "break",
+            BlockKind::Custom => "custom",
+            BlockKind::Busy => "busy",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// One time slot of a daily plan, in local time.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PlanBlock {
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+    pub title: String,
+    pub kind: BlockKind,
+    /// The todo this block works on, if any.
+    #[serde(default)]
+    pub todo_id: Option<u64>,
+    /// The routine this block comes from, if any.
+    #[serde(default)]
+    pub routine_id: Option<u64>,
+}
+
+impl PlanBlock {
+    pub fn new(start: NaiveTime, end: NaiveTime, title: &str, kind: BlockKind) -> Result<Self> {
+        if end <= start {
+            return Err(PlannerError::Invalid(format!(
+                "block '{}' ends at {} which is not after its start {}",
+                title,
+                end.format("%H:%M"),
+                start.format("%H:%M")
+            )));
+        }
+        Ok(PlanBlock {
+            start,
+            end,
+            title: title.to_string(),
+            kind,
+            todo_id: None,
+            routine_id: None,
+        })
+    }
+
+    pub fn minutes(&self) -> i64 {
+        (self.end - self.start).num_minutes()
+    }
+}
+
+/// The plan for one day.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Plan {
+    pub date: NaiveDate,
+    pub blocks: Vec<PlanBlock>,
+    /// Todos the backend could not fit into the day.
+    #[serde(default)]
+    pub unscheduled: Vec<u64>,
+    /// Name of the backend that generated the plan.
+    pub generated_by: String,
+    /// Set once the user changes the generated plan.
+    #[serde(default)]
+    pub edited: bool,
+}
+
+impl Plan {
+    pub fn new(date: NaiveDate, generated_by: &str) -> Self {
+        Plan {
+            date,
+            blocks: Vec::new(),
+            unscheduled: Vec::new(),
+            generated_by: generated_by.to_string(),
+            edited: false,
+        }
+    }
+
+    fn sort(&mut self) {
+        self.blocks.sort_by_key(|block| (block.start, block.end));
+    }
+
+    fn block_mut(&mut self, index: usize) -> Result<&mut PlanBlock> {
+        // Users see blocks numbered from 1
+        index
+            .checked_sub(1)
+            .and_then(|i| self.blocks.get_mut(i))
+            .ok_or_else(|| PlannerError::NotFound(format!("block {}", index)))
+    }
+
+    /// Adds a block and keeps the plan in time order.
+    pub fn add_block(&mut self, block: PlanBlock) {
+        if let Some(todo_id) = block.todo_id {
+            self.unscheduled.retain(|id| *id != todo_id);
+        }
+        self.blocks.push(block);
+        self.sort();
+        self.edited = true;
+    }
+
+    /// Removes the block with the given 1-based number.
+    pub fn remove_block(&mut self, index: usize) -> Result<PlanBlock> {
+        self.block_mut(index)?;
+        let block = self.blocks.remove(index - 1);
+        if let Some(todo_id) = block.todo_id {
+            self.unscheduled.push(todo_id);
+        }
+        self.edited = true;
+        Ok(block)
+    }
+
+    /// Moves a block to a new start time, keeping its length.
+    pub fn move_block(&mut self, index: usize, start: NaiveTime) -> Result<()> {
+        let block = self.block_mut(index)?;
+        let length = Duration::minutes(block.minutes());
+        let (end, wrapped) = start.overflowing_add_signed(length);
+        if wrapped != 0 {
+            return Err(PlannerError::Invalid(format!(
+                "block '{}' would run past midnight",
+                block.title
+            )));
+        }
+        block.start = start;
+        block.end = end;
+        self.sort();
+        self.edited = true;
+        Ok(())
+    }
+
+    /// Changes the start and end of a block.
+    pub fn resize_block(&mut self, index: usize, start: NaiveTime, end: NaiveTime) -> Result<()> {
+        let block = self.block_mut(index)?;
+        let resized = PlanBlock::new(start, end, &block.title, block.kind)?;
+        block.start = resized.start;
+        block.end = resized.end;
+        self.sort();
+        self.edited = true;
+        Ok(())
+    }
+
+    /// Gives a block a new title.
+    pub fn rename_block(&mut self, index: usize, title: &str) -> Result<()> {
+        self.block_mut(index)?.title = title.to_string();
+        self.edited = true;
+        Ok(())
+    }
+}
+
+impl fmt::Display for Plan {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let edited = if self.edited { ", edited" } else { "" };
+        writeln!(
+            f,
+            "Plan for {} (generated by {}{})",
+            self.date, self.generated_by, edited
+        )?;
+        if self.blocks.is_empty() {
+            writeln!(f, "  (no blocks)")?;
+        }
+        for (i, block) in self.blocks.iter().enumerate() {
+            writeln!(
+                f,
+                "{:>3}. {}-{}  [{}] {}",
+                i + 1,
+                block.start.format("%H:%M"),
+                block.end.format("%H:%M"),
+                block.kind,
+                block.title
+            )?;
+        }
+        if !self.unscheduled.is_empty() {
+            let ids: Vec<String> = self
+                .unscheduled
+                .iter()
+                .map(|id| format!("#{}", id))
+                .collect();
+            writeln!(f, "  Did not fit: {}", ids.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+/// Everything a planner backend needs to build a day.
+#[derive(Debug, Clone)]
+pub struct PlanRequest {
+    pub date: NaiveDate,
+    /// First and last minute of the working day.
+    pub day_start: NaiveTime,
+    pub day_end: NaiveTime,
+    /// Open todos the backend may schedule.
+    pub todos: Vec<Todo>,
+    /// Routines happening on `date`.
+    pub routines: Vec<Routine>,
+    /// Calendar events taking time on `date`.
+    pub busy: Vec<PlanBlock>,
+}
+
+impl PlanRequest {
+    /// Builds a request from all todos and routines, keeping what matters for `date`.
+    pub fn new(date: NaiveDate, todos: &[Todo], routines: &[Routine]) -> Self {
+        PlanRequest {
+            date,
+            day_start: NaiveTime::from_hms_opt(8, 0, 0).unwrap(),
+            day_end: NaiveTime::from_hms_opt(22, 0, 0).unwrap(),
+            todos: todos.iter().filter(|todo| !todo.done).cloned().collect(),
+            routines: routines
+                .iter()
+                .filter(|routine| routine.frequency.occurs_on(date))
+                .cloned()
+                .collect(),
+            busy: Vec::new(),
+        }
+    }
+
+    /// Adds the slots the calendar events take on the planned day.
+    pub fn with_busy(mut self, events: &[BusyEvent]) -> Self {
+        for event in events {
+            if let Some((start, end)) = event.slot_on(self.date) {
+                if let Ok(block) = PlanBlock::new(start, end, &event.summary, BlockKind::Busy) {
+                    self.busy.push(block);
+                }
+            }
+        }
+        self
+    }
+
+    /// The routine and busy blocks every backend has to keep as they are.
+    pub fn fixed_blocks(&self) -> Vec<PlanBlock> {
+        let mut blocks: Vec<PlanBlock> = self
+            .routines
+            .iter()
+            .filter_map(|routine| {
+                let mut block = PlanBlock::new(
+                    routine.start,
+                    routine.end(),
+                    &routine.name,
+                    BlockKind::Routine,
+                )
+                .ok()?;
+                block.routine_id = Some(routine.id);
+                Some(block)
+            })
+            .collect();
+        blocks.extend(self.busy.iter().cloned());
+        blocks.sort_by_key(|block| (block.start, block.end));
+        blocks
+    }
+}
+
+/// A backend that turns todos and routines into a daily plan.
+pub trait Planner {
+    /// Short name stored in the generated plan.
+    fn name(&self) -> &str;
+
+    fn generate(&self, request: &PlanRequest) -> Result<Plan>;
+}
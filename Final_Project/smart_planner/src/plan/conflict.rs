@@ -0,0 +1,604 @@
+// Finds what clashes in a day: blocks that overlap, blocks closer together
+// than the buffer the scheduler leaves, and task blocks that end after their
+// todo is due. Routines and calendar events the plan leaves out (added after
+// it was generated) are checked too. For each clash it works out ways to fix
+// it; a fix is tried on a copy of the plan and only kept when the blocks it
+// touched end up free of conflicts.
+use std::fmt;
+
+use chrono::{Duration, NaiveTime};
+
+use super::local::free_slots;
+use super::{BlockKind, Plan, PlanBlock, PlanRequest};
+use crate::data::PlannerData;
+use crate::error::{PlannerError, Result};
+use crate::time;
+use crate::todo::Priority;
+
+/// The shortest part a split leaves in place.
+const MIN_PART_MINUTES: i64 = 15;
+
+/// Where something taking time on the day comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Source {
+    /// A block of the plan, numbered from 1 like `plan show` does.
+    Block(usize),
+    /// A routine of the day that is not in the plan.
+    Routine(u64),
+    /// An imported calendar event that is not in the plan.
+    Busy,
+}
+
+/// Something taking time on the day.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Item {
+    pub source: Source,
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+    pub title: String,
+    pub kind: BlockKind,
+    /// When two items clash, the one with the lower rank gives way.
+    pub rank: u8,
+}
+
+impl Item {
+    /// Whether a resolution may move it. Routines and calendar events stay
+    /// where they are.
+    pub fn movable(&self) -> bool {
+        matches!(self.source, Source::Block(_))
+            && !matches!(self.kind, BlockKind::Routine | BlockKind::Busy)
+    }
+
+    fn length(&self) -> Duration {
+        self.end - self.start
+    }
+}
+
+impl fmt::Display for Item {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.source {
+            Source::Block(number) => write!(f, "block {}", number)?,
+            Source::Routine(_) => write!(f, "routine")?,
+            Source::Busy => write!(f, "busy")?,
+        }
+        write!(
+            f,
+            " \"{}\" ({}-{})",
+            self.title,
+            self.start.format("%H:%M"),
+            self.end.format("%H:%M")
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConflictKind {
+    Overlap {
+        minutes: i64,
+    },
+    /// Less than the buffer between the end of one and the start of the other.
+    TooClose {
+        gap: i64,
+    },
+    /// A task block ends after its todo is due or its deadline timer runs out.
+    PastDeadline {
+        deadline: NaiveTime,
+        reason: String,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Conflict {
+    pub kind: ConflictKind,
+    /// The item that should give way: the movable one, the one with the
+    /// lower rank, or the later one when that does not decide.
+    pub item: Item,
+    /// What it clashes with, none for a deadline.
+    pub other: Option<Item>,
+}
+
+impl fmt::Display for Conflict {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match (&self.kind, &self.other) {
+            (ConflictKind::Overlap { minutes }, Some(other)) => {
+                write!(f, "{} overlaps {} by {}m", self.item, other, minutes)
+            }
+            (ConflictKind::TooClose { gap }, Some(other)) => {
+                write!(f, "{} is only {}m away from {}", self.item, gap, other)
+            }
+            (ConflictKind::PastDeadline { deadline, reason }, _) => write!(
+                f,
+                "{} ends after {} at {}",
+                self.item,
+                reason,
+                deadline.format("%H:%M")
+            ),
+            (_, None) => write!(f, "{}", self.item),
+        }
+    }
+}
+
+/// A way to fix a conflict. Blocks are numbered from 1.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Resolution {
+    /// Pushes a block later, and the blocks after it as far as needed.
+    Shift { moves: Vec<(usize, NaiveTime)> },
+    /// Ends a block at `end` and does the rest of it from `rest_start`.
+    Split {
+        block: usize,
+        end: NaiveTime,
+        rest_start: NaiveTime,
+    },
+    /// Moves a block to the next free slot, keeping its length.
+    Move { block: usize, start: NaiveTime },
+}
+
+impl fmt::Display for Resolution {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Resolution::Shift { moves } => {
+                let (block, start) = moves[0];
+                write!(f, "shift block {} to {}", block, start.format("%H:%M"))?;
+                let rest: Vec<String> = moves[1..]
+                    .iter()
+                    .map(|(block, start)| format!("block {} to {}", block, start.format("%H:%M")))
+                    .collect();
+                if !rest.is_empty() {
+                    write!(f, " (and {})", rest.join(", "))?;
+                }
+                Ok(())
+            }
+            Resolution::Split {
+                block,
+                end,
+                rest_start,
+            } => write!(
+                f,
+                "split block {}: stop at {} and do the rest from {}",
+                block,
+                end.format("%H:%M"),
+                rest_start.format("%H:%M")
+            ),
+            Resolution::Move { block, start } => {
+                write!(f, "move block {} to {}", block, start.format("%H:%M"))
+            }
+        }
+    }
+}
+
+/// Checks a day's plan against itself, the day's routines and calendar
+/// events, and the deadlines of its todos.
+#[derive(Debug, Clone)]
+pub struct ConflictChecker {
+    /// Minutes wanted between two blocks.
+    pub buffer_minutes: u32,
+    /// Resolutions only move blocks inside this part of the day.
+    pub day_start: NaiveTime,
+    pub day_end: NaiveTime,
+}
+
+impl Default for ConflictChecker {
+    fn default() -> Self {
+        ConflictChecker {
+            buffer_minutes: 5,
+            day_start: NaiveTime::from_hms_opt(8, 0, 0).unwrap(),
+            day_end: NaiveTime::from_hms_opt(22, 0, 0).unwrap(),
+        }
+    }
+}
+
+/// Adds without going past midnight.
+fn add(time: NaiveTime, length: Duration) -> Option<NaiveTime> {
+    let (sum, wrapped) = time.overflowing_add_signed(length);
+    (wrapped == 0).then_some(sum)
+}
+
+/// Clamps to the start and end of the day instead of wrapping.
+fn add_clamped(time: NaiveTime, length: Duration) -> NaiveTime {
+    add(time, length).unwrap_or_else(|| {
+        if length < Duration::zero() {
+            NaiveTime::MIN
+        } else {
+            NaiveTime::from_hms_opt(23, 59, 59).unwrap()
+        }
+    })
+}
+
+/// Whether two spans overlap or leave less than `buffer` between them.
+fn too_close(
+    (start, end): (NaiveTime, NaiveTime),
+    (other_start, other_end): (NaiveTime, NaiveTime),
+    buffer: Duration,
+) -> bool {
+    start - other_end < buffer && other_start - end < buffer
+}
+
+fn rank(block: &PlanBlock, data: &PlannerData) -> u8 {
+    match block.kind {
+        BlockKind::Busy => 5,
+        BlockKind::Routine => 4,
+        BlockKind::Task => match block.todo_id.and_then(|id| data.todo(id).ok()) {
+            Some(todo) => match todo.priority {
+                Priority::High => 3,
+                Priority::Medium => 2,
+                Priority::Low => 1,
+            },
+            None => 2,
+        },
+        BlockKind::Custom => 2,
+        BlockKind::Break => 0,
+    }
+}
+
+/// Picks which of two clashing items gives way, returning it first.
+fn give_way<'a>(first: &'a Item, second: &'a Item) -> (&'a Item, &'a Item) {
+    let keep_first = match (first.movable(), second.movable()) {
+        (true, false) => false,
+        (false, true) => true,
+        _ => first.rank >= second.rank,
+    };
+    if keep_first {
+        (second, first)
+    } else {
+        (first, second)
+    }
+}
+
+impl ConflictChecker {
+    fn buffer(&self) -> Duration {
+        Duration::minutes(self.buffer_minutes as i64)
+    }
+
+    /// Everything taking time on the plan's day, in time order: the blocks,
+    /// and the routines and calendar events the plan leaves out.
+    pub fn items(&self, plan: &Plan, data: &PlannerData) -> Vec<Item> {
+        let mut items: Vec<Item> = plan
+            .blocks
+            .iter()
+            .enumerate()
+            .map(|(index, block)| Item {
+                source: Source::Block(index + 1),
+                start: block.start,
+                end: block.end,
+                title: block.title.clone(),
+                kind: block.kind,
+                rank: rank(block, data),
+            })
+            .collect();
+        let request = PlanRequest::new(plan.date, &[], &data.routines).with_busy(&data.busy);
+        for fixed in request.fixed_blocks() {
+            let in_plan = plan.blocks.iter().any(|block| match fixed.routine_id {
+                Some(id) => block.routine_id == Some(id),
+                None => {
+                    block.kind == BlockKind::Busy
+                        && (block.start, block.end) == (fixed.start, fixed.end)
+                        && block.title == fixed.title
+                }
+            });
+            if in_plan {
+                continue;
+            }
+            items.push(Item {
+                source: match fixed.routine_id {
+                    Some(id) => Source::Routine(id),
+                    None => Source::Busy,
+                },
+                rank: rank(&fixed, data),
+                start: fixed.start,
+                end: fixed.end,
+                title: fixed.title,
+                kind: fixed.kind,
+            });
+        }
+        items.sort_by_key(|item| (item.start, item.end));
+        items
+    }
+
+    /// The earliest local time on the plan's day that the todo of a block
+    /// has to be finished by, and why.
+    fn deadline(
+        &self,
+        plan: &Plan,
+        todo_id: u64,
+        data: &PlannerData,
+    ) -> Option<(NaiveTime, String)> {
+        let on_day = |due| {
+            let local = time::to_local(due);
+            (local.date() == plan.date).then_some(local.time())
+        };
+        let mut deadlines = Vec::new();
+        if let Some(due) = data.todo(todo_id).ok().and_then(|todo| todo.due) {
+            if let Some(deadline) = on_day(due) {
+                deadlines.push((deadline, format!("todo #{} is due", todo_id)));
+            }
+        }
+        for timer in data.pending_timers() {
+            if timer.todo_id != Some(todo_id) {
+                continue;
+            }
+            if let Some(deadline) = on_day(timer.due) {
+                deadlines.push((
+                    deadline,
+                    format!("timer #{} \"{}\" runs out", timer.id, timer.label),
+                ));
+            }
+        }
+        deadlines.into_iter().min_by_key(|(deadline, _)| *deadline)
+    }
+
+    /// Every conflict of the day, in time order. At least one side of a
+    /// clash is a block of the plan; back-to-back routines and events are
+    /// left alone.
+    pub fn check(&self, plan: &Plan, data: &PlannerData) -> Vec<Conflict> {
+        let items = self.items(plan, data);
+        let buffer = self.buffer();
+        let mut conflicts = Vec::new();
+        for (index, first) in items.iter().enumerate() {
+            for second in &items[index + 1..] {
+                let gap = second.start - first.end;
+                if gap >= buffer {
+                    // Items come in start order, so the rest are further away
+                    break;
+                }
+                let kind = if gap < Duration::zero() {
+                    if !matches!(first.source, Source::Block(_))
+                        && !matches!(second.source, Source::Block(_))
+                    {
+                        continue;
+                    }
+                    ConflictKind::Overlap {
+                        minutes: (first.end.min(second.end) - second.start).num_minutes(),
+                    }
+                } else {
+                    if !first.movable() && !second.movable() {
+                        continue;
+                    }
+                    ConflictKind::TooClose {
+                        gap: gap.num_minutes(),
+                    }
+                };
+                let (item, other) = give_way(first, second);
+                conflicts.push(Conflict {
+                    kind,
+                    item: item.clone(),
+                    other: Some(other.clone()),
+                });
+            }
+        }
+        for item in &items {
+            let Source::Block(number) = item.source else {
+                continue;
+            };
+            let Some(todo_id) = plan.blocks[number - 1].todo_id else {
+                continue;
+            };
+            if let Some((deadline, reason)) = self.deadline(plan, todo_id, data) {
+                if item.end > deadline {
+                    conflicts.push(Conflict {
+                        kind: ConflictKind::PastDeadline { deadline, reason },
+                        item: item.clone(),
+                        other: None,
+                    });
+                }
+            }
+        }
+        conflicts.sort_by_key(|conflict| (conflict.item.start, conflict.item.end));
+        conflicts
+    }
+
+    /// The first start at or after `from` where `length` fits between the
+    /// `taken` items with the buffer around them, ending by `deadline`.
+    fn free_start(
+        &self,
+        taken: &[&Item],
+        length: Duration,
+        from: NaiveTime,
+        deadline: Option<NaiveTime>,
+    ) -> Option<NaiveTime> {
+        let buffer = self.buffer();
+        let padded: Vec<PlanBlock> = taken
+            .iter()
+            .filter_map(|item| {
+                PlanBlock::new(
+                    add_clamped(item.start, -buffer),
+                    add_clamped(item.end, buffer),
+                    &item.title,
+                    item.kind,
+                )
+                .ok()
+            })
+            .collect();
+        let day_end = deadline.map_or(self.day_end, |deadline| deadline.min(self.day_end));
+        free_slots(self.day_start, day_end, &padded)
+            .into_iter()
+            .find_map(|(start, end)| {
+                let start = start.max(from);
+                (start < end && end - start >= length).then_some(start)
+            })
+    }
+
+    /// Moves `target` to `start` and pushes the movable blocks after it
+    /// along, leaving `keep` where it is. Nothing is offered when that runs
+    /// into something that cannot move or past the end of the day.
+    fn shift(
+        &self,
+        items: &[Item],
+        target: &Item,
+        keep: &Item,
+        start: NaiveTime,
+    ) -> Option<Resolution> {
+        let buffer = self.buffer();
+        let Source::Block(number) = target.source else {
+            return None;
+        };
+        let mut moves = vec![(number, start)];
+        let mut placed = vec![(start, add(start, target.length())?)];
+        let mut moved = vec![target.source];
+        for item in items {
+            if !item.movable() || item.start < target.start || item.source == target.source {
+                continue;
+            }
+            if item.source == keep.source {
+                continue;
+            }
+            let cursor = placed.last().unwrap().1;
+            if item.start - cursor >= buffer {
+                break;
+            }
+            let Source::Block(number) = item.source else {
+                continue;
+            };
+            let new_start = add(cursor, buffer)?;
+            moves.push((number, new_start));
+            placed.push((new_start, add(new_start, item.length())?));
+            moved.push(item.source);
+        }
+        if placed.last().unwrap().1 > self.day_end {
+            return None;
+        }
+        let clash = items
+            .iter()
+            .filter(|item| !moved.contains(&item.source))
+            .any(|item| {
+                placed
+                    .iter()
+                    .any(|span| too_close(*span, (item.start, item.end), buffer))
+            });
+        (!clash).then_some(Resolution::Shift { moves })
+    }
+
+    /// Ways to fix a conflict, best first. There are none when the item
+    /// giving way cannot be moved, like two calendar events overlapping.
+    pub fn resolutions(
+        &self,
+        plan: &Plan,
+        data: &PlannerData,
+        conflict: &Conflict,
+    ) -> Vec<Resolution> {
+        let item = &conflict.item;
+        let Source::Block(block) = item.source else {
+            return Vec::new();
+        };
+        if !item.movable() {
+            return Vec::new();
+        }
+        let items = self.items(plan, data);
+        let others: Vec<&Item> = items
+            .iter()
+            .filter(|other| other.source != item.source)
+            .collect();
+        let buffer = self.buffer();
+        let mut options = Vec::new();
+        match (&conflict.kind, &conflict.other) {
+            (ConflictKind::PastDeadline { deadline, .. }, _) => {
+                if let Some(start) =
+                    self.free_start(&others, item.length(), self.day_start, Some(*deadline))
+                {
+                    options.push(Resolution::Move { block, start });
+                }
+            }
+            (kind, Some(other)) => {
+                if let Some(start) = add(other.end, buffer) {
+                    options.extend(self.shift(&items, item, other, start));
+                }
+                if let (ConflictKind::Overlap { .. }, Some(end)) = (kind, add(other.start, -buffer))
+                {
+                    if item.start < other.start
+                        && end - item.start >= Duration::minutes(MIN_PART_MINUTES)
+                    {
+                        let rest = item.end - end;
+                        if let Some(rest_start) = self.free_start(&others, rest, other.end, None) {
+                            options.push(Resolution::Split {
+                                block,
+                                end,
+                                rest_start,
+                            });
+                        }
+                    }
+                }
+                let start = self
+                    .free_start(&others, item.length(), other.end, None)
+                    .or_else(|| self.free_start(&others, item.length(), self.day_start, None));
+                if let Some(start) = start {
+                    let same_as_shift = Resolution::Shift {
+                        moves: vec![(block, start)],
+                    };
+                    if !options.contains(&same_as_shift) {
+                        options.push(Resolution::Move { block, start });
+                    }
+                }
+            }
+            (_, None) => {}
+        }
+        options
+    }
+
+    /// Applies a resolution to a copy of the plan. The copy is returned only
+    /// when every block it moved or created is free of conflicts, so a plan
+    /// is never left half fixed.
+    pub fn apply(&self, plan: &Plan, data: &PlannerData, resolution: &Resolution) -> Result<Plan> {
+        let mut fixed = plan.clone();
+        let mut touched: Vec<PlanBlock> = Vec::new();
+        let place = |plan: &mut Plan, number: usize, start: NaiveTime| -> Result<PlanBlock> {
+            let block = plan.block_mut(number)?;
+            let end = add(start, block.end - block.start).ok_or_else(|| {
+                PlannerError::Invalid(format!("block '{}' would run past midnight", block.title))
+            })?;
+            block.start = start;
+            block.end = end;
+            Ok(block.clone())
+        };
+        match resolution {
+            Resolution::Shift { moves } => {
+                for (number, start) in moves {
+                    touched.push(place(&mut fixed, *number, *start)?);
+                }
+            }
+            Resolution::Move { block, start } => touched.push(place(&mut fixed, *block, *start)?),
+            Resolution::Split {
+                block,
+                end,
+                rest_start,
+            } => {
+                let first = fixed.block_mut(*block)?;
+                if *end <= first.start || *end >= first.end {
+                    return Err(PlannerError::Invalid(format!(
+                        "block '{}' cannot be split at {}",
+                        first.title,
+                        end.format("%H:%M")
+                    )));
+                }
+                let mut rest = first.clone();
+                rest.start = *rest_start;
+                rest.end = add(*rest_start, first.end - *end).ok_or_else(|| {
+                    PlannerError::Invalid(format!(
+                        "block '{}' would run past midnight",
+                        first.title
+                    ))
+                })?;
+                first.end = *end;
+                touched.push(first.clone());
+                touched.push(rest.clone());
+                fixed.blocks.push(rest);
+            }
+        }
+        fixed.sort();
+        fixed.edited = true;
+
+        let is_touched = |item: &Item| {
+            touched.iter().any(|block| {
+                (block.start, block.end) == (item.start, item.end) && block.title == item.title
+            })
+        };
+        let left = self.check(&fixed, data);
+        if let Some(conflict) = left.iter().find(|conflict| {
+            is_touched(&conflict.item) || conflict.other.as_ref().is_some_and(is_touched)
+        }) {
+            return Err(PlannerError::Invalid(format!(
+                "'{}' would leave a conflict: {}",
+                resolution, conflict
+            )));
+        }
+        Ok(fixed)
+    }
+}
@@ -0,0 +1,232 @@
+// A planner that asks a chat-completion API (OpenAI compatible, which most
+// providers and local model servers speak) to lay out the day.
+use std::time::Duration;
+
+use serde::Deserialize;
+use serde_json::json;
+
+use super::{BlockKind, Plan, PlanBlock, PlanRequest, Planner};
+use crate::error::{PlannerError, Result};
+use crate::time;
+
+const SYSTEM_PROMPT: &str = "You plan a single day. You get the working hours, \
+fixed blocks (routines and busy calendar events) and open todos as JSON. Put the \
+most important todos into the free time, finish todos before their due time and \
+use their estimated minutes. Never move or overlap a fixed block, and keep every \
+block within the working hours. Answer with JSON only, in the form \
+{\"blocks\": [{\"start\": \"HH:MM\", \"end\": \"HH:MM\", \"title\": \"...\", \"todo_id\": 1}]}. \
+Use \"todo_id\": null for breaks.";
+
+/// Planner backed by an HTTP chat-completion endpoint.
+#[derive(Debug, Clone)]
+pub struct LlmPlanner {
+    /// Base URL of the API, for example `https://api.openai.com/v1`.
+    pub base_url: String,
+    pub model: String,
+    pub api_key: Option<String>,
+    client: reqwest::blocking::Client,
+}
+
+#[derive(Deserialize)]
+struct ChatResponse {
+    choices: Vec<Choice>,
+}
+
+#[derive(Deserialize)]
+struct Choice {
+    message: Message,
+}
+
+#[derive(Deserialize)]
+struct Message {
+    content: String,
+}
+
+#[derive(Deserialize)]
+struct SuggestedPlan {
+    blocks: Vec<SuggestedBlock>,
+}
+
+#[derive(Deserialize)]
+struct SuggestedBlock {
+    start: String,
+    end: String,
+    title: String,
+    #[serde(default)]
+    todo_id: Option<u64>,
+}
+
+impl LlmPlanner {
+    pub fn new(base_url: &str, model: &str, api_key: Option<String>) -> Result<Self> {
+        let client = reqwest::blocking::Client::builder()
+            .timeout(Duration::from_secs(60))
+            .build()?;
+        Ok(LlmPlanner {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            model: model.to_string(),
+            api_key,
+            client,
+        })
+    }
+
+    fn prompt(request: &PlanRequest) -> String {
+        let fixed: Vec<_> = request
+            .fixed_blocks()
+            .iter()
+            .map(|block| {
+                json!({
+                    "start": block.start.format("%H:%M").to_string(),
+                    "end": block.end.format("%H:%M").to_string(),
+                    "name": block.title,
+                    "kind": block.kind.to_string(),
+                })
+            })
+            .collect();
+        let todos: Vec<_> = request
+            .todos
+            .iter()
+            .map(|todo| {
+                json!({
+                    "id": todo.id,
+                    "title": todo.title,
+                    "priority": todo.priority.to_string(),
+                    "due": todo.due.map(time::format_local),
+                    "estimate_minutes": todo.estimate_minutes,
+                })
+            })
+            .collect();
+        json!({
+            "date": request.date.to_string(),
+            "day_start": request.day_start.format("%H:%M").to_string(),
+            "day_end": request.day_end.format("%H:%M").to_string(),
+            "fixed": fixed,
+            "todos": todos,
+        })
+        .to_string()
+    }
+
+    /// Turns the model's answer into a plan. Routines and busy blocks always
+    /// come from the request, so a model cannot drop or move them, and the
+    /// answer is refused when its blocks overlap them or each other, or fall
+    /// outside the working day.
+    fn build_plan(&self, request: &PlanRequest, content: &str) -> Result<Plan> {
+        // Models like to wrap JSON in a code fence, keep only the object
+        let json_text = match (content.find('{'), content.rfind('}')) {
+            (Some(first), Some(last)) if first < last => &content[first..=last],
+            _ => {
+                return Err(PlannerError::Backend(format!(
+                    "the model did not answer with JSON: {}",
+                    content
+                )))
+            }
+        };
+        let suggested: SuggestedPlan = serde_json::from_str(json_text)
+            .map_err(|e| PlannerError::Backend(format!("the model's plan is malformed: {}", e)))?;
+
+        let mut plan = Plan::new(request.date, self.name());
+        plan.blocks.extend(request.fixed_blocks());
+        for suggestion in suggested.blocks {
+            let start = time::parse_time(&suggestion.start)
+                .map_err(|e| PlannerError::Backend(e.to_string()))?;
+            let end = time::parse_time(&suggestion.end)
+                .map_err(|e| PlannerError::Backend(e.to_string()))?;
+            // Ids the model made up are kept as plain blocks
+            let todo_id = suggestion
+                .todo_id
+                .filter(|id| request.todos.iter().any(|todo| todo.id == *id));
+            let kind = if todo_id.is_some() {
+                BlockKind::Task
+            } else {
+                BlockKind::Break
+            };
+            let mut block = PlanBlock::new(start, end, &suggestion.title, kind)
+                .map_err(|e| PlannerError::Backend(e.to_string()))?;
+            block.todo_id = todo_id;
+            if block.start < request.day_start || block.end > request.day_end {
+                return Err(PlannerError::Backend(format!(
+                    "the model put '{}' at {}, outside the day from {} to {}",
+                    block.title,
+                    span(&block),
+                    request.day_start.format("%H:%M"),
+                    request.day_end.format("%H:%M")
+                )));
+            }
+            if let Some(other) = plan
+                .blocks
+                .iter()
+                .find(|other| block.start < other.end && other.start < block.end)
+            {
+                return Err(PlannerError::Backend(format!(
+                    "the model put '{}' at {}, over '{}' at {}",
+                    block.title,
+                    span(&block),
+                    other.title,
+                    span(other)
+                )));
+            }
+            plan.blocks.push(block);
+        }
+        plan.blocks.sort_by_key(|block| (block.start, block.end));
+
+        for todo in &request.todos {
+            if !plan
+                .blocks
+                .iter()
+                .any(|block| block.todo_id == Some(todo.id))
+            {
+                plan.unscheduled.push(todo.id);
+            }
+        }
+        Ok(plan)
+    }
+}
+
+/// A block's times, like `10:00-11:30`.
+fn span(block: &PlanBlock) -> String {
+    format!(
+        "{}-{}",
+        block.start.format("%H:%M"),
+        block.end.format("%H:%M")
+    )
+}
+
+impl Planner for LlmPlanner {
+    fn name(&self) -> &str {
+        "llm"
+    }
+
+    fn generate(&self, request: &PlanRequest) -> Result<Plan> {
+        let body = json!({
+            "model": self.model,
+            "temperature": 0,
+            "messages": [
+                { "role": "system", "content": SYSTEM_PROMPT },
+                { "role": "user", "content": Self::prompt(request) },
+            ],
+        });
+        let mut http = self
+            .client
+            .post(format!("{}/chat/completions", self.base_url))
+            .json(&body);
+        if let Some(key) = &self.api_key {
+            http = http.bearer_auth(key);
+        }
+        let response = http.send()?;
+        let status = response.status();
+        if !status.is_success() {
+            let text = response.text().unwrap_or_default();
+            return Err(PlannerError::Http(format!(
+                "{} returned {}: {}",
+                self.base_url, status, text
+            )));
+        }
+        let chat: ChatResponse = response.json()?;
+        let content = chat
+            .choices
+            .into_iter()
+            .next()
+            .map(|choice| choice.message.content)
+            .ok_or_else(|| PlannerError::Backend("the model returned no choices".to_string()))?;
+        self.build_plan(request, &content)
+    }
+}
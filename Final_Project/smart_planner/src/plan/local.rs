@@ -0,0 +1,122 @@
+// The offline planner. It does not need a network connection and always
+// produces the same plan for the same todos and routines.
+use std::cmp::Reverse;
+
+use chrono::{Duration, NaiveTime};
+
+use super::{BlockKind, Plan, PlanBlock, PlanRequest, Planner};
+use crate::error::Result;
+use crate::time;
+
+/// Packs open todos into the free time around routines.
+///
+/// Todos are placed by priority (high first), then by due date. A todo due on
+/// the planned day has to finish before its due time, otherwise it is listed
+/// as unscheduled.
+#[derive(Debug, Clone)]
+pub struct LocalScheduler {
+    /// Gap left after every scheduled task.
+    pub buffer_minutes: u32,
+    /// Length used for todos without an estimate.
+    pub default_estimate_minutes: u32,
+}
+
+impl Default for LocalScheduler {
+    fn default() -> Self {
+        LocalScheduler {
+            buffer_minutes: 5,
+            default_estimate_minutes: 30,
+        }
+    }
+}
+
+/// Cuts the fixed blocks out of the working day and returns what is left.
+pub fn free_slots(
+    day_start: NaiveTime,
+    day_end: NaiveTime,
+    fixed: &[PlanBlock],
+) -> Vec<(NaiveTime, NaiveTime)> {
+    let mut slots = vec![(day_start, day_end)];
+    for block in fixed {
+        let mut next = Vec::new();
+        for (start, end) in slots {
+            if block.end <= start || block.start >= end {
+                next.push((start, end));
+                continue;
+            }
+            if block.start > start {
+                next.push((start, block.start));
+            }
+            if block.end < end {
+                next.push((block.end, end));
+            }
+        }
+        slots = next;
+    }
+    slots
+}
+
+impl Planner for LocalScheduler {
+    fn name(&self) -> &str {
+        "local"
+    }
+
+    fn generate(&self, request: &PlanRequest) -> Result<Plan> {
+        let mut plan = Plan::new(request.date, self.name());
+        let fixed = request.fixed_blocks();
+        let mut slots = free_slots(request.day_start, request.day_end, &fixed);
+        plan.blocks.extend(fixed);
+
+        let mut todos = request.todos.clone();
+        todos.sort_by_key(|todo| {
+            (
+                Reverse(todo.priority),
+                todo.due.is_none(),
+                todo.due,
+                todo.id,
+            )
+        });
+
+        for todo in todos {
+            let minutes = todo
+                .estimate_minutes
+                .unwrap_or(self.default_estimate_minutes) as i64;
+            let length = Duration::minutes(minutes);
+            // Only a todo due on this very day limits where it can go. Overdue
+            // todos go as early as possible, later ones anywhere in the day.
+            let deadline = todo
+                .due
+                .map(time::to_local)
+                .filter(|due| due.date() == request.date)
+                .map(|due| due.time());
+
+            let found = slots.iter().position(|(start, end)| {
+                let limit = match deadline {
+                    Some(deadline) if deadline < *end => deadline,
+                    _ => *end,
+                };
+                *start + length <= limit && *start + length > *start
+            });
+            let Some(index) = found else {
+                plan.unscheduled.push(todo.id);
+                continue;
+            };
+
+            let (start, end) = slots[index];
+            let block_end = start + length;
+            let mut block = PlanBlock::new(start, block_end, &todo.title, BlockKind::Task)?;
+            block.todo_id = Some(todo.id);
+            plan.blocks.push(block);
+
+            let next_start = block_end + Duration::minutes(self.buffer_minutes as i64);
+            if next_start < end && next_start > block_end {
+                slots[index] = (next_start, end);
+            } else {
+                slots.remove(index);
+            }
+        }
+
+        plan.blocks.sort_by_key(|block| (block.start, block.end));
+        Ok(plan)
+    }
+}
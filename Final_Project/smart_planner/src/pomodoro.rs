@@ -0,0 +1,178 @@
+// Pomodoro technique on top of the stopwatch: work for a while, take a short
+// break, and a longer one every few rounds. Each work interval is recorded as
+// a stopwatch session on the subject, so reports count it like any other
+// focus time.
+use std::fmt;
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::error::{PlannerError, Result};
+
+/// Lengths of the phases, in minutes. Read from the `pomodoro` section of
+/// `config.json` and overridable per run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PomodoroSettings {
+    pub work_minutes: u32,
+    pub short_break_minutes: u32,
+    pub long_break_minutes: u32,
+    /// A long break comes after every this many pomodoros.
+    pub long_break_every: u32,
+}
+
+impl Default for PomodoroSettings {
+    fn default() -> Self {
+        PomodoroSettings {
+            work_minutes: 25,
+            short_break_minutes: 5,
+            long_break_minutes: 15,
+            long_break_every: 4,
+        }
+    }
+}
+
+impl PomodoroSettings {
+    pub fn validate(&self) -> Result<()> {
+        let lengths = [
+            ("work", self.work_minutes),
+            ("short break", self.short_break_minutes),
+            ("long break", self.long_break_minutes),
+        ];
+        for (name, minutes) in lengths {
+            if minutes == 0 || minutes > 24 * 60 {
+                return Err(PlannerError::Invalid(format!(
+                    "the {} lasts between 1 minute and 24 hours",
+                    name
+                )));
+            }
+        }
+        if self.long_break_every == 0 {
+            return Err(PlannerError::Invalid(
+                "a long break has to come after at least 1 pomodoro".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Phase {
+    Work,
+    ShortBreak,
+    LongBreak,
+}
+
+impl fmt::Display for Phase {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Phase::Work => "work",
+            Phase::ShortBreak => "short break",
+            Phase::LongBreak => "long break",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// The pomodoro going on right now.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PomodoroRun {
+    pub subject: String,
+    pub settings: PomodoroSettings,
+    pub phase: Phase,
+    pub phase_start: DateTime<Utc>,
+    /// Work intervals finished in this run.
+    pub completed: u32,
+    /// Stop after this many work intervals, or go on until stopped.
+    #[serde(default)]
+    pub cycles: Option<u32>,
+    /// The stopwatch session of the current work interval.
+    #[serde(default)]
+    pub session_id: Option<u64>,
+}
+
+impl PomodoroRun {
+    pub fn phase_length(&self) -> Duration {
+        let minutes = match self.phase {
+            Phase::Work => self.settings.work_minutes,
+            Phase::ShortBreak => self.settings.short_break_minutes,
+            Phase::LongBreak => self.settings.long_break_minutes,
+        };
+        Duration::minutes(minutes as i64)
+    }
+
+    pub fn phase_end(&self) -> DateTime<Utc> {
+        self.phase_start + self.phase_length()
+    }
+
+    /// Time left in the current phase, zero once it is over.
+    pub fn remaining(&self, now: DateTime<Utc>) -> Duration {
+        (self.phase_end() - now).max(Duration::zero())
+    }
+
+    /// The break that follows the work interval that was just finished.
+    pub fn next_break(&self) -> Phase {
+        if self
+            .completed
+            .is_multiple_of(self.settings.long_break_every)
+        {
+            Phase::LongBreak
+        } else {
+            Phase::ShortBreak
+        }
+    }
+}
+
+/// A work interval, finished or cut short.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Pomodoro {
+    pub id: u64,
+    pub subject: String,
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    /// Set when the run was stopped before the interval was over.
+    #[serde(default)]
+    pub interrupted: bool,
+    /// The stopwatch session holding the same time.
+    pub session_id: u64,
+}
+
+/// A change of phase, for notifying the user.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Transition {
+    /// A work interval is over and a break starts.
+    BreakStarted {
+        at: DateTime<Utc>,
+        completed: u32,
+        phase: Phase,
+        minutes: u32,
+    },
+    /// A break is over and the next work interval starts.
+    WorkStarted { at: DateTime<Utc>, subject: String },
+    /// The last of the requested work intervals is over.
+    Finished { at: DateTime<Utc>, completed: u32 },
+}
+
+impl fmt::Display for Transition {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Transition::BreakStarted {
+                completed,
+                phase,
+                minutes,
+                ..
+            } => write!(
+                f,
+                "pomodoro {} done, take a {} ({}m)",
+                completed, phase, minutes
+            ),
+            Transition::WorkStarted { subject, .. } => {
+                write!(f, "break is over, back to {}", subject)
+            }
+            Transition::Finished { completed, .. } => {
+                write!(f, "all {} pomodoros done", completed)
+            }
+        }
+    }
+}
@@ -0,0 +1,351 @@
+// Named profiles, for several people sharing one planner installation. Each
+// profile is a planner folder of its own under `profiles/` in the home
+// folder, with its own data, config, history, vault and daemon, so nothing
+// one person does shows up in another's planner or backups. `profiles.json`
+// in the home folder says which profile is used when none is asked for.
+//
+// Data already in the home folder stays there, and is what is used when no
+// profile is picked and there is no default.
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::data::{NewTodo, PlannerData};
+use crate::error::{PlannerError, Result};
+use crate::vault;
+
+/// The folder in the home folder that holds one folder per profile.
+pub const PROFILES_DIR: &str = "profiles";
+/// The profile settings in the home folder.
+pub const SETTINGS_FILE: &str = "profiles.json";
+/// Longest profile name accepted.
+const MAX_NAME: usize = 32;
+
+/// What `profiles.json` holds.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProfileSettings {
+    /// The profile used when `--profile` is not given.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub default: Option<String>,
+}
+
+/// How much a profile keeps on disk.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Usage {
+    /// `None` for the data kept in the home folder itself.
+    pub name: Option<String>,
+    pub files: usize,
+    pub bytes: u64,
+    pub is_default: bool,
+    /// Encrypted and locked, so only the file sizes can be read.
+    pub locked: bool,
+}
+
+/// The profiles of one home folder.
+#[derive(Debug, Clone)]
+pub struct Profiles {
+    home: PathBuf,
+}
+
+impl Profiles {
+    pub fn new(home: &Path) -> Self {
+        Profiles {
+            home: home.to_path_buf(),
+        }
+    }
+
+    pub fn home(&self) -> &Path {
+        &self.home
+    }
+
+    /// Loads `profiles.json`, or the defaults when there is none.
+    pub fn settings(&self) -> Result<ProfileSettings> {
+        let path = self.home.join(SETTINGS_FILE);
+        if !path.exists() {
+            return Ok(ProfileSettings::default());
+        }
+        Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+    }
+
+    fn save_settings(&self, settings: &ProfileSettings) -> Result<()> {
+        fs::create_dir_all(&self.home)?;
+        let text = serde_json::to_string_pretty(settings)?;
+        fs::write(self.home.join(SETTINGS_FILE), text + "\n")?;
+        Ok(())
+    }
+
+    /// The names of the profiles, in order.
+    pub fn names(&self) -> Result<Vec<String>> {
+        let dir = self.home.join(PROFILES_DIR);
+        if !dir.exists() {
+            return Ok(Vec::new());
+        }
+        let mut names = Vec::new();
+        for entry in fs::read_dir(dir)? {
+            let entry = entry?;
+            if entry.path().is_dir() {
+                names.push(entry.file_name().to_string_lossy().into_owned());
+            }
+        }
+        names.sort();
+        Ok(names)
+    }
+
+    /// The folder of the profile `name`, which may not exist yet.
+    pub fn dir(&self, name: &str) -> Result<PathBuf> {
+        check_name(name)?;
+        Ok(self.home.join(PROFILES_DIR).join(name))
+    }
+
+    /// The folder of an existing profile.
+    pub fn existing(&self, name: &str) -> Result<PathBuf> {
+        let dir = self.dir(name)?;
+        if !dir.is_dir() {
+            return Err(PlannerError::Invalid(format!(
+                "there is no profile '{}', create it with `profile create {}`",
+                name, name
+            )));
+        }
+        Ok(dir)
+    }
+
+    /// The planner folder to use: the profile asked for, else the default
+    /// one, else the home folder itself.
+    pub fn resolve(&self, profile: Option<&str>) -> Result<PathBuf> {
+        match profile {
+            Some(name) => self.existing(name),
+            None => match self.settings()?.default {
+                Some(name) => self.existing(&name),
+                None => Ok(self.home.clone()),
+            },
+        }
+    }
+
+    pub fn create(&self, name: &str) -> Result<PathBuf> {
+        let dir = self.dir(name)?;
+        if dir.exists() {
+            return Err(PlannerError::Invalid(format!(
+                "the profile '{}' already exists",
+                name
+            )));
+        }
+        fs::create_dir_all(&dir)?;
+        Ok(dir)
+    }
+
+    /// Deletes the profile and everything in it, and stops it being the
+    /// default.
+    pub fn remove(&self, name: &str) -> Result<()> {
+        let dir = self.existing(name)?;
+        fs::remove_dir_all(dir)?;
+        let mut settings = self.settings()?;
+        if settings.default.as_deref() == Some(name) {
+            settings.default = None;
+            self.save_settings(&settings)?;
+        }
+        Ok(())
+    }
+
+    /// Makes `name` the default profile, or the home folder when `None`.
+    pub fn set_default(&self, name: Option<&str>) -> Result<()> {
+        if let Some(name) = name {
+            self.existing(name)?;
+        }
+        let mut settings = self.settings()?;
+        settings.default = name.map(str::to_string);
+        self.save_settings(&settings)
+    }
+
+    /// What each profile keeps on disk, in order of name, after the data in
+    /// the home folder itself when there is any.
+    pub fn usage(&self) -> Result<Vec<Usage>> {
+        let default = self.settings()?.default;
+        let mut usage = Vec::new();
+        let (files, bytes) = self.home_size()?;
+        if files > 0 {
+            usage.push(Usage {
+                name: None,
+                files,
+                bytes,
+                is_default: default.is_none(),
+                locked: vault::is_locked(&self.home)?,
+            });
+        }
+        for name in self.names()? {
+            let dir = self.dir(&name)?;
+            let (files, bytes) = folder_size(&dir)?;
+            usage.push(Usage {
+                is_default: default.as_deref() == Some(name.as_str()),
+                locked: vault::is_locked(&dir)?,
+                name: Some(name),
+                files,
+                bytes,
+            });
+        }
+        Ok(usage)
+    }
+
+    /// Number of files in the home folder and their total size, leaving out
+    /// the profiles.
+    fn home_size(&self) -> Result<(usize, u64)> {
+        let (mut files, mut bytes) = (0, 0);
+        if !self.home.exists() {
+            return Ok((files, bytes));
+        }
+        for entry in fs::read_dir(&self.home)? {
+            let entry = entry?;
+            let name = entry.file_name();
+            if name == PROFILES_DIR || name == SETTINGS_FILE {
+                continue;
+            }
+            let metadata = entry.metadata()?;
+            if metadata.is_dir() {
+                let (more, size) = folder_size(&entry.path())?;
+                files += more;
+                bytes += size;
+            } else {
+                files += 1;
+                bytes += metadata.len();
+            }
+        }
+        Ok((files, bytes))
+    }
+}
+
+/// The name of the profile whose folder `dir` is, if it is one.
+pub fn name_of(dir: &Path) -> Option<String> {
+    let parent = dir.parent()?;
+    if parent.file_name()? != PROFILES_DIR {
+        return None;
+    }
+    Some(dir.file_name()?.to_string_lossy().into_owned())
+}
+
+/// Names become folder names, so they are kept to letters, digits, `-` and
+/// `_`.
+fn check_name(name: &str) -> Result<()> {
+    let valid = !name.is_empty()
+        && name.len() <= MAX_NAME
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if !valid {
+        return Err(PlannerError::Invalid(format!(
+            "'{}' is not a profile name, use up to {} letters, digits, '-' or '_'",
+            name, MAX_NAME
+        )));
+    }
+    Ok(())
+}
+
+/// Number of files under `dir` and their total size.
+fn folder_size(dir: &Path) -> Result<(usize, u64)> {
+    let mut files = 0;
+    let mut bytes = 0;
+    let mut folders = vec![dir.to_path_buf()];
+    while let Some(folder) = folders.pop() {
+        for entry in fs::read_dir(&folder)? {
+            let entry = entry?;
+            let metadata = entry.metadata()?;
+            if metadata.is_dir() {
+                folders.push(entry.path());
+            } else {
+                files += 1;
+                bytes += metadata.len();
+            }
+        }
+    }
+    Ok((files, bytes))
+}
+
+/// Sizes like `1.2 MB`, for the usage listing.
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit + 1 < UNITS.len() {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, UNITS[unit])
+}
+
+/// Copies the routines `ids` (all when empty) from `from` into `to`, under
+/// new ids. Routines `to` already has by the same name are skipped. Returns
+/// the new ids.
+pub fn copy_routines(
+    from: &PlannerData,
+    to: &mut PlannerData,
+    ids: &[u64],
+    now: DateTime<Utc>,
+) -> Result<Vec<u64>> {
+    check_found(
+        ids,
+        from.routines.iter().map(|routine| routine.id),
+        "routine",
+    )?;
+    let mut copied = Vec::new();
+    for routine in &from.routines {
+        if !ids.is_empty() && !ids.contains(&routine.id) {
+            continue;
+        }
+        let exists = to
+            .routines
+            .iter()
+            .any(|other| other.name.eq_ignore_ascii_case(&routine.name));
+        if exists {
+            continue;
+        }
+        copied.push(to.add_routine(
+            &routine.name,
+            routine.start,
+            routine.duration_minutes,
+            routine.frequency.clone(),
+            now,
+        )?);
+    }
+    Ok(copied)
+}
+
+/// Copies the todos `ids` (all open ones when empty) from `from` into `to`
+/// as new open todos. Projects, subtasks and dependencies stay behind, as
+/// their ids mean nothing in the other profile. Returns the new ids.
+pub fn copy_todos(
+    from: &PlannerData,
+    to: &mut PlannerData,
+    ids: &[u64],
+    now: DateTime<Utc>,
+) -> Result<Vec<u64>> {
+    check_found(ids, from.todos.iter().map(|todo| todo.id), "todo")?;
+    let mut copied = Vec::new();
+    for todo in &from.todos {
+        let wanted = if ids.is_empty() {
+            !todo.done
+        } else {
+            ids.contains(&todo.id)
+        };
+        if !wanted {
+            continue;
+        }
+        let mut new = NewTodo::new(&todo.title, todo.priority);
+        new.due = todo.due;
+        new.estimate_minutes = todo.estimate_minutes;
+        new.subject = todo.subject.clone();
+        copied.push(to.add_todo(new, now)?);
+    }
+    Ok(copied)
+}
+
+fn check_found(ids: &[u64], existing: impl Iterator<Item = u64>, what: &str) -> Result<()> {
+    let existing: Vec<u64> = existing.collect();
+    match ids.iter().find(|id| !existing.contains(id)) {
+        Some(id) => Err(PlannerError::NotFound(format!("{} #{}", what, id))),
+        None => Ok(()),
+    }
+}
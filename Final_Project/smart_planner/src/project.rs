@@ -0,0 +1,25 @@
+// Projects group todos for work that takes weeks. Inside a project todos can
+// have subtasks and can be blocked by other todos; `graph` orders them by
+// those links and finds the critical path.
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+pub mod graph;
+
+/// A named group of todos.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Project {
+    pub id: u64,
+    pub name: String,
+    pub created_at: DateTime<Utc>,
+}
+
+impl Project {
+    pub fn new(id: u64, name: &str, created_at: DateTime<Utc>) -> Self {
+        Project {
+            id,
+            name: name.to_string(),
+            created_at,
+        }
+    }
+}
@@ -0,0 +1,204 @@
+// The order todos have to happen in. A todo waits for the todos blocking it
+// and for its own subtasks, since a parent is only finished once its children
+// are. Those links must never form a loop, or nothing in the loop could start.
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BTreeSet, BinaryHeap};
+
+use crate::error::{PlannerError, Result};
+use crate::todo::Todo;
+
+/// The longest chain of open todos, by estimated minutes. Nothing at its end
+/// can be done sooner than the chain takes.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CriticalPath {
+    /// The todos in the order they have to be done.
+    pub todos: Vec<u64>,
+    pub minutes: u32,
+    /// Open todos without an estimate, counted as taking no time.
+    pub unestimated: Vec<u64>,
+}
+
+/// What each todo waits for.
+pub struct Graph<'a> {
+    todos: BTreeMap<u64, &'a Todo>,
+    needs: BTreeMap<u64, Vec<u64>>,
+}
+
+impl<'a> Graph<'a> {
+    pub fn new(todos: &'a [Todo]) -> Self {
+        let mut needs: BTreeMap<u64, Vec<u64>> = todos
+            .iter()
+            .map(|todo| (todo.id, todo.blocked_by.clone()))
+            .collect();
+        for todo in todos {
+            if let Some(waiting) = todo.parent.and_then(|parent| needs.get_mut(&parent)) {
+                waiting.push(todo.id);
+            }
+        }
+        let ids: BTreeSet<u64> = needs.keys().copied().collect();
+        for waiting in needs.values_mut() {
+            waiting.retain(|id| ids.contains(id));
+        }
+        Graph {
+            todos: todos.iter().map(|todo| (todo.id, todo)).collect(),
+            needs,
+        }
+    }
+
+    /// A loop of todos each waiting for the next, starting and ending with
+    /// the same one, like `[1, 3, 1]`.
+    pub fn cycle(&self) -> Option<Vec<u64>> {
+        let mut finished = BTreeSet::new();
+        for &id in self.needs.keys() {
+            let mut path = Vec::new();
+            if let Some(cycle) = self.visit(id, &mut path, &mut finished) {
+                return Some(cycle);
+            }
+        }
+        None
+    }
+
+    fn visit(
+        &self,
+        id: u64,
+        path: &mut Vec<u64>,
+        finished: &mut BTreeSet<u64>,
+    ) -> Option<Vec<u64>> {
+        if let Some(start) = path.iter().position(|on_path| *on_path == id) {
+            let mut cycle = path[start..].to_vec();
+            cycle.push(id);
+            return Some(cycle);
+        }
+        if finished.contains(&id) {
+            return None;
+        }
+        path.push(id);
+        for &need in &self.needs[&id] {
+            if let Some(cycle) = self.visit(need, path, finished) {
+                return Some(cycle);
+            }
+        }
+        path.pop();
+        finished.insert(id);
+        None
+    }
+
+    /// The open todos `id` still waits for.
+    pub fn waiting_for(&self, id: u64) -> Vec<u64> {
+        self.needs
+            .get(&id)
+            .into_iter()
+            .flatten()
+            .copied()
+            .filter(|need| !self.todos[need].done)
+            .collect()
+    }
+
+    /// Open todos with nothing left to wait for.
+    pub fn is_ready(&self, id: u64) -> bool {
+        self.todos.get(&id).is_some_and(|todo| !todo.done) && self.waiting_for(id).is_empty()
+    }
+
+    /// Every open todo, each after the todos it waits for. Of the todos that
+    /// could come next, the most important goes first.
+    pub fn order(&self) -> Result<Vec<u64>> {
+        let open: Vec<u64> = self
+            .todos
+            .values()
+            .filter(|todo| !todo.done)
+            .map(|todo| todo.id)
+            .collect();
+        let mut left: BTreeMap<u64, usize> = open
+            .iter()
+            .map(|id| (*id, self.waiting_for(*id).len()))
+            .collect();
+        let mut unblocks: BTreeMap<u64, Vec<u64>> = BTreeMap::new();
+        for id in &open {
+            for need in self.waiting_for(*id) {
+                unblocks.entry(need).or_default().push(*id);
+            }
+        }
+
+        let key = |id: u64| (self.todos[&id].priority, Reverse(id));
+        let mut free: BinaryHeap<_> = left
+            .iter()
+            .filter(|(_, count)| **count == 0)
+            .map(|(id, _)| key(*id))
+            .collect();
+        let mut order = Vec::new();
+        while let Some((_, Reverse(id))) = free.pop() {
+            order.push(id);
+            for next in unblocks.get(&id).into_iter().flatten() {
+                let count = left.get_mut(next).expect("every open todo is counted");
+                *count -= 1;
+                if *count == 0 {
+                    free.push(key(*next));
+                }
+            }
+        }
+        if order.len() < open.len() {
+            let cycle = self.cycle().unwrap_or_default();
+            return Err(PlannerError::Invalid(format!(
+                "the todos wait for each other in a loop: {}",
+                show_cycle(&cycle)
+            )));
+        }
+        Ok(order)
+    }
+
+    /// The critical path to the open todos `ends` picks out, going through
+    /// whatever they wait for, even outside of them.
+    pub fn critical_path(&self, ends: impl Fn(&Todo) -> bool) -> Result<CriticalPath> {
+        let mut finish: BTreeMap<u64, (u32, Option<u64>)> = BTreeMap::new();
+        for id in self.order()? {
+            let before = self
+                .waiting_for(id)
+                .into_iter()
+                .map(|need| (finish[&need].0, need))
+                .max_by_key(|(minutes, need)| (*minutes, Reverse(*need)));
+            let own = self.todos[&id].estimate_minutes.unwrap_or(0);
+            let (minutes, previous) = match before {
+                Some((minutes, need)) => (minutes + own, Some(need)),
+                None => (own, None),
+            };
+            finish.insert(id, (minutes, previous));
+        }
+
+        let mut path = CriticalPath::default();
+        let end = finish
+            .iter()
+            .filter(|(id, _)| ends(self.todos[id]))
+            .max_by_key(|(id, (minutes, _))| (*minutes, Reverse(**id)));
+        let Some((&end, &(minutes, _))) = end else {
+            return Ok(path);
+        };
+        path.minutes = minutes;
+        let mut at = Some(end);
+        while let Some(id) = at {
+            path.todos.push(id);
+            at = finish[&id].1;
+        }
+        path.todos.reverse();
+        path.unestimated = finish
+            .keys()
+            .filter(|id| ends(self.todos[id]) && self.todos[id].estimate_minutes.is_none())
+            .copied()
+            .collect();
+        Ok(path)
+    }
+}
+
+/// Ids as `#1, #3`.
+pub fn list_ids(ids: &[u64]) -> String {
+    join_ids(ids, ", ")
+}
+
+/// A loop from `Graph::cycle` as `#1 → #3 → #1`.
+pub fn show_cycle(cycle: &[u64]) -> String {
+    join_ids(cycle, " → ")
+}
+
+fn join_ids(ids: &[u64], separator: &str) -> String {
+    let ids: Vec<String> = ids.iter().map(|id| format!("#{}", id)).collect();
+    ids.join(separator)
+}
@@ -0,0 +1,345 @@
+// Productivity reports. A report only looks at timestamps stored in the
+// data and the date range it is asked for, so building it again for the same
+// range later gives the same numbers.
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+use chrono::{Datelike, Duration, Months, NaiveDate};
+use serde::Serialize;
+
+use crate::budget::{self, Progress};
+use crate::data::PlannerData;
+use crate::error::{PlannerError, Result};
+use crate::routine::CheckStatus;
+use crate::time;
+use crate::todo::Priority;
+
+pub mod csv;
+pub mod html;
+pub mod text;
+
+/// Days from `start` to `end`, both included.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct DateRange {
+    pub start: NaiveDate,
+    pub end: NaiveDate,
+}
+
+impl DateRange {
+    pub fn new(start: NaiveDate, end: NaiveDate) -> Result<Self> {
+        if end < start {
+            return Err(PlannerError::Invalid(format!(
+                "the range ends on {} before it starts on {}",
+                end, start
+            )));
+        }
+        Ok(DateRange { start, end })
+    }
+
+    pub fn days(&self) -> i64 {
+        (self.end - self.start).num_days() + 1
+    }
+
+    /// The range of the same length right before this one.
+    pub fn previous(&self) -> DateRange {
+        let length = Duration::days(self.days());
+        DateRange {
+            start: self.start - length,
+            end: self.end - length,
+        }
+    }
+
+    pub fn dates(&self) -> impl Iterator<Item = NaiveDate> {
+        let end = self.end;
+        self.start.iter_days().take_while(move |date| *date <= end)
+    }
+}
+
+impl fmt::Display for DateRange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.start == self.end {
+            write!(f, "{}", self.start)
+        } else {
+            write!(f, "{} .. {}", self.start, self.end)
+        }
+    }
+}
+
+/// The calendar period a report covers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Period {
+    Day,
+    /// Monday to Sunday.
+    Week,
+    Month,
+}
+
+impl Period {
+    /// The period containing `date`.
+    pub fn range(&self, date: NaiveDate) -> DateRange {
+        match self {
+            Period::Day => DateRange {
+                start: date,
+                end: date,
+            },
+            Period::Week => {
+                let start = date - Duration::days(date.weekday().num_days_from_monday() as i64);
+                DateRange {
+                    start,
+                    end: start + Duration::days(6),
+                }
+            }
+            Period::Month => {
+                let start = date.with_day(1).unwrap();
+                let end = start + Months::new(1) - Duration::days(1);
+                DateRange { start, end }
+            }
+        }
+    }
+
+    /// The period before the one containing `date`. Months differ in length
+    /// so this is not always `range(date).previous()`.
+    pub fn previous_range(&self, date: NaiveDate) -> DateRange {
+        let current = self.range(date);
+        self.range(current.start - Duration::days(1))
+    }
+}
+
+impl fmt::Display for Period {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Period::Day => "day",
+            Period::Week => "week",
+            Period::Month => "month",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for Period {
+    type Err = PlannerError;
+
+    fn from_str(text: &str) -> Result<Self> {
+        match text.to_lowercase().as_str() {
+            "day" => Ok(Period::Day),
+            "week" => Ok(Period::Week),
+            "month" => Ok(Period::Month),
+            _ => Err(PlannerError::Invalid(format!(
+                "'{}' is not a period (day, week, month)",
+                text
+            ))),
+        }
+    }
+}
+
+/// The numbers for one date range.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Summary {
+    pub range: DateRange,
+    /// Minutes per subject, most worked first.
+    pub subjects: Vec<(String, i64)>,
+    /// Minutes worked on each day of the range.
+    pub daily_minutes: Vec<(NaiveDate, i64)>,
+    /// High priority todos open at some point in the range.
+    pub high_priority_total: usize,
+    /// How many of those were completed within the range.
+    pub high_priority_done: usize,
+    /// Routine occurrences due in the range.
+    pub routines_scheduled: usize,
+    /// How many of those were checked off.
+    pub routines_done: usize,
+    /// Pomodoros finished on each day of the range.
+    pub daily_pomodoros: Vec<(NaiveDate, usize)>,
+    /// Pomodoros started in the range and cut short.
+    pub pomodoros_interrupted: usize,
+    /// Time per budgeted subject against its budgets for the range.
+    pub budgets: Vec<Progress>,
+}
+
+impl Summary {
+    pub fn build(data: &PlannerData, range: DateRange) -> Self {
+        let from = time::from_local(range.start.and_hms_opt(0, 0, 0).unwrap());
+        let to = time::from_local(
+            (range.end + Duration::days(1))
+                .and_hms_opt(0, 0, 0)
+                .unwrap(),
+        );
+
+        let mut seconds: BTreeMap<String, i64> = BTreeMap::new();
+        let mut daily_minutes = Vec::new();
+        let mut daily_pomodoros = Vec::new();
+        for date in range.dates() {
+            let day_from = time::from_local(date.and_hms_opt(0, 0, 0).unwrap());
+            let day_to = time::from_local((date + Duration::days(1)).and_hms_opt(0, 0, 0).unwrap());
+            let mut day_seconds = 0;
+            for session in &data.sessions {
+                let part = session.seconds_between(day_from, day_to);
+                if part > 0 {
+                    *seconds.entry(session.subject.clone()).or_default() += part;
+                    day_seconds += part;
+                }
+            }
+            daily_minutes.push((date, day_seconds / 60));
+            let finished = data
+                .pomodoros
+                .iter()
+                .filter(|pomodoro| !pomodoro.interrupted)
+                .filter(|pomodoro| pomodoro.start >= day_from && pomodoro.start < day_to)
+                .count();
+            daily_pomodoros.push((date, finished));
+        }
+        let pomodoros_interrupted = data
+            .pomodoros
+            .iter()
+            .filter(|pomodoro| pomodoro.interrupted)
+            .filter(|pomodoro| pomodoro.start >= from && pomodoro.start < to)
+            .count();
+        let mut subjects: Vec<(String, i64)> = seconds
+            .into_iter()
+            .map(|(subject, seconds)| (subject, seconds / 60))
+            .collect();
+        subjects.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+        // A high priority todo counts when it existed before the range ended
+        // and was not already done when the range started
+        let high: Vec<_> = data
+            .todos
+            .iter()
+            .filter(|todo| todo.priority == Priority::High)
+            .filter(|todo| todo.created_at < to)
+            .filter(|todo| todo.completed_at.is_none_or(|done| done >= from))
+            .collect();
+        let high_priority_done = high
+            .iter()
+            .filter(|todo| todo.completed_at.is_some_and(|done| done < to))
+            .count();
+
+        let mut routines_scheduled = 0;
+        let mut routines_done = 0;
+        for routine in &data.routines {
+            for date in routine.occurrences(range.start, range.end) {
+                routines_scheduled += 1;
+                if data.routine_checks.iter().any(|check| {
+                    check.routine_id == routine.id
+                        && check.date == date
+                        && check.status == CheckStatus::Done
+                }) {
+                    routines_done += 1;
+                }
+            }
+        }
+
+        Summary {
+            range,
+            subjects,
+            daily_minutes,
+            high_priority_total: high.len(),
+            high_priority_done,
+            routines_scheduled,
+            routines_done,
+            daily_pomodoros,
+            pomodoros_interrupted,
+            budgets: budget::progress(data, range, None),
+        }
+    }
+
+    pub fn total_minutes(&self) -> i64 {
+        self.subjects.iter().map(|(_, minutes)| minutes).sum()
+    }
+
+    pub fn minutes_for(&self, subject: &str) -> i64 {
+        self.subjects
+            .iter()
+            .find(|(name, _)| name == subject)
+            .map_or(0, |(_, minutes)| *minutes)
+    }
+
+    pub fn budget_for(&self, subject: &str) -> Option<&Progress> {
+        self.budgets
+            .iter()
+            .find(|progress| progress.subject.to_lowercase() == subject.to_lowercase())
+    }
+
+    pub fn pomodoros_done(&self) -> usize {
+        self.daily_pomodoros.iter().map(|(_, count)| count).sum()
+    }
+
+    /// Percentage of high priority todos done, `None` when there were none.
+    pub fn completion_rate(&self) -> Option<f64> {
+        percentage(self.high_priority_done, self.high_priority_total)
+    }
+
+    /// Percentage of routine occurrences done, `None` when none were due.
+    pub fn routine_adherence(&self) -> Option<f64> {
+        percentage(self.routines_done, self.routines_scheduled)
+    }
+}
+
+fn percentage(part: usize, total: usize) -> Option<f64> {
+    if total == 0 {
+        None
+    } else {
+        Some(part as f64 * 100.0 / total as f64)
+    }
+}
+
+/// A summary of a range next to the one before it.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Report {
+    /// What the range is, like "week" or "custom range".
+    pub label: String,
+    pub current: Summary,
+    pub previous: Summary,
+}
+
+impl Report {
+    /// Report for the day, week or month containing `date`.
+    pub fn for_period(data: &PlannerData, period: Period, date: NaiveDate) -> Self {
+        Report {
+            label: period.to_string(),
+            current: Summary::build(data, period.range(date)),
+            previous: Summary::build(data, period.previous_range(date)),
+        }
+    }
+
+    /// Report for any range, compared with the range of the same length before it.
+    pub fn for_range(data: &PlannerData, range: DateRange) -> Self {
+        Report {
+            label: "custom range".to_string(),
+            current: Summary::build(data, range),
+            previous: Summary::build(data, range.previous()),
+        }
+    }
+
+    /// Every subject worked on in either range, in the order of the current one.
+    pub fn subjects(&self) -> Vec<String> {
+        let mut subjects: Vec<String> = self
+            .current
+            .subjects
+            .iter()
+            .map(|(subject, _)| subject.clone())
+            .collect();
+        for (subject, _) in &self.previous.subjects {
+            if !subjects.contains(subject) {
+                subjects.push(subject.clone());
+            }
+        }
+        subjects
+    }
+
+    /// Every subject with a budget in either range, in the order of the
+    /// current one.
+    pub fn budgeted(&self) -> Vec<String> {
+        let mut subjects: Vec<String> = Vec::new();
+        for progress in self.current.budgets.iter().chain(&self.previous.budgets) {
+            if !subjects
+                .iter()
+                .any(|subject| subject.to_lowercase() == progress.subject.to_lowercase())
+            {
+                subjects.push(progress.subject.clone());
+            }
+        }
+        subjects
+    }
+}
@@ -0,0 +1,217 @@
+// CSV report, one metric per row, for spreadsheets and other tools.
+// Times are whole minutes and rates are percentages.
+use super::{Report, Summary};
+use crate::budget::Progress;
+
+/// Quotes a field when it contains a separator, a quote or a line break.
+pub fn field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn rate(value: Option<f64>) -> String {
+    value
+        .map(|value| format!("{:.1}", value))
+        .unwrap_or_default()
+}
+
+fn row(out: &mut String, cells: &[String]) {
+    let cells: Vec<String> = cells.iter().map(|cell| field(cell)).collect();
+    out.push_str(&cells.join(","));
+    out.push('\n');
+}
+
+fn counts(out: &mut String, metric: &str, value: impl Fn(&Summary) -> usize, report: &Report) {
+    let current = value(&report.current) as i64;
+    let previous = value(&report.previous) as i64;
+    row(
+        out,
+        &[
+            metric.to_string(),
+            String::new(),
+            current.to_string(),
+            previous.to_string(),
+            (current - previous).to_string(),
+        ],
+    );
+}
+
+pub fn render(report: &Report) -> String {
+    let current = &report.current;
+    let previous = &report.previous;
+    let mut out = String::new();
+    row(
+        &mut out,
+        &[
+            "metric".to_string(),
+            "name".to_string(),
+            "current".to_string(),
+            "previous".to_string(),
+            "change".to_string(),
+        ],
+    );
+    row(
+        &mut out,
+        &[
+            "range".to_string(),
+            report.label.clone(),
+            current.range.to_string(),
+            previous.range.to_string(),
+            String::new(),
+        ],
+    );
+    for subject in report.subjects() {
+        let now = current.minutes_for(&subject);
+        let before = previous.minutes_for(&subject);
+        row(
+            &mut out,
+            &[
+                "subject_minutes".to_string(),
+                subject,
+                now.to_string(),
+                before.to_string(),
+                (now - before).to_string(),
+            ],
+        );
+    }
+    row(
+        &mut out,
+        &[
+            "total_minutes".to_string(),
+            String::new(),
+            current.total_minutes().to_string(),
+            previous.total_minutes().to_string(),
+            (current.total_minutes() - previous.total_minutes()).to_string(),
+        ],
+    );
+    counts(
+        &mut out,
+        "high_priority_done",
+        |s| s.high_priority_done,
+        report,
+    );
+    counts(
+        &mut out,
+        "high_priority_total",
+        |s| s.high_priority_total,
+        report,
+    );
+    let change = match (current.completion_rate(), previous.completion_rate()) {
+        (Some(now), Some(before)) => Some(now - before),
+        _ => None,
+    };
+    row(
+        &mut out,
+        &[
+            "high_priority_rate".to_string(),
+            String::new(),
+            rate(current.completion_rate()),
+            rate(previous.completion_rate()),
+            rate(change),
+        ],
+    );
+    counts(&mut out, "routines_done", |s| s.routines_done, report);
+    counts(
+        &mut out,
+        "routines_scheduled",
+        |s| s.routines_scheduled,
+        report,
+    );
+    let change = match (current.routine_adherence(), previous.routine_adherence()) {
+        (Some(now), Some(before)) => Some(now - before),
+        _ => None,
+    };
+    row(
+        &mut out,
+        &[
+            "routine_adherence".to_string(),
+            String::new(),
+            rate(current.routine_adherence()),
+            rate(previous.routine_adherence()),
+            rate(change),
+        ],
+    );
+    counts(&mut out, "pomodoros_done", Summary::pomodoros_done, report);
+    counts(
+        &mut out,
+        "pomodoros_interrupted",
+        |s| s.pomodoros_interrupted,
+        report,
+    );
+    for subject in report.budgeted() {
+        let now = current.budget_for(&subject);
+        let before = previous.budget_for(&subject);
+        let minutes = |progress: Option<&Progress>, value: fn(&Progress) -> i64| {
+            progress
+                .map(|progress| value(progress).to_string())
+                .unwrap_or_default()
+        };
+        let change = |value: fn(&Progress) -> i64| match (now, before) {
+            (Some(now), Some(before)) => (value(now) - value(before)).to_string(),
+            _ => String::new(),
+        };
+        row(
+            &mut out,
+            &[
+                "budget_minutes".to_string(),
+                subject.clone(),
+                minutes(now, |p| p.budget_minutes),
+                minutes(before, |p| p.budget_minutes),
+                change(|p| p.budget_minutes),
+            ],
+        );
+        row(
+            &mut out,
+            &[
+                "budget_spent_minutes".to_string(),
+                subject.clone(),
+                minutes(now, |p| p.spent_minutes),
+                minutes(before, |p| p.spent_minutes),
+                change(|p| p.spent_minutes),
+            ],
+        );
+        let percent = |progress: Option<&Progress>| progress.and_then(Progress::percent);
+        let percent_change = match (percent(now), percent(before)) {
+            (Some(now), Some(before)) => Some(now - before),
+            _ => None,
+        };
+        row(
+            &mut out,
+            &[
+                "budget_used".to_string(),
+                subject,
+                rate(percent(now)),
+                rate(percent(before)),
+                rate(percent_change),
+            ],
+        );
+    }
+    for (date, minutes) in &current.daily_minutes {
+        row(
+            &mut out,
+            &[
+                "day_minutes".to_string(),
+                date.to_string(),
+                minutes.to_string(),
+                String::new(),
+                String::new(),
+            ],
+        );
+    }
+    for (date, count) in &current.daily_pomodoros {
+        row(
+            &mut out,
+            &[
+                "day_pomodoros".to_string(),
+                date.to_string(),
+                count.to_string(),
+                String::new(),
+                String::new(),
+            ],
+        );
+    }
+    out
+}
@@ -0,0 +1,171 @@
+// A single HTML file with everything inline (styles and charts), so it can
+// be opened offline or attached to an email.
+use super::text::{minutes, minutes_change, percent, percent_change};
+use super::{Report, Summary};
+use crate::budget::Progress;
+
+/// Escapes text for use inside HTML.
+pub fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+const STYLE: &str = "body{font-family:sans-serif;max-width:760px;margin:2em auto;color:#222}\
+table{border-collapse:collapse;width:100%;margin-bottom:1.5em}\
+th,td{padding:4px 8px;border-bottom:1px solid #ddd;text-align:right}\
+th:first-child,td:first-child{text-align:left}\
+.bar{background:#eee;height:14px;width:100%}\
+.bar div{background:#4a7bd0;height:14px}\
+.muted{color:#777}";
+
+fn bar(fraction: f64) -> String {
+    let width = (fraction.clamp(0.0, 1.0) * 100.0).round();
+    format!(
+        "<div class=\"bar\"><div style=\"width:{}%\"></div></div>",
+        width
+    )
+}
+
+fn ratio(done: usize, total: usize, rate: Option<f64>) -> String {
+    format!("{}/{} ({})", done, total, percent(rate))
+}
+
+fn daily_chart(summary: &Summary) -> String {
+    let days = &summary.daily_minutes;
+    if days.len() < 2 {
+        return String::new();
+    }
+    let max = days.iter().map(|(_, m)| *m).max().unwrap_or(0).max(1);
+    let width = 20;
+    let height = 100;
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" role=\"img\">",
+        days.len() * width,
+        height + 2
+    );
+    for (i, (date, day_minutes)) in days.iter().enumerate() {
+        let bar_height = (*day_minutes * height / max) as usize;
+        svg.push_str(&format!(
+            "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"#4a7bd0\"><title>{}: {}</title></rect>",
+            i * width + 2,
+            height - bar_height as i64 + 1,
+            width - 4,
+            bar_height,
+            date,
+            escape(&minutes(*day_minutes))
+        ));
+    }
+    svg.push_str("</svg>");
+    format!("<h2>Focus time per day</h2>\n{}\n", svg)
+}
+
+pub fn render(report: &Report) -> String {
+    let current = &report.current;
+    let previous = &report.previous;
+    let title = format!("Report for {} {}", report.label, current.range);
+
+    let mut out = String::new();
+    out.push_str("<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n");
+    out.push_str(&format!("<title>{}</title>\n", escape(&title)));
+    out.push_str(&format!("<style>{}</style>\n</head>\n<body>\n", STYLE));
+    out.push_str(&format!("<h1>{}</h1>\n", escape(&title)));
+    out.push_str(&format!(
+        "<p class=\"muted\">Compared with {}</p>\n",
+        escape(&previous.range.to_string())
+    ));
+
+    out.push_str("<h2>Time per subject</h2>\n<table>\n");
+    out.push_str(
+        "<tr><th>Subject</th><th>This period</th><th>Previous</th><th>Change</th><th></th></tr>\n",
+    );
+    let longest = current
+        .subjects
+        .first()
+        .map_or(0, |(_, minutes)| *minutes)
+        .max(1);
+    for subject in report.subjects() {
+        let now = current.minutes_for(&subject);
+        let before = previous.minutes_for(&subject);
+        out.push_str(&format!(
+            "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td style=\"width:30%\">{}</td></tr>\n",
+            escape(&subject),
+            minutes(now),
+            minutes(before),
+            minutes_change(now, before),
+            bar(now as f64 / longest as f64)
+        ));
+    }
+    out.push_str(&format!(
+        "<tr><th>Total</th><th>{}</th><th>{}</th><th>{}</th><th></th></tr>\n</table>\n",
+        minutes(current.total_minutes()),
+        minutes(previous.total_minutes()),
+        minutes_change(current.total_minutes(), previous.total_minutes())
+    ));
+
+    out.push_str("<h2>Goals</h2>\n<table>\n");
+    out.push_str(
+        "<tr><th></th><th>This period</th><th>Previous</th><th>Change</th><th></th></tr>\n",
+    );
+    out.push_str(&format!(
+        "<tr><td>High priority todos done</td><td>{}</td><td>{}</td><td>{}</td><td style=\"width:30%\">{}</td></tr>\n",
+        ratio(current.high_priority_done, current.high_priority_total, current.completion_rate()),
+        ratio(previous.high_priority_done, previous.high_priority_total, previous.completion_rate()),
+        percent_change(current.completion_rate(), previous.completion_rate()),
+        bar(current.completion_rate().unwrap_or(0.0) / 100.0)
+    ));
+    out.push_str(&format!(
+        "<tr><td>Routine adherence</td><td>{}</td><td>{}</td><td>{}</td><td style=\"width:30%\">{}</td></tr>\n",
+        ratio(current.routines_done, current.routines_scheduled, current.routine_adherence()),
+        ratio(previous.routines_done, previous.routines_scheduled, previous.routine_adherence()),
+        percent_change(current.routine_adherence(), previous.routine_adherence()),
+        bar(current.routine_adherence().unwrap_or(0.0) / 100.0)
+    ));
+    out.push_str(&format!(
+        "<tr><td>Pomodoros done</td><td>{} ({} interrupted)</td><td>{} ({} interrupted)</td><td>{:+}</td><td></td></tr>\n</table>\n",
+        current.pomodoros_done(),
+        current.pomodoros_interrupted,
+        previous.pomodoros_done(),
+        previous.pomodoros_interrupted,
+        current.pomodoros_done() as i64 - previous.pomodoros_done() as i64
+    ));
+
+    let budgeted = report.budgeted();
+    if !budgeted.is_empty() {
+        out.push_str("<h2>Budgets</h2>\n<table>\n");
+        out.push_str(
+            "<tr><th>Subject</th><th>Spent</th><th>Budget</th><th>Used</th><th>Previous</th><th></th></tr>\n",
+        );
+        for subject in budgeted {
+            let now = current.budget_for(&subject);
+            let before = previous.budget_for(&subject);
+            let used = |progress: Option<&Progress>| progress.and_then(Progress::percent);
+            out.push_str(&format!(
+                "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td style=\"width:30%\">{}</td></tr>\n",
+                escape(&match now {
+                    Some(progress) => format!("{} ({})", subject, progress.kind),
+                    None => subject,
+                }),
+                now.map_or("-".to_string(), |p| minutes(p.spent_minutes)),
+                now.map_or("-".to_string(), |p| minutes(p.budget_minutes)),
+                percent(used(now)),
+                percent(used(before)),
+                bar(used(now).unwrap_or(0.0) / 100.0)
+            ));
+        }
+        out.push_str("</table>\n");
+    }
+
+    out.push_str(&daily_chart(current));
+    out.push_str("</body>\n</html>\n");
+    out
+}
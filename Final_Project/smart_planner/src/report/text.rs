@@ -0,0 +1,217 @@
+// Plain text report with aligned columns, for the terminal.
+use chrono::{Duration, NaiveDate};
+
+use super::{Report, Summary};
+use crate::budget::Progress;
+use crate::chart::{self, Charset};
+use crate::stopwatch::format_duration;
+
+/// Lines up the columns of `rows`. The first column is left aligned, the
+/// others right aligned so numbers are easy to compare.
+pub fn table(rows: &[Vec<String>]) -> String {
+    let columns = rows.iter().map(|row| row.len()).max().unwrap_or(0);
+    let mut widths = vec![0; columns];
+    for row in rows {
+        for (i, cell) in row.iter().enumerate() {
+            widths[i] = widths[i].max(cell.chars().count());
+        }
+    }
+    let mut out = String::new();
+    for row in rows {
+        let mut line = String::new();
+        for (i, cell) in row.iter().enumerate() {
+            if i == 0 {
+                line.push_str(&format!("{:<width$}", cell, width = widths[0]));
+            } else {
+                line.push_str(&format!("  {:>width$}", cell, width = widths[i]));
+            }
+        }
+        out.push_str(line.trim_end());
+        out.push('\n');
+    }
+    out
+}
+
+pub fn minutes(minutes: i64) -> String {
+    format_duration(Duration::minutes(minutes))
+}
+
+pub fn minutes_change(current: i64, previous: i64) -> String {
+    let change = current - previous;
+    if change > 0 {
+        format!("+{}", minutes(change))
+    } else {
+        minutes(change)
+    }
+}
+
+pub fn percent(value: Option<f64>) -> String {
+    match value {
+        Some(value) => format!("{:.0}%", value),
+        None => "-".to_string(),
+    }
+}
+
+pub fn percent_change(current: Option<f64>, previous: Option<f64>) -> String {
+    match (current, previous) {
+        (Some(current), Some(previous)) => format!("{:+.0} pts", current - previous),
+        _ => "-".to_string(),
+    }
+}
+
+fn ratio(part: usize, total: usize, rate: Option<f64>) -> String {
+    format!("{}/{} {}", part, total, percent(rate))
+}
+
+fn high_priority(summary: &Summary) -> String {
+    ratio(
+        summary.high_priority_done,
+        summary.high_priority_total,
+        summary.completion_rate(),
+    )
+}
+
+fn routines(summary: &Summary) -> String {
+    ratio(
+        summary.routines_done,
+        summary.routines_scheduled,
+        summary.routine_adherence(),
+    )
+}
+
+fn count_change(current: usize, previous: usize) -> String {
+    format!("{:+}", current as i64 - previous as i64)
+}
+
+pub fn render(report: &Report) -> String {
+    let current = &report.current;
+    let previous = &report.previous;
+    let mut out = format!(
+        "Report for {} {} (compared with {})\n\n",
+        report.label, current.range, previous.range
+    );
+
+    let mut rows = vec![vec![
+        "Time per subject".to_string(),
+        "This period".to_string(),
+        "Previous".to_string(),
+        "Change".to_string(),
+    ]];
+    for subject in report.subjects() {
+        let now = current.minutes_for(&subject);
+        let before = previous.minutes_for(&subject);
+        rows.push(vec![
+            subject,
+            minutes(now),
+            minutes(before),
+            minutes_change(now, before),
+        ]);
+    }
+    rows.push(vec![
+        "Total".to_string(),
+        minutes(current.total_minutes()),
+        minutes(previous.total_minutes()),
+        minutes_change(current.total_minutes(), previous.total_minutes()),
+    ]);
+    rows.push(Vec::new());
+    rows.push(vec![
+        "High priority todos done".to_string(),
+        high_priority(current),
+        high_priority(previous),
+        percent_change(current.completion_rate(), previous.completion_rate()),
+    ]);
+    rows.push(vec![
+        "Routine adherence".to_string(),
+        routines(current),
+        routines(previous),
+        percent_change(current.routine_adherence(), previous.routine_adherence()),
+    ]);
+    rows.push(vec![
+        "Pomodoros done".to_string(),
+        current.pomodoros_done().to_string(),
+        previous.pomodoros_done().to_string(),
+        count_change(current.pomodoros_done(), previous.pomodoros_done()),
+    ]);
+    rows.push(vec![
+        "Pomodoros interrupted".to_string(),
+        current.pomodoros_interrupted.to_string(),
+        previous.pomodoros_interrupted.to_string(),
+        count_change(
+            current.pomodoros_interrupted,
+            previous.pomodoros_interrupted,
+        ),
+    ]);
+    out.push_str(&table(&rows));
+
+    let budgeted = report.budgeted();
+    if !budgeted.is_empty() {
+        out.push('\n');
+        let mut budgets = vec![vec![
+            "Budget".to_string(),
+            "Spent".to_string(),
+            "Of".to_string(),
+            "Used".to_string(),
+            "Previous".to_string(),
+            "Result".to_string(),
+        ]];
+        for subject in budgeted {
+            let now = current.budget_for(&subject);
+            let before = previous.budget_for(&subject);
+            let used = |progress: Option<&Progress>| percent(progress.and_then(Progress::percent));
+            budgets.push(vec![
+                match now {
+                    Some(progress) => format!("{} ({})", subject, progress.kind),
+                    None => subject,
+                },
+                now.map_or("-".to_string(), |p| minutes(p.spent_minutes)),
+                now.map_or("-".to_string(), |p| minutes(p.budget_minutes)),
+                used(now),
+                used(before),
+                now.map_or("-".to_string(), Progress::outcome),
+            ]);
+        }
+        out.push_str(&table(&budgets));
+    }
+
+    if current.daily_minutes.len() > 1 {
+        out.push('\n');
+        let mut days = vec![vec![
+            "Day".to_string(),
+            "Focus time".to_string(),
+            "Pomodoros".to_string(),
+        ]];
+        let daily = current.daily_minutes.iter().zip(&current.daily_pomodoros);
+        for ((date, day_minutes), (_, pomodoros)) in daily {
+            days.push(vec![
+                date.format("%a %Y-%m-%d").to_string(),
+                minutes(*day_minutes),
+                pomodoros.to_string(),
+            ]);
+        }
+        out.push_str(&table(&days));
+    }
+    out
+}
+
+/// Focus time of each day and per subject in the period, and `year` (the
+/// minutes of each day up to its end) as a heatmap.
+pub fn charts(report: &Report, year: &[(NaiveDate, i64)], charset: Charset) -> String {
+    let current = &report.current;
+    let daily: Vec<i64> = current.daily_minutes.iter().map(|(_, m)| *m).collect();
+    let mut out = format!(
+        "Focus per day  {}  {} to {}\n",
+        chart::sparkline(&daily, charset),
+        current.range.start.format("%a %d"),
+        current.range.end.format("%a %d")
+    );
+    if !current.subjects.is_empty() {
+        out.push_str("\nTime per subject\n");
+        out.push_str(&chart::bars(&current.subjects, 30, charset, minutes));
+    }
+    out.push_str(&format!(
+        "\nWork sessions in the year to {}\n",
+        current.range.end
+    ));
+    out.push_str(&chart::heatmap(year, charset));
+    out
+}
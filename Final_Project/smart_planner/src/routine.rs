@@ -0,0 +1,186 @@
+use std::fmt;
+use std::str::FromStr;
+
+use chrono::{DateTime, Datelike, NaiveDate, NaiveTime, Utc, Weekday};
+use serde::{Deserialize, Serialize};
+
+use crate::error::PlannerError;
+use crate::time;
+use crate::time::zone::Zone;
+
+pub mod habit;
+
+/// On which days a routine happens.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Frequency {
+    Daily,
+    Weekdays,
+    Weekends,
+    Days(Vec<Weekday>),
+}
+
+impl Frequency {
+    /// Whether the routine happens on this date.
+    pub fn occurs_on(&self, date: NaiveDate) -> bool {
+        let weekday = date.weekday();
+        match self {
+            Frequency::Daily => true,
+            Frequency::Weekdays => weekday.number_from_monday() <= 5,
+            Frequency::Weekends => weekday.number_from_monday() > 5,
+            Frequency::Days(days) => days.contains(&weekday),
+        }
+    }
+}
+
+impl fmt::Display for Frequency {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Frequency::Daily => write!(f, "daily"),
+            Frequency::Weekdays => write!(f, "weekdays"),
+            Frequency::Weekends => write!(f, "weekends"),
+            Frequency::Days(days) => {
+                let names: Vec<String> = days
+                    .iter()
+                    .map(|day| day.to_string().to_lowercase())
+                    .collect();
+                write!(f, "{}", names.join(","))
+            }
+        }
+    }
+}
+
+impl FromStr for Frequency {
+    type Err = PlannerError;
+
+    /// Accepts `daily`, `weekdays`, `weekends` or a list like `mon,wed,fri`.
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        match text.to_lowercase().as_str() {
+            "daily" => return Ok(Frequency::Daily),
+            "weekdays" => return Ok(Frequency::Weekdays),
+            "weekends" => return Ok(Frequency::Weekends),
+            _ => {}
+        }
+        let mut days = Vec::new();
+        for part in text.split(',') {
+            let day: Weekday = part.trim().parse().map_err(|_| {
+                PlannerError::Invalid(format!(
+                    "'{}' is not daily, weekdays, weekends or a day list like mon,wed",
+                    text
+                ))
+            })?;
+            if !days.contains(&day) {
+                days.push(day);
+            }
+        }
+        days.sort_by_key(|day| day.number_from_monday());
+        Ok(Frequency::Days(days))
+    }
+}
+
+/// Something the user does at a fixed time, like breakfast or exercise.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Routine {
+    pub id: u64,
+    pub name: String,
+    /// Local time the routine starts.
+    pub start: NaiveTime,
+    pub duration_minutes: u32,
+    pub frequency: Frequency,
+    #[serde(default)]
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+/// How much of a routine got done on a day.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckStatus {
+    #[default]
+    Done,
+    Partial,
+    /// Left out on purpose. Breaks a streak like a missed day does.
+    Skipped,
+}
+
+impl fmt::Display for CheckStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            CheckStatus::Done => "done",
+            CheckStatus::Partial => "partial",
+            CheckStatus::Skipped => "skipped",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for CheckStatus {
+    type Err = PlannerError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "done" => Ok(CheckStatus::Done),
+            "partial" => Ok(CheckStatus::Partial),
+            "skipped" | "skip" => Ok(CheckStatus::Skipped),
+            _ => Err(PlannerError::Invalid(format!(
+                "'{}' is not done, partial or skipped",
+                s
+            ))),
+        }
+    }
+}
+
+/// Records what the user did about a routine on a day.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RoutineCheck {
+    pub routine_id: u64,
+    pub date: NaiveDate,
+    /// Checks saved before statuses existed were all done.
+    #[serde(default)]
+    pub status: CheckStatus,
+}
+
+impl Routine {
+    /// Local end time, clamped to midnight.
+    pub fn end(&self) -> NaiveTime {
+        let (end, wrapped) = self
+            .start
+            .overflowing_add_signed(chrono::Duration::minutes(self.duration_minutes as i64));
+        if wrapped != 0 {
+            NaiveTime::from_hms_opt(23, 59, 59).unwrap()
+        } else {
+            end
+        }
+    }
+
+    /// The days between `from` and `to` (both included) the routine was due,
+    /// leaving out days before it was created.
+    pub fn occurrences(&self, from: NaiveDate, to: NaiveDate) -> Vec<NaiveDate> {
+        let first = match self.created_at {
+            Some(created_at) => from.max(time::to_local(created_at).date()),
+            None => from,
+        };
+        first
+            .iter_days()
+            .take_while(|date| *date <= to)
+            .filter(|date| self.frequency.occurs_on(*date))
+            .collect()
+    }
+
+    /// When the routine starts on `date` on the clocks of `zone`. A start the
+    /// clocks skip moves as far past the change, one that happens twice is
+    /// the first.
+    pub fn start_on(&self, date: NaiveDate, zone: &Zone) -> DateTime<Utc> {
+        zone.resolve(date.and_time(self.start)).instant()
+    }
+
+    /// The first start after `after`, looking a week ahead.
+    pub fn next_start(&self, after: DateTime<Utc>, zone: &Zone) -> Option<DateTime<Utc>> {
+        zone.to_local(after)
+            .date()
+            .iter_days()
+            .take(8)
+            .filter(|date| self.frequency.occurs_on(*date))
+            .map(|date| self.start_on(date, zone))
+            .find(|start| *start > after)
+    }
+}
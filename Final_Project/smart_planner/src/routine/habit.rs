@@ -0,0 +1,211 @@
+// Streaks and adherence for routines. Only the days a routine is scheduled
+// count, so a weekdays routine keeps its streak over the weekend and a
+// mon,wed,fri routine is not broken by the Tuesday in between.
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use chrono::{Datelike, Duration, NaiveDate, Weekday};
+
+use super::{CheckStatus, Routine, RoutineCheck};
+use crate::time;
+
+/// What happened to a routine on one day.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Day {
+    Done,
+    Partial,
+    Skipped,
+    /// Scheduled, gone by and never checked.
+    Missed,
+    /// Scheduled today and not checked yet.
+    Pending,
+    /// Not scheduled, before the routine started, or still to come.
+    Off,
+}
+
+impl Day {
+    /// How much the day counts towards adherence, `None` when it does not
+    /// count at all.
+    fn score(self) -> Option<f64> {
+        match self {
+            Day::Done => Some(1.0),
+            Day::Partial => Some(0.5),
+            Day::Skipped | Day::Missed => Some(0.0),
+            Day::Pending | Day::Off => None,
+        }
+    }
+
+    /// One character for the heatmap.
+    fn symbol(self) -> char {
+        match self {
+            Day::Done => '#',
+            Day::Partial => '+',
+            Day::Skipped => '-',
+            Day::Missed => '.',
+            Day::Pending => 'o',
+            Day::Off => ' ',
+        }
+    }
+}
+
+/// Streak lengths in scheduled days.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Streaks {
+    /// Scheduled days in a row done or partly done, up to today. An
+    /// unchecked today does not break it yet.
+    pub current: u32,
+    pub longest: u32,
+}
+
+/// A routine with its check-ins, as of `today`.
+pub struct Habit<'a> {
+    pub routine: &'a Routine,
+    checks: BTreeMap<NaiveDate, CheckStatus>,
+    today: NaiveDate,
+}
+
+impl<'a> Habit<'a> {
+    pub fn new(routine: &'a Routine, checks: &[RoutineCheck], today: NaiveDate) -> Self {
+        let checks = checks
+            .iter()
+            .filter(|check| check.routine_id == routine.id)
+            .map(|check| (check.date, check.status))
+            .collect();
+        Habit {
+            routine,
+            checks,
+            today,
+        }
+    }
+
+    /// The first day that counts: when the routine was created, or the first
+    /// check-in if one was recorded for an earlier day.
+    pub fn first_day(&self) -> NaiveDate {
+        let created = self
+            .routine
+            .created_at
+            .map(|created| time::to_local(created).date());
+        let checked = self.checks.keys().next().copied();
+        match (created, checked) {
+            (Some(created), Some(checked)) => created.min(checked),
+            (Some(day), None) | (None, Some(day)) => day,
+            (None, None) => self.today,
+        }
+    }
+
+    pub fn day(&self, date: NaiveDate) -> Day {
+        if date > self.today || date < self.first_day() || !self.routine.frequency.occurs_on(date) {
+            return Day::Off;
+        }
+        match self.checks.get(&date) {
+            Some(CheckStatus::Done) => Day::Done,
+            Some(CheckStatus::Partial) => Day::Partial,
+            Some(CheckStatus::Skipped) => Day::Skipped,
+            None if date == self.today => Day::Pending,
+            None => Day::Missed,
+        }
+    }
+
+    pub fn streaks(&self) -> Streaks {
+        let mut streaks = Streaks::default();
+        for date in self
+            .first_day()
+            .iter_days()
+            .take_while(|date| *date <= self.today)
+        {
+            match self.day(date) {
+                Day::Done | Day::Partial => {
+                    streaks.current += 1;
+                    streaks.longest = streaks.longest.max(streaks.current);
+                }
+                Day::Skipped | Day::Missed => streaks.current = 0,
+                Day::Pending | Day::Off => {}
+            }
+        }
+        streaks
+    }
+
+    /// Percentage of the scheduled days from `from` to `to` that were done,
+    /// a partly done day counting half. `None` when no day counted.
+    pub fn adherence(&self, from: NaiveDate, to: NaiveDate) -> Option<f64> {
+        let scores: Vec<f64> = from
+            .iter_days()
+            .take_while(|date| *date <= to)
+            .filter_map(|date| self.day(date).score())
+            .collect();
+        if scores.is_empty() {
+            return None;
+        }
+        Some(scores.iter().sum::<f64>() * 100.0 / scores.len() as f64)
+    }
+
+    /// Adherence for each of the last `weeks` weeks (Monday to Sunday),
+    /// oldest first, with the Monday starting each.
+    pub fn weekly(&self, weeks: u32) -> Vec<(NaiveDate, Option<f64>)> {
+        let monday = week_start(self.today);
+        (0..weeks)
+            .rev()
+            .map(|back| {
+                let start = monday - Duration::weeks(back as i64);
+                (start, self.adherence(start, start + Duration::days(6)))
+            })
+            .collect()
+    }
+
+    /// A calendar of the last `weeks` weeks: one column per week, one row per
+    /// weekday, like a contribution graph.
+    pub fn heatmap(&self, weeks: u32) -> String {
+        let weeks = weeks.max(1);
+        let first = week_start(self.today) - Duration::weeks(weeks as i64 - 1);
+        let mut out = String::new();
+        // Month names above the first week and the weeks a month starts in
+        let mut header = String::new();
+        for week in 0..weeks {
+            let monday = first + Duration::weeks(week as i64);
+            let sunday = monday + Duration::days(6);
+            let column = 4 + 2 * week as usize;
+            let label = if week == 0 {
+                Some(monday)
+            } else if sunday.day() <= 7 {
+                Some(sunday)
+            } else {
+                None
+            };
+            if let Some(month) = label.filter(|_| header.len() <= column) {
+                header.push_str(&" ".repeat(column - header.len()));
+                header.push_str(&month.format("%b").to_string());
+            }
+        }
+        writeln!(out, "{}", header.trim_end()).unwrap();
+        for (row, weekday) in WEEKDAYS.iter().enumerate() {
+            let mut line = format!("{:<4}", weekday.to_string());
+            for week in 0..weeks {
+                let date = first + Duration::weeks(week as i64) + Duration::days(row as i64);
+                line.push(self.day(date).symbol());
+                line.push(' ');
+            }
+            writeln!(out, "{}", line.trim_end()).unwrap();
+        }
+        write!(
+            out,
+            "# done  + partial  - skipped  . missed  o today, not checked yet"
+        )
+        .unwrap();
+        out
+    }
+}
+
+const WEEKDAYS: [Weekday; 7] = [
+    Weekday::Mon,
+    Weekday::Tue,
+    Weekday::Wed,
+    Weekday::Thu,
+    Weekday::Fri,
+    Weekday::Sat,
+    Weekday::Sun,
+];
+
+/// The Monday of the week `date` is in.
+fn week_start(date: NaiveDate) -> NaiveDate {
+    date - Duration::days(date.weekday().num_days_from_monday() as i64)
+}
@@ -0,0 +1,463 @@
+// Local REST API, so other tools can add todos and read plans without
+// running the command line. Every request loads the data, calls the same
+// `PlannerData` methods as the CLI and saves when something changed, so the
+// rules are the same however a change comes in. Requests are handled one at
+// a time, which keeps two of them from saving over each other.
+//
+// Every request needs `Authorization: Bearer <token>`. Bodies and answers
+// are JSON; errors come back as `{"error": "..."}`.
+use std::net::SocketAddr;
+
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tiny_http::{Header, Method, Request, Response, Server};
+
+use crate::data::{NewTodo, PlannerData};
+use crate::error::{PlannerError, Result};
+use crate::report::{self, DateRange, Period, Report};
+use crate::routine::{CheckStatus, Frequency};
+use crate::store::Store;
+use crate::sync::{Delta, SyncState};
+use crate::time;
+use crate::todo::{Priority, Todo};
+
+/// Port `serve` listens on unless told otherwise.
+pub const DEFAULT_PORT: u16 = 7878;
+
+/// Body of `POST /todos`. Values are written as on the command line.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TodoBody {
+    title: String,
+    #[serde(default)]
+    priority: Option<String>,
+    /// `2024-01-31 17:00`, `2024-01-31` or an RFC 3339 instant.
+    #[serde(default)]
+    due: Option<String>,
+    #[serde(default)]
+    estimate_minutes: Option<u32>,
+    #[serde(default)]
+    subject: Option<String>,
+}
+
+/// Body of `POST /routines`.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RoutineBody {
+    name: String,
+    /// Local start time like `07:30`.
+    at: String,
+    duration_minutes: u32,
+    /// `daily`, `weekdays`, `weekends` or a list like `mon,wed,fri`.
+    #[serde(default)]
+    days: Option<String>,
+}
+
+/// Body of `POST /routines/{id}/done`, the day defaults to today.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CheckBody {
+    #[serde(default)]
+    date: Option<String>,
+    /// `done`, `partial` or `skipped`, done when left out.
+    #[serde(default)]
+    status: Option<String>,
+}
+
+/// Body of `POST /sessions`.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SessionBody {
+    subject: String,
+}
+
+/// Body of `POST /timers`, with either `minutes` or `at`.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TimerBody {
+    label: String,
+    #[serde(default)]
+    minutes: Option<u32>,
+    #[serde(default)]
+    at: Option<String>,
+    #[serde(default)]
+    todo_id: Option<u64>,
+}
+
+/// An answer before it is turned into an HTTP response.
+struct Reply {
+    status: u16,
+    content_type: &'static str,
+    body: String,
+}
+
+impl Reply {
+    fn json(status: u16, value: &impl Serialize) -> Result<Self> {
+        Ok(Reply {
+            status,
+            content_type: "application/json",
+            body: serde_json::to_string(value)?,
+        })
+    }
+
+    fn ok(value: &impl Serialize) -> Result<Self> {
+        Reply::json(200, value)
+    }
+
+    fn created(value: &impl Serialize) -> Result<Self> {
+        Reply::json(201, value)
+    }
+
+    fn error(status: u16, message: &str) -> Self {
+        Reply {
+            status,
+            content_type: "application/json",
+            body: json!({ "error": message }).to_string(),
+        }
+    }
+
+    /// The status matching an error: the caller's fault or ours.
+    fn from_error(error: &PlannerError) -> Self {
+        let status = match error {
+            PlannerError::NotFound(_) => 404,
+            PlannerError::Invalid(_) | PlannerError::Json(_) => 400,
+            _ => 500,
+        };
+        Reply::error(status, &error.to_string())
+    }
+}
+
+/// The parts of a request the routes look at.
+struct Call<'a> {
+    method: &'a Method,
+    segments: Vec<&'a str>,
+    /// Decoded, so `deep%20work` and `deep+work` are both `deep work`.
+    query: Vec<(String, String)>,
+    body: &'a str,
+}
+
+impl Call<'_> {
+    fn param(&self, name: &str) -> Option<&str> {
+        self.query
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    fn flag(&self, name: &str) -> bool {
+        matches!(self.param(name), Some("true" | "1" | "yes"))
+    }
+
+    fn date(&self, name: &str) -> Result<Option<NaiveDate>> {
+        self.param(name).map(time::parse_date).transpose()
+    }
+
+    /// Parses the JSON body, treating an empty body as `{}` when `T` allows it.
+    fn body<T: for<'de> Deserialize<'de>>(&self) -> Result<T> {
+        let body = if self.body.trim().is_empty() {
+            "{}"
+        } else {
+            self.body
+        };
+        serde_json::from_str(body).map_err(|e| PlannerError::Invalid(e.to_string()))
+    }
+}
+
+/// The `serve` command's HTTP server, bound to a local address.
+pub struct ApiServer {
+    http: Server,
+    store: Store,
+    token: String,
+}
+
+impl ApiServer {
+    /// Listens on `address` (use port 0 to get a free one). An empty token
+    /// is refused so the API is never open to every local program.
+    pub fn bind(mut store: Store, address: &str, token: &str) -> Result<Self> {
+        if token.trim().is_empty() {
+            return Err(PlannerError::Invalid(
+                "the server needs a token, set SMART_PLANNER_API_TOKEN or `api_token` in config.json"
+                    .to_string(),
+            ));
+        }
+        let http = Server::http(address)
+            .map_err(|e| PlannerError::Http(format!("cannot listen on {}: {}", address, e)))?;
+        // The history tells changes made over the API apart
+        let actor = format!("{} via api", store.actor());
+        store.set_actor(&actor);
+        Ok(ApiServer {
+            http,
+            store,
+            token: token.trim().to_string(),
+        })
+    }
+
+    pub fn address(&self) -> Option<SocketAddr> {
+        self.http.server_addr().to_ip()
+    }
+
+    /// Answers requests until the process is stopped.
+    pub fn run(&self) -> Result<()> {
+        for request in self.http.incoming_requests() {
+            self.respond(request);
+        }
+        Ok(())
+    }
+
+    fn respond(&self, mut request: Request) {
+        let mut body = String::new();
+        let reply = if !self.authorized(&request) {
+            Reply::error(401, "missing or wrong bearer token")
+        } else if request.as_reader().read_to_string(&mut body).is_err() {
+            Reply::error(400, "the body is not UTF-8 text")
+        } else {
+            let url = request.url().to_string();
+            let (path, query) = url.split_once('?').unwrap_or((&url, ""));
+            let call = Call {
+                method: request.method(),
+                segments: path.split('/').filter(|s| !s.is_empty()).collect(),
+                query: form_urlencoded::parse(query.as_bytes())
+                    .into_owned()
+                    .collect(),
+                body: &body,
+            };
+            self.handle(&call)
+                .unwrap_or_else(|error| Reply::from_error(&error))
+        };
+        let header = Header::from_bytes(&b"Content-Type"[..], reply.content_type.as_bytes())
+            .expect("content types are valid headers");
+        let response = Response::from_string(reply.body)
+            .with_status_code(reply.status)
+            .with_header(header);
+        // A client that went away is not the server's problem
+        let _ = request.respond(response);
+    }
+
+    fn authorized(&self, request: &Request) -> bool {
+        request.headers().iter().any(|header| {
+            header.field.equiv("Authorization")
+                && header
+                    .value
+                    .as_str()
+                    .strip_prefix("Bearer ")
+                    .is_some_and(|token| same_token(token.trim(), &self.token))
+        })
+    }
+
+    /// Loads the data, runs the route and saves when it changed something.
+    fn handle(&self, call: &Call) -> Result<Reply> {
+        if call.segments == ["sync"] {
+            if *call.method != Method::Post {
+                return Ok(Reply::error(405, "method not allowed"));
+            }
+            return self.sync(call);
+        }
+        let mut data = self.store.load()?;
+        let now = Utc::now();
+        let (reply, changed) = route(&mut data, call, now)?;
+        if changed {
+            self.store.save(&data)?;
+        }
+        Ok(reply)
+    }
+
+    /// Takes in the changes of another planner and answers with the ones it
+    /// lacks.
+    fn sync(&self, call: &Call) -> Result<Reply> {
+        let delta: Delta = call.body()?;
+        let mut data = self.store.load()?;
+        let before = data.clone();
+        let mut state = SyncState::load(self.store.dir())?;
+        state.capture(&data)?;
+        state.receive(&delta)?;
+        state.apply_to(&mut data)?;
+        if data != before {
+            self.store.save(&data)?;
+        }
+        state.save(self.store.dir())?;
+        Reply::ok(&state.delta_for(&delta.seen))
+    }
+}
+
+/// Compares every byte so the time taken does not give the token away.
+fn same_token(given: &str, expected: &str) -> bool {
+    given.len() == expected.len()
+        && given
+            .bytes()
+            .zip(expected.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+fn id(segment: &str) -> Result<u64> {
+    segment
+        .parse()
+        .map_err(|_| PlannerError::Invalid(format!("'{}' is not an id", segment)))
+}
+
+/// Accepts what the command line accepts, and RFC 3339 for programs.
+fn instant(text: &str) -> Result<DateTime<Utc>> {
+    match DateTime::parse_from_rfc3339(text.trim()) {
+        Ok(instant) => Ok(instant.with_timezone(&Utc)),
+        Err(_) => time::parse_local_datetime(text),
+    }
+}
+
+/// Returns the reply and whether the data changed.
+fn route(data: &mut PlannerData, call: &Call, now: DateTime<Utc>) -> Result<(Reply, bool)> {
+    let get = *call.method == Method::Get;
+    let post = *call.method == Method::Post;
+    let delete = *call.method == Method::Delete;
+    let segments = call.segments.as_slice();
+    let reply = match segments {
+        ["todos"] if get => {
+            let all = call.flag("all");
+            let mut todos: Vec<&Todo> = data.todos.iter().filter(|t| all || !t.done).collect();
+            todos.sort_by_key(|t| (t.done, std::cmp::Reverse(t.priority), t.due, t.id));
+            Reply::ok(&todos)?
+        }
+        ["todos"] if post => {
+            let body: TodoBody = call.body()?;
+            let priority = match &body.priority {
+                Some(priority) => priority.parse()?,
+                None => Priority::Medium,
+            };
+            let mut new = NewTodo::new(&body.title, priority);
+            new.due = body.due.as_deref().map(instant).transpose()?;
+            new.estimate_minutes = body.estimate_minutes;
+            new.subject = body.subject;
+            let id = data.add_todo(new, now)?;
+            return Ok((Reply::created(data.todo(id)?)?, true));
+        }
+        ["todos", todo] if get => Reply::ok(data.todo(id(todo)?)?)?,
+        ["todos", todo] if delete => return Ok((Reply::ok(&data.remove_todo(id(todo)?)?)?, true)),
+        ["todos", todo, "done"] if post => {
+            let id = id(todo)?;
+            data.complete_todo(id, now)?;
+            return Ok((Reply::ok(data.todo(id)?)?, true));
+        }
+
+        ["routines"] if get => Reply::ok(&data.routines)?,
+        ["routines"] if post => {
+            let body: RoutineBody = call.body()?;
+            let frequency: Frequency = body.days.as_deref().unwrap_or("daily").parse()?;
+            let id = data.add_routine(
+                &body.name,
+                time::parse_time(&body.at)?,
+                body.duration_minutes,
+                frequency,
+                now,
+            )?;
+            let routine = data.routines.iter().find(|r| r.id == id).unwrap();
+            return Ok((Reply::created(routine)?, true));
+        }
+        ["routines", routine] if delete => {
+            return Ok((Reply::ok(&data.remove_routine(id(routine)?)?)?, true))
+        }
+        ["routines", routine, "done"] if post => {
+            let body: CheckBody = call.body()?;
+            let date = match body.date {
+                Some(date) => time::parse_date(&date)?,
+                None => time::today(),
+            };
+            let status = match &body.status {
+                Some(status) => status.parse()?,
+                None => CheckStatus::Done,
+            };
+            let check = data.mark_routine(id(routine)?, date, status)?;
+            return Ok((Reply::ok(&check)?, true));
+        }
+
+        ["sessions"] if get => Reply::ok(&data.sessions)?,
+        ["sessions"] if post => {
+            let body: SessionBody = call.body()?;
+            let id = data.start_session(&body.subject, now)?;
+            let session = data.sessions.iter().find(|s| s.id == id).unwrap();
+            return Ok((Reply::created(session)?, true));
+        }
+        ["sessions", "running"] if get => match data.running_session() {
+            Some(session) => Reply::ok(session)?,
+            None => Reply::error(404, "the stopwatch is not running"),
+        },
+        ["sessions", "stop"] if post => return Ok((Reply::ok(&data.stop_session(now)?)?, true)),
+
+        ["timers"] if get => {
+            if call.flag("all") {
+                Reply::ok(&data.timers)?
+            } else {
+                Reply::ok(&data.pending_timers())?
+            }
+        }
+        ["timers"] if post => {
+            let body: TimerBody = call.body()?;
+            let due = match (body.minutes, body.at.as_deref()) {
+                (Some(minutes), None) => now + chrono::Duration::minutes(minutes as i64),
+                (None, Some(at)) => instant(at)?,
+                _ => {
+                    return Err(PlannerError::Invalid(
+                        "a timer needs either minutes or at".to_string(),
+                    ))
+                }
+            };
+            let id = data.add_timer(&body.label, due, body.todo_id, now)?;
+            let timer = data.timers.iter().find(|t| t.id == id).unwrap();
+            return Ok((Reply::created(timer)?, true));
+        }
+        ["timers", timer] if delete => {
+            return Ok((Reply::ok(&data.cancel_timer(id(timer)?)?)?, true))
+        }
+
+        ["plans", "today"] if get => plan(data, time::today())?,
+        ["plans", date] if get => plan(data, time::parse_date(date)?)?,
+
+        ["reports"] if get => report(data, call)?,
+
+        ["todos" | "routines" | "sessions" | "timers" | "plans" | "reports", ..] => {
+            Reply::error(405, "method not allowed")
+        }
+        _ => Reply::error(404, "no such endpoint"),
+    };
+    Ok((reply, false))
+}
+
+fn plan(data: &PlannerData, date: NaiveDate) -> Result<Reply> {
+    let plan = data
+        .plan(date)
+        .ok_or_else(|| PlannerError::NotFound(format!("plan for {}", date)))?;
+    Reply::ok(plan)
+}
+
+/// `GET /reports?period=week&date=...` or `?from=...&to=...`, as JSON unless
+/// `format` asks for `text`, `csv` or `html`.
+fn report(data: &PlannerData, call: &Call) -> Result<Reply> {
+    let report = match (call.date("from")?, call.date("to")?) {
+        (Some(from), Some(to)) => Report::for_range(data, DateRange::new(from, to)?),
+        (None, None) => {
+            let period: Period = call.param("period").unwrap_or("week").parse()?;
+            Report::for_period(data, period, call.date("date")?.unwrap_or_else(time::today))
+        }
+        _ => {
+            return Err(PlannerError::Invalid(
+                "a custom range needs both from and to".to_string(),
+            ))
+        }
+    };
+    let (content_type, body) = match call.param("format").unwrap_or("json") {
+        "json" => return Reply::ok(&report),
+        "text" => ("text/plain; charset=utf-8", report::text::render(&report)),
+        "csv" => ("text/csv; charset=utf-8", report::csv::render(&report)),
+        "html" => ("text/html; charset=utf-8", report::html::render(&report)),
+        other => {
+            return Err(PlannerError::Invalid(format!(
+                "'{}' is not a report format (json, text, csv, html)",
+                other
+            )))
+        }
+    };
+    Ok(Reply {
+        status: 200,
+        content_type,
+        body,
+    })
+}
@@ -0,0 +1,60 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// A stretch of time spent on one subject. The session that is still
+/// running has no end yet.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Session {
+    pub id: u64,
+    pub subject: String,
+    pub start: DateTime<Utc>,
+    #[serde(default)]
+    pub end: Option<DateTime<Utc>>,
+}
+
+impl Session {
+    pub fn is_running(&self) -> bool {
+        self.end.is_none()
+    }
+
+    /// Seconds of the finished session that fall between `from` and `to`.
+    /// A running session counts as zero so past reports never change.
+    pub fn seconds_between(&self, from: DateTime<Utc>, to: DateTime<Utc>) -> i64 {
+        match self.end {
+            Some(end) => self.overlap(from, to, end),
+            None => 0,
+        }
+    }
+
+    /// Like `seconds_between`, but a running session counts up to `now`.
+    pub fn seconds_until(&self, from: DateTime<Utc>, to: DateTime<Utc>, now: DateTime<Utc>) -> i64 {
+        self.overlap(from, to, self.end.unwrap_or(now))
+    }
+
+    fn overlap(&self, from: DateTime<Utc>, to: DateTime<Utc>, end: DateTime<Utc>) -> i64 {
+        let start = self.start.max(from);
+        let end = end.min(to);
+        if end > start {
+            (end - start).num_seconds()
+        } else {
+            0
+        }
+    }
+
+    /// Length so far, up to `now` for a running session.
+    pub fn elapsed(&self, now: DateTime<Utc>) -> chrono::Duration {
+        self.end.unwrap_or(now) - self.start
+    }
+}
+
+/// Formats a length as `1h 05m`.
+pub fn format_duration(duration: chrono::Duration) -> String {
+    let minutes = duration.num_minutes();
+    let sign = if minutes < 0 { "-" } else { "" };
+    let minutes = minutes.abs();
+    if minutes >= 60 {
+        format!("{}{}h {:02}m", sign, minutes / 60, minutes % 60)
+    } else {
+        format!("{}{}m", sign, minutes)
+    }
+}
@@ -0,0 +1,145 @@
+// Where the planner keeps its data. Every backend stores the same things
+// (todos, routines and their check-ins, stopwatch sessions, timers and plans)
+// and hands them back unchanged, so data can be moved between them.
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::data::PlannerData;
+use crate::error::{PlannerError, Result};
+
+pub mod http;
+pub mod json;
+pub mod sqlite;
+
+pub use http::HttpStorage;
+pub use json::JsonStorage;
+pub use sqlite::SqliteStorage;
+
+/// A place the planner data can be loaded from and saved to.
+pub trait Storage {
+    /// Short description used in messages, like `sqlite /home/me/planner.db`.
+    fn describe(&self) -> String;
+
+    /// Loads everything, or empty data when nothing was saved yet.
+    fn load(&self) -> Result<PlannerData>;
+
+    /// Replaces what is stored with `data`. A failed save leaves the old data
+    /// in place.
+    fn save(&self, data: &PlannerData) -> Result<()>;
+}
+
+/// The `storage` section of `config.json`, also accepted on the command line
+/// as `json`, `json:PATH`, `sqlite`, `sqlite:PATH` or an `http(s)://` URL.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "backend", rename_all = "lowercase")]
+pub enum StorageConfig {
+    /// One JSON file, `data.json` in the planner folder by default.
+    Json {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        path: Option<PathBuf>,
+    },
+    /// A SQLite database, `planner.db` in the planner folder by default.
+    Sqlite {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        path: Option<PathBuf>,
+    },
+    /// A sync server reached over HTTP.
+    Http {
+        url: String,
+        /// Better kept in `SMART_PLANNER_SYNC_TOKEN` than in the file.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        token: Option<String>,
+    },
+}
+
+impl Default for StorageConfig {
+    fn default() -> Self {
+        StorageConfig::Json { path: None }
+    }
+}
+
+impl StorageConfig {
+    /// Opens the backend. Relative paths are taken from the planner folder.
+    pub fn open(&self, dir: &Path) -> Result<Box<dyn Storage>> {
+        let resolve = |path: &Option<PathBuf>, default: &str| match path {
+            Some(path) => dir.join(path),
+            None => dir.join(default),
+        };
+        Ok(match self {
+            StorageConfig::Json { path } => Box::new(JsonStorage::new(&resolve(path, "data.json"))),
+            StorageConfig::Sqlite { path } => {
+                Box::new(SqliteStorage::open(&resolve(path, "planner.db"))?)
+            }
+            StorageConfig::Http { url, token } => {
+                let token = std::env::var("SMART_PLANNER_SYNC_TOKEN")
+                    .ok()
+                    .or_else(|| token.clone());
+                Box::new(HttpStorage::new(url, token)?)
+            }
+        })
+    }
+}
+
+impl fmt::Display for StorageConfig {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StorageConfig::Json { path: None } => write!(f, "json"),
+            StorageConfig::Json { path: Some(path) } => write!(f, "json:{}", path.display()),
+            StorageConfig::Sqlite { path: None } => write!(f, "sqlite"),
+            StorageConfig::Sqlite { path: Some(path) } => {
+                write!(f, "sqlite:{}", path.display())
+            }
+            StorageConfig::Http { url, .. } => write!(f, "{}", url),
+        }
+    }
+}
+
+impl FromStr for StorageConfig {
+    type Err = PlannerError;
+
+    fn from_str(text: &str) -> Result<Self> {
+        let text = text.trim();
+        if text.starts_with("http://") || text.starts_with("https://") {
+            return Ok(StorageConfig::Http {
+                url: text.to_string(),
+                token: None,
+            });
+        }
+        let (backend, path) = match text.split_once(':') {
+            Some((backend, path)) => (backend, Some(PathBuf::from(path))),
+            None => (text, None),
+        };
+        match backend {
+            "json" => Ok(StorageConfig::Json { path }),
+            "sqlite" => Ok(StorageConfig::Sqlite { path }),
+            _ => Err(PlannerError::Invalid(format!(
+                "'{}' is not a storage (json, sqlite, json:PATH, sqlite:PATH or an http URL)",
+                text
+            ))),
+        }
+    }
+}
+
+/// Copies everything from one backend to another and reads it back to make
+/// sure nothing was lost. Refuses to overwrite a backend that already holds
+/// data unless `overwrite` is set.
+pub fn migrate(from: &dyn Storage, to: &dyn Storage, overwrite: bool) -> Result<PlannerData> {
+    let data = from.load()?;
+    if !overwrite && to.load()? != PlannerData::default() {
+        return Err(PlannerError::Invalid(format!(
+            "{} already holds data, pass --overwrite to replace it",
+            to.describe()
+        )));
+    }
+    to.save(&data)?;
+    if to.load()? != data {
+        return Err(PlannerError::Storage(format!(
+            "{} did not give back the data that was saved",
+            to.describe()
+        )));
+    }
+    Ok(data)
+}
@@ -0,0 +1,129 @@
+// Keeps the data on a sync server so several machines share one planner.
+//
+// The protocol is two requests on `{url}/data`:
+// - `GET` answers `{"revision": 3, "data": {...}}`, or 404 while the server
+//   is empty.
+// - `PUT` sends `{"base_revision": 3, "data": {...}}` and gets back
+//   `{"revision": 4}`. When someone else saved in between the server answers
+//   409 and nothing is overwritten.
+use std::cell::Cell;
+use std::time::Duration;
+
+use reqwest::blocking::{Client, RequestBuilder};
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+
+use super::Storage;
+use crate::data::PlannerData;
+use crate::error::{PlannerError, Result};
+
+/// What `GET /data` returns.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub revision: u64,
+    pub data: PlannerData,
+}
+
+/// What `PUT /data` sends.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Upload {
+    /// The revision the client loaded before changing the data.
+    pub base_revision: u64,
+    pub data: PlannerData,
+}
+
+/// What `PUT /data` returns.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Saved {
+    pub revision: u64,
+}
+
+pub struct HttpStorage {
+    url: String,
+    token: Option<String>,
+    client: Client,
+    /// Revision of the last load or save, `None` before the first one.
+    revision: Cell<Option<u64>>,
+}
+
+impl HttpStorage {
+    pub fn new(base_url: &str, token: Option<String>) -> Result<Self> {
+        let client = Client::builder().timeout(Duration::from_secs(30)).build()?;
+        Ok(HttpStorage {
+            url: format!("{}/data", base_url.trim_end_matches('/')),
+            token,
+            client,
+            revision: Cell::new(None),
+        })
+    }
+
+    fn authorize(&self, request: RequestBuilder) -> RequestBuilder {
+        match &self.token {
+            Some(token) => request.bearer_auth(token),
+            None => request,
+        }
+    }
+
+    fn fetch(&self) -> Result<Snapshot> {
+        let response = self.authorize(self.client.get(&self.url)).send()?;
+        match response.status() {
+            StatusCode::NOT_FOUND => Ok(Snapshot {
+                revision: 0,
+                data: PlannerData::default(),
+            }),
+            status if status.is_success() => Ok(response.json()?),
+            status => Err(PlannerError::Http(format!(
+                "{} returned {}: {}",
+                self.url,
+                status,
+                response.text().unwrap_or_default()
+            ))),
+        }
+    }
+}
+
+impl Storage for HttpStorage {
+    fn describe(&self) -> String {
+        format!("sync server {}", self.url)
+    }
+
+    fn load(&self) -> Result<PlannerData> {
+        let snapshot = self.fetch()?;
+        self.revision.set(Some(snapshot.revision));
+        Ok(snapshot.data)
+    }
+
+    /// Saves on top of the revision that was loaded. Without an earlier load
+    /// the current revision is fetched first, so the save replaces it.
+    fn save(&self, data: &PlannerData) -> Result<()> {
+        let base_revision = match self.revision.get() {
+            Some(revision) => revision,
+            None => self.fetch()?.revision,
+        };
+        let upload = Upload {
+            base_revision,
+            data: data.clone(),
+        };
+        let response = self
+            .authorize(self.client.put(&self.url))
+            .json(&upload)
+            .send()?;
+        match response.status() {
+            status if status.is_success() => {
+                let saved: Saved = response.json()?;
+                self.revision.set(Some(saved.revision));
+                Ok(())
+            }
+            StatusCode::CONFLICT => Err(PlannerError::Storage(format!(
+                "{} changed since revision {} was loaded, run the command again",
+                self.url, base_revision
+            ))),
+            status => Err(PlannerError::Http(format!(
+                "{} returned {}: {}",
+                self.url,
+                status,
+                response.text().unwrap_or_default()
+            ))),
+        }
+    }
+}
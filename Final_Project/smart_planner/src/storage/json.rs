@@ -0,0 +1,46 @@
+// The whole planner in one pretty-printed JSON file.
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::Storage;
+use crate::data::PlannerData;
+use crate::error::Result;
+
+#[derive(Debug, Clone)]
+pub struct JsonStorage {
+    path: PathBuf,
+}
+
+impl JsonStorage {
+    pub fn new(path: &Path) -> Self {
+        JsonStorage {
+            path: path.to_path_buf(),
+        }
+    }
+}
+
+impl Storage for JsonStorage {
+    fn describe(&self) -> String {
+        format!("json {}", self.path.display())
+    }
+
+    fn load(&self) -> Result<PlannerData> {
+        if !self.path.exists() {
+            return Ok(PlannerData::default());
+        }
+        let text = fs::read_to_string(&self.path)?;
+        Ok(serde_json::from_str(&text)?)
+    }
+
+    /// Writes a temporary file first so a crash never leaves half a file behind.
+    fn save(&self, data: &PlannerData) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut temp = self.path.clone().into_os_string();
+        temp.push(".tmp");
+        fs::write(&temp, serde_json::to_string_pretty(data)?)?;
+        fs::rename(temp, &self.path)?;
+        Ok(())
+    }
+}
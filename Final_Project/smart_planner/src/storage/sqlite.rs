@@ -0,0 +1,703 @@
+// A SQLite database with one table per kind of item. The schema is versioned
+// with `PRAGMA user_version`; opening a database applies the migrations it
+// has not seen yet, each in its own transaction.
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use chrono::{DateTime, NaiveDate, NaiveTime, Utc};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use sqlite::{Connection, ConnectionThreadSafe, Row, State, Value};
+
+use super::Storage;
+use crate::budget::Budget;
+use crate::busy::{BusyEvent, BusyTime};
+use crate::data::PlannerData;
+use crate::error::{PlannerError, Result};
+use crate::plan::{Plan, PlanBlock};
+use crate::pomodoro::{Pomodoro, PomodoroRun, PomodoroSettings};
+use crate::project::Project;
+use crate::routine::{Routine, RoutineCheck};
+use crate::stopwatch::Session;
+use crate::timer::Timer;
+use crate::todo::Todo;
+
+/// Schema changes in order. Entry `n` takes the database from version `n` to
+/// `n + 1`. Never edit an entry once released, add a new one instead.
+const MIGRATIONS: &[&str] = &[
+    "
+    CREATE TABLE meta (
+        key TEXT PRIMARY KEY,
+        value TEXT NOT NULL
+    );
+    CREATE TABLE todos (
+        id INTEGER PRIMARY KEY,
+        title TEXT NOT NULL,
+        priority TEXT NOT NULL,
+        done INTEGER NOT NULL,
+        due TEXT,
+        estimate_minutes INTEGER,
+        subject TEXT,
+        created_at TEXT NOT NULL,
+        completed_at TEXT
+    );
+    CREATE TABLE routines (
+        id INTEGER PRIMARY KEY,
+        name TEXT NOT NULL,
+        start TEXT NOT NULL,
+        duration_minutes INTEGER NOT NULL,
+        frequency TEXT NOT NULL,
+        created_at TEXT
+    );
+    CREATE TABLE routine_checks (
+        routine_id INTEGER NOT NULL,
+        date TEXT NOT NULL,
+        PRIMARY KEY (routine_id, date)
+    );
+    CREATE TABLE sessions (
+        id INTEGER PRIMARY KEY,
+        subject TEXT NOT NULL,
+        start TEXT NOT NULL,
+        end TEXT
+    );
+    CREATE TABLE timers (
+        id INTEGER PRIMARY KEY,
+        label TEXT NOT NULL,
+        due TEXT NOT NULL,
+        created_at TEXT NOT NULL,
+        todo_id INTEGER,
+        fired INTEGER NOT NULL
+    );
+    CREATE TABLE plans (
+        date TEXT PRIMARY KEY,
+        generated_by TEXT NOT NULL,
+        edited INTEGER NOT NULL,
+        unscheduled TEXT NOT NULL
+    );
+    CREATE TABLE plan_blocks (
+        plan_date TEXT NOT NULL REFERENCES plans (date) ON DELETE CASCADE,
+        position INTEGER NOT NULL,
+        start TEXT NOT NULL,
+        end TEXT NOT NULL,
+        title TEXT NOT NULL,
+        kind TEXT NOT NULL,
+        todo_id INTEGER,
+        routine_id INTEGER,
+        PRIMARY KEY (plan_date, position)
+    );
+",
+    "
+    CREATE TABLE busy_events (
+        uid TEXT NOT NULL,
+        summary TEXT NOT NULL,
+        all_day INTEGER NOT NULL,
+        start TEXT NOT NULL,
+        end TEXT NOT NULL
+    );
+",
+    "
+    CREATE TABLE pomodoros (
+        id INTEGER PRIMARY KEY,
+        subject TEXT NOT NULL,
+        start TEXT NOT NULL,
+        end TEXT NOT NULL,
+        interrupted INTEGER NOT NULL,
+        session_id INTEGER NOT NULL
+    );
+    CREATE TABLE pomodoro_run (
+        subject TEXT NOT NULL,
+        work_minutes INTEGER NOT NULL,
+        short_break_minutes INTEGER NOT NULL,
+        long_break_minutes INTEGER NOT NULL,
+        long_break_every INTEGER NOT NULL,
+        phase TEXT NOT NULL,
+        phase_start TEXT NOT NULL,
+        completed INTEGER NOT NULL,
+        cycles INTEGER,
+        session_id INTEGER
+    );
+",
+    "
+    ALTER TABLE routine_checks ADD COLUMN status TEXT NOT NULL DEFAULT 'done';
+",
+    "
+    CREATE TABLE projects (
+        id INTEGER PRIMARY KEY,
+        name TEXT NOT NULL,
+        created_at TEXT NOT NULL
+    );
+    ALTER TABLE todos ADD COLUMN project_id INTEGER;
+    ALTER TABLE todos ADD COLUMN parent_id INTEGER;
+    CREATE TABLE todo_blockers (
+        todo_id INTEGER NOT NULL,
+        blocker_id INTEGER NOT NULL,
+        PRIMARY KEY (todo_id, blocker_id)
+    );
+",
+    "
+    CREATE TABLE budgets (
+        id INTEGER PRIMARY KEY,
+        subject TEXT NOT NULL,
+        kind TEXT NOT NULL,
+        minutes INTEGER NOT NULL,
+        from_date TEXT NOT NULL,
+        until_date TEXT,
+        alerted TEXT NOT NULL
+    );
+",
+    "
+    ALTER TABLE timers ADD COLUMN zone TEXT;
+    ALTER TABLE timers ADD COLUMN daily TEXT;
+",
+];
+
+/// The schema version this build writes.
+pub const SCHEMA_VERSION: usize = MIGRATIONS.len();
+
+pub struct SqliteStorage {
+    path: PathBuf,
+    connection: ConnectionThreadSafe,
+}
+
+impl SqliteStorage {
+    /// Opens or creates the database and brings its schema up to date.
+    pub fn open(path: &Path) -> Result<Self> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let mut connection = Connection::open_thread_safe(path)?;
+        connection.set_busy_timeout(5000)?;
+        connection.execute("PRAGMA foreign_keys = ON;")?;
+        let storage = SqliteStorage {
+            path: path.to_path_buf(),
+            connection,
+        };
+        storage.upgrade()?;
+        Ok(storage)
+    }
+
+    /// The version recorded in the database file.
+    pub fn schema_version(&self) -> Result<usize> {
+        let mut statement = self.connection.prepare("PRAGMA user_version;")?;
+        statement.next()?;
+        Ok(statement.read::<i64, _>(0)? as usize)
+    }
+
+    fn upgrade(&self) -> Result<()> {
+        let version = self.schema_version()?;
+        if version > SCHEMA_VERSION {
+            return Err(PlannerError::Storage(format!(
+                "{} has schema version {}, this build only knows up to {}",
+                self.path.display(),
+                version,
+                SCHEMA_VERSION
+            )));
+        }
+        for (index, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+            self.transaction(|connection| {
+                connection.execute(migration)?;
+                connection.execute(format!("PRAGMA user_version = {};", index + 1))?;
+                Ok(())
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Runs `work` in a transaction, rolling back when it fails.
+    fn transaction(&self, work: impl FnOnce(&Connection) -> Result<()>) -> Result<()> {
+        self.connection.execute("BEGIN IMMEDIATE;")?;
+        match work(&self.connection) {
+            Ok(()) => {
+                self.connection.execute("COMMIT;")?;
+                Ok(())
+            }
+            Err(e) => {
+                let _ = self.connection.execute("ROLLBACK;");
+                Err(e)
+            }
+        }
+    }
+}
+
+impl Storage for SqliteStorage {
+    fn describe(&self) -> String {
+        format!("sqlite {}", self.path.display())
+    }
+
+    fn load(&self) -> Result<PlannerData> {
+        let connection = &self.connection;
+        let mut data = PlannerData::default();
+        for row in select(connection, "SELECT value FROM meta WHERE key = 'next_id'")? {
+            data.next_id = parse(text(&row, "value")?)?;
+        }
+        for row in select(connection, "SELECT * FROM todos ORDER BY id")? {
+            data.todos.push(Todo {
+                id: id(&row, "id")?,
+                title: text(&row, "title")?.to_string(),
+                priority: from_text(text(&row, "priority")?)?,
+                done: flag(&row, "done")?,
+                due: optional(&row, "due", instant)?,
+                estimate_minutes: row
+                    .try_read::<Option<i64>, _>("estimate_minutes")?
+                    .map(|minutes| minutes as u32),
+                subject: optional_text(&row, "subject")?,
+                created_at: instant(text(&row, "created_at")?)?,
+                completed_at: optional(&row, "completed_at", instant)?,
+                project: optional_id(&row, "project_id")?,
+                parent: optional_id(&row, "parent_id")?,
+                blocked_by: Vec::new(),
+            });
+        }
+        for row in select(connection, "SELECT * FROM todo_blockers ORDER BY rowid")? {
+            let todo_id = id(&row, "todo_id")?;
+            let todo = data
+                .todos
+                .iter_mut()
+                .find(|todo| todo.id == todo_id)
+                .ok_or_else(|| {
+                    PlannerError::Storage(format!("blockers for a missing todo #{}", todo_id))
+                })?;
+            todo.blocked_by.push(id(&row, "blocker_id")?);
+        }
+        for row in select(connection, "SELECT * FROM routines ORDER BY id")? {
+            data.routines.push(Routine {
+                id: id(&row, "id")?,
+                name: text(&row, "name")?.to_string(),
+                start: parse::<NaiveTime>(text(&row, "start")?)?,
+                duration_minutes: row.try_read::<i64, _>("duration_minutes")? as u32,
+                frequency: parse(text(&row, "frequency")?)?,
+                created_at: optional(&row, "created_at", instant)?,
+            });
+        }
+        for row in select(connection, "SELECT * FROM routine_checks ORDER BY rowid")? {
+            data.routine_checks.push(RoutineCheck {
+                routine_id: id(&row, "routine_id")?,
+                date: parse::<NaiveDate>(text(&row, "date")?)?,
+                status: parse(text(&row, "status")?)?,
+            });
+        }
+        for row in select(connection, "SELECT * FROM sessions ORDER BY id")? {
+            data.sessions.push(Session {
+                id: id(&row, "id")?,
+                subject: text(&row, "subject")?.to_string(),
+                start: instant(text(&row, "start")?)?,
+                end: optional(&row, "end", instant)?,
+            });
+        }
+        for row in select(connection, "SELECT * FROM timers ORDER BY id")? {
+            data.timers.push(Timer {
+                id: id(&row, "id")?,
+                label: text(&row, "label")?.to_string(),
+                due: instant(text(&row, "due")?)?,
+                created_at: instant(text(&row, "created_at")?)?,
+                todo_id: optional_id(&row, "todo_id")?,
+                fired: flag(&row, "fired")?,
+                zone: optional_text(&row, "zone")?,
+                daily: optional(&row, "daily", parse::<NaiveTime>)?,
+            });
+        }
+        for row in select(connection, "SELECT * FROM plans ORDER BY date")? {
+            let mut plan = Plan::new(parse(text(&row, "date")?)?, text(&row, "generated_by")?);
+            plan.edited = flag(&row, "edited")?;
+            plan.unscheduled = text(&row, "unscheduled")?
+                .split(',')
+                .filter(|id| !id.is_empty())
+                .map(parse)
+                .collect::<Result<_>>()?;
+            data.plans.push(plan);
+        }
+        for row in select(
+            connection,
+            "SELECT * FROM plan_blocks ORDER BY plan_date, position",
+        )? {
+            let date: NaiveDate = parse(text(&row, "plan_date")?)?;
+            let plan = data
+                .plans
+                .iter_mut()
+                .find(|plan| plan.date == date)
+                .ok_or_else(|| {
+                    PlannerError::Storage(format!("blocks without a plan for {}", date))
+                })?;
+            plan.blocks.push(PlanBlock {
+                start: parse(text(&row, "start")?)?,
+                end: parse(text(&row, "end")?)?,
+                title: text(&row, "title")?.to_string(),
+                kind: from_text(text(&row, "kind")?)?,
+                todo_id: optional_id(&row, "todo_id")?,
+                routine_id: optional_id(&row, "routine_id")?,
+            });
+        }
+        for row in select(connection, "SELECT * FROM busy_events ORDER BY rowid")? {
+            let (start, end) = (text(&row, "start")?, text(&row, "end")?);
+            let time = if flag(&row, "all_day")? {
+                BusyTime::AllDay {
+                    first: parse(start)?,
+                    last: parse(end)?,
+                }
+            } else {
+                BusyTime::Timed {
+                    start: instant(start)?,
+                    end: instant(end)?,
+                }
+            };
+            data.busy.push(BusyEvent {
+                uid: text(&row, "uid")?.to_string(),
+                summary: text(&row, "summary")?.to_string(),
+                time,
+            });
+        }
+        for row in select(connection, "SELECT * FROM pomodoros ORDER BY id")? {
+            data.pomodoros.push(Pomodoro {
+                id: id(&row, "id")?,
+                subject: text(&row, "subject")?.to_string(),
+                start: instant(text(&row, "start")?)?,
+                end: instant(text(&row, "end")?)?,
+                interrupted: flag(&row, "interrupted")?,
+                session_id: id(&row, "session_id")?,
+            });
+        }
+        for row in select(connection, "SELECT * FROM pomodoro_run")? {
+            let minutes = |column| row.try_read::<i64, _>(column).map(|value| value as u32);
+            data.pomodoro = Some(PomodoroRun {
+                subject: text(&row, "subject")?.to_string(),
+                settings: PomodoroSettings {
+                    work_minutes: minutes("work_minutes")?,
+                    short_break_minutes: minutes("short_break_minutes")?,
+                    long_break_minutes: minutes("long_break_minutes")?,
+                    long_break_every: minutes("long_break_every")?,
+                },
+                phase: from_text(text(&row, "phase")?)?,
+                phase_start: instant(text(&row, "phase_start")?)?,
+                completed: minutes("completed")?,
+                cycles: row
+                    .try_read::<Option<i64>, _>("cycles")?
+                    .map(|cycles| cycles as u32),
+                session_id: optional_id(&row, "session_id")?,
+            });
+        }
+        for row in select(connection, "SELECT * FROM projects ORDER BY id")? {
+            data.projects.push(Project {
+                id: id(&row, "id")?,
+                name: text(&row, "name")?.to_string(),
+                created_at: instant(text(&row, "created_at")?)?,
+            });
+        }
+        for row in select(connection, "SELECT * FROM budgets ORDER BY id")? {
+            data.budgets.push(Budget {
+                id: id(&row, "id")?,
+                subject: text(&row, "subject")?.to_string(),
+                kind: from_text(text(&row, "kind")?)?,
+                minutes: row.try_read::<i64, _>("minutes")? as u32,
+                from: parse(text(&row, "from_date")?)?,
+                until: optional(&row, "until_date", parse)?,
+                alerted: text(&row, "alerted")?
+                    .split(',')
+                    .filter(|alert| !alert.is_empty())
+                    .map(|alert| {
+                        let (week, percent) = alert.split_once('@').ok_or_else(|| {
+                            PlannerError::Storage(format!("cannot read budget alert '{}'", alert))
+                        })?;
+                        Ok((parse(week)?, parse(percent)?))
+                    })
+                    .collect::<Result<_>>()?,
+            });
+        }
+        Ok(data)
+    }
+
+    /// Replaces every row in one transaction.
+    fn save(&self, data: &PlannerData) -> Result<()> {
+        self.transaction(|connection| {
+            connection.execute(
+                "DELETE FROM meta; DELETE FROM todos; DELETE FROM routines;
+                 DELETE FROM routine_checks; DELETE FROM sessions; DELETE FROM timers;
+                 DELETE FROM plan_blocks; DELETE FROM plans; DELETE FROM busy_events;
+                 DELETE FROM pomodoros; DELETE FROM pomodoro_run; DELETE FROM projects;
+                 DELETE FROM todo_blockers; DELETE FROM budgets;",
+            )?;
+            insert(
+                connection,
+                "INSERT INTO meta (key, value) VALUES ('next_id', ?)",
+                [vec![data.next_id.to_string().into()]],
+            )?;
+            insert(
+                connection,
+                "INSERT INTO todos (id, title, priority, done, due, estimate_minutes, subject,
+                 created_at, completed_at, project_id, parent_id)
+                 VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+                data.todos.iter().map(|todo| {
+                    vec![
+                        (todo.id as i64).into(),
+                        todo.title.as_str().into(),
+                        to_text(&todo.priority).into(),
+                        (todo.done as i64).into(),
+                        todo.due.map(stamp).into(),
+                        todo.estimate_minutes.map(i64::from).into(),
+                        todo.subject.clone().into(),
+                        stamp(todo.created_at).into(),
+                        todo.completed_at.map(stamp).into(),
+                        todo.project.map(|id| id as i64).into(),
+                        todo.parent.map(|id| id as i64).into(),
+                    ]
+                }),
+            )?;
+            insert(
+                connection,
+                "INSERT INTO todo_blockers (todo_id, blocker_id) VALUES (?, ?)",
+                data.todos.iter().flat_map(|todo| {
+                    todo.blocked_by
+                        .iter()
+                        .map(|blocker| vec![(todo.id as i64).into(), (*blocker as i64).into()])
+                }),
+            )?;
+            insert(
+                connection,
+                "INSERT INTO routines (id, name, start, duration_minutes, frequency, created_at)
+                 VALUES (?, ?, ?, ?, ?, ?)",
+                data.routines.iter().map(|routine| {
+                    vec![
+                        (routine.id as i64).into(),
+                        routine.name.as_str().into(),
+                        routine.start.to_string().into(),
+                        i64::from(routine.duration_minutes).into(),
+                        routine.frequency.to_string().into(),
+                        routine.created_at.map(stamp).into(),
+                    ]
+                }),
+            )?;
+            insert(
+                connection,
+                "INSERT INTO routine_checks (routine_id, date, status) VALUES (?, ?, ?)",
+                data.routine_checks.iter().map(|check| {
+                    vec![
+                        (check.routine_id as i64).into(),
+                        check.date.to_string().into(),
+                        check.status.to_string().into(),
+                    ]
+                }),
+            )?;
+            insert(
+                connection,
+                "INSERT INTO sessions (id, subject, start, end) VALUES (?, ?, ?, ?)",
+                data.sessions.iter().map(|session| {
+                    vec![
+                        (session.id as i64).into(),
+                        session.subject.as_str().into(),
+                        stamp(session.start).into(),
+                        session.end.map(stamp).into(),
+                    ]
+                }),
+            )?;
+            insert(
+                connection,
+                "INSERT INTO timers (id, label, due, created_at, todo_id, fired, zone, daily)
+                 VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
+                data.timers.iter().map(|timer| {
+                    vec![
+                        (timer.id as i64).into(),
+                        timer.label.as_str().into(),
+                        stamp(timer.due).into(),
+                        stamp(timer.created_at).into(),
+                        timer.todo_id.map(|id| id as i64).into(),
+                        (timer.fired as i64).into(),
+                        timer.zone.as_deref().into(),
+                        timer.daily.map(|at| at.to_string()).into(),
+                    ]
+                }),
+            )?;
+            insert(
+                connection,
+                "INSERT INTO plans (date, generated_by, edited, unscheduled) VALUES (?, ?, ?, ?)",
+                data.plans.iter().map(|plan| {
+                    let unscheduled: Vec<String> =
+                        plan.unscheduled.iter().map(|id| id.to_string()).collect();
+                    vec![
+                        plan.date.to_string().into(),
+                        plan.generated_by.as_str().into(),
+                        (plan.edited as i64).into(),
+                        unscheduled.join(",").into(),
+                    ]
+                }),
+            )?;
+            insert(
+                connection,
+                "INSERT INTO plan_blocks (plan_date, position, start, end, title, kind, todo_id,
+                 routine_id) VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
+                data.plans.iter().flat_map(|plan| {
+                    plan.blocks.iter().enumerate().map(|(position, block)| {
+                        vec![
+                            plan.date.to_string().into(),
+                            (position as i64).into(),
+                            block.start.to_string().into(),
+                            block.end.to_string().into(),
+                            block.title.as_str().into(),
+                            to_text(&block.kind).into(),
+                            block.todo_id.map(|id| id as i64).into(),
+                            block.routine_id.map(|id| id as i64).into(),
+                        ]
+                    })
+                }),
+            )?;
+            insert(
+                connection,
+                "INSERT INTO busy_events (uid, summary, all_day, start, end) VALUES (?, ?, ?, ?, ?)",
+                data.busy.iter().map(|event| {
+                    let (all_day, start, end) = match &event.time {
+                        BusyTime::AllDay { first, last } => (1, first.to_string(), last.to_string()),
+                        BusyTime::Timed { start, end } => (0, stamp(*start), stamp(*end)),
+                    };
+                    vec![
+                        event.uid.as_str().into(),
+                        event.summary.as_str().into(),
+                        (all_day as i64).into(),
+                        start.into(),
+                        end.into(),
+                    ]
+                }),
+            )?;
+            insert(
+                connection,
+                "INSERT INTO pomodoros (id, subject, start, end, interrupted, session_id)
+                 VALUES (?, ?, ?, ?, ?, ?)",
+                data.pomodoros.iter().map(|pomodoro| {
+                    vec![
+                        (pomodoro.id as i64).into(),
+                        pomodoro.subject.as_str().into(),
+                        stamp(pomodoro.start).into(),
+                        stamp(pomodoro.end).into(),
+                        (pomodoro.interrupted as i64).into(),
+                        (pomodoro.session_id as i64).into(),
+                    ]
+                }),
+            )?;
+            insert(
+                connection,
+                "INSERT INTO pomodoro_run (subject, work_minutes, short_break_minutes,
+                 long_break_minutes, long_break_every, phase, phase_start, completed, cycles,
+                 session_id) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+                data.pomodoro.iter().map(|run| {
+                    vec![
+                        run.subject.as_str().into(),
+                        i64::from(run.settings.work_minutes).into(),
+                        i64::from(run.settings.short_break_minutes).into(),
+                        i64::from(run.settings.long_break_minutes).into(),
+                        i64::from(run.settings.long_break_every).into(),
+                        to_text(&run.phase).into(),
+                        stamp(run.phase_start).into(),
+                        i64::from(run.completed).into(),
+                        run.cycles.map(i64::from).into(),
+                        run.session_id.map(|id| id as i64).into(),
+                    ]
+                }),
+            )?;
+            insert(
+                connection,
+                "INSERT INTO projects (id, name, created_at) VALUES (?, ?, ?)",
+                data.projects.iter().map(|project| {
+                    vec![
+                        (project.id as i64).into(),
+                        project.name.as_str().into(),
+                        stamp(project.created_at).into(),
+                    ]
+                }),
+            )?;
+            insert(
+                connection,
+                "INSERT INTO budgets (id, subject, kind, minutes, from_date, until_date, alerted)
+                 VALUES (?, ?, ?, ?, ?, ?, ?)",
+                data.budgets.iter().map(|budget| {
+                    let alerted: Vec<String> = budget
+                        .alerted
+                        .iter()
+                        .map(|(week, percent)| format!("{}@{}", week, percent))
+                        .collect();
+                    vec![
+                        (budget.id as i64).into(),
+                        budget.subject.as_str().into(),
+                        to_text(&budget.kind).into(),
+                        i64::from(budget.minutes).into(),
+                        budget.from.to_string().into(),
+                        budget.until.map(|until| until.to_string()).into(),
+                        alerted.join(",").into(),
+                    ]
+                }),
+            )?;
+            Ok(())
+        })
+    }
+}
+
+fn select(connection: &Connection, query: &str) -> Result<Vec<Row>> {
+    let statement = connection.prepare(query)?;
+    Ok(statement.into_iter().collect::<sqlite::Result<_>>()?)
+}
+
+fn insert(
+    connection: &Connection,
+    query: &str,
+    rows: impl IntoIterator<Item = Vec<Value>>,
+) -> Result<()> {
+    let mut statement = connection.prepare(query)?;
+    for values in rows {
+        statement.reset()?;
+        statement.bind(&values[..])?;
+        while statement.next()? != State::Done {}
+    }
+    Ok(())
+}
+
+/// Instants are stored as RFC 3339 text, which keeps every digit.
+fn stamp(instant: DateTime<Utc>) -> String {
+    instant.to_rfc3339()
+}
+
+fn instant(text: &str) -> Result<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(text)
+        .map(|instant| instant.with_timezone(&Utc))
+        .map_err(|_| PlannerError::Storage(format!("'{}' is not a stored time", text)))
+}
+
+fn parse<T: FromStr>(text: &str) -> Result<T> {
+    text.parse()
+        .map_err(|_| PlannerError::Storage(format!("cannot read stored value '{}'", text)))
+}
+
+/// Enums are stored under the same names as in the JSON file.
+fn to_text<T: Serialize>(value: &T) -> String {
+    match serde_json::to_value(value) {
+        Ok(serde_json::Value::String(text)) => text,
+        other => panic!("expected a unit enum, got {:?}", other),
+    }
+}
+
+fn from_text<T: DeserializeOwned>(text: &str) -> Result<T> {
+    Ok(serde_json::from_value(serde_json::Value::String(
+        text.to_string(),
+    ))?)
+}
+
+fn text<'r>(row: &'r Row, column: &str) -> Result<&'r str> {
+    Ok(row.try_read::<&str, _>(column)?)
+}
+
+fn optional_text(row: &Row, column: &str) -> Result<Option<String>> {
+    Ok(row.try_read::<Option<&str>, _>(column)?.map(str::to_string))
+}
+
+fn optional<T>(row: &Row, column: &str, read: fn(&str) -> Result<T>) -> Result<Option<T>> {
+    row.try_read::<Option<&str>, _>(column)?
+        .map(read)
+        .transpose()
+}
+
+fn id(row: &Row, column: &str) -> Result<u64> {
+    Ok(row.try_read::<i64, _>(column)? as u64)
+}
+
+fn optional_id(row: &Row, column: &str) -> Result<Option<u64>> {
+    Ok(row.try_read::<Option<i64>, _>(column)?.map(|id| id as u64))
+}
+
+fn flag(row: &Row, column: &str) -> Result<bool> {
+    Ok(row.try_read::<i64, _>(column)? != 0)
+}
@@ -0,0 +1,135 @@
+use std::cell::RefCell;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use chrono::Utc;
+
+use crate::config::Config;
+use crate::daemon;
+use crate::data::PlannerData;
+use crate::error::{PlannerError, Result};
+use crate::history::History;
+use crate::storage::Storage;
+use crate::vault;
+
+/// The folder holding the planner's files, and the storage backend chosen in
+/// its `config.json`. The data is loaded from the history, and every save is
+/// recorded there too.
+pub struct Store {
+    dir: PathBuf,
+    storage: Box<dyn Storage>,
+    history: History,
+    /// Who the history names for the changes saved here.
+    actor: String,
+    /// The data as last loaded or saved, to tell what a save changed.
+    last: RefCell<Option<PlannerData>>,
+}
+
+impl Store {
+    /// `$SMART_PLANNER_HOME`, or `~/.smart_planner` when it is not set.
+    pub fn default_dir() -> PathBuf {
+        if let Some(dir) = env::var_os("SMART_PLANNER_HOME") {
+            return PathBuf::from(dir);
+        }
+        let home = env::var_os("HOME")
+            .or_else(|| env::var_os("USERPROFILE"))
+            .unwrap_or_else(|| ".".into());
+        PathBuf::from(home).join(".smart_planner")
+    }
+
+    /// Fails while the folder is locked.
+    pub fn open(dir: &Path) -> Result<Self> {
+        fs::create_dir_all(dir)?;
+        check_unlocked(dir)?;
+        let storage = read_config(dir)?.storage.open(dir)?;
+        Ok(Store {
+            dir: dir.to_path_buf(),
+            storage,
+            history: History::new(&dir.join("history")),
+            actor: default_actor(),
+            last: RefCell::new(None),
+        })
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn storage(&self) -> &dyn Storage {
+        self.storage.as_ref()
+    }
+
+    pub fn history(&self) -> &History {
+        &self.history
+    }
+
+    pub fn actor(&self) -> &str {
+        &self.actor
+    }
+
+    pub fn set_actor(&mut self, actor: &str) {
+        self.actor = actor.to_string();
+    }
+
+    /// Loads the data by replaying the history, or empty data on the first
+    /// run. Only reads, so it never races with another program saving.
+    /// Checks the lock again, as commands that keep running may see it
+    /// locked meanwhile.
+    pub fn load(&self) -> Result<PlannerData> {
+        check_unlocked(&self.dir)?;
+        let stored = self.storage.load()?;
+        let data = self.history.load(&stored)?;
+        *self.last.borrow_mut() = Some(data.clone());
+        Ok(data)
+    }
+
+    /// Saves the data, then records what changed since it was loaded, after
+    /// any changes the history had not seen. The history is written second
+    /// so it never holds a change that failed to save. A running daemon is
+    /// told to reload.
+    pub fn save(&self, data: &PlannerData) -> Result<()> {
+        check_unlocked(&self.dir)?;
+        let before = match self.last.borrow_mut().take() {
+            Some(before) => before,
+            None => self.storage.load()?,
+        };
+        self.storage.save(data)?;
+        *self.last.borrow_mut() = Some(data.clone());
+        self.history
+            .record(&before, data, &self.actor, Utc::now())?;
+        daemon::data_changed(&self.dir);
+        Ok(())
+    }
+
+    /// Loads `config.json`, or the defaults when there is none.
+    pub fn config(&self) -> Result<Config> {
+        read_config(&self.dir)
+    }
+}
+
+/// `$SMART_PLANNER_USER`, or the login name.
+fn default_actor() -> String {
+    ["SMART_PLANNER_USER", "USER", "USERNAME"]
+        .iter()
+        .find_map(|name| env::var(name).ok().filter(|user| !user.trim().is_empty()))
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+fn read_config(dir: &Path) -> Result<Config> {
+    let path = dir.join("config.json");
+    if !path.exists() {
+        return Ok(Config::default());
+    }
+    let text = fs::read_to_string(path)?;
+    Ok(serde_json::from_str(&text)?)
+}
+
+fn check_unlocked(dir: &Path) -> Result<()> {
+    if vault::is_locked(dir)? {
+        return Err(PlannerError::Encryption(
+            "the planner is locked, run `unlock` first".to_string(),
+        ));
+    }
+    Ok(())
+}
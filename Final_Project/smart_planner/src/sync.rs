@@ -0,0 +1,416 @@
+// Sync between machines that each keep their own planner. Todos, routines
+// and stopwatch sessions are replicated through the document in `crdt`: local
+// edits become operations, operations travel as deltas (through a file or
+// `POST /sync` on a `serve`), and every machine that has seen the same
+// operations shows the same items, whatever order they arrived in.
+//
+// The rest of the planner still works on `PlannerData`. Before an exchange the
+// local edits since the last sync are found by comparing with what the last
+// sync left, and afterwards the synced lists are rebuilt from the document.
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use std::process;
+use std::time::Duration;
+
+use chrono::Utc;
+use reqwest::blocking::Client;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::data::PlannerData;
+use crate::error::{PlannerError, Result};
+
+pub mod crdt;
+
+use crdt::{Doc, Kind, Op, Stamp};
+
+/// The sync state kept next to the data in the planner folder.
+pub const STATE_FILE: &str = "sync.json";
+
+/// How far each replica's operations have been seen, by replica name. All of
+/// a replica's operations up to that clock are known.
+pub type Seen = BTreeMap<String, u64>;
+
+/// Operations sent from one replica to another.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Delta {
+    /// The replica that sent it.
+    pub from: String,
+    /// What the sender had seen, so the other side can answer with only what
+    /// it lacks.
+    pub seen: Seen,
+    pub ops: Vec<Op>,
+}
+
+/// What this machine knows about the replicated data.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SyncState {
+    /// This machine's name in operation stamps.
+    pub replica: String,
+    /// Lamport clock, the stamp of the latest operation seen or made.
+    pub clock: u64,
+    pub doc: Doc,
+    /// Every operation seen, to pass on to replicas that lack it.
+    #[serde(default)]
+    pub log: Vec<Op>,
+    #[serde(default)]
+    pub seen: Seen,
+    /// What each peer had seen when it last sent something.
+    #[serde(default)]
+    pub peers: BTreeMap<String, Seen>,
+    /// The replica answering at each URL synced with.
+    #[serde(default)]
+    pub remotes: BTreeMap<String, String>,
+    /// The local id of each synced item.
+    #[serde(default)]
+    pub ids: BTreeMap<String, u64>,
+    /// The synced items as the last sync left them, by kind and local id.
+    #[serde(default)]
+    base: BTreeMap<Kind, BTreeMap<u64, Value>>,
+}
+
+impl SyncState {
+    pub fn new(replica: &str) -> Self {
+        SyncState {
+            replica: replica.to_string(),
+            clock: 0,
+            doc: Doc::default(),
+            log: Vec::new(),
+            seen: Seen::new(),
+            peers: BTreeMap::new(),
+            remotes: BTreeMap::new(),
+            ids: BTreeMap::new(),
+            base: BTreeMap::new(),
+        }
+    }
+
+    /// Reads the state in `dir`, or starts one under a new replica name.
+    pub fn load(dir: &Path) -> Result<Self> {
+        let path = dir.join(STATE_FILE);
+        if !path.exists() {
+            return Ok(SyncState::new(&replica_name()));
+        }
+        Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+    }
+
+    pub fn save(&self, dir: &Path) -> Result<()> {
+        let path = dir.join(STATE_FILE);
+        let temp = dir.join(format!("{}.tmp", STATE_FILE));
+        fs::write(&temp, serde_json::to_string(self)?)?;
+        fs::rename(temp, path)?;
+        Ok(())
+    }
+
+    /// Turns what changed in `data` since the last sync into operations.
+    /// Returns how many were made.
+    pub fn capture(&mut self, data: &PlannerData) -> Result<usize> {
+        let state = serde_json::to_value(data)?;
+        // Every item is named before any is compared, so links between items
+        // can be written with names
+        for kind in Kind::ALL {
+            for id in items(&state, kind)?.keys() {
+                if !self.ids.values().any(|known| known == id) {
+                    self.ids.insert(format!("{}-{}", self.replica, id), *id);
+                }
+            }
+        }
+        let names: BTreeMap<u64, String> = self
+            .ids
+            .iter()
+            .map(|(name, id)| (*id, name.clone()))
+            .collect();
+
+        let mut made = 0;
+        for kind in Kind::ALL {
+            let now = self.synced_items(&state, kind)?;
+            let before = self.base.get(&kind).cloned().unwrap_or_default();
+            for (id, fields) in &now {
+                let name = names[id].clone();
+                let old = before.get(id).and_then(Value::as_object);
+                if old.is_none() {
+                    let stamp = self.tick();
+                    self.record(Op::Add {
+                        item: name.clone(),
+                        kind,
+                        stamp,
+                    });
+                    made += 1;
+                }
+                let empty = Map::new();
+                let old = old.unwrap_or(&empty);
+                let fields = fields.as_object().unwrap_or(&empty);
+                let mut keys: Vec<&String> = fields.keys().collect();
+                keys.extend(old.keys().filter(|field| !fields.contains_key(*field)));
+                for field in keys {
+                    let value = fields.get(field).cloned().unwrap_or(Value::Null);
+                    if old.get(field) != Some(&value) {
+                        let stamp = self.tick();
+                        self.record(Op::Set {
+                            item: name.clone(),
+                            kind,
+                            field: field.clone(),
+                            value,
+                            stamp,
+                        });
+                        made += 1;
+                    }
+                }
+            }
+
+            for id in before.keys().filter(|id| !now.contains_key(id)) {
+                let Some(name) = names.get(id) else { continue };
+                let Some(entry) = self.doc.entries.get(name) else {
+                    continue;
+                };
+                let tags = entry.live_tags();
+                if tags.is_empty() {
+                    continue;
+                }
+                let stamp = self.tick();
+                self.record(Op::Remove {
+                    item: name.clone(),
+                    kind,
+                    tags,
+                    stamp,
+                });
+                made += 1;
+            }
+            self.base.insert(kind, now);
+        }
+        Ok(made)
+    }
+
+    /// The operations a replica that has seen `seen` lacks.
+    pub fn delta_for(&self, seen: &Seen) -> Delta {
+        let ops = self
+            .log
+            .iter()
+            .filter(|op| {
+                let stamp = op.stamp();
+                stamp.clock > seen.get(&stamp.replica).copied().unwrap_or(0)
+            })
+            .cloned()
+            .collect();
+        Delta {
+            from: self.replica.clone(),
+            seen: self.seen.clone(),
+            ops,
+        }
+    }
+
+    /// The operations `peer` lacks, as far as this machine knows; everything
+    /// for a peer it has not heard from.
+    pub fn delta_for_peer(&self, peer: Option<&str>) -> Delta {
+        let seen = peer
+            .and_then(|peer| self.peers.get(peer))
+            .cloned()
+            .unwrap_or_default();
+        self.delta_for(&seen)
+    }
+
+    /// Takes in the operations of a delta that are new here. Returns how many
+    /// there were.
+    pub fn receive(&mut self, delta: &Delta) -> Result<usize> {
+        if delta.from == self.replica {
+            return Err(PlannerError::Invalid(
+                "these changes were sent from this planner".to_string(),
+            ));
+        }
+        let mut new = 0;
+        for op in &delta.ops {
+            let stamp = op.stamp();
+            if stamp.clock <= self.seen.get(&stamp.replica).copied().unwrap_or(0) {
+                continue;
+            }
+            self.clock = self.clock.max(stamp.clock);
+            self.record(op.clone());
+            new += 1;
+        }
+        let peer = self.peers.entry(delta.from.clone()).or_default();
+        for (replica, clock) in &delta.seen {
+            let known = peer.entry(replica.clone()).or_default();
+            *known = (*known).max(*clock);
+        }
+        Ok(new)
+    }
+
+    /// Rebuilds the synced lists of `data` from the document. Items that
+    /// arrived from other replicas get new local ids.
+    pub fn apply_to(&mut self, data: &mut PlannerData) -> Result<()> {
+        let alive: Vec<(Kind, String, Value)> = Kind::ALL
+            .into_iter()
+            .flat_map(|kind| {
+                self.doc
+                    .alive(kind)
+                    .map(move |(name, entry)| (kind, name.clone(), entry.value()))
+            })
+            .collect();
+        for (_, name, _) in &alive {
+            if !self.ids.contains_key(name) {
+                let id = data.next_id();
+                self.ids.insert(name.clone(), id);
+            }
+        }
+
+        let mut state = serde_json::to_value(&*data)?;
+        for kind in Kind::ALL {
+            let mut list = BTreeMap::new();
+            for (_, name, fields) in alive.iter().filter(|(of, _, _)| *of == kind) {
+                let id = self.ids[name];
+                let mut fields = fields.clone();
+                // A field that was left out, like an empty list of blockers,
+                // is synced as null; left out again it reads as its default
+                if let Some(fields) = fields.as_object_mut() {
+                    fields.retain(|_, value| !value.is_null());
+                }
+                fields["id"] = Value::from(id);
+                for field in links(kind) {
+                    relink(&mut fields, field, |name| {
+                        name.as_str()
+                            .and_then(|name| self.ids.get(name))
+                            .map(|id| Value::from(*id))
+                    });
+                }
+                list.insert(id, fields);
+            }
+            state[field(kind)] = Value::Array(list.into_values().collect());
+        }
+        *data = serde_json::from_value(state).map_err(|e| {
+            PlannerError::Storage(format!("the synced items are unreadable: {}", e))
+        })?;
+
+        let state = serde_json::to_value(&*data)?;
+        for kind in Kind::ALL {
+            let items = self.synced_items(&state, kind)?;
+            self.base.insert(kind, items);
+        }
+        Ok(())
+    }
+
+    /// The items of `kind` as they are synced: by local id, with links to
+    /// other items given by name.
+    fn synced_items(&self, state: &Value, kind: Kind) -> Result<BTreeMap<u64, Value>> {
+        let names: BTreeMap<u64, &String> = self.ids.iter().map(|(name, id)| (*id, name)).collect();
+        let mut items = items(state, kind)?;
+        for fields in items.values_mut() {
+            for field in links(kind) {
+                relink(fields, field, |id| {
+                    id.as_u64()
+                        .and_then(|id| names.get(&id))
+                        .map(|name| Value::from(name.as_str()))
+                });
+            }
+        }
+        Ok(items)
+    }
+
+    fn tick(&mut self) -> Stamp {
+        self.clock += 1;
+        Stamp::new(self.clock, &self.replica)
+    }
+
+    fn record(&mut self, op: Op) {
+        self.doc.apply(&op);
+        let stamp = op.stamp();
+        let seen = self.seen.entry(stamp.replica.clone()).or_default();
+        *seen = (*seen).max(stamp.clock);
+        self.log.push(op);
+    }
+}
+
+/// Sends `delta` to the `serve` of another planner at `url`, which answers
+/// with what this planner lacks.
+pub fn exchange(url: &str, token: &str, delta: &Delta) -> Result<Delta> {
+    let url = format!("{}/sync", url.trim_end_matches('/'));
+    let client = Client::builder().timeout(Duration::from_secs(30)).build()?;
+    let response = client.post(&url).bearer_auth(token).json(delta).send()?;
+    let status = response.status();
+    if !status.is_success() {
+        return Err(PlannerError::Http(format!(
+            "{} returned {}: {}",
+            url,
+            status,
+            response.text().unwrap_or_default()
+        )));
+    }
+    Ok(response.json()?)
+}
+
+/// The field of `PlannerData` holding `kind`.
+fn field(kind: Kind) -> &'static str {
+    match kind {
+        Kind::Todo => "todos",
+        Kind::Routine => "routines",
+        Kind::Session => "sessions",
+        Kind::Project => "projects",
+    }
+}
+
+/// Fields of `kind` holding the ids of other items, alone or in a list.
+fn links(kind: Kind) -> &'static [&'static str] {
+    match kind {
+        Kind::Todo => &["project", "parent", "blocked_by"],
+        _ => &[],
+    }
+}
+
+/// Maps the link in `field` through `to`, dropping links to items it does
+/// not know.
+fn relink(fields: &mut Value, field: &str, to: impl Fn(&Value) -> Option<Value>) {
+    let Some(fields) = fields.as_object_mut() else {
+        return;
+    };
+    match fields.get(field) {
+        Some(Value::Array(list)) => {
+            let list = list.iter().filter_map(&to).collect();
+            fields.insert(field.to_string(), Value::Array(list));
+        }
+        Some(Value::Null) | None => {}
+        Some(link) => match to(link) {
+            Some(link) => {
+                fields.insert(field.to_string(), link);
+            }
+            None => {
+                fields.remove(field);
+            }
+        },
+    }
+}
+
+/// The items of `kind` by local id, without the id.
+fn items(state: &Value, kind: Kind) -> Result<BTreeMap<u64, Value>> {
+    let mut items = BTreeMap::new();
+    for item in state[field(kind)].as_array().into_iter().flatten() {
+        let mut item = item.clone();
+        let id = item
+            .as_object_mut()
+            .and_then(|fields| fields.remove("id"))
+            .and_then(|id| id.as_u64())
+            .ok_or_else(|| {
+                PlannerError::Storage(format!("an item in {} has no id", field(kind)))
+            })?;
+        items.insert(id, item);
+    }
+    Ok(items)
+}
+
+/// `$HOSTNAME` (or `replica`) with a few characters that differ between
+/// runs, so two planners started on one machine still get different names.
+fn replica_name() -> String {
+    let host: String = std::env::var("HOSTNAME")
+        .unwrap_or_else(|_| "replica".to_string())
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .take(16)
+        .collect::<String>()
+        .to_lowercase();
+    let nanos = Utc::now().timestamp_nanos_opt().unwrap_or_default() as u64;
+    let salt = nanos ^ ((process::id() as u64) << 32);
+    let host = if host.is_empty() {
+        "replica".to_string()
+    } else {
+        host
+    };
+    format!("{}-{:06x}", host, salt & 0xff_ffff)
+}
@@ -0,0 +1,210 @@
+// The replicated document behind sync. Every item is an add/remove set (it
+// exists while one of its adds has not been removed) with one last-writer-wins
+// register per field. Applying the same operations in any order, any number
+// of times, gives the same document, which is what lets two machines edit
+// offline and still end up agreeing.
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+/// When and where a change was made. The clock is a Lamport clock, so a
+/// change made after seeing another always has the higher stamp; ties
+/// between machines are broken by the replica name.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct Stamp {
+    pub clock: u64,
+    pub replica: String,
+}
+
+impl Stamp {
+    pub fn new(clock: u64, replica: &str) -> Self {
+        Stamp {
+            clock,
+            replica: replica.to_string(),
+        }
+    }
+}
+
+/// The kinds of items that are synced.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Kind {
+    Todo,
+    Routine,
+    Session,
+    Project,
+}
+
+impl Kind {
+    pub const ALL: [Kind; 4] = [Kind::Todo, Kind::Routine, Kind::Session, Kind::Project];
+}
+
+/// One change to the document.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum Op {
+    /// The item exists, under the tag `stamp`.
+    Add {
+        item: String,
+        kind: Kind,
+        stamp: Stamp,
+    },
+    /// A field of the item was set.
+    Set {
+        item: String,
+        kind: Kind,
+        field: String,
+        value: Value,
+        stamp: Stamp,
+    },
+    /// The adds in `tags` are undone. Adds made without seeing this remove
+    /// survive it, so an edit on another machine is never lost to a delete.
+    Remove {
+        item: String,
+        kind: Kind,
+        tags: BTreeSet<Stamp>,
+        stamp: Stamp,
+    },
+}
+
+impl Op {
+    pub fn item(&self) -> &str {
+        match self {
+            Op::Add { item, .. } | Op::Set { item, .. } | Op::Remove { item, .. } => item,
+        }
+    }
+
+    pub fn kind(&self) -> Kind {
+        match self {
+            Op::Add { kind, .. } | Op::Set { kind, .. } | Op::Remove { kind, .. } => *kind,
+        }
+    }
+
+    pub fn stamp(&self) -> &Stamp {
+        match self {
+            Op::Add { stamp, .. } | Op::Set { stamp, .. } | Op::Remove { stamp, .. } => stamp,
+        }
+    }
+}
+
+/// A field value and the stamp of the write that set it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Register {
+    pub value: Value,
+    pub stamp: Stamp,
+}
+
+/// One item of the document.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Entry {
+    pub kind: Kind,
+    #[serde(default)]
+    pub adds: BTreeSet<Stamp>,
+    #[serde(default)]
+    pub removes: BTreeSet<Stamp>,
+    #[serde(default)]
+    pub fields: BTreeMap<String, Register>,
+}
+
+impl Entry {
+    fn new(kind: Kind) -> Self {
+        Entry {
+            kind,
+            adds: BTreeSet::new(),
+            removes: BTreeSet::new(),
+            fields: BTreeMap::new(),
+        }
+    }
+
+    pub fn is_alive(&self) -> bool {
+        self.adds.iter().any(|tag| !self.removes.contains(tag))
+    }
+
+    /// The adds a remove made now would undo.
+    pub fn live_tags(&self) -> BTreeSet<Stamp> {
+        self.adds.difference(&self.removes).cloned().collect()
+    }
+
+    /// The fields as one JSON object.
+    pub fn value(&self) -> Value {
+        let fields: Map<String, Value> = self
+            .fields
+            .iter()
+            .map(|(name, register)| (name.clone(), register.value.clone()))
+            .collect();
+        Value::Object(fields)
+    }
+}
+
+/// Every item ever synced, by item name.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Doc {
+    pub entries: BTreeMap<String, Entry>,
+}
+
+impl Doc {
+    pub fn apply(&mut self, op: &Op) {
+        let entry = self
+            .entries
+            .entry(op.item().to_string())
+            .or_insert_with(|| Entry::new(op.kind()));
+        match op {
+            Op::Add { stamp, .. } => {
+                entry.adds.insert(stamp.clone());
+            }
+            Op::Set {
+                field,
+                value,
+                stamp,
+                ..
+            } => {
+                let newer = entry
+                    .fields
+                    .get(field)
+                    .is_none_or(|register| *stamp > register.stamp);
+                if newer {
+                    entry.fields.insert(
+                        field.clone(),
+                        Register {
+                            value: value.clone(),
+                            stamp: stamp.clone(),
+                        },
+                    );
+                }
+            }
+            Op::Remove { tags, .. } => {
+                entry.removes.extend(tags.iter().cloned());
+            }
+        }
+    }
+
+    /// Joins another document into this one, as if its operations had been
+    /// applied here.
+    pub fn merge(&mut self, other: &Doc) {
+        for (item, theirs) in &other.entries {
+            let entry = self
+                .entries
+                .entry(item.clone())
+                .or_insert_with(|| Entry::new(theirs.kind));
+            entry.adds.extend(theirs.adds.iter().cloned());
+            entry.removes.extend(theirs.removes.iter().cloned());
+            for (field, register) in &theirs.fields {
+                let newer = entry
+                    .fields
+                    .get(field)
+                    .is_none_or(|mine| register.stamp > mine.stamp);
+                if newer {
+                    entry.fields.insert(field.clone(), register.clone());
+                }
+            }
+        }
+    }
+
+    /// The items of `kind` that exist, by item name.
+    pub fn alive(&self, kind: Kind) -> impl Iterator<Item = (&String, &Entry)> {
+        self.entries
+            .iter()
+            .filter(move |(_, entry)| entry.kind == kind && entry.is_alive())
+    }
+}
@@ -0,0 +1,122 @@
+// Instants are stored in UTC. Everything the user sees (plan days, routine
+// times, due dates typed on the command line) is local wall-clock time.
+// These helpers are the only place that converts between the two, using the
+// zone rules of the system tzdata files.
+use std::sync::{Arc, RwLock};
+
+use chrono::{DateTime, NaiveDate, NaiveDateTime, NaiveTime, Utc};
+
+use crate::error::{PlannerError, Result};
+
+pub mod zone;
+
+use zone::{LocalTime, Zone};
+
+/// The zone local times are in, read from the system on first use.
+static ZONE: RwLock<Option<Arc<Zone>>> = RwLock::new(None);
+
+/// The zone local times are in: `TZ`, otherwise the system zone.
+pub fn zone() -> Arc<Zone> {
+    if let Some(zone) = ZONE.read().unwrap().as_ref() {
+        return zone.clone();
+    }
+    ZONE.write()
+        .unwrap()
+        .get_or_insert_with(|| Arc::new(Zone::system()))
+        .clone()
+}
+
+/// Uses `zone` for local times from now on, for the whole process.
+pub fn set_zone(zone: Zone) {
+    *ZONE.write().unwrap() = Some(Arc::new(zone));
+}
+
+/// Converts a stored instant to local wall-clock time.
+pub fn to_local(instant: DateTime<Utc>) -> NaiveDateTime {
+    zone().to_local(instant)
+}
+
+/// Converts local wall-clock time to a stored instant. A time that happened
+/// twice is the first of the two, and a time skipped by a clock change is as
+/// long after the change as it was after the old reading of the clocks.
+pub fn from_local(local: NaiveDateTime) -> DateTime<Utc> {
+    zone().resolve(local).instant()
+}
+
+/// Converts local wall-clock time the user typed to a stored instant,
+/// refusing a time that never happened or happened twice.
+pub fn exact_local(local: NaiveDateTime) -> Result<DateTime<Utc>> {
+    let zone = zone();
+    let shown = local.format("%Y-%m-%d %H:%M");
+    match zone.resolve(local) {
+        LocalTime::Single(instant) => Ok(instant),
+        LocalTime::Ambiguous { earlier, later } => Err(PlannerError::Invalid(format!(
+            "{} happens twice in {}, add the offset you mean, like '{} {}' or '{} {}'",
+            shown,
+            zone,
+            shown,
+            zone.offset(earlier),
+            shown,
+            zone.offset(later)
+        ))),
+        LocalTime::Skipped {
+            transition,
+            before,
+            after,
+            ..
+        } => Err(PlannerError::Invalid(format!(
+            "{} does not exist in {}, the clocks go from {} to {} that night",
+            shown,
+            zone,
+            (transition + before).format("%H:%M"),
+            (transition + after).format("%H:%M")
+        ))),
+    }
+}
+
+/// Today's local date.
+pub fn today() -> NaiveDate {
+    to_local(Utc::now()).date()
+}
+
+/// Parses `HH:MM`.
+pub fn parse_time(text: &str) -> Result<NaiveTime> {
+    NaiveTime::parse_from_str(text.trim(), "%H:%M")
+        .map_err(|_| PlannerError::Invalid(format!("'{}' is not a time like 09:30", text)))
+}
+
+/// Parses `YYYY-MM-DD`.
+pub fn parse_date(text: &str) -> Result<NaiveDate> {
+    NaiveDate::parse_from_str(text.trim(), "%Y-%m-%d")
+        .map_err(|_| PlannerError::Invalid(format!("'{}' is not a date like 2024-01-31", text)))
+}
+
+/// Parses a local `YYYY-MM-DD HH:MM` (or just a date, meaning end of that day).
+/// An offset like `+01:00` may follow the time to pick one of a time that
+/// happens twice.
+pub fn parse_local_datetime(text: &str) -> Result<DateTime<Utc>> {
+    let text = text.trim();
+    for format in [
+        "%Y-%m-%d %H:%M %:z",
+        "%Y-%m-%d %H:%M%:z",
+        "%Y-%m-%dT%H:%M%:z",
+    ] {
+        if let Ok(instant) = DateTime::parse_from_str(text, format) {
+            return Ok(instant.with_timezone(&Utc));
+        }
+    }
+    for format in ["%Y-%m-%d %H:%M", "%Y-%m-%dT%H:%M"] {
+        if let Ok(local) = NaiveDateTime::parse_from_str(text, format) {
+            return exact_local(local);
+        }
+    }
+    let date = parse_date(text).map_err(|_| {
+        PlannerError::Invalid(format!("'{}' is not a date like 2024-01-31 17:00", text))
+    })?;
+    Ok(from_local(date.and_hms_opt(23, 59, 0).unwrap()))
+}
+
+/// Formats a stored instant as local `YYYY-MM-DD HH:MM`.
+pub fn format_local(instant: DateTime<Utc>) -> String {
+    to_local(instant).format("%Y-%m-%d %H:%M").to_string()
+}
@@ -0,0 +1,573 @@
+// Time zone rules read from the system tzdata files (TZif, RFC 8536), so
+// local times follow daylight saving changes without a zone database of our
+// own. A file lists the instants the offset changed and ends with a POSIX TZ
+// rule for the years after its last entry; slim files rely on the rule alone
+// for the present.
+use std::env;
+use std::fmt;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+use chrono::{DateTime, Datelike, FixedOffset, NaiveDate, NaiveDateTime, Utc};
+
+use crate::error::{PlannerError, Result};
+
+/// Where tzdata lives unless `TZDIR` says otherwise.
+const DEFAULT_DIR: &str = "/usr/share/zoneinfo";
+/// The system zone, usually a link into the tzdata folder.
+const LOCALTIME: &str = "/etc/localtime";
+const DAY: i64 = 24 * 60 * 60;
+
+/// The offset rules of one named zone, like `Europe/Berlin`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Zone {
+    name: String,
+    /// UTC seconds at which the offset changes, with the offset from then on.
+    transitions: Vec<(i64, i32)>,
+    /// Offset before the first transition.
+    initial: i32,
+    /// Offsets after the last transition.
+    rule: Option<Rule>,
+}
+
+/// What a local wall-clock time is in a zone.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LocalTime {
+    Single(DateTime<Utc>),
+    /// The clocks were turned back, so the time happened twice.
+    Ambiguous {
+        earlier: DateTime<Utc>,
+        later: DateTime<Utc>,
+    },
+    /// The clocks were turned forward past the time. `instant` reads it with
+    /// the offset from before the change, which lands as far after the change
+    /// as the time was after the old reading of the clocks.
+    Skipped {
+        instant: DateTime<Utc>,
+        transition: DateTime<Utc>,
+        before: FixedOffset,
+        after: FixedOffset,
+    },
+}
+
+impl LocalTime {
+    /// The instant the planner uses for the time: the first of two, and for
+    /// a skipped time the one as long after the change (RFC 5545 does both).
+    pub fn instant(&self) -> DateTime<Utc> {
+        match *self {
+            LocalTime::Single(instant) => instant,
+            LocalTime::Ambiguous { earlier, .. } => earlier,
+            LocalTime::Skipped { instant, .. } => instant,
+        }
+    }
+}
+
+impl Zone {
+    pub fn utc() -> Self {
+        Zone {
+            name: "UTC".to_string(),
+            transitions: Vec::new(),
+            initial: 0,
+            rule: None,
+        }
+    }
+
+    /// Reads the zone called `name` from the tzdata folder.
+    pub fn named(name: &str) -> Result<Self> {
+        let relative = Path::new(name);
+        let plain = relative
+            .components()
+            .all(|part| matches!(part, Component::Normal(_)));
+        if name.is_empty() || !plain {
+            return Err(PlannerError::Invalid(format!(
+                "'{}' is not a time zone name like Europe/Berlin",
+                name
+            )));
+        }
+        let dir = env::var_os("TZDIR")
+            .map(PathBuf::from)
+            .unwrap_or_else(|| PathBuf::from(DEFAULT_DIR));
+        let bytes = fs::read(dir.join(relative)).map_err(|_| {
+            PlannerError::Invalid(format!(
+                "'{}' is not a time zone known to this system",
+                name
+            ))
+        })?;
+        Zone::from_tzif(name, &bytes)
+    }
+
+    /// The zone the system runs in: `TZ` when set, otherwise `/etc/localtime`,
+    /// otherwise UTC.
+    pub fn system() -> Self {
+        if let Ok(tz) = env::var("TZ") {
+            let tz = tz.strip_prefix(':').unwrap_or(&tz);
+            if tz.starts_with('/') {
+                if let Some(zone) = fs::read(tz)
+                    .ok()
+                    .and_then(|bytes| Zone::from_tzif(tz, &bytes).ok())
+                {
+                    return zone;
+                }
+            } else if let Ok(zone) = Zone::named(tz) {
+                return zone;
+            } else if let Some(rule) = Rule::parse(tz) {
+                return Zone {
+                    name: tz.to_string(),
+                    transitions: Vec::new(),
+                    initial: rule.standard,
+                    rule: Some(rule),
+                };
+            }
+        }
+        // The link target names the zone, the file holds it either way
+        let name = fs::read_link(LOCALTIME)
+            .ok()
+            .and_then(|target| {
+                let target = target.to_string_lossy().into_owned();
+                target
+                    .split_once("zoneinfo/")
+                    .map(|(_, name)| name.to_string())
+            })
+            .unwrap_or_else(|| "localtime".to_string());
+        fs::read(LOCALTIME)
+            .ok()
+            .and_then(|bytes| Zone::from_tzif(&name, &bytes).ok())
+            .unwrap_or_else(Zone::utc)
+    }
+
+    /// Reads the contents of a TZif file.
+    pub fn from_tzif(name: &str, bytes: &[u8]) -> Result<Self> {
+        let invalid = || PlannerError::Invalid(format!("{} is not a TZif time zone file", name));
+        let header = Header::read(bytes, 0).ok_or_else(invalid)?;
+        let (header, start, wide) = if header.version >= b'2' {
+            let second = header.v1_len() + 44;
+            (
+                Header::read(bytes, second).ok_or_else(invalid)?,
+                second + 44,
+                true,
+            )
+        } else {
+            (header, 44, false)
+        };
+        let mut reader = Reader { bytes, at: start };
+        let size = if wide { 8 } else { 4 };
+        let mut times = Vec::with_capacity(header.times);
+        for _ in 0..header.times {
+            times.push(reader.signed(size).ok_or_else(invalid)?);
+        }
+        let mut indices = Vec::with_capacity(header.times);
+        for _ in 0..header.times {
+            indices.push(reader.take(1).ok_or_else(invalid)?[0] as usize);
+        }
+        let mut offsets = Vec::with_capacity(header.types);
+        for _ in 0..header.types {
+            offsets.push(reader.signed(4).ok_or_else(invalid)? as i32);
+            reader.take(2).ok_or_else(invalid)?;
+        }
+        let leap_size = if wide { 12 } else { 8 };
+        reader
+            .take(header.chars + header.leaps * leap_size + header.std_flags + header.ut_flags)
+            .ok_or_else(invalid)?;
+
+        let mut transitions = Vec::with_capacity(header.times);
+        for (at, index) in times.into_iter().zip(indices) {
+            transitions.push((at, *offsets.get(index).ok_or_else(invalid)?));
+        }
+        let initial = *offsets.first().ok_or_else(invalid)?;
+        let rule = if wide {
+            let footer = String::from_utf8_lossy(&bytes[reader.at.min(bytes.len())..]);
+            Rule::parse(footer.trim_matches('\n'))
+        } else {
+            None
+        };
+        Ok(Zone {
+            name: name.to_string(),
+            transitions,
+            initial,
+            rule,
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The offset from UTC in force at `instant`.
+    pub fn offset(&self, instant: DateTime<Utc>) -> FixedOffset {
+        FixedOffset::east_opt(self.offset_at(instant.timestamp())).expect("offsets are in range")
+    }
+
+    /// Seconds east of UTC at `at` seconds since the epoch.
+    fn offset_at(&self, at: i64) -> i32 {
+        let passed = self.transitions.partition_point(|(when, _)| *when <= at);
+        match (passed, &self.rule) {
+            (n, Some(rule)) if n == self.transitions.len() => rule.offset_at(at),
+            (0, _) => self.initial,
+            (n, _) => self.transitions[n - 1].1,
+        }
+    }
+
+    /// Wall-clock time in the zone at `instant`.
+    pub fn to_local(&self, instant: DateTime<Utc>) -> NaiveDateTime {
+        instant.naive_utc() + chrono::Duration::seconds(self.offset_at(instant.timestamp()) as i64)
+    }
+
+    /// Finds when `local` happens in the zone.
+    pub fn resolve(&self, local: NaiveDateTime) -> LocalTime {
+        let wall = local.and_utc().timestamp();
+        // An offset that fits is in force within a day of the wall time
+        let mut offsets = vec![
+            self.offset_at(wall - DAY),
+            self.offset_at(wall),
+            self.offset_at(wall + DAY),
+        ];
+        offsets.sort_unstable();
+        offsets.dedup();
+        let fits: Vec<i64> = offsets
+            .iter()
+            .map(|offset| wall - *offset as i64)
+            .filter(|at| self.offset_at(*at) as i64 == wall - at)
+            .collect();
+        let utc = |at: i64| DateTime::from_timestamp(at, 0).expect("in range");
+        match fits.as_slice() {
+            [only] => LocalTime::Single(utc(*only)),
+            [earlier, .., later] => LocalTime::Ambiguous {
+                earlier: utc(*later.min(earlier)),
+                later: utc(*later.max(earlier)),
+            },
+            [] => {
+                // Look for the change between reading the time with the
+                // largest and with the smallest offset
+                let mut low = wall - *offsets.last().unwrap() as i64;
+                let mut high = wall - offsets[0] as i64;
+                let first = self.offset_at(low);
+                while high - low > 1 {
+                    let middle = low + (high - low) / 2;
+                    if self.offset_at(middle) == first {
+                        low = middle;
+                    } else {
+                        high = middle;
+                    }
+                }
+                let before = self.offset_at(high - 1);
+                let after = self.offset_at(high);
+                LocalTime::Skipped {
+                    instant: utc(wall - before as i64),
+                    transition: utc(high),
+                    before: FixedOffset::east_opt(before).expect("offsets are in range"),
+                    after: FixedOffset::east_opt(after).expect("offsets are in range"),
+                }
+            }
+        }
+    }
+}
+
+impl fmt::Display for Zone {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name)
+    }
+}
+
+/// The counts in a TZif header.
+struct Header {
+    version: u8,
+    ut_flags: usize,
+    std_flags: usize,
+    leaps: usize,
+    times: usize,
+    types: usize,
+    chars: usize,
+}
+
+impl Header {
+    fn read(bytes: &[u8], at: usize) -> Option<Self> {
+        let header = bytes.get(at..at + 44)?;
+        if &header[..4] != b"TZif" {
+            return None;
+        }
+        let count = |index: usize| {
+            let start = 20 + index * 4;
+            u32::from_be_bytes(header[start..start + 4].try_into().unwrap()) as usize
+        };
+        Some(Header {
+            version: header[4],
+            ut_flags: count(0),
+            std_flags: count(1),
+            leaps: count(2),
+            times: count(3),
+            types: count(4),
+            chars: count(5),
+        })
+    }
+
+    /// Length of the version 1 data, which later versions repeat with 64-bit
+    /// times.
+    fn v1_len(&self) -> usize {
+        self.times * 5
+            + self.types * 6
+            + self.chars
+            + self.leaps * 8
+            + self.std_flags
+            + self.ut_flags
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    at: usize,
+}
+
+impl Reader<'_> {
+    fn take(&mut self, count: usize) -> Option<&[u8]> {
+        let taken = self.bytes.get(self.at..self.at.checked_add(count)?)?;
+        self.at += count;
+        Some(taken)
+    }
+
+    /// A big-endian signed number of 4 or 8 bytes.
+    fn signed(&mut self, size: usize) -> Option<i64> {
+        let bytes = self.take(size)?;
+        Some(match size {
+            4 => i32::from_be_bytes(bytes.try_into().ok()?) as i64,
+            _ => i64::from_be_bytes(bytes.try_into().ok()?),
+        })
+    }
+}
+
+/// A POSIX TZ rule like `CET-1CEST,M3.5.0,M10.5.0/3`.
+#[derive(Debug, Clone, PartialEq)]
+struct Rule {
+    /// Seconds east of UTC.
+    standard: i32,
+    dst: Option<Dst>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Dst {
+    offset: i32,
+    /// Day and local standard time daylight saving starts.
+    start: (Day, i32),
+    /// Day and local daylight time it ends.
+    end: (Day, i32),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Day {
+    /// `Jn`: day 1 to 365, never counting February 29.
+    Julian(u16),
+    /// `n`: day 0 to 365, counting February 29.
+    Ordinal(u16),
+    /// `Mm.w.d`: weekday `d` (0 is Sunday) of week `w` of month `m`, week 5
+    /// being the last.
+    Month { month: u32, week: u32, weekday: u32 },
+}
+
+impl Day {
+    fn date(self, year: i32) -> Option<NaiveDate> {
+        let new_year = NaiveDate::from_ymd_opt(year, 1, 1)?;
+        match self {
+            Day::Julian(day) => {
+                let leap = NaiveDate::from_ymd_opt(year, 2, 29).is_some();
+                let skip = if leap && day >= 60 { 1 } else { 0 };
+                new_year.checked_add_days(chrono::Days::new((day - 1 + skip) as u64))
+            }
+            Day::Ordinal(day) => new_year.checked_add_days(chrono::Days::new(day as u64)),
+            Day::Month {
+                month,
+                week,
+                weekday,
+            } => {
+                let first = NaiveDate::from_ymd_opt(year, month, 1)?;
+                let shift = (weekday + 7 - first.weekday().num_days_from_sunday()) % 7;
+                let mut day = 1 + shift + (week - 1) * 7;
+                while NaiveDate::from_ymd_opt(year, month, day).is_none() {
+                    day -= 7;
+                }
+                NaiveDate::from_ymd_opt(year, month, day)
+            }
+        }
+    }
+}
+
+impl Rule {
+    fn parse(text: &str) -> Option<Self> {
+        let mut parser = Parser { text, at: 0 };
+        parser.name()?;
+        let standard = -parser.offset()?;
+        if parser.done() {
+            return Some(Rule {
+                standard,
+                dst: None,
+            });
+        }
+        parser.name()?;
+        let offset = if parser.done() || parser.peek() == Some(',') {
+            standard + 3600
+        } else {
+            -parser.offset()?
+        };
+        // The US rules are the default when none are given
+        let (start, end) = if parser.done() {
+            let at = 2 * 3600;
+            (
+                (
+                    Day::Month {
+                        month: 3,
+                        week: 2,
+                        weekday: 0,
+                    },
+                    at,
+                ),
+                (
+                    Day::Month {
+                        month: 11,
+                        week: 1,
+                        weekday: 0,
+                    },
+                    at,
+                ),
+            )
+        } else {
+            parser.expect(',')?;
+            let start = parser.change()?;
+            parser.expect(',')?;
+            (start, parser.change()?)
+        };
+        parser.done().then_some(Rule {
+            standard,
+            dst: Some(Dst { offset, start, end }),
+        })
+    }
+
+    fn offset_at(&self, at: i64) -> i32 {
+        let Some(dst) = &self.dst else {
+            return self.standard;
+        };
+        let Some(year) = DateTime::from_timestamp(at + self.standard as i64, 0).map(|t| t.year())
+        else {
+            return self.standard;
+        };
+        let instant = |(day, time): (Day, i32), offset: i32| {
+            day.date(year)
+                .map(|date| date.and_hms_opt(0, 0, 0).unwrap().and_utc().timestamp())
+                .map(|midnight| midnight + time as i64 - offset as i64)
+        };
+        let (Some(start), Some(end)) = (
+            instant(dst.start, self.standard),
+            instant(dst.end, dst.offset),
+        ) else {
+            return self.standard;
+        };
+        let summer = if start < end {
+            start <= at && at < end
+        } else {
+            // Southern hemisphere: daylight saving spans the new year
+            !(end <= at && at < start)
+        };
+        if summer {
+            dst.offset
+        } else {
+            self.standard
+        }
+    }
+}
+
+struct Parser<'a> {
+    text: &'a str,
+    at: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<char> {
+        self.text[self.at..].chars().next()
+    }
+
+    fn done(&self) -> bool {
+        self.at == self.text.len()
+    }
+
+    fn expect(&mut self, wanted: char) -> Option<()> {
+        (self.peek()? == wanted).then(|| self.at += wanted.len_utf8())
+    }
+
+    /// Skips a zone abbreviation, `CET` or quoted like `<+03>`.
+    fn name(&mut self) -> Option<()> {
+        let rest = &self.text[self.at..];
+        let length = if let Some(quoted) = rest.strip_prefix('<') {
+            quoted.find('>')? + 2
+        } else {
+            rest.find(|c: char| !c.is_ascii_alphabetic())
+                .unwrap_or(rest.len())
+        };
+        (length >= 3).then(|| self.at += length)
+    }
+
+    fn number(&mut self) -> Option<i32> {
+        let rest = &self.text[self.at..];
+        let length = rest
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        let number = rest[..length].parse().ok()?;
+        self.at += length;
+        Some(number)
+    }
+
+    /// `[+-]hh[:mm[:ss]]` in seconds, west of UTC being positive as POSIX has
+    /// it. Also reads the times of day of a change, which may go past 24.
+    fn offset(&mut self) -> Option<i32> {
+        let sign = match self.peek()? {
+            '-' => {
+                self.at += 1;
+                -1
+            }
+            '+' => {
+                self.at += 1;
+                1
+            }
+            _ => 1,
+        };
+        let mut seconds = self.number()? * 3600;
+        for scale in [60, 1] {
+            if self.peek() != Some(':') {
+                break;
+            }
+            self.at += 1;
+            seconds += self.number()? * scale;
+        }
+        Some(sign * seconds)
+    }
+
+    /// A day of change with its optional time, two in the morning by default.
+    fn change(&mut self) -> Option<(Day, i32)> {
+        let day = match self.peek()? {
+            'J' => {
+                self.at += 1;
+                Day::Julian(self.number()?.clamp(1, 365) as u16)
+            }
+            'M' => {
+                self.at += 1;
+                let month = self.number()? as u32;
+                self.expect('.')?;
+                let week = self.number()? as u32;
+                self.expect('.')?;
+                let weekday = self.number()? as u32;
+                if !(1..=12).contains(&month) || !(1..=5).contains(&week) || weekday > 6 {
+                    return None;
+                }
+                Day::Month {
+                    month,
+                    week,
+                    weekday,
+                }
+            }
+            _ => Day::Ordinal(self.number()?.clamp(0, 365) as u16),
+        };
+        let time = if self.peek() == Some('/') {
+            self.at += 1;
+            self.offset()?
+        } else {
+            2 * 3600
+        };
+        Some((day, time))
+    }
+}
@@ -0,0 +1,58 @@
+use chrono::{DateTime, NaiveTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::time::zone::Zone;
+
+/// A countdown that notifies the user when it runs out.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Timer {
+    pub id: u64,
+    pub label: String,
+    pub due: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+    /// The todo the timer is a deadline for, if any.
+    #[serde(default)]
+    pub todo_id: Option<u64>,
+    /// Set once the user has been notified.
+    #[serde(default)]
+    pub fired: bool,
+    /// The time zone `due` was worked out in.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub zone: Option<String>,
+    /// Local time a timer that goes off every day is set for. It is set again
+    /// for the next day each time it goes off, in the zone of that moment.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub daily: Option<NaiveTime>,
+}
+
+impl Timer {
+    /// Whether the timer has run out but nobody was told yet.
+    pub fn is_due(&self, now: DateTime<Utc>) -> bool {
+        !self.fired && self.due <= now
+    }
+
+    /// Time left until the timer runs out, zero once it has.
+    pub fn remaining(&self, now: DateTime<Utc>) -> chrono::Duration {
+        (self.due - now).max(chrono::Duration::zero())
+    }
+
+    /// When a daily timer goes off next after `after`, reading its time in
+    /// `zone`. `None` for a timer that goes off once.
+    pub fn next_due(&self, after: DateTime<Utc>, zone: &Zone) -> Option<DateTime<Utc>> {
+        self.daily.map(|at| next_daily(at, after, zone))
+    }
+}
+
+/// The first instant after `after` that is `at` on the clocks of `zone`. On a
+/// day the clocks skip `at` it is as long after the change as `at` was after
+/// the old reading, on a day `at` happens twice it is the first of the two.
+pub fn next_daily(at: NaiveTime, after: DateTime<Utc>, zone: &Zone) -> DateTime<Utc> {
+    let mut date = zone.to_local(after).date();
+    loop {
+        let due = zone.resolve(date.and_time(at)).instant();
+        if due > after {
+            return due;
+        }
+        date = date.succ_opt().expect("dates run out far in the future");
+    }
+}
@@ -0,0 +1,137 @@
+use std::fmt;
+use std::str::FromStr;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::error::PlannerError;
+
+pub mod markdown;
+pub mod quick;
+pub mod todotxt;
+
+/// How important a todo is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Priority {
+    Low,
+    Medium,
+    High,
+}
+
+impl fmt::Display for Priority {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Priority::Low => "low",
+            Priority::Medium => "medium",
+            Priority::High => "high",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for Priority {
+    type Err = PlannerError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        match text.to_lowercase().as_str() {
+            "low" | "l" => Ok(Priority::Low),
+            "medium" | "med" | "m" => Ok(Priority::Medium),
+            "high" | "h" => Ok(Priority::High),
+            _ => Err(PlannerError::Invalid(format!(
+                "'{}' is not a priority (low, medium, high)",
+                text
+            ))),
+        }
+    }
+}
+
+/// A single entry of the todo list.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Todo {
+    pub id: u64,
+    pub title: String,
+    pub priority: Priority,
+    #[serde(default)]
+    pub done: bool,
+    #[serde(default)]
+    pub due: Option<DateTime<Utc>>,
+    /// How long the user expects the task to take, in minutes.
+    #[serde(default)]
+    pub estimate_minutes: Option<u32>,
+    /// What the task is about, matches stopwatch subjects.
+    #[serde(default)]
+    pub subject: Option<String>,
+    pub created_at: DateTime<Utc>,
+    #[serde(default)]
+    pub completed_at: Option<DateTime<Utc>>,
+    /// The project the todo belongs to.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub project: Option<u64>,
+    /// The todo this one is a subtask of.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent: Option<u64>,
+    /// Todos that have to be done before this one can start.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub blocked_by: Vec<u64>,
+}
+
+impl Todo {
+    pub fn new(id: u64, title: &str, priority: Priority, created_at: DateTime<Utc>) -> Self {
+        Todo {
+            id,
+            title: title.to_string(),
+            priority,
+            done: false,
+            due: None,
+            estimate_minutes: None,
+            subject: None,
+            created_at,
+            completed_at: None,
+            project: None,
+            parent: None,
+            blocked_by: Vec::new(),
+        }
+    }
+
+    /// Marks the todo as done at the given time.
+    pub fn complete(&mut self, at: DateTime<Utc>) {
+        self.done = true;
+        self.completed_at = Some(at);
+    }
+}
+
+/// What reading todos from a file changed in the planner.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Merged {
+    pub added: usize,
+    pub updated: usize,
+    pub unchanged: usize,
+    /// Projects created for tags no project had.
+    pub projects: usize,
+}
+
+impl fmt::Display for Merged {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "added {}, updated {}, {} unchanged",
+            self.added, self.updated, self.unchanged
+        )?;
+        if self.projects > 0 {
+            write!(f, ", {} new project(s)", self.projects)?;
+        }
+        Ok(())
+    }
+}
+
+/// Whether two titles name the same todo, ignoring case and spacing.
+pub fn same_title(a: &str, b: &str) -> bool {
+    let words = |title: &str| {
+        title
+            .split_whitespace()
+            .map(str::to_lowercase)
+            .collect::<Vec<_>>()
+    };
+    words(a) == words(b)
+}
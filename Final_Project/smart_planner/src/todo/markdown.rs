@@ -0,0 +1,302 @@
+// Markdown checklists, as kept in READMEs and notes:
+//
+//     - [ ] Launch site
+//       - [x] Design
+//       - [ ] Build
+//
+// Items nested under another are its subtasks. `sync` matches items to todos
+// by title and edits the file in place: it only ever ticks a box or inserts
+// an item line, so the text around the checklist stays as it was.
+use chrono::{DateTime, Utc};
+
+use super::{same_title, Merged, Priority, Todo};
+use crate::data::{NewTodo, PlannerData};
+use crate::error::Result;
+
+/// One checklist item of a Markdown file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Item {
+    /// Line number, counting from 0.
+    pub line: usize,
+    pub indent: usize,
+    pub checked: bool,
+    pub title: String,
+}
+
+/// The result of `sync`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Synced {
+    /// What changed in the planner.
+    pub merged: Merged,
+    /// Items added to the file or ticked in it.
+    pub written: usize,
+    /// The file with those changes.
+    pub text: String,
+}
+
+/// The checklist items of a Markdown text, in order.
+pub fn parse(text: &str) -> Vec<Item> {
+    text.lines()
+        .enumerate()
+        .filter_map(|(line, text)| {
+            let (indent, _, checked, title) = item(text)?;
+            Some(Item {
+                line,
+                indent,
+                checked,
+                title: title.to_string(),
+            })
+        })
+        .collect()
+}
+
+/// Splits `  - [x] Title` into its indent, bullet, box and title.
+fn item(line: &str) -> Option<(usize, &str, bool, &str)> {
+    let rest = line.trim_start();
+    let indent = line.len() - rest.len();
+    let bullet_end = match rest.chars().next()? {
+        '-' | '*' | '+' => 1,
+        c if c.is_ascii_digit() => {
+            let digits = rest.find(|c: char| !c.is_ascii_digit())?;
+            rest[digits..]
+                .starts_with(['.', ')'])
+                .then_some(digits + 1)?
+        }
+        _ => return None,
+    };
+    let (bullet, rest) = rest.split_at(bullet_end);
+    let rest = rest.strip_prefix(' ')?.trim_start();
+    let checked = match rest.get(..3)? {
+        "[ ]" => false,
+        "[x]" | "[X]" => true,
+        _ => return None,
+    };
+    let title = rest[3..].trim();
+    if !rest[3..].starts_with(' ') || title.is_empty() {
+        return None;
+    }
+    Some((indent, bullet, checked, title))
+}
+
+/// The todos, or only those of `project`, as a checklist with subtasks
+/// nested under their parents.
+pub fn export(data: &PlannerData, project: Option<u64>) -> String {
+    let todos = scope(data, project);
+    let mut out = String::new();
+    for todo in &todos {
+        if !todo
+            .parent
+            .is_some_and(|parent| todos.iter().any(|t| t.id == parent))
+        {
+            write_tree(&todos, todo, 0, &mut out);
+        }
+    }
+    out
+}
+
+fn write_tree(todos: &[&Todo], todo: &Todo, depth: usize, out: &mut String) {
+    out.push_str(&format!("{}{}\n", "  ".repeat(depth), line(todo)));
+    for subtask in todos.iter().filter(|t| t.parent == Some(todo.id)) {
+        write_tree(todos, subtask, depth + 1, out);
+    }
+}
+
+fn line(todo: &Todo) -> String {
+    format!("- [{}] {}", if todo.done { "x" } else { " " }, todo.title)
+}
+
+fn scope(data: &PlannerData, project: Option<u64>) -> Vec<&Todo> {
+    data.todos
+        .iter()
+        .filter(|todo| project.is_none() || todo.project == project)
+        .collect()
+}
+
+/// Brings a Markdown checklist and the planner up to date with each other.
+///
+/// - An item without a todo of the same title becomes a new todo, in
+///   `project` if given, and a subtask of the item it is nested under.
+/// - A todo done on either side ends up done on both; a ticked parent
+///   completes its subtasks too.
+/// - An open todo missing from the file is inserted, under its parent when
+///   the parent is there, otherwise after the last item.
+pub fn sync(
+    data: &mut PlannerData,
+    text: &str,
+    project: Option<u64>,
+    now: DateTime<Utc>,
+) -> Result<Synced> {
+    if let Some(project) = project {
+        data.project(project)?;
+    }
+    let newline = if text.contains("\r\n") { "\r\n" } else { "\n" };
+    let mut lines: Vec<String> = text.lines().map(str::to_string).collect();
+    let mut merged = Merged::default();
+
+    // Each item with its todo, whose parent is the item it is nested under
+    let mut linked: Vec<(Item, u64)> = Vec::new();
+    let mut added = Vec::new();
+    let mut open: Vec<(usize, u64)> = Vec::new();
+    for item in parse(text) {
+        while open
+            .last()
+            .is_some_and(|(indent, _)| *indent >= item.indent)
+        {
+            open.pop();
+        }
+        let parent = open.last().map(|(_, id)| *id);
+        let existing = scope(data, project)
+            .into_iter()
+            .find(|todo| {
+                same_title(&todo.title, &item.title) && !linked.iter().any(|(_, id)| *id == todo.id)
+            })
+            .map(|todo| todo.id);
+        let id = match existing {
+            Some(id) => id,
+            None => {
+                let mut new = NewTodo::new(&item.title, Priority::Medium);
+                new.parent = parent;
+                new.project = if parent.is_some() { None } else { project };
+                let id = data.add_todo(new, now)?;
+                added.push(id);
+                id
+            }
+        };
+        open.push((item.indent, id));
+        linked.push((item, id));
+    }
+
+    let before: Vec<Todo> = data.todos.clone();
+    for (item, id) in &linked {
+        if item.checked && !data.todo(*id)?.done {
+            data.complete_todo_tree(*id, now)?;
+        }
+    }
+    merged.added = added.len();
+    for (_, id) in &linked {
+        if added.contains(id) {
+            continue;
+        }
+        if before.iter().find(|todo| todo.id == *id) != Some(data.todo(*id)?) {
+            merged.updated += 1;
+        } else {
+            merged.unchanged += 1;
+        }
+    }
+
+    // Done in the planner, still open in the file
+    let mut written = 0;
+    for (item, id) in &linked {
+        if !item.checked && data.todo(*id)?.done {
+            let line = &mut lines[item.line];
+            if let Some(at) = line.find("[ ]") {
+                line.replace_range(at..at + 3, "[x]");
+                written += 1;
+            }
+        }
+    }
+
+    // Open todos the file does not have yet, parents before their subtasks
+    let missing: Vec<u64> = tree_order(data, project)
+        .into_iter()
+        .filter(|id| !linked.iter().any(|(_, linked)| linked == id))
+        .filter(|id| data.todo(*id).is_ok_and(|todo| !todo.done))
+        .collect();
+    let bullet = linked
+        .first()
+        .and_then(|(item, _)| item_bullet(&lines[item.line]))
+        .unwrap_or("-");
+    let top = linked
+        .iter()
+        .map(|(item, _)| item.indent)
+        .min()
+        .unwrap_or(0);
+    for id in missing {
+        let todo = data.todo(id)?;
+        let parent = todo
+            .parent
+            .and_then(|parent| linked.iter().find(|(_, linked)| *linked == parent))
+            .map(|(item, _)| item.clone());
+        let (at, indent) = match parent {
+            Some(parent) => {
+                // After the parent and everything nested under it
+                let mut at = parent.line + 1;
+                while at < lines.len()
+                    && item(&lines[at]).is_some_and(|(indent, ..)| indent > parent.indent)
+                {
+                    at += 1;
+                }
+                (at, parent.indent + 2)
+            }
+            None => match linked.iter().map(|(item, _)| item.line).max() {
+                Some(last) => (last + 1, top),
+                None => {
+                    if lines.last().is_some_and(|line| !line.trim().is_empty()) {
+                        lines.push(String::new());
+                    }
+                    (lines.len(), top)
+                }
+            },
+        };
+        let text = format!("{}{} [ ] {}", " ".repeat(indent), bullet, todo.title);
+        lines.insert(at, text);
+        for (item, _) in &mut linked {
+            if item.line >= at {
+                item.line += 1;
+            }
+        }
+        linked.push((
+            Item {
+                line: at,
+                indent,
+                checked: false,
+                title: todo.title.clone(),
+            },
+            id,
+        ));
+        written += 1;
+    }
+
+    let mut text_out = lines.join(newline);
+    if text.ends_with('\n') || (text.is_empty() && !lines.is_empty()) {
+        text_out.push_str(newline);
+    }
+    Ok(Synced {
+        merged,
+        written,
+        text: text_out,
+    })
+}
+
+/// The bullet of a list item line, with numbered items written as `-`.
+fn item_bullet(line: &str) -> Option<&'static str> {
+    match item(line)?.1 {
+        "*" => Some("*"),
+        "+" => Some("+"),
+        _ => Some("-"),
+    }
+}
+
+/// The ids of the todos in scope, each parent right before its subtasks.
+fn tree_order(data: &PlannerData, project: Option<u64>) -> Vec<u64> {
+    let todos = scope(data, project);
+    let mut order = Vec::new();
+    fn visit(todos: &[&Todo], id: u64, order: &mut Vec<u64>) {
+        if order.contains(&id) {
+            return;
+        }
+        order.push(id);
+        for subtask in todos.iter().filter(|t| t.parent == Some(id)) {
+            visit(todos, subtask.id, order);
+        }
+    }
+    for todo in &todos {
+        if !todo
+            .parent
+            .is_some_and(|parent| todos.iter().any(|t| t.id == parent))
+        {
+            visit(&todos, todo.id, &mut order);
+        }
+    }
+    order
+}
@@ -0,0 +1,305 @@
+// Quick-add: one line of text like `finish report tomorrow 3pm !high #work
+// ~45m` turned into a todo. Words the parser recognises become fields and
+// everything else is the title. Relative dates are worked out from a `now`
+// passed in by the caller, so the same text always gives the same todo in
+// tests.
+use std::fmt;
+
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime, NaiveTime, Weekday};
+
+use super::Priority;
+use crate::data::NewTodo;
+use crate::error::{PlannerError, Result};
+use crate::stopwatch::format_duration;
+use crate::time;
+
+/// Words that only introduce a date or time, dropped along with it.
+const CONNECTORS: &[&str] = &["by", "due", "at", "on"];
+
+/// What the parser understood, in local time.
+#[derive(Debug, Clone, PartialEq)]
+pub struct QuickTodo {
+    pub title: String,
+    pub priority: Option<Priority>,
+    pub due: Option<NaiveDateTime>,
+    pub estimate_minutes: Option<u32>,
+    pub subject: Option<String>,
+}
+
+impl QuickTodo {
+    /// The todo to store, medium priority unless the text said otherwise.
+    pub fn to_new_todo(&self) -> NewTodo {
+        let mut new = NewTodo::new(&self.title, self.priority.unwrap_or(Priority::Medium));
+        new.due = self.due.map(time::from_local);
+        new.estimate_minutes = self.estimate_minutes;
+        new.subject = self.subject.clone();
+        new
+    }
+}
+
+impl fmt::Display for QuickTodo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Title:     {}", self.title)?;
+        let priority = match self.priority {
+            Some(priority) => priority.to_string(),
+            None => format!("{} (default)", Priority::Medium),
+        };
+        writeln!(f, "Priority:  {}", priority)?;
+        if let Some(due) = self.due {
+            writeln!(f, "Due:       {}", due.format("%a %Y-%m-%d %H:%M"))?;
+        }
+        if let Some(subject) = &self.subject {
+            writeln!(f, "Subject:   {}", subject)?;
+        }
+        if let Some(minutes) = self.estimate_minutes {
+            writeln!(
+                f,
+                "Estimate:  {}",
+                format_duration(Duration::minutes(minutes as i64))
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// A date or time found in the text.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum When {
+    Date(NaiveDate),
+    Time(NaiveTime),
+    /// Both at once, like "in 2 hours" or "tonight".
+    At(NaiveDateTime),
+}
+
+/// Parses quick-add text.
+///
+/// - `!high`, `!med`, `!low` (or `!h`, `!m`, `!l`) set the priority.
+/// - `#word` sets the subject.
+/// - `~45m`, `~2h`, `~1h30m` or `~90` set the estimate.
+/// - `today`, `tomorrow`, `tonight`, a weekday (`friday`, or `by fri`), `next
+///   friday` (the Friday of next week), `next week` (next Monday), `in 2 days`,
+///   `in 3 weeks`, `in 2 hours`, `in 30 min` or `2024-03-08` set the due day.
+///   A short weekday like `mon` is only a day after `by`, `on`, `due` or
+///   `next`, so "fix mon script" keeps it in the title.
+/// - `3pm`, `3:30pm`, `15:00` or `noon` set the due time.
+///
+/// A day without a time is due at 23:59, a time without a day is due the
+/// next time the clock shows it.
+pub fn parse(input: &str, now: NaiveDateTime) -> Result<QuickTodo> {
+    let words: Vec<&str> = input.split_whitespace().collect();
+    let mut title = Vec::new();
+    let mut priority = None;
+    let mut subject = None;
+    let mut estimate_minutes = None;
+    let mut found: Vec<(When, String)> = Vec::new();
+
+    let mut i = 0;
+    while i < words.len() {
+        let word = words[i];
+        if let Some(name) = word.strip_prefix('!').filter(|name| !name.is_empty()) {
+            if priority.is_some() {
+                return Err(twice("priority", word));
+            }
+            priority = Some(name.parse::<Priority>()?);
+            i += 1;
+            continue;
+        }
+        if let Some(tag) = word.strip_prefix('#').filter(|tag| !tag.is_empty()) {
+            if subject.is_some() {
+                return Err(twice("subject", word));
+            }
+            subject = Some(tag.to_string());
+            i += 1;
+            continue;
+        }
+        if let Some(length) = word.strip_prefix('~').filter(|length| !length.is_empty()) {
+            if estimate_minutes.is_some() {
+                return Err(twice("estimate", word));
+            }
+            estimate_minutes = Some(minutes(length)?);
+            i += 1;
+            continue;
+        }
+        // "by friday", "at 3pm": the connector goes with what follows it
+        let start = i;
+        let lower = word.to_lowercase();
+        let skip = usize::from(CONNECTORS.contains(&lower.as_str()));
+        let names_day = matches!(lower.as_str(), "by" | "on" | "due");
+        if let Some((when, used)) = when_at(&words[i + skip..], names_day, now) {
+            i += skip + used;
+            found.push((when, words[start..i].join(" ")));
+            continue;
+        }
+        title.push(word);
+        i += 1;
+    }
+
+    Ok(QuickTodo {
+        title: title.join(" "),
+        priority,
+        due: due(&found, now)?,
+        estimate_minutes,
+        subject,
+    })
+}
+
+fn twice(what: &str, word: &str) -> PlannerError {
+    PlannerError::Invalid(format!("'{}' gives a second {}", word, what))
+}
+
+/// Parses `45m`, `2h`, `1h30m` or a bare number of minutes.
+pub(crate) fn minutes(text: &str) -> Result<u32> {
+    let invalid = || PlannerError::Invalid(format!("'~{}' is not a length like ~45m", text));
+    let lower = text.to_lowercase();
+    if let Ok(minutes) = lower.parse::<u32>() {
+        return Ok(minutes);
+    }
+    let (hours, rest) = match lower.split_once('h') {
+        Some((hours, rest)) => (hours.parse::<u32>().map_err(|_| invalid())?, rest),
+        None => (0, lower.as_str()),
+    };
+    let minutes = match rest.strip_suffix('m').unwrap_or(rest) {
+        "" if rest.is_empty() => 0,
+        minutes => minutes.parse::<u32>().map_err(|_| invalid())?,
+    };
+    // Checked, so "~99999999h" is refused instead of overflowing
+    hours
+        .checked_mul(60)
+        .and_then(|hours| hours.checked_add(minutes))
+        .ok_or_else(invalid)
+}
+
+/// The date or time starting at `words[0]`, and how many words it took.
+/// `after_day_word` is set when "by", "on" or "due" came just before.
+fn when_at(words: &[&str], after_day_word: bool, now: NaiveDateTime) -> Option<(When, usize)> {
+    let first = words.first()?.to_lowercase();
+    let second = words.get(1).map(|word| word.to_lowercase());
+    let today = now.date();
+    match first.as_str() {
+        "today" => return Some((When::Date(today), 1)),
+        "tomorrow" | "tmr" => return Some((When::Date(today + Duration::days(1)), 1)),
+        "tonight" => {
+            let evening = today.and_time(NaiveTime::from_hms_opt(20, 0, 0).unwrap());
+            return Some((When::At(evening), 1));
+        }
+        "noon" => return Some((When::Time(NaiveTime::from_hms_opt(12, 0, 0).unwrap()), 1)),
+        "next" => {
+            let second = second?;
+            let monday = today - Duration::days(today.weekday().num_days_from_monday() as i64)
+                + Duration::weeks(1);
+            if second == "week" {
+                return Some((When::Date(monday), 2));
+            }
+            let weekday = weekday(&second, true)?;
+            let date = monday + Duration::days(weekday.num_days_from_monday() as i64);
+            return Some((When::Date(date), 2));
+        }
+        "in" => {
+            let count: u32 = words.get(1)?.parse().ok()?;
+            let count = count as i64;
+            let unit = words.get(2)?.to_lowercase();
+            // Checked, so "in 99999999 weeks" is left in the title instead of panicking
+            let when = match unit.trim_end_matches('s') {
+                "day" => When::Date(today.checked_add_signed(Duration::days(count))?),
+                "week" => When::Date(today.checked_add_signed(Duration::weeks(count))?),
+                "hour" | "hr" | "h" => When::At(now.checked_add_signed(Duration::hours(count))?),
+                "minute" | "min" | "m" => {
+                    When::At(now.checked_add_signed(Duration::minutes(count))?)
+                }
+                _ => return None,
+            };
+            return Some((when, 3));
+        }
+        _ => {}
+    }
+    if let Some(weekday) = weekday(&first, after_day_word) {
+        let ahead = (weekday.num_days_from_monday() as i64
+            - today.weekday().num_days_from_monday() as i64)
+            .rem_euclid(7);
+        // The same weekday as today means a week from now
+        let ahead = if ahead == 0 { 7 } else { ahead };
+        return Some((When::Date(today + Duration::days(ahead)), 1));
+    }
+    if let Ok(date) = NaiveDate::parse_from_str(&first, "%Y-%m-%d") {
+        return Some((When::Date(date), 1));
+    }
+    if let Some(time) = clock(&first) {
+        return Some((When::Time(time), 1));
+    }
+    // "3 pm" written as two words
+    if matches!(second.as_deref(), Some("am" | "pm")) {
+        let time = clock(&format!("{}{}", first, second?))?;
+        return Some((When::Time(time), 2));
+    }
+    None
+}
+
+/// `friday`, or `fri` when `short` allows it; short names like `mon` or
+/// `sat` are too likely to be part of the title on their own.
+fn weekday(word: &str, short: bool) -> Option<Weekday> {
+    if word.len() < 3 || (word.len() == 3 && !short) {
+        return None;
+    }
+    word.parse().ok()
+}
+
+/// Parses `3pm`, `3:30pm`, `12am` or `15:00`.
+fn clock(word: &str) -> Option<NaiveTime> {
+    let (digits, offset) = if let Some(digits) = word.strip_suffix("am") {
+        (digits, Some(0))
+    } else if let Some(digits) = word.strip_suffix("pm") {
+        (digits, Some(12))
+    } else {
+        (word, None)
+    };
+    let (hour, minute) = match digits.split_once(':') {
+        Some((hour, minute)) if minute.len() == 2 => (hour, minute.parse::<u32>().ok()?),
+        Some(_) => return None,
+        // A bare number is only a time with am or pm after it
+        None if offset.is_some() => (digits, 0),
+        None => return None,
+    };
+    let hour: u32 = hour.parse().ok()?;
+    let hour = match offset {
+        Some(offset) if (1..=12).contains(&hour) => hour % 12 + offset,
+        Some(_) => return None,
+        None => hour,
+    };
+    NaiveTime::from_hms_opt(hour, minute, 0)
+}
+
+/// Puts the dates and times found together into one due time.
+fn due(found: &[(When, String)], now: NaiveDateTime) -> Result<Option<NaiveDateTime>> {
+    let mut date = None;
+    let mut clock_time = None;
+    let mut at = None;
+    for (when, text) in found {
+        let clash = match when {
+            When::Date(day) => date.replace(*day).is_some() || at.is_some(),
+            When::Time(time) => clock_time.replace(*time).is_some() || at.is_some(),
+            When::At(instant) => {
+                at.replace(*instant).is_some() || date.is_some() || clock_time.is_some()
+            }
+        };
+        if clash {
+            return Err(PlannerError::Invalid(format!(
+                "'{}' gives a second due date",
+                text
+            )));
+        }
+    }
+    Ok(match (at, date, clock_time) {
+        (Some(at), _, _) => Some(at),
+        (None, Some(date), Some(time)) => Some(date.and_time(time)),
+        (None, Some(date), None) => Some(date.and_hms_opt(23, 59, 0).unwrap()),
+        (None, None, Some(time)) => {
+            let today = now.date().and_time(time);
+            Some(if today > now {
+                today
+            } else {
+                today + Duration::days(1)
+            })
+        }
+        (None, None, None) => None,
+    })
+}
@@ -0,0 +1,312 @@
+// The todo.txt format (github.com/todotxt/todo.txt): one todo per line, like
+//
+//     (A) 2024-03-01 Write report +Launch @work due:2024-03-08 est:90
+//     x 2024-03-05 2024-03-01 Tidy desk pri:C
+//
+// `(A)` is high priority, `(B)` medium and anything lower low. The first
+// `+project` and `@context` become the todo's project and subject; `due:` and
+// `est:` (minutes) fill in the due time and estimate, and `id:` is the id of
+// the todo exported. Other words, tags and `key:value` pairs stay in the
+// title. Title words that would be read as one of those are written with a
+// `\` in front, which is dropped again on import.
+use std::collections::HashSet;
+
+use chrono::{DateTime, NaiveDate, NaiveTime, Utc};
+
+use super::{same_title, Merged, Priority, Todo};
+use crate::data::{NewTodo, PlannerData};
+use crate::error::{PlannerError, Result};
+use crate::time;
+
+/// One line of a todo.txt file.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Entry {
+    pub title: String,
+    pub priority: Option<Priority>,
+    pub done: bool,
+    pub completed: Option<NaiveDate>,
+    pub created: Option<NaiveDate>,
+    pub project: Option<String>,
+    pub context: Option<String>,
+    pub due: Option<DateTime<Utc>>,
+    pub estimate_minutes: Option<u32>,
+    /// The id of the todo in the planner it was exported from.
+    pub id: Option<u64>,
+}
+
+/// Reads every line that holds a todo. Blank lines are skipped.
+pub fn parse(text: &str) -> Result<Vec<Entry>> {
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(number, line)| {
+            parse_line(line).map_err(|e| match e {
+                PlannerError::Invalid(message) => {
+                    PlannerError::Invalid(format!("line {}: {}", number + 1, message))
+                }
+                other => other,
+            })
+        })
+        .collect()
+}
+
+pub fn parse_line(line: &str) -> Result<Entry> {
+    let mut words = line.split_whitespace().peekable();
+    let mut entry = Entry::default();
+    if words.peek() == Some(&"x") {
+        words.next();
+        entry.done = true;
+        entry.completed = words.next_if(|word| date(word).is_some()).and_then(date);
+    }
+    if let Some(word) = words.next_if(|word| priority(word).is_some()) {
+        entry.priority = priority(word);
+    }
+    entry.created = words.next_if(|word| date(word).is_some()).and_then(date);
+
+    let mut title = Vec::new();
+    for word in words {
+        if let Some(plain) = word.strip_prefix('\\') {
+            title.push(plain);
+            continue;
+        }
+        if let Some(tag) = word.strip_prefix('+').filter(|tag| !tag.is_empty()) {
+            if entry.project.is_none() {
+                entry.project = Some(tag.to_string());
+                continue;
+            }
+        } else if let Some(tag) = word.strip_prefix('@').filter(|tag| !tag.is_empty()) {
+            if entry.context.is_none() {
+                entry.context = Some(tag.replace('_', " "));
+                continue;
+            }
+        } else if let Some(due) = word.strip_prefix("due:") {
+            entry.due = Some(time::parse_local_datetime(due)?);
+            continue;
+        } else if let Some(length) = word.strip_prefix("est:") {
+            entry.estimate_minutes = Some(super::quick::minutes(length).map_err(|_| {
+                PlannerError::Invalid(format!("'{}' is not a length like est:45m", word))
+            })?);
+            continue;
+        } else if let Some(id) = word.strip_prefix("id:") {
+            entry.id = Some(id.parse().map_err(|_| {
+                PlannerError::Invalid(format!("'{}' is not an id like id:12", word))
+            })?);
+            continue;
+        } else if let Some(letter) = word.strip_prefix("pri:") {
+            if let Some(priority) = priority(&format!("({})", letter)) {
+                entry.priority = Some(priority);
+                continue;
+            }
+        }
+        title.push(word);
+    }
+    entry.title = title.join(" ");
+    if entry.title.is_empty() {
+        return Err(PlannerError::Invalid(format!("'{}' has no title", line)));
+    }
+    Ok(entry)
+}
+
+fn date(word: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(word, "%Y-%m-%d").ok()
+}
+
+fn priority(word: &str) -> Option<Priority> {
+    let letter = word.strip_prefix('(')?.strip_suffix(')')?;
+    match letter {
+        "A" => Some(Priority::High),
+        "B" => Some(Priority::Medium),
+        _ if letter.len() == 1 && letter.chars().all(|c| c.is_ascii_uppercase()) => {
+            Some(Priority::Low)
+        }
+        _ => None,
+    }
+}
+
+fn letter(priority: Priority) -> char {
+    match priority {
+        Priority::High => 'A',
+        Priority::Medium => 'B',
+        Priority::Low => 'C',
+    }
+}
+
+/// The title with a `\` in front of each word `parse_line` would otherwise
+/// read as a tag, a `key:value` pair or an escape.
+fn escape(title: &str) -> String {
+    title
+        .split_whitespace()
+        .map(|word| {
+            let tag = word.len() > 1 && word.starts_with(['+', '@']);
+            let key = ["due:", "est:", "id:", "pri:"]
+                .iter()
+                .any(|key| word.starts_with(key));
+            if tag || key || word.starts_with('\\') {
+                format!("\\{}", word)
+            } else {
+                word.to_string()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// A tag can hold no spaces, so they become underscores.
+fn tag(name: &str) -> String {
+    name.split_whitespace().collect::<Vec<_>>().join("_")
+}
+
+/// The todo as one todo.txt line.
+pub fn to_line(todo: &Todo, data: &PlannerData) -> String {
+    let mut words = Vec::new();
+    if todo.done {
+        words.push("x".to_string());
+        if let Some(completed) = todo.completed_at {
+            words.push(time::to_local(completed).date().to_string());
+        }
+    } else {
+        words.push(format!("({})", letter(todo.priority)));
+    }
+    words.push(time::to_local(todo.created_at).date().to_string());
+    words.push(escape(&todo.title));
+    if let Some(project) = todo.project.and_then(|id| data.project(id).ok()) {
+        words.push(format!("+{}", tag(&project.name)));
+    }
+    if let Some(subject) = &todo.subject {
+        words.push(format!("@{}", tag(subject)));
+    }
+    if let Some(due) = todo.due {
+        let local = time::to_local(due);
+        // A due day without a time is stored as 23:59
+        if local.time() == NaiveTime::from_hms_opt(23, 59, 0).unwrap() {
+            words.push(format!("due:{}", local.date()));
+        } else {
+            words.push(format!("due:{}", local.format("%Y-%m-%dT%H:%M")));
+        }
+    }
+    if let Some(minutes) = todo.estimate_minutes {
+        words.push(format!("est:{}", minutes));
+    }
+    words.push(format!("id:{}", todo.id));
+    // Finished todos lose their `(A)`, so the priority is kept as a tag
+    if todo.done {
+        words.push(format!("pri:{}", letter(todo.priority)));
+    }
+    words.join(" ")
+}
+
+/// Every todo, or only those of `project`, one per line.
+pub fn export(data: &PlannerData, project: Option<u64>) -> String {
+    data.todos
+        .iter()
+        .filter(|todo| project.is_none() || todo.project == project)
+        .map(|todo| format!("{}\n", to_line(todo, data)))
+        .collect()
+}
+
+/// Adds the lines of a todo.txt file to the planner. A line with the `id:`
+/// of an existing todo updates it with what the line says, and so does one
+/// without a known id that has the title of a todo no other line matched;
+/// the others become new todos, in `project` unless they name one.
+pub fn import(
+    data: &mut PlannerData,
+    text: &str,
+    project: Option<u64>,
+    now: DateTime<Utc>,
+) -> Result<Merged> {
+    if let Some(project) = project {
+        data.project(project)?;
+    }
+    let mut merged = Merged::default();
+    let mut matched = HashSet::new();
+    for entry in parse(text)? {
+        let mut new = NewTodo::new(&entry.title, entry.priority.unwrap_or(Priority::Medium));
+        new.subject = entry.context.clone();
+        new.due = entry.due;
+        new.estimate_minutes = entry.estimate_minutes;
+        new.validate().map_err(|e| match e {
+            PlannerError::Invalid(message) => {
+                PlannerError::Invalid(format!("'{}': {}", entry.title, message))
+            }
+            other => other,
+        })?;
+        let project = match &entry.project {
+            Some(tag) => Some(project_for(data, tag, now, &mut merged)?),
+            None => project,
+        };
+        let existing = entry.id.filter(|id| data.todo(*id).is_ok()).or_else(|| {
+            data.todos
+                .iter()
+                .find(|todo| !matched.contains(&todo.id) && same_title(&todo.title, &entry.title))
+                .map(|todo| todo.id)
+        });
+        let id = match existing {
+            Some(id) => id,
+            None => {
+                new.project = project;
+                let id = data.add_todo(new, now)?;
+                if let Some(created) = entry.created {
+                    data.todo_mut(id)?.created_at = midnight(created);
+                }
+                id
+            }
+        };
+
+        matched.insert(id);
+        let before = data.todo(id)?.clone();
+        let todo = data.todo_mut(id)?;
+        if let Some(priority) = entry.priority {
+            todo.priority = priority;
+        }
+        if entry.context.is_some() {
+            todo.subject = entry.context.clone();
+        }
+        if entry.due.is_some() {
+            todo.due = entry.due;
+        }
+        if entry.estimate_minutes.is_some() {
+            todo.estimate_minutes = entry.estimate_minutes;
+        }
+        if entry.project.is_some() && todo.parent.is_none() {
+            data.move_todo(id, project)?;
+        }
+        let todo = data.todo(id)?;
+        if entry.done && !todo.done {
+            let at = entry.completed.map(midnight).unwrap_or(now);
+            data.complete_todo_tree(id, at)?;
+        } else if !entry.done && todo.done {
+            data.reopen_todo(id)?;
+        }
+
+        if existing.is_none() {
+            merged.added += 1;
+        } else if *data.todo(id)? != before {
+            merged.updated += 1;
+        } else {
+            merged.unchanged += 1;
+        }
+    }
+    Ok(merged)
+}
+
+/// The project a `+tag` names, created when there is none.
+fn project_for(
+    data: &mut PlannerData,
+    name: &str,
+    now: DateTime<Utc>,
+    merged: &mut Merged,
+) -> Result<u64> {
+    let found = data
+        .projects
+        .iter()
+        .find(|project| tag(&project.name).eq_ignore_ascii_case(name));
+    if let Some(project) = found {
+        return Ok(project.id);
+    }
+    merged.projects += 1;
+    data.add_project(&name.replace('_', " "), now)
+}
+
+fn midnight(date: NaiveDate) -> DateTime<Utc> {
+    time::from_local(date.and_time(NaiveTime::MIN))
+}
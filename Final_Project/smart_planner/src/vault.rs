@@ -0,0 +1,493 @@
+// Optional encryption of the planner folder. Locking encrypts every file in
+// it (the data, the history and the sync state) except `config.json`,
+// `vault.json` and any profiles; unlocking gives them back, and no command
+// runs in between.
+//
+// Files are encrypted with XChaCha20-Poly1305 under a random file key. That
+// key is kept in `vault.json`, encrypted with a key derived from the
+// passphrase with Argon2id, so a wrong passphrase is told apart from a
+// damaged file. The rest of `vault.json`, the Argon2id settings, the salt and
+// whether the folder is locked, is authenticated along with each file key.
+// An encrypted file starts with `MAGIC`, the id of its key and the nonce; its
+// path in the folder is authenticated along with it, so files cannot be
+// swapped around either.
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use argon2::{Algorithm, Argon2, Params, Version};
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use chacha20poly1305::{Key, XChaCha20Poly1305, XNonce};
+use serde::{Deserialize, Serialize};
+
+use crate::daemon;
+use crate::error::{PlannerError, Result};
+use crate::profile;
+
+pub const VAULT_FILE: &str = "vault.json";
+/// Added to the name of a file while it is encrypted.
+pub const LOCKED_SUFFIX: &str = ".locked";
+const MAGIC: &[u8; 8] = b"SPVAULT1";
+const KEY_ID_LEN: usize = 8;
+const NONCE_LEN: usize = 24;
+/// Vaults before version 1 authenticate only the id of each file key.
+const VAULT_VERSION: u32 = 1;
+
+/// How hard the passphrase is to guess by brute force. The defaults follow
+/// the OWASP advice for Argon2id.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KdfParams {
+    pub memory_kib: u32,
+    pub iterations: u32,
+    pub parallelism: u32,
+}
+
+impl Default for KdfParams {
+    fn default() -> Self {
+        KdfParams {
+            memory_kib: 19 * 1024,
+            iterations: 2,
+            parallelism: 1,
+        }
+    }
+}
+
+/// A file key, encrypted with the passphrase key.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct WrappedKey {
+    id: String,
+    /// Nonce and encrypted key, in hex.
+    key: String,
+}
+
+/// The contents of `vault.json`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Vault {
+    #[serde(default)]
+    version: u32,
+    pub kdf: KdfParams,
+    salt: String,
+    /// File keys, the one in use last. An older key is only kept while a
+    /// rotation is encrypting the files again.
+    keys: Vec<WrappedKey>,
+    /// Set before the files are encrypted and cleared once all are back.
+    pub locked: bool,
+}
+
+/// The file keys of an opened vault.
+pub struct Keys {
+    keys: Vec<([u8; KEY_ID_LEN], Key)>,
+    /// The passphrase key, to wrap the keys again when the vault changes.
+    wrapping: Key,
+}
+
+impl Vault {
+    /// The vault of `dir`, `None` when encryption is off.
+    pub fn load(dir: &Path) -> Result<Option<Vault>> {
+        let path = dir.join(VAULT_FILE);
+        if !path.exists() {
+            return Ok(None);
+        }
+        Ok(Some(serde_json::from_str(&fs::read_to_string(path)?)?))
+    }
+
+    pub(crate) fn save(&self, dir: &Path) -> Result<()> {
+        write_atomic(
+            &dir.join(VAULT_FILE),
+            serde_json::to_string_pretty(self)?.as_bytes(),
+        )
+    }
+
+    /// A new vault with one file key, locked by `passphrase`.
+    fn create(passphrase: &str, kdf: KdfParams) -> Result<(Vault, Keys)> {
+        check_new_passphrase(passphrase)?;
+        let salt = random::<16>();
+        let wrapping = derive(passphrase, &salt, kdf)?;
+        let keys = Keys {
+            keys: vec![(random(), XChaCha20Poly1305::generate_key(&mut OsRng))],
+            wrapping,
+        };
+        let mut vault = Vault {
+            version: VAULT_VERSION,
+            kdf,
+            salt: hex(&salt),
+            keys: Vec::new(),
+            locked: false,
+        };
+        vault.seal(&keys, false);
+        Ok((vault, keys))
+    }
+
+    /// Sets whether the folder is locked and wraps the file keys again, so
+    /// the change is authenticated.
+    fn seal(&mut self, keys: &Keys, locked: bool) {
+        self.version = VAULT_VERSION;
+        self.locked = locked;
+        self.keys = keys.wrap(&self.header());
+    }
+
+    /// What each wrapped file key is authenticated with, besides its id.
+    fn header(&self) -> Vec<u8> {
+        if self.version == 0 {
+            return Vec::new();
+        }
+        format!(
+            "{}:{}:{}:{}:{}:{}",
+            self.version,
+            self.kdf.memory_kib,
+            self.kdf.iterations,
+            self.kdf.parallelism,
+            self.salt,
+            self.locked
+        )
+        .into_bytes()
+    }
+
+    /// The file keys, or an error saying the passphrase is wrong.
+    pub fn open(&self, passphrase: &str) -> Result<Keys> {
+        let wrapping = derive(passphrase, &unhex(&self.salt)?, self.kdf)?;
+        let cipher = XChaCha20Poly1305::new(&wrapping);
+        let header = self.header();
+        let mut keys = Vec::new();
+        for wrapped in &self.keys {
+            let id: [u8; KEY_ID_LEN] = unhex(&wrapped.id)?
+                .try_into()
+                .map_err(|_| damaged(VAULT_FILE))?;
+            let sealed = unhex(&wrapped.key)?;
+            if sealed.len() < NONCE_LEN {
+                return Err(damaged(VAULT_FILE));
+            }
+            let (nonce, key) = sealed.split_at(NONCE_LEN);
+            let aad = [id.as_slice(), &header].concat();
+            let key = cipher
+                .decrypt(
+                    XNonce::from_slice(nonce),
+                    Payload {
+                        msg: key,
+                        aad: &aad,
+                    },
+                )
+                .map_err(|_| PlannerError::Encryption("wrong passphrase".to_string()))?;
+            if key.len() != 32 {
+                return Err(damaged(VAULT_FILE));
+            }
+            keys.push((id, *Key::from_slice(&key)));
+        }
+        Ok(Keys { keys, wrapping })
+    }
+}
+
+impl Keys {
+    fn wrap(&self, header: &[u8]) -> Vec<WrappedKey> {
+        let cipher = XChaCha20Poly1305::new(&self.wrapping);
+        self.keys
+            .iter()
+            .map(|(id, key)| {
+                let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+                let aad = [id.as_slice(), header].concat();
+                let sealed = cipher
+                    .encrypt(
+                        &nonce,
+                        Payload {
+                            msg: key,
+                            aad: &aad,
+                        },
+                    )
+                    .expect("a key always fits");
+                WrappedKey {
+                    id: hex(id),
+                    key: hex(&[nonce.as_slice(), &sealed].concat()),
+                }
+            })
+            .collect()
+    }
+
+    /// Encrypts the contents of the file at `name`, a path in the planner
+    /// folder, with the newest key.
+    pub fn encrypt(&self, name: &str, plain: &[u8]) -> Vec<u8> {
+        let (id, key) = self.keys.last().expect("a vault has a key");
+        let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let aad = [MAGIC.as_slice(), id, name.as_bytes()].concat();
+        let sealed = XChaCha20Poly1305::new(key)
+            .encrypt(
+                &nonce,
+                Payload {
+                    msg: plain,
+                    aad: &aad,
+                },
+            )
+            .expect("the file fits in memory");
+        [MAGIC.as_slice(), id, nonce.as_slice(), &sealed].concat()
+    }
+
+    /// Gives back what `encrypt` got for the same `name`.
+    pub fn decrypt(&self, name: &str, sealed: &[u8]) -> Result<Vec<u8>> {
+        let header = MAGIC.len() + KEY_ID_LEN + NONCE_LEN;
+        if sealed.len() < header || !sealed.starts_with(MAGIC) {
+            return Err(PlannerError::Encryption(format!(
+                "{} is not an encrypted planner file",
+                name
+            )));
+        }
+        let id = &sealed[MAGIC.len()..MAGIC.len() + KEY_ID_LEN];
+        let nonce = &sealed[MAGIC.len() + KEY_ID_LEN..header];
+        let (_, key) = self
+            .keys
+            .iter()
+            .find(|(known, _)| known == id)
+            .ok_or_else(|| {
+                PlannerError::Encryption(format!("{} was encrypted with another key", name))
+            })?;
+        let aad = [MAGIC.as_slice(), id, name.as_bytes()].concat();
+        XChaCha20Poly1305::new(key)
+            .decrypt(
+                XNonce::from_slice(nonce),
+                Payload {
+                    msg: &sealed[header..],
+                    aad: &aad,
+                },
+            )
+            .map_err(|_| damaged(name))
+    }
+}
+
+/// Whether `dir` has a vault and is locked, so nothing can be read. Files
+/// still encrypted count too, whatever `vault.json` says.
+pub fn is_locked(dir: &Path) -> Result<bool> {
+    match Vault::load(dir)? {
+        None => Ok(false),
+        Some(vault) if vault.locked => Ok(true),
+        Some(_) => Ok(files(dir)?
+            .iter()
+            .any(|(_, name)| name.ends_with(LOCKED_SUFFIX))),
+    }
+}
+
+/// Turns encryption on and locks the folder. Returns how many files were
+/// encrypted.
+pub fn enable(dir: &Path, passphrase: &str, kdf: KdfParams) -> Result<usize> {
+    if Vault::load(dir)?.is_some() {
+        return Err(PlannerError::Invalid(
+            "encryption is already on, use `lock` and `unlock`".to_string(),
+        ));
+    }
+    let (vault, _) = Vault::create(passphrase, kdf)?;
+    vault.save(dir)?;
+    lock(dir, passphrase)
+}
+
+/// Encrypts every file not encrypted yet. Returns how many there were.
+///
+/// The plain files are only removed once all of them are encrypted and the
+/// vault says it is locked, so a failure halfway leaves the folder readable.
+pub fn lock(dir: &Path, passphrase: &str) -> Result<usize> {
+    let mut vault = existing(dir)?;
+    let keys = vault.open(passphrase)?;
+    let mut plain: Vec<PathBuf> = Vec::new();
+    for (path, name) in files(dir)? {
+        if name.ends_with(LOCKED_SUFFIX) {
+            continue;
+        }
+        let sealed = fs::read(&path)
+            .map_err(PlannerError::from)
+            .and_then(|contents| {
+                write_atomic(&locked_path(&path), &keys.encrypt(&name, &contents))
+            });
+        if let Err(e) = sealed {
+            for path in &plain {
+                let _ = fs::remove_file(locked_path(path));
+            }
+            return Err(e);
+        }
+        plain.push(path);
+    }
+    vault.seal(&keys, true);
+    vault.save(dir)?;
+    for path in &plain {
+        fs::remove_file(path)?;
+    }
+    Ok(plain.len())
+}
+
+/// Decrypts every encrypted file. Returns how many there were.
+///
+/// All of them are decrypted before anything is written, and the encrypted
+/// files are only removed once the vault says it is unlocked, so a failure
+/// halfway leaves the folder locked.
+pub fn unlock(dir: &Path, passphrase: &str) -> Result<usize> {
+    let mut vault = existing(dir)?;
+    let keys = vault.open(passphrase)?;
+    let mut opened = Vec::new();
+    for (path, name) in files(dir)? {
+        let Some(plain_name) = name.strip_suffix(LOCKED_SUFFIX) else {
+            continue;
+        };
+        opened.push((keys.decrypt(plain_name, &fs::read(&path)?)?, path));
+    }
+    let mut written: Vec<PathBuf> = Vec::new();
+    for (plain, path) in &opened {
+        if let Err(e) = write_atomic(&plain_path(path), plain) {
+            for path in &written {
+                let _ = fs::remove_file(path);
+            }
+            return Err(e);
+        }
+        written.push(plain_path(path));
+    }
+    vault.seal(&keys, false);
+    vault.save(dir)?;
+    for (_, path) in &opened {
+        fs::remove_file(path)?;
+    }
+    Ok(opened.len())
+}
+
+/// Unlocks the folder for good and removes the vault.
+pub fn disable(dir: &Path, passphrase: &str) -> Result<usize> {
+    let count = unlock(dir, passphrase)?;
+    fs::remove_file(dir.join(VAULT_FILE))?;
+    Ok(count)
+}
+
+/// Replaces the file key with a new one and the passphrase with
+/// `new_passphrase`, which may be the same. Encrypted files are encrypted
+/// again; the old key stays in the vault until that is done, so a crash
+/// halfway loses nothing. Returns how many files were encrypted again.
+pub fn rotate(dir: &Path, passphrase: &str, new_passphrase: &str, kdf: KdfParams) -> Result<usize> {
+    let vault = existing(dir)?;
+    let mut keys = vault.open(passphrase)?;
+    let (mut rotated, new) = Vault::create(new_passphrase, kdf)?;
+    keys.keys.extend(new.keys);
+    keys.wrapping = new.wrapping;
+    rotated.seal(&keys, vault.locked);
+    rotated.save(dir)?;
+
+    let mut count = 0;
+    for (path, name) in files(dir)? {
+        let Some(plain_name) = name.strip_suffix(LOCKED_SUFFIX) else {
+            continue;
+        };
+        let plain = keys.decrypt(plain_name, &fs::read(&path)?)?;
+        write_atomic(&path, &keys.encrypt(plain_name, &plain))?;
+        count += 1;
+    }
+    rotated.keys.drain(..rotated.keys.len() - 1);
+    rotated.save(dir)?;
+    Ok(count)
+}
+
+fn existing(dir: &Path) -> Result<Vault> {
+    Vault::load(dir)?.ok_or_else(|| {
+        PlannerError::Invalid("encryption is off, turn it on with `encryption enable`".to_string())
+    })
+}
+
+pub fn check_new_passphrase(passphrase: &str) -> Result<()> {
+    if passphrase.chars().count() < 8 {
+        return Err(PlannerError::Invalid(
+            "the passphrase needs at least 8 characters".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Every file in the folder to encrypt, with its path relative to `dir`
+/// using `/`. Unfinished writes (`.tmp`), the daemon's folder and anything
+/// that is not a plain file, like its socket, are left alone.
+fn files(dir: &Path) -> Result<Vec<(PathBuf, String)>> {
+    let profiles = format!("{}/", profile::PROFILES_DIR);
+    let mut found = Vec::new();
+    let mut folders = vec![dir.to_path_buf()];
+    while let Some(folder) = folders.pop() {
+        for entry in fs::read_dir(&folder)? {
+            let path = entry?.path();
+            if path.is_dir() {
+                if path != dir.join(daemon::DIR) {
+                    folders.push(path);
+                }
+                continue;
+            }
+            if !path.is_file() {
+                continue;
+            }
+            let name: Vec<String> = path
+                .strip_prefix(dir)
+                .expect("found inside the folder")
+                .components()
+                .map(|part| part.as_os_str().to_string_lossy().into_owned())
+                .collect();
+            let name = name.join("/");
+            if name == VAULT_FILE || name == "config.json" || name.ends_with(".tmp") {
+                continue;
+            }
+            // Profiles in a home folder have vaults of their own
+            if name == profile::SETTINGS_FILE || name.starts_with(&profiles) {
+                continue;
+            }
+            found.push((path, name));
+        }
+    }
+    found.sort();
+    Ok(found)
+}
+
+fn locked_path(path: &Path) -> PathBuf {
+    let mut locked = path.as_os_str().to_owned();
+    locked.push(LOCKED_SUFFIX);
+    PathBuf::from(locked)
+}
+
+fn plain_path(path: &Path) -> PathBuf {
+    let name = path.to_string_lossy();
+    PathBuf::from(name.strip_suffix(LOCKED_SUFFIX).unwrap_or(&name))
+}
+
+/// Writes a temporary file first so a crash never leaves half a file behind.
+fn write_atomic(path: &Path, contents: &[u8]) -> Result<()> {
+    let mut temp = path.as_os_str().to_owned();
+    temp.push(".tmp");
+    fs::write(&temp, contents)?;
+    fs::rename(temp, path)?;
+    Ok(())
+}
+
+fn derive(passphrase: &str, salt: &[u8], kdf: KdfParams) -> Result<Key> {
+    let params = Params::new(kdf.memory_kib, kdf.iterations, kdf.parallelism, Some(32))
+        .map_err(|e| PlannerError::Encryption(format!("bad key derivation settings: {}", e)))?;
+    let mut key = Key::default();
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|e| PlannerError::Encryption(format!("cannot derive the key: {}", e)))?;
+    Ok(key)
+}
+
+fn random<const N: usize>() -> [u8; N] {
+    let mut bytes = [0; N];
+    OsRng.fill_bytes(&mut bytes);
+    bytes
+}
+
+fn damaged(name: &str) -> PlannerError {
+    PlannerError::Encryption(format!(
+        "{} was changed or damaged after it was encrypted",
+        name
+    ))
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn unhex(text: &str) -> Result<Vec<u8>> {
+    if !text.len().is_multiple_of(2) {
+        return Err(damaged(VAULT_FILE));
+    }
+    (0..text.len())
+        .step_by(2)
+        .map(|i| {
+            text.get(i..i + 2)
+                .and_then(|byte| u8::from_str_radix(byte, 16).ok())
+                .ok_or_else(|| damaged(VAULT_FILE))
+        })
+        .collect()
+}
@@ -0,0 +1,469 @@
+// Planner events POSTed as JSON to the URLs in the `webhooks` section of
+// `config.json`. Each body is signed with HMAC-SHA256 when the hook has a
+// secret, so the receiver can tell it came from this planner. Failed
+// deliveries are tried again with a growing delay; those that still fail are
+// kept in `webhooks/dead_letters.jsonl` until `webhook retry` gets them
+// through. The daemon and the commands may write that file at the same time,
+// so they take turns through a lock on `dead_letters.lock`.
+use std::env;
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::OsRng;
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use reqwest::blocking::Client;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::Sha256;
+
+use crate::data::PlannerData;
+use crate::error::{PlannerError, Result};
+use crate::plan::Plan;
+use crate::report::{self, Report};
+use crate::timer::Timer;
+use crate::todo::Todo;
+
+/// The folder in the planner folder kept for webhooks.
+pub const DIR: &str = "webhooks";
+const DEAD_LETTERS: &str = "dead_letters.jsonl";
+const DEAD_LETTERS_LOCK: &str = "dead_letters.lock";
+/// The longest wait between two attempts, however the delay grew.
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(300);
+/// How often a delivery waiting to try again looks whether to stop.
+const STOP_CHECK: Duration = Duration::from_millis(50);
+/// The error of a delivery given up on because the sender stopped.
+const STOPPED: &str = "stopped before trying again";
+/// Overdue todos already announced, so each is sent once.
+const OVERDUE_FILE: &str = "overdue.json";
+/// Holds `sha256=` and the hex HMAC of the body.
+pub const SIGNATURE_HEADER: &str = "X-Planner-Signature";
+pub const EVENT_HEADER: &str = "X-Planner-Event";
+/// The id of the event, the same on every attempt.
+pub const DELIVERY_HEADER: &str = "X-Planner-Delivery";
+
+/// What happened.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EventKind {
+    TimerExpired,
+    TodoOverdue,
+    PlanGenerated,
+    ReportReady,
+    /// Sent by `webhook test` to every hook.
+    Ping,
+}
+
+impl EventKind {
+    fn name(self) -> &'static str {
+        match self {
+            EventKind::TimerExpired => "timer_expired",
+            EventKind::TodoOverdue => "todo_overdue",
+            EventKind::PlanGenerated => "plan_generated",
+            EventKind::ReportReady => "report_ready",
+            EventKind::Ping => "ping",
+        }
+    }
+}
+
+fn default_retries() -> u32 {
+    3
+}
+
+fn default_retry_delay_ms() -> u64 {
+    1000
+}
+
+fn default_timeout_secs() -> u64 {
+    10
+}
+
+/// One entry of the `webhooks` list in `config.json`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WebhookConfig {
+    pub url: String,
+    /// Key for the signature. Falls back to `SMART_PLANNER_WEBHOOK_SECRET`,
+    /// and the body goes unsigned when neither is set.
+    #[serde(default)]
+    pub secret: Option<String>,
+    /// The events to send, every one when left empty.
+    #[serde(default)]
+    pub events: Vec<EventKind>,
+    /// How many times a failed delivery is tried again.
+    #[serde(default = "default_retries")]
+    pub retries: u32,
+    /// Wait before the first retry, doubled for every retry after it.
+    #[serde(default = "default_retry_delay_ms")]
+    pub retry_delay_ms: u64,
+    #[serde(default = "default_timeout_secs")]
+    pub timeout_secs: u64,
+}
+
+impl WebhookConfig {
+    pub fn new(url: &str) -> Self {
+        WebhookConfig {
+            url: url.to_string(),
+            secret: None,
+            events: Vec::new(),
+            retries: default_retries(),
+            retry_delay_ms: default_retry_delay_ms(),
+            timeout_secs: default_timeout_secs(),
+        }
+    }
+
+    pub fn wants(&self, kind: EventKind) -> bool {
+        kind == EventKind::Ping || self.events.is_empty() || self.events.contains(&kind)
+    }
+
+    fn secret(&self) -> Option<String> {
+        self.secret
+            .clone()
+            .or_else(|| env::var("SMART_PLANNER_WEBHOOK_SECRET").ok())
+            .filter(|secret| !secret.is_empty())
+    }
+}
+
+/// The JSON body of a delivery.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Event {
+    pub id: String,
+    pub event: EventKind,
+    pub created_at: DateTime<Utc>,
+    pub data: Value,
+}
+
+impl Event {
+    pub fn new(kind: EventKind, data: Value, now: DateTime<Utc>) -> Self {
+        let mut id = [0u8; 16];
+        OsRng.fill_bytes(&mut id);
+        Event {
+            id: id.iter().map(|byte| format!("{:02x}", byte)).collect(),
+            event: kind,
+            created_at: now,
+            data,
+        }
+    }
+
+    pub fn timer_expired(timer: &Timer, now: DateTime<Utc>) -> Self {
+        Event::new(EventKind::TimerExpired, json!({ "timer": timer }), now)
+    }
+
+    pub fn todo_overdue(todo: &Todo, now: DateTime<Utc>) -> Self {
+        Event::new(EventKind::TodoOverdue, json!({ "todo": todo }), now)
+    }
+
+    pub fn plan_generated(plan: &Plan, now: DateTime<Utc>) -> Self {
+        Event::new(EventKind::PlanGenerated, json!({ "plan": plan }), now)
+    }
+
+    /// Carries the report as data and as the text `report` prints.
+    pub fn report_ready(report: &Report, now: DateTime<Utc>) -> Self {
+        let data = json!({ "report": report, "text": report::text::render(report) });
+        Event::new(EventKind::ReportReady, data, now)
+    }
+}
+
+/// The value of `SIGNATURE_HEADER` for `body`.
+pub fn sign(secret: &str, body: &[u8]) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("any key length");
+    mac.update(body);
+    let bytes = mac.finalize().into_bytes();
+    let hex: String = bytes.iter().map(|byte| format!("{:02x}", byte)).collect();
+    format!("sha256={}", hex)
+}
+
+/// Whether `signature` is the one for `body`, compared in constant time. For
+/// receivers written in Rust, and the tests.
+pub fn verify(secret: &str, body: &[u8], signature: &str) -> bool {
+    let Some(hex) = signature.strip_prefix("sha256=") else {
+        return false;
+    };
+    let Some(expected) = unhex(hex) else {
+        return false;
+    };
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("any key length");
+    mac.update(body);
+    mac.verify_slice(&expected).is_ok()
+}
+
+fn unhex(text: &str) -> Option<Vec<u8>> {
+    if !text.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..text.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(text.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+/// A delivery that failed every attempt.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DeadLetter {
+    pub url: String,
+    pub failed_at: DateTime<Utc>,
+    pub attempts: u32,
+    pub error: String,
+    pub event: Event,
+}
+
+/// Delivers events and keeps the dead letters of one planner folder.
+pub struct Sender {
+    dir: PathBuf,
+    retries: bool,
+    /// Once set, nothing more is tried.
+    stop: Arc<AtomicBool>,
+}
+
+impl Sender {
+    /// For the planner folder `dir`.
+    pub fn new(dir: &Path) -> Self {
+        Sender {
+            dir: dir.join(DIR),
+            retries: true,
+            stop: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// Tries each delivery once, whatever the hooks' `retries`, so nothing
+    /// waits on a hook that is down. Failures go to the dead letters.
+    pub fn without_retries(mut self) -> Self {
+        self.retries = false;
+        self
+    }
+
+    /// Gives up once `stop` is set: the attempt going on is finished, and
+    /// the deliveries left go to the dead letters.
+    pub fn stopped_by(mut self, stop: Arc<AtomicBool>) -> Self {
+        self.stop = stop;
+        self
+    }
+
+    /// Sleeps for `delay`, returning early with `false` when told to stop.
+    fn wait(&self, delay: Duration) -> bool {
+        let until = Instant::now() + delay;
+        while !self.stop.load(Ordering::SeqCst) {
+            let left = until.saturating_duration_since(Instant::now());
+            if left.is_zero() {
+                return true;
+            }
+            thread::sleep(left.min(STOP_CHECK));
+        }
+        false
+    }
+
+    /// Posts `event` to one hook, trying again after network errors, a
+    /// timeout, `429` or a `5xx` reply. Returns how many attempts it took.
+    pub fn deliver(&self, hook: &WebhookConfig, event: &Event) -> Result<u32> {
+        self.try_deliver(hook, event).map_err(|(attempts, error)| {
+            PlannerError::Http(format!(
+                "posting to {} failed after {} attempt(s): {}",
+                hook.url, attempts, error
+            ))
+        })
+    }
+
+    /// Like `deliver`, failing with the number of attempts and the last error.
+    fn try_deliver(
+        &self,
+        hook: &WebhookConfig,
+        event: &Event,
+    ) -> std::result::Result<u32, (u32, String)> {
+        let body = serde_json::to_vec(event).map_err(|e| (0, e.to_string()))?;
+        let client = Client::builder()
+            .timeout(Duration::from_secs(hook.timeout_secs))
+            .build()
+            .map_err(|e| (0, e.to_string()))?;
+        let retries = if self.retries { hook.retries } else { 0 };
+        let mut delay = Duration::from_millis(hook.retry_delay_ms).min(MAX_RETRY_DELAY);
+        if self.stop.load(Ordering::SeqCst) {
+            return Err((0, STOPPED.to_string()));
+        }
+        let mut attempt = 1;
+        loop {
+            let mut request = client
+                .post(&hook.url)
+                .header("Content-Type", "application/json")
+                .header("User-Agent", "smart_planner")
+                .header(EVENT_HEADER, event.event.name())
+                .header(DELIVERY_HEADER, &event.id)
+                .body(body.clone());
+            if let Some(secret) = hook.secret() {
+                request = request.header(SIGNATURE_HEADER, sign(&secret, &body));
+            }
+            let (error, transient) = match request.send() {
+                Ok(response) if response.status().is_success() => return Ok(attempt),
+                Ok(response) => {
+                    let status = response.status();
+                    let transient = status.is_server_error() || status.as_u16() == 429;
+                    (format!("the hook answered {}", status), transient)
+                }
+                Err(e) => (e.to_string(), true),
+            };
+            if !transient || attempt > retries {
+                return Err((attempt, error));
+            }
+            if !self.wait(delay) {
+                return Err((attempt, format!("{}, then {}", error, STOPPED)));
+            }
+            delay = delay.saturating_mul(2).min(MAX_RETRY_DELAY);
+            attempt += 1;
+        }
+    }
+
+    /// Posts `event` to every hook that wants it. Deliveries that fail are
+    /// written to the dead letters and returned.
+    pub fn send(&self, hooks: &[WebhookConfig], event: &Event) -> Result<Vec<DeadLetter>> {
+        let mut failed = Vec::new();
+        for hook in hooks.iter().filter(|hook| hook.wants(event.event)) {
+            if let Err((attempts, error)) = self.try_deliver(hook, event) {
+                let letter = DeadLetter {
+                    url: hook.url.clone(),
+                    failed_at: Utc::now(),
+                    attempts,
+                    error,
+                    event: event.clone(),
+                };
+                self.append(&letter)?;
+                failed.push(letter);
+            }
+        }
+        Ok(failed)
+    }
+
+    fn dead_letters_path(&self) -> PathBuf {
+        self.dir.join(DEAD_LETTERS)
+    }
+
+    /// Waits for the dead letters to be free and keeps them until dropped.
+    fn lock(&self) -> Result<File> {
+        fs::create_dir_all(&self.dir)?;
+        let lock = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(self.dir.join(DEAD_LETTERS_LOCK))?;
+        lock.lock()?;
+        Ok(lock)
+    }
+
+    fn append(&self, letter: &DeadLetter) -> Result<()> {
+        let _lock = self.lock()?;
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.dead_letters_path())?;
+        writeln!(file, "{}", serde_json::to_string(letter)?)?;
+        Ok(())
+    }
+
+    /// Deliveries that failed, oldest first.
+    pub fn dead_letters(&self) -> Result<Vec<DeadLetter>> {
+        if !self.dead_letters_path().exists() {
+            return Ok(Vec::new());
+        }
+        let _lock = self.lock()?;
+        self.read_dead_letters()
+    }
+
+    fn read_dead_letters(&self) -> Result<Vec<DeadLetter>> {
+        let path = self.dead_letters_path();
+        if !path.exists() {
+            return Ok(Vec::new());
+        }
+        fs::read_to_string(path)?
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| Ok(serde_json::from_str(line)?))
+            .collect()
+    }
+
+    /// Sends every dead letter again, to the hook with its URL as configured
+    /// now. Those delivered are dropped from the log; returns how many were
+    /// and the letters still failing. Letters added while it runs are kept.
+    pub fn retry(&self, hooks: &[WebhookConfig]) -> Result<(usize, Vec<DeadLetter>)> {
+        let mut done = Vec::new();
+        let mut failed = Vec::new();
+        for mut letter in self.dead_letters()? {
+            let Some(hook) = hooks.iter().find(|hook| hook.url == letter.url) else {
+                letter.error = "the hook is no longer in config.json".to_string();
+                failed.push(letter);
+                continue;
+            };
+            match self.try_deliver(hook, &letter.event) {
+                Ok(_) => done.push(letter),
+                Err((attempts, error)) => {
+                    letter.attempts += attempts;
+                    letter.error = error;
+                    letter.failed_at = Utc::now();
+                    failed.push(letter);
+                }
+            }
+        }
+
+        let _lock = self.lock()?;
+        let same = |a: &DeadLetter, b: &DeadLetter| a.url == b.url && a.event.id == b.event.id;
+        let mut left = Vec::new();
+        for letter in self.read_dead_letters()? {
+            if done.iter().any(|done| same(done, &letter)) {
+                continue;
+            }
+            match failed.iter().find(|failed| same(failed, &letter)) {
+                Some(failed) => left.push(failed.clone()),
+                None => left.push(letter),
+            }
+        }
+        let mut text = String::new();
+        for letter in &left {
+            text.push_str(&serde_json::to_string(letter)?);
+            text.push('\n');
+        }
+        let path = self.dead_letters_path();
+        if left.is_empty() {
+            if path.exists() {
+                fs::remove_file(path)?;
+            }
+        } else {
+            let temp = self.dir.join(format!("{}.tmp", DEAD_LETTERS));
+            fs::write(&temp, text)?;
+            fs::rename(temp, path)?;
+        }
+        Ok((done.len(), left))
+    }
+
+    /// Events for todos past their due time that were not announced yet. A
+    /// todo is announced again when it gets a new due time.
+    pub fn overdue(&self, data: &PlannerData, now: DateTime<Utc>) -> Result<Vec<Event>> {
+        let path = self.dir.join(OVERDUE_FILE);
+        let announced: Vec<(u64, DateTime<Utc>)> = if path.exists() {
+            serde_json::from_str(&fs::read_to_string(&path)?)?
+        } else {
+            Vec::new()
+        };
+        let overdue: Vec<&Todo> = data
+            .todos
+            .iter()
+            .filter(|todo| !todo.done && todo.due.is_some_and(|due| due < now))
+            .collect();
+        let events: Vec<Event> = overdue
+            .iter()
+            .filter(|todo| !announced.contains(&(todo.id, todo.due.unwrap())))
+            .map(|todo| Event::todo_overdue(todo, now))
+            .collect();
+        // Only todos still overdue are kept, so the file does not grow
+        let now_announced: Vec<(u64, DateTime<Utc>)> = overdue
+            .iter()
+            .map(|todo| (todo.id, todo.due.unwrap()))
+            .collect();
+        if now_announced != announced {
+            fs::create_dir_all(&self.dir)?;
+            fs::write(&path, serde_json::to_string(&now_announced)?)?;
+        }
+        Ok(events)
+    }
+}
@@ -0,0 +1,232 @@
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+
+use chrono::{NaiveDate, TimeZone, Utc};
+use smart_planner::backup::{self, EXPORT_FORMAT, EXPORT_VERSION};
+use smart_planner::data::{NewTodo, PlannerData};
+use smart_planner::routine::CheckStatus;
+use smart_planner::store::Store;
+use smart_planner::todo::Priority;
+use smart_planner::vault::{self, KdfParams};
+use smart_planner::PlannerError;
+
+/// A fresh folder under the system temp folder.
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("smart_planner_backup_{}_{}", name, process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// A planner with two saves in its history and a config file.
+fn planner(dir: &Path) -> PlannerData {
+    fs::write(
+        dir.join("config.json"),
+        "{\"budgets\": {\"thresholds\": [90]}}",
+    )
+    .unwrap();
+    let store = Store::open(dir).unwrap();
+    let mut data = store.load().unwrap();
+    data.add_todo(NewTodo::new("Renew passport", Priority::High), Utc::now())
+        .unwrap();
+    store.save(&data).unwrap();
+    data.complete_todo(1, Utc::now()).unwrap();
+    store.save(&data).unwrap();
+    data
+}
+
+fn invalid(result: smart_planner::Result<impl Sized>) -> String {
+    match result {
+        Err(PlannerError::Invalid(message)) => message,
+        Err(other) => panic!("unexpected error {}", other),
+        Ok(_) => panic!("expected an error"),
+    }
+}
+
+#[test]
+fn a_backup_puts_back_the_data_and_its_history() {
+    let dir = temp_dir("round_trip");
+    let saved = planner(&dir);
+    let store = Store::open(&dir).unwrap();
+    let file = backup::create(&store, None, Utc::now()).unwrap();
+    assert!(!backup::is_encrypted(&file).unwrap());
+
+    // Later changes are undone by restoring
+    let mut later = saved.clone();
+    later.remove_todo(1).unwrap();
+    store.save(&later).unwrap();
+    fs::write(dir.join("config.json"), "{}").unwrap();
+
+    let read = backup::read(&file, None).unwrap();
+    assert_eq!(read.data, saved);
+    assert!(read.files.contains_key("history/events.jsonl"));
+    backup::restore(&dir, &read, None).unwrap();
+    let store = Store::open(&dir).unwrap();
+    assert_eq!(store.load().unwrap(), saved);
+    assert_eq!(store.history().current().unwrap(), saved);
+    assert_eq!(store.history().events().unwrap().len(), 2);
+    assert_eq!(store.config().unwrap().budgets.thresholds, vec![90]);
+
+    // A restore into an empty folder works the same
+    let elsewhere = temp_dir("round_trip_elsewhere");
+    backup::restore(&elsewhere, &read, None).unwrap();
+    assert_eq!(Store::open(&elsewhere).unwrap().load().unwrap(), saved);
+}
+
+#[test]
+fn a_restore_keeps_the_storage_set_here() {
+    let dir = temp_dir("storage");
+    let saved = planner(&dir);
+    let file = backup::create(&Store::open(&dir).unwrap(), None, Utc::now()).unwrap();
+    let mut read = backup::read(&file, None).unwrap();
+    read.files.insert(
+        "config.json".to_string(),
+        "{\"budgets\": {\"thresholds\": [50]}, \
+         \"storage\": {\"backend\": \"http\", \"url\": \"http://127.0.0.1:9/\"}}"
+            .to_string(),
+    );
+
+    // The backup's config comes back, but not its storage backend
+    let here = temp_dir("storage_here");
+    fs::write(
+        here.join("config.json"),
+        "{\"storage\": {\"backend\": \"sqlite\"}}",
+    )
+    .unwrap();
+    backup::restore(&here, &read, None).unwrap();
+    let store = Store::open(&here).unwrap();
+    let config = store.config().unwrap();
+    assert_eq!(config.storage.to_string(), "sqlite");
+    assert_eq!(config.budgets.thresholds, vec![50]);
+    assert_eq!(store.load().unwrap(), saved);
+    assert!(here.join("planner.db").exists());
+    assert!(!here.join("data.json").exists());
+
+    // A restore that fails halfway puts back what it replaced
+    let blocked = temp_dir("storage_blocked");
+    planner(&blocked);
+    fs::create_dir(blocked.join("wall")).unwrap();
+    let config = "{\"storage\": {\"backend\": \"json\", \"path\": \"wall\"}}";
+    fs::write(blocked.join("config.json"), config).unwrap();
+    let events = blocked.join("history").join("events.jsonl");
+    let log = fs::read_to_string(&events).unwrap();
+    assert!(backup::restore(&blocked, &read, None).is_err());
+    assert_eq!(
+        fs::read_to_string(blocked.join("config.json")).unwrap(),
+        config
+    );
+    assert_eq!(fs::read_to_string(&events).unwrap(), log);
+    let mut folders: Vec<String> = fs::read_dir(&blocked)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.is_dir())
+        .map(|path| path.file_name().unwrap().to_string_lossy().into_owned())
+        .collect();
+    folders.sort();
+    assert_eq!(
+        folders,
+        vec!["history", "wall"],
+        "nothing else is left behind"
+    );
+}
+
+#[test]
+fn a_damaged_backup_is_refused() {
+    let dir = temp_dir("damaged");
+    planner(&dir);
+    let file = backup::create(&Store::open(&dir).unwrap(), None, Utc::now()).unwrap();
+
+    let mut changed = file.clone();
+    let last = changed.len() - 2;
+    changed[last] ^= 1;
+    assert!(invalid(backup::read(&changed, None)).contains("checksum does not match"));
+    assert!(invalid(backup::read(&file[..file.len() - 10], None)).contains("checksum"));
+    assert!(invalid(backup::read(b"{\"todos\": []}\n", None)).contains("not a planner backup"));
+    assert!(invalid(backup::read(b"", None)).contains("no header"));
+
+    let newer =
+        String::from_utf8(file.clone())
+            .unwrap()
+            .replacen("\"version\":1", "\"version\":2", 1);
+    assert!(invalid(backup::read(newer.as_bytes(), None)).contains("version 2"));
+
+    // Nothing in the folder was touched
+    assert_eq!(
+        Store::open(&dir).unwrap().load().unwrap().todos[0].title,
+        "Renew passport"
+    );
+}
+
+#[test]
+fn backups_of_an_encrypted_planner_stay_encrypted() {
+    let dir = temp_dir("encrypted");
+    let saved = planner(&dir);
+    let kdf = KdfParams {
+        memory_kib: 64,
+        iterations: 1,
+        parallelism: 1,
+    };
+    vault::enable(&dir, "correct horse", kdf).unwrap();
+    vault::unlock(&dir, "correct horse").unwrap();
+    let store = Store::open(&dir).unwrap();
+    assert!(backup::create(&store, None, Utc::now()).is_err());
+    let file = backup::create(&store, Some("correct horse"), Utc::now()).unwrap();
+    assert!(backup::is_encrypted(&file).unwrap());
+    assert!(!String::from_utf8_lossy(&file).contains("Renew passport"));
+
+    assert!(matches!(
+        backup::read(&file, None),
+        Err(PlannerError::Encryption(_))
+    ));
+    assert!(matches!(
+        backup::read(&file, Some("wrong horse")),
+        Err(PlannerError::Encryption(message)) if message == "wrong passphrase"
+    ));
+
+    // The backup carries its key, so a new folder can be restored from it
+    let read = backup::read(&file, Some("correct horse")).unwrap();
+    let elsewhere = temp_dir("encrypted_elsewhere");
+    backup::restore(&elsewhere, &read, Some("correct horse")).unwrap();
+    assert!(vault::is_locked(&elsewhere).unwrap());
+    vault::unlock(&elsewhere, "correct horse").unwrap();
+    assert_eq!(Store::open(&elsewhere).unwrap().load().unwrap(), saved);
+}
+
+#[test]
+fn exports_from_older_versions_are_imported() {
+    let mut data = PlannerData::default();
+    data.add_todo(NewTodo::new("Water plants", Priority::Low), Utc::now())
+        .unwrap();
+    let now = Utc.with_ymd_and_hms(2024, 3, 4, 9, 0, 0).unwrap();
+    let export = backup::export(&data, now).unwrap();
+    assert!(export.contains(&format!("\"format\": \"{}\"", EXPORT_FORMAT)));
+    assert!(export.contains("\"exported_at\": \"2024-03-04T09:00:00Z\""));
+    assert_eq!(backup::import(&export).unwrap(), data);
+
+    // A data file from before exports had a version, and before routine
+    // checks had a status or budgets existed
+    let old = r#"{
+        "next_id": 2,
+        "todos": [],
+        "routines": [{"id": 1, "name": "Stretch", "start": "07:00:00", "duration_minutes": 10, "frequency": "daily"}],
+        "routine_checks": [{"routine_id": 1, "date": "2024-03-01"}]
+    }"#;
+    let imported = backup::import(old).unwrap();
+    assert_eq!(imported.routines[0].name, "Stretch");
+    assert_eq!(imported.routine_checks[0].status, CheckStatus::Done);
+    assert_eq!(
+        imported.routine_checks[0].date,
+        NaiveDate::from_ymd_opt(2024, 3, 1).unwrap()
+    );
+    assert!(imported.budgets.is_empty());
+
+    let newer = export.replace(
+        &format!("\"version\": {}", EXPORT_VERSION),
+        &format!("\"version\": {}", EXPORT_VERSION + 1),
+    );
+    assert!(invalid(backup::import(&newer)).contains("reads up to"));
+    assert!(invalid(backup::import("{\"format\": \"something else\"}")).contains("not a planner"));
+    assert!(invalid(backup::import("todos")).contains("not JSON"));
+}
@@ -0,0 +1,135 @@
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use smart_planner::budget::{self, BudgetKind, Progress};
+use smart_planner::data::PlannerData;
+use smart_planner::report::{text, Period, Report};
+use smart_planner::stopwatch::Session;
+use smart_planner::time;
+
+fn day(d: u32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(2024, 3, d).unwrap()
+}
+
+fn local(d: u32, hour: u32, minute: u32) -> DateTime<Utc> {
+    time::from_local(day(d).and_hms_opt(hour, minute, 0).unwrap())
+}
+
+fn session(data: &mut PlannerData, subject: &str, start: DateTime<Utc>, minutes: Option<i64>) {
+    let id = data.next_id();
+    data.sessions.push(Session {
+        id,
+        subject: subject.to_string(),
+        start,
+        end: minutes.map(|minutes| start + Duration::minutes(minutes)),
+    });
+}
+
+#[test]
+fn a_change_partway_through_a_week_counts_from_that_day() {
+    let mut data = PlannerData::default();
+    // 7h a week from Wednesday 6 March, then 14h from Friday
+    data.set_budget("Rust", BudgetKind::Target, 7 * 60, day(6))
+        .unwrap();
+    data.set_budget("rust", BudgetKind::Target, 10 * 60, day(8))
+        .unwrap();
+    data.set_budget("rust", BudgetKind::Target, 14 * 60, day(8))
+        .unwrap();
+    assert_eq!(
+        data.budgets.len(),
+        2,
+        "a budget set the same day is replaced"
+    );
+    assert_eq!(data.budgets[0].until, Some(day(8)));
+    assert!(data.set_budget("rust", BudgetKind::Cap, 0, day(8)).is_err());
+
+    // Monday came before any budget, Sunday's session is still running
+    session(&mut data, "rust", local(4, 9, 0), Some(60));
+    session(&mut data, "RUST", local(7, 9, 0), Some(120));
+    session(&mut data, "email", local(7, 11, 0), Some(30));
+    session(&mut data, "rust", local(10, 10, 0), None);
+
+    let week = Period::Week.range(day(10));
+    let live = budget::progress(&data, week, Some(local(10, 10, 30)));
+    assert_eq!(
+        live,
+        vec![Progress {
+            subject: "rust".to_string(),
+            kind: BudgetKind::Target,
+            // Wednesday and Thursday at 1h a day, then 2h a day
+            budget_minutes: 2 * 60 + 3 * 120,
+            spent_minutes: 150,
+        }]
+    );
+    assert_eq!(
+        live[0].to_string(),
+        "rust: 2h 30m of a 8h 00m target (31%), 5h 30m short"
+    );
+
+    // Reports leave the running session out, and the week before had none
+    let report = Report::for_period(&data, Period::Week, day(10));
+    assert_eq!(report.current.budgets[0].spent_minutes, 120);
+    assert!(report.previous.budgets.is_empty());
+    assert!(text::render(&report)
+        .contains("rust (target)  2h 00m  8h 00m   25%         -  6h 00m short\n"));
+
+    // Changing the budget later leaves this week as it was
+    data.set_budget("rust", BudgetKind::Cap, 60, day(11))
+        .unwrap();
+    assert_eq!(Report::for_period(&data, Period::Week, day(10)), report);
+    data.remove_budget("rust", day(12)).unwrap();
+    assert!(data.budget("rust").is_err());
+    let next = budget::progress(&data, Period::Week.range(day(11)), None);
+    assert_eq!((next[0].kind, next[0].budget_minutes), (BudgetKind::Cap, 9));
+}
+
+#[test]
+fn each_threshold_is_announced_once_a_week() {
+    let mut data = PlannerData::default();
+    data.set_budget("meetings", BudgetKind::Cap, 5 * 60, day(4))
+        .unwrap();
+    data.set_budget("rust", BudgetKind::Target, 60, day(4))
+        .unwrap();
+    let thresholds = [80, 100];
+
+    session(&mut data, "meetings", local(5, 9, 0), Some(230));
+    assert!(data
+        .fire_budget_alerts(&thresholds, local(5, 13, 0))
+        .is_empty());
+    session(&mut data, "meetings", local(6, 9, 0), None);
+    let alerts = data.fire_budget_alerts(&thresholds, local(6, 9, 15));
+    assert_eq!(alerts.len(), 1);
+    assert_eq!(
+        alerts[0].to_string(),
+        "meetings is at 80% of its 5h 00m cap this week (4h 05m)"
+    );
+    assert!(data
+        .fire_budget_alerts(&thresholds, local(6, 9, 30))
+        .is_empty());
+    let alerts = data.fire_budget_alerts(&thresholds, local(6, 10, 10));
+    assert_eq!(
+        alerts[0].to_string(),
+        "meetings reached its 5h 00m cap this week (5h 00m)"
+    );
+    data.stop_session(local(6, 11, 0)).unwrap();
+
+    // Going past both at once only tells about the higher one
+    session(&mut data, "rust", local(7, 9, 0), Some(90));
+    let alerts = data.fire_budget_alerts(&thresholds, local(7, 12, 0));
+    assert_eq!(alerts.len(), 1);
+    assert_eq!(alerts[0].threshold, 100);
+    assert!(alerts[0].progress.is_met());
+    assert_eq!(alerts[0].progress.outcome(), "reached");
+    assert_eq!(data.budget("rust").unwrap().alerted.len(), 2);
+
+    // A new week starts over
+    session(&mut data, "meetings", local(11, 9, 0), Some(5 * 60));
+    let alerts = data.fire_budget_alerts(&thresholds, local(11, 15, 0));
+    assert_eq!(alerts[0].threshold, 100);
+    assert_eq!(
+        data.budget("meetings").unwrap().alerted,
+        vec![(day(11), 80), (day(11), 100)]
+    );
+    assert!(
+        alerts[0].progress.is_met(),
+        "exactly the cap is still within it"
+    );
+}
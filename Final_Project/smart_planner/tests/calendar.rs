@@ -0,0 +1,459 @@
+use chrono::{DateTime, Duration, NaiveDate, NaiveTime, TimeZone, Utc};
+use smart_planner::busy::{BusyEvent, BusyTime};
+use smart_planner::data::{NewTodo, PlannerData};
+use smart_planner::ical::{self, ExportOptions, Recurrence};
+use smart_planner::plan::{BlockKind, LocalScheduler, Plan, PlanBlock, PlanRequest, Planner};
+use smart_planner::routine::Frequency;
+use smart_planner::time;
+use smart_planner::todo::Priority;
+
+fn day(month: u32, d: u32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(2024, month, d).unwrap()
+}
+
+fn at(hour: u32, minute: u32) -> NaiveTime {
+    NaiveTime::from_hms_opt(hour, minute, 0).unwrap()
+}
+
+fn utc(month: u32, d: u32, hour: u32, minute: u32) -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2024, month, d, hour, minute, 0)
+        .unwrap()
+}
+
+const NEW_YORK: &str = "BEGIN:VTIMEZONE\r
+TZID:America/New_York\r
+BEGIN:DAYLIGHT\r
+DTSTART:20070311T020000\r
+TZOFFSETFROM:-0500\r
+TZOFFSETTO:-0400\r
+RRULE:FREQ=YEARLY;BYMONTH=3;BYDAY=2SU\r
+TZNAME:EDT\r
+END:DAYLIGHT\r
+BEGIN:STANDARD\r
+DTSTART:20071104T020000\r
+TZOFFSETFROM:-0400\r
+TZOFFSETTO:-0500\r
+RRULE:FREQ=YEARLY;BYMONTH=11;BYDAY=1SU\r
+TZNAME:EST\r
+END:STANDARD\r
+END:VTIMEZONE\r
+";
+
+fn calendar(events: &str) -> String {
+    format!(
+        "BEGIN:VCALENDAR\r\nVERSION:2.0\r\nPRODID:-//Test//EN\r\n{}{}END:VCALENDAR\r\n",
+        NEW_YORK, events
+    )
+}
+
+fn import(events: &str, from: NaiveDate, to: NaiveDate) -> Vec<BusyEvent> {
+    let calendar = ical::parse(&calendar(events)).unwrap();
+    let imported = ical::busy_events(&calendar, from, to).unwrap();
+    assert!(imported.skipped.is_empty(), "{:?}", imported.skipped);
+    imported.events
+}
+
+#[test]
+fn parser_unfolds_lines_and_reads_params() {
+    let text = "BEGIN:VCALENDAR\nBEGIN:VEVENT\r\nSUMMARY:Planning\\, budget\r\n  and \\\"more\\\"\\nsecond line\r\nDTSTART;TZID=\"Europe/Paris: office\";VALUE=DATE-TIME:20240304T090000\r\nEND:VEVENT\r\nEND:VCALENDAR\r\n";
+    let calendar = ical::parse(text).unwrap();
+    let event = calendar.children("VEVENT").next().unwrap();
+    assert_eq!(
+        event.text("SUMMARY").unwrap(),
+        "Planning, budget and \"more\"\nsecond line"
+    );
+    let start = event.property("DTSTART").unwrap();
+    assert_eq!(start.param("TZID"), Some("Europe/Paris: office"));
+    assert_eq!(start.value, "20240304T090000");
+
+    assert!(ical::parse("BEGIN:VCALENDAR\r\nBEGIN:VEVENT\r\nEND:VCALENDAR\r\n").is_err());
+    assert!(ical::parse("SUMMARY:nothing\r\n").is_err());
+}
+
+#[test]
+fn declared_time_zones_follow_daylight_saving() {
+    let events = "BEGIN:VEVENT\r
+UID:before\r
+SUMMARY:Before the switch\r
+DTSTART;TZID=America/New_York:20240308T090000\r
+DTEND;TZID=America/New_York:20240308T100000\r
+END:VEVENT\r
+BEGIN:VEVENT\r
+UID:after\r
+SUMMARY:After the switch\r
+DTSTART;TZID=America/New_York:20240311T090000\r
+DURATION:PT90M\r
+END:VEVENT\r
+BEGIN:VEVENT\r
+UID:utc\r
+SUMMARY:In UTC\r
+DTSTART:20240312T150000Z\r
+DTEND:20240312T153000Z\r
+END:VEVENT\r
+";
+    let events = import(events, day(3, 1), day(3, 31));
+    let times: Vec<&BusyTime> = events.iter().map(|event| &event.time).collect();
+    assert_eq!(
+        times,
+        vec![
+            &BusyTime::Timed {
+                start: utc(3, 8, 14, 0),
+                end: utc(3, 8, 15, 0)
+            },
+            &BusyTime::Timed {
+                start: utc(3, 11, 13, 0),
+                end: utc(3, 11, 14, 30)
+            },
+            &BusyTime::Timed {
+                start: utc(3, 12, 15, 0),
+                end: utc(3, 12, 15, 30)
+            },
+        ]
+    );
+}
+
+#[test]
+fn all_day_events_cover_whole_days() {
+    let events = "BEGIN:VEVENT\r
+UID:trip\r
+SUMMARY:Conference\r
+DTSTART;VALUE=DATE:20240304\r
+DTEND;VALUE=DATE:20240306\r
+END:VEVENT\r
+BEGIN:VEVENT\r
+UID:holiday\r
+SUMMARY:Holiday\r
+DTSTART;VALUE=DATE:20240308\r
+END:VEVENT\r
+BEGIN:VEVENT\r
+UID:birthday\r
+SUMMARY:Birthday\r
+DTSTART;VALUE=DATE:20240307\r
+TRANSP:TRANSPARENT\r
+END:VEVENT\r
+";
+    let events = import(events, day(3, 1), day(3, 31));
+    assert_eq!(events.len(), 2);
+    assert_eq!(
+        events[0].time,
+        BusyTime::AllDay {
+            first: day(3, 4),
+            last: day(3, 5)
+        }
+    );
+    assert_eq!(
+        events[1].time,
+        BusyTime::AllDay {
+            first: day(3, 8),
+            last: day(3, 8)
+        }
+    );
+    assert_eq!(
+        events[0].slot_on(day(3, 5)),
+        Some((at(0, 0), NaiveTime::from_hms_opt(23, 59, 59).unwrap()))
+    );
+    assert_eq!(events[0].slot_on(day(3, 6)), None);
+}
+
+#[test]
+fn repeating_events_are_expanded_with_exceptions() {
+    let events = "BEGIN:VEVENT\r
+UID:standup\r
+SUMMARY:Standup\r
+DTSTART;TZID=America/New_York:20240304T093000\r
+DTEND;TZID=America/New_York:20240304T094500\r
+RRULE:FREQ=WEEKLY;BYDAY=MO,WE;COUNT=6\r
+EXDATE;TZID=America/New_York:20240306T093000\r
+END:VEVENT\r
+BEGIN:VEVENT\r
+UID:standup\r
+RECURRENCE-ID;TZID=America/New_York:20240311T093000\r
+SUMMARY:Standup (moved)\r
+DTSTART;TZID=America/New_York:20240311T110000\r
+DTEND;TZID=America/New_York:20240311T111500\r
+END:VEVENT\r
+BEGIN:VEVENT\r
+UID:review\r
+SUMMARY:Review\r
+DTSTART:20240301T160000Z\r
+DURATION:PT1H\r
+RRULE:FREQ=MONTHLY;BYDAY=-1FR;UNTIL=20240601T000000Z\r
+STATUS:CONFIRMED\r
+END:VEVENT\r
+BEGIN:VEVENT\r
+UID:dropped\r
+SUMMARY:Dropped\r
+DTSTART:20240305T160000Z\r
+DURATION:PT1H\r
+STATUS:CANCELLED\r
+END:VEVENT\r
+";
+    let events = import(events, day(3, 1), day(12, 31));
+    let starts = |uid: &str, summary: &str| -> Vec<DateTime<Utc>> {
+        events
+            .iter()
+            .filter(|event| event.uid == uid && event.summary == summary)
+            .map(|event| match event.time {
+                BusyTime::Timed { start, .. } => start,
+                BusyTime::AllDay { .. } => panic!("not timed"),
+            })
+            .collect()
+    };
+    // Mondays and Wednesdays, six in all, without the excluded and the moved one.
+    // New York is on daylight saving time from 10 March.
+    assert_eq!(
+        starts("standup", "Standup"),
+        vec![
+            utc(3, 4, 14, 30),
+            utc(3, 13, 13, 30),
+            utc(3, 18, 13, 30),
+            utc(3, 20, 13, 30),
+        ]
+    );
+    assert_eq!(
+        starts("standup", "Standup (moved)"),
+        vec![utc(3, 11, 15, 0)]
+    );
+    // The last Friday of each month until June
+    assert_eq!(
+        starts("review", "Review"),
+        vec![utc(3, 29, 16, 0), utc(4, 26, 16, 0), utc(5, 31, 16, 0)]
+    );
+    assert!(events.iter().all(|event| event.uid != "dropped"));
+}
+
+#[test]
+fn recurrence_rules_read_and_write() {
+    let rule: Recurrence = "FREQ=YEARLY;BYMONTH=11;BYDAY=1SU;WKST=MO".parse().unwrap();
+    assert_eq!(rule.to_string(), "FREQ=YEARLY;BYMONTH=11;BYDAY=1SU");
+    let starts = rule
+        .occurrences(
+            day(1, 1).and_time(at(2, 0)),
+            day(12, 31).and_time(at(0, 0)) + Duration::days(365),
+            None,
+        )
+        .unwrap();
+    assert_eq!(
+        starts,
+        vec![
+            day(11, 3).and_time(at(2, 0)),
+            NaiveDate::from_ymd_opt(2025, 11, 2)
+                .unwrap()
+                .and_time(at(2, 0))
+        ]
+    );
+
+    let weekdays = Recurrence::from(&Frequency::Weekdays);
+    assert_eq!(weekdays.to_string(), "FREQ=WEEKLY;BYDAY=MO,TU,WE,TH,FR");
+    assert!("FREQ=HOURLY".parse::<Recurrence>().is_err());
+    assert!("INTERVAL=2".parse::<Recurrence>().is_err());
+    assert!("FREQ=WEEKLY;BYDAY=€".parse::<Recurrence>().is_err());
+    assert!("FREQ=WEEKLY;BYDAY=1€".parse::<Recurrence>().is_err());
+}
+
+#[test]
+fn out_of_range_events_are_skipped() {
+    let event = |uid: &str, lines: &str| {
+        format!(
+            "BEGIN:VEVENT\r\nUID:{}\r\nSUMMARY:{}\r\n{}END:VEVENT\r\n",
+            uid, uid, lines
+        )
+    };
+    let timed = "DTSTART:20240304T090000Z\r\n";
+    let events = [
+        event("weeks", &format!("{}DURATION:P99999999999999W\r\n", timed)),
+        event(
+            "hours",
+            &format!("{}DURATION:PT99999999999999999H\r\n", timed),
+        ),
+        event("sum", &format!("{}DURATION:P1W9999999999999D\r\n", timed)),
+        event(
+            "days",
+            "DTSTART;VALUE=DATE:20240304\r\nDURATION:P99999999D\r\n",
+        ),
+        event(
+            "daily",
+            &format!(
+                "{}DURATION:PT1H\r\nRRULE:FREQ=DAILY;INTERVAL=4000000000\r\n",
+                timed
+            ),
+        ),
+        event(
+            "weekly",
+            &format!(
+                "{}DURATION:PT1H\r\nRRULE:FREQ=WEEKLY;INTERVAL=4000000000\r\n",
+                timed
+            ),
+        ),
+        event(
+            "monthly",
+            &format!(
+                "{}DURATION:PT1H\r\nRRULE:FREQ=MONTHLY;INTERVAL=4000000000\r\n",
+                timed
+            ),
+        ),
+        event(
+            "yearly",
+            &format!(
+                "{}DURATION:PT1H\r\nRRULE:FREQ=YEARLY;INTERVAL=1000000\r\n",
+                timed
+            ),
+        ),
+        event(
+            "byday",
+            &format!("{}DURATION:PT1H\r\nRRULE:FREQ=WEEKLY;BYDAY=€\r\n", timed),
+        ),
+        event("fine", &format!("{}DURATION:PT1H\r\n", timed)),
+    ];
+    let calendar = ical::parse(&calendar(&events.concat())).unwrap();
+    let imported = ical::busy_events(&calendar, day(3, 1), day(3, 31)).unwrap();
+    let skipped: Vec<&str> = imported
+        .skipped
+        .iter()
+        .map(|line| line.split(':').next().unwrap())
+        .collect();
+    assert_eq!(
+        skipped,
+        vec!["weeks", "hours", "sum", "days", "daily", "weekly", "monthly", "yearly", "byday"]
+    );
+    assert_eq!(imported.events.len(), 1);
+    assert_eq!(imported.events[0].uid, "fine");
+
+    // A time zone that repeats too far apart is refused as a whole
+    let zone = NEW_YORK.replace("BYMONTH=3;BYDAY=2SU", "INTERVAL=1000000");
+    let file = format!(
+        "BEGIN:VCALENDAR\r\nVERSION:2.0\r\n{}{}END:VCALENDAR\r\n",
+        zone, events[9]
+    );
+    let calendar = ical::parse(&file).unwrap();
+    assert!(ical::busy_events(&calendar, day(3, 1), day(3, 31)).is_err());
+}
+
+#[test]
+fn plans_leave_busy_time_free() {
+    let mut data = PlannerData::default();
+    let mut report = NewTodo::new("Write report", Priority::High);
+    report.estimate_minutes = Some(60);
+    data.add_todo(report, Utc::now()).unwrap();
+    data.import_busy(vec![BusyEvent {
+        uid: "meeting".to_string(),
+        summary: "Meeting".to_string(),
+        time: BusyTime::Timed {
+            start: time::from_local(day(3, 4).and_time(at(8, 0))),
+            end: time::from_local(day(3, 4).and_time(at(9, 30))),
+        },
+    }]);
+
+    let request = PlanRequest::new(day(3, 4), &data.todos, &data.routines).with_busy(&data.busy);
+    let plan = LocalScheduler::default().generate(&request).unwrap();
+    let blocks: Vec<(NaiveTime, NaiveTime, BlockKind)> = plan
+        .blocks
+        .iter()
+        .map(|block| (block.start, block.end, block.kind))
+        .collect();
+    assert_eq!(
+        blocks,
+        vec![
+            (at(8, 0), at(9, 30), BlockKind::Busy),
+            (at(9, 30), at(10, 30), BlockKind::Task),
+        ]
+    );
+
+    // The next day is free
+    let request = PlanRequest::new(day(3, 5), &data.todos, &data.routines).with_busy(&data.busy);
+    assert!(request.busy.is_empty());
+
+    // Importing the same calendar again replaces its events
+    data.import_busy(vec![]);
+    assert_eq!(data.busy.len(), 1);
+    data.import_busy(vec![data.busy[0].clone(), data.busy[0].clone()]);
+    assert_eq!(data.busy.len(), 2);
+    assert_eq!(data.clear_busy(), 2);
+}
+
+#[test]
+fn export_writes_events_todos_and_alarms() {
+    let now = time::from_local(day(3, 4).and_time(at(7, 0)));
+    let mut data = PlannerData::default();
+    let gym = data
+        .add_routine(
+            "Gym",
+            at(18, 0),
+            60,
+            Frequency::Weekdays,
+            now - Duration::days(1),
+        )
+        .unwrap();
+    let mut report = NewTodo::new(
+        "Write the quarterly report; include the numbers, charts and a long summary",
+        Priority::High,
+    );
+    report.due = Some(time::from_local(day(3, 8).and_time(at(17, 0))));
+    let report = data.add_todo(report, now).unwrap();
+    data.add_todo(NewTodo::new("No due date", Priority::Low), now)
+        .unwrap();
+    data.add_timer("Tea", now + Duration::minutes(5), None, now)
+        .unwrap();
+    let mut plan = Plan::new(day(3, 4), "local");
+    let mut routine = PlanBlock::new(at(18, 0), at(19, 0), "Gym", BlockKind::Routine).unwrap();
+    routine.routine_id = Some(gym);
+    plan.add_block(routine);
+    let mut task = PlanBlock::new(at(9, 0), at(10, 0), "Write report", BlockKind::Task).unwrap();
+    task.todo_id = Some(report);
+    plan.add_block(task);
+    plan.add_block(PlanBlock::new(at(12, 0), at(13, 0), "Lunch meeting", BlockKind::Busy).unwrap());
+    data.set_plan(plan);
+
+    let text = ical::export(&data, ExportOptions::default(), now);
+    assert!(text.starts_with("BEGIN:VCALENDAR\r\nVERSION:2.0\r\n"));
+    assert!(text
+        .lines()
+        .all(|line| line.trim_end_matches('\r').len() <= 75));
+
+    let calendar = ical::parse(&text).unwrap();
+    let events: Vec<_> = calendar.children("VEVENT").collect();
+    let summaries: Vec<String> = events.iter().map(|e| e.text("SUMMARY").unwrap()).collect();
+    // The routine block is exported once, as the repeating routine, and the busy block not at all
+    assert_eq!(summaries, vec!["Write report", "Gym", "Tea"]);
+    assert_eq!(
+        events[1].text("RRULE").unwrap(),
+        "FREQ=WEEKLY;BYDAY=MO,TU,WE,TH,FR"
+    );
+    // Created on Sunday, so the first occurrence is Monday
+    assert_eq!(events[1].text("DTSTART").unwrap(), "20240304T180000");
+    assert_eq!(events[1].text("DURATION").unwrap(), "PT1H");
+    assert_eq!(
+        events[2]
+            .children("VALARM")
+            .next()
+            .unwrap()
+            .text("TRIGGER")
+            .unwrap(),
+        "PT0S"
+    );
+
+    let todos: Vec<_> = calendar.children("VTODO").collect();
+    assert_eq!(todos.len(), 1);
+    assert_eq!(
+        todos[0].text("SUMMARY").unwrap(),
+        "Write the quarterly report; include the numbers, charts and a long summary"
+    );
+    assert_eq!(todos[0].text("PRIORITY").unwrap(), "1");
+    assert_eq!(todos[0].text("STATUS").unwrap(), "NEEDS-ACTION");
+
+    // Reading the file back gives the plan block and the routine as busy time
+    let imported = ical::busy_events(&calendar, day(3, 4), day(3, 5)).unwrap();
+    let slots: Vec<(String, Option<(NaiveTime, NaiveTime)>)> = imported
+        .events
+        .iter()
+        .map(|event| (event.summary.clone(), event.slot_on(day(3, 4))))
+        .collect();
+    assert_eq!(
+        slots,
+        vec![
+            ("Write report".to_string(), Some((at(9, 0), at(10, 0)))),
+            ("Gym".to_string(), Some((at(18, 0), at(19, 0)))),
+            ("Gym".to_string(), None),
+        ]
+    );
+}
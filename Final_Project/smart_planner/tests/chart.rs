@@ -0,0 +1,78 @@
+use chrono::{Duration, NaiveDate};
+use smart_planner::chart::{bars, heatmap, sparkline, Charset};
+
+fn day(month: u32, day: u32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(2024, month, day).unwrap()
+}
+
+fn minutes(minutes: i64) -> String {
+    format!("{}m", minutes)
+}
+
+#[test]
+fn sparklines_scale_to_the_busiest_day() {
+    let values = [0, 10, 40, 80, 5];
+    assert_eq!(sparkline(&values, Charset::Unicode), "▁▂▅█▂");
+    assert_eq!(sparkline(&values, Charset::Ascii), "_.=#.");
+    // Nothing worked at all still draws a line, just a flat one
+    assert_eq!(sparkline(&[0, 0, 0], Charset::Ascii), "___");
+    assert_eq!(sparkline(&[], Charset::Unicode), "");
+}
+
+#[test]
+fn bars_line_up_labels_and_values() {
+    let rows = vec![
+        ("rust".to_string(), 120),
+        ("maths".to_string(), 45),
+        ("art".to_string(), 1),
+        ("idle".to_string(), 0),
+    ];
+    assert_eq!(
+        bars(&rows, 8, Charset::Ascii, minutes),
+        "rust   ########  120m\n\
+         maths  ###       45m\n\
+         art    #         1m\n\
+         idle             0m\n"
+    );
+    // Eighths of a cell at the end, and a sliver for anything above zero
+    let unicode = bars(&rows, 8, Charset::Unicode, minutes);
+    let lines: Vec<&str> = unicode.lines().collect();
+    assert_eq!(lines[0], "rust   ████████  120m");
+    assert_eq!(lines[1], "maths  ███       45m");
+    assert_eq!(lines[2], "art    ▏         1m");
+    assert_eq!(bars(&[], 8, Charset::Ascii, minutes), "");
+}
+
+#[test]
+fn the_heatmap_has_a_column_per_week_and_a_row_per_weekday() {
+    // Wednesday 28 February to Sunday 10 March
+    let days: Vec<(NaiveDate, i64)> = (0..12)
+        .map(|i| (day(2, 28) + Duration::days(i), i * 10))
+        .collect();
+    let map = heatmap(&days, Charset::Ascii);
+    let lines: Vec<&str> = map.lines().collect();
+    assert_eq!(lines[0], "     Feb");
+    assert_eq!(lines[1], "Mon   +");
+    assert_eq!(lines[2], "      *");
+    assert_eq!(lines[3], "Wed  .*");
+    assert_eq!(lines[4], "     -*");
+    assert_eq!(lines[5], "Fri  -#");
+    assert_eq!(lines[6], "     +#");
+    assert_eq!(lines[7], "     +#");
+    assert_eq!(lines[8], "     Less . - + * # More");
+    assert_eq!(heatmap(&[], Charset::Ascii), "");
+
+    // A year fits in 53 weeks, with a label for the months as they start
+    let year: Vec<(NaiveDate, i64)> = (0..365)
+        .map(|i| (day(1, 1) + Duration::days(i), i % 3))
+        .collect();
+    let map = heatmap(&year, Charset::Unicode);
+    let lines: Vec<&str> = map.lines().collect();
+    assert_eq!(lines.len(), 9);
+    assert!(lines[0].starts_with("     Jan"));
+    assert!(lines[0].contains("Jun") && lines[0].contains("Dec"));
+    assert!(lines[1..8]
+        .iter()
+        .all(|line| line.chars().count() <= 5 + 53));
+    assert!(lines[1].contains('▒') && lines[1].contains('█'));
+}
@@ -0,0 +1,299 @@
+use chrono::{Duration, NaiveDate, NaiveTime, Utc};
+use smart_planner::busy::{BusyEvent, BusyTime};
+use smart_planner::data::{NewTodo, PlannerData};
+use smart_planner::plan::conflict::{ConflictKind, Source};
+use smart_planner::plan::{BlockKind, ConflictChecker, Plan, PlanBlock, Resolution};
+use smart_planner::routine::Frequency;
+use smart_planner::time;
+use smart_planner::todo::Priority;
+use smart_planner::PlannerError;
+
+fn at(hour: u32, minute: u32) -> NaiveTime {
+    NaiveTime::from_hms_opt(hour, minute, 0).unwrap()
+}
+
+fn monday() -> NaiveDate {
+    NaiveDate::from_ymd_opt(2024, 3, 4).unwrap()
+}
+
+fn task(data: &mut PlannerData, title: &str, priority: Priority) -> u64 {
+    data.add_todo(NewTodo::new(title, priority), Utc::now())
+        .unwrap()
+}
+
+fn block(
+    start: NaiveTime,
+    end: NaiveTime,
+    title: &str,
+    kind: BlockKind,
+    todo: Option<u64>,
+) -> PlanBlock {
+    let mut block = PlanBlock::new(start, end, title, kind).unwrap();
+    block.todo_id = todo;
+    block
+}
+
+/// Blocks Tidy 09:00-10:00 (low), Meeting 09:30-10:30 (busy), Report
+/// 10:32-11:35 (high) and Call 18:30-18:45, and a Gym routine at 18:00 that
+/// the plan leaves out.
+fn sample_data() -> PlannerData {
+    let mut data = PlannerData::default();
+    let tidy = task(&mut data, "Tidy", Priority::Low);
+    let report = task(&mut data, "Report", Priority::High);
+    data.add_routine("Gym", at(18, 0), 60, Frequency::Daily, Utc::now())
+        .unwrap();
+    let mut plan = Plan::new(monday(), "local");
+    plan.add_block(block(
+        at(9, 0),
+        at(10, 0),
+        "Tidy",
+        BlockKind::Task,
+        Some(tidy),
+    ));
+    plan.add_block(block(
+        at(9, 30),
+        at(10, 30),
+        "Meeting",
+        BlockKind::Busy,
+        None,
+    ));
+    plan.add_block(block(
+        at(10, 32),
+        at(11, 35),
+        "Report",
+        BlockKind::Task,
+        Some(report),
+    ));
+    plan.add_block(block(
+        at(18, 30),
+        at(18, 45),
+        "Call",
+        BlockKind::Custom,
+        None,
+    ));
+    data.set_plan(plan);
+    data
+}
+
+#[test]
+fn finds_overlaps_missing_buffers_and_left_out_routines() {
+    let data = sample_data();
+    let plan = data.plan(monday()).unwrap();
+    let conflicts = ConflictChecker::default().check(plan, &data);
+    let lines: Vec<String> = conflicts.iter().map(|c| c.to_string()).collect();
+    assert_eq!(
+        lines,
+        vec![
+            "block 1 \"Tidy\" (09:00-10:00) overlaps block 2 \"Meeting\" (09:30-10:30) by 30m",
+            "block 3 \"Report\" (10:32-11:35) is only 2m away from block 2 \"Meeting\" (09:30-10:30)",
+            "block 4 \"Call\" (18:30-18:45) overlaps routine \"Gym\" (18:00-19:00) by 15m",
+        ]
+    );
+    assert!(matches!(
+        conflicts[2].other.as_ref().unwrap().source,
+        Source::Routine(_)
+    ));
+
+    // Without a buffer, touching blocks are fine
+    let checker = ConflictChecker {
+        buffer_minutes: 0,
+        ..ConflictChecker::default()
+    };
+    assert_eq!(checker.check(plan, &data).len(), 2);
+}
+
+#[test]
+fn proposes_shift_split_and_move() {
+    let data = sample_data();
+    let plan = data.plan(monday()).unwrap();
+    let checker = ConflictChecker::default();
+    let conflicts = checker.check(plan, &data);
+
+    let options = checker.resolutions(plan, &data, &conflicts[0]);
+    assert_eq!(
+        options,
+        vec![
+            // Pushing Tidy after the meeting pushes Report along
+            Resolution::Shift {
+                moves: vec![(1, at(10, 35)), (3, at(11, 40))]
+            },
+            Resolution::Split {
+                block: 1,
+                end: at(9, 25),
+                rest_start: at(11, 40)
+            },
+            Resolution::Move {
+                block: 1,
+                start: at(11, 40)
+            },
+        ]
+    );
+    assert_eq!(
+        options[0].to_string(),
+        "shift block 1 to 10:35 (and block 3 to 11:40)"
+    );
+
+    let shifted = checker.apply(plan, &data, &options[0]).unwrap();
+    let times: Vec<(NaiveTime, NaiveTime, &str)> = shifted
+        .blocks
+        .iter()
+        .map(|b| (b.start, b.end, b.title.as_str()))
+        .collect();
+    assert_eq!(
+        times,
+        vec![
+            (at(9, 30), at(10, 30), "Meeting"),
+            (at(10, 35), at(11, 35), "Tidy"),
+            (at(11, 40), at(12, 43), "Report"),
+            (at(18, 30), at(18, 45), "Call"),
+        ]
+    );
+
+    let split = checker.apply(plan, &data, &options[1]).unwrap();
+    let tidy: Vec<(NaiveTime, NaiveTime)> = split
+        .blocks
+        .iter()
+        .filter(|b| b.title == "Tidy")
+        .map(|b| (b.start, b.end))
+        .collect();
+    assert_eq!(tidy, vec![(at(9, 0), at(9, 25)), (at(11, 40), at(12, 15))]);
+    assert!(split
+        .blocks
+        .iter()
+        .filter(|b| b.title == "Tidy")
+        .all(|b| b.todo_id == Some(1)));
+
+    // The custom block gives way to the routine, which is not in the plan
+    let options = checker.resolutions(plan, &data, &conflicts[2]);
+    assert_eq!(
+        options,
+        vec![Resolution::Shift {
+            moves: vec![(4, at(19, 5))]
+        }]
+    );
+}
+
+#[test]
+fn task_blocks_must_end_before_their_deadline() {
+    let mut data = sample_data();
+    let mut letter = NewTodo::new("Letter", Priority::Medium);
+    letter.due = Some(time::from_local(monday().and_time(at(15, 0))));
+    let letter = data.add_todo(letter, Utc::now()).unwrap();
+    let post = task(&mut data, "Post office", Priority::Medium);
+    let now = time::from_local(monday().and_time(at(7, 0)));
+    data.add_timer(
+        "Post closes",
+        time::from_local(monday().and_time(at(13, 0))),
+        Some(post),
+        now,
+    )
+    .unwrap();
+    let plan = data.plan_mut(monday()).unwrap();
+    plan.add_block(block(
+        at(14, 30),
+        at(15, 30),
+        "Letter",
+        BlockKind::Task,
+        Some(letter),
+    ));
+    plan.add_block(block(
+        at(12, 30),
+        at(13, 30),
+        "Post office",
+        BlockKind::Task,
+        Some(post),
+    ));
+
+    let checker = ConflictChecker::default();
+    let plan = data.plan(monday()).unwrap();
+    let deadlines: Vec<String> = checker
+        .check(plan, &data)
+        .into_iter()
+        .filter(|c| matches!(c.kind, ConflictKind::PastDeadline { .. }))
+        .map(|c| {
+            let options = checker.resolutions(plan, &data, &c);
+            format!("{} -> {}", c, options[0])
+        })
+        .collect();
+    assert_eq!(
+        deadlines,
+        vec![
+            "block 4 \"Post office\" (12:30-13:30) ends after timer #6 \"Post closes\" runs out at 13:00 -> move block 4 to 11:40",
+            "block 5 \"Letter\" (14:30-15:30) ends after todo #4 is due at 15:00 -> move block 5 to 13:35",
+        ]
+    );
+}
+
+#[test]
+fn resolving_replaces_the_plan_only_when_it_works() {
+    let mut data = sample_data();
+    let before = data.plan(monday()).unwrap().clone();
+    let checker = ConflictChecker::default();
+
+    assert!(matches!(
+        data.resolve_conflict(monday(), &checker, 1, 9),
+        Err(PlannerError::NotFound(_))
+    ));
+    assert!(matches!(
+        data.resolve_conflict(monday(), &checker, 7, 1),
+        Err(PlannerError::NotFound(_))
+    ));
+    // A move onto the meeting is refused as a whole
+    let bad = Resolution::Shift {
+        moves: vec![(3, at(12, 0)), (1, at(9, 45))],
+    };
+    let error = checker.apply(&before, &data, &bad).unwrap_err();
+    assert!(error.to_string().contains("would leave a conflict"));
+    assert_eq!(data.plan(monday()).unwrap(), &before);
+
+    let applied = data.resolve_conflict(monday(), &checker, 1, 3).unwrap();
+    assert_eq!(
+        applied,
+        Resolution::Move {
+            block: 1,
+            start: at(11, 40)
+        }
+    );
+    let plan = data.plan(monday()).unwrap();
+    assert!(plan.edited);
+    // The report is still too close to the meeting, and the call clashes with the gym
+    assert_eq!(checker.check(plan, &data).len(), 2);
+    data.resolve_conflict(monday(), &checker, 1, 1).unwrap();
+    data.resolve_conflict(monday(), &checker, 1, 1).unwrap();
+    assert!(checker
+        .check(data.plan(monday()).unwrap(), &data)
+        .is_empty());
+}
+
+#[test]
+fn calendar_events_that_clash_cannot_be_moved() {
+    let mut data = PlannerData::default();
+    let event = |uid: &str, start: u32, end: u32| BusyEvent {
+        uid: uid.to_string(),
+        summary: uid.to_string(),
+        time: BusyTime::Timed {
+            start: time::from_local(monday().and_time(at(start, 0))),
+            end: time::from_local(monday().and_time(at(end, 0))),
+        },
+    };
+    data.import_busy(vec![event("Standup", 9, 10), event("Review", 9, 11)]);
+    let mut plan = Plan::new(monday(), "local");
+    plan.add_block(block(at(9, 0), at(10, 0), "Standup", BlockKind::Busy, None));
+    data.set_plan(plan);
+
+    let checker = ConflictChecker::default();
+    let plan = data.plan(monday()).unwrap();
+    let conflicts = checker.check(plan, &data);
+    assert_eq!(conflicts.len(), 1);
+    // Neither can move, so the later one is named as giving way
+    assert_eq!(conflicts[0].item.source, Source::Busy);
+    assert_eq!(
+        conflicts[0].item.end - conflicts[0].item.start,
+        Duration::hours(2)
+    );
+    assert_eq!(
+        conflicts[0].other.as_ref().unwrap().source,
+        Source::Block(1)
+    );
+    assert!(checker.resolutions(plan, &data, &conflicts[0]).is_empty());
+}
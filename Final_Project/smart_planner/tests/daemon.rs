@@ -0,0 +1,273 @@
+use std::env;
+use std::fs;
+use std::net::TcpListener;
+use std::os::unix::net::UnixListener;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use std::time::{Duration as StdDuration, Instant};
+
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use smart_planner::daemon::clock::ManualClock;
+use smart_planner::daemon::{self, socket, Daemon, Request};
+use smart_planner::data::{NewTodo, PlannerData};
+use smart_planner::notify::Notifier;
+use smart_planner::store::Store;
+use smart_planner::time;
+use smart_planner::todo::Priority;
+use smart_planner::webhook::{EventKind, Sender};
+use smart_planner::PlannerError;
+
+/// A fresh folder under the system temp folder.
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("smart_planner_daemon_{}_{}", name, process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn monday(hour: u32, minute: u32) -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2024, 3, 4, hour, minute, 0).unwrap()
+}
+
+/// Keeps what it was asked to show.
+#[derive(Clone, Default)]
+struct Recorder(Arc<Mutex<Vec<String>>>);
+
+impl Notifier for Recorder {
+    fn notify(&self, title: &str, message: &str) {
+        self.0
+            .lock()
+            .unwrap()
+            .push(format!("{}: {}", title, message));
+    }
+}
+
+impl Recorder {
+    fn take(&self) -> Vec<String> {
+        self.0.lock().unwrap().drain(..).collect()
+    }
+}
+
+/// Changes the data the way another planner command would.
+fn change(dir: &Path, work: impl FnOnce(&mut PlannerData)) {
+    let store = Store::open(dir).unwrap();
+    let mut data = store.load().unwrap();
+    work(&mut data);
+    store.save(&data).unwrap();
+}
+
+fn due_todo(title: &str, due: DateTime<Utc>) -> NewTodo {
+    let mut todo = NewTodo::new(title, Priority::High);
+    todo.due = Some(due);
+    todo
+}
+
+#[test]
+fn timers_and_due_todos_go_off_once_each() {
+    let dir = temp_dir("reminders");
+    change(&dir, |data| {
+        data.add_todo(due_todo("Long overdue", monday(6, 0)), monday(5, 0))
+            .unwrap();
+        data.add_todo(due_todo("Write report", monday(12, 0)), monday(8, 0))
+            .unwrap();
+        data.add_timer("Tea", monday(9, 5), None, monday(9, 0))
+            .unwrap();
+    });
+    let clock = Arc::new(ManualClock::new(monday(9, 0)));
+    let recorder = Recorder::default();
+    let mut daemon = Daemon::new(
+        Store::open(&dir).unwrap(),
+        clock.clone(),
+        Box::new(recorder.clone()),
+    );
+
+    // What passed before the daemon ever ran is only noted
+    assert!(daemon.tick().unwrap().is_empty());
+    assert_eq!(daemon.next().unwrap().at, monday(9, 5));
+    let status = daemon.status();
+    assert_eq!((status.timers, status.reminders), (1, 2));
+
+    clock.set(monday(9, 5));
+    daemon.tick().unwrap();
+    assert_eq!(recorder.take(), vec!["Time is up: #3 Tea"]);
+    assert!(daemon.tick().unwrap().is_empty());
+    let saved = Store::open(&dir).unwrap().load().unwrap();
+    assert!(saved.timers[0].fired, "the daemon saves fired timers");
+
+    assert_eq!(daemon.next().unwrap().at, monday(11, 45));
+    clock.set(monday(11, 45));
+    daemon.tick().unwrap();
+    assert_eq!(
+        recorder.take(),
+        vec![format!(
+            "Due soon: #2 Write report is due at {} (in 15m)",
+            time::format_local(monday(12, 0))
+        )]
+    );
+
+    // A restart does not repeat what was sent
+    let mut daemon = Daemon::new(
+        Store::open(&dir).unwrap(),
+        clock.clone(),
+        Box::new(recorder.clone()),
+    );
+    assert!(daemon.tick().unwrap().is_empty());
+    clock.set(monday(12, 30));
+    daemon.tick().unwrap();
+    assert_eq!(recorder.take(), vec!["Due now: #2 Write report"]);
+
+    // A new due time is a new deadline, and a down daemon catches up with
+    // only the latest reminder
+    change(&dir, |data| {
+        data.todo_mut(2).unwrap().due = Some(monday(13, 0))
+    });
+    clock.set(monday(13, 10));
+    daemon.tick().unwrap();
+    assert_eq!(recorder.take(), vec!["Due now: #2 Write report"]);
+    assert!(daemon.next().is_none());
+    assert_eq!(daemon.status().notified, 2, "counted since it started");
+}
+
+#[test]
+fn the_daemon_reloads_when_the_data_changes() {
+    let dir = temp_dir("reload");
+    let clock = Arc::new(ManualClock::new(monday(9, 0)));
+    let recorder = Recorder::default();
+    let mut daemon = Daemon::new(
+        Store::open(&dir).unwrap(),
+        clock.clone(),
+        Box::new(recorder.clone()),
+    );
+    daemon.tick().unwrap();
+    assert!(daemon.next().is_none());
+
+    change(&dir, |data| {
+        data.add_timer("Stretch", monday(9, 30), None, monday(9, 0))
+            .unwrap();
+    });
+    daemon.tick().unwrap();
+    assert_eq!(daemon.next().unwrap().message, "#1 Stretch");
+
+    // Cancelled elsewhere before it ran out
+    change(&dir, |data| {
+        data.cancel_timer(1).unwrap();
+    });
+    clock.set(monday(10, 0));
+    assert!(daemon.tick().unwrap().is_empty());
+    assert!(recorder.take().is_empty());
+    assert_eq!(daemon.status().timers, 0);
+}
+
+/// Waits up to two seconds for `check` to hold.
+fn eventually(mut check: impl FnMut() -> bool) {
+    let start = Instant::now();
+    while !check() {
+        assert!(
+            start.elapsed() < StdDuration::from_secs(2),
+            "gave up waiting"
+        );
+        thread::sleep(StdDuration::from_millis(10));
+    }
+}
+
+#[test]
+fn a_running_daemon_answers_on_its_socket() {
+    let dir = temp_dir("socket");
+    let path = daemon::socket_path(&dir);
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    assert!(matches!(
+        socket::send(&path, Request::Status),
+        Err(PlannerError::NotFound(_))
+    ));
+
+    // Left behind by a daemon that did not stop cleanly
+    drop(UnixListener::bind(&path).unwrap());
+    assert!(path.exists());
+
+    let clock = Arc::new(ManualClock::new(monday(9, 0)));
+    let recorder = Recorder::default();
+    let (calls, incoming) = mpsc::channel();
+    let listener = socket::listen(&path, calls.clone()).unwrap();
+    assert!(
+        socket::listen(&path, calls).is_err(),
+        "one daemon per folder"
+    );
+    let running = {
+        let (dir, clock, recorder) = (dir.clone(), clock.clone(), recorder.clone());
+        thread::spawn(move || {
+            let daemon = Daemon::new(Store::open(&dir).unwrap(), clock, Box::new(recorder));
+            daemon.run(incoming).unwrap();
+        })
+    };
+
+    let status = socket::send(&path, Request::Status).unwrap();
+    assert_eq!(status.pid, process::id());
+    assert_eq!(status.started_at, monday(9, 0));
+    assert!(status.next.is_none());
+
+    // A save tells the daemon, which does not look at the files meanwhile
+    change(&dir, |data| {
+        data.add_timer("Tea", monday(9, 4), None, monday(9, 0))
+            .unwrap();
+    });
+    eventually(|| socket::send(&path, Request::Status).unwrap().timers == 1);
+    let status = socket::send(&path, Request::Reload).unwrap();
+    assert_eq!(status.timers, 1);
+    assert_eq!(status.next.unwrap().at, monday(9, 4));
+
+    // The manual clock does not wake it, a request does
+    clock.advance(Duration::minutes(5));
+    socket::send(&path, Request::Status).unwrap();
+    eventually(|| recorder.0.lock().unwrap().len() == 1);
+    assert_eq!(recorder.take(), vec!["Time is up: #1 Tea"]);
+    assert_eq!(socket::send(&path, Request::Status).unwrap().notified, 1);
+
+    assert!(socket::send(&path, Request::Stop).unwrap().stopping);
+    running.join().unwrap();
+    listener.close();
+    assert!(!path.exists());
+    assert!(matches!(
+        socket::send(&path, Request::Status),
+        Err(PlannerError::NotFound(_))
+    ));
+}
+
+#[test]
+fn webhooks_are_posted_without_holding_up_the_daemon() {
+    let dir = temp_dir("webhooks");
+    // Nothing listens there, so every attempt fails and is tried again
+    let closed = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}/hook", closed.local_addr().unwrap());
+    drop(closed);
+    let config = serde_json::json!({
+        "webhooks": [{ "url": url, "retries": 1, "retry_delay_ms": 1500 }]
+    });
+    fs::write(dir.join("config.json"), config.to_string()).unwrap();
+    change(&dir, |data| {
+        data.add_todo(due_todo("Long overdue", monday(6, 0)), monday(5, 0))
+            .unwrap();
+    });
+    let mut daemon = Daemon::new(
+        Store::open(&dir).unwrap(),
+        Arc::new(ManualClock::new(monday(9, 0))),
+        Box::new(Recorder::default()),
+    );
+
+    let start = Instant::now();
+    daemon.tick().unwrap();
+    assert!(start.elapsed() < StdDuration::from_secs(1));
+    // The first attempt fails at once on a closed port
+    thread::sleep(StdDuration::from_millis(200));
+
+    // Stopping does not wait out the retry, it leaves it to the dead letters
+    let start = Instant::now();
+    daemon.finish_deliveries();
+    assert!(start.elapsed() < StdDuration::from_secs(1));
+    let letters = Sender::new(&dir).dead_letters().unwrap();
+    assert_eq!(letters.len(), 1);
+    assert_eq!(letters[0].attempts, 1);
+    assert!(letters[0].error.ends_with("stopped before trying again"));
+    assert_eq!(letters[0].event.event, EventKind::TodoOverdue);
+}
@@ -0,0 +1,167 @@
+use chrono::{DateTime, Duration, NaiveDate, NaiveTime, Utc};
+use ratatui::backend::TestBackend;
+use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use ratatui::Terminal;
+use smart_planner::dashboard::tui::{action_for, draw};
+use smart_planner::dashboard::{render_plain, Action, Dashboard, Snapshot};
+use smart_planner::data::{NewTodo, PlannerData};
+use smart_planner::plan::{BlockKind, Plan, PlanBlock};
+use smart_planner::time;
+use smart_planner::todo::Priority;
+
+fn at(hour: u32, minute: u32) -> NaiveTime {
+    NaiveTime::from_hms_opt(hour, minute, 0).unwrap()
+}
+
+fn date() -> NaiveDate {
+    NaiveDate::from_ymd_opt(2024, 3, 4).unwrap()
+}
+
+/// 09:45 local time on Monday 4 March 2024.
+fn now() -> DateTime<Utc> {
+    time::from_local(date().and_time(at(9, 45)))
+}
+
+fn sample_data() -> PlannerData {
+    let now = now();
+    let mut data = PlannerData::default();
+    let mut report = NewTodo::new("Write report", Priority::High);
+    report.due = Some(now + Duration::hours(3));
+    report.subject = Some("work".to_string());
+    data.add_todo(report, now).unwrap();
+    data.add_todo(NewTodo::new("Call the bank", Priority::High), now)
+        .unwrap();
+    data.add_todo(NewTodo::new("Water plants", Priority::Low), now)
+        .unwrap();
+    data.add_timer("Tea", now + Duration::minutes(5), None, now)
+        .unwrap();
+
+    let mut plan = Plan::new(date(), "local");
+    plan.add_block(PlanBlock::new(at(8, 0), at(9, 0), "Gym", BlockKind::Routine).unwrap());
+    plan.add_block(PlanBlock::new(at(9, 30), at(10, 30), "Write report", BlockKind::Task).unwrap());
+    plan.add_block(PlanBlock::new(at(12, 0), at(13, 0), "Team lunch", BlockKind::Busy).unwrap());
+    data.set_plan(plan);
+    data
+}
+
+fn screen(snapshot: &Snapshot, dashboard: &Dashboard, width: u16, height: u16) -> String {
+    let mut terminal = Terminal::new(TestBackend::new(width, height)).unwrap();
+    terminal
+        .draw(|frame| draw(frame, snapshot, dashboard))
+        .unwrap();
+    let buffer = terminal.backend().buffer();
+    let mut text = String::new();
+    for y in 0..height {
+        for x in 0..width {
+            text.push_str(buffer[(x, y)].symbol());
+        }
+        text.push('\n');
+    }
+    text
+}
+
+#[test]
+fn snapshot_picks_todays_plan_and_urgent_todos() {
+    let mut data = sample_data();
+    data.start_session("rust", now() - Duration::minutes(25))
+        .unwrap();
+    let snapshot = Snapshot::new(&data, now());
+    assert_eq!(snapshot.date, date());
+    assert_eq!(snapshot.current_block(), Some(1));
+    let titles: Vec<&str> = snapshot.todos.iter().map(|t| t.title.as_str()).collect();
+    // High priority only, the one with a due time first
+    assert_eq!(titles, vec!["Write report", "Call the bank"]);
+    assert_eq!(snapshot.timers.len(), 1);
+
+    let text = render_plain(&snapshot);
+    assert!(text.starts_with("Smart Planner  Mon 2024-03-04\nNow 09:45\n"));
+    assert!(text.contains("  08:00-09:00  routine  Gym\n"));
+    assert!(text.contains("> 09:30-10:30  task     Write report\n"));
+    assert!(text.contains("Running on rust for 25m (since 09:20)"));
+    assert!(text.contains("#4 Tea  at 09:50 (in 5m)"));
+    assert!(text.contains("  #2 Call the bank\n"));
+    assert!(!text.contains("Water plants"));
+
+    let empty = render_plain(&Snapshot::new(&PlannerData::default(), now()));
+    assert!(empty.contains("No plan yet, run `plan generate`."));
+    assert!(empty.contains("Not running."));
+    assert!(empty.contains("None running."));
+    assert!(empty.contains("Nothing urgent."));
+}
+
+#[test]
+fn keys_start_and_stop_the_stopwatch_and_complete_todos() {
+    let mut data = sample_data();
+    let mut dashboard = Dashboard::default();
+    let key = |code| action_for(KeyEvent::new(code, KeyModifiers::NONE));
+    assert_eq!(key(KeyCode::Char('s')), Some(Action::ToggleStopwatch));
+    assert_eq!(key(KeyCode::Enter), Some(Action::Complete));
+    assert_eq!(key(KeyCode::Char('x')), None);
+    assert_eq!(
+        action_for(KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL)),
+        Some(Action::Quit)
+    );
+
+    // The selected todo's subject is what gets timed
+    assert!(dashboard.apply(Action::ToggleStopwatch, &mut data, now()));
+    assert_eq!(data.running_session().unwrap().subject, "work");
+    assert!(dashboard.apply(
+        Action::ToggleStopwatch,
+        &mut data,
+        now() + Duration::minutes(30)
+    ));
+    assert!(data.running_session().is_none());
+    assert_eq!(dashboard.status.as_deref(), Some("Recorded 30m on work"));
+
+    // Moving stops at the ends of the list
+    assert!(!dashboard.apply(Action::Down, &mut data, now()));
+    assert!(!dashboard.apply(Action::Down, &mut data, now()));
+    assert_eq!(dashboard.selected, 1);
+    assert!(dashboard.apply(Action::ToggleStopwatch, &mut data, now()));
+    assert_eq!(data.running_session().unwrap().subject, "Call the bank");
+
+    assert!(dashboard.apply(Action::Complete, &mut data, now()));
+    assert!(data.todo(2).unwrap().done);
+    assert_eq!(dashboard.selected, 0);
+    assert!(dashboard.apply(Action::Complete, &mut data, now()));
+    assert!(data.todo(1).unwrap().done);
+
+    // Nothing left: the refusal is shown, not returned
+    assert!(!dashboard.apply(Action::Complete, &mut data, now()));
+    assert_eq!(
+        dashboard.status.as_deref(),
+        Some("error: invalid input: there is no todo to complete")
+    );
+}
+
+#[test]
+fn layout_follows_the_terminal_size() {
+    let mut data = sample_data();
+    data.start_session("rust", now() - Duration::minutes(25))
+        .unwrap();
+    let snapshot = Snapshot::new(&data, now());
+    let dashboard = Dashboard {
+        selected: 1,
+        status: Some("Completed #7 Tidy desk".to_string()),
+    };
+
+    let wide = screen(&snapshot, &dashboard, 120, 24);
+    let lines: Vec<&str> = wide.lines().collect();
+    assert!(lines[0].starts_with("Smart Planner  Mon 2024-03-04  09:45"));
+    // Side by side: the plan and the stopwatch share a row
+    assert!(lines[1].contains("Today's plan") && lines[1].contains("Stopwatch"));
+    assert!(wide.contains("▶ 09:30-10:30  Write report"));
+    assert!(wide.contains("> #2 Call the bank"));
+    assert!(wide.contains("Focus") && wide.contains("14 days"));
+    assert!(lines[23].starts_with("Completed #7 Tidy desk  |  ↑/↓ select"));
+
+    // Stacked on a narrow terminal
+    let narrow = screen(&snapshot, &dashboard, 60, 40);
+    let lines: Vec<&str> = narrow.lines().collect();
+    assert!(lines[1].contains("Today's plan") && !lines[1].contains("Stopwatch"));
+    assert!(narrow.contains("Stopwatch"));
+    assert!(narrow.contains("Running on rust"));
+
+    // Too small to show much, but drawing must not fail
+    screen(&snapshot, &dashboard, 10, 3);
+}
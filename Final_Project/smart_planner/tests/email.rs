@@ -0,0 +1,253 @@
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use chrono::NaiveDate;
+use smart_planner::data::PlannerData;
+use smart_planner::email::{build_message, Mailer, ReportEmail, Security, SmtpConfig};
+use smart_planner::report::{Period, Report};
+use smart_planner::PlannerError;
+
+/// What the stand-in server saw of one delivered message.
+#[derive(Debug, Clone, Default)]
+struct Captured {
+    auth: Option<String>,
+    from: String,
+    to: Vec<String>,
+    data: String,
+}
+
+/// How the stand-in server should behave.
+#[derive(Clone, Copy, Default)]
+struct Behaviour {
+    /// Answer the first N connections with `421` and hang up.
+    busy_connections: usize,
+    /// Reject every recipient with `550`.
+    reject_recipients: bool,
+}
+
+/// A tiny SMTP server on localhost that stores what it receives.
+struct FakeSmtp {
+    port: u16,
+    messages: Arc<Mutex<Vec<Captured>>>,
+    connections: Arc<Mutex<usize>>,
+}
+
+impl FakeSmtp {
+    fn start(behaviour: Behaviour) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let messages = Arc::new(Mutex::new(Vec::new()));
+        let connections = Arc::new(Mutex::new(0));
+        let (stored, counter) = (messages.clone(), connections.clone());
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let stream = stream.unwrap();
+                let number = {
+                    let mut count = counter.lock().unwrap();
+                    *count += 1;
+                    *count
+                };
+                serve(
+                    stream,
+                    number <= behaviour.busy_connections,
+                    behaviour,
+                    &stored,
+                );
+            }
+        });
+        FakeSmtp {
+            port,
+            messages,
+            connections,
+        }
+    }
+
+    fn config(&self) -> SmtpConfig {
+        let mut config = SmtpConfig::plain("127.0.0.1", self.port, "Planner <planner@example.com>");
+        config.retry_delay_ms = 10;
+        config
+    }
+}
+
+fn serve(stream: TcpStream, busy: bool, behaviour: Behaviour, stored: &Mutex<Vec<Captured>>) {
+    let mut writer = stream.try_clone().unwrap();
+    let mut reader = BufReader::new(stream);
+    if busy {
+        writer
+            .write_all(b"421 localhost busy, try later\r\n")
+            .unwrap();
+        return;
+    }
+    writer.write_all(b"220 localhost ready\r\n").unwrap();
+    let mut message = Captured::default();
+    let mut line = String::new();
+    loop {
+        line.clear();
+        if reader.read_line(&mut line).unwrap_or(0) == 0 {
+            return;
+        }
+        let command = line.trim_end().to_string();
+        let upper = command.to_uppercase();
+        let reply: &[u8] = if upper.starts_with("EHLO") {
+            b"250-localhost\r\n250-AUTH PLAIN LOGIN\r\n250 8BITMIME\r\n"
+        } else if upper.starts_with("AUTH") {
+            message.auth = Some(command.clone());
+            b"235 ok\r\n"
+        } else if upper.starts_with("MAIL FROM") {
+            message.from = command[10..].to_string();
+            b"250 ok\r\n"
+        } else if upper.starts_with("RCPT TO") {
+            if behaviour.reject_recipients {
+                b"550 no such user\r\n"
+            } else {
+                message.to.push(command[8..].to_string());
+                b"250 ok\r\n"
+            }
+        } else if upper == "DATA" {
+            writer.write_all(b"354 go ahead\r\n").unwrap();
+            loop {
+                line.clear();
+                reader.read_line(&mut line).unwrap();
+                if line == ".\r\n" {
+                    break;
+                }
+                message.data.push_str(&line);
+            }
+            stored.lock().unwrap().push(message.clone());
+            b"250 queued\r\n"
+        } else if upper == "QUIT" {
+            writer.write_all(b"221 bye\r\n").unwrap();
+            return;
+        } else {
+            b"250 ok\r\n"
+        };
+        writer.write_all(reply).unwrap();
+    }
+}
+
+fn sample_email(attach_csv: bool) -> ReportEmail {
+    let date = NaiveDate::from_ymd_opt(2024, 3, 6).unwrap();
+    let report = Report::for_period(&PlannerData::default(), Period::Week, date);
+    ReportEmail::from_report(&report, attach_csv)
+}
+
+#[test]
+fn sends_multipart_report_with_csv_attachment() {
+    let server = FakeSmtp::start(Behaviour::default());
+    let mut config = server.config();
+    config.username = Some("alice".to_string());
+    config.password = Some("secret".to_string());
+    let mailer = Mailer::new(config).unwrap();
+
+    let to = vec!["Bob <bob@example.com>".to_string()];
+    let attempts = mailer.send(&to, &sample_email(true)).unwrap();
+    assert_eq!(attempts, 1);
+
+    let messages = server.messages.lock().unwrap();
+    assert_eq!(messages.len(), 1);
+    let message = &messages[0];
+    assert_eq!(
+        message.auth.as_deref(),
+        Some("AUTH PLAIN AGFsaWNlAHNlY3JldA==")
+    );
+    assert_eq!(message.from, "<planner@example.com>");
+    assert_eq!(message.to, vec!["<bob@example.com>"]);
+
+    let data = &message.data;
+    assert!(data.contains("Subject: Smart Planner report for week 2024-03-04 .. 2024-03-10"));
+    assert!(data.contains("Content-Type: multipart/mixed"));
+    assert!(data.contains("Content-Type: multipart/alternative"));
+    assert!(data.contains("Content-Type: text/plain"));
+    assert!(data.contains("Content-Type: text/html"));
+    assert!(data.contains("Content-Disposition: attachment; filename=\"report.csv\""));
+    assert!(data.contains("Content-Type: text/csv"));
+}
+
+#[test]
+fn without_csv_the_message_is_only_text_and_html() {
+    let config = SmtpConfig::plain("localhost", 25, "planner@example.com");
+    let message = build_message(
+        &config,
+        &["bob@example.com".to_string()],
+        &sample_email(false),
+    )
+    .unwrap();
+    let text = String::from_utf8(message.formatted()).unwrap();
+    assert!(text.contains("Content-Type: multipart/alternative"));
+    assert!(!text.contains("multipart/mixed"));
+    assert!(!text.contains("report.csv"));
+}
+
+#[test]
+fn retries_transient_failures() {
+    let server = FakeSmtp::start(Behaviour {
+        busy_connections: 2,
+        ..Behaviour::default()
+    });
+    let mailer = Mailer::new(server.config()).unwrap();
+    let attempts = mailer
+        .send(&["bob@example.com".to_string()], &sample_email(false))
+        .unwrap();
+    assert_eq!(attempts, 3);
+    assert_eq!(server.messages.lock().unwrap().len(), 1);
+}
+
+#[test]
+fn gives_up_after_the_configured_retries() {
+    let server = FakeSmtp::start(Behaviour {
+        busy_connections: 10,
+        ..Behaviour::default()
+    });
+    let mut config = server.config();
+    config.retries = 1;
+    let mailer = Mailer::new(config).unwrap();
+    let result = mailer.send(&["bob@example.com".to_string()], &sample_email(false));
+    assert!(matches!(result, Err(PlannerError::Email(_))));
+    assert_eq!(*server.connections.lock().unwrap(), 2);
+}
+
+#[test]
+fn does_not_retry_permanent_failures() {
+    let server = FakeSmtp::start(Behaviour {
+        reject_recipients: true,
+        ..Behaviour::default()
+    });
+    let mailer = Mailer::new(server.config()).unwrap();
+    let result = mailer.send(&["nobody@example.com".to_string()], &sample_email(false));
+    match result {
+        Err(PlannerError::Email(message)) => assert!(message.contains("1 attempt")),
+        other => panic!("expected an email error, got {:?}", other),
+    }
+    assert_eq!(*server.connections.lock().unwrap(), 1);
+}
+
+#[test]
+fn starttls_is_required_when_configured() {
+    // The stand-in server does not offer STARTTLS, so nothing may be sent in clear text
+    let server = FakeSmtp::start(Behaviour::default());
+    let mut config = server.config();
+    config.security = Security::StartTls;
+    config.username = Some("alice".to_string());
+    config.password = Some("secret".to_string());
+    let mailer = Mailer::new(config).unwrap();
+    let result = mailer.send(&["bob@example.com".to_string()], &sample_email(false));
+    assert!(matches!(result, Err(PlannerError::Email(_))));
+    assert_eq!(*server.connections.lock().unwrap(), 1);
+    assert!(server.messages.lock().unwrap().is_empty());
+}
+
+#[test]
+fn rejects_bad_addresses_and_missing_recipients() {
+    let config = SmtpConfig::plain("localhost", 25, "planner@example.com");
+    let email = sample_email(false);
+    assert!(matches!(
+        build_message(&config, &[], &email),
+        Err(PlannerError::Invalid(_))
+    ));
+    assert!(matches!(
+        build_message(&config, &["not an address".to_string()], &email),
+        Err(PlannerError::Invalid(_))
+    ));
+}
@@ -0,0 +1,101 @@
+use chrono::{NaiveDate, NaiveTime, TimeZone, Utc};
+use smart_planner::data::PlannerData;
+use smart_planner::routine::habit::{Day, Habit, Streaks};
+use smart_planner::routine::CheckStatus;
+
+fn date(day: u32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(2024, 3, day).unwrap()
+}
+
+/// A planner with one routine on `days`, created on Monday 4 March 2024.
+fn planner(days: &str) -> PlannerData {
+    let mut data = PlannerData::default();
+    let created = Utc.with_ymd_and_hms(2024, 3, 4, 12, 0, 0).unwrap();
+    let at = NaiveTime::from_hms_opt(7, 0, 0).unwrap();
+    data.add_routine("Run", at, 30, days.parse().unwrap(), created)
+        .unwrap();
+    data
+}
+
+#[test]
+fn weekends_do_not_break_a_weekdays_streak() {
+    let mut weekdays = planner("weekdays");
+    let mut daily = planner("daily");
+    for day in [4, 5, 6, 7, 8, 11] {
+        weekdays.check_routine(1, date(day)).unwrap();
+        daily.check_routine(1, date(day)).unwrap();
+    }
+    assert!(weekdays.check_routine(1, date(9)).is_err());
+
+    // Tuesday, not checked yet: the streak is still on
+    let habit = Habit::new(&weekdays.routines[0], &weekdays.routine_checks, date(12));
+    assert_eq!(
+        habit.streaks(),
+        Streaks {
+            current: 6,
+            longest: 6
+        }
+    );
+    assert_eq!(habit.day(date(10)), Day::Off);
+    assert_eq!(habit.day(date(12)), Day::Pending);
+    assert_eq!(
+        habit.weekly(2),
+        vec![(date(4), Some(100.0)), (date(11), Some(100.0))]
+    );
+
+    // The same days break a daily routine on Saturday
+    let habit = Habit::new(&daily.routines[0], &daily.routine_checks, date(12));
+    assert_eq!(
+        habit.streaks(),
+        Streaks {
+            current: 1,
+            longest: 5
+        }
+    );
+
+    // Once Tuesday has gone by unchecked it is missed
+    let habit = Habit::new(&weekdays.routines[0], &weekdays.routine_checks, date(13));
+    assert_eq!(habit.day(date(12)), Day::Missed);
+    assert_eq!(habit.streaks().current, 0);
+    assert_eq!(habit.weekly(1), vec![(date(11), Some(50.0))]);
+}
+
+#[test]
+fn partial_days_keep_a_streak_and_skipped_ones_end_it() {
+    let mut data = planner("mon,wed,fri");
+    data.mark_routine(1, date(4), CheckStatus::Done).unwrap();
+    data.mark_routine(1, date(6), CheckStatus::Skipped).unwrap();
+    // Checking a day again replaces what was recorded
+    data.mark_routine(1, date(6), CheckStatus::Partial).unwrap();
+    data.mark_routine(1, date(11), CheckStatus::Skipped)
+        .unwrap();
+    assert_eq!(data.routine_checks.len(), 3);
+
+    let habit = Habit::new(&data.routines[0], &data.routine_checks, date(13));
+    assert_eq!(
+        habit.streaks(),
+        Streaks {
+            current: 0,
+            longest: 2
+        }
+    );
+    // Done, half, missed on Friday and skipped; today does not count yet
+    assert_eq!(habit.adherence(date(1), date(13)), Some(37.5));
+    assert_eq!(habit.adherence(date(14), date(20)), None);
+
+    assert_eq!(
+        habit.heatmap(2),
+        [
+            "    Mar",
+            "Mon # -",
+            "Tue",
+            "Wed + o",
+            "Thu",
+            "Fri .",
+            "Sat",
+            "Sun",
+            "# done  + partial  - skipped  . missed  o today, not checked yet",
+        ]
+        .join("\n")
+    );
+}
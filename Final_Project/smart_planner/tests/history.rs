@@ -0,0 +1,289 @@
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::process;
+
+use chrono::{DateTime, Duration, NaiveDate, TimeZone, Utc};
+use smart_planner::data::{NewTodo, PlannerData};
+use smart_planner::history::{Change, History, Kind, ELSEWHERE, SNAPSHOT_EVERY};
+use smart_planner::plan::Plan;
+use smart_planner::storage::{JsonStorage, Storage};
+use smart_planner::store::Store;
+use smart_planner::todo::Priority;
+use smart_planner::PlannerError;
+
+/// A fresh folder under the system temp folder.
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("smart_planner_history_{}_{}", name, process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Monday 4 March 2024 at `hour`:00 UTC.
+fn monday(hour: u32) -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2024, 3, 4, hour, 0, 0).unwrap()
+}
+
+/// Applies `change` to a copy of `data` and records it.
+fn save(
+    history: &History,
+    data: &mut PlannerData,
+    actor: &str,
+    at: DateTime<Utc>,
+    change: impl FnOnce(&mut PlannerData),
+) -> Option<u64> {
+    let before = data.clone();
+    change(data);
+    history.record(&before, data, actor, at).unwrap()
+}
+
+fn descriptions(history: &History) -> Vec<String> {
+    history
+        .events()
+        .unwrap()
+        .iter()
+        .flat_map(|event| event.changes.iter().map(|change| change.to_string()))
+        .collect()
+}
+
+#[test]
+fn every_change_is_recorded_with_who_made_it() {
+    let dir = temp_dir("record");
+    let history = History::new(&dir);
+    let mut data = PlannerData::default();
+
+    save(&history, &mut data, "alice", monday(8), |data| {
+        data.add_todo(NewTodo::new("Write report", Priority::Medium), monday(8))
+            .unwrap();
+    });
+    save(&history, &mut data, "bob", monday(9), |data| {
+        data.todo_mut(1).unwrap().priority = Priority::High;
+    });
+    save(&history, &mut data, "alice", monday(10), |data| {
+        data.start_session("rust", monday(10)).unwrap();
+    });
+    save(&history, &mut data, "alice", monday(11), |data| {
+        data.stop_session(monday(11)).unwrap();
+        data.complete_todo(1, monday(11)).unwrap();
+    });
+    save(&history, &mut data, "bob", monday(12), |data| {
+        data.remove_todo(1).unwrap();
+    });
+    // Saving without a change adds nothing
+    assert_eq!(save(&history, &mut data, "bob", monday(13), |_| {}), None);
+
+    assert_eq!(
+        descriptions(&history),
+        vec![
+            "todo #1 created: Write report",
+            "todo #1 changed: priority medium → high",
+            "session #2 started: rust",
+            "todo #1 completed",
+            "session #2 stopped",
+            "todo #1 deleted: Write report",
+        ]
+    );
+    let events = history.events().unwrap();
+    let seqs: Vec<u64> = events.iter().map(|event| event.seq).collect();
+    assert_eq!(seqs, vec![1, 2, 3, 4, 5]);
+    let actors: Vec<&str> = events.iter().map(|event| event.actor.as_str()).collect();
+    assert_eq!(actors, vec!["alice", "bob", "alice", "alice", "bob"]);
+    assert_eq!(events[1].at, monday(9));
+    match &events[1].changes[0] {
+        Change::Updated { kind, key, fields } => {
+            assert_eq!((*kind, key.as_u64()), (Kind::Todo, Some(1)));
+            assert_eq!(fields[0].field, "priority");
+            assert_eq!(
+                (&fields[0].before, &fields[0].after),
+                (&"medium".into(), &"high".into())
+            );
+        }
+        other => panic!("expected an update, got {:?}", other),
+    }
+
+    assert_eq!("todos".parse::<Kind>().unwrap(), Kind::Todo);
+    assert_eq!("routine-check".parse::<Kind>().unwrap(), Kind::RoutineCheck);
+    assert!("widget".parse::<Kind>().is_err());
+}
+
+#[test]
+fn replaying_gives_the_planner_at_any_time() {
+    let dir = temp_dir("replay");
+    let history = History::new(&dir);
+    let mut data = PlannerData::default();
+    let mut states = Vec::new();
+
+    let date = |day| NaiveDate::from_ymd_opt(2024, 3, day).unwrap();
+    for (hour, day) in [(8, 6), (9, 4), (10, 5)] {
+        save(&history, &mut data, "alice", monday(hour), |data| {
+            data.add_todo(
+                NewTodo::new(&format!("Task {}", hour), Priority::Low),
+                monday(hour),
+            )
+            .unwrap();
+            // Plans are kept sorted, so later ones go in front of earlier ones
+            data.set_plan(Plan::new(date(day), "local"));
+        });
+        states.push(data.clone());
+    }
+    save(&history, &mut data, "alice", monday(11), |data| {
+        data.remove_plan(date(5)).unwrap();
+        data.todo_mut(1).unwrap().estimate_minutes = Some(30);
+        data.todo_mut(3).unwrap().subject = Some("home".to_string());
+        data.todos.swap(0, 2);
+    });
+    assert_eq!(
+        descriptions(&history)[6..],
+        [
+            "todo #3 changed: subject none → home",
+            "todo #1 changed: estimate minutes none → 30",
+            "todo list reordered",
+            "plan for 2024-03-05 deleted",
+        ]
+    );
+
+    assert_eq!(history.state_at(monday(7)).unwrap(), PlannerData::default());
+    assert_eq!(history.state_at(monday(8)).unwrap(), states[0]);
+    assert_eq!(
+        history.state_at(monday(9) + Duration::minutes(30)).unwrap(),
+        states[1]
+    );
+    assert_eq!(history.state_at(monday(10)).unwrap(), states[2]);
+    assert_eq!(history.current().unwrap(), data);
+    assert_eq!(history.current().unwrap().next_id, 3);
+}
+
+#[test]
+fn snapshots_and_changes_made_elsewhere() {
+    let dir = temp_dir("snapshots");
+    let history = History::new(&dir);
+
+    // Data from before the history existed becomes its starting point
+    let mut data = PlannerData::default();
+    data.add_todo(NewTodo::new("Old task", Priority::Low), monday(1))
+        .unwrap();
+    let old = data.clone();
+    let count = SNAPSHOT_EVERY + 5;
+    for n in 0..count {
+        let at = monday(2) + Duration::minutes(n as i64);
+        save(&history, &mut data, "alice", at, |data| {
+            data.todo_mut(1).unwrap().estimate_minutes = Some(n as u32 + 1);
+        });
+    }
+    assert!(dir.join("snapshot-00000000.json").exists());
+    assert!(dir
+        .join(format!("snapshot-{:08}.json", SNAPSHOT_EVERY))
+        .exists());
+    assert_eq!(history.current().unwrap(), data);
+    let at = monday(2) + Duration::minutes(SNAPSHOT_EVERY as i64 + 2);
+    let then = history.state_at(at).unwrap();
+    assert_eq!(
+        then.todos[0].estimate_minutes,
+        Some(SNAPSHOT_EVERY as u32 + 3)
+    );
+    assert_eq!(
+        history.state_at(monday(2)).unwrap().todos[0].estimate_minutes,
+        Some(1)
+    );
+    assert_ne!(history.state_at(monday(2)).unwrap(), old);
+    assert!(matches!(
+        history.state_at(monday(1)),
+        Err(PlannerError::Invalid(_))
+    ));
+
+    // Something the history did not see is recorded before the next change
+    let mut synced = data.clone();
+    synced
+        .add_todo(NewTodo::new("From the laptop", Priority::High), monday(4))
+        .unwrap();
+    let mut after = synced.clone();
+    after.complete_todo(1, monday(5)).unwrap();
+    let seq = history.record(&synced, &after, "alice", monday(5)).unwrap();
+    assert_eq!(seq, Some(count + 2));
+    let events = history.events().unwrap();
+    let elsewhere = &events[events.len() - 2];
+    assert_eq!(elsewhere.actor, ELSEWHERE);
+    assert_eq!(
+        elsewhere.changes[0].to_string(),
+        "todo #2 created: From the laptop"
+    );
+    assert_eq!(history.current().unwrap(), after);
+}
+
+#[test]
+fn the_store_records_every_save() {
+    let dir = temp_dir("store");
+    let mut store = Store::open(&dir).unwrap();
+    store.set_actor("carol");
+    let mut data = store.load().unwrap();
+    data.add_todo(NewTodo::new("Water plants", Priority::Low), Utc::now())
+        .unwrap();
+    store.save(&data).unwrap();
+    data.complete_todo(1, Utc::now()).unwrap();
+    store.save(&data).unwrap();
+
+    // Written straight to the file, as another program might
+    let mut outside = data.clone();
+    outside.remove_todo(1).unwrap();
+    JsonStorage::new(&dir.join("data.json"))
+        .save(&outside)
+        .unwrap();
+    let mut store = Store::open(&dir).unwrap();
+    store.set_actor("carol");
+    let mut data = store.load().unwrap();
+    data.add_todo(NewTodo::new("Pay rent", Priority::High), Utc::now())
+        .unwrap();
+    store.save(&data).unwrap();
+
+    let events = store.history().events().unwrap();
+    let actors: Vec<&str> = events.iter().map(|event| event.actor.as_str()).collect();
+    assert_eq!(actors, vec!["carol", "carol", ELSEWHERE, "carol"]);
+    assert_eq!(store.history().current().unwrap(), store.load().unwrap());
+    assert!(dir.join("history").join("events.jsonl").exists());
+}
+
+#[test]
+fn the_store_loads_by_replaying_the_history() {
+    let dir = temp_dir("load");
+    let store = Store::open(&dir).unwrap();
+    let mut data = store.load().unwrap();
+    data.add_todo(NewTodo::new("Water plants", Priority::Low), Utc::now())
+        .unwrap();
+    store.save(&data).unwrap();
+    assert_eq!(store.load().unwrap(), data);
+
+    // A change written straight to the file is loaded without writing, and
+    // taken into the history on the next save
+    let mut outside = data.clone();
+    outside.todo_mut(1).unwrap().priority = Priority::High;
+    JsonStorage::new(&dir.join("data.json"))
+        .save(&outside)
+        .unwrap();
+    let path = dir.join("history").join("events.jsonl");
+    let log = fs::read_to_string(&path).unwrap();
+    assert_eq!(store.load().unwrap(), outside);
+    assert_eq!(fs::read_to_string(&path).unwrap(), log);
+    store.save(&outside).unwrap();
+    let events = store.history().events().unwrap();
+    assert_eq!(events.last().unwrap().actor, ELSEWHERE);
+
+    // An event still being written is not read yet
+    let log = fs::read_to_string(&path).unwrap();
+    fs::write(&path, format!("{}{{\"seq\": 3, \"at", log)).unwrap();
+    assert_eq!(store.load().unwrap(), outside);
+    assert_eq!(store.history().events().unwrap(), events);
+
+    // Data from before the history existed is loaded as it is
+    let before = temp_dir("load_before");
+    JsonStorage::new(&before.join("data.json"))
+        .save(&data)
+        .unwrap();
+    assert_eq!(Store::open(&before).unwrap().load().unwrap(), data);
+    assert!(!before.join("history").exists());
+
+    // A history that no longer replays to the saved data is refused
+    let log = fs::read_to_string(&path).unwrap();
+    fs::write(&path, log.replace("Water plants", "Water cactus")).unwrap();
+    assert!(matches!(store.load(), Err(PlannerError::Storage(_))));
+}
@@ -0,0 +1,249 @@
+use std::thread;
+
+use chrono::{NaiveDate, NaiveTime, Utc};
+use smart_planner::data::{NewTodo, PlannerData};
+use smart_planner::plan::{BlockKind, LlmPlanner, LocalScheduler, PlanRequest, Planner};
+use smart_planner::routine::Frequency;
+use smart_planner::time;
+use smart_planner::todo::Priority;
+use smart_planner::PlannerError;
+
+fn at(hour: u32, minute: u32) -> NaiveTime {
+    NaiveTime::from_hms_opt(hour, minute, 0).unwrap()
+}
+
+fn monday() -> NaiveDate {
+    NaiveDate::from_ymd_opt(2024, 3, 4).unwrap()
+}
+
+fn sample_data() -> PlannerData {
+    let mut data = PlannerData::default();
+    data.add_routine("Breakfast", at(8, 0), 30, Frequency::Daily, Utc::now())
+        .unwrap();
+    data.add_routine("Gym", at(9, 0), 60, Frequency::Weekends, Utc::now())
+        .unwrap();
+    let mut low = NewTodo::new("Tidy desk", Priority::Low);
+    low.estimate_minutes = Some(20);
+    data.add_todo(low, Utc::now()).unwrap();
+    let mut high = NewTodo::new("Write report", Priority::High);
+    high.estimate_minutes = Some(90);
+    data.add_todo(high, Utc::now()).unwrap();
+    data
+}
+
+#[test]
+fn local_scheduler_puts_high_priority_first_around_routines() {
+    let data = sample_data();
+    let request = PlanRequest::new(monday(), &data.todos, &data.routines);
+    let plan = LocalScheduler::default().generate(&request).unwrap();
+
+    let titles: Vec<&str> = plan.blocks.iter().map(|b| b.title.as_str()).collect();
+    // The gym is a weekend routine, so it is not on a Monday
+    assert_eq!(titles, vec!["Breakfast", "Write report", "Tidy desk"]);
+    assert_eq!(plan.blocks[0].kind, BlockKind::Routine);
+    assert_eq!(
+        (plan.blocks[1].start, plan.blocks[1].end),
+        (at(8, 30), at(10, 0))
+    );
+    // Five minutes of buffer after the report
+    assert_eq!(
+        (plan.blocks[2].start, plan.blocks[2].end),
+        (at(10, 5), at(10, 25))
+    );
+    assert!(plan.unscheduled.is_empty());
+}
+
+#[test]
+fn local_scheduler_respects_due_times() {
+    let mut data = sample_data();
+    let mut urgent = NewTodo::new("Call the bank", Priority::Medium);
+    urgent.estimate_minutes = Some(45);
+    urgent.due = Some(time::from_local(monday().and_time(at(8, 20))));
+    let urgent_id = data.add_todo(urgent, Utc::now()).unwrap();
+    let mut later = NewTodo::new("Send invoice", Priority::Medium);
+    later.estimate_minutes = Some(15);
+    later.due = Some(time::from_local(monday().and_time(at(12, 0))));
+    let later_id = data.add_todo(later, Utc::now()).unwrap();
+
+    let request = PlanRequest::new(monday(), &data.todos, &data.routines);
+    let plan = LocalScheduler::default().generate(&request).unwrap();
+
+    // 45 minutes cannot fit between 08:00 and 08:20
+    assert_eq!(plan.unscheduled, vec![urgent_id]);
+    let invoice = plan
+        .blocks
+        .iter()
+        .find(|b| b.todo_id == Some(later_id))
+        .unwrap();
+    assert!(invoice.end <= at(12, 0));
+}
+
+#[test]
+fn local_scheduler_skips_finished_todos() {
+    let mut data = sample_data();
+    data.complete_todo(4, Utc::now()).unwrap();
+    let request = PlanRequest::new(monday(), &data.todos, &data.routines);
+    let plan = LocalScheduler::default().generate(&request).unwrap();
+    assert!(plan.blocks.iter().all(|b| b.todo_id != Some(4)));
+    assert!(plan.blocks.iter().any(|b| b.todo_id == Some(3)));
+}
+
+#[test]
+fn generated_plans_can_be_edited() {
+    let data = sample_data();
+    let request = PlanRequest::new(monday(), &data.todos, &data.routines);
+    let mut plan = LocalScheduler::default().generate(&request).unwrap();
+    assert!(!plan.edited);
+
+    // Move "Tidy desk" before breakfast
+    plan.move_block(3, at(7, 30)).unwrap();
+    assert_eq!(plan.blocks[0].title, "Tidy desk");
+    assert_eq!(plan.blocks[0].end, at(7, 50));
+    plan.rename_block(1, "Tidy the desk").unwrap();
+    let removed = plan.remove_block(3).unwrap();
+    assert_eq!(removed.title, "Write report");
+    assert_eq!(plan.unscheduled, vec![removed.todo_id.unwrap()]);
+    assert!(plan.edited);
+
+    assert!(matches!(
+        plan.remove_block(9),
+        Err(PlannerError::NotFound(_))
+    ));
+    assert!(matches!(
+        plan.resize_block(1, at(10, 0), at(9, 0)),
+        Err(PlannerError::Invalid(_))
+    ));
+}
+
+/// Serves one canned chat-completion reply and hands back the request it got.
+fn mock_llm(status: u16, reply: String) -> (String, thread::JoinHandle<(String, String, String)>) {
+    let server = tiny_http::Server::http("127.0.0.1:0").unwrap();
+    let url = format!("http://{}/v1", server.server_addr());
+    let handle = thread::spawn(move || {
+        let mut request = server.recv().unwrap();
+        let mut body = String::new();
+        request.as_reader().read_to_string(&mut body).unwrap();
+        let auth = request
+            .headers()
+            .iter()
+            .find(|h| h.field.equiv("Authorization"))
+            .map(|h| h.value.to_string())
+            .unwrap_or_default();
+        let path = request.url().to_string();
+        let response = tiny_http::Response::from_string(reply).with_status_code(status);
+        request.respond(response).unwrap();
+        (path, auth, body)
+    });
+    (url, handle)
+}
+
+fn chat_reply(content: &str) -> String {
+    serde_json::json!({
+        "choices": [{ "message": { "role": "assistant", "content": content } }]
+    })
+    .to_string()
+}
+
+#[test]
+fn llm_planner_builds_plan_from_model_answer() {
+    let data = sample_data();
+    let content = "```json\n{\"blocks\": [\
+        {\"start\": \"10:00\", \"end\": \"11:30\", \"title\": \"Write report\", \"todo_id\": 4},\
+        {\"start\": \"11:30\", \"end\": \"11:45\", \"title\": \"Coffee\", \"todo_id\": null}]}\n```";
+    let (url, server) = mock_llm(200, chat_reply(content));
+
+    let planner = LlmPlanner::new(&url, "test-model", Some("secret".to_string())).unwrap();
+    let request = PlanRequest::new(monday(), &data.todos, &data.routines);
+    let plan = planner.generate(&request).unwrap();
+
+    let (path, auth, body) = server.join().unwrap();
+    assert_eq!(path, "/v1/chat/completions");
+    assert_eq!(auth, "Bearer secret");
+    let sent: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(sent["model"], "test-model");
+    assert!(sent["messages"][1]["content"]
+        .as_str()
+        .unwrap()
+        .contains("Write report"));
+
+    assert_eq!(plan.generated_by, "llm");
+    let titles: Vec<&str> = plan.blocks.iter().map(|b| b.title.as_str()).collect();
+    assert_eq!(titles, vec!["Breakfast", "Write report", "Coffee"]);
+    assert_eq!(plan.blocks[2].kind, BlockKind::Break);
+    // The model left out the low priority todo
+    assert_eq!(plan.unscheduled, vec![3]);
+}
+
+#[test]
+fn llm_planner_reports_http_errors() {
+    let data = sample_data();
+    let (url, server) = mock_llm(500, "upstream down".to_string());
+    let planner = LlmPlanner::new(&url, "test-model", None).unwrap();
+    let request = PlanRequest::new(monday(), &data.todos, &data.routines);
+    let result = planner.generate(&request);
+    server.join().unwrap();
+    match result {
+        Err(PlannerError::Http(message)) => assert!(message.contains("upstream down")),
+        other => panic!("expected an HTTP error, got {:?}", other),
+    }
+}
+
+#[test]
+fn llm_planner_rejects_answers_without_json() {
+    let data = sample_data();
+    let (url, server) = mock_llm(200, chat_reply("Sorry, I cannot help with that."));
+    let planner = LlmPlanner::new(&url, "test-model", None).unwrap();
+    let request = PlanRequest::new(monday(), &data.todos, &data.routines);
+    let result = planner.generate(&request);
+    server.join().unwrap();
+    assert!(matches!(result, Err(PlannerError::Backend(_))));
+}
+
+#[test]
+fn llm_planner_refuses_blocks_that_clash() {
+    let data = sample_data();
+    let request = PlanRequest::new(monday(), &data.todos, &data.routines);
+    let replies = [
+        // Over breakfast
+        (
+            "08:15",
+            "09:00",
+            "Write report",
+            "over 'Breakfast' at 08:00-08:30",
+        ),
+        // Before the day starts
+        ("07:00", "07:45", "Write report", "outside the day"),
+        // After it ends
+        ("21:30", "22:30", "Tidy desk", "outside the day"),
+    ];
+    for (start, end, title, error) in replies {
+        let content = format!(
+            "{{\"blocks\": [{{\"start\": \"{}\", \"end\": \"{}\", \
+             \"title\": \"{}\", \"todo_id\": 4}}]}}",
+            start, end, title
+        );
+        let (url, server) = mock_llm(200, chat_reply(&content));
+        let planner = LlmPlanner::new(&url, "test-model", None).unwrap();
+        let result = planner.generate(&request);
+        server.join().unwrap();
+        match result {
+            Err(PlannerError::Backend(message)) => assert!(message.contains(error), "{}", message),
+            other => panic!("expected the plan to be refused, got {:?}", other),
+        }
+    }
+
+    // Two suggested blocks on top of each other
+    let content = "{\"blocks\": [\
+        {\"start\": \"10:00\", \"end\": \"11:30\", \"title\": \"Write report\", \"todo_id\": 4},\
+        {\"start\": \"11:00\", \"end\": \"11:20\", \"title\": \"Tidy desk\", \"todo_id\": 3}]}";
+    let (url, server) = mock_llm(200, chat_reply(content));
+    let planner = LlmPlanner::new(&url, "test-model", None).unwrap();
+    let result = planner.generate(&request);
+    server.join().unwrap();
+    match result {
+        Err(PlannerError::Backend(message)) => {
+            assert!(message.contains("over 'Write report' at 10:00-11:30"))
+        }
+        other => panic!("expected the plan to be refused, got {:?}", other),
+    }
+}
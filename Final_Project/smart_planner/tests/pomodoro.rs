@@ -0,0 +1,180 @@
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use smart_planner::data::PlannerData;
+use smart_planner::pomodoro::{Phase, PomodoroSettings, Transition};
+use smart_planner::report::{self, Period, Report, Summary};
+use smart_planner::time;
+use smart_planner::PlannerError;
+
+fn date() -> NaiveDate {
+    NaiveDate::from_ymd_opt(2024, 3, 4).unwrap()
+}
+
+/// 09:00 local time on Monday 4 March 2024, plus `minutes`.
+fn at(minutes: i64) -> DateTime<Utc> {
+    time::from_local(date().and_hms_opt(9, 0, 0).unwrap()) + Duration::minutes(minutes)
+}
+
+/// 25 minutes of work, 5 minute breaks and a 15 minute one after every 2.
+fn settings() -> PomodoroSettings {
+    PomodoroSettings {
+        long_break_every: 2,
+        ..PomodoroSettings::default()
+    }
+}
+
+#[test]
+fn phases_follow_each_other_and_work_is_logged() {
+    let mut data = PlannerData::default();
+    data.start_pomodoro("rust", settings(), Some(3), at(0))
+        .unwrap();
+    assert_eq!(data.running_session().unwrap().subject, "rust");
+    assert!(data.advance_pomodoro(at(24)).is_empty());
+
+    let messages: Vec<String> = data
+        .advance_pomodoro(at(25))
+        .iter()
+        .map(|t| t.to_string())
+        .collect();
+    assert_eq!(messages, vec!["pomodoro 1 done, take a short break (5m)"]);
+    assert!(data.running_session().is_none());
+
+    // Left alone through a work interval and into the long break
+    let transitions = data.advance_pomodoro(at(62));
+    assert_eq!(
+        transitions,
+        vec![
+            Transition::WorkStarted {
+                at: at(30),
+                subject: "rust".to_string()
+            },
+            Transition::BreakStarted {
+                at: at(55),
+                completed: 2,
+                phase: Phase::LongBreak,
+                minutes: 15
+            },
+        ]
+    );
+    let run = data.pomodoro.as_ref().unwrap();
+    assert_eq!(run.remaining(at(62)), Duration::minutes(8));
+
+    let transitions = data.advance_pomodoro(at(120));
+    assert_eq!(
+        transitions.last().unwrap().to_string(),
+        "all 3 pomodoros done"
+    );
+    assert!(data.pomodoro.is_none());
+
+    // Each work interval is a finished session, ending when the interval did
+    let sessions: Vec<(DateTime<Utc>, Option<DateTime<Utc>>)> = data
+        .sessions
+        .iter()
+        .map(|session| (session.start, session.end))
+        .collect();
+    assert_eq!(
+        sessions,
+        vec![
+            (at(0), Some(at(25))),
+            (at(30), Some(at(55))),
+            (at(70), Some(at(95))),
+        ]
+    );
+    assert_eq!(data.pomodoros.len(), 3);
+    assert!(data.pomodoros.iter().all(|p| !p.interrupted));
+}
+
+#[test]
+fn stopping_early_records_an_interrupted_pomodoro() {
+    let mut data = PlannerData::default();
+    data.start_pomodoro("email", settings(), None, at(0))
+        .unwrap();
+
+    // The stopwatch belongs to the run until it is stopped
+    assert!(data.start_session("rust", at(1)).is_err());
+    assert!(matches!(
+        data.stop_session(at(1)),
+        Err(PlannerError::Invalid(_))
+    ));
+    assert!(data.reset_session().is_err());
+    assert!(data
+        .start_pomodoro("rust", settings(), None, at(1))
+        .is_err());
+
+    let run = data.stop_pomodoro(at(40)).unwrap();
+    assert_eq!((run.phase, run.completed), (Phase::Work, 1));
+    let last = data.pomodoros.last().unwrap();
+    assert!(last.interrupted);
+    assert_eq!((last.start, last.end), (at(30), at(40)));
+    assert_eq!(data.sessions[1].end, Some(at(40)));
+    assert!(data.stop_pomodoro(at(41)).is_err());
+
+    // Stopping during a break leaves nothing cut short
+    data.start_pomodoro("email", settings(), None, at(60))
+        .unwrap();
+    data.stop_pomodoro(at(87)).unwrap();
+    assert_eq!(data.pomodoros.iter().filter(|p| p.interrupted).count(), 1);
+
+    let bad = PomodoroSettings {
+        work_minutes: 0,
+        ..settings()
+    };
+    assert!(data.start_pomodoro("email", bad, None, at(90)).is_err());
+    assert!(data
+        .start_pomodoro("email", settings(), Some(0), at(90))
+        .is_err());
+    assert!(data.pomodoro.is_none());
+}
+
+#[test]
+fn no_session_starts_during_a_break() {
+    let mut data = PlannerData::default();
+    data.start_pomodoro("rust", settings(), None, at(0))
+        .unwrap();
+    data.advance_pomodoro(at(26));
+    assert!(data.running_session().is_none());
+    assert!(matches!(
+        data.start_session("email", at(27)),
+        Err(PlannerError::Invalid(_))
+    ));
+
+    // Work starts again with only the run's own session going
+    data.advance_pomodoro(at(31));
+    let running: Vec<&str> = data
+        .sessions
+        .iter()
+        .filter(|session| session.is_running())
+        .map(|session| session.subject.as_str())
+        .collect();
+    assert_eq!(running, vec!["rust"]);
+}
+
+#[test]
+fn reports_count_pomodoros_per_day() {
+    let mut data = PlannerData::default();
+    data.start_pomodoro("rust", settings(), Some(2), at(0))
+        .unwrap();
+    data.advance_pomodoro(at(60));
+    // The next day, one finished and one cut short
+    data.start_pomodoro("rust", settings(), None, at(24 * 60))
+        .unwrap();
+    data.stop_pomodoro(at(24 * 60 + 40)).unwrap();
+
+    let summary = Summary::build(&data, Period::Week.range(date()));
+    assert_eq!(summary.pomodoros_done(), 3);
+    assert_eq!(summary.pomodoros_interrupted, 1);
+    assert_eq!(summary.daily_pomodoros[0], (date(), 2));
+    assert_eq!(summary.daily_pomodoros[1], (date().succ_opt().unwrap(), 1));
+    // The time counts as focus time like any stopwatch session
+    assert_eq!(summary.minutes_for("rust"), 85);
+
+    let report = Report::for_period(&data, Period::Week, date());
+    let text = report::text::render(&report);
+    assert!(text.contains("Pomodoros done                      3         0       +3"));
+    assert!(text.contains("Pomodoros interrupted               1         0       +1"));
+    assert!(text.contains("Mon 2024-03-04         50m          2"));
+    let csv = report::csv::render(&report);
+    assert!(csv.contains("\npomodoros_done,,3,0,3\n"));
+    assert!(csv.contains("\nday_pomodoros,2024-03-05,1,,\n"));
+    let html = report::html::render(&report);
+    assert!(html.contains("<td>Pomodoros done</td><td>3 (1 interrupted)</td>"));
+}
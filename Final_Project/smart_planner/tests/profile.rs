@@ -0,0 +1,180 @@
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+
+use chrono::{NaiveTime, Utc};
+use smart_planner::backup;
+use smart_planner::data::{NewTodo, PlannerData};
+use smart_planner::profile::{self, Profiles};
+use smart_planner::routine::Frequency;
+use smart_planner::store::Store;
+use smart_planner::todo::Priority;
+use smart_planner::vault::{self, KdfParams};
+use smart_planner::PlannerError;
+
+/// A fresh folder under the system temp folder.
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("smart_planner_profile_{}_{}", name, process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn at(hour: u32, minute: u32) -> NaiveTime {
+    NaiveTime::from_hms_opt(hour, minute, 0).unwrap()
+}
+
+fn change(dir: &Path, work: impl FnOnce(&mut PlannerData)) {
+    let store = Store::open(dir).unwrap();
+    let mut data = store.load().unwrap();
+    work(&mut data);
+    store.save(&data).unwrap();
+}
+
+fn load(dir: &Path) -> PlannerData {
+    Store::open(dir).unwrap().load().unwrap()
+}
+
+#[test]
+fn profiles_keep_their_data_apart() {
+    let home = temp_dir("apart");
+    let profiles = Profiles::new(&home);
+    assert_eq!(profiles.resolve(None).unwrap(), home);
+    assert!(profiles.create("../escape").is_err());
+    assert!(profiles.create("").is_err());
+    assert!(matches!(
+        profiles.resolve(Some("alice")),
+        Err(PlannerError::Invalid(_))
+    ));
+
+    let alice = profiles.create("alice").unwrap();
+    let bob = profiles.create("bob").unwrap();
+    assert!(profiles.create("bob").is_err());
+    assert_eq!(profiles.names().unwrap(), vec!["alice", "bob"]);
+    assert_eq!(profile::name_of(&alice).as_deref(), Some("alice"));
+    assert_eq!(profile::name_of(&home), None);
+
+    change(&alice, |data| {
+        data.add_todo(NewTodo::new("Alice's report", Priority::High), Utc::now())
+            .unwrap();
+    });
+    assert_eq!(load(&alice).todos.len(), 1);
+    assert!(load(&bob).todos.is_empty());
+    assert!(load(&home).todos.is_empty());
+
+    // The default is used without --profile, and forgotten with its profile
+    profiles.set_default(Some("alice")).unwrap();
+    assert_eq!(profiles.resolve(None).unwrap(), alice);
+    assert_eq!(profiles.resolve(Some("bob")).unwrap(), bob);
+    assert!(profiles.set_default(Some("carol")).is_err());
+    let usage = profiles.usage().unwrap();
+    assert_eq!(usage.len(), 2);
+    assert!(usage[0].is_default && !usage[1].is_default);
+    assert!(usage[0].bytes > usage[1].bytes);
+    assert!(usage[0].files >= 2, "the data and its history");
+
+    // Data kept in the home folder is listed first, without the profiles
+    change(&home, |data| {
+        data.add_todo(NewTodo::new("Shared chores", Priority::Low), Utc::now())
+            .unwrap();
+    });
+    let with_home = profiles.usage().unwrap();
+    assert_eq!(with_home.len(), 3);
+    assert_eq!(with_home[0].name, None);
+    assert!(!with_home[0].is_default, "alice is the default");
+    assert_eq!(with_home[0].files, 2, "the data and its history");
+    assert_eq!(&with_home[1..], &usage[..]);
+
+    profiles.remove("alice").unwrap();
+    assert!(!alice.exists());
+    assert_eq!(profiles.settings().unwrap().default, None);
+    assert_eq!(profiles.resolve(None).unwrap(), home);
+}
+
+#[test]
+fn routines_and_todos_copy_between_profiles() {
+    let now = Utc::now();
+    let mut from = PlannerData::default();
+    from.add_routine("Gym", at(7, 0), 45, Frequency::Daily, now)
+        .unwrap();
+    from.add_routine("Read", at(21, 0), 30, Frequency::Daily, now)
+        .unwrap();
+    let mut todo = NewTodo::new("Book flights", Priority::High);
+    todo.subject = Some("travel".to_string());
+    todo.estimate_minutes = Some(20);
+    from.add_todo(todo, now).unwrap();
+    from.add_todo(NewTodo::new("Old task", Priority::Low), now)
+        .unwrap();
+    from.complete_todo(4, now).unwrap();
+
+    let mut to = PlannerData::default();
+    to.add_routine("gym", at(6, 30), 60, Frequency::Daily, now)
+        .unwrap();
+    // The same name is already there, only Read is new
+    let copied = profile::copy_routines(&from, &mut to, &[], now).unwrap();
+    assert_eq!(copied, vec![2]);
+    assert_eq!(to.routines[1].name, "Read");
+    assert_eq!(to.routines[1].start, at(21, 0));
+
+    // Open todos only, unless asked for by id
+    let copied = profile::copy_todos(&from, &mut to, &[], now).unwrap();
+    let copy = to.todo(copied[0]).unwrap();
+    assert_eq!(copied.len(), 1);
+    assert_eq!(copy.title, "Book flights");
+    assert_eq!(copy.subject.as_deref(), Some("travel"));
+    assert_eq!(copy.estimate_minutes, Some(20));
+    assert_eq!(
+        profile::copy_todos(&from, &mut to, &[4], now)
+            .unwrap()
+            .len(),
+        1
+    );
+    assert!(!to.todos.last().unwrap().done);
+
+    assert!(matches!(
+        profile::copy_todos(&from, &mut to, &[3, 99], now),
+        Err(PlannerError::NotFound(_))
+    ));
+    assert_eq!(to.todos.len(), 2, "nothing copied when an id is missing");
+}
+
+#[test]
+fn backups_and_encryption_stay_within_a_profile() {
+    let home = temp_dir("backups");
+    let profiles = Profiles::new(&home);
+    let alice = profiles.create("alice").unwrap();
+    change(&home, |data| {
+        data.add_todo(NewTodo::new("Shared printer", Priority::Low), Utc::now())
+            .unwrap();
+    });
+    change(&alice, |data| {
+        data.add_todo(NewTodo::new("Alice's secret", Priority::High), Utc::now())
+            .unwrap();
+    });
+
+    let store = Store::open(&alice).unwrap();
+    let backup = backup::read(&backup::create(&store, None, Utc::now()).unwrap(), None).unwrap();
+    assert_eq!(backup.profile.as_deref(), Some("alice"));
+    assert_eq!(backup.data.todos.len(), 1);
+    let store = Store::open(&home).unwrap();
+    let backup = backup::read(&backup::create(&store, None, Utc::now()).unwrap(), None).unwrap();
+    assert_eq!(backup.profile, None);
+    assert_eq!(backup.data.todos[0].title, "Shared printer");
+    assert!(backup
+        .files
+        .keys()
+        .all(|name| !name.starts_with("profiles")));
+
+    // Locking the home folder leaves the profiles to their own vaults
+    let cheap = KdfParams {
+        memory_kib: 64,
+        iterations: 1,
+        parallelism: 1,
+    };
+    vault::enable(&home, "correct horse", cheap).unwrap();
+    assert!(vault::is_locked(&home).unwrap());
+    assert!(!vault::is_locked(&alice).unwrap());
+    assert_eq!(load(&alice).todos[0].title, "Alice's secret");
+    vault::unlock(&home, "correct horse").unwrap();
+}
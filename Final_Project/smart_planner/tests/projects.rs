@@ -0,0 +1,122 @@
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use smart_planner::data::{NewTodo, PlannerData};
+use smart_planner::project::graph::{CriticalPath, Graph};
+use smart_planner::todo::Priority;
+use smart_planner::PlannerError;
+
+fn at(minute: i64) -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2024, 3, 4, 9, 0, 0).unwrap() + Duration::minutes(minute)
+}
+
+/// Adds a todo taking `minutes`, as a subtask of `parent` if given.
+fn task(data: &mut PlannerData, title: &str, minutes: Option<u32>, parent: Option<u64>) -> u64 {
+    let mut new = NewTodo::new(title, Priority::Medium);
+    new.estimate_minutes = minutes;
+    new.parent = parent;
+    data.add_todo(new, at(0)).unwrap()
+}
+
+#[test]
+fn circular_dependencies_are_rejected() {
+    let mut data = PlannerData::default();
+    let design = task(&mut data, "Design", None, None);
+    let build = task(&mut data, "Build", None, None);
+    let test = task(&mut data, "Test", None, None);
+    data.block_todo(build, design).unwrap();
+    data.block_todo(test, build).unwrap();
+
+    match data.block_todo(design, test) {
+        Err(PlannerError::Invalid(message)) => {
+            assert!(message.ends_with("#1 → #3 → #2 → #1"), "{}", message)
+        }
+        other => panic!("expected a loop, got {:?}", other),
+    }
+    assert!(data.todo(design).unwrap().blocked_by.is_empty());
+    assert!(data.block_todo(design, design).is_err());
+    assert!(data.block_todo(build, design).is_err());
+
+    // A parent waits for its subtasks, so a subtask cannot wait for it
+    let docs = task(&mut data, "Docs", None, Some(test));
+    assert!(data.block_todo(docs, test).is_err());
+    assert!(data.set_parent(test, Some(docs)).is_err());
+    assert_eq!(data.todo(test).unwrap().parent, None);
+    data.set_parent(docs, None).unwrap();
+    data.block_todo(docs, test).unwrap();
+}
+
+#[test]
+fn ready_todos_come_first_and_the_critical_path_is_the_longest_chain() {
+    let mut data = PlannerData::default();
+    let project = data.add_project("Website", at(0)).unwrap();
+    let mut site = NewTodo::new("Launch site", Priority::High);
+    site.project = Some(project);
+    let site = data.add_todo(site, at(0)).unwrap();
+    let design = task(&mut data, "Design", Some(120), Some(site));
+    let copy = task(&mut data, "Write copy", Some(60), Some(site));
+    let build = task(&mut data, "Build", Some(240), Some(site));
+    let review = task(&mut data, "Review copy", None, Some(site));
+    let errand = task(&mut data, "Buy milk", Some(10), None);
+    data.block_todo(build, design).unwrap();
+    data.block_todo(review, copy).unwrap();
+    assert_eq!(data.todo(build).unwrap().project, Some(project));
+
+    let graph = Graph::new(&data.todos);
+    let order = graph.order().unwrap();
+    assert_eq!(order, vec![design, copy, build, review, site, errand]);
+    let ready: Vec<u64> = order.into_iter().filter(|id| graph.is_ready(*id)).collect();
+    assert_eq!(ready, vec![design, copy, errand]);
+    assert_eq!(graph.waiting_for(site), vec![design, copy, build, review]);
+
+    assert_eq!(
+        graph
+            .critical_path(|todo| todo.project == Some(project))
+            .unwrap(),
+        CriticalPath {
+            todos: vec![design, build, site],
+            minutes: 360,
+            unestimated: vec![site, review],
+        }
+    );
+
+    // Done todos no longer hold anything up
+    data.complete_todo(design, at(60)).unwrap();
+    let graph = Graph::new(&data.todos);
+    assert!(graph.is_ready(build));
+    let path = graph
+        .critical_path(|todo| todo.project == Some(project))
+        .unwrap();
+    assert_eq!((path.todos, path.minutes), (vec![build, site], 240));
+}
+
+#[test]
+fn a_parent_is_completed_after_its_subtasks() {
+    let mut data = PlannerData::default();
+    let trip = task(&mut data, "Plan trip", None, None);
+    let tickets = task(&mut data, "Book tickets", None, Some(trip));
+    let seats = task(&mut data, "Pick seats", None, Some(tickets));
+    let hotel = task(&mut data, "Book hotel", None, Some(trip));
+    data.complete_todo(hotel, at(10)).unwrap();
+
+    assert_eq!(data.open_subtasks(trip).unwrap(), vec![seats, tickets]);
+    assert!(matches!(
+        data.complete_todo(trip, at(20)),
+        Err(PlannerError::Invalid(_))
+    ));
+    assert!(!data.todo(trip).unwrap().done);
+    assert_eq!(
+        data.complete_todo_tree(trip, at(20)).unwrap(),
+        vec![seats, tickets, trip]
+    );
+    assert!(data.todos.iter().all(|todo| todo.done));
+
+    // Deleting a todo moves its subtasks up and drops it as a blocker
+    let mut data = PlannerData::default();
+    let trip = task(&mut data, "Plan trip", None, None);
+    let tickets = task(&mut data, "Book tickets", None, Some(trip));
+    let seats = task(&mut data, "Pick seats", None, Some(tickets));
+    let pack = task(&mut data, "Pack", None, None);
+    data.block_todo(pack, tickets).unwrap();
+    data.remove_todo(tickets).unwrap();
+    assert_eq!(data.todo(seats).unwrap().parent, Some(trip));
+    assert!(data.todo(pack).unwrap().blocked_by.is_empty());
+}
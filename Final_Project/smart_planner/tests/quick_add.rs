@@ -0,0 +1,141 @@
+use chrono::{NaiveDate, NaiveDateTime};
+use smart_planner::data::PlannerData;
+use smart_planner::time;
+use smart_planner::todo::quick::{self, QuickTodo};
+use smart_planner::todo::Priority;
+use smart_planner::PlannerError;
+
+/// Wednesday 6 March 2024, 10:30 local time.
+fn now() -> NaiveDateTime {
+    NaiveDate::from_ymd_opt(2024, 3, 6)
+        .unwrap()
+        .and_hms_opt(10, 30, 0)
+        .unwrap()
+}
+
+fn local(day: u32, hour: u32, minute: u32) -> NaiveDateTime {
+    NaiveDate::from_ymd_opt(2024, 3, day)
+        .unwrap()
+        .and_hms_opt(hour, minute, 0)
+        .unwrap()
+}
+
+fn due(text: &str) -> Option<NaiveDateTime> {
+    quick::parse(text, now()).unwrap().due
+}
+
+#[test]
+fn reads_every_field() {
+    let parsed = quick::parse("finish report tomorrow 3pm !high #work ~45m", now()).unwrap();
+    assert_eq!(
+        parsed,
+        QuickTodo {
+            title: "finish report".to_string(),
+            priority: Some(Priority::High),
+            due: Some(local(7, 15, 0)),
+            estimate_minutes: Some(45),
+            subject: Some("work".to_string()),
+        }
+    );
+    assert_eq!(
+        parsed.to_string(),
+        "Title:     finish report\nPriority:  high\nDue:       Thu 2024-03-07 15:00\nSubject:   work\nEstimate:  45m\n"
+    );
+
+    // The order does not matter and the markers can go anywhere
+    let parsed = quick::parse("~1h30m #home !l Fix the tap by Friday", now()).unwrap();
+    assert_eq!(parsed.title, "Fix the tap");
+    assert_eq!(parsed.priority, Some(Priority::Low));
+    assert_eq!(parsed.estimate_minutes, Some(90));
+    assert_eq!(parsed.due, Some(local(8, 23, 59)));
+
+    let plain = quick::parse("water the plants", now()).unwrap();
+    assert_eq!(plain.due, None);
+    assert!(plain.to_string().contains("Priority:  medium (default)"));
+    assert_eq!(plain.to_new_todo().priority, Priority::Medium);
+}
+
+#[test]
+fn relative_dates_count_from_now() {
+    assert_eq!(due("pay rent today"), Some(local(6, 23, 59)));
+    assert_eq!(due("pay rent in 2 days"), Some(local(8, 23, 59)));
+    assert_eq!(due("pay rent in 1 week"), Some(local(13, 23, 59)));
+    assert_eq!(due("stretch in 2 hours"), Some(local(6, 12, 30)));
+    assert_eq!(due("stretch in 45 min"), Some(local(6, 11, 15)));
+    assert_eq!(due("call tonight"), Some(local(6, 20, 0)));
+    // A weekday is the next one; today's weekday means a week from now
+    assert_eq!(due("call by fri"), Some(local(8, 23, 59)));
+    assert_eq!(due("call on sat"), Some(local(9, 23, 59)));
+    assert_eq!(due("call wednesday"), Some(local(13, 23, 59)));
+    // "next" is the week after this one, which runs Monday to Sunday
+    assert_eq!(due("call next friday"), Some(local(15, 23, 59)));
+    assert_eq!(due("call next monday 9am"), Some(local(11, 9, 0)));
+    assert_eq!(due("plan next week"), Some(local(11, 23, 59)));
+    let april = NaiveDate::from_ymd_opt(2024, 4, 15).unwrap();
+    assert_eq!(
+        due("taxes due 2024-04-15 noon"),
+        april.and_hms_opt(12, 0, 0)
+    );
+
+    // A time alone is the next time the clock shows it
+    assert_eq!(due("lunch at 12:15"), Some(local(6, 12, 15)));
+    assert_eq!(due("coffee 9:30am"), Some(local(7, 9, 30)));
+    assert_eq!(due("late call 11 pm"), Some(local(6, 23, 0)));
+    assert_eq!(due("midnight snack 12am"), Some(local(7, 0, 0)));
+}
+
+#[test]
+fn ordinary_words_stay_in_the_title() {
+    let parsed = quick::parse("read in the park at the weekend on my own", now()).unwrap();
+    assert_eq!(parsed.title, "read in the park at the weekend on my own");
+    assert_eq!(parsed.due, None);
+    let parsed = quick::parse("buy 3 apples next time", now()).unwrap();
+    assert_eq!(parsed.title, "buy 3 apples next time");
+    // Short weekday names on their own are words, not days
+    let parsed = quick::parse("fix mon script tomorrow", now()).unwrap();
+    assert_eq!(parsed.title, "fix mon script");
+    assert_eq!(parsed.due, Some(local(7, 23, 59)));
+    let parsed = quick::parse("sat nav update for wed demo", now()).unwrap();
+    assert_eq!(parsed.title, "sat nav update for wed demo");
+    assert_eq!(parsed.due, None);
+    // Too far ahead to be a date
+    let parsed = quick::parse("relax in 99999999 weeks", now()).unwrap();
+    assert_eq!(parsed.due, None);
+}
+
+#[test]
+fn unclear_text_is_refused() {
+    let invalid = |text: &str| matches!(quick::parse(text, now()), Err(PlannerError::Invalid(_)));
+    assert!(invalid("report !urgent"));
+    assert!(invalid("report !high !low"));
+    assert!(invalid("report #work #home"));
+    assert!(invalid("report ~soon"));
+    assert!(invalid("report ~1x"));
+    assert!(invalid("big thing ~99999999h"));
+    assert!(invalid("big thing ~71582788h59m"));
+    assert!(invalid("report tomorrow friday"));
+    assert!(invalid("report 3pm 4pm"));
+    assert!(invalid("report in 2 hours 3pm"));
+
+    // What the parser accepts still goes through the planner's own checks
+    let mut data = PlannerData::default();
+    let created = time::from_local(now());
+    let empty = quick::parse("!high tomorrow", now()).unwrap();
+    assert_eq!(empty.title, "");
+    assert!(data.add_todo(empty.to_new_todo(), created).is_err());
+    let zero = quick::parse("nap ~0", now()).unwrap();
+    assert!(data.add_todo(zero.to_new_todo(), created).is_err());
+
+    let id = data
+        .add_todo(
+            quick::parse("ship it tomorrow 5pm", now())
+                .unwrap()
+                .to_new_todo(),
+            created,
+        )
+        .unwrap();
+    assert_eq!(
+        data.todo(id).unwrap().due,
+        Some(time::from_local(local(7, 17, 0)))
+    );
+}
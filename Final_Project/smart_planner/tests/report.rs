@@ -0,0 +1,189 @@
+use chrono::{DateTime, NaiveDate, NaiveTime, Utc};
+use smart_planner::data::{NewTodo, PlannerData};
+use smart_planner::report::{self, DateRange, Period, Report, Summary};
+use smart_planner::routine::Frequency;
+use smart_planner::stopwatch::Session;
+use smart_planner::time;
+use smart_planner::todo::Priority;
+
+fn day(d: u32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(2024, 3, d).unwrap()
+}
+
+fn local(d: u32, hour: u32, minute: u32) -> DateTime<Utc> {
+    time::from_local(day(d).and_hms_opt(hour, minute, 0).unwrap())
+}
+
+fn session(data: &mut PlannerData, subject: &str, start: DateTime<Utc>, minutes: i64) {
+    let id = data.next_id();
+    data.sessions.push(Session {
+        id,
+        subject: subject.to_string(),
+        start,
+        end: Some(start + chrono::Duration::minutes(minutes)),
+    });
+}
+
+/// Two weeks of data: Monday 2024-02-26 to Sunday 2024-03-10.
+fn sample_data() -> PlannerData {
+    let mut data = PlannerData::default();
+    let created = time::from_local(
+        NaiveDate::from_ymd_opt(2024, 2, 20)
+            .unwrap()
+            .and_hms_opt(9, 0, 0)
+            .unwrap(),
+    );
+
+    // Previous week: 2h of rust
+    session(&mut data, "rust", local(1, 9, 0), 120);
+    // This week: 3h of rust, 45m of email, one session over midnight
+    session(&mut data, "rust", local(4, 9, 0), 90);
+    session(&mut data, "rust", local(5, 23, 30), 90);
+    session(&mut data, "email", local(6, 14, 0), 45);
+
+    let gym = data
+        .add_routine(
+            "Gym",
+            NaiveTime::from_hms_opt(7, 0, 0).unwrap(),
+            30,
+            Frequency::Weekdays,
+            created,
+        )
+        .unwrap();
+    for d in [4, 5, 6] {
+        data.check_routine(gym, day(d)).unwrap();
+    }
+
+    let report = data
+        .add_todo(NewTodo::new("Report", Priority::High), created)
+        .unwrap();
+    data.complete_todo(report, local(5, 10, 0)).unwrap();
+    let slides = data
+        .add_todo(NewTodo::new("Slides", Priority::High), created)
+        .unwrap();
+    data.complete_todo(slides, local(1, 10, 0)).unwrap();
+    data.add_todo(NewTodo::new("Taxes", Priority::High), local(7, 8, 0))
+        .unwrap();
+    data.add_todo(NewTodo::new("Plants", Priority::Low), created)
+        .unwrap();
+    data
+}
+
+#[test]
+fn summary_counts_time_todos_and_routines() {
+    let data = sample_data();
+    let week = Summary::build(&data, Period::Week.range(day(6)));
+
+    assert_eq!(week.range, DateRange::new(day(4), day(10)).unwrap());
+    assert_eq!(
+        week.subjects,
+        vec![("rust".to_string(), 180), ("email".to_string(), 45)]
+    );
+    assert_eq!(week.total_minutes(), 225);
+    // The late session is split at midnight
+    assert_eq!(week.daily_minutes[1], (day(5), 30));
+    assert_eq!(week.daily_minutes[2], (day(6), 105));
+
+    // "Slides" was done before the week started, "Taxes" is still open
+    assert_eq!((week.high_priority_done, week.high_priority_total), (1, 2));
+    assert_eq!(week.completion_rate(), Some(50.0));
+    // Five weekdays, three checked
+    assert_eq!((week.routines_done, week.routines_scheduled), (3, 5));
+    assert_eq!(week.routine_adherence(), Some(60.0));
+}
+
+#[test]
+fn periods_compare_with_the_one_before() {
+    let data = sample_data();
+    let report = Report::for_period(&data, Period::Week, day(6));
+    let monday = NaiveDate::from_ymd_opt(2024, 2, 26).unwrap();
+    assert_eq!(
+        report.previous.range,
+        DateRange::new(monday, day(3)).unwrap()
+    );
+    assert_eq!(report.previous.minutes_for("rust"), 120);
+    assert_eq!(report.previous.minutes_for("email"), 0);
+    assert_eq!(report.subjects(), vec!["rust", "email"]);
+
+    let month = Period::Month.previous_range(day(15));
+    assert_eq!(
+        month,
+        DateRange::new(
+            NaiveDate::from_ymd_opt(2024, 2, 1).unwrap(),
+            NaiveDate::from_ymd_opt(2024, 2, 29).unwrap()
+        )
+        .unwrap()
+    );
+
+    let custom = Report::for_range(&data, DateRange::new(day(4), day(5)).unwrap());
+    assert_eq!(
+        custom.previous.range,
+        DateRange::new(day(2), day(3)).unwrap()
+    );
+}
+
+#[test]
+fn past_reports_do_not_change() {
+    let mut data = sample_data();
+    let range = DateRange::new(day(4), day(10)).unwrap();
+    let before = report::text::render(&Report::for_range(&data, range));
+
+    // Work done later, including a session still running, does not touch the range
+    session(&mut data, "rust", local(12, 9, 0), 60);
+    data.start_session("email", local(13, 9, 0)).unwrap();
+    let after = report::text::render(&Report::for_range(&data, range));
+    assert_eq!(before, after);
+}
+
+#[test]
+fn text_report_is_an_aligned_table() {
+    let data = sample_data();
+    let text = report::text::render(&Report::for_period(&data, Period::Week, day(6)));
+    let lines: Vec<&str> = text.lines().collect();
+    assert_eq!(
+        lines[0],
+        "Report for week 2024-03-04 .. 2024-03-10 (compared with 2024-02-26 .. 2024-03-03)"
+    );
+    assert_eq!(
+        lines[2],
+        "Time per subject          This period  Previous   Change"
+    );
+    assert_eq!(
+        lines[3],
+        "rust                           3h 00m    2h 00m  +1h 00m"
+    );
+    assert_eq!(
+        lines[4],
+        "email                             45m        0m     +45m"
+    );
+    assert!(text.contains("High priority todos done      1/2 50%   1/2 50%   +0 pts"));
+    assert!(text.contains("Routine adherence             3/5 60%    0/5 0%  +60 pts"));
+}
+
+#[test]
+fn csv_report_has_one_metric_per_row() {
+    let mut data = sample_data();
+    session(&mut data, "reading, writing", local(7, 9, 0), 10);
+    let csv = report::csv::render(&Report::for_period(&data, Period::Week, day(6)));
+    let lines: Vec<&str> = csv.lines().collect();
+    assert_eq!(lines[0], "metric,name,current,previous,change");
+    assert!(lines.contains(&"subject_minutes,rust,180,120,60"));
+    assert!(lines.contains(&"subject_minutes,\"reading, writing\",10,0,10"));
+    assert!(lines.contains(&"high_priority_rate,,50.0,50.0,0.0"));
+    assert!(lines.contains(&"routine_adherence,,60.0,0.0,60.0"));
+    assert!(lines.contains(&"day_minutes,2024-03-06,105,,"));
+}
+
+#[test]
+fn html_report_is_self_contained() {
+    let mut data = sample_data();
+    session(&mut data, "<script>", local(7, 9, 0), 10);
+    let html = report::html::render(&Report::for_period(&data, Period::Week, day(6)));
+    assert!(html.starts_with("<!DOCTYPE html>"));
+    assert!(html.contains("<style>"));
+    assert!(html.contains("<svg"));
+    assert!(html.contains("&lt;script&gt;"));
+    assert!(!html.contains("<script"));
+    assert!(!html.contains("<link"));
+    assert!(!html.contains("src="));
+}
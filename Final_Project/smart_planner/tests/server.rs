@@ -0,0 +1,266 @@
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::process;
+use std::sync::mpsc;
+use std::thread;
+
+use reqwest::blocking::{Client, RequestBuilder, Response};
+use serde_json::{json, Value};
+use smart_planner::data::PlannerData;
+use smart_planner::plan::{BlockKind, Plan, PlanBlock};
+use smart_planner::server::ApiServer;
+use smart_planner::store::Store;
+use smart_planner::time;
+
+const TOKEN: &str = "let-me-in";
+
+/// A fresh folder under the system temp folder.
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("smart_planner_server_{}_{}", name, process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// A server on a free loopback port, answering from its own thread.
+struct Api {
+    url: String,
+    dir: PathBuf,
+    client: Client,
+}
+
+impl Api {
+    fn start(name: &str) -> Self {
+        let dir = temp_dir(name);
+        let home = dir.clone();
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            let store = Store::open(&home).unwrap();
+            let server = ApiServer::bind(store, "127.0.0.1:0", TOKEN).unwrap();
+            sender.send(server.address().unwrap()).unwrap();
+            server.run().unwrap();
+        });
+        let address = receiver.recv().unwrap();
+        Api {
+            url: format!("http://{}", address),
+            dir,
+            client: Client::new(),
+        }
+    }
+
+    fn get(&self, path: &str) -> RequestBuilder {
+        self.client
+            .get(format!("{}{}", self.url, path))
+            .bearer_auth(TOKEN)
+    }
+
+    fn post(&self, path: &str, body: Value) -> RequestBuilder {
+        self.client
+            .post(format!("{}{}", self.url, path))
+            .bearer_auth(TOKEN)
+            .json(&body)
+    }
+
+    fn delete(&self, path: &str) -> RequestBuilder {
+        self.client
+            .delete(format!("{}{}", self.url, path))
+            .bearer_auth(TOKEN)
+    }
+
+    /// What the command line would see.
+    fn data(&self) -> PlannerData {
+        Store::open(&self.dir).unwrap().load().unwrap()
+    }
+}
+
+fn send(request: RequestBuilder) -> (u16, Value) {
+    let response: Response = request.send().unwrap();
+    let status = response.status().as_u16();
+    let text = response.text().unwrap();
+    let body = serde_json::from_str(&text).unwrap_or(Value::String(text));
+    (status, body)
+}
+
+#[test]
+fn requests_need_the_token() {
+    let api = Api::start("auth");
+    let url = format!("{}/todos", api.url);
+    let (status, body) = send(api.client.get(&url));
+    assert_eq!(status, 401);
+    assert_eq!(body["error"], "missing or wrong bearer token");
+    let (status, _) = send(api.client.get(&url).bearer_auth("let-me-out"));
+    assert_eq!(status, 401);
+    let (status, body) = send(api.get("/todos"));
+    assert_eq!((status, body), (200, json!([])));
+
+    let store = Store::open(&api.dir).unwrap();
+    assert!(ApiServer::bind(store, "127.0.0.1:0", " ").is_err());
+}
+
+#[test]
+fn todos_go_through_the_planner_rules() {
+    let api = Api::start("todos");
+    let (status, todo) = send(api.post(
+        "/todos",
+        json!({"title": "Write report", "priority": "high", "due": "2024-03-04 17:00", "subject": "work"}),
+    ));
+    assert_eq!(status, 201);
+    assert_eq!(todo["id"], 1);
+    assert_eq!(todo["priority"], "high");
+    let due = time::parse_local_datetime("2024-03-04 17:00").unwrap();
+    assert_eq!(api.data().todos[0].due, Some(due));
+
+    send(api.post("/todos", json!({"title": "Water plants", "priority": "l"})));
+    let (_, list) = send(api.get("/todos"));
+    let titles: Vec<&str> = list
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|todo| todo["title"].as_str().unwrap())
+        .collect();
+    assert_eq!(titles, vec!["Write report", "Water plants"]);
+
+    // Same refusals as on the command line
+    let (status, body) = send(api.post("/todos", json!({"title": "  "})));
+    assert_eq!(status, 400);
+    assert!(body["error"].as_str().unwrap().starts_with("invalid input"));
+    let (status, _) = send(api.post("/todos", json!({"title": "Tidy", "priority": "urgent"})));
+    assert_eq!(status, 400);
+    let (status, _) = send(api.post("/todos", json!({"title": "Tidy", "colour": "red"})));
+    assert_eq!(status, 400);
+    let (status, _) = send(api.post("/todos/1/done", json!({})));
+    assert_eq!(status, 200);
+    let (status, body) = send(api.post("/todos/1/done", json!({})));
+    assert_eq!(status, 400);
+    assert!(body["error"].as_str().unwrap().contains("already done"));
+
+    let (status, _) = send(api.get("/todos/9"));
+    assert_eq!(status, 404);
+    let (status, _) = send(api.delete("/todos/2"));
+    assert_eq!(status, 200);
+    let (_, list) = send(api.get("/todos?all=true"));
+    assert_eq!(list.as_array().unwrap().len(), 1);
+    assert_eq!(api.data().todos.len(), 1);
+
+    let (status, _) = send(api.delete("/todos"));
+    assert_eq!(status, 405);
+    let (status, _) = send(api.get("/nothing"));
+    assert_eq!(status, 404);
+}
+
+#[test]
+fn routines_sessions_and_timers() {
+    let api = Api::start("items");
+    let (status, routine) = send(api.post(
+        "/routines",
+        json!({"name": "Gym", "at": "18:00", "duration_minutes": 60, "days": "mon,wed"}),
+    ));
+    assert_eq!(status, 201);
+    assert_eq!(routine["start"], "18:00:00");
+    let (status, check) = send(api.post("/routines/1/done", json!({"date": "2024-03-04"})));
+    assert_eq!(
+        (status, check),
+        (
+            200,
+            json!({"routine_id": 1, "date": "2024-03-04", "status": "done"})
+        )
+    );
+    let (status, check) = send(api.post(
+        "/routines/1/done",
+        json!({"date": "2024-03-04", "status": "partial"}),
+    ));
+    assert_eq!((status, &check["status"]), (200, &json!("partial")));
+    // 5 March 2024 is a Tuesday
+    let (status, _) = send(api.post("/routines/1/done", json!({"date": "2024-03-05"})));
+    assert_eq!(status, 400);
+    let (status, _) = send(api.post(
+        "/routines",
+        json!({"name": "Nap", "at": "25:00", "duration_minutes": 20}),
+    ));
+    assert_eq!(status, 400);
+
+    let (status, _) = send(api.get("/sessions/running"));
+    assert_eq!(status, 404);
+    let (status, _) = send(api.post("/sessions", json!({"subject": "rust"})));
+    assert_eq!(status, 201);
+    let (status, _) = send(api.post("/sessions", json!({"subject": "email"})));
+    assert_eq!(status, 400);
+    let (_, running) = send(api.get("/sessions/running"));
+    assert_eq!(running["subject"], "rust");
+    let (status, stopped) = send(api.post("/sessions/stop", json!({})));
+    assert_eq!(status, 200);
+    assert!(stopped["end"].is_string());
+
+    let (status, timer) = send(api.post("/timers", json!({"label": "Tea", "minutes": 5})));
+    assert_eq!(status, 201);
+    let (status, _) = send(api.post(
+        "/timers",
+        json!({"label": "Late", "at": "2020-01-01T09:00:00Z"}),
+    ));
+    assert_eq!(status, 400);
+    let (status, _) = send(api.post(
+        "/timers",
+        json!({"label": "Both", "minutes": 5, "at": "2099-01-01"}),
+    ));
+    assert_eq!(status, 400);
+    let (_, timers) = send(api.get("/timers"));
+    assert_eq!(timers.as_array().unwrap().len(), 1);
+    let (status, _) = send(api.delete(&format!("/timers/{}", timer["id"])));
+    assert_eq!(status, 200);
+    assert!(api.data().timers.is_empty());
+}
+
+#[test]
+fn plans_and_reports_can_be_read() {
+    let api = Api::start("reports");
+    let date = time::parse_date("2024-03-04").unwrap();
+    let store = Store::open(&api.dir).unwrap();
+    let mut data = store.load().unwrap();
+    let mut plan = Plan::new(date, "local");
+    let start = time::parse_time("09:00").unwrap();
+    let end = time::parse_time("10:00").unwrap();
+    plan.add_block(PlanBlock::new(start, end, "Write report", BlockKind::Task).unwrap());
+    data.set_plan(plan);
+    let session = time::from_local(date.and_time(start));
+    data.start_session("rust", session).unwrap();
+    data.stop_session(session + chrono::Duration::minutes(45))
+        .unwrap();
+    store.save(&data).unwrap();
+
+    let (status, plan) = send(api.get("/plans/2024-03-04"));
+    assert_eq!(status, 200);
+    assert_eq!(plan["blocks"][0]["title"], "Write report");
+    let (status, _) = send(api.get("/plans/2024-03-05"));
+    assert_eq!(status, 404);
+    let (status, _) = send(api.get("/plans/monday"));
+    assert_eq!(status, 400);
+
+    let (status, report) = send(api.get("/reports?period=day&date=2024-03-04"));
+    assert_eq!(status, 200);
+    assert_eq!(report["current"]["subjects"], json!([["rust", 45]]));
+    assert_eq!(report["previous"]["range"]["start"], "2024-03-03");
+
+    let response = api
+        .get("/reports?from=2024-03-04&to=2024-03-05&format=csv")
+        .send()
+        .unwrap();
+    assert_eq!(
+        response.headers()["content-type"],
+        "text/csv; charset=utf-8"
+    );
+    assert!(response
+        .text()
+        .unwrap()
+        .contains("subject_minutes,rust,45,0,45"));
+    // Values are decoded the way browsers and URL libraries encode them
+    let (status, encoded) = send(api.get("/reports?period=d%61y&date=2024%2D03%2D04"));
+    assert_eq!(status, 200);
+    assert_eq!(encoded, report);
+    let (status, _) = send(api.get("/reports?period=day&date=2024-03-04&format=+text"));
+    assert_eq!(status, 400, "a plus is a space");
+    let (status, _) = send(api.get("/reports?from=2024-03-04"));
+    assert_eq!(status, 400);
+    let (status, _) = send(api.get("/reports?format=pdf"));
+    assert_eq!(status, 400);
+}
@@ -0,0 +1,356 @@
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::process;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use chrono::{DateTime, Duration, NaiveDate, NaiveTime, TimeZone, Utc};
+use smart_planner::budget::BudgetKind;
+use smart_planner::busy::{BusyEvent, BusyTime};
+use smart_planner::data::{NewTodo, PlannerData};
+use smart_planner::plan::{BlockKind, Plan, PlanBlock};
+use smart_planner::pomodoro::{Phase, PomodoroRun, PomodoroSettings};
+use smart_planner::routine::{CheckStatus, Frequency};
+use smart_planner::storage::http::{Saved, Snapshot, Upload};
+use smart_planner::storage::sqlite::SCHEMA_VERSION;
+use smart_planner::storage::{
+    self, HttpStorage, JsonStorage, SqliteStorage, Storage, StorageConfig,
+};
+use smart_planner::todo::Priority;
+use smart_planner::PlannerError;
+use tiny_http::{Header, Method, Response, Server};
+
+/// A fresh folder under the system temp folder.
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("smart_planner_{}_{}", name, process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn at(hour: u32, minute: u32) -> NaiveTime {
+    NaiveTime::from_hms_opt(hour, minute, 0).unwrap()
+}
+
+/// One of everything, with every optional field filled in somewhere.
+fn sample_data() -> PlannerData {
+    let now: DateTime<Utc> =
+        Utc.with_ymd_and_hms(2024, 3, 4, 8, 0, 0).unwrap() + Duration::nanoseconds(123_456_789);
+    let date = NaiveDate::from_ymd_opt(2024, 3, 4).unwrap();
+    let mut data = PlannerData::default();
+
+    let mut report = NewTodo::new("Write report, part \"one\"", Priority::High);
+    report.due = Some(now + Duration::hours(9));
+    report.estimate_minutes = Some(90);
+    report.subject = Some("work".to_string());
+    let report = data.add_todo(report, now).unwrap();
+    let tidy = data
+        .add_todo(NewTodo::new("Tidy desk", Priority::Low), now)
+        .unwrap();
+    data.complete_todo(tidy, now + Duration::minutes(5))
+        .unwrap();
+    let launch = data.add_project("Launch", now).unwrap();
+    data.move_todo(report, Some(launch)).unwrap();
+    let mut outline = NewTodo::new("Outline", Priority::Medium);
+    outline.parent = Some(report);
+    let outline = data.add_todo(outline, now).unwrap();
+    data.block_todo(outline, tidy).unwrap();
+
+    let gym = data
+        .add_routine("Gym", at(18, 0), 60, "mon,wed,fri".parse().unwrap(), now)
+        .unwrap();
+    let breakfast = data
+        .add_routine("Breakfast", at(7, 30), 30, Frequency::Daily, now)
+        .unwrap();
+    data.routines[1].created_at = None;
+    data.check_routine(gym, date).unwrap();
+    data.mark_routine(breakfast, date, CheckStatus::Partial)
+        .unwrap();
+
+    data.set_budget("rust", BudgetKind::Target, 600, date - Duration::days(3))
+        .unwrap();
+    data.set_budget("rust", BudgetKind::Target, 300, date)
+        .unwrap();
+    data.set_budget("meetings", BudgetKind::Cap, 1, date)
+        .unwrap();
+    data.start_session("meetings", now - Duration::hours(1))
+        .unwrap();
+    data.stop_session(now - Duration::minutes(58)).unwrap();
+    data.fire_budget_alerts(&[80, 100], now);
+
+    data.start_session("rust", now).unwrap();
+    data.stop_session(now + Duration::minutes(50)).unwrap();
+    let short = PomodoroSettings {
+        work_minutes: 1,
+        short_break_minutes: 1,
+        ..PomodoroSettings::default()
+    };
+    data.start_pomodoro("reading", short, None, now + Duration::minutes(50))
+        .unwrap();
+    data.advance_pomodoro(now + Duration::minutes(52));
+    data.stop_pomodoro(now + Duration::seconds(52 * 60 + 30))
+        .unwrap();
+    data.start_session("email", now + Duration::hours(1))
+        .unwrap();
+
+    data.add_timer("Tea", now + Duration::minutes(4), None, now)
+        .unwrap();
+    data.add_timer("Report due", now + Duration::hours(2), Some(report), now)
+        .unwrap();
+    data.fire_timers(now + Duration::minutes(4));
+    data.add_daily_timer("Stretch", at(7, 0), None, now)
+        .unwrap();
+
+    let mut plan = Plan::new(date, "local");
+    plan.add_block(PlanBlock::new(at(9, 0), at(10, 30), "Write report", BlockKind::Task).unwrap());
+    plan.blocks[0].todo_id = Some(report);
+    let mut routine = PlanBlock::new(at(18, 0), at(19, 0), "Gym", BlockKind::Routine).unwrap();
+    routine.routine_id = Some(gym);
+    plan.add_block(routine);
+    plan.unscheduled = vec![tidy, 99];
+    data.set_plan(plan);
+    data.set_plan(Plan::new(date.succ_opt().unwrap(), "llm"));
+
+    data.import_busy(vec![
+        BusyEvent {
+            uid: "standup@example.com".to_string(),
+            summary: "Standup, daily".to_string(),
+            time: BusyTime::Timed {
+                start: Utc.with_ymd_and_hms(2024, 3, 4, 9, 30, 0).unwrap(),
+                end: Utc.with_ymd_and_hms(2024, 3, 4, 9, 45, 0).unwrap(),
+            },
+        },
+        BusyEvent {
+            uid: "trip@example.com".to_string(),
+            summary: "Trip".to_string(),
+            time: BusyTime::AllDay {
+                first: date,
+                last: date + Duration::days(2),
+            },
+        },
+    ]);
+    // A run whose work interval is the running email session
+    data.pomodoro = Some(PomodoroRun {
+        subject: "email".to_string(),
+        settings: PomodoroSettings::default(),
+        phase: Phase::Work,
+        phase_start: now + Duration::hours(1),
+        completed: 4,
+        cycles: Some(6),
+        session_id: Some(data.running_session().unwrap().id),
+    });
+    data
+}
+
+#[test]
+fn json_storage_keeps_everything() {
+    let dir = temp_dir("json");
+    let storage = JsonStorage::new(&dir.join("nested/data.json"));
+    assert_eq!(storage.load().unwrap(), PlannerData::default());
+
+    let data = sample_data();
+    storage.save(&data).unwrap();
+    assert_eq!(storage.load().unwrap(), data);
+    assert!(!dir.join("nested/data.json.tmp").exists());
+}
+
+#[test]
+fn sqlite_storage_keeps_everything_and_replaces_on_save() {
+    let dir = temp_dir("sqlite");
+    let path = dir.join("planner.db");
+    let storage = SqliteStorage::open(&path).unwrap();
+    assert_eq!(storage.schema_version().unwrap(), SCHEMA_VERSION);
+    assert_eq!(storage.load().unwrap(), PlannerData::default());
+
+    let mut data = sample_data();
+    storage.save(&data).unwrap();
+    assert_eq!(storage.load().unwrap(), data);
+
+    // Saving again drops what was removed, including plan blocks
+    data.remove_todo(1).unwrap();
+    data.plans.remove(0);
+    storage.save(&data).unwrap();
+    drop(storage);
+    let reopened = SqliteStorage::open(&path).unwrap();
+    assert_eq!(reopened.load().unwrap(), data);
+}
+
+#[test]
+fn sqlite_refuses_a_newer_schema() {
+    let dir = temp_dir("sqlite_newer");
+    let path = dir.join("planner.db");
+    let connection = sqlite::open(&path).unwrap();
+    connection
+        .execute(format!("PRAGMA user_version = {};", SCHEMA_VERSION + 1))
+        .unwrap();
+    drop(connection);
+    assert!(matches!(
+        SqliteStorage::open(&path),
+        Err(PlannerError::Storage(_))
+    ));
+}
+
+/// A sync server on localhost following the protocol of `HttpStorage`.
+struct SyncServer {
+    url: String,
+    state: Arc<Mutex<Option<Snapshot>>>,
+}
+
+impl SyncServer {
+    fn start(token: &'static str) -> Self {
+        let server = Server::http("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/v1", server.server_addr().to_ip().unwrap());
+        let state: Arc<Mutex<Option<Snapshot>>> = Arc::new(Mutex::new(None));
+        let shared = state.clone();
+        thread::spawn(move || {
+            for mut request in server.incoming_requests() {
+                let authorized = request.headers().iter().any(|header| {
+                    header.field.equiv("Authorization")
+                        && header.value.as_str() == format!("Bearer {}", token)
+                });
+                let mut body = String::new();
+                request.as_reader().read_to_string(&mut body).unwrap();
+                let mut state = shared.lock().unwrap();
+                let (status, reply) = if !authorized {
+                    (401, String::new())
+                } else if request.url() != "/v1/data" {
+                    (404, String::new())
+                } else if *request.method() == Method::Get {
+                    match &*state {
+                        Some(snapshot) => (200, serde_json::to_string(snapshot).unwrap()),
+                        None => (404, String::new()),
+                    }
+                } else {
+                    let upload: Upload = serde_json::from_str(&body).unwrap();
+                    let revision = state.as_ref().map_or(0, |snapshot| snapshot.revision);
+                    if upload.base_revision != revision {
+                        (409, String::new())
+                    } else {
+                        *state = Some(Snapshot {
+                            revision: revision + 1,
+                            data: upload.data,
+                        });
+                        let saved = Saved {
+                            revision: revision + 1,
+                        };
+                        (200, serde_json::to_string(&saved).unwrap())
+                    }
+                };
+                let header =
+                    Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..]).unwrap();
+                let response = Response::from_string(reply)
+                    .with_status_code(status)
+                    .with_header(header);
+                request.respond(response).unwrap();
+            }
+        });
+        SyncServer { url, state }
+    }
+}
+
+#[test]
+fn http_storage_syncs_through_the_server() {
+    let server = SyncServer::start("secret");
+    let laptop = HttpStorage::new(&server.url, Some("secret".to_string())).unwrap();
+    assert_eq!(laptop.load().unwrap(), PlannerData::default());
+
+    let data = sample_data();
+    laptop.save(&data).unwrap();
+    assert_eq!(server.state.lock().unwrap().as_ref().unwrap().revision, 1);
+
+    let phone = HttpStorage::new(&server.url, Some("secret".to_string())).unwrap();
+    assert_eq!(phone.load().unwrap(), data);
+}
+
+#[test]
+fn http_storage_does_not_overwrite_newer_changes() {
+    let server = SyncServer::start("secret");
+    let laptop = HttpStorage::new(&server.url, Some("secret".to_string())).unwrap();
+    let phone = HttpStorage::new(&server.url, Some("secret".to_string())).unwrap();
+
+    let mut on_laptop = laptop.load().unwrap();
+    let mut on_phone = phone.load().unwrap();
+    on_laptop
+        .add_todo(NewTodo::new("From the laptop", Priority::Low), Utc::now())
+        .unwrap();
+    laptop.save(&on_laptop).unwrap();
+
+    on_phone
+        .add_todo(NewTodo::new("From the phone", Priority::Low), Utc::now())
+        .unwrap();
+    assert!(matches!(
+        phone.save(&on_phone),
+        Err(PlannerError::Storage(_))
+    ));
+
+    // After loading again the phone can save
+    let mut on_phone = phone.load().unwrap();
+    assert_eq!(on_phone.todos[0].title, "From the laptop");
+    on_phone
+        .add_todo(NewTodo::new("From the phone", Priority::Low), Utc::now())
+        .unwrap();
+    phone.save(&on_phone).unwrap();
+    assert_eq!(laptop.load().unwrap().todos.len(), 2);
+}
+
+#[test]
+fn http_storage_reports_a_wrong_token() {
+    let server = SyncServer::start("secret");
+    let storage = HttpStorage::new(&server.url, Some("wrong".to_string())).unwrap();
+    match storage.load() {
+        Err(PlannerError::Http(message)) => assert!(message.contains("401")),
+        other => panic!("expected an HTTP error, got {:?}", other),
+    }
+}
+
+#[test]
+fn migrate_moves_data_between_every_backend() {
+    let dir = temp_dir("migrate");
+    let server = SyncServer::start("secret");
+    let json = JsonStorage::new(&dir.join("data.json"));
+    let sqlite = SqliteStorage::open(&dir.join("planner.db")).unwrap();
+    let http = HttpStorage::new(&server.url, Some("secret".to_string())).unwrap();
+
+    let data = sample_data();
+    json.save(&data).unwrap();
+    assert_eq!(storage::migrate(&json, &sqlite, false).unwrap(), data);
+    assert_eq!(storage::migrate(&sqlite, &http, false).unwrap(), data);
+
+    let back = JsonStorage::new(&dir.join("back.json"));
+    storage::migrate(&http, &back, false).unwrap();
+    assert_eq!(back.load().unwrap(), data);
+
+    // A target holding data is only replaced when asked to
+    assert!(matches!(
+        storage::migrate(&back, &sqlite, false),
+        Err(PlannerError::Invalid(_))
+    ));
+    storage::migrate(&back, &sqlite, true).unwrap();
+}
+
+#[test]
+fn storage_config_parses_command_line_names() {
+    assert_eq!(
+        "json".parse::<StorageConfig>().unwrap(),
+        StorageConfig::Json { path: None }
+    );
+    assert_eq!(
+        "sqlite:/tmp/planner.db".parse::<StorageConfig>().unwrap(),
+        StorageConfig::Sqlite {
+            path: Some(PathBuf::from("/tmp/planner.db"))
+        }
+    );
+    assert_eq!(
+        "https://sync.example.com".parse::<StorageConfig>().unwrap(),
+        StorageConfig::Http {
+            url: "https://sync.example.com".to_string(),
+            token: None
+        }
+    );
+    assert!("mongodb".parse::<StorageConfig>().is_err());
+
+    let config: StorageConfig =
+        serde_json::from_str(r#"{"backend": "sqlite", "path": "planner.db"}"#).unwrap();
+    assert_eq!(config.to_string(), "sqlite:planner.db");
+}
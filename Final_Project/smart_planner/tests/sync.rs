@@ -0,0 +1,459 @@
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use proptest::prelude::*;
+use serde_json::Value;
+use smart_planner::data::{NewTodo, PlannerData};
+use smart_planner::routine::Frequency;
+use smart_planner::sync::crdt::{Doc, Kind, Op, Stamp};
+use smart_planner::sync::{Delta, SyncState};
+use smart_planner::time;
+use smart_planner::todo::Priority;
+
+fn at(minute: i64) -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2024, 3, 4, 9, 0, 0).unwrap() + Duration::minutes(minute)
+}
+
+/// A planner on one machine.
+struct Machine {
+    state: SyncState,
+    data: PlannerData,
+}
+
+impl Machine {
+    fn new(name: &str) -> Self {
+        Machine {
+            state: SyncState::new(name),
+            data: PlannerData::default(),
+        }
+    }
+
+    /// What `sync export` would write for `peer`, read back as `sync import`
+    /// would.
+    fn export(&mut self, peer: &str) -> Delta {
+        self.state.capture(&self.data).unwrap();
+        let text = serde_json::to_string(&self.state.delta_for_peer(Some(peer))).unwrap();
+        serde_json::from_str(&text).unwrap()
+    }
+
+    fn import(&mut self, delta: &Delta) -> usize {
+        self.state.capture(&self.data).unwrap();
+        let received = self.state.receive(delta).unwrap();
+        self.state.apply_to(&mut self.data).unwrap();
+        received
+    }
+
+    /// The synced items by replicated name, without the local ids that
+    /// differ from machine to machine.
+    fn items(&self) -> BTreeMap<String, Value> {
+        let names: BTreeMap<u64, &String> = self
+            .state
+            .ids
+            .iter()
+            .map(|(name, id)| (*id, name))
+            .collect();
+        let data = serde_json::to_value(&self.data).unwrap();
+        let mut items = BTreeMap::new();
+        for field in ["todos", "routines", "sessions"] {
+            for item in data[field].as_array().unwrap() {
+                let mut item = item.clone();
+                let id = item.as_object_mut().unwrap().remove("id").unwrap();
+                let name = names[&id.as_u64().unwrap()];
+                items.insert(name.clone(), item);
+            }
+        }
+        items
+    }
+}
+
+fn sync(machines: &mut [Machine], from: usize, to: usize) {
+    let peer = machines[to].state.replica.clone();
+    let delta = machines[from].export(&peer);
+    machines[to].import(&delta);
+}
+
+#[test]
+fn edits_on_both_machines_are_kept() {
+    let mut machines = [Machine::new("laptop"), Machine::new("desktop")];
+    let id = machines[0]
+        .data
+        .add_todo(NewTodo::new("Write report", Priority::Low), at(0))
+        .unwrap();
+    sync(&mut machines, 0, 1);
+    let [laptop, desktop] = &mut machines;
+    assert_eq!(desktop.data.todos.len(), 1);
+    assert_eq!(desktop.data.todos[0].title, "Write report");
+    let remote_id = desktop.data.todos[0].id;
+
+    // Different fields of one todo, changed on each machine
+    laptop.data.todo_mut(id).unwrap().priority = Priority::High;
+    desktop.data.complete_todo(remote_id, at(5)).unwrap();
+    desktop
+        .data
+        .add_routine(
+            "Gym",
+            time::parse_time("18:00").unwrap(),
+            60,
+            Frequency::Daily,
+            at(5),
+        )
+        .unwrap();
+    sync(&mut machines, 0, 1);
+    sync(&mut machines, 1, 0);
+    for machine in &machines {
+        let todo = &machine.data.todos[0];
+        assert_eq!((todo.priority, todo.done), (Priority::High, true));
+        assert_eq!(machine.data.routines[0].name, "Gym");
+    }
+    assert_eq!(machines[0].items(), machines[1].items());
+
+    // A second sync with nothing new sends nothing
+    let peer = machines[1].state.replica.clone();
+    assert!(machines[0].export(&peer).ops.is_empty());
+
+    // Deleted on one machine while edited on the other: the delete wins
+    let [laptop, desktop] = &mut machines;
+    laptop.data.remove_todo(id).unwrap();
+    desktop.data.todo_mut(remote_id).unwrap().title = "Write the report".to_string();
+    sync(&mut machines, 1, 0);
+    sync(&mut machines, 0, 1);
+    assert!(machines.iter().all(|machine| machine.data.todos.is_empty()));
+    assert_eq!(machines[0].items(), machines[1].items());
+}
+
+#[test]
+fn projects_and_links_between_todos_follow_the_local_ids() {
+    let mut machines = [Machine::new("laptop"), Machine::new("desktop")];
+    let [laptop, desktop] = &mut machines;
+    // Takes the desktop's first ids, so the same items get other ids there
+    desktop
+        .data
+        .add_todo(NewTodo::new("Water plants", Priority::Low), at(0))
+        .unwrap();
+    let project = laptop.data.add_project("Move house", at(0)).unwrap();
+    let mut pack = NewTodo::new("Pack", Priority::High);
+    pack.project = Some(project);
+    let pack = laptop.data.add_todo(pack, at(1)).unwrap();
+    let mut books = NewTodo::new("Pack books", Priority::Low);
+    books.parent = Some(pack);
+    let books = laptop.data.add_todo(books, at(2)).unwrap();
+    let boxes = laptop
+        .data
+        .add_todo(NewTodo::new("Buy boxes", Priority::Medium), at(3))
+        .unwrap();
+    laptop.data.block_todo(books, boxes).unwrap();
+    sync(&mut machines, 0, 1);
+
+    let desktop = &machines[1].data;
+    let id = |title: &str| {
+        desktop
+            .todos
+            .iter()
+            .find(|todo| todo.title == title)
+            .unwrap()
+            .id
+    };
+    assert_eq!(desktop.projects.len(), 1);
+    assert_ne!(desktop.projects[0].id, project);
+    let subtask = desktop.todo(id("Pack books")).unwrap();
+    assert_eq!(subtask.parent, Some(id("Pack")));
+    assert_eq!(subtask.project, Some(desktop.projects[0].id));
+    assert_eq!(subtask.blocked_by, vec![id("Buy boxes")]);
+
+    // Deleting the blocker on one side drops the link on the other
+    machines[1].data.remove_todo(id("Buy boxes")).unwrap();
+    sync(&mut machines, 1, 0);
+    assert!(machines[0].data.todo(books).unwrap().blocked_by.is_empty());
+    assert_eq!(machines[0].data.todo(books).unwrap().parent, Some(pack));
+}
+
+#[test]
+fn the_same_field_ends_up_the_same_everywhere() {
+    let mut machines = [Machine::new("a"), Machine::new("b")];
+    machines[0]
+        .data
+        .add_todo(NewTodo::new("Call the bank", Priority::Medium), at(0))
+        .unwrap();
+    sync(&mut machines, 0, 1);
+    for (machine, title) in machines.iter_mut().zip(["Call bank", "Phone the bank"]) {
+        machine.data.todos[0].title = title.to_string();
+    }
+    sync(&mut machines, 0, 1);
+    sync(&mut machines, 1, 0);
+    let titles: Vec<&str> = machines
+        .iter()
+        .map(|machine| machine.data.todos[0].title.as_str())
+        .collect();
+    assert_eq!(titles[0], titles[1]);
+
+    // Sessions sync too, named after the machine that started them
+    let session = machines[1].data.start_session("rust", at(10)).unwrap();
+    machines[1].data.stop_session(at(40)).unwrap();
+    sync(&mut machines, 1, 0);
+    let synced = &machines[0].data.sessions[0];
+    assert_eq!(
+        (synced.subject.as_str(), synced.end),
+        ("rust", Some(at(40)))
+    );
+    assert_eq!(machines[0].state.ids[&format!("b-{}", session)], synced.id);
+    // Changes already seen are skipped
+    let again = machines[1].state.delta_for(&Default::default());
+    assert_eq!(machines[0].import(&again), 0);
+}
+
+/// Something one machine does before the next sync.
+#[derive(Debug, Clone)]
+enum Action {
+    AddTodo(u8),
+    SetPriority(usize, u8),
+    Rename(usize, u8),
+    Complete(usize),
+    Delete(usize),
+    AddRoutine(u8),
+    DeleteRoutine(usize),
+    Session(u8),
+    Sync(usize),
+}
+
+fn action() -> impl Strategy<Value = Action> {
+    prop_oneof![
+        3 => any::<u8>().prop_map(Action::AddTodo),
+        2 => (any::<usize>(), any::<u8>()).prop_map(|(n, p)| Action::SetPriority(n, p)),
+        2 => (any::<usize>(), any::<u8>()).prop_map(|(n, t)| Action::Rename(n, t)),
+        2 => any::<usize>().prop_map(Action::Complete),
+        2 => any::<usize>().prop_map(Action::Delete),
+        1 => any::<u8>().prop_map(Action::AddRoutine),
+        1 => any::<usize>().prop_map(Action::DeleteRoutine),
+        1 => any::<u8>().prop_map(Action::Session),
+        3 => any::<usize>().prop_map(Action::Sync),
+    ]
+}
+
+fn perform(machines: &mut [Machine], who: usize, action: &Action, minute: i64) {
+    let count = machines.len();
+    let data = &mut machines[who].data;
+    let pick = |n: usize, len: usize| (len > 0).then(|| n % len);
+    match action {
+        Action::AddTodo(n) => {
+            let title = format!("Task {}", n);
+            data.add_todo(NewTodo::new(&title, Priority::Medium), at(minute))
+                .unwrap();
+        }
+        Action::SetPriority(n, p) => {
+            if let Some(i) = pick(*n, data.todos.len()) {
+                data.todos[i].priority =
+                    [Priority::Low, Priority::Medium, Priority::High][*p as usize % 3];
+            }
+        }
+        Action::Rename(n, t) => {
+            if let Some(i) = pick(*n, data.todos.len()) {
+                data.todos[i].title = format!("Renamed {}", t);
+            }
+        }
+        Action::Complete(n) => {
+            if let Some(i) = pick(*n, data.todos.len()) {
+                let id = data.todos[i].id;
+                let _ = data.complete_todo(id, at(minute));
+            }
+        }
+        Action::Delete(n) => {
+            if let Some(i) = pick(*n, data.todos.len()) {
+                let id = data.todos[i].id;
+                data.remove_todo(id).unwrap();
+            }
+        }
+        Action::AddRoutine(n) => {
+            let start = time::parse_time("07:30").unwrap();
+            data.add_routine(
+                &format!("Routine {}", n),
+                start,
+                30,
+                Frequency::Daily,
+                at(minute),
+            )
+            .unwrap();
+        }
+        Action::DeleteRoutine(n) => {
+            if let Some(i) = pick(*n, data.routines.len()) {
+                let id = data.routines[i].id;
+                data.remove_routine(id).unwrap();
+            }
+        }
+        Action::Session(n) => {
+            if data.running_session().is_some() {
+                data.stop_session(at(minute)).unwrap();
+            } else {
+                data.start_session(&format!("subject {}", n % 4), at(minute))
+                    .unwrap();
+            }
+        }
+        Action::Sync(n) => {
+            let to = (who + 1 + n % (count - 1)) % count;
+            sync(machines, who, to);
+        }
+    }
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(64))]
+
+    /// Three machines edit and sync in a random order; once every machine has
+    /// heard from every other, they all hold the same items.
+    #[test]
+    fn machines_converge(steps in prop::collection::vec((0..3usize, action()), 1..60)) {
+        let mut machines = [Machine::new("a"), Machine::new("b"), Machine::new("c")];
+        for (minute, (who, action)) in steps.iter().enumerate() {
+            perform(&mut machines, *who, action, minute as i64);
+        }
+        for _ in 0..2 {
+            for from in 0..3 {
+                for to in 0..3 {
+                    if from != to {
+                        sync(&mut machines, from, to);
+                    }
+                }
+            }
+        }
+        let items = machines[0].items();
+        for machine in &machines[1..] {
+            prop_assert_eq!(&machine.items(), &items);
+            prop_assert_eq!(&machine.state.doc, &machines[0].state.doc);
+        }
+        prop_assert_eq!(machines[0].data.todos.len() + machines[0].data.routines.len()
+            + machines[0].data.sessions.len(), items.len());
+    }
+
+    /// Operations give the same document in any order, applied any number of
+    /// times, and merging documents does not depend on the order either.
+    #[test]
+    fn operations_commute(
+        steps in prop::collection::vec((0..3usize, action()), 1..40),
+        order in prop::collection::vec(any::<prop::sample::Index>(), 0..80),
+    ) {
+        let mut machines = [Machine::new("a"), Machine::new("b"), Machine::new("c")];
+        for (minute, (who, action)) in steps.iter().enumerate() {
+            perform(&mut machines, *who, action, minute as i64);
+        }
+        for machine in machines.iter_mut() {
+            machine.state.capture(&machine.data).unwrap();
+        }
+        let all: Vec<Op> = machines.iter().flat_map(|machine| machine.state.log.clone()).collect();
+        prop_assume!(!all.is_empty());
+
+        let mut in_order = Doc::default();
+        for op in &all {
+            in_order.apply(op);
+        }
+        // Shuffled, with some operations repeated
+        let mut shuffled = all.clone();
+        for (i, index) in order.iter().enumerate() {
+            let len = shuffled.len();
+            let j = index.index(len);
+            shuffled.swap(i % len, j);
+            if i % 3 == 0 {
+                shuffled.push(all[j % all.len()].clone());
+            }
+        }
+        let mut any_order = Doc::default();
+        for op in shuffled.iter().rev() {
+            any_order.apply(op);
+        }
+        prop_assert_eq!(&any_order, &in_order);
+
+        let docs: Vec<Doc> = machines.iter().map(|machine| machine.state.doc.clone()).collect();
+        let mut forward = docs[0].clone();
+        forward.merge(&docs[1]);
+        forward.merge(&docs[2]);
+        let mut backward = docs[2].clone();
+        backward.merge(&docs[1]);
+        backward.merge(&docs[0]);
+        backward.merge(&docs[1]);
+        prop_assert_eq!(&forward, &backward);
+        prop_assert_eq!(&forward, &in_order);
+    }
+}
+
+#[test]
+fn later_stamps_win_and_removes_only_undo_what_they_saw() {
+    let set = |value: &str, clock, replica: &str| Op::Set {
+        item: "a-1".to_string(),
+        kind: Kind::Todo,
+        field: "title".to_string(),
+        value: Value::from(value),
+        stamp: Stamp::new(clock, replica),
+    };
+    let add = |clock, replica: &str| Op::Add {
+        item: "a-1".to_string(),
+        kind: Kind::Todo,
+        stamp: Stamp::new(clock, replica),
+    };
+    let mut doc = Doc::default();
+    for op in [
+        add(1, "a"),
+        set("later", 3, "a"),
+        set("earlier", 2, "b"),
+        set("tie", 3, "b"),
+    ] {
+        doc.apply(&op);
+    }
+    let entry = &doc.entries["a-1"];
+    assert_eq!(entry.fields["title"].value, "tie");
+
+    // A remove that saw the first add, while another machine added it again
+    doc.apply(&Op::Remove {
+        item: "a-1".to_string(),
+        kind: Kind::Todo,
+        tags: [Stamp::new(1, "a")].into_iter().collect(),
+        stamp: Stamp::new(4, "a"),
+    });
+    assert!(!doc.entries["a-1"].is_alive());
+    doc.apply(&add(4, "b"));
+    assert!(doc.entries["a-1"].is_alive());
+}
+
+#[test]
+fn planners_swap_changes_over_http() {
+    use smart_planner::server::ApiServer;
+    use smart_planner::store::Store;
+    use std::sync::mpsc;
+    use std::{env, fs, process, thread};
+
+    let dir = |name: &str| {
+        let dir = env::temp_dir().join(format!("smart_planner_sync_{}_{}", name, process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    };
+    let desktop = dir("desktop");
+    let home = desktop.clone();
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let store = Store::open(&home).unwrap();
+        let mut data = store.load().unwrap();
+        data.add_todo(NewTodo::new("Back up photos", Priority::High), at(0))
+            .unwrap();
+        store.save(&data).unwrap();
+        let server = ApiServer::bind(store, "127.0.0.1:0", "secret").unwrap();
+        sender.send(server.address().unwrap()).unwrap();
+        server.run().unwrap();
+    });
+    let url = format!("http://{}", receiver.recv().unwrap());
+
+    let mut laptop = Machine::new("laptop");
+    laptop
+        .data
+        .add_todo(NewTodo::new("Pack charger", Priority::Low), at(1))
+        .unwrap();
+    laptop.state.capture(&laptop.data).unwrap();
+    let delta = laptop.state.delta_for_peer(None);
+    assert!(smart_planner::sync::exchange(&url, "wrong", &delta).is_err());
+    let answer = smart_planner::sync::exchange(&url, "secret", &delta).unwrap();
+    laptop.import(&answer);
+
+    let mut titles: Vec<&str> = laptop.data.todos.iter().map(|t| t.title.as_str()).collect();
+    titles.sort();
+    assert_eq!(titles, vec!["Back up photos", "Pack charger"]);
+    let saved = Store::open(&desktop).unwrap().load().unwrap();
+    assert_eq!(saved.todos.len(), 2);
+    assert!(desktop.join("sync.json").exists());
+}
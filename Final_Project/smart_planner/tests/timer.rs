@@ -0,0 +1,55 @@
+use chrono::{Duration, TimeZone, Utc};
+use smart_planner::data::{NewTodo, PlannerData};
+use smart_planner::todo::Priority;
+use smart_planner::PlannerError;
+
+#[test]
+fn timers_fire_once_in_order() {
+    let now = Utc.with_ymd_and_hms(2024, 3, 4, 9, 0, 0).unwrap();
+    let mut data = PlannerData::default();
+    let later = data
+        .add_timer("Stretch", now + Duration::minutes(50), None, now)
+        .unwrap();
+    let soon = data
+        .add_timer("Tea", now + Duration::minutes(4), None, now)
+        .unwrap();
+    let pending: Vec<u64> = data.pending_timers().iter().map(|t| t.id).collect();
+    assert_eq!(pending, vec![soon, later]);
+
+    assert!(data.fire_timers(now + Duration::minutes(3)).is_empty());
+    let fired = data.fire_timers(now + Duration::minutes(60));
+    let fired: Vec<u64> = fired.iter().map(|t| t.id).collect();
+    assert_eq!(fired, vec![soon, later]);
+    assert!(data.fire_timers(now + Duration::minutes(61)).is_empty());
+    assert!(data.pending_timers().is_empty());
+}
+
+#[test]
+fn timers_are_validated_and_can_be_cancelled() {
+    let now = Utc.with_ymd_and_hms(2024, 3, 4, 9, 0, 0).unwrap();
+    let mut data = PlannerData::default();
+    assert!(matches!(
+        data.add_timer("Late", now, None, now),
+        Err(PlannerError::Invalid(_))
+    ));
+    assert!(matches!(
+        data.add_timer(" ", now + Duration::minutes(1), None, now),
+        Err(PlannerError::Invalid(_))
+    ));
+    assert!(matches!(
+        data.add_timer("Deadline", now + Duration::hours(1), Some(7), now),
+        Err(PlannerError::NotFound(_))
+    ));
+
+    let todo = data
+        .add_todo(NewTodo::new("Report", Priority::High), now)
+        .unwrap();
+    let id = data
+        .add_timer("Deadline", now + Duration::hours(1), Some(todo), now)
+        .unwrap();
+    assert_eq!(data.cancel_timer(id).unwrap().todo_id, Some(todo));
+    assert!(matches!(
+        data.cancel_timer(id),
+        Err(PlannerError::NotFound(_))
+    ));
+}
@@ -0,0 +1,285 @@
+use chrono::{DateTime, Duration, NaiveDate, NaiveTime, Utc};
+use smart_planner::data::{NewTodo, PlannerData};
+use smart_planner::time;
+use smart_planner::todo::markdown;
+use smart_planner::todo::todotxt::{self, Entry};
+use smart_planner::todo::{Merged, Priority};
+use smart_planner::PlannerError;
+
+fn at(minute: i64) -> DateTime<Utc> {
+    time::from_local(
+        NaiveDate::from_ymd_opt(2024, 3, 4)
+            .unwrap()
+            .and_hms_opt(9, 0, 0)
+            .unwrap(),
+    ) + Duration::minutes(minute)
+}
+
+fn local(date: &str, time: &str) -> DateTime<Utc> {
+    time::from_local(
+        NaiveDate::parse_from_str(date, "%Y-%m-%d")
+            .unwrap()
+            .and_time(NaiveTime::parse_from_str(time, "%H:%M").unwrap()),
+    )
+}
+
+#[test]
+fn todo_txt_lines_are_read_and_written() {
+    let entry = todotxt::parse_line(
+        "(A) 2024-03-01 Call +Big_Move the landlord @phone @home due:2024-03-08 est:1h30m id:7",
+    )
+    .unwrap();
+    assert_eq!(
+        entry,
+        Entry {
+            title: "Call the landlord @home".to_string(),
+            priority: Some(Priority::High),
+            done: false,
+            completed: None,
+            created: NaiveDate::from_ymd_opt(2024, 3, 1),
+            project: Some("Big_Move".to_string()),
+            context: Some("phone".to_string()),
+            due: Some(local("2024-03-08", "23:59")),
+            estimate_minutes: Some(90),
+            id: Some(7),
+        }
+    );
+    let done = todotxt::parse_line("x 2024-03-05 2024-03-01 Tidy desk pri:C").unwrap();
+    assert!(done.done);
+    assert_eq!(done.completed, NaiveDate::from_ymd_opt(2024, 3, 5));
+    assert_eq!(done.priority, Some(Priority::Low));
+    assert_eq!(
+        todotxt::parse_line("(D) x").unwrap().priority,
+        Some(Priority::Low)
+    );
+    assert!(todotxt::parse_line("(B) 2024-03-01 +Move").is_err());
+    assert!(todotxt::parse_line("Pay due:someday").is_err());
+    assert!(todotxt::parse_line("Pay id:first").is_err());
+    let escaped = todotxt::parse_line("Read \\+1 \\@home \\due:soon \\\\n +Books").unwrap();
+    assert_eq!(escaped.title, "Read +1 @home due:soon \\n");
+    assert_eq!(escaped.project.as_deref(), Some("Books"));
+
+    // Everything the planner writes it reads back the same
+    let mut data = PlannerData::default();
+    let project = data.add_project("Big move", at(0)).unwrap();
+    let mut call = NewTodo::new("Call the landlord", Priority::High);
+    call.project = Some(project);
+    call.subject = Some("phone".to_string());
+    call.due = Some(local("2024-03-08", "17:00"));
+    call.estimate_minutes = Some(20);
+    data.add_todo(call, at(0)).unwrap();
+    let tidy = data
+        .add_todo(NewTodo::new("Tidy desk", Priority::Low), at(0))
+        .unwrap();
+    data.complete_todo(tidy, at(60)).unwrap();
+    let text = todotxt::export(&data, None);
+    assert_eq!(
+        text,
+        "(A) 2024-03-04 Call the landlord +Big_move @phone due:2024-03-08T17:00 est:20 id:2\n\
+         x 2024-03-04 2024-03-04 Tidy desk id:3 pri:C\n"
+    );
+
+    let mut copy = PlannerData::default();
+    let merged = todotxt::import(&mut copy, &text, None, at(90)).unwrap();
+    assert_eq!(
+        merged,
+        Merged {
+            added: 2,
+            projects: 1,
+            ..Merged::default()
+        }
+    );
+    assert_eq!(copy.projects[0].name, "Big move");
+    assert_eq!(todotxt::export(&copy, None), text);
+}
+
+#[test]
+fn importing_todo_txt_updates_todos_with_the_same_title() {
+    let mut data = PlannerData::default();
+    let report = data
+        .add_todo(NewTodo::new("Write report", Priority::Low), at(0))
+        .unwrap();
+    let tidy = data
+        .add_todo(NewTodo::new("Tidy desk", Priority::Low), at(0))
+        .unwrap();
+    data.complete_todo(tidy, at(5)).unwrap();
+    let milk = data
+        .add_todo(NewTodo::new("Buy milk", Priority::Low), at(0))
+        .unwrap();
+
+    let text = "\
+(A) write  REPORT @work
+tidy desk
+
+x 2024-03-05 Buy milk
+Plan trip +Holiday
+";
+    let merged = todotxt::import(&mut data, text, None, at(10)).unwrap();
+    assert_eq!(
+        merged,
+        Merged {
+            added: 1,
+            updated: 3,
+            unchanged: 0,
+            projects: 1,
+        }
+    );
+    let report = data.todo(report).unwrap();
+    assert_eq!(report.priority, Priority::High);
+    assert_eq!(report.subject.as_deref(), Some("work"));
+    assert!(!data.todo(tidy).unwrap().done);
+    assert_eq!(
+        data.todo(milk).unwrap().completed_at,
+        Some(local("2024-03-05", "00:00"))
+    );
+    let trip = data.todos.last().unwrap();
+    assert_eq!(
+        (trip.title.as_str(), trip.priority),
+        ("Plan trip", Priority::Medium)
+    );
+    assert_eq!(data.project(trip.project.unwrap()).unwrap().name, "Holiday");
+
+    // The same file again changes nothing
+    let merged = todotxt::import(&mut data, text, None, at(20)).unwrap();
+    assert_eq!((merged.updated, merged.unchanged), (0, 4));
+}
+
+#[test]
+fn syncing_a_markdown_checklist_keeps_the_text_around_it() {
+    let mut data = PlannerData::default();
+    let project = data.add_project("Release", at(0)).unwrap();
+    let mut notes = NewTodo::new("Write release notes", Priority::Medium);
+    notes.project = Some(project);
+    let notes = data.add_todo(notes, at(0)).unwrap();
+    let mut blog = NewTodo::new("Blog post", Priority::Medium);
+    blog.parent = Some(notes);
+    let blog = data.add_todo(blog, at(0)).unwrap();
+    let mut tag = NewTodo::new("Tag the release", Priority::High);
+    tag.project = Some(project);
+    let tag = data.add_todo(tag, at(0)).unwrap();
+    data.complete_todo(tag, at(5)).unwrap();
+    data.add_todo(NewTodo::new("Not in the project", Priority::Low), at(0))
+        .unwrap();
+
+    let file = "\
+# Release 1.2
+
+Things left before we ship:
+
+* [ ] Tag the release
+* [ ] Write release notes
+  * [x] Changelog
+* [X] Update docs
+
+Ask in #release if unsure.
+- [ ] a list item [ ] that is not
+- [] not an item either
+";
+    let synced = markdown::sync(&mut data, file, Some(project), at(10)).unwrap();
+    assert_eq!(
+        synced.text,
+        "\
+# Release 1.2
+
+Things left before we ship:
+
+* [x] Tag the release
+* [ ] Write release notes
+  * [x] Changelog
+  * [ ] Blog post
+* [X] Update docs
+
+Ask in #release if unsure.
+- [ ] a list item [ ] that is not
+- [] not an item either
+"
+    );
+    assert_eq!(synced.written, 2);
+    // "a list item" is a checklist item too, added like the others
+    assert_eq!(
+        synced.merged,
+        Merged {
+            added: 3,
+            updated: 0,
+            unchanged: 2,
+            projects: 0,
+        }
+    );
+    let changelog = data
+        .todos
+        .iter()
+        .find(|todo| todo.title == "Changelog")
+        .unwrap();
+    assert_eq!((changelog.parent, changelog.done), (Some(notes), true));
+    assert_eq!(changelog.project, Some(project));
+
+    // Ticking a parent in the file completes it with its subtasks
+    let file = synced
+        .text
+        .replace("* [ ] Write release notes", "* [x] Write release notes");
+    let synced = markdown::sync(&mut data, &file, Some(project), at(20)).unwrap();
+    assert!(data.todo(notes).unwrap().done && data.todo(blog).unwrap().done);
+    assert!(synced.text.contains("  * [x] Blog post\n"));
+    assert_eq!(synced.merged.updated, 2);
+
+    // A file without a checklist gets one at the end
+    let mut data = PlannerData::default();
+    data.add_todo(NewTodo::new("Water plants", Priority::Low), at(0))
+        .unwrap();
+    let synced = markdown::sync(&mut data, "Notes\r\n", None, at(0)).unwrap();
+    assert_eq!(synced.text, "Notes\r\n\r\n- [ ] Water plants\r\n");
+    assert_eq!(
+        markdown::export(&data, None),
+        "- [ ] Water plants\n".to_string()
+    );
+}
+
+#[test]
+fn todo_txt_keeps_todos_with_the_same_title_apart() {
+    let mut data = PlannerData::default();
+    let first = data
+        .add_todo(NewTodo::new("Call mum", Priority::Low), at(0))
+        .unwrap();
+    let second = data
+        .add_todo(NewTodo::new("Call mum", Priority::Low), at(0))
+        .unwrap();
+    let odd = data
+        .add_todo(
+            NewTodo::new("Sort +1 votes @noon due:later est:x \\o/", Priority::Low),
+            at(0),
+        )
+        .unwrap();
+    data.complete_todo(second, at(5)).unwrap();
+
+    // Each line finds its todo by id, even with the title changed
+    let text = todotxt::export(&data, None).replace("Call mum id:1", "Call dad id:1");
+    let merged = todotxt::import(&mut data, &text, None, at(10)).unwrap();
+    assert_eq!((merged.added, merged.updated, merged.unchanged), (0, 0, 3));
+    assert!(!data.todo(first).unwrap().done && data.todo(second).unwrap().done);
+    assert_eq!(
+        data.todo(odd).unwrap().title,
+        "Sort +1 votes @noon due:later est:x \\o/"
+    );
+    assert!(data.todo(odd).unwrap().subject.is_none());
+
+    // Without ids, each title matches one todo
+    let mut copy = PlannerData::default();
+    let text = "Call mum\nCall mum\n";
+    assert_eq!(
+        todotxt::import(&mut copy, text, None, at(0)).unwrap().added,
+        2
+    );
+    let merged = todotxt::import(&mut copy, text, None, at(5)).unwrap();
+    assert_eq!((merged.added, merged.unchanged), (0, 2));
+    assert_eq!(copy.todos.len(), 2);
+
+    // The same rules as any other todo
+    match todotxt::import(&mut copy, "Call mum est:0\n", None, at(10)) {
+        Err(PlannerError::Invalid(message)) => assert!(message.contains("at least one minute")),
+        other => panic!("expected est:0 to be refused, got {:?}", other),
+    }
+    assert!(copy
+        .todos
+        .iter()
+        .all(|todo| todo.estimate_minutes.is_none()));
+}
@@ -0,0 +1,233 @@
+use std::env;
+use std::fs;
+use std::os::unix::net::UnixListener;
+use std::path::{Path, PathBuf};
+use std::process;
+
+use chrono::Utc;
+use smart_planner::daemon;
+use smart_planner::data::NewTodo;
+use smart_planner::store::Store;
+use smart_planner::todo::Priority;
+use smart_planner::vault::{self, KdfParams, Vault};
+use smart_planner::PlannerError;
+
+/// A fresh folder under the system temp folder.
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("smart_planner_vault_{}_{}", name, process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Quick to derive, so the tests do not wait on Argon2.
+fn cheap() -> KdfParams {
+    KdfParams {
+        memory_kib: 64,
+        iterations: 1,
+        parallelism: 1,
+    }
+}
+
+/// A planner with one todo saved twice, so there is some history too.
+fn planner(dir: &Path) {
+    let store = Store::open(dir).unwrap();
+    let mut data = store.load().unwrap();
+    data.add_todo(NewTodo::new("Renew passport", Priority::High), Utc::now())
+        .unwrap();
+    store.save(&data).unwrap();
+    data.complete_todo(1, Utc::now()).unwrap();
+    store.save(&data).unwrap();
+}
+
+/// Every file under `dir`, read as text where it can be.
+fn contents(dir: &Path) -> Vec<(PathBuf, String)> {
+    let mut found = Vec::new();
+    for entry in fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.is_dir() {
+            found.extend(contents(&path));
+        } else {
+            let text = String::from_utf8_lossy(&fs::read(&path).unwrap()).into_owned();
+            found.push((path, text));
+        }
+    }
+    found
+}
+
+#[test]
+fn a_locked_planner_holds_no_plain_text() {
+    let dir = temp_dir("round_trip");
+    planner(&dir);
+    let before = Store::open(&dir).unwrap().load().unwrap();
+
+    assert!(vault::enable(&dir, "short", cheap()).is_err());
+    let encrypted = vault::enable(&dir, "correct horse", cheap()).unwrap();
+    assert!(encrypted >= 2, "the data and the history are encrypted");
+    assert!(vault::is_locked(&dir).unwrap());
+    for (path, text) in contents(&dir) {
+        assert!(
+            !text.contains("Renew passport"),
+            "{} is readable",
+            path.display()
+        );
+    }
+    match Store::open(&dir) {
+        Err(PlannerError::Encryption(message)) => assert!(message.contains("unlock")),
+        Err(other) => panic!("unexpected error {}", other),
+        Ok(_) => panic!("a locked planner opened"),
+    }
+
+    match vault::unlock(&dir, "wrong horse") {
+        Err(PlannerError::Encryption(message)) => assert_eq!(message, "wrong passphrase"),
+        other => panic!("unexpected {:?}", other.map(|_| ())),
+    }
+    assert!(
+        vault::is_locked(&dir).unwrap(),
+        "a failed unlock changes nothing"
+    );
+
+    assert_eq!(vault::unlock(&dir, "correct horse").unwrap(), encrypted);
+    let store = Store::open(&dir).unwrap();
+    assert_eq!(store.load().unwrap(), before);
+    assert_eq!(store.history().current().unwrap(), before);
+
+    // Locking again only needs the passphrase, and turning it off for good
+    // leaves the files as they were
+    assert_eq!(vault::lock(&dir, "correct horse").unwrap(), encrypted);
+    assert_eq!(vault::disable(&dir, "correct horse").unwrap(), encrypted);
+    assert!(Vault::load(&dir).unwrap().is_none());
+    assert_eq!(Store::open(&dir).unwrap().load().unwrap(), before);
+}
+
+#[test]
+fn rotating_replaces_the_key_and_the_passphrase() {
+    let dir = temp_dir("rotate");
+    planner(&dir);
+    vault::enable(&dir, "first passphrase", cheap()).unwrap();
+    let data_file = dir.join("data.json.locked");
+    let sealed = fs::read(&data_file).unwrap();
+
+    assert!(vault::rotate(&dir, "second passphrase", "third passphrase", cheap()).is_err());
+    vault::rotate(&dir, "first passphrase", "second passphrase", cheap()).unwrap();
+    assert_ne!(fs::read(&data_file).unwrap(), sealed);
+    assert!(vault::unlock(&dir, "first passphrase").is_err());
+
+    // A copy made before the rotation cannot be read with the new key
+    let backup = temp_dir("rotate_backup");
+    fs::copy(&data_file, backup.join("data.json.locked")).unwrap();
+    fs::write(&data_file, &sealed).unwrap();
+    match vault::unlock(&dir, "second passphrase") {
+        Err(PlannerError::Encryption(message)) => assert!(message.contains("another key")),
+        other => panic!("unexpected {:?}", other.map(|_| ())),
+    }
+    fs::copy(backup.join("data.json.locked"), &data_file).unwrap();
+    vault::unlock(&dir, "second passphrase").unwrap();
+    assert_eq!(Store::open(&dir).unwrap().load().unwrap().todos.len(), 1);
+
+    // Rotating while unlocked only changes the vault
+    assert_eq!(
+        vault::rotate(&dir, "second passphrase", "second passphrase", cheap()).unwrap(),
+        0
+    );
+    vault::lock(&dir, "second passphrase").unwrap();
+    vault::unlock(&dir, "second passphrase").unwrap();
+}
+
+#[test]
+fn changed_or_swapped_files_are_refused() {
+    let dir = temp_dir("tamper");
+    planner(&dir);
+    vault::enable(&dir, "correct horse", cheap()).unwrap();
+    let data_file = dir.join("data.json.locked");
+    let events = dir.join("history").join("events.jsonl.locked");
+    let sealed = fs::read(&data_file).unwrap();
+
+    let mut flipped = sealed.clone();
+    let last = flipped.len() - 1;
+    flipped[last] ^= 1;
+    fs::write(&data_file, &flipped).unwrap();
+    match vault::unlock(&dir, "correct horse") {
+        Err(PlannerError::Encryption(message)) => assert_eq!(
+            message,
+            "data.json was changed or damaged after it was encrypted"
+        ),
+        other => panic!("unexpected {:?}", other.map(|_| ())),
+    }
+
+    // Each file is tied to its place in the folder
+    fs::write(&data_file, fs::read(&events).unwrap()).unwrap();
+    assert!(vault::unlock(&dir, "correct horse").is_err());
+    fs::write(&data_file, b"{\"todos\": []}").unwrap();
+    match vault::unlock(&dir, "correct horse") {
+        Err(PlannerError::Encryption(message)) => {
+            assert!(message.contains("not an encrypted planner file"))
+        }
+        other => panic!("unexpected {:?}", other.map(|_| ())),
+    }
+
+    // A damaged file found late leaves the others encrypted
+    fs::write(&data_file, &sealed).unwrap();
+    let sealed_events = fs::read(&events).unwrap();
+    let mut damaged = sealed_events.clone();
+    let end = damaged.len() - 1;
+    damaged[end] ^= 1;
+    fs::write(&events, &damaged).unwrap();
+    assert!(vault::unlock(&dir, "correct horse").is_err());
+    assert!(vault::is_locked(&dir).unwrap());
+    assert!(data_file.exists());
+    assert!(!dir.join("data.json").exists());
+    fs::write(&events, &sealed_events).unwrap();
+
+    let vault_file = dir.join(vault::VAULT_FILE);
+    let saved = fs::read_to_string(&vault_file).unwrap();
+    let mut changed: serde_json::Value = serde_json::from_str(&saved).unwrap();
+    changed["salt"] = "é".into();
+    fs::write(&vault_file, changed.to_string()).unwrap();
+    match vault::unlock(&dir, "correct horse") {
+        Err(PlannerError::Encryption(message)) => assert_eq!(
+            message,
+            "vault.json was changed or damaged after it was encrypted"
+        ),
+        other => panic!("unexpected {:?}", other.map(|_| ())),
+    }
+
+    // The rest of the vault is authenticated with the keys
+    for (field, value) in [
+        ("locked", serde_json::json!(false)),
+        (
+            "kdf",
+            serde_json::json!({"memory_kib": 8, "iterations": 1, "parallelism": 1}),
+        ),
+    ] {
+        let mut changed: serde_json::Value = serde_json::from_str(&saved).unwrap();
+        changed[field] = value;
+        fs::write(&vault_file, changed.to_string()).unwrap();
+        assert!(
+            vault::is_locked(&dir).unwrap(),
+            "the files are still encrypted"
+        );
+        assert!(vault::unlock(&dir, "correct horse").is_err());
+        assert!(data_file.exists());
+    }
+
+    fs::write(&vault_file, saved).unwrap();
+    vault::unlock(&dir, "correct horse").unwrap();
+    assert_eq!(Store::open(&dir).unwrap().load().unwrap().todos.len(), 1);
+}
+
+#[test]
+fn the_daemon_folder_is_left_plain() {
+    let dir = temp_dir("daemon");
+    planner(&dir);
+    let socket = daemon::socket_path(&dir);
+    fs::create_dir_all(socket.parent().unwrap()).unwrap();
+    let _running = UnixListener::bind(&socket).unwrap();
+    let reminded = socket.with_file_name("reminded.json");
+    fs::write(&reminded, "[]").unwrap();
+
+    assert_eq!(vault::enable(&dir, "correct horse", cheap()).unwrap(), 2);
+    assert!(socket.exists());
+    assert_eq!(fs::read_to_string(&reminded).unwrap(), "[]");
+    assert_eq!(vault::unlock(&dir, "correct horse").unwrap(), 2);
+}
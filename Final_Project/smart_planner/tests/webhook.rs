@@ -0,0 +1,205 @@
+use std::collections::VecDeque;
+use std::env;
+use std::fs;
+use std::net::TcpListener;
+use std::path::PathBuf;
+use std::process;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use smart_planner::data::{NewTodo, PlannerData};
+use smart_planner::todo::Priority;
+use smart_planner::webhook::{
+    self, Event, EventKind, Sender, WebhookConfig, DELIVERY_HEADER, EVENT_HEADER, SIGNATURE_HEADER,
+};
+use tiny_http::{Request, Response, Server};
+
+/// A fresh folder under the system temp folder.
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("smart_planner_webhook_{}_{}", name, process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn monday(hour: u32) -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2024, 3, 4, hour, 0, 0).unwrap()
+}
+
+/// What the receiver saw of one request.
+#[derive(Debug, Clone)]
+struct Received {
+    event: String,
+    delivery: String,
+    signature: Option<String>,
+    body: Vec<u8>,
+}
+
+/// A local HTTP server answering with `statuses` in turn, then 200.
+struct Receiver {
+    url: String,
+    received: Arc<Mutex<Vec<Received>>>,
+}
+
+impl Receiver {
+    fn start(statuses: &[u16]) -> Self {
+        let server = Server::http("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/hook", server.server_addr().to_ip().unwrap());
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let stored = received.clone();
+        let mut statuses = VecDeque::from(statuses.to_vec());
+        thread::spawn(move || {
+            for mut request in server.incoming_requests() {
+                let mut seen = Received {
+                    event: header(&request, EVENT_HEADER).unwrap_or_default(),
+                    delivery: header(&request, DELIVERY_HEADER).unwrap_or_default(),
+                    signature: header(&request, SIGNATURE_HEADER),
+                    body: Vec::new(),
+                };
+                request.as_reader().read_to_end(&mut seen.body).unwrap();
+                stored.lock().unwrap().push(seen);
+                let status = statuses.pop_front().unwrap_or(200);
+                request
+                    .respond(Response::from_string("").with_status_code(status))
+                    .unwrap();
+            }
+        });
+        Receiver { url, received }
+    }
+
+    fn hook(&self) -> WebhookConfig {
+        let mut hook = WebhookConfig::new(&self.url);
+        hook.retry_delay_ms = 10;
+        hook
+    }
+
+    fn received(&self) -> Vec<Received> {
+        self.received.lock().unwrap().clone()
+    }
+}
+
+fn header(request: &Request, name: &str) -> Option<String> {
+    request
+        .headers()
+        .iter()
+        .find(|header| header.field.as_str().as_str().eq_ignore_ascii_case(name))
+        .map(|header| header.value.to_string())
+}
+
+/// A URL nothing listens on.
+fn closed_url() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    drop(listener);
+    format!("http://127.0.0.1:{}/hook", port)
+}
+
+#[test]
+fn deliveries_are_signed_and_tried_again() {
+    let receiver = Receiver::start(&[500, 503]);
+    let mut hook = receiver.hook();
+    hook.secret = Some("shh".to_string());
+    let mut data = PlannerData::default();
+    data.add_timer("Tea", monday(9) + Duration::minutes(4), None, monday(9))
+        .unwrap();
+    let fired = data.fire_timers(monday(10));
+    let event = Event::timer_expired(&fired[0], monday(10));
+
+    let sender = Sender::new(&temp_dir("signed"));
+    assert_eq!(sender.deliver(&hook, &event).unwrap(), 3);
+    let received = receiver.received();
+    assert_eq!(received.len(), 3);
+    for seen in &received {
+        assert_eq!(seen.event, "timer_expired");
+        assert_eq!(seen.delivery, event.id, "every attempt has the same id");
+        let signature = seen.signature.as_deref().unwrap();
+        assert!(webhook::verify("shh", &seen.body, signature));
+        assert!(!webhook::verify("guess", &seen.body, signature));
+    }
+    let body: Event = serde_json::from_slice(&received[0].body).unwrap();
+    assert_eq!(body, event);
+    assert_eq!(body.data["timer"]["label"], "Tea");
+    // The HMAC-SHA256 example of RFC 4231
+    assert_eq!(
+        webhook::sign("Jefe", b"what do ya want for nothing?"),
+        "sha256=5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+    );
+
+    // Hooks only get the events they asked for
+    let mut plans_only = receiver.hook();
+    plans_only.events = vec![EventKind::PlanGenerated];
+    assert!(sender.send(&[plans_only], &event).unwrap().is_empty());
+    assert_eq!(receiver.received().len(), 3);
+}
+
+#[test]
+fn failed_deliveries_wait_in_the_dead_letters() {
+    let dir = temp_dir("dead_letters");
+    let sender = Sender::new(&dir);
+    let event = Event::new(EventKind::Ping, serde_json::json!({}), monday(9));
+
+    // A client error is not tried again, a closed port is
+    let refused = Receiver::start(&[400, 400]);
+    let mut down = WebhookConfig::new(&closed_url());
+    down.retries = 2;
+    down.retry_delay_ms = 10;
+    let failed = sender
+        .send(&[refused.hook(), down.clone()], &event)
+        .unwrap();
+    assert_eq!(failed.len(), 2);
+    assert_eq!(refused.received().len(), 1);
+    assert_eq!(failed[0].attempts, 1);
+    assert!(failed[0].error.contains("400"));
+    assert_eq!(failed[1].attempts, 3);
+    assert_eq!(sender.dead_letters().unwrap(), failed);
+    assert!(sender.deliver(&down, &event).is_err());
+
+    // Commands try once and leave the rest to `webhook retry`
+    let once = Sender::new(&temp_dir("dead_letters_once")).without_retries();
+    let failed_once = once.send(&[down.clone()], &event).unwrap();
+    assert_eq!(failed_once[0].attempts, 1);
+    assert_eq!(once.dead_letters().unwrap(), failed_once);
+
+    // The same event goes out again once the hook answers
+    let (delivered, left) = sender.retry(&[refused.hook(), down]).unwrap();
+    assert_eq!(delivered, 0, "the second 400 fails it again");
+    assert_eq!(left.len(), 2);
+    assert_eq!(left[0].attempts, 2);
+    let (delivered, left) = sender.retry(&[refused.hook()]).unwrap();
+    assert_eq!(delivered, 1);
+    assert_eq!(left.len(), 1);
+    assert_eq!(left[0].error, "the hook is no longer in config.json");
+    let ids: Vec<String> = refused
+        .received()
+        .iter()
+        .map(|seen| seen.delivery.clone())
+        .collect();
+    assert_eq!(ids, vec![event.id.clone(); 3]);
+    assert_eq!(sender.dead_letters().unwrap(), left);
+}
+
+#[test]
+fn an_overdue_todo_is_announced_once_per_due_time() {
+    let sender = Sender::new(&temp_dir("overdue"));
+    let mut data = PlannerData::default();
+    let mut report = NewTodo::new("Write report", Priority::High);
+    report.due = Some(monday(12));
+    let report = data.add_todo(report, monday(8)).unwrap();
+    data.add_todo(NewTodo::new("No due time", Priority::Low), monday(8))
+        .unwrap();
+
+    assert!(sender.overdue(&data, monday(11)).unwrap().is_empty());
+    let events = sender.overdue(&data, monday(13)).unwrap();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].event, EventKind::TodoOverdue);
+    assert_eq!(events[0].data["todo"]["title"], "Write report");
+    assert!(sender.overdue(&data, monday(14)).unwrap().is_empty());
+
+    // A new due time counts as a new deadline, a finished todo as none
+    data.todo_mut(report).unwrap().due = Some(monday(15));
+    assert!(sender.overdue(&data, monday(14)).unwrap().is_empty());
+    assert_eq!(sender.overdue(&data, monday(16)).unwrap().len(), 1);
+    data.complete_todo(report, monday(17)).unwrap();
+    assert!(sender.overdue(&data, monday(18)).unwrap().is_empty());
+}
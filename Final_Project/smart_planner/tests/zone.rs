@@ -0,0 +1,259 @@
+use chrono::{DateTime, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc};
+use smart_planner::data::PlannerData;
+use smart_planner::routine::Frequency;
+use smart_planner::time::zone::{LocalTime, Zone};
+use smart_planner::time::{self, parse_local_datetime};
+use smart_planner::timer;
+use smart_planner::PlannerError;
+
+fn utc(month: u32, day: u32, hour: u32, minute: u32) -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2024, month, day, hour, minute, 0)
+        .unwrap()
+}
+
+fn local(month: u32, day: u32, hour: u32, minute: u32) -> NaiveDateTime {
+    NaiveDate::from_ymd_opt(2024, month, day)
+        .unwrap()
+        .and_hms_opt(hour, minute, 0)
+        .unwrap()
+}
+
+fn at(hour: u32, minute: u32) -> NaiveTime {
+    NaiveTime::from_hms_opt(hour, minute, 0).unwrap()
+}
+
+/// The planner zone is one per process, so every test here that uses it
+/// sets the same one.
+fn berlin() -> Zone {
+    let zone = Zone::named("Europe/Berlin").unwrap();
+    time::set_zone(zone.clone());
+    zone
+}
+
+/// A version 2 TZif file with no transitions, only the rule `footer`.
+fn rule_only(footer: &str) -> Vec<u8> {
+    let header = |types: u32, chars: u32| {
+        let mut bytes = b"TZif2".to_vec();
+        bytes.extend([0; 15]);
+        for count in [0, 0, 0, 0, types, chars] {
+            bytes.extend(count.to_be_bytes());
+        }
+        bytes
+    };
+    let mut file = header(1, 4);
+    file.extend([0, 0, 0, 0, 0, 0]);
+    file.extend(b"UTC\0");
+    file.extend(header(1, 4));
+    file.extend([0, 0, 0, 0, 0, 0]);
+    file.extend(b"UTC\0");
+    file.extend(format!("\n{}\n", footer).into_bytes());
+    file
+}
+
+#[test]
+fn clock_changes_skip_and_repeat_local_times() {
+    let berlin = berlin();
+    assert_eq!(berlin.offset(utc(1, 15, 12, 0)).to_string(), "+01:00");
+    assert_eq!(berlin.offset(utc(7, 15, 12, 0)).to_string(), "+02:00");
+    // Past the last transition in the file, read from its rule
+    let far = Utc.with_ymd_and_hms(2100, 7, 1, 12, 0, 0).unwrap();
+    assert_eq!(berlin.offset(far).to_string(), "+02:00");
+
+    // On March 31 the clocks go from 02:00 to 03:00
+    assert_eq!(
+        berlin.resolve(local(3, 31, 2, 30)),
+        LocalTime::Skipped {
+            instant: utc(3, 31, 1, 30),
+            transition: utc(3, 31, 1, 0),
+            before: berlin.offset(utc(1, 1, 0, 0)),
+            after: berlin.offset(utc(7, 1, 0, 0)),
+        }
+    );
+    assert_eq!(berlin.to_local(utc(3, 31, 1, 30)), local(3, 31, 3, 30));
+    assert_eq!(
+        berlin.resolve(local(3, 31, 3, 0)),
+        LocalTime::Single(utc(3, 31, 1, 0))
+    );
+
+    // On October 27 they go back from 03:00 to 02:00
+    let twice = berlin.resolve(local(10, 27, 2, 30));
+    assert_eq!(
+        twice,
+        LocalTime::Ambiguous {
+            earlier: utc(10, 27, 0, 30),
+            later: utc(10, 27, 1, 30),
+        }
+    );
+    assert_eq!(twice.instant(), utc(10, 27, 0, 30));
+
+    let new_york = Zone::named("America/New_York").unwrap();
+    assert!(matches!(
+        new_york.resolve(local(3, 10, 2, 30)),
+        LocalTime::Skipped { instant, .. } if instant == utc(3, 10, 7, 30)
+    ));
+    assert!(matches!(
+        new_york.resolve(local(11, 3, 1, 30)),
+        LocalTime::Ambiguous { earlier, later }
+            if earlier == utc(11, 3, 5, 30) && later == utc(11, 3, 6, 30)
+    ));
+
+    assert!(Zone::named("../../etc/passwd").is_err());
+    assert!(Zone::named("Mars/Olympus_Mons").is_err());
+}
+
+#[test]
+fn zones_without_transitions_follow_their_rule() {
+    let central = Zone::from_tzif("CET", &rule_only("CET-1CEST,M3.5.0,M10.5.0/3")).unwrap();
+    let berlin = Zone::named("Europe/Berlin").unwrap();
+    for instant in [
+        utc(3, 31, 0, 59),
+        utc(3, 31, 1, 0),
+        utc(10, 27, 0, 59),
+        utc(10, 27, 1, 0),
+    ] {
+        assert_eq!(central.offset(instant), berlin.offset(instant));
+    }
+
+    // Summer time across the new year, and offsets with minutes
+    let sydney = Zone::from_tzif("Sydney", &rule_only("AEST-10AEDT,M10.1.0,M4.1.0/3")).unwrap();
+    assert_eq!(sydney.offset(utc(1, 15, 0, 0)).to_string(), "+11:00");
+    assert_eq!(sydney.offset(utc(7, 15, 0, 0)).to_string(), "+10:00");
+    let kathmandu = Zone::from_tzif("Kathmandu", &rule_only("<+0545>-5:45")).unwrap();
+    assert_eq!(kathmandu.to_local(utc(6, 1, 0, 0)), local(6, 1, 5, 45));
+
+    assert!(Zone::from_tzif("junk", b"not a zone").is_err());
+}
+
+#[test]
+fn a_daily_timer_keeps_to_the_clock_across_clock_changes() {
+    let berlin = berlin();
+    let mut data = PlannerData::default();
+    data.add_daily_timer("Wake up", at(7, 0), None, utc(3, 29, 12, 0))
+        .unwrap();
+    assert_eq!(data.timers[0].due, utc(3, 30, 6, 0));
+
+    let mut went_off = Vec::new();
+    for now in [
+        utc(3, 30, 6, 0),
+        utc(3, 31, 5, 0),
+        utc(10, 26, 5, 0),
+        utc(10, 27, 6, 0),
+    ] {
+        if now.date_naive() == NaiveDate::from_ymd_opt(2024, 10, 26).unwrap() {
+            // Left alone for months, it goes off late once and carries on
+            data.timers[0].due = utc(10, 25, 5, 0);
+        }
+        let fired = data.fire_timers(now);
+        assert_eq!(fired.len(), 1);
+        assert!(fired[0].fired);
+        went_off.push(berlin.to_local(fired[0].due).time());
+        assert!(!data.timers[0].fired);
+        assert_eq!(berlin.to_local(data.timers[0].due).time(), at(7, 0));
+    }
+    assert_eq!(went_off, vec![at(7, 0); 4]);
+    assert_eq!(data.timers[0].due, utc(10, 28, 6, 0));
+    assert_eq!(data.pending_timers().len(), 1);
+
+    // A time the clocks skip goes off as long after the change
+    assert_eq!(
+        timer::next_daily(at(2, 30), utc(3, 30, 12, 0), &berlin),
+        utc(3, 31, 1, 30)
+    );
+    assert_eq!(
+        timer::next_daily(at(2, 30), utc(10, 26, 12, 0), &berlin),
+        utc(10, 27, 0, 30)
+    );
+}
+
+#[test]
+fn a_daily_timer_follows_the_traveller() {
+    let berlin = berlin();
+    let tokyo = Zone::named("Asia/Tokyo").unwrap();
+    let mut data = PlannerData::default();
+    let id = data
+        .add_daily_timer("Wake up", at(7, 0), None, utc(6, 1, 12, 0))
+        .unwrap();
+    assert_eq!(data.timers[0].zone.as_deref(), Some("Europe/Berlin"));
+
+    // Set while in Tokyo, then back home
+    let timer = data.timers.iter_mut().find(|timer| timer.id == id).unwrap();
+    timer.zone = Some(tokyo.name().to_string());
+    timer.due = timer::next_daily(at(7, 0), utc(6, 1, 12, 0), &tokyo);
+    assert_eq!(timer.due, utc(6, 1, 22, 0));
+    assert!(data.fire_timers(utc(6, 1, 22, 0)).is_empty());
+    assert_eq!(data.timers[0].due, utc(6, 2, 5, 0));
+    assert_eq!(data.timers[0].zone.as_deref(), Some("Europe/Berlin"));
+    assert_eq!(berlin.to_local(data.timers[0].due).time(), at(7, 0));
+
+    // A one-off timer stays at its instant wherever the user is
+    let tea = data
+        .add_timer("Tea", utc(6, 3, 15, 0), None, utc(6, 1, 12, 0))
+        .unwrap();
+    data.fire_timers(utc(6, 3, 5, 0));
+    let tea = data.timers.iter().find(|timer| timer.id == tea).unwrap();
+    assert_eq!(tea.due, utc(6, 3, 15, 0));
+}
+
+#[test]
+fn routines_start_at_their_local_time_every_day() {
+    let new_york = Zone::named("America/New_York").unwrap();
+    let mut data = PlannerData::default();
+    let id = data
+        .add_routine("Run", at(7, 0), 30, Frequency::Daily, utc(3, 1, 0, 0))
+        .unwrap();
+    let routine = data
+        .routines
+        .iter()
+        .find(|routine| routine.id == id)
+        .unwrap();
+    let day = |day: u32| NaiveDate::from_ymd_opt(2024, 3, day).unwrap();
+    assert_eq!(routine.start_on(day(9), &new_york), utc(3, 9, 12, 0));
+    assert_eq!(routine.start_on(day(10), &new_york), utc(3, 10, 11, 0));
+    assert_eq!(
+        routine.next_start(utc(3, 9, 12, 0), &new_york),
+        Some(utc(3, 10, 11, 0))
+    );
+
+    let mut weekly = routine.clone();
+    weekly.frequency = "sun".parse().unwrap();
+    weekly.start = at(2, 30);
+    assert_eq!(
+        weekly.next_start(utc(3, 4, 0, 0), &new_york),
+        Some(utc(3, 10, 7, 30)),
+        "skipped by the change, so an hour later on the clock"
+    );
+}
+
+#[test]
+fn typed_times_that_clocks_skip_or_repeat_are_refused() {
+    berlin();
+    let error = |text: &str| match parse_local_datetime(text) {
+        Err(PlannerError::Invalid(message)) => message,
+        other => panic!("unexpected {:?}", other),
+    };
+    assert_eq!(
+        error("2024-03-31 02:30"),
+        "2024-03-31 02:30 does not exist in Europe/Berlin, the clocks go from 02:00 to 03:00 that night"
+    );
+    let twice = error("2024-10-27 02:30");
+    assert!(twice.contains("happens twice"), "{}", twice);
+    assert!(twice.contains("'2024-10-27 02:30 +02:00'"), "{}", twice);
+
+    assert_eq!(
+        parse_local_datetime("2024-10-27 02:30 +01:00").unwrap(),
+        utc(10, 27, 1, 30)
+    );
+    assert_eq!(
+        parse_local_datetime("2024-10-27T02:30+02:00").unwrap(),
+        utc(10, 27, 0, 30)
+    );
+    assert_eq!(
+        parse_local_datetime("2024-07-01 09:00").unwrap(),
+        utc(7, 1, 7, 0)
+    );
+    // A date alone means the end of the day, which always exists
+    assert_eq!(
+        parse_local_datetime("2024-03-31").unwrap(),
+        utc(3, 31, 21, 59)
+    );
+}
@@ -0,0 +1,55 @@
+use super::exercise::*;
+
+// hint: `self.width * self.height`.
+#[test]
+fn area_of_a_rectangle() {
+    let rect = Rectangle {
+        width: 10,
+        height: 20,
+    };
+    assert_eq!(rect.area(), 200);
+}
+
+#[test]
+fn rectangle_with_no_width_has_no_area() {
+    let rect = Rectangle {
+        width: 0,
+        height: 20,
+    };
+    assert_eq!(rect.area(), 0);
+}
+
+// hint: `Rectangle { width: size, height: size }`.
+#[test]
+fn square_has_equal_sides() {
+    let square = Rectangle::square(3);
+    assert_eq!((square.width, square.height), (3, 3));
+    assert_eq!(square.area(), 9);
+}
+
+// hint: It fits when its width and its height are both no larger than ours.
+#[test]
+fn larger_rectangle_can_hold_a_smaller_one() {
+    let larger = Rectangle {
+        width: 8,
+        height: 7,
+    };
+    let smaller = Rectangle {
+        width: 5,
+        height: 1,
+    };
+    assert!(larger.can_hold(&smaller));
+    assert!(!smaller.can_hold(&larger));
+    assert!(larger.can_hold(&larger));
+}
+
+// hint: Both sides have to fit, a long thin rectangle does not fit in a square.
+#[test]
+fn rectangle_that_is_too_wide_does_not_fit() {
+    let square = Rectangle::square(5);
+    let long = Rectangle {
+        width: 6,
+        height: 1,
+    };
+    assert!(!square.can_hold(&long));
+}
@@ -0,0 +1,38 @@
+// Exercise: automated tests
+//
+// Replace each `todo!()` below with your code, and add tests of your own to
+// `my_tests`, they are run and listed along with the hidden ones. Check your
+// solution from the root of the tutorial with `cargo run -- verify 7`, or run
+// `cargo run -- watch 7` to check it every time you save.
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Rectangle {
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Rectangle {
+    /// A rectangle as wide as it is high.
+    pub fn square(size: u32) -> Rectangle {
+        todo!()
+    }
+
+    pub fn area(&self) -> u32 {
+        todo!()
+    }
+
+    /// Whether `other` fits inside this rectangle without turning it.
+    pub fn can_hold(&self, other: &Rectangle) -> bool {
+        todo!()
+    }
+}
+
+#[cfg(test)]
+mod my_tests {
+    use super::*;
+
+    #[test]
+    fn area_of_a_rectangle() {
+        // Create a Rectangle and check its area with `assert_eq!`
+    }
+}
@@ -0,0 +1,61 @@
+use super::exercise::*;
+
+fn sorted(mut words: Vec<String>) -> Vec<String> {
+    words.sort();
+    words
+}
+
+// hint: A word is a palindrome when its chars are the same as its chars in
+// hint: reverse: `word.chars().eq(word.chars().rev())`.
+#[test]
+fn single_words_that_are_palindromes() {
+    assert_eq!(
+        sorted(find_palindromes(vec!["Level", "rust", "noon"])),
+        vec!["level", "noon"]
+    );
+}
+
+// hint: Go over every pair of positions `i` and `j` with `i != j` and try
+// hint: `words[i]` followed by `words[j]`.
+#[test]
+fn pairs_of_words_that_make_a_palindrome() {
+    assert_eq!(
+        sorted(find_palindromes(vec!["mad", "am", "race", "car"])),
+        vec!["madam", "racecar"]
+    );
+}
+
+// hint: Use the lowercase of every word, and skip a palindrome that is
+// hint: already in the result, or collect into a `HashSet` first.
+#[test]
+fn palindromes_are_listed_once() {
+    assert_eq!(
+        sorted(find_palindromes(vec!["mad", "dam", "a", "A"])),
+        vec!["a", "aa", "dammad", "maddam"]
+    );
+}
+
+#[test]
+fn no_words_no_palindromes() {
+    assert!(find_palindromes(vec![]).is_empty());
+}
+
+// hint: `move |x| x + amount` keeps its own copy of `amount`.
+#[test]
+fn adder_adds_its_amount() {
+    let add_five = make_adder(5);
+    let add_none = make_adder(0);
+    assert_eq!(add_five(10), 15);
+    assert_eq!(add_five(-5), 0);
+    assert_eq!(add_none(3), 3);
+}
+
+// hint: `words.iter().map(|word| word.len()).filter(|len| *len > min).collect()`.
+#[test]
+fn lengths_of_long_words() {
+    assert_eq!(
+        long_word_lengths(&["a", "closure", "is", "handy"], 2),
+        vec![7, 5]
+    );
+    assert!(long_word_lengths(&["tiny"], 10).is_empty());
+}
@@ -0,0 +1,26 @@
+// Exercise: closures and iterators
+//
+// Replace each `todo!()` below with your code. Check your solution from the
+// root of the tutorial with `cargo run -- verify 8`, or run
+// `cargo run -- watch 8` to check it every time you save.
+
+/// Every palindrome among the words, and among two different words joined
+/// together in either order. Letters are compared in lowercase and each
+/// palindrome is in the result once, in lowercase, in any order.
+///
+/// `["mad", "am", "Race", "car", "x"]` gives `"x"`, `"madam"` and
+/// `"racecar"`, among others.
+pub fn find_palindromes(words: Vec<&str>) -> Vec<String> {
+    todo!()
+}
+
+/// A closure that adds `amount` to its argument.
+pub fn make_adder(amount: i32) -> impl Fn(i32) -> i32 {
+    |x| todo!()
+}
+
+/// The lengths of the words longer than `min`, with iterator adapters
+/// instead of a loop.
+pub fn long_word_lengths(words: &[&str], min: usize) -> Vec<usize> {
+    todo!()
+}
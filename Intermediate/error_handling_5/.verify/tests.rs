@@ -0,0 +1,54 @@
+use super::exercise::*;
+use std::env;
+use std::fs;
+
+fn file_with(name: &str, contents: &str) -> String {
+    let path = env::temp_dir().join(format!("error_handling_5_{}_{}", std::process::id(), name));
+    fs::write(&path, contents).unwrap();
+    path.to_string_lossy().into_owned()
+}
+
+// hint: Read the file with `fs::read_to_string(path)?`, then
+// hint: `Ok(text.trim().parse()?)`.
+#[test]
+fn number_is_read_from_a_file() {
+    let path = file_with("good", " 42\n");
+    assert_eq!(read_number(&path).unwrap(), 42);
+    fs::remove_file(path).unwrap();
+}
+
+// hint: The `?` operator returns the error of a failed `read_to_string`
+// hint: instead of panicking.
+#[test]
+fn missing_file_is_an_error() {
+    assert!(read_number("there/is/no/such/file.txt").is_err());
+}
+
+// hint: `parse` fails on text that is not a number, pass that on with `?`.
+#[test]
+fn text_that_is_not_a_number_is_an_error() {
+    let path = file_with("bad", "forty-two");
+    assert!(read_number(&path).is_err());
+    fs::remove_file(path).unwrap();
+}
+
+// hint: Return `Err(MathError { message: "Cannot divide by zero".to_string() })`
+// hint: when `b == 0`, and `Ok(a / b)` otherwise.
+#[test]
+fn division() {
+    assert_eq!(div(10, 2).unwrap(), 5);
+    assert_eq!(div(-9, 3).unwrap(), -3);
+    let error = div(1, 0).unwrap_err();
+    assert_eq!(error.message, "Cannot divide by zero");
+}
+
+// hint: `write!(f, "{}", self.message)`.
+#[test]
+fn math_error_shows_its_message() {
+    let error = MathError {
+        message: "Too big".to_string(),
+    };
+    assert_eq!(error.to_string(), "Too big");
+    let boxed: Box<dyn std::error::Error> = Box::new(error);
+    assert_eq!(boxed.to_string(), "Too big");
+}
@@ -0,0 +1,33 @@
+// Exercise: error handling
+//
+// Replace each `todo!()` below with your code. Check your solution from the
+// root of the tutorial with `cargo run -- verify 5`, or run
+// `cargo run -- watch 5` to check it every time you save.
+use std::error::Error;
+use std::fmt;
+
+/// The number written in the file at `path`, with the whitespace around it
+/// left out. Fails when the file cannot be read or holds no number.
+pub fn read_number(path: &str) -> Result<i32, Box<dyn Error>> {
+    todo!()
+}
+
+/// What went wrong in a calculation.
+#[derive(Debug)]
+pub struct MathError {
+    pub message: String,
+}
+
+impl fmt::Display for MathError {
+    /// Shows just the message.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        todo!()
+    }
+}
+
+impl Error for MathError {}
+
+/// `a` divided by `b`, or a `MathError` saying `"Cannot divide by zero"`.
+pub fn div(a: i32, b: i32) -> Result<i32, MathError> {
+    todo!()
+}
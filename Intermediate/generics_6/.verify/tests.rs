@@ -0,0 +1,35 @@
+use super::exercise::*;
+
+// hint: `std::mem::swap(&mut self.first, &mut self.second)` swaps two values
+// hint: without copying them.
+#[test]
+fn pairs_of_any_type_are_swapped() {
+    let mut numbers = Pair { first: 1, second: 2 };
+    numbers.swap();
+    assert_eq!(numbers, Pair { first: 2, second: 1 });
+
+    let mut words = Pair {
+        first: String::from("Hello"),
+        second: String::from("World"),
+    };
+    words.swap();
+    assert_eq!(words.first, "World");
+    assert_eq!(words.second, "Hello");
+}
+
+// hint: Compare with `>` and return a reference to the field, like `&self.first`.
+#[test]
+fn larger_value_of_a_pair() {
+    assert_eq!(*Pair { first: 3, second: 8 }.larger(), 8);
+    assert_eq!(*Pair { first: 'z', second: 'a' }.larger(), 'z');
+    assert_eq!(*Pair { first: 1.5, second: 1.5 }.larger(), 1.5);
+}
+
+// hint: Start with the first item, `let mut largest = *items.first()?;`, and
+// hint: replace it with every item that is greater.
+#[test]
+fn largest_item() {
+    assert_eq!(largest(&[34, 50, 25, 100, 65]), Some(100));
+    assert_eq!(largest(&['y', 'm', 'a', 'q']), Some('y'));
+    assert_eq!(largest::<f64>(&[]), None);
+}
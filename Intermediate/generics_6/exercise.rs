@@ -0,0 +1,38 @@
+// Exercise: generics
+//
+// Replace each `todo!()` below with your code. Check your solution from the
+// root of the tutorial with `cargo run -- verify 6`, or run
+// `cargo run -- watch 6` to check it every time you save.
+
+/// Two values of the same type.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pair<T> {
+    pub first: T,
+    pub second: T,
+}
+
+pub trait Swap {
+    /// Swaps the two values in place.
+    fn swap(&mut self);
+}
+
+impl<T> Swap for Pair<T> {
+    fn swap(&mut self) {
+        todo!()
+    }
+}
+
+impl<T> Pair<T>
+where
+    T: PartialOrd,
+{
+    /// The larger of the two values, `first` when they are equal.
+    pub fn larger(&self) -> &T {
+        todo!()
+    }
+}
+
+/// The largest item, `None` when there are none.
+pub fn largest<T: PartialOrd + Copy>(items: &[T]) -> Option<T> {
+    todo!()
+}
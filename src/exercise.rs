@@ -0,0 +1,323 @@
+// Checking a learner's solution to the exercise of a lesson. Each lesson
+// ships `exercise.rs`, a stub to fill in, and hidden tests in
+// `.verify/tests.rs`. They are hidden only by the dot in the folder name,
+// which keeps them out of the way; nothing stops a learner reading them. Both are compiled into one test binary with rustc,
+// outside the lesson crate, and what passed is read from its output.
+//
+// A `// hint:` comment above a test is shown when that test fails. Tests the
+// learner wrote in the stub are run and shown too, but only the hidden ones
+// count.
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Output, Stdio};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::lesson::Lesson;
+
+/// The stub the learner fills in, in the lesson folder.
+pub const STUB_FILE: &str = "exercise.rs";
+/// The tests the stub is checked with, in the lesson folder.
+pub const TESTS_FILE: &str = ".verify/tests.rs";
+/// How long the tests may run, so a solution stuck in a loop still ends.
+const TIMEOUT: Duration = Duration::from_secs(20);
+
+/// The exercise of one lesson.
+#[derive(Debug, Clone)]
+pub struct Exercise {
+    pub name: String,
+    pub stub: PathBuf,
+    pub tests: PathBuf,
+}
+
+/// How one hidden test went.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TestResult {
+    pub name: String,
+    pub passed: bool,
+    /// Written by the learner in the stub, not one of the hidden tests.
+    pub own: bool,
+    /// What the failed test said, like the two sides of an `assert_eq!`.
+    pub message: Option<String>,
+    pub hint: Option<String>,
+}
+
+/// What verifying a solution found.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Outcome {
+    /// The compiler's errors.
+    DoesNotCompile(String),
+    Ran(Vec<TestResult>),
+    /// Still running after `TIMEOUT`.
+    TimedOut,
+}
+
+impl Outcome {
+    pub fn passed(&self) -> usize {
+        match self {
+            Outcome::Ran(results) => results
+                .iter()
+                .filter(|result| result.passed && !result.own)
+                .count(),
+            _ => 0,
+        }
+    }
+}
+
+impl Exercise {
+    /// The exercise of `lesson`, if it has one.
+    pub fn of(lesson: &Lesson) -> Option<Exercise> {
+        let stub = lesson.dir.join(STUB_FILE);
+        let tests = lesson.dir.join(TESTS_FILE);
+        (stub.exists() && tests.exists()).then(|| Exercise {
+            name: lesson.name.clone(),
+            stub,
+            tests,
+        })
+    }
+
+    /// How many hidden tests there are.
+    pub fn total(&self) -> io::Result<usize> {
+        Ok(hints(&fs::read_to_string(&self.tests)?).len())
+    }
+
+    /// When the stub or the tests last changed.
+    pub fn modified(&self) -> io::Result<SystemTime> {
+        let stub = fs::metadata(&self.stub)?.modified()?;
+        let tests = fs::metadata(&self.tests)?.modified()?;
+        Ok(stub.max(tests))
+    }
+
+    /// Compiles the stub with the tests in `build_dir` and runs them.
+    /// Compiler errors are coloured when `color` is set.
+    pub fn verify(&self, build_dir: &Path, color: bool) -> io::Result<Outcome> {
+        fs::create_dir_all(build_dir)?;
+        let harness = build_dir.join(format!("{}.rs", self.name));
+        let binary = build_dir.join(format!("{}{}", self.name, std::env::consts::EXE_SUFFIX));
+        fs::write(&harness, harness_source(&self.stub, &self.tests))?;
+        let compiled = Command::new("rustc")
+            .args(["--edition", "2021", "--test", "--crate-name", "exercise"])
+            .args(["--color", if color { "always" } else { "never" }])
+            .arg("-o")
+            .arg(&binary)
+            .arg(&harness)
+            .output()?;
+        if !compiled.status.success() {
+            return Ok(Outcome::DoesNotCompile(
+                String::from_utf8_lossy(&compiled.stderr).into_owned(),
+            ));
+        }
+
+        let child = Command::new(&binary)
+            .args(["--test-threads", "1", "--color", "never"])
+            // Keeps the failure messages free of backtraces
+            .env("RUST_BACKTRACE", "0")
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+        let Some(output) = wait(child, TIMEOUT)? else {
+            return Ok(Outcome::TimedOut);
+        };
+        let hints = hints(&fs::read_to_string(&self.tests)?);
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let mut results: Vec<TestResult> = results(&stdout)
+            .into_iter()
+            .map(|(path, passed, message)| {
+                let (name, own) = match path.strip_prefix("tests::") {
+                    Some(name) => (name.to_string(), false),
+                    None => (path.rsplit("::").next().unwrap_or(&path).to_string(), true),
+                };
+                let hint = hints
+                    .iter()
+                    .find(|(test, _)| !own && *test == name)
+                    .and_then(|(_, hint)| hint.clone());
+                TestResult {
+                    name,
+                    passed,
+                    own,
+                    message,
+                    hint,
+                }
+            })
+            .collect();
+        // In the order of the tests file rather than by name, own tests last
+        results.sort_by_key(|result| {
+            hints
+                .iter()
+                .position(|(test, _)| !result.own && *test == result.name)
+                .unwrap_or(usize::MAX)
+        });
+        Ok(Outcome::Ran(results))
+    }
+}
+
+/// The crate that puts the stub and the tests together. The stub's macros
+/// are made visible to the tests, and its unused code is not warned about.
+fn harness_source(stub: &Path, tests: &Path) -> String {
+    format!(
+        "#![allow(unused, dead_code)]\n\
+         #[macro_use]\n\
+         #[path = {:?}]\n\
+         mod exercise;\n\
+         #[cfg(test)]\n\
+         #[path = {:?}]\n\
+         mod tests;\n",
+        stub, tests
+    )
+}
+
+/// Waits for `child` for up to `timeout`, killing it after that. Its output
+/// is read as it comes, so a lot of it cannot fill the pipes and stall it.
+fn wait(mut child: Child, timeout: Duration) -> io::Result<Option<Output>> {
+    let stdout = read_all(child.stdout.take());
+    let stderr = read_all(child.stderr.take());
+    let start = Instant::now();
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        if start.elapsed() > timeout {
+            child.kill()?;
+            child.wait()?;
+            return Ok(None);
+        }
+        thread::sleep(Duration::from_millis(20));
+    };
+    Ok(Some(Output {
+        status,
+        stdout: stdout.join().unwrap_or_default(),
+        stderr: stderr.join().unwrap_or_default(),
+    }))
+}
+
+/// Reads `pipe` to the end on a thread of its own.
+fn read_all(pipe: Option<impl Read + Send + 'static>) -> JoinHandle<Vec<u8>> {
+    thread::spawn(move || {
+        let mut bytes = Vec::new();
+        if let Some(mut pipe) = pipe {
+            let _ = pipe.read_to_end(&mut bytes);
+        }
+        bytes
+    })
+}
+
+/// Each test in `tests` in order, with the `// hint:` lines above it.
+fn hints(tests: &str) -> Vec<(String, Option<String>)> {
+    let mut found = Vec::new();
+    let mut hint: Vec<&str> = Vec::new();
+    let mut is_test = false;
+    for line in tests.lines().map(str::trim) {
+        if let Some(text) = line.strip_prefix("// hint:") {
+            hint.push(text.trim());
+        } else if line == "#[test]" {
+            is_test = true;
+        } else if let Some(rest) = line.strip_prefix("fn ") {
+            let name = rest.split('(').next().unwrap_or(rest).trim();
+            if is_test {
+                let text = (!hint.is_empty()).then(|| hint.join(" "));
+                found.push((name.to_string(), text));
+            }
+            hint.clear();
+            is_test = false;
+        }
+    }
+    found
+}
+
+/// The path, result and failure message of each test in the output of a
+/// test binary.
+fn results(output: &str) -> Vec<(String, bool, Option<String>)> {
+    let mut found = Vec::new();
+    for line in output.lines() {
+        let Some(rest) = line.strip_prefix("test ") else {
+            continue;
+        };
+        let Some((name, result)) = rest.split_once(" ... ") else {
+            continue;
+        };
+        let name = name.to_string();
+        let passed = result.trim() == "ok";
+        let message = (!passed).then(|| failure(output, &name)).flatten();
+        found.push((name, passed, message));
+    }
+    found
+}
+
+/// What the test at `path` printed when it panicked.
+fn failure(output: &str, path: &str) -> Option<String> {
+    let header = format!("---- {} stdout ----", path);
+    let section = output.split_once(&header)?.1;
+    let mut lines = section.lines().skip_while(|line| line.trim().is_empty());
+    // The first line says where it panicked, the message follows
+    lines.next()?;
+    let message: Vec<&str> = lines
+        .take_while(|line| !line.trim().is_empty() && !line.starts_with("note:"))
+        .collect();
+    let message = message.join("\n");
+    if message.contains("not yet implemented") {
+        return Some("not written yet (still `todo!()`)".to_string());
+    }
+    (!message.is_empty()).then_some(message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hints_belong_to_the_test_below_them() {
+        let tests = "use super::exercise::*;\n\n\
+            // hint: Multiply the width by the height.\n\
+            #[test]\n\
+            fn area_of_a_square() {}\n\n\
+            fn helper() {}\n\n\
+            #[test]\n\
+            fn zero_width() {}\n";
+        assert_eq!(
+            hints(tests),
+            vec![
+                (
+                    "area_of_a_square".to_string(),
+                    Some("Multiply the width by the height.".to_string())
+                ),
+                ("zero_width".to_string(), None),
+            ]
+        );
+    }
+
+    #[test]
+    fn results_are_read_from_the_test_output() {
+        let output = "\nrunning 3 tests\n\
+            test tests::area_of_a_square ... ok\n\
+            test tests::zero_width ... FAILED\n\
+            test tests::can_hold ... FAILED\n\n\
+            failures:\n\n\
+            ---- tests::zero_width stdout ----\n\n\
+            thread 'tests::zero_width' panicked at src/exercise.rs:3:5:\n\
+            assertion `left == right` failed\n  \
+            left: 1\n \
+            right: 0\n\
+            note: run with `RUST_BACKTRACE=1` environment variable to display a backtrace\n\n\
+            ---- tests::can_hold stdout ----\n\n\
+            thread 'tests::can_hold' panicked at src/exercise.rs:9:5:\n\
+            not yet implemented\n\n\
+            failures:\n    tests::zero_width\n";
+        assert_eq!(
+            results(output),
+            vec![
+                ("tests::area_of_a_square".to_string(), true, None),
+                (
+                    "tests::zero_width".to_string(),
+                    false,
+                    Some("assertion `left == right` failed\n  left: 1\n right: 0".to_string())
+                ),
+                (
+                    "tests::can_hold".to_string(),
+                    false,
+                    Some("not written yet (still `todo!()`)".to_string())
+                ),
+            ]
+        );
+    }
+}
@@ -0,0 +1,217 @@
+// Finding the lesson crates of the tutorial, and building and running them.
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitStatus};
+
+/// The section folders, in the order they are meant to be read.
+pub const SECTIONS: [&str; 3] = ["Beginner", "Intermediate", "Advance"];
+
+/// One lesson crate, like `Intermediate/error_handling_5`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Lesson {
+    /// The number at the end of the folder name, the order of the lessons.
+    pub number: u32,
+    pub section: &'static str,
+    /// The folder name.
+    pub name: String,
+    /// The first heading of the README.
+    pub topic: String,
+    pub dir: PathBuf,
+}
+
+impl Lesson {
+    pub fn readme(&self) -> io::Result<String> {
+        fs::read_to_string(self.dir.join("README.md"))
+    }
+
+    /// The exercise of the lesson, when it has one.
+    pub fn task(&self) -> io::Result<Option<String>> {
+        let path = self.dir.join("task.md");
+        if !path.exists() {
+            return Ok(None);
+        }
+        fs::read_to_string(path).map(Some)
+    }
+
+    /// Lessons without a `main.rs` are libraries, they are run through their
+    /// tests.
+    pub fn has_binary(&self) -> bool {
+        self.dir.join("src").join("main.rs").exists()
+    }
+
+    /// `cargo run`, or `cargo test` for a library.
+    pub fn cargo_command(&self) -> &'static str {
+        if self.has_binary() {
+            "run"
+        } else {
+            "test"
+        }
+    }
+
+    /// Builds and runs the lesson in its folder, its output going straight
+    /// to the terminal.
+    pub fn run(&self, args: &[String]) -> io::Result<ExitStatus> {
+        // Set when this runner itself was started by cargo
+        let cargo = env::var_os("CARGO").unwrap_or_else(|| "cargo".into());
+        let mut command = Command::new(cargo);
+        command
+            .arg(self.cargo_command())
+            .arg("--quiet")
+            .current_dir(&self.dir);
+        if !args.is_empty() {
+            command.arg("--").args(args);
+        }
+        command.status()
+    }
+}
+
+/// Every lesson under the section folders of `root`, in order.
+pub fn discover(root: &Path) -> io::Result<Vec<Lesson>> {
+    let mut lessons = Vec::new();
+    for section in SECTIONS {
+        let folder = root.join(section);
+        if !folder.is_dir() {
+            continue;
+        }
+        for entry in fs::read_dir(folder)? {
+            let dir = entry?.path();
+            if !dir.join("Cargo.toml").exists() {
+                continue;
+            }
+            let name = dir.file_name().unwrap().to_string_lossy().into_owned();
+            let Some(number) = lesson_number(&name) else {
+                continue;
+            };
+            let topic = fs::read_to_string(dir.join("README.md"))
+                .ok()
+                .and_then(|readme| first_heading(&readme))
+                .unwrap_or_else(|| topic_from_name(&name));
+            lessons.push(Lesson {
+                number,
+                section,
+                name,
+                topic,
+                dir,
+            });
+        }
+    }
+    lessons.sort_by_key(|lesson| lesson.number);
+    Ok(lessons)
+}
+
+/// Finds a lesson by its number (`5`), its folder name (`error_handling_5`)
+/// or a part of its name or topic that only it has (`error`).
+pub fn find<'a>(lessons: &'a [Lesson], query: &str) -> Result<&'a Lesson, String> {
+    let query = query.trim().to_lowercase();
+    if let Ok(number) = query.parse::<u32>() {
+        return lessons
+            .iter()
+            .find(|lesson| lesson.number == number)
+            .ok_or_else(|| format!("there is no lesson {}", number));
+    }
+    if let Some(lesson) = lessons.iter().find(|lesson| lesson.name == query) {
+        return Ok(lesson);
+    }
+    let matches: Vec<&Lesson> = lessons
+        .iter()
+        .filter(|lesson| {
+            lesson.name.to_lowercase().contains(&query)
+                || lesson.topic.to_lowercase().contains(&query)
+        })
+        .collect();
+    match matches.as_slice() {
+        [lesson] => Ok(lesson),
+        [] => Err(format!("no lesson matches '{}'", query)),
+        _ => {
+            let names: Vec<&str> = matches.iter().map(|lesson| lesson.name.as_str()).collect();
+            Err(format!(
+                "'{}' matches more than one lesson: {}",
+                query,
+                names.join(", ")
+            ))
+        }
+    }
+}
+
+/// The number after the last `_`, as in `concurrency_11`.
+fn lesson_number(name: &str) -> Option<u32> {
+    name.rsplit_once('_')?.1.parse().ok()
+}
+
+/// The text of the first heading, without its `#` and `**`.
+fn first_heading(markdown: &str) -> Option<String> {
+    let line = markdown
+        .lines()
+        .map(|line| line.trim_start_matches('\u{feff}').trim())
+        .find(|line| line.starts_with('#'))?;
+    let text = line.trim_start_matches('#').replace("**", "");
+    let text = text.trim();
+    (!text.is_empty()).then(|| text.to_string())
+}
+
+/// `error_handling_5` becomes `Error handling`.
+fn topic_from_name(name: &str) -> String {
+    let words = name.rsplit_once('_').map_or(name, |(words, _)| words);
+    let words = words.replace(['_', '-'], " ");
+    let mut chars = words.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => name.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lesson(dir: &Path, readme: Option<&str>) {
+        fs::create_dir_all(dir.join("src")).unwrap();
+        fs::write(dir.join("Cargo.toml"), "[package]\n").unwrap();
+        if let Some(readme) = readme {
+            fs::write(dir.join("README.md"), readme).unwrap();
+        }
+    }
+
+    #[test]
+    fn lessons_are_found_in_order_with_their_topics() {
+        let root = env::temp_dir().join(format!("rust_lessons_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        lesson(
+            &root.join("Advance/concurrency_11"),
+            Some("# Concurrency\n"),
+        );
+        lesson(
+            &root.join("Beginner/structs_and_enums_3"),
+            Some("# **Structs and Enums in Rust**\n\nText"),
+        );
+        lesson(&root.join("Intermediate/error_handling_5"), None);
+        // Not lessons: no manifest, or no number
+        fs::create_dir_all(root.join("Beginner/notes_1")).unwrap();
+        lesson(&root.join("Beginner/scratch"), None);
+
+        let lessons = discover(&root).unwrap();
+        let names: Vec<(u32, &str, &str)> = lessons
+            .iter()
+            .map(|lesson| (lesson.number, lesson.section, lesson.topic.as_str()))
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                (3, "Beginner", "Structs and Enums in Rust"),
+                (5, "Intermediate", "Error handling"),
+                (11, "Advance", "Concurrency"),
+            ]
+        );
+        assert!(!lessons[0].has_binary());
+        assert_eq!(lessons[0].cargo_command(), "test");
+
+        assert_eq!(find(&lessons, "5").unwrap().name, "error_handling_5");
+        assert_eq!(find(&lessons, "concurrency_11").unwrap().number, 11);
+        assert_eq!(find(&lessons, "Enums").unwrap().number, 3);
+        assert!(find(&lessons, "4").is_err());
+        assert!(find(&lessons, "n").unwrap_err().contains("more than one"));
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -1,4 +1,341 @@
+// Runs the lessons of the tutorial from one place: lists them in order, shows
+// a lesson's README or task in the terminal, builds and runs its code, and
+// checks the learner's solution to its exercise. Started without a command in
+// a terminal, it asks what to do next.
+use std::env;
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::path::Path;
+use std::process::{self, Command, Stdio};
+use std::thread;
+use std::time::Duration;
+
+mod exercise;
+mod lesson;
+mod markdown;
+mod progress;
+
+use exercise::{Exercise, Outcome};
+use lesson::Lesson;
+use progress::Progress;
+
+const USAGE: &str = "\
+Usage: cargo run -- [COMMAND]
+
+Commands:
+  list                   List the lessons in order
+  show <LESSON> [--task] Show the README of a lesson, or its task
+  run <LESSON> [-- ARGS] Build and run a lesson (its tests for a library)
+  verify <LESSON>        Check your solution to the exercise of a lesson
+  watch <LESSON>         Verify again every time the exercise is saved
+  help                   Print this message
+
+A lesson is its number (5), its folder (error_handling_5) or part of its
+name (error). Without a command, in a terminal, the lessons are listed and
+you pick one. What you verified is kept in progress.txt.";
+
 fn main() {
-    println!("Welcome to the Rust tutorial!");
-    println!("Please start by reading the README.md file in the root directory of this project.");
-}
\ No newline at end of file
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let args: Vec<String> = env::args().skip(1).collect();
+    if let Err(message) = run(root, &args) {
+        eprintln!("error: {}", message);
+        process::exit(1);
+    }
+}
+
+fn run(root: &Path, args: &[String]) -> Result<(), String> {
+    let lessons = lesson::discover(root).map_err(|e| e.to_string())?;
+    let mut progress = Progress::load(root).map_err(|e| e.to_string())?;
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    match args.as_slice() {
+        [] if io::stdin().is_terminal() => interactive(root, &lessons, &mut progress),
+        [] | ["list"] => {
+            print!("{}", list(&lessons, &progress));
+            Ok(())
+        }
+        ["show", query] => show(lesson::find(&lessons, query)?, false),
+        ["show", query, "--task"] | ["show", "--task", query] => {
+            show(lesson::find(&lessons, query)?, true)
+        }
+        ["run", query, rest @ ..] => {
+            let rest = match rest {
+                ["--", rest @ ..] => rest,
+                rest => rest,
+            };
+            let rest: Vec<String> = rest.iter().map(|arg| arg.to_string()).collect();
+            run_lesson(lesson::find(&lessons, query)?, &rest)
+        }
+        ["verify", query] => {
+            let lesson = lesson::find(&lessons, query)?;
+            if verify(root, lesson, &mut progress)? {
+                Ok(())
+            } else {
+                Err(format!("{} is not solved yet", lesson.name))
+            }
+        }
+        ["watch", query] => watch(root, lesson::find(&lessons, query)?, &mut progress),
+        ["help" | "-h" | "--help"] => {
+            println!("{}", USAGE);
+            Ok(())
+        }
+        _ => Err(format!("unknown command\n\n{}", USAGE)),
+    }
+}
+
+/// The lessons under their section names, one per line, with how far their
+/// exercises got.
+fn list(lessons: &[Lesson], progress: &Progress) -> String {
+    let width = lessons
+        .iter()
+        .map(|lesson| lesson.topic.chars().count())
+        .max()
+        .unwrap_or(0);
+    let name_width = lessons
+        .iter()
+        .map(|lesson| lesson.name.len())
+        .max()
+        .unwrap_or(0);
+    let mut out = String::new();
+    let mut section = "";
+    for lesson in lessons {
+        if lesson.section != section {
+            if !section.is_empty() {
+                out.push('\n');
+            }
+            section = lesson.section;
+            out.push_str(&format!("{}\n", section));
+        }
+        let line = match progress.get(&lesson.name) {
+            Some(entry) => format!(
+                "  {:>2}. {:<width$}  {:<name_width$}  {}",
+                lesson.number,
+                lesson.topic,
+                lesson.name,
+                entry.mark()
+            ),
+            None => format!(
+                "  {:>2}. {:<width$}  {}",
+                lesson.number, lesson.topic, lesson.name
+            ),
+        };
+        out.push_str(&line);
+        out.push('\n');
+    }
+    if lessons.is_empty() {
+        out.push_str("No lessons found.\n");
+    } else if progress.completed() > 0 {
+        out.push_str(&format!(
+            "\n{} of {} exercises done.\n",
+            progress.completed(),
+            lessons.len()
+        ));
+    }
+    out
+}
+
+fn show(lesson: &Lesson, task: bool) -> Result<(), String> {
+    let text = if task {
+        lesson
+            .task()
+            .map_err(|e| e.to_string())?
+            .ok_or_else(|| format!("{} has no task", lesson.name))?
+    } else {
+        lesson.readme().map_err(|e| e.to_string())?
+    };
+    let color = io::stdout().is_terminal();
+    page(&markdown::render(&text, color));
+    Ok(())
+}
+
+/// Shows long text in `$PAGER` (or `less`) when writing to a terminal.
+fn page(text: &str) {
+    if io::stdout().is_terminal() {
+        let pager = env::var("PAGER").unwrap_or_else(|_| "less -R".to_string());
+        let mut parts = pager.split_whitespace();
+        if let Some(program) = parts.next() {
+            let child = Command::new(program)
+                .args(parts)
+                .stdin(Stdio::piped())
+                .spawn();
+            if let Ok(mut child) = child {
+                if let Some(mut stdin) = child.stdin.take() {
+                    // The pager may be quit before it read everything
+                    let _ = stdin.write_all(text.as_bytes());
+                }
+                let _ = child.wait();
+                return;
+            }
+        }
+    }
+    print!("{}", text);
+}
+
+fn run_lesson(lesson: &Lesson, args: &[String]) -> Result<(), String> {
+    println!(
+        "Building and running {} (cargo {} in {})\n",
+        lesson.name,
+        lesson.cargo_command(),
+        lesson.dir.display()
+    );
+    let status = lesson
+        .run(args)
+        .map_err(|e| format!("could not start cargo: {}", e))?;
+    println!();
+    if status.success() {
+        println!("{} finished.", lesson.name);
+    } else {
+        // Some lessons panic on purpose, to show what that looks like
+        println!("{} stopped with {}.", lesson.name, status);
+    }
+    Ok(())
+}
+
+fn exercise_of(lesson: &Lesson) -> Result<Exercise, String> {
+    Exercise::of(lesson).ok_or_else(|| format!("{} has no exercise", lesson.name))
+}
+
+/// Compiles the exercise of `lesson` with its hidden tests, shows how each
+/// went and records it. Returns whether every test passed.
+fn verify(root: &Path, lesson: &Lesson, progress: &mut Progress) -> Result<bool, String> {
+    let exercise = exercise_of(lesson)?;
+    let stub = exercise.stub.strip_prefix(root).unwrap_or(&exercise.stub);
+    let total = exercise.total().map_err(|e| e.to_string())?;
+    println!("Verifying {} ({} tests)\n", lesson.name, total);
+    let color = io::stdout().is_terminal();
+    let outcome = exercise
+        .verify(&root.join("target").join("exercises"), color)
+        .map_err(|e| format!("could not verify {}: {}", lesson.name, e))?;
+    match &outcome {
+        Outcome::DoesNotCompile(errors) => {
+            println!("{} does not compile yet:\n", stub.display());
+            print!("{}", errors);
+        }
+        Outcome::TimedOut => println!("The tests did not finish, is there a loop that never ends?"),
+        Outcome::Ran(results) => {
+            for result in results {
+                let mark = if result.passed { "✓" } else { "✗" };
+                let own = if result.own { " (your test)" } else { "" };
+                println!("  {} {}{}", mark, result.name.replace('_', " "), own);
+                if result.passed {
+                    continue;
+                }
+                if let Some(message) = &result.message {
+                    for line in message.lines() {
+                        println!("      {}", line);
+                    }
+                }
+                if let Some(hint) = &result.hint {
+                    println!("      hint: {}", hint);
+                }
+            }
+        }
+    }
+    let passed = outcome.passed();
+    let entry = progress
+        .record(&lesson.name, passed, total)
+        .map_err(|e| format!("could not save progress: {}", e))?;
+    println!();
+    if passed == total {
+        println!("All {} tests passed, {} is done!", total, lesson.name);
+    } else {
+        println!(
+            "{} of {} tests passed. Keep working on {} and verify again.",
+            passed,
+            total,
+            stub.display()
+        );
+        if entry.completed {
+            println!("(You solved it before, so it stays done.)");
+        }
+    }
+    Ok(passed == total)
+}
+
+/// Verifies the exercise of `lesson` again whenever it is saved, until the
+/// runner is stopped.
+fn watch(root: &Path, lesson: &Lesson, progress: &mut Progress) -> Result<(), String> {
+    let exercise = exercise_of(lesson)?;
+    let stub = exercise.stub.strip_prefix(root).unwrap_or(&exercise.stub);
+    let mut seen = None;
+    loop {
+        // Missing for a moment while some editors save
+        if let Ok(modified) = exercise.modified() {
+            if seen != Some(modified) {
+                seen = Some(modified);
+                if io::stdout().is_terminal() {
+                    print!("\x1b[2J\x1b[H");
+                }
+                if let Err(message) = verify(root, lesson, progress) {
+                    println!("error: {}", message);
+                }
+                println!("\nWatching {} for changes, Ctrl-C to stop.", stub.display());
+            }
+        }
+        thread::sleep(Duration::from_millis(500));
+    }
+}
+
+/// Reads one trimmed line after showing `prompt`, `None` at the end of input.
+fn ask(prompt: &str) -> Option<String> {
+    print!("{}", prompt);
+    io::stdout().flush().ok()?;
+    let mut line = String::new();
+    match io::stdin().lock().read_line(&mut line) {
+        Ok(0) | Err(_) => None,
+        Ok(_) => Some(line.trim().to_string()),
+    }
+}
+
+fn interactive(root: &Path, lessons: &[Lesson], progress: &mut Progress) -> Result<(), String> {
+    print!("{}", list(lessons, progress));
+    loop {
+        let Some(answer) = ask("\nLesson to open (number or name, q to quit): ") else {
+            return Ok(());
+        };
+        match answer.as_str() {
+            "" => continue,
+            "q" | "quit" => return Ok(()),
+            "l" | "list" => {
+                print!("{}", list(lessons, progress));
+                continue;
+            }
+            _ => {}
+        }
+        let lesson = match lesson::find(lessons, &answer) {
+            Ok(lesson) => lesson,
+            Err(message) => {
+                println!("{}", message);
+                continue;
+            }
+        };
+        if !lesson_menu(root, lesson, progress) {
+            return Ok(());
+        }
+    }
+}
+
+/// Offers what can be done with `lesson` until another lesson is wanted.
+/// Returns false to quit altogether.
+fn lesson_menu(root: &Path, lesson: &Lesson, progress: &mut Progress) -> bool {
+    println!(
+        "\n{}. {} ({}/{})",
+        lesson.number, lesson.topic, lesson.section, lesson.name
+    );
+    loop {
+        let Some(answer) = ask("[r]ead  [t]ask  [b]uild and run  [v]erify  [l]essons  [q]uit: ")
+        else {
+            return false;
+        };
+        let result = match answer.as_str() {
+            "r" | "read" => show(lesson, false),
+            "t" | "task" => show(lesson, true),
+            "b" | "run" => run_lesson(lesson, &[]),
+            "v" | "verify" => verify(root, lesson, progress).map(|_| ()),
+            "l" | "lessons" => return true,
+            "q" | "quit" => return false,
+            _ => continue,
+        };
+        if let Err(message) = result {
+            println!("error: {}", message);
+        }
+    }
+}
@@ -0,0 +1,206 @@
+// Shows the Markdown of a lesson in the terminal: headings stand out, code
+// blocks are indented and coloured, and the `**`, backticks and link syntax
+// are turned into what they mean. Without colours the text stays readable as
+// plain text.
+
+const RESET: &str = "\x1b[0m";
+const BOLD: &str = "\x1b[1m";
+const UNDERLINE: &str = "\x1b[4m";
+const DIM: &str = "\x1b[2m";
+const CYAN: &str = "\x1b[36m";
+const YELLOW: &str = "\x1b[33m";
+
+/// Renders `markdown` for the terminal, with ANSI colours when `color` is set.
+pub fn render(markdown: &str, color: bool) -> String {
+    let style = |codes: &str, text: &str| {
+        if color {
+            format!("{}{}{}", codes, text, RESET)
+        } else {
+            text.to_string()
+        }
+    };
+    let mut out = String::new();
+    let mut in_code = false;
+    for line in markdown.lines() {
+        let line = line.trim_start_matches('\u{feff}').trim_end();
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") {
+            in_code = !in_code;
+            let language = trimmed.trim_start_matches('`').trim();
+            if in_code && !language.is_empty() {
+                out.push_str(&format!("    {}\n", style(DIM, language)));
+            }
+            continue;
+        }
+        if in_code {
+            out.push_str(&format!("    {}\n", style(YELLOW, line)));
+            continue;
+        }
+        if let Some(heading) = heading(trimmed) {
+            let (level, text) = heading;
+            let mut text = text.replace("**", "");
+            if level == 1 {
+                text = text.to_uppercase();
+            }
+            let text = inline(&text, color);
+            match level {
+                1 => {
+                    let width = text_width(&text);
+                    out.push_str(&format!("{}\n", style(BOLD, &text)));
+                    out.push_str(&format!("{}\n", "=".repeat(width)));
+                }
+                2 => {
+                    let width = text_width(&text);
+                    out.push_str(&format!("{}\n", style(BOLD, &text)));
+                    out.push_str(&format!("{}\n", "-".repeat(width)));
+                }
+                _ => out.push_str(&format!(
+                    "{}\n",
+                    style(&format!("{}{}", BOLD, UNDERLINE), &text)
+                )),
+            }
+            continue;
+        }
+        if let Some(item) = trimmed
+            .strip_prefix("- ")
+            .or_else(|| trimmed.strip_prefix("* "))
+        {
+            let indent = line.len() - trimmed.len();
+            out.push_str(&format!(
+                "{}  • {}\n",
+                " ".repeat(indent),
+                inline(item, color)
+            ));
+            continue;
+        }
+        if let Some(quote) = trimmed.strip_prefix('>') {
+            out.push_str(&format!("  │ {}\n", inline(quote.trim_start(), color)));
+            continue;
+        }
+        if trimmed == "---" || trimmed == "***" {
+            out.push_str(&format!("{}\n", style(DIM, &"─".repeat(40))));
+            continue;
+        }
+        out.push_str(&inline(line, color));
+        out.push('\n');
+    }
+    out
+}
+
+/// The level and text of a `#` heading line.
+fn heading(line: &str) -> Option<(usize, &str)> {
+    let level = line.chars().take_while(|c| *c == '#').count();
+    if level == 0 || level > 6 {
+        return None;
+    }
+    let text = line[level..].strip_prefix(' ')?;
+    Some((level, text.trim()))
+}
+
+/// Turns `code`, **bold**, links and images within a line into plain text,
+/// coloured when asked.
+fn inline(text: &str, color: bool) -> String {
+    let mut out = String::new();
+    let mut rest = text;
+    while let Some(c) = rest.chars().next() {
+        if c == '`' {
+            if let Some(end) = rest[1..].find('`') {
+                let code = &rest[1..1 + end];
+                if color {
+                    out.push_str(&format!("{}{}{}", CYAN, code, RESET));
+                } else {
+                    out.push('`');
+                    out.push_str(code);
+                    out.push('`');
+                }
+                rest = &rest[end + 2..];
+                continue;
+            }
+        }
+        if let Some(after) = rest.strip_prefix("**") {
+            if let Some(end) = after.find("**") {
+                let bold = inline(&after[..end], color);
+                if color {
+                    out.push_str(&format!("{}{}{}", BOLD, bold, RESET));
+                } else {
+                    out.push_str(&bold);
+                }
+                rest = &after[end + 2..];
+                continue;
+            }
+        }
+        let image = rest.starts_with("![");
+        if c == '[' || image {
+            if let Some((label, url, len)) = link(&rest[if image { 1 } else { 0 }..]) {
+                if image {
+                    out.push_str(&format!("[image: {}]", label));
+                } else if color {
+                    out.push_str(&format!("{}{}{} <{}>", UNDERLINE, label, RESET, url));
+                } else {
+                    out.push_str(&format!("{} <{}>", label, url));
+                }
+                rest = &rest[len + usize::from(image)..];
+                continue;
+            }
+        }
+        out.push(c);
+        rest = &rest[c.len_utf8()..];
+    }
+    out
+}
+
+/// The label and url of a `[label](url)` at the start of `text`, and its
+/// length.
+fn link(text: &str) -> Option<(&str, &str, usize)> {
+    let close = text.find("](")?;
+    let end = text[close..].find(')')? + close;
+    Some((&text[1..close], &text[close + 2..end], end + 1))
+}
+
+/// How wide `text` shows, leaving out colour codes.
+fn text_width(text: &str) -> usize {
+    let mut width = 0;
+    let mut in_code = false;
+    for c in text.chars() {
+        match c {
+            '\x1b' => in_code = true,
+            'm' if in_code => in_code = false,
+            _ if in_code => {}
+            _ => width += 1,
+        }
+    }
+    width
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn markdown_becomes_plain_terminal_text() {
+        let markdown = "\u{feff}# **Error Handling**\n\n\
+            Use `Result` and **never** [panic](https://doc.rust-lang.org).\n\
+            ![ownership](ownership.png)\n\n\
+            ## Part 1\n\
+            - one\n  - two\n\
+            ```rust\nfn main() {}\n```\n";
+        assert_eq!(
+            render(markdown, false),
+            "ERROR HANDLING\n\
+             ==============\n\
+             \n\
+             Use `Result` and never panic <https://doc.rust-lang.org>.\n\
+             [image: ownership]\n\
+             \n\
+             Part 1\n\
+             ------\n  \
+             • one\n    \
+             • two\n    \
+             rust\n    \
+             fn main() {}\n"
+        );
+        let colored = render("#### Step `1`\nRun `cargo run`", true);
+        assert!(colored.contains("\x1b[36mcargo run\x1b[0m"));
+        assert!(colored.starts_with("\x1b[1m\x1b[4mStep "));
+    }
+}
@@ -0,0 +1,150 @@
+// What the learner has verified so far, kept in `progress.txt` in the root of
+// the tutorial. Each line is a lesson, how many of its tests passed the last
+// time, how many there are, whether they all passed once, and when, in
+// seconds since 1970, separated by tabs.
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub const FILE: &str = "progress.txt";
+
+/// The last verification of one lesson.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Entry {
+    pub passed: usize,
+    pub total: usize,
+    /// Set once every test passed, and kept when a later change breaks one.
+    pub completed: bool,
+    pub at: u64,
+}
+
+impl Entry {
+    /// How the lesson shows in the list: `✓`, or the tests passed so far.
+    pub fn mark(&self) -> String {
+        if self.completed {
+            "✓".to_string()
+        } else {
+            format!("{}/{}", self.passed, self.total)
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Progress {
+    path: PathBuf,
+    entries: BTreeMap<String, Entry>,
+}
+
+impl Progress {
+    /// Reads the progress kept in `root`, nothing yet when there is no file.
+    /// Lines that cannot be read are left out.
+    pub fn load(root: &Path) -> io::Result<Progress> {
+        let path = root.join(FILE);
+        let text = match fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(e),
+        };
+        let entries = text.lines().filter_map(parse_line).collect();
+        Ok(Progress { path, entries })
+    }
+
+    pub fn get(&self, lesson: &str) -> Option<&Entry> {
+        self.entries.get(lesson)
+    }
+
+    /// How many lessons were completed.
+    pub fn completed(&self) -> usize {
+        self.entries
+            .values()
+            .filter(|entry| entry.completed)
+            .count()
+    }
+
+    /// Records a verification of `lesson` and writes the file.
+    pub fn record(&mut self, lesson: &str, passed: usize, total: usize) -> io::Result<Entry> {
+        let at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |since| since.as_secs());
+        let was_completed = self.get(lesson).is_some_and(|entry| entry.completed);
+        let entry = Entry {
+            passed,
+            total,
+            completed: was_completed || (total > 0 && passed == total),
+            at,
+        };
+        self.entries.insert(lesson.to_string(), entry);
+        self.save()?;
+        Ok(entry)
+    }
+
+    fn save(&self) -> io::Result<()> {
+        let mut text = String::new();
+        for (lesson, entry) in &self.entries {
+            text.push_str(&format!(
+                "{}\t{}\t{}\t{}\t{}\n",
+                lesson,
+                entry.passed,
+                entry.total,
+                if entry.completed { "done" } else { "-" },
+                entry.at
+            ));
+        }
+        fs::write(&self.path, text)
+    }
+}
+
+fn parse_line(line: &str) -> Option<(String, Entry)> {
+    let mut fields = line.split('\t');
+    let lesson = fields.next()?.trim();
+    let passed = fields.next()?.parse().ok()?;
+    let total = fields.next()?.parse().ok()?;
+    let completed = fields.next()? == "done";
+    let at = fields.next()?.parse().ok()?;
+    (!lesson.is_empty()).then(|| {
+        (
+            lesson.to_string(),
+            Entry {
+                passed,
+                total,
+                completed,
+                at,
+            },
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    #[test]
+    fn progress_is_kept_between_runs() {
+        let root = env::temp_dir().join(format!("rust_lessons_progress_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+
+        let mut progress = Progress::load(&root).unwrap();
+        assert!(progress.get("intro_1").is_none());
+        progress.record("intro_1", 1, 2).unwrap();
+        progress.record("closures_and_iterators_8", 3, 3).unwrap();
+        // Breaking a finished exercise later does not undo it
+        progress.record("closures_and_iterators_8", 2, 3).unwrap();
+        fs::write(
+            root.join(FILE),
+            fs::read_to_string(root.join(FILE)).unwrap() + "garbage line\n",
+        )
+        .unwrap();
+
+        let progress = Progress::load(&root).unwrap();
+        assert_eq!(progress.get("intro_1").unwrap().mark(), "1/2");
+        let closures = progress.get("closures_and_iterators_8").unwrap();
+        assert_eq!((closures.passed, closures.total), (2, 3));
+        assert_eq!(closures.mark(), "✓");
+        assert_eq!(progress.completed(), 1);
+        fs::remove_dir_all(&root).unwrap();
+    }
+}